@@ -0,0 +1,10 @@
+CREATE TABLE version_quarantines (
+    version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+    status INTEGER NOT NULL DEFAULT 0,
+    findings JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    reviewed_by INTEGER REFERENCES users (id),
+    reviewed_at TIMESTAMP
+);
+
+CREATE INDEX version_quarantines_status ON version_quarantines (status);
@@ -0,0 +1,4 @@
+DROP TABLE publish_rate_override_actions;
+
+ALTER TABLE publish_rate_overrides
+    DROP COLUMN expires_at;
@@ -0,0 +1,14 @@
+ALTER TABLE publish_rate_overrides
+    ADD COLUMN expires_at TIMESTAMP;
+
+CREATE TABLE publish_rate_override_actions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    action INTEGER NOT NULL,
+    burst INTEGER,
+    expires_at TIMESTAMP,
+    time TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX publish_rate_override_actions_user_id ON publish_rate_override_actions (user_id);
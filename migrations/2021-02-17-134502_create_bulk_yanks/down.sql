@@ -0,0 +1,2 @@
+DROP TABLE bulk_yank_versions;
+DROP TABLE bulk_yanks;
@@ -0,0 +1,20 @@
+CREATE TABLE bulk_yanks (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    api_token_id INTEGER REFERENCES api_tokens (id) ON DELETE CASCADE,
+    since TIMESTAMP NOT NULL,
+    until TIMESTAMP NOT NULL,
+    reason VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP,
+    versions_yanked INTEGER
+);
+
+CREATE TABLE bulk_yank_versions (
+    bulk_yank_id INTEGER NOT NULL REFERENCES bulk_yanks (id) ON DELETE CASCADE,
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    PRIMARY KEY (bulk_yank_id, version_id)
+);
+
+CREATE INDEX bulk_yank_versions_version_id ON bulk_yank_versions (version_id);
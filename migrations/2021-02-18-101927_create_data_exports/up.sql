@@ -0,0 +1,9 @@
+CREATE TABLE data_exports (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP,
+    archive BYTEA
+);
+
+CREATE INDEX data_exports_user_id ON data_exports (user_id);
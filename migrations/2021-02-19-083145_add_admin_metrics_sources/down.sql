@@ -0,0 +1,3 @@
+DROP TABLE publish_rate_limit_rejections;
+
+ALTER TABLE users DROP COLUMN created_at;
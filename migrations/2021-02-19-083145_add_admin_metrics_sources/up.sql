@@ -0,0 +1,12 @@
+-- Existing accounts are left without a creation time instead of all of them
+-- appearing as new accounts on the day of the migration
+ALTER TABLE users ADD COLUMN created_at TIMESTAMP;
+ALTER TABLE users ALTER COLUMN created_at SET DEFAULT CURRENT_TIMESTAMP;
+
+CREATE TABLE publish_rate_limit_rejections (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    time TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX publish_rate_limit_rejections_time ON publish_rate_limit_rejections (time);
@@ -0,0 +1 @@
+DROP FUNCTION reserved_crate_name_pattern(text);
@@ -0,0 +1,5 @@
+-- Turns a reserved name into a `LIKE` pattern. A `*` in the reserved name
+-- matches any sequence of characters, everything else matches literally.
+CREATE FUNCTION reserved_crate_name_pattern(text) RETURNS text AS $$
+    SELECT replace(replace(canon_crate_name($1), '_', '\_'), '*', '%')
+$$ LANGUAGE SQL IMMUTABLE;
@@ -0,0 +1,3 @@
+ALTER TABLE background_jobs
+    DROP COLUMN queue,
+    DROP COLUMN priority;
@@ -0,0 +1,12 @@
+ALTER TABLE background_jobs
+    ADD COLUMN queue TEXT NOT NULL DEFAULT 'default',
+    ADD COLUMN priority SMALLINT NOT NULL DEFAULT 0;
+
+UPDATE background_jobs SET queue = 'index'
+    WHERE job_type IN ('add_crate', 'yank', 'bulk_yank');
+UPDATE background_jobs SET queue = 'readme'
+    WHERE job_type = 'render_and_upload_readme';
+UPDATE background_jobs SET queue = 'maintenance'
+    WHERE job_type IN ('dump_db', 'update_downloads');
+
+CREATE INDEX background_jobs_queue_priority_idx ON background_jobs (queue, priority DESC, id);
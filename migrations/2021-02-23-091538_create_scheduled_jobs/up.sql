@@ -0,0 +1,6 @@
+CREATE TABLE scheduled_jobs (
+    name TEXT PRIMARY KEY,
+    schedule TEXT NOT NULL,
+    last_run_at TIMESTAMP,
+    next_run_at TIMESTAMP NOT NULL
+);
@@ -0,0 +1,3 @@
+DROP TABLE background_job_runs;
+
+ALTER TABLE background_jobs DROP COLUMN last_error;
@@ -0,0 +1,15 @@
+ALTER TABLE background_jobs ADD COLUMN last_error TEXT;
+
+CREATE TABLE background_job_runs (
+    id BIGSERIAL PRIMARY KEY,
+    job_id BIGINT NOT NULL,
+    job_type TEXT NOT NULL,
+    queue TEXT NOT NULL,
+    enqueued_at TIMESTAMP NOT NULL,
+    started_at TIMESTAMP NOT NULL,
+    finished_at TIMESTAMP NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    error TEXT
+);
+
+CREATE INDEX background_job_runs_finished_at_idx ON background_job_runs (finished_at);
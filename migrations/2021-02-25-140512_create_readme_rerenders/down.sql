@@ -0,0 +1 @@
+DROP TABLE readme_rerenders;
@@ -0,0 +1,14 @@
+CREATE TABLE readme_rerenders (
+    id SERIAL PRIMARY KEY,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    crate_name TEXT,
+    rendered_before TIMESTAMP NOT NULL,
+    batch_size INTEGER NOT NULL,
+    total_versions INTEGER NOT NULL,
+    processed_versions INTEGER NOT NULL DEFAULT 0,
+    failed_versions INTEGER NOT NULL DEFAULT 0,
+    last_version_id INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    completed_at TIMESTAMP,
+    cancelled_at TIMESTAMP
+);
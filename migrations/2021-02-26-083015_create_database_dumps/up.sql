@@ -0,0 +1,10 @@
+CREATE TABLE database_dumps (
+    id SERIAL PRIMARY KEY,
+    target_name TEXT NOT NULL,
+    incremental BOOLEAN NOT NULL,
+    since TIMESTAMP,
+    dumped_at TIMESTAMP NOT NULL,
+    size BIGINT NOT NULL
+);
+
+CREATE INDEX database_dumps_dumped_at ON database_dumps (dumped_at);
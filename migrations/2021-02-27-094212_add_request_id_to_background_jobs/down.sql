@@ -0,0 +1,2 @@
+ALTER TABLE background_jobs DROP COLUMN request_id;
+ALTER TABLE background_job_runs DROP COLUMN request_id;
@@ -0,0 +1,2 @@
+ALTER TABLE background_jobs ADD COLUMN request_id TEXT;
+ALTER TABLE background_job_runs ADD COLUMN request_id TEXT;
@@ -0,0 +1,8 @@
+CREATE TABLE pending_uploads (
+    version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+    tarball BYTEA NOT NULL,
+    jobs JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX pending_uploads_created_at ON pending_uploads (created_at);
@@ -0,0 +1,3 @@
+DROP TABLE storage_mismatches;
+
+ALTER TABLE versions DROP COLUMN checksum;
@@ -0,0 +1,15 @@
+ALTER TABLE versions ADD COLUMN checksum VARCHAR;
+
+CREATE TABLE storage_mismatches (
+    id SERIAL PRIMARY KEY,
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    kind INTEGER NOT NULL,
+    expected TEXT,
+    actual TEXT,
+    detected_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    resolved_by INTEGER REFERENCES users (id) ON DELETE SET NULL,
+    resolved_at TIMESTAMP
+);
+
+CREATE UNIQUE INDEX storage_mismatches_unresolved_version_id
+    ON storage_mismatches (version_id) WHERE resolved_at IS NULL;
@@ -0,0 +1,5 @@
+CREATE TABLE materialized_responses (
+    name VARCHAR PRIMARY KEY,
+    body BYTEA NOT NULL,
+    refreshed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+CREATE TABLE crate_dependents (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    direct_dependents INTEGER NOT NULL DEFAULT 0,
+    total_dependents INTEGER NOT NULL DEFAULT 0,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_dependents_direct_dependents ON crate_dependents (direct_dependents DESC);
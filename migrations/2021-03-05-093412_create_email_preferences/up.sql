@@ -0,0 +1,7 @@
+CREATE TABLE email_preferences (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    owner_invitations BOOLEAN NOT NULL DEFAULT TRUE,
+    new_versions BOOLEAN NOT NULL DEFAULT TRUE,
+    security_notices BOOLEAN NOT NULL DEFAULT TRUE,
+    moderation BOOLEAN NOT NULL DEFAULT TRUE
+);
@@ -0,0 +1,2 @@
+DROP TABLE version_advisories;
+DROP TABLE advisories;
@@ -0,0 +1,25 @@
+CREATE TABLE advisories (
+    id VARCHAR PRIMARY KEY,
+    crate_name VARCHAR NOT NULL,
+    title TEXT NOT NULL,
+    url TEXT,
+    date DATE NOT NULL,
+    patched TEXT[] NOT NULL DEFAULT '{}',
+    unaffected TEXT[] NOT NULL DEFAULT '{}',
+    informational VARCHAR,
+    withdrawn BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+SELECT diesel_manage_updated_at('advisories');
+
+CREATE INDEX advisories_crate_name ON advisories (canon_crate_name(crate_name));
+
+CREATE TABLE version_advisories (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    advisory_id VARCHAR NOT NULL REFERENCES advisories (id) ON DELETE CASCADE,
+    PRIMARY KEY (version_id, advisory_id)
+);
+
+CREATE INDEX version_advisories_advisory_id ON version_advisories (advisory_id);
@@ -0,0 +1,3 @@
+ALTER TABLE email_preferences
+    DROP COLUMN weekly_digest,
+    DROP COLUMN weekly_digest_sent_at;
@@ -0,0 +1,3 @@
+ALTER TABLE email_preferences
+    ADD COLUMN weekly_digest BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN weekly_digest_sent_at TIMESTAMP;
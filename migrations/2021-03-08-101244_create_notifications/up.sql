@@ -0,0 +1,12 @@
+CREATE TABLE notifications (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    kind INTEGER NOT NULL,
+    title TEXT NOT NULL,
+    crate_name VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    read_at TIMESTAMP
+);
+
+CREATE INDEX notifications_user_id_created_at ON notifications (user_id, created_at DESC);
+CREATE INDEX notifications_unread ON notifications (user_id) WHERE read_at IS NULL;
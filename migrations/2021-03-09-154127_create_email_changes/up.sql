@@ -0,0 +1,9 @@
+CREATE TABLE email_changes (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    email VARCHAR NOT NULL,
+    old_email_token TEXT NOT NULL UNIQUE DEFAULT random_string(26),
+    new_email_token TEXT NOT NULL UNIQUE DEFAULT random_string(26),
+    old_email_confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    new_email_confirmed BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+DROP SEQUENCE owner_advisory_number_seq;
+
+ALTER TABLE advisories
+    DROP COLUMN description,
+    DROP COLUMN severity,
+    DROP COLUMN affected,
+    DROP COLUMN published_by;
@@ -0,0 +1,7 @@
+ALTER TABLE advisories
+    ADD COLUMN description TEXT,
+    ADD COLUMN severity VARCHAR,
+    ADD COLUMN affected TEXT[] NOT NULL DEFAULT '{}',
+    ADD COLUMN published_by INTEGER REFERENCES users (id) ON DELETE SET NULL;
+
+CREATE SEQUENCE owner_advisory_number_seq;
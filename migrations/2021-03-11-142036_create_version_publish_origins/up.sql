@@ -0,0 +1,4 @@
+CREATE TABLE version_publish_origins (
+    version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+    ip_class VARCHAR NOT NULL
+);
@@ -0,0 +1,3 @@
+-- Tokens with `NULL` iterations are an unsalted SHA-256 hash of the token,
+-- which is replaced the next time they are used
+ALTER TABLE api_tokens ADD COLUMN kdf_iterations INTEGER;
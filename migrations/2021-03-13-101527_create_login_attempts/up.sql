@@ -0,0 +1,20 @@
+CREATE TABLE login_attempts (
+    id SERIAL PRIMARY KEY,
+    ip VARCHAR NOT NULL,
+    -- `begin` or `authorize`, the two steps of the GitHub OAuth flow
+    endpoint VARCHAR NOT NULL,
+    succeeded BOOLEAN NOT NULL,
+    time TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX login_attempts_ip_time ON login_attempts (ip, time);
+
+CREATE TABLE login_anomalies (
+    id SERIAL PRIMARY KEY,
+    ip VARCHAR NOT NULL,
+    kind VARCHAR NOT NULL,
+    attempts INTEGER NOT NULL,
+    detected_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX login_anomalies_detected_at ON login_anomalies (detected_at);
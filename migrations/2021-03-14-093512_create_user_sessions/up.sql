@@ -0,0 +1,12 @@
+CREATE TABLE user_sessions (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    hashed_token BYTEA NOT NULL,
+    user_agent VARCHAR NOT NULL DEFAULT '',
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    last_used_at TIMESTAMP NOT NULL DEFAULT now(),
+    revoked_at TIMESTAMP
+);
+
+CREATE UNIQUE INDEX user_sessions_hashed_token ON user_sessions (hashed_token);
+CREATE INDEX user_sessions_user_id ON user_sessions (user_id);
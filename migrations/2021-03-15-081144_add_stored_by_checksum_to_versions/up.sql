@@ -0,0 +1,3 @@
+-- Whether the crate file is stored by its checksum in addition to its name
+-- and version, in which case downloads are served from there
+ALTER TABLE versions ADD COLUMN stored_by_checksum BOOLEAN NOT NULL DEFAULT FALSE;
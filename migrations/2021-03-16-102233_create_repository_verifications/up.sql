@@ -0,0 +1,12 @@
+CREATE TABLE repository_verifications (
+    crate_id INTEGER PRIMARY KEY REFERENCES crates (id) ON DELETE CASCADE,
+    repository VARCHAR NOT NULL,
+    token VARCHAR NOT NULL DEFAULT random_string(32),
+    requested_by INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    verified_at TIMESTAMP,
+    last_checked_at TIMESTAMP
+);
+
+CREATE INDEX repository_verifications_last_checked_at
+    ON repository_verifications (last_checked_at) WHERE verified_at IS NOT NULL;
@@ -0,0 +1,5 @@
+DROP TABLE verified_publishers;
+
+ALTER TABLE version_quarantines
+    DROP COLUMN held_index_entry,
+    DROP COLUMN release_at;
@@ -0,0 +1,12 @@
+ALTER TABLE version_quarantines
+    ADD COLUMN held_index_entry JSONB,
+    ADD COLUMN release_at TIMESTAMP;
+
+CREATE INDEX version_quarantines_release_at
+    ON version_quarantines (release_at) WHERE release_at IS NOT NULL;
+
+CREATE TABLE verified_publishers (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    verified_by INTEGER NOT NULL REFERENCES users (id),
+    verified_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+ALTER TABLE readme_renderings DROP COLUMN source_file_name;
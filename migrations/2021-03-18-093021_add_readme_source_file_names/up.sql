@@ -0,0 +1,4 @@
+-- The path of the README in the crate file, whose contents are stored next
+-- to the rendered HTML. `NULL` for versions without a README, and for
+-- versions rendered before the sources were stored.
+ALTER TABLE readme_renderings ADD COLUMN source_file_name VARCHAR;
@@ -0,0 +1,3 @@
+ALTER TABLE readme_renderings
+    DROP COLUMN rendered_size,
+    DROP COLUMN source_size;
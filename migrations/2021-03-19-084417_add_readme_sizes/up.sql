@@ -0,0 +1,6 @@
+-- The sizes of the rendered HTML and of the source of the README as stored,
+-- in bytes. `NULL` for versions without a README, and for versions rendered
+-- before the sizes were recorded.
+ALTER TABLE readme_renderings
+    ADD COLUMN rendered_size INTEGER,
+    ADD COLUMN source_size INTEGER;
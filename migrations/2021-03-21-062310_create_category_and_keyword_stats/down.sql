@@ -0,0 +1,2 @@
+DROP TABLE keyword_stats;
+DROP TABLE category_stats;
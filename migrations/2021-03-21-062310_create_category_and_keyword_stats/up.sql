@@ -0,0 +1,19 @@
+CREATE TABLE category_stats (
+    category_id INTEGER NOT NULL REFERENCES categories (id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    crates_cnt INTEGER NOT NULL,
+    new_crates INTEGER NOT NULL,
+    downloads BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (category_id, month)
+);
+
+CREATE TABLE keyword_stats (
+    keyword_id INTEGER NOT NULL REFERENCES keywords (id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    crates_cnt INTEGER NOT NULL,
+    new_crates INTEGER NOT NULL,
+    downloads BIGINT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (keyword_id, month)
+);
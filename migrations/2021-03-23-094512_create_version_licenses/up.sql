@@ -0,0 +1,8 @@
+CREATE TABLE version_licenses (
+    version_id INTEGER PRIMARY KEY REFERENCES versions (id) ON DELETE CASCADE,
+    expression VARCHAR NOT NULL,
+    licenses TEXT[] NOT NULL,
+    exceptions TEXT[] NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX version_licenses_licenses_idx ON version_licenses USING GIN (licenses);
@@ -0,0 +1 @@
+ALTER TABLE database_dumps DROP COLUMN download_history;
@@ -0,0 +1 @@
+ALTER TABLE database_dumps ADD COLUMN download_history BOOLEAN NOT NULL DEFAULT FALSE;
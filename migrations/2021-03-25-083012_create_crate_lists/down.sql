@@ -0,0 +1,2 @@
+DROP TABLE crate_list_entries;
+DROP TABLE crate_lists;
@@ -0,0 +1,22 @@
+CREATE TABLE crate_lists (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    name VARCHAR NOT NULL,
+    description VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX crate_lists_user_id ON crate_lists (user_id);
+
+SELECT diesel_manage_updated_at('crate_lists');
+
+CREATE TABLE crate_list_entries (
+    crate_list_id INTEGER NOT NULL REFERENCES crate_lists (id) ON DELETE CASCADE,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    note VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (crate_list_id, crate_id)
+);
+
+CREATE INDEX crate_list_entries_crate_id ON crate_list_entries (crate_id);
@@ -0,0 +1,2 @@
+DROP TABLE user_follows;
+ALTER TABLE users DROP COLUMN followable;
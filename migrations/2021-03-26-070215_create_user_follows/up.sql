@@ -0,0 +1,10 @@
+ALTER TABLE users ADD COLUMN followable BOOLEAN NOT NULL DEFAULT TRUE;
+
+CREATE TABLE user_follows (
+    follower_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    followed_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (follower_id, followed_id)
+);
+
+CREATE INDEX user_follows_followed_id ON user_follows (followed_id);
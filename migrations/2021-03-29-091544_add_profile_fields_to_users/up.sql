@@ -0,0 +1,5 @@
+ALTER TABLE users
+    ADD COLUMN display_name VARCHAR,
+    ADD COLUMN bio VARCHAR,
+    ADD COLUMN website VARCHAR,
+    ADD COLUMN location VARCHAR;
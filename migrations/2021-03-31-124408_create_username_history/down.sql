@@ -0,0 +1,2 @@
+DROP TABLE username_history;
+ALTER TABLE users DROP COLUMN username;
@@ -0,0 +1,13 @@
+ALTER TABLE users ADD COLUMN username VARCHAR;
+
+CREATE UNIQUE INDEX users_username ON users (lower(username));
+
+CREATE TABLE username_history (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    username VARCHAR NOT NULL,
+    changed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX username_history_username ON username_history (lower(username));
+CREATE INDEX username_history_user_id ON username_history (user_id);
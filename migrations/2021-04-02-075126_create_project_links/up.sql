@@ -0,0 +1,10 @@
+CREATE TABLE project_links (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    kind INTEGER NOT NULL,
+    url VARCHAR NOT NULL,
+    label VARCHAR,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX project_links_crate_id ON project_links (crate_id);
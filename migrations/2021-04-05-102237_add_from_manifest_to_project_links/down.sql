@@ -0,0 +1 @@
+ALTER TABLE project_links DROP COLUMN from_manifest;
@@ -0,0 +1 @@
+ALTER TABLE project_links ADD COLUMN from_manifest BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,13 @@
+CREATE TABLE mutes (
+    id SERIAL PRIMARY KEY,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    crate_id INTEGER REFERENCES crates (id) ON DELETE CASCADE,
+    muted_user_id INTEGER REFERENCES users (id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((crate_id IS NULL) <> (muted_user_id IS NULL))
+);
+
+CREATE UNIQUE INDEX mutes_user_id_crate_id ON mutes (user_id, crate_id)
+    WHERE crate_id IS NOT NULL;
+CREATE UNIQUE INDEX mutes_user_id_muted_user_id ON mutes (user_id, muted_user_id)
+    WHERE muted_user_id IS NOT NULL;
@@ -0,0 +1,12 @@
+CREATE TABLE featured_crates (
+    id SERIAL PRIMARY KEY,
+    crate_id INTEGER NOT NULL REFERENCES crates (id) ON DELETE CASCADE,
+    starts_on DATE NOT NULL,
+    ends_on DATE NOT NULL,
+    description VARCHAR,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (starts_on <= ends_on)
+);
+
+CREATE INDEX featured_crates_ends_on ON featured_crates (ends_on);
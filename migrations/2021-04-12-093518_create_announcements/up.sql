@@ -0,0 +1,13 @@
+CREATE TABLE announcements (
+    id SERIAL PRIMARY KEY,
+    message VARCHAR NOT NULL,
+    url VARCHAR,
+    severity INTEGER NOT NULL,
+    starts_at TIMESTAMP NOT NULL,
+    ends_at TIMESTAMP,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK (ends_at IS NULL OR starts_at < ends_at)
+);
+
+CREATE INDEX announcements_ends_at ON announcements (ends_at);
@@ -0,0 +1,8 @@
+-- Has a row while admins put crates.io into maintenance mode, during which
+-- API requests that change data are rejected
+CREATE TABLE maintenance_mode (
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    message VARCHAR,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    started_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,10 @@
+-- Features that are rolled out gradually, see the `feature_flags` module
+CREATE TABLE feature_flags (
+    name VARCHAR PRIMARY KEY,
+    description VARCHAR,
+    enabled BOOLEAN NOT NULL DEFAULT FALSE,
+    percentage INTEGER NOT NULL DEFAULT 0 CHECK (percentage BETWEEN 0 AND 100),
+    user_ids INTEGER[] NOT NULL DEFAULT '{}',
+    updated_by INTEGER NOT NULL REFERENCES users (id),
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,8 @@
+-- The health of the configured download origins, see the `download_origins` module
+CREATE TABLE download_origins (
+    url VARCHAR PRIMARY KEY,
+    healthy BOOLEAN NOT NULL DEFAULT TRUE,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error VARCHAR,
+    checked_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
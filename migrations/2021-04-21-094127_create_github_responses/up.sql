@@ -0,0 +1,8 @@
+-- Responses of the GitHub API, see the `github::cache` module
+CREATE TABLE github_responses (
+    key VARCHAR PRIMARY KEY,
+    etag VARCHAR,
+    body JSONB NOT NULL,
+    fetched_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX github_responses_fetched_at ON github_responses (fetched_at);
@@ -0,0 +1,12 @@
+-- Responses of mutating requests with an `Idempotency-Key` header, see the
+-- `controllers::helpers::idempotency` module
+CREATE TABLE idempotency_keys (
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    key VARCHAR NOT NULL,
+    fingerprint VARCHAR NOT NULL,
+    response_status INTEGER,
+    response_body BYTEA,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (user_id, key)
+);
+CREATE INDEX idempotency_keys_created_at ON idempotency_keys (created_at);
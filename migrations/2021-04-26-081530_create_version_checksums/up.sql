@@ -0,0 +1,14 @@
+-- Checksums of the crate file of every version, by hash algorithm, see the
+-- `models::version_checksum` module
+CREATE TABLE version_checksums (
+    version_id INTEGER NOT NULL REFERENCES versions (id) ON DELETE CASCADE,
+    algorithm VARCHAR NOT NULL,
+    digest VARCHAR NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    PRIMARY KEY (version_id, algorithm)
+);
+
+-- The checksums of the other algorithms are added by the
+-- `backfill_checksums` job
+INSERT INTO version_checksums (version_id, algorithm, digest)
+SELECT id, 'sha256', checksum FROM versions WHERE checksum IS NOT NULL;
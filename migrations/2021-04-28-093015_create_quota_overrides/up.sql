@@ -0,0 +1,10 @@
+-- The quotas of an account that an admin changed, see the `quotas` module.
+-- Quotas that are NULL are the configured ones.
+CREATE TABLE quota_overrides (
+    user_id INTEGER PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    storage_bytes BIGINT,
+    versions_per_day BIGINT,
+    crates BIGINT,
+    admin_id INTEGER NOT NULL REFERENCES users (id),
+    updated_at TIMESTAMP NOT NULL DEFAULT now()
+);
@@ -0,0 +1 @@
+ALTER TABLE background_jobs ADD COLUMN run_at TIMESTAMP NOT NULL DEFAULT now();
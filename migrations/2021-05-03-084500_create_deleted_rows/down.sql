@@ -0,0 +1,15 @@
+DROP TRIGGER record_deleted_advisory ON advisories;
+DROP TRIGGER record_deleted_badge ON badges;
+DROP TRIGGER record_deleted_category_stat ON category_stats;
+DROP TRIGGER record_deleted_crate_dependent ON crate_dependents;
+DROP TRIGGER record_deleted_crate ON crates;
+DROP TRIGGER record_deleted_crate_category ON crates_categories;
+DROP TRIGGER record_deleted_crate_keyword ON crates_keywords;
+DROP TRIGGER record_deleted_dependency ON dependencies;
+DROP TRIGGER record_deleted_keyword_stat ON keyword_stats;
+DROP TRIGGER record_deleted_version_author ON version_authors;
+DROP TRIGGER record_deleted_version_checksum ON version_checksums;
+DROP TRIGGER record_deleted_version_download ON version_downloads;
+DROP TRIGGER record_deleted_version ON versions;
+DROP FUNCTION record_deleted_row();
+DROP TABLE deleted_rows;
@@ -0,0 +1,52 @@
+-- The keys of rows deleted from the tables that are dumped incrementally,
+-- so incremental dumps can tell their consumers to delete them as well
+CREATE TABLE deleted_rows (
+    id BIGSERIAL PRIMARY KEY,
+    table_name VARCHAR NOT NULL,
+    key JSONB NOT NULL,
+    deleted_at TIMESTAMP NOT NULL DEFAULT now()
+);
+
+CREATE INDEX deleted_rows_deleted_at ON deleted_rows (deleted_at);
+
+-- The trigger arguments are the columns of the incremental key of the table
+-- in `dump-db.toml`
+CREATE FUNCTION record_deleted_row() RETURNS trigger AS $$
+DECLARE
+    key JSONB := '{}';
+    col TEXT;
+BEGIN
+    FOREACH col IN ARRAY TG_ARGV LOOP
+        key := key || jsonb_build_object(col, to_jsonb(OLD) -> col);
+    END LOOP;
+    INSERT INTO deleted_rows (table_name, key) VALUES (TG_TABLE_NAME, key);
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER record_deleted_advisory AFTER DELETE ON advisories
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('id');
+CREATE TRIGGER record_deleted_badge AFTER DELETE ON badges
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('crate_id');
+CREATE TRIGGER record_deleted_category_stat AFTER DELETE ON category_stats
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('category_id', 'month');
+CREATE TRIGGER record_deleted_crate_dependent AFTER DELETE ON crate_dependents
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('crate_id');
+CREATE TRIGGER record_deleted_crate AFTER DELETE ON crates
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('id');
+CREATE TRIGGER record_deleted_crate_category AFTER DELETE ON crates_categories
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('crate_id');
+CREATE TRIGGER record_deleted_crate_keyword AFTER DELETE ON crates_keywords
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('crate_id');
+CREATE TRIGGER record_deleted_dependency AFTER DELETE ON dependencies
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('id');
+CREATE TRIGGER record_deleted_keyword_stat AFTER DELETE ON keyword_stats
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('keyword_id', 'month');
+CREATE TRIGGER record_deleted_version_author AFTER DELETE ON version_authors
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('id');
+CREATE TRIGGER record_deleted_version_checksum AFTER DELETE ON version_checksums
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('version_id', 'algorithm');
+CREATE TRIGGER record_deleted_version_download AFTER DELETE ON version_downloads
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('version_id', 'date');
+CREATE TRIGGER record_deleted_version AFTER DELETE ON versions
+FOR EACH ROW EXECUTE PROCEDURE record_deleted_row('id');
@@ -0,0 +1,2 @@
+DROP TRIGGER trigger_users_revoke_sessions_on_admin_change ON users;
+DROP FUNCTION revoke_sessions_on_admin_change();
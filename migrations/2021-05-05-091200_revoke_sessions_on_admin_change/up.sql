@@ -0,0 +1,16 @@
+-- Gaining or losing admin rights logs the user out of all browsers, like
+-- locking the account. The flag is only ever changed in the database, so the
+-- sessions are revoked by a trigger.
+CREATE FUNCTION revoke_sessions_on_admin_change() RETURNS trigger AS $$
+  BEGIN
+    IF NEW.is_admin IS DISTINCT FROM OLD.is_admin THEN
+      UPDATE user_sessions SET revoked_at = CURRENT_TIMESTAMP
+        WHERE user_id = NEW.id AND revoked_at IS NULL;
+    END IF;
+    RETURN NEW;
+  END
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER trigger_users_revoke_sessions_on_admin_change AFTER UPDATE OF is_admin
+ON users
+FOR EACH ROW EXECUTE PROCEDURE revoke_sessions_on_admin_change();
@@ -0,0 +1,9 @@
+ALTER TABLE bulk_yanks
+    DROP CONSTRAINT bulk_yanks_user_id_fkey,
+    ADD CONSTRAINT bulk_yanks_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE;
+
+ALTER TABLE bulk_yanks
+    DROP CONSTRAINT bulk_yanks_api_token_id_fkey,
+    ADD CONSTRAINT bulk_yanks_api_token_id_fkey
+        FOREIGN KEY (api_token_id) REFERENCES api_tokens (id) ON DELETE CASCADE;
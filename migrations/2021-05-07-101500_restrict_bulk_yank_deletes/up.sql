@@ -0,0 +1,11 @@
+-- Bulk yanks are an audit record of what was yanked and why, so deleting the
+-- yanked account or token must not silently delete them.
+ALTER TABLE bulk_yanks
+    DROP CONSTRAINT bulk_yanks_user_id_fkey,
+    ADD CONSTRAINT bulk_yanks_user_id_fkey
+        FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE RESTRICT;
+
+ALTER TABLE bulk_yanks
+    DROP CONSTRAINT bulk_yanks_api_token_id_fkey,
+    ADD CONSTRAINT bulk_yanks_api_token_id_fkey
+        FOREIGN KEY (api_token_id) REFERENCES api_tokens (id) ON DELETE RESTRICT;
@@ -158,7 +158,7 @@ fn get_readme(
 ) -> Option<String> {
     let location = config
         .uploader
-        .crate_location(krate_name, &version.num.to_string());
+        .crate_location(None, krate_name, &version.num.to_string());
 
     let response = match client.get(&location).send() {
         Ok(r) => r,
@@ -1,5 +1,10 @@
 //! Application-wide components in a struct accessible from each request
 
+use crate::cache::{ResponseCache, VersionLookupCache};
+use crate::download_origins::DownloadOriginHealth;
+use crate::feature_flags::FeatureFlags;
+use crate::maintenance::MaintenanceMode;
+use crate::metrics::{InstanceMetrics, ServiceMetrics};
 use crate::{db, Config, Env};
 use std::{sync::Arc, time::Duration};
 
@@ -20,6 +25,12 @@ pub struct App {
     /// The read-only replica database connection pool
     pub read_only_replica_database: Option<db::DieselPool>,
 
+    /// Whether reads currently go to the replica
+    pub replica_status: db::ReplicaStatus,
+
+    /// Caps on the connections expensive endpoints may hold
+    pub pool_budgets: db::PoolBudgets,
+
     /// GitHub API client
     pub github: GitHubClient,
 
@@ -32,6 +43,28 @@ pub struct App {
     /// The server configuration
     pub config: Config,
 
+    /// Metrics collected by this server process
+    pub instance_metrics: InstanceMetrics,
+
+    /// Metrics computed from the database when they are scraped
+    pub service_metrics: ServiceMetrics,
+
+    /// Responses of popular crates, see the `cache` module
+    pub response_cache: Arc<ResponseCache>,
+
+    /// Versions resolved by the download endpoint, see the `cache` module
+    pub version_lookups: VersionLookupCache,
+
+    /// Whether changes are currently accepted, see the `maintenance` module
+    pub maintenance_mode: MaintenanceMode,
+
+    /// The cached feature flags, see the `feature_flags` module
+    pub feature_flags: FeatureFlags,
+
+    /// The cached health of the download origins, see the `download_origins`
+    /// module
+    pub download_origins: DownloadOriginHealth,
+
     /// A configured client for outgoing HTTP requests
     ///
     /// In production this shares a single connection pool across requests.  In tests
@@ -51,7 +84,11 @@ impl App {
     pub fn new(config: Config, http_client: Option<Client>) -> App {
         use oauth2::{AuthUrl, ClientId, ClientSecret, TokenUrl};
 
-        let github = GitHubClient::new(http_client.clone(), config.gh_base_url.clone());
+        let github = if config.dev_mode {
+            GitHubClient::fake()
+        } else {
+            GitHubClient::new(http_client.clone(), config.gh_base_url.clone())
+        };
 
         let github_oauth = BasicClient::new(
             ClientId::new(config.gh_client_id.clone()),
@@ -88,6 +125,15 @@ impl App {
             _ => 30,
         };
 
+        // How many seconds the replica may lag behind before reads go to the
+        // primary instead, 0 disables the check
+        let db_replica_max_lag = match dotenv::var("DB_REPLICA_MAX_LAG") {
+            Ok(num) => num.parse().expect("couldn't parse DB_REPLICA_MAX_LAG"),
+            _ => 30,
+        };
+        let replica_max_lag = Some(Duration::from_secs(db_replica_max_lag))
+            .filter(|max_lag| *max_lag > Duration::from_secs(0));
+
         // Determine if the primary pool is also read-only
         let read_only_mode = dotenv::var("READ_ONLY_MODE").is_ok();
         let primary_db_connection_config = db::ConnectionConfig {
@@ -124,13 +170,36 @@ impl App {
             None
         };
 
+        // Tests replay recorded GitHub responses, which cached responses
+        // would get out of order
+        let github = if config.env == Env::Test {
+            github
+        } else {
+            github.with_cache(primary_database.clone())
+        };
+
+        let response_cache = Arc::new(ResponseCache::from_config(&config));
+
+        let instance_metrics =
+            InstanceMetrics::new().expect("could not initialize instance metrics");
+        let service_metrics = ServiceMetrics::new().expect("could not initialize service metrics");
+
         App {
             primary_database,
             read_only_replica_database,
+            replica_status: db::ReplicaStatus::new(replica_max_lag),
+            pool_budgets: db::PoolBudgets::from_environment(),
             github,
             github_oauth,
             session_key: config.session_key.clone(),
             config,
+            instance_metrics,
+            service_metrics,
+            response_cache,
+            version_lookups: VersionLookupCache::default(),
+            maintenance_mode: MaintenanceMode::new(read_only_mode),
+            feature_flags: FeatureFlags::default(),
+            download_origins: DownloadOriginHealth::default(),
             http_client,
         }
     }
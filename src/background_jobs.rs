@@ -1,23 +1,340 @@
+//! The background jobs run by the `background-worker` binary.
+//!
+//! Every job is a variant of [`Job`]. Enqueueing a job stores the variant name
+//! as the `job_type` and its fields as the `data` of a row in the
+//! `background_jobs` table, along with the [`Queue`] and priority of the job.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
 use reqwest::blocking::Client;
 use std::panic::AssertUnwindSafe;
 use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
-use diesel::r2d2::PoolError;
-use swirl::PerformError;
-
-use crate::db::{DieselPool, DieselPooledConn};
-use crate::git::Repository;
+use crate::cache::ResponseCache;
+use crate::email::OutgoingEmail;
+use crate::git::{self, Repository};
+use crate::models::{ReadmeRerender, Version};
+use crate::scanning::{self, ScannerConfig};
+use crate::schema::background_jobs;
+use crate::swirl::{EnqueueError, PerformError};
 use crate::uploaders::Uploader;
+use crate::util::request_id;
+use crate::{data_export, render, tasks};
+
+/// The queues that background jobs are distributed across.
+///
+/// Every queue is worked by its own set of threads, so a backlog on one queue
+/// (like a large number of README renders) doesn't delay the jobs on another
+/// (like index updates).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Queue {
+    /// Jobs that update the index. They all need exclusive access to the
+    /// index repository, so there is no point in running more than one.
+    Index,
+    /// Rendering and uploading of READMEs
+    Readme,
+    /// Long-running jobs like database dumps and download count updates
+    Maintenance,
+    /// Everything else
+    Default,
+}
+
+impl Queue {
+    pub const ALL: &'static [Queue] = &[
+        Queue::Index,
+        Queue::Default,
+        Queue::Readme,
+        Queue::Maintenance,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Queue::Index => "index",
+            Queue::Readme => "readme",
+            Queue::Maintenance => "maintenance",
+            Queue::Default => "default",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|queue| queue.name() == name)
+    }
+
+    /// The number of jobs from this queue that are run at the same time,
+    /// unless configured otherwise.
+    pub fn default_concurrency(self) -> usize {
+        match self {
+            Queue::Index | Queue::Maintenance => 1,
+            Queue::Readme | Queue::Default => 2,
+        }
+    }
+}
+
+/// The number of items a job has processed so far, out of all of its items
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobProgress {
+    pub completed: i64,
+    pub total: i64,
+}
 
-impl<'a> swirl::db::BorrowedConnection<'a> for DieselPool {
-    type Connection = DieselPooledConn<'a>;
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "job_type", content = "data", rename_all = "snake_case")]
+pub enum Job {
+    AddCrate {
+        krate: git::Crate,
+    },
+    BackfillChecksums {},
+    BulkYank {
+        bulk_yank_id: i32,
+    },
+    CheckDownloadOrigins {},
+    CleanUpStaleData {},
+    ComputeTrendingScores {},
+    DumpDb {
+        database_url: String,
+        target_name: String,
+    },
+    DumpDbIncremental {
+        database_url: String,
+    },
+    DumpDownloadHistory {
+        database_url: String,
+    },
+    ExportDependencyGraph {},
+    ExportUserData {
+        data_export_id: i32,
+    },
+    GenerateSitemaps {},
+    NormalizeVersionLicenses {},
+    NotifyAdvisory {
+        advisory_id: String,
+    },
+    NotifyNewVersion {
+        version_id: i32,
+    },
+    RenderAndUploadReadme {
+        version_id: i32,
+        text: String,
+        file_name: String,
+        base_url: Option<String>,
+    },
+    RecheckRepositoryVerifications {},
+    ReconcileDependents {},
+    ReleaseHeldVersions {},
+    RefreshMonthlyStats {
+        backfill: bool,
+    },
+    RefreshSummary {},
+    RerenderReadmes {
+        readme_rerender_id: i32,
+    },
+    ScanVersion {
+        version_id: i32,
+        krate: String,
+        version: String,
+        scanners: Vec<ScannerConfig>,
+    },
+    SendEmail {
+        email: OutgoingEmail,
+        enqueued_at: NaiveDateTime,
+    },
+    SendWeeklyDigests {},
+    SyncAdvisories {},
+    UpdateDownloads {},
+    UploadPendingCrates {},
+    VerifyStorage {},
+    Yank {
+        krate: String,
+        version: Version,
+        yanked: bool,
+    },
 }
 
-impl swirl::db::DieselPool for DieselPool {
-    type Error = PoolError;
+impl Job {
+    pub fn queue(&self) -> Queue {
+        match self {
+            Job::AddCrate { .. } | Job::BulkYank { .. } | Job::Yank { .. } => Queue::Index,
+            Job::RenderAndUploadReadme { .. } | Job::RerenderReadmes { .. } => Queue::Readme,
+            Job::BackfillChecksums {}
+            | Job::CleanUpStaleData {}
+            | Job::ComputeTrendingScores {}
+            | Job::DumpDb { .. }
+            | Job::DumpDbIncremental { .. }
+            | Job::DumpDownloadHistory { .. }
+            | Job::ExportDependencyGraph {}
+            | Job::GenerateSitemaps {}
+            | Job::NormalizeVersionLicenses {}
+            | Job::RecheckRepositoryVerifications {}
+            | Job::ReconcileDependents {}
+            | Job::RefreshMonthlyStats { .. }
+            | Job::SyncAdvisories {}
+            | Job::UpdateDownloads {}
+            | Job::VerifyStorage {} => Queue::Maintenance,
+            Job::CheckDownloadOrigins {}
+            | Job::ExportUserData { .. }
+            | Job::NotifyAdvisory { .. }
+            | Job::NotifyNewVersion { .. }
+            | Job::RefreshSummary {}
+            | Job::ReleaseHeldVersions {}
+            | Job::ScanVersion { .. }
+            | Job::SendEmail { .. }
+            | Job::SendWeeklyDigests {}
+            | Job::UploadPendingCrates {} => Queue::Default,
+        }
+    }
+
+    /// Jobs with a higher priority are run before other jobs on the same
+    /// queue, regardless of when they were enqueued.
+    ///
+    /// Both the queue and the priority are stored when the job is enqueued,
+    /// so changing them only affects jobs enqueued afterwards.
+    pub fn priority(&self) -> i16 {
+        match self {
+            // A publish or yank is waiting on these
+            Job::AddCrate { .. } | Job::Yank { .. } => 10,
+            // Quarantining a malicious version should not wait for user data exports
+            Job::ScanVersion { .. } => 10,
+            Job::UpdateDownloads {} => 5,
+            // Runs every minute, a backlog only means serving an older summary
+            Job::RefreshSummary {} => 5,
+            // Downloads keep going to an origin that is down until it runs
+            Job::CheckDownloadOrigins {} => 5,
+            // The READMEs of new versions should not wait for a bulk re-render
+            Job::RerenderReadmes { .. } => -10,
+            // Sampling the storage is never urgent
+            Job::VerifyStorage {} => -10,
+            // Older versions already have a SHA256 checksum
+            Job::BackfillChecksums {} => -10,
+            _ => 0,
+        }
+    }
+
+    /// The progress of a job that works through its items over multiple
+    /// runs, which is `None` for all other jobs.
+    pub fn progress(&self, conn: &PgConnection) -> QueryResult<Option<JobProgress>> {
+        match self {
+            Job::RerenderReadmes { readme_rerender_id } => {
+                let rerender = ReadmeRerender::find(conn, *readme_rerender_id).optional()?;
+                Ok(rerender.map(|rerender| rerender.progress()))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn enqueue(&self, conn: &PgConnection) -> Result<(), EnqueueError> {
+        self.enqueue_after(conn, Duration::from_secs(0))
+    }
+
+    /// Enqueues the job to run once the delay has passed. Until then, the
+    /// job waits in the table without occupying a worker.
+    pub fn enqueue_after(&self, conn: &PgConnection, delay: Duration) -> Result<(), EnqueueError> {
+        use diesel::dsl::{now, IntervalDsl};
+
+        let (job_type, data) = self.to_parts()?;
+
+        diesel::insert_into(background_jobs::table)
+            .values((
+                background_jobs::job_type.eq(job_type),
+                background_jobs::data.eq(data),
+                background_jobs::queue.eq(self.queue().name()),
+                background_jobs::priority.eq(self.priority()),
+                background_jobs::request_id.eq(request_id::current()),
+                background_jobs::run_at.eq(now + (delay.as_secs() as i32).seconds()),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    pub(crate) fn to_parts(&self) -> serde_json::Result<(String, serde_json::Value)> {
+        let mut value = serde_json::to_value(self)?;
+        let job_type = value["job_type"].as_str().unwrap_or_default().to_string();
+        Ok((job_type, value["data"].take()))
+    }
+
+    pub(crate) fn from_parts(
+        job_type: &str,
+        data: serde_json::Value,
+    ) -> Result<Self, PerformError> {
+        let value = json!({ "job_type": job_type, "data": data });
+        serde_json::from_value(value)
+            .map_err(|e| format!("invalid `{}` job: {}", job_type, e).into())
+    }
 
-    fn get(&self) -> Result<swirl::db::DieselPooledConn<'_, Self>, Self::Error> {
-        self.get()
+    pub(crate) fn perform(
+        self,
+        env: &Environment,
+        conn: &PgConnection,
+    ) -> Result<(), PerformError> {
+        match self {
+            Job::AddCrate { krate } => git::perform_add_crate(env, krate),
+            Job::BackfillChecksums {} => tasks::perform_backfill_checksums(conn, env),
+            Job::BulkYank { bulk_yank_id } => git::perform_bulk_yank(conn, env, bulk_yank_id),
+            Job::CheckDownloadOrigins {} => tasks::perform_check_download_origins(conn, env),
+            Job::CleanUpStaleData {} => tasks::perform_clean_up_stale_data(conn),
+            Job::ComputeTrendingScores {} => tasks::perform_compute_trending_scores(conn),
+            Job::DumpDb {
+                database_url,
+                target_name,
+            } => tasks::perform_dump_db(conn, env, database_url, target_name),
+            Job::DumpDbIncremental { database_url } => {
+                tasks::perform_dump_db_incremental(conn, env, database_url)
+            }
+            Job::DumpDownloadHistory { database_url } => {
+                tasks::perform_dump_download_history(conn, env, database_url)
+            }
+            Job::ExportDependencyGraph {} => tasks::perform_export_dependency_graph(conn, env),
+            Job::GenerateSitemaps {} => tasks::perform_generate_sitemaps(conn),
+            Job::ExportUserData { data_export_id } => {
+                data_export::perform_export_user_data(conn, data_export_id)
+            }
+            Job::NormalizeVersionLicenses {} => tasks::perform_normalize_version_licenses(conn),
+            Job::NotifyAdvisory { advisory_id } => {
+                tasks::perform_notify_advisory(conn, &advisory_id)
+            }
+            Job::NotifyNewVersion { version_id } => {
+                tasks::perform_notify_new_version(conn, version_id)
+            }
+            Job::RenderAndUploadReadme {
+                version_id,
+                text,
+                file_name,
+                base_url,
+            } => render::perform_render_and_upload_readme(
+                conn, env, version_id, text, file_name, base_url,
+            ),
+            Job::RecheckRepositoryVerifications {} => {
+                tasks::perform_recheck_repository_verifications(conn, env)
+            }
+            Job::ReconcileDependents {} => tasks::perform_reconcile_dependents(conn),
+            Job::ReleaseHeldVersions {} => tasks::perform_release_held_versions(conn),
+            Job::RefreshMonthlyStats { backfill } => {
+                tasks::perform_refresh_monthly_stats(conn, backfill)
+            }
+            Job::RefreshSummary {} => tasks::perform_refresh_summary(conn),
+            Job::RerenderReadmes { readme_rerender_id } => {
+                render::perform_rerender_readmes(conn, env, readme_rerender_id)
+            }
+            Job::ScanVersion {
+                version_id,
+                krate,
+                version,
+                scanners,
+            } => scanning::perform_scan_version(conn, env, version_id, krate, version, scanners),
+            Job::SendEmail { email, enqueued_at } => {
+                crate::email::perform_send_email(email, enqueued_at)
+            }
+            Job::SendWeeklyDigests {} => tasks::perform_send_weekly_digests(conn),
+            Job::SyncAdvisories {} => tasks::perform_sync_advisories(conn),
+            Job::UpdateDownloads {} => tasks::perform_update_downloads(conn),
+            Job::UploadPendingCrates {} => tasks::perform_upload_pending_crates(conn, env),
+            Job::VerifyStorage {} => tasks::perform_verify_storage(conn, env),
+            Job::Yank {
+                krate,
+                version,
+                yanked,
+            } => git::perform_yank(conn, env, krate, version, yanked),
+        }
     }
 }
 
@@ -26,6 +343,9 @@ pub struct Environment {
     index: Arc<Mutex<Repository>>,
     pub uploader: Uploader,
     http_client: AssertUnwindSafe<Client>,
+    /// The cache of the crate responses, which jobs that change a crate
+    /// invalidate once their changes are committed
+    pub response_cache: Arc<ResponseCache>,
 }
 
 impl Clone for Environment {
@@ -34,24 +354,37 @@ impl Clone for Environment {
             index: self.index.clone(),
             uploader: self.uploader.clone(),
             http_client: AssertUnwindSafe(self.http_client.0.clone()),
+            response_cache: self.response_cache.clone(),
         }
     }
 }
 
 impl Environment {
-    pub fn new(index: Repository, uploader: Uploader, http_client: Client) -> Self {
-        Self::new_shared(Arc::new(Mutex::new(index)), uploader, http_client)
+    pub fn new(
+        index: Repository,
+        uploader: Uploader,
+        http_client: Client,
+        response_cache: Arc<ResponseCache>,
+    ) -> Self {
+        Self::new_shared(
+            Arc::new(Mutex::new(index)),
+            uploader,
+            http_client,
+            response_cache,
+        )
     }
 
     pub fn new_shared(
         index: Arc<Mutex<Repository>>,
         uploader: Uploader,
         http_client: Client,
+        response_cache: Arc<ResponseCache>,
     ) -> Self {
         Self {
             index,
             uploader,
             http_client: AssertUnwindSafe(http_client),
+            response_cache,
         }
     }
 
@@ -66,3 +399,32 @@ impl Environment {
         &self.http_client
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn job_type_and_data_are_stored_separately() {
+        let job = Job::ExportUserData { data_export_id: 1 };
+        let (job_type, data) = job.to_parts().unwrap();
+        assert_eq!(job_type, "export_user_data");
+        assert_eq!(data, json!({ "data_export_id": 1 }));
+
+        let job = Job::from_parts(&job_type, data).unwrap();
+        assert!(matches!(job, Job::ExportUserData { data_export_id: 1 }));
+    }
+
+    #[test]
+    fn jobs_without_arguments_have_empty_data() {
+        let (job_type, data) = Job::UpdateDownloads {}.to_parts().unwrap();
+        assert_eq!(job_type, "update_downloads");
+        assert_eq!(data, json!({}));
+        assert_ok!(Job::from_parts("update_downloads", data));
+    }
+
+    #[test]
+    fn unknown_job_types_are_rejected() {
+        assert_err!(Job::from_parts("unknown", json!({})));
+    }
+}
@@ -1,29 +1,46 @@
 //! Runs enqueued background jobs
 //!
 //! This binary will loop until interrupted. It will run all jobs in the
-//! background queue, sleeping for 1 second whenever the queue is empty. If we
+//! background queues, checking for new jobs every second. Every queue has its
+//! own limit on the number of jobs that run concurrently, which can be
+//! configured with `BACKGROUND_JOB_CONCURRENCY` (e.g. `index=1,readme=4`). If we
 //! are unable to spawn workers to run jobs (either because we couldn't connect
 //! to the DB, an error occurred while loading, or we just never heard back from
 //! the worker thread), we will rebuild the runner and try again up to 5 times.
 //! After the 5th occurrance, we will panic.
 //!
+//! Once a minute, the jobs in `cargo_registry::schedule::SCHEDULED_JOBS`
+//! that are due are enqueued, so recurring jobs no longer need an external
+//! scheduler. If multiple workers are running, each job is still only
+//! enqueued once.
+//!
 //! Usage:
 //!      cargo run --bin background-worker
 
 #![warn(clippy::all, rust_2018_idioms)]
 
+use cargo_registry::cache::ResponseCache;
 use cargo_registry::git::{Repository, RepositoryConfig};
-use cargo_registry::{background_jobs::*, db};
+use cargo_registry::swirl::Runner;
+use cargo_registry::{background_jobs::*, db, schedule};
+use chrono::Utc;
 use diesel::r2d2;
 use reqwest::blocking::Client;
 use std::sync::{Arc, Mutex};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+
+/// How often to check for scheduled jobs that are due
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
 
 fn main() {
     println!("Booting runner");
 
-    let config = cargo_registry::Config::default();
+    let config = cargo_registry::Config::load().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+    cargo_registry::logging::init(config.log_format);
     let db_url = db::connection_url(&config.db_url);
 
     let job_start_timeout = dotenv::var("BACKGROUND_JOB_TIMEOUT")
@@ -31,6 +48,10 @@ fn main() {
         .parse()
         .expect("Invalid value for `BACKGROUND_JOB_TIMEOUT`");
 
+    let concurrency = dotenv::var("BACKGROUND_JOB_CONCURRENCY")
+        .map(|s| parse_concurrency(&s))
+        .unwrap_or_default();
+
     println!("Cloning index");
 
     let repository_config = RepositoryConfig::from_environment();
@@ -39,22 +60,58 @@ fn main() {
     ));
     println!("Index cloned");
 
+    // Only reaches the servers if they share the cache through Redis
+    let response_cache = Arc::new(ResponseCache::from_config(&config));
+
     let build_runner = || {
-        let environment =
-            Environment::new_shared(repository.clone(), config.uploader.clone(), Client::new());
-        let db_config = r2d2::Pool::builder().min_idle(Some(0));
-        swirl::Runner::builder(environment)
-            .connection_pool_builder(&db_url, db_config)
-            .job_start_timeout(Duration::from_secs(job_start_timeout))
-            .build()
+        let environment = Environment::new_shared(
+            repository.clone(),
+            config.uploader.clone(),
+            Client::new(),
+            response_cache.clone(),
+        );
+        let mut builder =
+            Runner::builder(environment).job_start_timeout(Duration::from_secs(job_start_timeout));
+        for &(queue, threads) in &concurrency {
+            builder = builder.concurrency(queue, threads);
+        }
+
+        let threads: usize = Queue::ALL
+            .iter()
+            .map(|queue| match concurrency.iter().find(|(q, _)| q == queue) {
+                Some(&(_, threads)) => threads,
+                None => queue.default_concurrency(),
+            })
+            .sum();
+        let db_config = r2d2::Pool::builder()
+            .max_size(threads as u32)
+            .min_idle(Some(0));
+        let connection_pool = db::diesel_pool(&db_url, config.env, db_config);
+
+        builder.connection_pool(connection_pool).build()
     };
     let mut runner = build_runner();
 
+    for scheduled in schedule::SCHEDULED_JOBS {
+        if !scheduled.is_enabled() {
+            println!(
+                "Not scheduling `{}`, as its settings (like `READ_ONLY_REPLICA_URL`) are missing",
+                scheduled.name
+            );
+        }
+    }
+
     println!("Runner booted, running jobs");
 
     let mut failure_count = 0;
+    let mut last_schedule_check: Option<Instant> = None;
 
     loop {
+        if last_schedule_check.map_or(true, |time| time.elapsed() >= SCHEDULE_INTERVAL) {
+            enqueue_scheduled_jobs();
+            last_schedule_check = Some(Instant::now());
+        }
+
         if let Err(e) = runner.run_all_pending_jobs() {
             failure_count += 1;
             if failure_count < 5 {
@@ -70,3 +127,41 @@ fn main() {
         sleep(Duration::from_secs(1));
     }
 }
+
+fn enqueue_scheduled_jobs() {
+    let result = db::connect_now()
+        .map_err(|e| e.to_string())
+        .and_then(|conn| {
+            schedule::enqueue_due_jobs(&conn, Utc::now().naive_utc()).map_err(|e| e.to_string())
+        });
+
+    match result {
+        Ok(enqueued) => {
+            for name in enqueued {
+                println!("Enqueued scheduled job `{}`", name);
+            }
+        }
+        Err(e) => eprintln!("Error enqueueing scheduled jobs: {}", e),
+    }
+}
+
+/// Parses a list like `index=1,readme=4` into the concurrency of each queue
+fn parse_concurrency(s: &str) -> Vec<(Queue, usize)> {
+    s.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let queue = Queue::from_name(name)
+                .unwrap_or_else(|| panic!("Unknown background job queue `{}`", name));
+            // A queue without threads would never run its jobs
+            let threads = parts
+                .next()
+                .and_then(|threads| threads.trim().parse().ok())
+                .filter(|&threads| threads > 0)
+                .unwrap_or_else(|| panic!("Invalid concurrency for queue `{}`", name));
+            (queue, threads)
+        })
+        .collect()
+}
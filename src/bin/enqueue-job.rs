@@ -1,10 +1,10 @@
 #![deny(clippy::all)]
 
 use anyhow::{anyhow, Result};
-use cargo_registry::{db, env, tasks};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::schema::background_jobs::dsl::*;
+use cargo_registry::{db, env};
 use diesel::prelude::*;
-use swirl::schema::background_jobs::dsl::*;
-use swirl::Job;
 
 fn main() -> Result<()> {
     let conn = db::connect_now()?;
@@ -25,7 +25,7 @@ fn main() -> Result<()> {
                 println!("Did not enqueue update_downloads, existing job already in progress");
                 Ok(())
             } else {
-                Ok(tasks::update_downloads().enqueue(&conn)?)
+                Ok(Job::UpdateDownloads {}.enqueue(&conn)?)
             }
         }
         "dump_db" => {
@@ -33,8 +33,41 @@ fn main() -> Result<()> {
             let target_name = args
                 .next()
                 .unwrap_or_else(|| String::from("db-dump.tar.gz"));
-            Ok(tasks::dump_db(database_url, target_name).enqueue(&conn)?)
+            Ok(Job::DumpDb {
+                database_url,
+                target_name,
+            }
+            .enqueue(&conn)?)
+        }
+        "dump_db_incremental" => {
+            let database_url = args.next().unwrap_or_else(|| env("READ_ONLY_REPLICA_URL"));
+            Ok(Job::DumpDbIncremental { database_url }.enqueue(&conn)?)
+        }
+        "dump_download_history" => {
+            let database_url = args.next().unwrap_or_else(|| env("READ_ONLY_REPLICA_URL"));
+            Ok(Job::DumpDownloadHistory { database_url }.enqueue(&conn)?)
+        }
+        "backfill_checksums" => Ok(Job::BackfillChecksums {}.enqueue(&conn)?),
+        "check_download_origins" => Ok(Job::CheckDownloadOrigins {}.enqueue(&conn)?),
+        "clean_up_stale_data" => Ok(Job::CleanUpStaleData {}.enqueue(&conn)?),
+        "compute_trending_scores" => Ok(Job::ComputeTrendingScores {}.enqueue(&conn)?),
+        "export_dependency_graph" => Ok(Job::ExportDependencyGraph {}.enqueue(&conn)?),
+        "generate_sitemaps" => Ok(Job::GenerateSitemaps {}.enqueue(&conn)?),
+        "normalize_version_licenses" => Ok(Job::NormalizeVersionLicenses {}.enqueue(&conn)?),
+        "recheck_repository_verifications" => {
+            Ok(Job::RecheckRepositoryVerifications {}.enqueue(&conn)?)
+        }
+        "reconcile_dependents" => Ok(Job::ReconcileDependents {}.enqueue(&conn)?),
+        "refresh_monthly_stats" => {
+            let backfill = args.next().map_or(false, |arg| arg == "backfill");
+            Ok(Job::RefreshMonthlyStats { backfill }.enqueue(&conn)?)
         }
+        "refresh_summary" => Ok(Job::RefreshSummary {}.enqueue(&conn)?),
+        "release_held_versions" => Ok(Job::ReleaseHeldVersions {}.enqueue(&conn)?),
+        "send_weekly_digests" => Ok(Job::SendWeeklyDigests {}.enqueue(&conn)?),
+        "sync_advisories" => Ok(Job::SyncAdvisories {}.enqueue(&conn)?),
+        "upload_pending_crates" => Ok(Job::UploadPendingCrates {}.enqueue(&conn)?),
+        "verify_storage" => Ok(Job::VerifyStorage {}.enqueue(&conn)?),
         other => Err(anyhow!("Unrecognized job type `{}`", other)),
     }
 }
@@ -4,6 +4,7 @@
 use cargo_registry::{boot, App, Env};
 use std::{
     borrow::Cow,
+    convert::Infallible,
     fs::File,
     sync::{mpsc::channel, Arc, Mutex},
     thread,
@@ -11,7 +12,6 @@ use std::{
 };
 
 use civet::Server as CivetServer;
-use conduit_hyper::Service;
 use futures_util::future::FutureExt;
 use reqwest::blocking::Client;
 use sentry::{ClientOptions, IntoDsn};
@@ -27,6 +27,12 @@ enum Server {
 use Server::*;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Loaded first, since the config file may also set the Sentry variables
+    let config = cargo_registry::Config::load().unwrap_or_else(|error| {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    });
+
     let _sentry = dotenv::var("SENTRY_DSN_API")
         .ok()
         .into_dsn()
@@ -45,13 +51,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         });
 
     // Initialize logging
-    tracing_subscriber::fmt::init();
-
-    let config = cargo_registry::Config::default();
+    cargo_registry::logging::init(config.log_format);
     let client = Client::new();
 
-    let app = App::new(config.clone(), Some(client));
-    let app = cargo_registry::build_handler(Arc::new(app));
+    let app = Arc::new(App::new(config.clone(), Some(client)));
+    cargo_registry::cache::spawn_warm_up(Arc::clone(&app));
+    let app = cargo_registry::build_handler(app);
 
     // On every server restart, ensure the categories available in the database match
     // the information in *src/categories.toml*.
@@ -100,12 +105,16 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             .build()
             .unwrap();
 
-        let handler = Arc::new(conduit_hyper::BlockingHandler::new(app));
+        let handler = Arc::new(app);
         let make_service =
             hyper::service::make_service_fn(move |socket: &hyper::server::conn::AddrStream| {
                 let addr = socket.remote_addr();
                 let handler = handler.clone();
-                async move { Service::from_blocking(handler, addr) }
+                async move {
+                    Ok::<_, Infallible>(hyper::service::service_fn(move |request| {
+                        cargo_registry::server::serve(handler.clone(), request, addr)
+                    }))
+                }
             });
 
         let addr = (ip, port).into();
@@ -0,0 +1,665 @@
+//! Caches for the busiest endpoints.
+//!
+//! The [`ResponseCache`] holds the JSON responses of the crate metadata,
+//! trending and keyword endpoints, and the [`VersionLookupCache`] the versions resolved by
+//! the download endpoint.
+//!
+//! Responses are kept in a [`CacheStore`], which is either [`MemoryStore`],
+//! local to every server process, or [`RedisStore`] if `REDIS_URL` is set, so
+//! that all servers share one cache. Responses are cached for
+//! `Config::response_cache_ttl`, and dropped once a publish, yank or owner
+//! change of the crate has been committed.
+//!
+//! After a deploy the first requests for popular crates are slow, since the
+//! database has to load all of their versions first. Every server process
+//! therefore also warms up the cache with the `show` and `versions` responses
+//! of the most downloaded crates and the `summary` response, once on startup
+//! and then every [`WARM_UP_INTERVAL`]. These entries are kept for the same
+//! TTL as the requested responses.
+//!
+//! Invalidation only reaches the cache of the server that handled the
+//! change when the [`MemoryStore`] is used, so the TTLs bound how stale a
+//! response served by another server can be.
+//!
+//! The keyword responses are tagged with [`KEYWORDS_TAG`], so that a publish
+//! can drop all of them without looking through the other entries.
+
+use conduit::{header, Body, Response};
+use diesel::prelude::*;
+use diesel::r2d2::{self, PooledConnection};
+use redis::Commands;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::controllers::krate::metadata;
+use crate::schema::crates;
+use crate::util::errors::AppResult;
+use crate::util::AppResponse;
+use crate::{App, Config};
+
+/// How often the cache is warmed up again
+pub const WARM_UP_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// A serialized JSON response body
+#[derive(Clone, Debug)]
+pub struct CachedJson(Arc<Vec<u8>>);
+
+impl CachedJson {
+    pub fn new<T: serde::Serialize>(value: &T) -> Self {
+        Self(Arc::new(serde_json::to_vec(value).unwrap()))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn to_response(&self) -> AppResponse {
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .header(header::CONTENT_LENGTH, self.0.len())
+            .body(Body::from_vec(self.0.to_vec()))
+            .unwrap() // Header values are well formed, so should not panic
+    }
+}
+
+impl From<Vec<u8>> for CachedJson {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(Arc::new(bytes))
+    }
+}
+
+/// The responses that can be cached
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    /// `GET /crates/:crate_id`
+    Crate(String),
+    /// `GET /crates/:crate_id/versions`
+    Versions(String),
+    /// `GET /summary`
+    Summary,
+    /// `GET /trending`
+    Trending,
+    /// `GET /licenses`
+    Licenses,
+    /// `GET /keywords`, with the page and the normalized sort order of the
+    /// request
+    Keywords {
+        page: u32,
+        per_page: u32,
+        sort: String,
+    },
+    /// `GET /keywords/:keyword_id`
+    Keyword(String),
+}
+
+/// The tag of the keyword responses, whose crate counts change when crates
+/// are published
+pub const KEYWORDS_TAG: &str = "keywords";
+
+impl CacheKey {
+    /// The key of the entry in the [`CacheStore`]. Crate names and keywords
+    /// are compared case insensitively, like the endpoints do.
+    pub fn store_key(&self) -> String {
+        match self {
+            CacheKey::Crate(name) => format!("crate:{}", canon_crate_name(name)),
+            CacheKey::Versions(name) => format!("versions:{}", canon_crate_name(name)),
+            CacheKey::Summary => "summary".into(),
+            CacheKey::Trending => "trending".into(),
+            CacheKey::Licenses => "licenses".into(),
+            CacheKey::Keywords {
+                page,
+                per_page,
+                sort,
+            } => format!("keywords:{}:{}:{}", sort, per_page, page),
+            CacheKey::Keyword(keyword) => format!("keyword:{}", keyword.to_lowercase()),
+        }
+    }
+
+    /// The tag the entry is removed with by [`CacheStore::remove_tagged`]
+    fn tag(&self) -> Option<&'static str> {
+        match self {
+            CacheKey::Keywords { .. } | CacheKey::Keyword(_) => Some(KEYWORDS_TAG),
+            _ => None,
+        }
+    }
+}
+
+/// Where the [`ResponseCache`] keeps its entries.
+///
+/// Stores are best effort: if they fail, entries behave as if they were not
+/// cached.
+pub trait CacheStore: Send + Sync {
+    fn get(&self, key: &str) -> Option<CachedJson>;
+
+    /// Stores an entry for `ttl`, optionally with a `tag` to remove it by
+    fn set(&self, key: &str, tag: Option<&str>, json: &CachedJson, ttl: Duration);
+
+    fn remove(&self, keys: &[String]);
+
+    /// Removes all entries that were stored with the `tag`
+    fn remove_tagged(&self, tag: &str);
+
+    /// Frees the memory of expired entries, if the store doesn't do that
+    /// itself
+    fn remove_expired(&self) {}
+}
+
+/// How many entries the [`MemoryStore`] holds. If it is full after removing
+/// the expired entries, it is cleared.
+pub const MAX_MEMORY_ENTRIES: usize = 10_000;
+
+/// A [`CacheStore`] in the memory of the server process
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    entries: Mutex<HashMap<String, MemoryEntry>>,
+}
+
+#[derive(Debug)]
+struct MemoryEntry {
+    expires_at: Instant,
+    tag: Option<String>,
+    json: CachedJson,
+}
+
+impl MemoryStore {
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl CacheStore for MemoryStore {
+    fn get(&self, key: &str) -> Option<CachedJson> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .get(key)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.json.clone())
+    }
+
+    fn set(&self, key: &str, tag: Option<&str>, json: &CachedJson, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_MEMORY_ENTRIES && !entries.contains_key(key) {
+            entries.retain(|_, entry| entry.expires_at > now);
+            if entries.len() >= MAX_MEMORY_ENTRIES {
+                entries.clear();
+            }
+        }
+        let entry = MemoryEntry {
+            expires_at: now + ttl,
+            tag: tag.map(Into::into),
+            json: json.clone(),
+        };
+        entries.insert(key.into(), entry);
+    }
+
+    fn remove(&self, keys: &[String]) {
+        let mut entries = self.entries.lock().unwrap();
+        for key in keys {
+            entries.remove(key);
+        }
+    }
+
+    fn remove_tagged(&self, tag: &str) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.tag.as_deref() != Some(tag));
+    }
+
+    fn remove_expired(&self) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|_, entry| entry.expires_at > now);
+    }
+}
+
+/// A [`CacheStore`] shared by all servers through Redis. Entries expire on
+/// their own, and all keys are prefixed with `response_cache:`.
+///
+/// The keys of the entries with a tag are also added to the set
+/// `response_cache:tag:<tag>`, which is dropped with them. The set doesn't
+/// expire, since entries of different TTLs share it, but it only holds the
+/// keys stored since the last time the tag was removed.
+#[allow(missing_debug_implementations)]
+pub struct RedisStore {
+    pool: r2d2::Pool<redis::Client>,
+}
+
+const REDIS_PREFIX: &str = "response_cache:";
+
+impl RedisStore {
+    pub fn new(url: &str) -> anyhow::Result<Self> {
+        let client = redis::Client::open(url)?;
+        let pool = r2d2::Pool::builder()
+            .max_size(10)
+            .min_idle(Some(0))
+            .connection_timeout(Duration::from_secs(1))
+            .build_unchecked(client);
+        Ok(Self { pool })
+    }
+
+    fn conn(&self) -> Option<PooledConnection<redis::Client>> {
+        self.pool
+            .get()
+            .map_err(|error| warn!(%error, "Failed to connect to the response cache"))
+            .ok()
+    }
+}
+
+fn log_redis_error<T>(result: redis::RedisResult<T>) -> Option<T> {
+    result
+        .map_err(|error| warn!(%error, "Response cache request failed"))
+        .ok()
+}
+
+impl CacheStore for RedisStore {
+    fn get(&self, key: &str) -> Option<CachedJson> {
+        let mut conn = self.conn()?;
+        let bytes: Option<Vec<u8>> = log_redis_error(conn.get(format!("{}{}", REDIS_PREFIX, key)))?;
+        bytes.map(CachedJson::from)
+    }
+
+    fn set(&self, key: &str, tag: Option<&str>, json: &CachedJson, ttl: Duration) {
+        if let Some(mut conn) = self.conn() {
+            let key = format!("{}{}", REDIS_PREFIX, key);
+            let ttl = ttl.as_secs().max(1) as usize;
+            let mut pipe = redis::pipe();
+            pipe.atomic().set_ex(&key, json.as_bytes(), ttl).ignore();
+            if let Some(tag) = tag {
+                pipe.sadd(redis_tag_key(tag), &key).ignore();
+            }
+            log_redis_error(pipe.query::<()>(&mut *conn));
+        }
+    }
+
+    fn remove(&self, keys: &[String]) {
+        if let Some(mut conn) = self.conn() {
+            let keys: Vec<String> = keys
+                .iter()
+                .map(|key| format!("{}{}", REDIS_PREFIX, key))
+                .collect();
+            log_redis_error(conn.del::<_, ()>(keys));
+        }
+    }
+
+    fn remove_tagged(&self, tag: &str) {
+        if let Some(mut conn) = self.conn() {
+            // The set is read and dropped at once, so entries stored in the
+            // meantime are added to a new set instead of being forgotten
+            let tag = redis_tag_key(tag);
+            let tagged: Option<(Vec<String>,)> = log_redis_error(
+                redis::pipe()
+                    .atomic()
+                    .smembers(&tag)
+                    .del(&tag)
+                    .ignore()
+                    .query(&mut *conn),
+            );
+            if let Some((keys,)) = tagged.filter(|(keys,)| !keys.is_empty()) {
+                log_redis_error(conn.del::<_, ()>(keys));
+            }
+        }
+    }
+}
+
+fn redis_tag_key(tag: &str) -> String {
+    format!("{}tag:{}", REDIS_PREFIX, tag)
+}
+
+#[allow(missing_debug_implementations)]
+pub struct ResponseCache {
+    store: Box<dyn CacheStore>,
+    ttl: Duration,
+}
+
+impl Default for ResponseCache {
+    /// An in-memory cache which doesn't hold any responses
+    fn default() -> Self {
+        Self::new(Box::new(MemoryStore::default()), Duration::from_secs(0))
+    }
+}
+
+impl ResponseCache {
+    /// Responses are cached for `ttl`, except if it is 0
+    pub fn new(store: Box<dyn CacheStore>, ttl: Duration) -> Self {
+        Self { store, ttl }
+    }
+
+    /// Uses Redis if `Config::redis_url` is set, or memory otherwise
+    pub fn from_config(config: &Config) -> Self {
+        let store: Box<dyn CacheStore> = match &config.redis_url {
+            Some(url) => Box::new(RedisStore::new(url).expect("invalid REDIS_URL")),
+            None => Box::new(MemoryStore::default()),
+        };
+        Self::new(store, Duration::from_secs(config.response_cache_ttl))
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<CachedJson> {
+        self.store.get(&key.store_key())
+    }
+
+    /// Caches an entry for the TTL of the cache.
+    ///
+    /// Entries of the warm-up use the same TTL, since it also bounds how long
+    /// another server can serve a response that was invalidated elsewhere.
+    pub fn insert(&self, key: CacheKey, json: &CachedJson) {
+        if self.ttl > Duration::from_secs(0) {
+            self.store.set(&key.store_key(), key.tag(), json, self.ttl);
+        }
+    }
+
+    /// Returns the cached response, or builds and caches it
+    pub fn get_or_insert_with<F>(&self, key: CacheKey, f: F) -> AppResult<CachedJson>
+    where
+        F: FnOnce() -> AppResult<CachedJson>,
+    {
+        if let Some(cached) = self.get(&key) {
+            return Ok(cached);
+        }
+        let json = f()?;
+        self.insert(key, &json);
+        Ok(json)
+    }
+
+    /// Drops the cached responses of a crate, along with the summary which
+    /// may list it
+    pub fn invalidate_crate(&self, name: &str) {
+        self.store.remove(&[
+            CacheKey::Crate(name.into()).store_key(),
+            CacheKey::Versions(name.into()).store_key(),
+            CacheKey::Summary.store_key(),
+        ]);
+    }
+
+    /// Drops the cached keyword responses, whose crate counts change when
+    /// crates are published
+    pub fn invalidate_keywords(&self) {
+        self.store.remove_tagged(KEYWORDS_TAG);
+    }
+
+    fn remove_expired(&self) {
+        self.store.remove_expired();
+    }
+}
+
+/// How many versions the [`VersionLookupCache`] holds before it is cleared
+pub const MAX_VERSION_LOOKUPS: usize = 500_000;
+
+/// How long the [`VersionLookupCache`] trusts the quarantine state of a
+/// version before checking the database again
+pub const QUARANTINE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Maps the crate name and version number of download requests to the ID of
+/// the version, the crate name as stored in the database and the checksum the
+/// crate file is stored by, if it is.
+///
+/// Versions are added the first time they are downloaded from this server
+/// and are never changed afterwards, so entries don't expire. Versions that
+/// are only stored by name when they are cached keep being served from there,
+/// which is still correct. Since yanked
+/// versions can still be downloaded, yanking doesn't affect them either. The
+/// entries of a crate are only dropped when a crate with the same name is
+/// published, which may be a new crate replacing a deleted one.
+///
+/// Whether a version is quarantined is cached by version ID for
+/// [`QUARANTINE_CHECK_INTERVAL`], since the upload scanners and the release
+/// of held versions run on the background worker. The admin endpoints that
+/// review a quarantine invalidate it right away.
+#[derive(Debug, Default)]
+pub struct VersionLookupCache {
+    entries: RwLock<HashMap<(String, String), VersionLookup>>,
+    quarantines: RwLock<HashMap<i32, (Instant, bool)>>,
+}
+
+/// The version ID, the stored crate name and the checksum of a download
+pub type VersionLookup = (i32, String, Option<String>);
+
+impl VersionLookupCache {
+    pub fn get(&self, crate_name: &str, num: &str) -> Option<VersionLookup> {
+        let key = (canon_crate_name(crate_name), num.to_string());
+        self.entries.read().unwrap().get(&key).cloned()
+    }
+
+    pub fn insert(&self, crate_name: &str, num: &str, lookup: VersionLookup) {
+        let mut entries = self.entries.write().unwrap();
+        if entries.len() >= MAX_VERSION_LOOKUPS {
+            entries.clear();
+        }
+        let key = (canon_crate_name(crate_name), num.to_string());
+        entries.insert(key, lookup);
+    }
+
+    /// Whether the version is awaiting review in the quarantine, if that was
+    /// checked within the [`QUARANTINE_CHECK_INTERVAL`]
+    pub fn is_quarantined(&self, version_id: i32) -> Option<bool> {
+        let quarantines = self.quarantines.read().unwrap();
+        quarantines
+            .get(&version_id)
+            .filter(|(checked_at, _)| checked_at.elapsed() < QUARANTINE_CHECK_INTERVAL)
+            .map(|&(_, quarantined)| quarantined)
+    }
+
+    pub fn insert_quarantined(&self, version_id: i32, quarantined: bool) {
+        let mut quarantines = self.quarantines.write().unwrap();
+        if quarantines.len() >= MAX_VERSION_LOOKUPS {
+            quarantines.clear();
+        }
+        quarantines.insert(version_id, (Instant::now(), quarantined));
+    }
+
+    pub fn invalidate_quarantine(&self, version_id: i32) {
+        self.quarantines.write().unwrap().remove(&version_id);
+    }
+
+    pub fn invalidate_crate(&self, crate_name: &str) {
+        let canonical = canon_crate_name(crate_name);
+        let mut entries = self.entries.write().unwrap();
+        entries.retain(|(cached, _), _| *cached != canonical);
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Crate names are compared like the `canon_crate_name` SQL function does
+fn canon_crate_name(name: &str) -> String {
+    name.to_lowercase().replace('-', "_")
+}
+
+/// Caches the summary and the responses of the `crates` most downloaded
+/// crates, returning the number of cached crates
+pub fn warm_up(app: &App, crates: i64) -> AppResult<usize> {
+    let pool = app
+        .read_only_replica_database
+        .as_ref()
+        .unwrap_or(&app.primary_database);
+    let conn = pool.get()?;
+    let cache = &app.response_cache;
+
+    cache.remove_expired();
+    cache.insert(CacheKey::Summary, &metadata::summary_json(&conn)?);
+
+    let names: Vec<String> = crates::table
+        .select(crates::name)
+        .order(crates::downloads.desc())
+        .limit(crates)
+        .load(&*conn)?;
+    for name in &names {
+        let (krate, versions) = metadata::crate_json(&conn, name)?;
+        cache.insert(CacheKey::Crate(name.clone()), &krate);
+        cache.insert(CacheKey::Versions(name.clone()), &versions);
+    }
+
+    Ok(names.len())
+}
+
+/// Warms up the cache on a background thread, right away and then every
+/// [`WARM_UP_INTERVAL`]. Does nothing if `Config::cache_warm_up_crates` or
+/// `Config::response_cache_ttl` is 0.
+pub fn spawn_warm_up(app: Arc<App>) {
+    let crates = app.config.cache_warm_up_crates;
+    if crates == 0 || app.config.response_cache_ttl == 0 {
+        return;
+    }
+
+    thread::spawn(move || loop {
+        let start = Instant::now();
+        match warm_up(&app, crates) {
+            Ok(count) => println!(
+                "Warmed up the response cache for {} crates in {:?}",
+                count,
+                start.elapsed()
+            ),
+            Err(e) => eprintln!("Failed to warm up the response cache: {}", e),
+        }
+        thread::sleep(WARM_UP_INTERVAL);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_cache(ttl: u64) -> ResponseCache {
+        ResponseCache::new(Box::new(MemoryStore::default()), Duration::from_secs(ttl))
+    }
+
+    #[test]
+    fn invalidating_a_crate_keeps_other_crates() {
+        let cache = memory_cache(60);
+        let json = CachedJson::new(&json!({}));
+        cache.insert(CacheKey::Crate("foo".into()), &json);
+        cache.insert(CacheKey::Versions("foo".into()), &json);
+        cache.insert(CacheKey::Crate("bar".into()), &json);
+        cache.insert(CacheKey::Summary, &json);
+        cache.insert(CacheKey::Keyword("baz".into()), &json);
+
+        cache.invalidate_crate("Foo");
+        assert_none!(cache.get(&CacheKey::Crate("foo".into())));
+        assert_none!(cache.get(&CacheKey::Versions("foo".into())));
+        assert_none!(cache.get(&CacheKey::Summary));
+        assert_some!(cache.get(&CacheKey::Crate("bar".into())));
+        assert_some!(cache.get(&CacheKey::Keyword("baz".into())));
+    }
+
+    #[test]
+    fn invalidating_keywords_keeps_crates() {
+        let cache = memory_cache(60);
+        let json = CachedJson::new(&json!({}));
+        let keywords = CacheKey::Keywords {
+            page: 1,
+            per_page: 10,
+            sort: "crates".into(),
+        };
+        cache.insert(keywords.clone(), &json);
+        cache.insert(CacheKey::Keyword("baz".into()), &json);
+        cache.insert(CacheKey::Crate("foo".into()), &json);
+
+        cache.invalidate_keywords();
+        assert_none!(cache.get(&keywords));
+        assert_none!(cache.get(&CacheKey::Keyword("BAZ".into())));
+        assert_some!(cache.get(&CacheKey::Crate("foo".into())));
+    }
+
+    #[test]
+    fn responses_are_only_cached_with_a_ttl() {
+        let json = CachedJson::new(&json!({}));
+        let key = CacheKey::Crate("foo".into());
+
+        let cache = memory_cache(0);
+        assert_ok!(cache.get_or_insert_with(key.clone(), || Ok(json.clone())));
+        assert_none!(cache.get(&key));
+
+        let cache = memory_cache(60);
+        assert_ok!(cache.get_or_insert_with(key.clone(), || Ok(json.clone())));
+        assert_some!(cache.get(&key));
+    }
+
+    #[test]
+    fn expired_entries_are_not_served() {
+        let store = MemoryStore::default();
+        let json = CachedJson::new(&json!({}));
+        store.set("foo", None, &json, Duration::from_secs(0));
+        store.set("bar", None, &json, Duration::from_secs(60));
+        assert_none!(store.get("foo"));
+        assert_some!(store.get("bar"));
+
+        store.remove_expired();
+        assert_eq!(store.len(), 1);
+    }
+
+    #[test]
+    fn full_memory_stores_drop_expired_entries_first() {
+        let store = MemoryStore::default();
+        let json = CachedJson::new(&json!({}));
+        store.set("expired", None, &json, Duration::from_secs(0));
+        for i in 1..MAX_MEMORY_ENTRIES {
+            store.set(&i.to_string(), None, &json, Duration::from_secs(60));
+        }
+        assert_eq!(store.len(), MAX_MEMORY_ENTRIES);
+
+        store.set("new", None, &json, Duration::from_secs(60));
+        assert_eq!(store.len(), MAX_MEMORY_ENTRIES);
+        assert_some!(store.get("1"));
+
+        store.set("newer", None, &json, Duration::from_secs(60));
+        assert_eq!(store.len(), 1);
+        assert_some!(store.get("newer"));
+    }
+
+    #[test]
+    fn version_lookups_use_canonical_crate_names() {
+        let cache = VersionLookupCache::default();
+        cache.insert("Foo-Bar", "1.0.0", (1, "foo_bar".into(), None));
+        cache.insert("baz", "1.0.0", (2, "baz".into(), Some("abc".into())));
+
+        assert_eq!(
+            cache.get("foo_bar", "1.0.0"),
+            Some((1, "foo_bar".into(), None))
+        );
+        assert_eq!(
+            cache.get("FOO-bar", "1.0.0"),
+            Some((1, "foo_bar".into(), None))
+        );
+        assert_none!(cache.get("foo_bar", "1.0.1"));
+
+        cache.invalidate_crate("foo-bar");
+        assert_none!(cache.get("foo_bar", "1.0.0"));
+        assert_some!(cache.get("baz", "1.0.0"));
+    }
+
+    #[test]
+    fn quarantine_states_are_cached_until_invalidated() {
+        let cache = VersionLookupCache::default();
+        assert_none!(cache.is_quarantined(1));
+
+        cache.insert_quarantined(1, true);
+        cache.insert_quarantined(2, false);
+        assert_eq!(cache.is_quarantined(1), Some(true));
+        assert_eq!(cache.is_quarantined(2), Some(false));
+
+        cache.invalidate_quarantine(1);
+        assert_none!(cache.is_quarantined(1));
+        assert_eq!(cache.is_quarantined(2), Some(false));
+    }
+
+    #[test]
+    fn cached_responses_are_json() {
+        let response = CachedJson::new(&json!({ "ok": true })).to_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "11");
+    }
+}
@@ -1,9 +1,25 @@
+use crate::download_origins::DownloadOrigin;
+use crate::first_publish_hold::FirstPublishHold;
+use crate::license::InvalidLicensePolicy;
+use crate::logging::LogFormat;
+use crate::login_rate_limit::LoginRateLimit;
+use crate::middleware::security_headers::SecurityHeaders;
+use crate::middleware::session_cookie::SessionCookie;
 use crate::publish_rate_limit::PublishRateLimit;
+use crate::quotas::Quotas;
+use crate::scanning::ScannerConfig;
+use crate::storage::{self, Gcs, Storage};
 use crate::{env, uploaders::Uploader, Env, Replica};
 
+use std::fmt;
+use std::path::PathBuf;
+
+mod file;
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub uploader: Uploader,
+    pub download_region_header: String,
     pub session_key: String,
     pub gh_client_id: String,
     pub gh_client_secret: String,
@@ -11,14 +27,27 @@ pub struct Config {
     pub db_url: String,
     pub replica_db_url: Option<String>,
     pub env: Env,
+    pub dev_mode: bool,
     pub max_upload_size: u64,
     pub max_unpack_size: u64,
     pub mirror: Replica,
     pub api_protocol: String,
     pub publish_rate_limit: PublishRateLimit,
+    pub login_rate_limit: LoginRateLimit,
     pub blocked_traffic: Vec<(String, Vec<String>)>,
     pub domain_name: String,
     pub allowed_origins: Vec<String>,
+    pub upload_scanners: Vec<ScannerConfig>,
+    pub metrics_authorization_token: Option<String>,
+    pub log_format: LogFormat,
+    pub cache_warm_up_crates: i64,
+    pub redis_url: Option<String>,
+    pub response_cache_ttl: u64,
+    pub security_headers: SecurityHeaders,
+    pub session_cookie: SessionCookie,
+    pub first_publish_hold: Option<FirstPublishHold>,
+    pub quotas: Quotas,
+    pub invalid_license_policy: InvalidLicensePolicy,
 }
 
 impl Default for Config {
@@ -38,6 +67,13 @@ impl Default for Config {
     /// - `S3_REGION`: The region in which the bucket was created. Optional if US standard.
     /// - `S3_ACCESS_KEY`: The access key to interact with S3. Optional if running a mirror.
     /// - `S3_SECRET_KEY`: The secret key to interact with S3. Optional if running a mirror.
+    /// - `GCS_BUCKET`: The Google Cloud Storage bucket used to store crate files instead of S3,
+    ///    if `S3_BUCKET` is not set. `GCS_CDN` and `GCS_ACCESS_TOKEN` are optional.
+    /// - `STORAGE_SECONDARY`: `s3`, `gcs` or `local` to store a copy of every file in another
+    ///    backend, see the `storage` module for more documentation.
+    /// - `DOWNLOAD_ORIGINS` and `DOWNLOAD_REGION_HEADER`: Redirect downloads to other hosts
+    ///    serving the crate files, by region and weight. See the `download_origins` module for
+    ///    more documentation.
     /// - `SESSION_KEY`: The key used to sign and encrypt session cookies.
     /// - `GH_CLIENT_ID`: The client ID of the associated GitHub application.
     /// - `GH_CLIENT_SECRET`: The client secret of the associated GitHub application.
@@ -45,6 +81,34 @@ impl Default for Config {
     /// - `READ_ONLY_REPLICA_URL`: The URL of an optional postgres read-only replica database.
     /// - `BLOCKED_TRAFFIC`: A list of headers and environment variables to use for blocking
     ///.  traffic. See the `block_traffic` module for more documentation.
+    /// - `UPLOAD_SCANNERS`: A list of scanners to run uploaded crate files through. See the
+    ///    `scanning` module for more documentation.
+    /// - `METRICS_AUTHORIZATION_TOKEN`: The bearer token required to scrape the Prometheus
+    ///    metrics. The metrics are not available if this is not set.
+    /// - `LOG_FORMAT`: Either `logfmt` (the default) or `json`. See the `logging` module for
+    ///    more documentation.
+    /// - `CACHE_WARM_UP_CRATES`: The number of most downloaded crates whose responses are
+    ///    cached by every server, 100 by default. See the `cache` module for more documentation.
+    /// - `REDIS_URL`: The URL of an optional Redis server which holds the response cache shared by
+    ///    all servers. Every server caches responses in its own memory if this is not set.
+    /// - `RESPONSE_CACHE_TTL`: How many seconds requested responses are cached, 60 by default.
+    ///    Set to 0 to only cache the responses of the warm-up.
+    /// - `CONTENT_SECURITY_POLICY`, `STRICT_TRANSPORT_SECURITY`, `REFERRER_POLICY`,
+    ///   `FRAME_OPTIONS` and `CONTENT_SECURITY_POLICY_OVERRIDES`: Change the security headers of
+    ///    responses. See the `security_headers` middleware for more documentation.
+    /// - `SESSION_COOKIE_SAME_SITE` and `SESSION_COOKIE_SECURE`: Change the attributes of the
+    ///    session cookie. See the `session_cookie` middleware for more documentation.
+    /// - `FIRST_PUBLISH_HOLD_ACCOUNT_AGE_HOURS` and `FIRST_PUBLISH_HOLD_WINDOW_HOURS`: Hold the
+    ///    versions published by new accounts out of the index for a while. Disabled if the account
+    ///    age is not set. See the `first_publish_hold` module for more documentation.
+    /// - `STORAGE_QUOTA_BYTES`, `VERSIONS_PER_DAY_QUOTA` and `CRATES_QUOTA`: Limit the storage,
+    ///    the versions published per day and the crates of every account, unless an admin changed
+    ///    them for the account. See the `quotas` module for more documentation.
+    /// - `INVALID_LICENSE_POLICY`: `reject` (the default) or `warn` about versions whose license
+    ///    isn't a valid SPDX expression. See the `license` module for more documentation.
+    /// - `DEV_MODE`: Replaces GitHub with the built-in fake of the `github::fake` module and
+    ///    stores crate files in `local_uploads`, so everything works offline. The `GH_CLIENT_ID`
+    ///    and `GH_CLIENT_SECRET` are not needed then. Ignored on Heroku.
     fn default() -> Config {
         let api_protocol = String::from("https");
         let mirror = if dotenv::var("MIRROR").is_ok() {
@@ -58,21 +122,28 @@ impl Default for Config {
         } else {
             Env::Development
         };
+        let dev_mode = !heroku && dotenv::var("DEV_MODE").is_ok();
+        let gcs = dotenv::var("GCS_BUCKET").is_ok() && dotenv::var("S3_BUCKET").is_err();
         let uploader = match (cargo_env, mirror) {
+            // Google Cloud Storage is used instead of S3 if only its bucket is set
+            _ if gcs && !dev_mode => {
+                println!("Using Google Cloud Storage uploader");
+                Uploader::new(Gcs::from_environment())
+            }
             (Env::Production, Replica::Primary) => {
                 // `env` panics if these vars are not set, and in production for a primary instance,
                 // that's what we want since we don't want to be able to start the server if the
                 // server doesn't know where to upload crates.
-                Uploader::S3 {
-                    bucket: s3::Bucket::new(
+                Uploader::s3(
+                    s3::Bucket::new(
                         env("S3_BUCKET"),
                         dotenv::var("S3_REGION").ok(),
                         env("S3_ACCESS_KEY"),
                         env("S3_SECRET_KEY"),
                         &api_protocol,
                     ),
-                    cdn: dotenv::var("S3_CDN").ok(),
-                }
+                    dotenv::var("S3_CDN").ok(),
+                )
             }
             (Env::Production, Replica::ReadOnlyMirror) => {
                 // Read-only mirrors don't need access key or secret key since by definition,
@@ -83,35 +154,35 @@ impl Default for Config {
                 //
                 // Read-only mirrors definitely need bucket though, so that they know where
                 // to serve crate files from.
-                Uploader::S3 {
-                    bucket: s3::Bucket::new(
+                Uploader::s3(
+                    s3::Bucket::new(
                         env("S3_BUCKET"),
                         dotenv::var("S3_REGION").ok(),
                         dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
                         dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
                         &api_protocol,
                     ),
-                    cdn: dotenv::var("S3_CDN").ok(),
-                }
+                    dotenv::var("S3_CDN").ok(),
+                )
             }
             // In Development mode, either running as a primary instance or a read-only mirror
             _ => {
-                if dotenv::var("S3_BUCKET").is_ok() {
+                if dotenv::var("S3_BUCKET").is_ok() && !dev_mode {
                     // If we've set the `S3_BUCKET` variable to any value, use all of the values
                     // for the related S3 environment variables and configure the app to upload to
                     // and read from S3 like production does. All values except for bucket are
                     // optional, like production read-only mirrors.
                     println!("Using S3 uploader");
-                    Uploader::S3 {
-                        bucket: s3::Bucket::new(
+                    Uploader::s3(
+                        s3::Bucket::new(
                             env("S3_BUCKET"),
                             dotenv::var("S3_REGION").ok(),
                             dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
                             dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
                             &api_protocol,
                         ),
-                        cdn: dotenv::var("S3_CDN").ok(),
-                    }
+                        dotenv::var("S3_CDN").ok(),
+                    )
                 } else {
                     // If we don't set the `S3_BUCKET` variable, we'll use a development-only
                     // uploader that makes it possible to run and publish to a locally-running
@@ -119,32 +190,298 @@ impl Default for Config {
                     println!(
                         "Using local uploader, crate files will be in the local_uploads directory"
                     );
-                    Uploader::Local
+                    Uploader::local()
                 }
             }
         };
+        let uploader = match storage::secondary_from_environment(&api_protocol) {
+            Some(secondary) => uploader.with_secondary(secondary),
+            None => uploader,
+        };
+        let uploader = uploader.with_download_origins(DownloadOrigin::from_environment());
+        let security_headers =
+            SecurityHeaders::from_environment(cargo_env, uploader.host().as_deref());
         let allowed_origins = env("WEB_ALLOWED_ORIGINS")
             .split(',')
             .map(ToString::to_string)
             .collect();
         Config {
             uploader,
+            download_region_header: dotenv::var("DOWNLOAD_REGION_HEADER")
+                .unwrap_or_else(|_| String::from("CloudFront-Viewer-Country")),
             session_key: env("SESSION_KEY"),
-            gh_client_id: env("GH_CLIENT_ID"),
-            gh_client_secret: env("GH_CLIENT_SECRET"),
-            gh_base_url: "https://api.github.com".to_string(),
+            gh_client_id: gh_setting("GH_CLIENT_ID", dev_mode),
+            gh_client_secret: gh_setting("GH_CLIENT_SECRET", dev_mode),
+            gh_base_url: crate::github::DEFAULT_BASE_URL.to_string(),
             db_url: env("DATABASE_URL"),
             replica_db_url: dotenv::var("READ_ONLY_REPLICA_URL").ok(),
             env: cargo_env,
+            dev_mode,
             max_upload_size: 10 * 1024 * 1024, // 10 MB default file upload size limit
             max_unpack_size: 512 * 1024 * 1024, // 512 MB max when decompressed
             mirror,
             api_protocol,
             publish_rate_limit: Default::default(),
+            login_rate_limit: Default::default(),
             blocked_traffic: blocked_traffic(),
             domain_name: domain_name(),
             allowed_origins,
+            upload_scanners: ScannerConfig::from_environment(),
+            metrics_authorization_token: dotenv::var("METRICS_AUTHORIZATION_TOKEN").ok(),
+            log_format: LogFormat::from_environment(),
+            cache_warm_up_crates: dotenv::var("CACHE_WARM_UP_CRATES")
+                .map(|s| {
+                    s.parse()
+                        .expect("CACHE_WARM_UP_CRATES was not a valid number")
+                })
+                .unwrap_or(100),
+            redis_url: dotenv::var("REDIS_URL").ok(),
+            response_cache_ttl: dotenv::var("RESPONSE_CACHE_TTL")
+                .map(|s| {
+                    s.parse()
+                        .expect("RESPONSE_CACHE_TTL was not a valid number")
+                })
+                .unwrap_or(60),
+            security_headers,
+            session_cookie: SessionCookie::from_environment(cargo_env),
+            first_publish_hold: FirstPublishHold::from_environment(),
+            quotas: Quotas::from_environment(),
+            invalid_license_policy: InvalidLicensePolicy::from_environment(),
+        }
+    }
+}
+
+/// The problems with the settings that were found at startup
+#[derive(Debug)]
+pub struct ConfigError(Vec<String>);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "crates.io is not configured correctly:")?;
+        for problem in &self.0 {
+            writeln!(f, "  - {}", problem)?;
+        }
+        write!(
+            f,
+            "See `.env.sample` and `crates-io.toml.sample` for the available settings."
+        )
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+/// The environment variables that have to be set, with what they are for
+const REQUIRED_VARIABLES: &[(&str, &str)] = &[
+    ("DATABASE_URL", "the URL of the postgres database"),
+    (
+        "SESSION_KEY",
+        "the key used to sign and encrypt session cookies, at least 32 bytes long",
+    ),
+    (
+        "GH_CLIENT_ID",
+        "the client ID of the GitHub OAuth application",
+    ),
+    (
+        "GH_CLIENT_SECRET",
+        "the client secret of the GitHub OAuth application",
+    ),
+    (
+        "WEB_ALLOWED_ORIGINS",
+        "the comma-separated origins allowed to use authenticated endpoints",
+    ),
+];
+
+/// The environment variables that have to be numbers if they are set
+const NUMERIC_VARIABLES: &[&str] = &[
+    "CACHE_WARM_UP_CRATES",
+    "RESPONSE_CACHE_TTL",
+    "STORAGE_QUOTA_BYTES",
+    "DB_POOL_SIZE",
+    "DB_MIN_IDLE",
+    "DB_HELPER_THREADS",
+    "DB_TIMEOUT",
+    "DB_REPLICA_MAX_LAG",
+    "FIRST_PUBLISH_HOLD_ACCOUNT_AGE_HOURS",
+    "FIRST_PUBLISH_HOLD_WINDOW_HOURS",
+];
+
+impl Config {
+    /// Reads the config file if there is one, checks the settings and
+    /// returns the config. Unlike `Config::default`, which panics at the first
+    /// missing setting, this reports all problems at once.
+    pub fn load() -> Result<Config, ConfigError> {
+        if let Some(path) = file::path() {
+            file::apply(&path).map_err(|e| ConfigError(vec![e]))?;
+        }
+        let problems = check_environment();
+        if !problems.is_empty() {
+            return Err(ConfigError(problems));
+        }
+        Ok(Config::default())
+    }
+
+    /// The config file that was read at startup, if any
+    pub fn file_path() -> Option<PathBuf> {
+        file::path()
+    }
+
+    /// The effective settings without secrets like keys, passwords and
+    /// database URLs, for `GET /admin/config`
+    pub fn public_settings(&self) -> serde_json::Value {
+        let storage = |storage: &dyn Storage| {
+            json!({
+                "kind": storage.kind(),
+                "host": storage.host(),
+            })
+        };
+        let uploader = json!({
+            "primary": storage(self.uploader.primary()),
+            "secondary": self.uploader.secondary().map(storage),
+        });
+        json!({
+            "config_file": Config::file_path(),
+            "env": format!("{:?}", self.env),
+            "dev_mode": self.dev_mode,
+            "mirror": self.mirror == Replica::ReadOnlyMirror,
+            "domain_name": self.domain_name,
+            "api_protocol": self.api_protocol,
+            "allowed_origins": self.allowed_origins,
+            "uploader": uploader,
+            "download_origins": self
+                .uploader
+                .download_origins()
+                .iter()
+                .map(|origin| &origin.url)
+                .collect::<Vec<_>>(),
+            "download_region_header": self.download_region_header,
+            "gh_base_url": self.gh_base_url,
+            "read_only_replica": self.replica_db_url.is_some(),
+            "redis": self.redis_url.is_some(),
+            "max_upload_size": self.max_upload_size,
+            "max_unpack_size": self.max_unpack_size,
+            "blocked_traffic_headers": self
+                .blocked_traffic
+                .iter()
+                .map(|(header, _)| header)
+                .collect::<Vec<_>>(),
+            "upload_scanners": self.upload_scanners.len(),
+            "metrics_enabled": self.metrics_authorization_token.is_some(),
+            "log_format": format!("{:?}", self.log_format),
+            "cache_warm_up_crates": self.cache_warm_up_crates,
+            "response_cache_ttl": self.response_cache_ttl,
+            "first_publish_hold": self.first_publish_hold.is_some(),
+            "storage_quota": self.quotas.storage_bytes,
+            "versions_per_day_quota": self.quotas.versions_per_day,
+            "crates_quota": self.quotas.crates,
+            "invalid_license_policy": format!("{:?}", self.invalid_license_policy),
+        })
+    }
+}
+
+/// Finds missing and invalid environment variables
+fn check_environment() -> Vec<String> {
+    let is_set = |name: &str| dotenv::var(name).map_or(false, |value| !value.is_empty());
+    let mut problems = Vec::new();
+
+    let dev_mode = dotenv::var("DEV_MODE").is_ok();
+    for (name, description) in REQUIRED_VARIABLES {
+        // The fake GitHub of the development mode needs no OAuth application
+        if dev_mode && name.starts_with("GH_") {
+            continue;
+        }
+        if !is_set(name) {
+            problems.push(format!("`{}` must be set to {}", name, description));
+        }
+    }
+    let gcs = is_set("GCS_BUCKET") && !is_set("S3_BUCKET");
+    if dotenv::var("HEROKU").is_ok() {
+        if dev_mode {
+            problems.push("`DEV_MODE` must not be set in production".into());
+        }
+        let mut s3_variables = if gcs { vec![] } else { vec!["S3_BUCKET"] };
+        if dotenv::var("MIRROR").is_err() && !gcs {
+            s3_variables.extend(&["S3_ACCESS_KEY", "S3_SECRET_KEY"]);
         }
+        for name in s3_variables {
+            if !is_set(name) {
+                problems.push(format!(
+                    "`{}` must be set in production, where crate files are stored in S3",
+                    name
+                ));
+            }
+        }
+        if !is_set("API_TOKEN_PEPPER") {
+            problems.push(
+                "`API_TOKEN_PEPPER` must be set in production, where it is the secret \
+                 mixed into the hashes of API tokens"
+                    .into(),
+            );
+        }
+    }
+    if let Ok(secondary) = dotenv::var("STORAGE_SECONDARY") {
+        let primary = if is_set("S3_BUCKET") || (dotenv::var("HEROKU").is_ok() && !gcs) {
+            "s3"
+        } else if gcs {
+            "gcs"
+        } else {
+            "local"
+        };
+        let bucket = match &*secondary {
+            "s3" => Some("S3_BUCKET"),
+            "gcs" => Some("GCS_BUCKET"),
+            "local" => None,
+            _ => {
+                problems.push(format!(
+                    "`STORAGE_SECONDARY` must be `s3`, `gcs` or `local`, but is `{}`",
+                    secondary
+                ));
+                None
+            }
+        };
+        if secondary == primary {
+            problems.push(format!(
+                "`STORAGE_SECONDARY` must differ from the primary storage, which is `{}`",
+                primary
+            ));
+        } else if let Some(bucket) = bucket.filter(|bucket| !is_set(bucket)) {
+            problems.push(format!(
+                "`{}` must be set to use `{}` as the secondary storage",
+                bucket, secondary
+            ));
+        }
+    }
+    if let Ok(origins) = dotenv::var("DOWNLOAD_ORIGINS") {
+        if let Err(e) = DownloadOrigin::parse(&origins) {
+            problems.push(e);
+        }
+    }
+    for name in NUMERIC_VARIABLES {
+        if let Ok(value) = dotenv::var(name) {
+            if value.parse::<u64>().is_err() {
+                problems.push(format!("`{}` must be a number, but is `{}`", name, value));
+            }
+        }
+    }
+    let patterns = dotenv::var("BLOCKED_TRAFFIC").unwrap_or_default();
+    if patterns
+        .split_terminator(',')
+        .any(|pattern| !pattern.contains('='))
+    {
+        problems.push(format!(
+            "`BLOCKED_TRAFFIC` must be in the form HEADER=VALUE_ENV_VAR, but is `{}`",
+            patterns
+        ));
+    }
+
+    problems
+}
+
+/// The GitHub OAuth settings are only needed outside of the development mode
+fn gh_setting(name: &str, dev_mode: bool) -> String {
+    if dev_mode {
+        dotenv::var(name).unwrap_or_default()
+    } else {
+        env(name)
     }
 }
 
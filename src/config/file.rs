@@ -0,0 +1,162 @@
+//! Settings from a TOML file, for deployments that prefer a file over setting
+//! every environment variable
+//!
+//! The file is read from the path in `CRATES_IO_CONFIG`, or from
+//! `crates-io.toml` in the working directory if it exists. Every setting
+//! stands for the environment variable of the same name in upper case, where
+//! keys in tables are prefixed with the name of the table:
+//!
+//! ```toml
+//! session_key = "..."
+//! web_allowed_origins = ["https://staging.crates.io"]
+//!
+//! [database]
+//! url = "postgres://postgres@localhost/cargo_registry"  # DATABASE_URL
+//!
+//! [gh]
+//! client_id = "..."                                     # GH_CLIENT_ID
+//! ```
+//!
+//! Arrays are joined with commas, `true` sets the variable to `1` and `false`
+//! leaves it unset. Environment variables, including the ones in `.env`,
+//! take precedence over the file.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+/// The file that is read if `CRATES_IO_CONFIG` isn't set
+const DEFAULT_PATH: &str = "crates-io.toml";
+
+/// The path of the file, if there is one
+pub fn path() -> Option<PathBuf> {
+    match dotenv::var("CRATES_IO_CONFIG") {
+        Ok(path) => Some(path.into()),
+        Err(_) => Some(PathBuf::from(DEFAULT_PATH)).filter(|path| path.exists()),
+    }
+}
+
+/// Sets the environment variables of the file that aren't set yet
+pub fn apply(path: &Path) -> Result<(), String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("could not read the config file {}: {}", path.display(), e))?;
+    let settings = settings(&contents)
+        .map_err(|e| format!("invalid config file {}: {}", path.display(), e))?;
+
+    // Makes sure that `.env` is loaded before the file, so that it wins
+    dotenv::dotenv().ok();
+    for (name, value) in settings {
+        if let Some(value) = value {
+            if std::env::var_os(&name).is_none() {
+                std::env::set_var(name, value);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The environment variables of the file with their values, or `None` for
+/// variables that are `false`
+fn settings(contents: &str) -> Result<Vec<(String, Option<String>)>, String> {
+    let table = match contents.parse::<Value>().map_err(|e| e.to_string())? {
+        Value::Table(table) => table,
+        _ => return Err("expected a table".into()),
+    };
+    let mut settings = Vec::new();
+    flatten("", table, &mut settings)?;
+    Ok(settings)
+}
+
+fn flatten(
+    prefix: &str,
+    table: toml::value::Table,
+    settings: &mut Vec<(String, Option<String>)>,
+) -> Result<(), String> {
+    for (key, value) in table {
+        let name = format!("{}{}", prefix, key.to_uppercase());
+        let value = match value {
+            Value::Table(table) => {
+                flatten(&format!("{}_", name), table, settings)?;
+                continue;
+            }
+            Value::Boolean(false) => None,
+            Value::Array(values) => Some(
+                values
+                    .into_iter()
+                    .map(|value| scalar(&name, value))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .join(","),
+            ),
+            value => Some(scalar(&name, value)?),
+        };
+        settings.push((name, value));
+    }
+    Ok(())
+}
+
+fn scalar(name: &str, value: Value) -> Result<String, String> {
+    match value {
+        Value::String(string) => Ok(string),
+        Value::Integer(integer) => Ok(integer.to_string()),
+        Value::Float(float) => Ok(float.to_string()),
+        Value::Boolean(_) => Ok("1".into()),
+        Value::Datetime(datetime) => Ok(datetime.to_string()),
+        Value::Array(_) | Value::Table(_) => {
+            Err(format!("`{}` must be a string, number or boolean", name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tables_prefix_their_keys() {
+        let settings = settings(
+            r#"
+            session_key = "secret"
+            mirror = true
+            heroku = false
+            web_allowed_origins = ["http://localhost:8888", "http://localhost:4200"]
+
+            [database]
+            url = "postgres://localhost/cargo_registry"
+
+            [response_cache]
+            ttl = 30
+            "#,
+        )
+        .unwrap();
+
+        let get = |name: &str| {
+            settings
+                .iter()
+                .find(|(setting, _)| setting == name)
+                .map(|(_, value)| value.as_deref())
+        };
+        assert_eq!(get("SESSION_KEY"), Some(Some("secret")));
+        assert_eq!(get("MIRROR"), Some(Some("1")));
+        assert_eq!(get("HEROKU"), Some(None));
+        assert_eq!(
+            get("WEB_ALLOWED_ORIGINS"),
+            Some(Some("http://localhost:8888,http://localhost:4200"))
+        );
+        assert_eq!(
+            get("DATABASE_URL"),
+            Some(Some("postgres://localhost/cargo_registry"))
+        );
+        assert_eq!(get("RESPONSE_CACHE_TTL"), Some(Some("30")));
+    }
+
+    #[test]
+    fn nested_arrays_are_rejected() {
+        let error = settings("upload_scanners = [[1]]").unwrap_err();
+        assert_eq!(
+            error,
+            "`UPLOAD_SCANNERS` must be a string, number or boolean"
+        );
+        assert!(settings("not toml").is_err());
+    }
+}
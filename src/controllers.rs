@@ -17,7 +17,7 @@ mod prelude {
     pub use conduit::{header, RequestExt, StatusCode};
     pub use conduit_router::RequestParams;
 
-    pub use crate::db::RequestTransaction;
+    pub use crate::db::{EndpointFamily, RequestTransaction};
     pub use crate::middleware::app::RequestApp;
     pub use crate::middleware::log_request::TimingRecorder;
     pub use crate::util::errors::{cargo_err, AppError, AppResult, ChainError}; // TODO: Remove cargo_err from here
@@ -97,11 +97,20 @@ mod prelude {
 pub mod helpers;
 mod util;
 
+pub mod admin;
+pub mod announcement;
+pub mod audit;
 pub mod category;
+pub mod crate_list;
 pub mod crate_owner_invitation;
 pub mod keyword;
 pub mod krate;
+pub mod license;
+pub mod metrics;
+pub mod policy;
+pub mod purl;
 pub mod site_metadata;
+pub mod sitemap;
 pub mod team;
 pub mod token;
 pub mod user;
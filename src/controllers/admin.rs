@@ -0,0 +1,42 @@
+//! Endpoints that are only available to crates.io administrators
+
+pub mod announcements;
+pub mod background_jobs;
+pub mod bulk_yanks;
+pub mod config;
+pub mod feature_flags;
+pub mod featured_crates;
+pub mod login_anomalies;
+pub mod maintenance_mode;
+pub mod metrics;
+pub mod quarantine;
+pub mod quotas;
+pub mod rate_limits;
+pub mod readme_rerenders;
+pub mod reserved_names;
+pub mod scheduled_jobs;
+pub mod storage_mismatches;
+pub mod users;
+
+use super::prelude::*;
+use super::util::AuthenticatedUser;
+
+use crate::models::User;
+use crate::util::errors::{bad_request, forbidden};
+
+/// Authenticates the user and ensures that they are an administrator.
+fn authenticate_admin(req: &mut dyn RequestExt) -> AppResult<AuthenticatedUser> {
+    let authenticated_user = req.authenticate()?;
+    if !authenticated_user.is_admin() {
+        return Err(forbidden());
+    }
+    Ok(authenticated_user)
+}
+
+/// Loads the user referenced by the `:user_id` route parameter.
+fn find_user(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<User> {
+    let user_id = req.params()["user_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid user_id"))?;
+    Ok(User::find(conn, user_id)?)
+}
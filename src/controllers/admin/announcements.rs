@@ -0,0 +1,119 @@
+//! Endpoints for managing the registry-wide notices listed by
+//! `GET /announcements`
+//!
+//! Announcements of `warning` and `critical` severity are also shown by cargo
+//! when publishing, as warnings of the publish response.
+
+use chrono::{NaiveDateTime, Utc};
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Announcement, AnnouncementSeverity, NewAnnouncement};
+use crate::util::errors::not_found;
+use crate::util::rfc3339;
+use crate::views::EncodableAnnouncement;
+
+#[derive(Deserialize)]
+struct AnnouncementRequest {
+    message: String,
+    url: Option<String>,
+    severity: String,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    starts_at: Option<NaiveDateTime>,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    ends_at: Option<NaiveDateTime>,
+}
+
+/// Handles the `GET /admin/announcements` route.
+///
+/// Lists past, current and upcoming announcements, the latest first.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let announcements = Announcement::all(&conn)?
+        .into_iter()
+        .map(EncodableAnnouncement::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        announcements: Vec<EncodableAnnouncement>,
+    }
+    Ok(req.json(&R { announcements }))
+}
+
+/// Handles the `PUT /admin/announcements` route.
+///
+/// The announcement is shown from `starts_at`, defaulting to now, until
+/// `ends_at`, or until it is deleted if no end is given.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: AnnouncementRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let message = request.message.trim();
+    if message.is_empty() || message.chars().count() > Announcement::MAX_MESSAGE_LENGTH {
+        return Err(bad_request(&format_args!(
+            "the message must be between 1 and {} characters long",
+            Announcement::MAX_MESSAGE_LENGTH
+        )));
+    }
+    let severity = AnnouncementSeverity::from_name(&request.severity).ok_or_else(|| {
+        bad_request("the severity must be one of `info`, `warning` or `critical`")
+    })?;
+    let url = request
+        .url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty());
+    if let Some(url) = url {
+        let valid = url::Url::parse(url)
+            .map(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+            .unwrap_or(false);
+        if !valid {
+            return Err(bad_request("the url must be an http or https URL"));
+        }
+    }
+    let starts_at = request.starts_at.unwrap_or_else(|| Utc::now().naive_utc());
+    if matches!(request.ends_at, Some(ends_at) if ends_at <= starts_at) {
+        return Err(bad_request("`ends_at` must be after `starts_at`"));
+    }
+
+    let conn = req.db_conn()?;
+    let announcement = NewAnnouncement {
+        message,
+        url,
+        severity,
+        starts_at,
+        ends_at: request.ends_at,
+        admin_id: admin.user_id(),
+    }
+    .create(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        announcement: EncodableAnnouncement,
+    }
+    Ok(req.json(&R {
+        announcement: announcement.into(),
+    }))
+}
+
+/// Handles the `DELETE /admin/announcements/:announcement_id` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let id = req.params()["announcement_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid announcement_id"))?;
+    let conn = req.db_conn()?;
+    if !Announcement::delete(&conn, id)? {
+        return Err(not_found());
+    }
+
+    ok_true()
+}
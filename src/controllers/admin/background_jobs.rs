@@ -0,0 +1,318 @@
+//! Endpoints for inspecting and managing the background job queues
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, Utc};
+use diesel::dsl::exists;
+use diesel::sql_types::{BigInt, Double, Nullable, Text, Timestamp};
+
+use super::authenticate_admin;
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::schema::{background_job_runs, background_jobs};
+use crate::swirl::{lock_idle_job, running_job_ids};
+use crate::util::errors::not_found;
+use crate::views::{EncodableBackgroundJob, EncodableBackgroundJobRun};
+
+/// The number of recent runs included when showing a single job
+const RECENT_RUNS: i64 = 10;
+
+/// The default and maximum number of hours covered by the statistics
+const DEFAULT_STATS_HOURS: i64 = 24;
+const MAX_STATS_HOURS: i64 = 24 * 30;
+
+#[derive(Queryable)]
+struct BackgroundJob {
+    id: i64,
+    job_type: String,
+    data: serde_json::Value,
+    retries: i32,
+    last_retry: NaiveDateTime,
+    created_at: NaiveDateTime,
+    queue: String,
+    priority: i16,
+    last_error: Option<String>,
+    request_id: Option<String>,
+    run_at: NaiveDateTime,
+}
+
+impl BackgroundJob {
+    fn encodable(
+        self,
+        conn: &PgConnection,
+        running: &[i64],
+    ) -> QueryResult<EncodableBackgroundJob> {
+        let progress = match Job::from_parts(&self.job_type, self.data.clone()) {
+            Ok(job) => job.progress(conn)?,
+            Err(_) => None,
+        };
+        let retried = self.retries > 0;
+        let status = if running.contains(&self.id) {
+            "running"
+        } else if retried {
+            "failed"
+        } else {
+            "queued"
+        };
+
+        Ok(EncodableBackgroundJob {
+            id: self.id,
+            job_type: self.job_type,
+            queue: self.queue,
+            priority: self.priority,
+            status: status.into(),
+            data: redact(self.data),
+            retries: self.retries,
+            last_retry: Some(self.last_retry).filter(|_| retried),
+            last_error: self.last_error,
+            created_at: self.created_at,
+            request_id: self.request_id,
+            run_at: self.run_at,
+            progress,
+        })
+    }
+}
+
+/// Hides the fields of a job's data that must not leave the server, like the
+/// credentials in the `database_url` of the database dump jobs.
+fn redact(mut data: serde_json::Value) -> serde_json::Value {
+    if let Some(database_url) = data.get_mut("database_url") {
+        *database_url = "[REDACTED]".into();
+    }
+    data
+}
+
+fn job_id(req: &dyn RequestExt) -> AppResult<i64> {
+    req.params()["job_id"]
+        .parse()
+        .chain_error(|| bad_request("invalid job_id"))
+}
+
+/// Handles the `GET /admin/background_jobs` route.
+///
+/// The jobs can be filtered by `status` (`queued`, `running` or `failed`)
+/// and by `job_type`. They are listed in the order in which they will run.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let pagination = PaginationOptions::new(req)?;
+
+    // Running jobs can only be detected on the primary database
+    let conn = req.db_conn()?;
+    let running = running_job_ids(&conn)?;
+
+    let params = req.query();
+    let mut query = background_jobs::table.into_boxed();
+    match params.get("status").map(|s| &s[..]) {
+        None => {}
+        Some("running") => query = query.filter(background_jobs::id.eq_any(running.clone())),
+        Some("failed") => {
+            query = query
+                .filter(background_jobs::retries.gt(0))
+                .filter(background_jobs::id.ne_all(running.clone()))
+        }
+        Some("queued") => {
+            query = query
+                .filter(background_jobs::retries.eq(0))
+                .filter(background_jobs::id.ne_all(running.clone()))
+        }
+        Some(status) => {
+            return Err(bad_request(&format_args!(
+                "invalid background job status: {}",
+                status
+            )))
+        }
+    }
+    if let Some(job_type) = params.get("job_type") {
+        query = query.filter(background_jobs::job_type.eq(job_type.clone()));
+    }
+
+    let query = query
+        .order((
+            background_jobs::queue,
+            background_jobs::priority.desc(),
+            background_jobs::id,
+        ))
+        .paginate_with(pagination);
+
+    let data: Paginated<BackgroundJob> = query.load(&*conn)?;
+    let total = data.total();
+    let background_jobs = data
+        .into_iter()
+        .map(|job| job.encodable(&conn, &running))
+        .collect::<QueryResult<_>>()?;
+
+    #[derive(Serialize)]
+    struct R {
+        background_jobs: Vec<EncodableBackgroundJob>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        background_jobs,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /admin/background_jobs/:job_id` route.
+///
+/// Includes the most recent attempts to run the job, along with their errors.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let job_id = job_id(req)?;
+    let conn = req.db_conn()?;
+    let running = running_job_ids(&conn)?;
+    let job: BackgroundJob = background_jobs::table
+        .find(job_id)
+        .first(&*conn)
+        .optional()?
+        .ok_or_else(not_found)?;
+
+    let runs = background_job_runs::table
+        .filter(background_job_runs::job_id.eq(job_id))
+        .select((
+            background_job_runs::started_at,
+            background_job_runs::finished_at,
+            background_job_runs::succeeded,
+            background_job_runs::error,
+        ))
+        .order(background_job_runs::id.desc())
+        .limit(RECENT_RUNS)
+        .load(&*conn)?
+        .into_iter()
+        .map(
+            |(started_at, finished_at, succeeded, error)| EncodableBackgroundJobRun {
+                started_at,
+                finished_at,
+                succeeded,
+                error,
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        background_job: EncodableBackgroundJob,
+        recent_runs: Vec<EncodableBackgroundJobRun>,
+    }
+    Ok(req.json(&R {
+        background_job: job.encodable(&conn, &running)?,
+        recent_runs: runs,
+    }))
+}
+
+/// Locks a job for modification, failing if it is currently running.
+fn lock_job(conn: &PgConnection, job_id: i64) -> AppResult<()> {
+    if lock_idle_job(conn, job_id)?.is_some() {
+        return Ok(());
+    }
+
+    let job_exists =
+        diesel::select(exists(background_jobs::table.find(job_id))).get_result(conn)?;
+    if job_exists {
+        Err(bad_request("the job is currently running"))
+    } else {
+        Err(not_found())
+    }
+}
+
+/// Handles the `PUT /admin/background_jobs/:job_id/retry` route.
+///
+/// Resets the retry count of the job, so it is run again as soon as a worker
+/// is available instead of waiting for its backoff to expire.
+pub fn retry(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let job_id = job_id(req)?;
+    let conn = req.db_conn()?;
+    conn.transaction(|| {
+        lock_job(&conn, job_id)?;
+        diesel::update(background_jobs::table.find(job_id))
+            .set((
+                background_jobs::retries.eq(0),
+                background_jobs::last_retry.eq(NaiveDate::from_ymd(1970, 1, 1).and_hms(0, 0, 0)),
+            ))
+            .execute(&*conn)?;
+        ok_true()
+    })
+}
+
+/// Handles the `DELETE /admin/background_jobs/:job_id` route.
+///
+/// Removes the job from its queue. Jobs that are currently running cannot
+/// be cancelled.
+pub fn cancel(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let job_id = job_id(req)?;
+    let conn = req.db_conn()?;
+    conn.transaction(|| {
+        lock_job(&conn, job_id)?;
+        diesel::delete(background_jobs::table.find(job_id)).execute(&*conn)?;
+        ok_true()
+    })
+}
+
+#[derive(Serialize, QueryableByName)]
+struct JobTypeLatency {
+    #[sql_type = "Text"]
+    job_type: String,
+    #[sql_type = "BigInt"]
+    runs: i64,
+    #[sql_type = "BigInt"]
+    failures: i64,
+    /// Seconds between enqueueing the job and a worker picking it up
+    #[sql_type = "Nullable<Double>"]
+    wait_p50: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    wait_p95: Option<f64>,
+    /// Seconds a worker spent running the job
+    #[sql_type = "Nullable<Double>"]
+    duration_p50: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    duration_p95: Option<f64>,
+}
+
+/// Handles the `GET /admin/background_jobs/stats` route.
+///
+/// Reports the latency of every job type over the last `hours` (24 by
+/// default). Runs of failed attempts are included in the durations.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let hours = match req.query().get("hours") {
+        Some(hours) => hours
+            .parse::<i64>()
+            .ok()
+            .filter(|hours| (1..=MAX_STATS_HOURS).contains(hours))
+            .ok_or_else(|| bad_request(&format_args!("invalid number of hours: {}", hours)))?,
+        None => DEFAULT_STATS_HOURS,
+    };
+
+    let now = Utc::now().naive_utc();
+    let conn = req.db_read_only()?;
+    let job_types: Vec<JobTypeLatency> = diesel::sql_query(
+        "SELECT job_type, COUNT(*) AS runs, \
+         COUNT(*) FILTER (WHERE NOT succeeded) AS failures, \
+         percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM started_at - enqueued_at)) AS wait_p50, \
+         percentile_cont(0.95) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM started_at - enqueued_at)) AS wait_p95, \
+         percentile_cont(0.5) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM finished_at - started_at)) AS duration_p50, \
+         percentile_cont(0.95) WITHIN GROUP (ORDER BY EXTRACT(EPOCH FROM finished_at - started_at)) AS duration_p95 \
+         FROM background_job_runs WHERE finished_at > $1 \
+         GROUP BY job_type ORDER BY job_type",
+    )
+    .bind::<Timestamp, _>(now - Duration::hours(hours))
+    .load(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        hours: i64,
+        job_types: Vec<JobTypeLatency>,
+    }
+    Ok(req.json(&R { hours, job_types }))
+}
@@ -0,0 +1,140 @@
+//! Endpoints for yanking everything published by a compromised account
+
+use chrono::{NaiveDateTime, Utc};
+
+use super::authenticate_admin;
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::models::{BulkYank, NewBulkYank, User};
+use crate::schema::{api_tokens, bulk_yanks};
+use crate::util::rfc3339;
+use crate::views::EncodableBulkYank;
+
+#[derive(Deserialize)]
+struct BulkYankRequest {
+    user_id: Option<i32>,
+    api_token_id: Option<i32>,
+    #[serde(with = "rfc3339")]
+    since: NaiveDateTime,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    until: Option<NaiveDateTime>,
+    reason: String,
+}
+
+/// Handles the `GET /admin/bulk_yanks` route.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let query = bulk_yanks::table
+        .order(bulk_yanks::id.desc())
+        .paginate(req)?;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<BulkYank> = query.load(&*conn)?;
+    let total = data.total();
+    let bulk_yanks = data.into_iter().map(EncodableBulkYank::from).collect();
+
+    #[derive(Serialize)]
+    struct R {
+        bulk_yanks: Vec<EncodableBulkYank>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        bulk_yanks,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /admin/bulk_yanks/:bulk_yank_id` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+    let bulk_yank_id = req.params()["bulk_yank_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid bulk_yank_id"))?;
+
+    let conn = req.db_read_only()?;
+    let bulk_yank = BulkYank::find(&conn, bulk_yank_id)?;
+
+    respond(req, bulk_yank)
+}
+
+/// Handles the `PUT /admin/bulk_yanks` route.
+///
+/// Yanks every version published by a user, or by a single API token if
+/// `api_token_id` is given, between `since` and `until` (defaulting to now).
+/// The request is recorded before any version is touched, and the yanking
+/// itself happens in a background job. The `completed_at` field of the
+/// returned record is set once the job has finished.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: BulkYankRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if request.reason.trim().is_empty() {
+        return Err(bad_request("a reason for the bulk yank must be provided"));
+    }
+
+    let until = request.until.unwrap_or_else(|| Utc::now().naive_utc());
+    if request.since >= until {
+        return Err(bad_request("`since` must be before `until`"));
+    }
+
+    let conn = req.db_conn()?;
+    let user_id = match (request.user_id, request.api_token_id) {
+        (_, Some(api_token_id)) => {
+            let token_user_id: i32 = api_tokens::table
+                .find(api_token_id)
+                .select(api_tokens::user_id)
+                .first(&*conn)?;
+            if request
+                .user_id
+                .map_or(false, |user_id| user_id != token_user_id)
+            {
+                return Err(bad_request("the API token does not belong to the user"));
+            }
+            token_user_id
+        }
+        (Some(user_id), None) => User::find(&conn, user_id)?.id,
+        (None, None) => return Err(bad_request("either user_id or api_token_id is required")),
+    };
+
+    let bulk_yank = conn.transaction(|| -> AppResult<_> {
+        let bulk_yank = NewBulkYank {
+            admin_id: admin.user_id(),
+            user_id,
+            api_token_id: request.api_token_id,
+            since: request.since,
+            until,
+            reason: &request.reason,
+        }
+        .create(&conn)?;
+
+        Job::BulkYank {
+            bulk_yank_id: bulk_yank.id,
+        }
+        .enqueue(&conn)?;
+
+        Ok(bulk_yank)
+    })?;
+
+    respond(req, bulk_yank)
+}
+
+fn respond(req: &dyn RequestExt, bulk_yank: BulkYank) -> EndpointResult {
+    #[derive(Serialize)]
+    struct R {
+        bulk_yank: EncodableBulkYank,
+    }
+    Ok(req.json(&R {
+        bulk_yank: bulk_yank.into(),
+    }))
+}
@@ -0,0 +1,21 @@
+//! The effective configuration of the server, for checking a deployment
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+
+/// Handles the `GET /admin/config` route.
+///
+/// Returns the settings the server was started with, after the config file
+/// and the environment variables were combined. Secrets, like the session
+/// key, credentials and database URLs, are left out.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let config = req.app().config.public_settings();
+
+    #[derive(Serialize)]
+    struct R {
+        config: serde_json::Value,
+    }
+    Ok(req.json(&R { config }))
+}
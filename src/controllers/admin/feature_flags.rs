@@ -0,0 +1,106 @@
+//! Endpoints for managing the flags of features that are rolled out
+//! gradually, see the `feature_flags` module
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::feature_flags::{FeatureFlag, NewFeatureFlag};
+use crate::util::errors::not_found;
+use crate::views::EncodableFeatureFlag;
+
+#[derive(Deserialize)]
+struct FeatureFlagRequest {
+    description: Option<String>,
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    percentage: i32,
+    #[serde(default)]
+    user_ids: Vec<i32>,
+}
+
+/// Handles the `GET /admin/feature_flags` route.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let feature_flags = FeatureFlag::all(&conn)?
+        .into_iter()
+        .map(EncodableFeatureFlag::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        feature_flags: Vec<EncodableFeatureFlag>,
+    }
+    Ok(req.json(&R { feature_flags }))
+}
+
+/// Handles the `PUT /admin/feature_flags/:name` route.
+///
+/// Creates the flag, or replaces all of its settings. The flag is enabled for
+/// everyone if `enabled` is set, and otherwise for the users in `user_ids`
+/// and for `percentage` percent of the other users.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let name = req.params()["name"].clone();
+    if !FeatureFlag::valid_name(&name) {
+        return Err(bad_request(&format_args!(
+            "the name must consist of at most {} lowercase letters, digits and underscores",
+            FeatureFlag::MAX_NAME_LENGTH
+        )));
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: FeatureFlagRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if !(0..=100).contains(&request.percentage) {
+        return Err(bad_request("the percentage must be between 0 and 100"));
+    }
+    let description = request
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+
+    let mut user_ids = request.user_ids;
+    user_ids.sort_unstable();
+    user_ids.dedup();
+
+    let conn = req.db_conn()?;
+    let flag = NewFeatureFlag {
+        name: &name,
+        description,
+        enabled: request.enabled,
+        percentage: request.percentage,
+        user_ids: &user_ids,
+        updated_by: admin.user_id(),
+    }
+    .save(&conn)?;
+    req.app().feature_flags.invalidate();
+
+    #[derive(Serialize)]
+    struct R {
+        feature_flag: EncodableFeatureFlag,
+    }
+    Ok(req.json(&R {
+        feature_flag: flag.into(),
+    }))
+}
+
+/// Handles the `DELETE /admin/feature_flags/:name` route.
+///
+/// The feature is disabled for everyone afterwards.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let name = req.params()["name"].clone();
+    let conn = req.db_conn()?;
+    if !FeatureFlag::delete(&conn, &name)? {
+        return Err(not_found());
+    }
+    req.app().feature_flags.invalidate();
+
+    ok_true()
+}
@@ -0,0 +1,114 @@
+//! Endpoints for scheduling the crates that are featured on the front page,
+//! which are listed by `GET /featured_crates` and the summary
+//!
+//! The summary is computed every minute by the `RefreshSummary` job, so it
+//! picks up changes to the schedule with a short delay.
+
+use chrono::{Duration, NaiveDate};
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, FeaturedCrate, NewFeaturedCrate};
+use crate::util::errors::not_found;
+use crate::views::EncodableFeaturedCrate;
+
+#[derive(Deserialize)]
+struct FeaturedCrateRequest {
+    #[serde(rename = "crate")]
+    crate_name: String,
+    starts_on: NaiveDate,
+    ends_on: Option<NaiveDate>,
+    description: Option<String>,
+}
+
+/// Handles the `GET /admin/featured_crates` route.
+///
+/// Lists past, current and upcoming featured crates, the latest first.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let featured_crates = FeaturedCrate::all(&conn)?
+        .into_iter()
+        .map(EncodableFeaturedCrate::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        featured_crates: Vec<EncodableFeaturedCrate>,
+    }
+    Ok(req.json(&R { featured_crates }))
+}
+
+/// Handles the `PUT /admin/featured_crates` route.
+///
+/// Features the crate from `starts_on` until `ends_on`, which defaults to a
+/// week later. A crate can't be featured twice on the same day.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: FeaturedCrateRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let ends_on = request
+        .ends_on
+        .unwrap_or_else(|| request.starts_on + Duration::days(FeaturedCrate::DEFAULT_DAYS - 1));
+    if ends_on < request.starts_on {
+        return Err(bad_request("`ends_on` must not be before `starts_on`"));
+    }
+    let description = request
+        .description
+        .as_deref()
+        .map(str::trim)
+        .filter(|description| !description.is_empty());
+    if let Some(description) = description {
+        if description.chars().count() > FeaturedCrate::MAX_DESCRIPTION_LENGTH {
+            return Err(bad_request(&format_args!(
+                "the description must be at most {} characters long",
+                FeaturedCrate::MAX_DESCRIPTION_LENGTH
+            )));
+        }
+    }
+
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(&request.crate_name).first(&*conn)?;
+    let new_featured = NewFeaturedCrate {
+        crate_id: krate.id,
+        starts_on: request.starts_on,
+        ends_on,
+        description,
+        admin_id: admin.user_id(),
+    };
+    if new_featured.overlaps(&conn)? {
+        return Err(bad_request(&format_args!(
+            "`{}` is already featured during this time",
+            krate.name
+        )));
+    }
+    let featured = new_featured.create(&conn)?;
+    req.app().response_cache.invalidate_crate(&krate.name);
+
+    #[derive(Serialize)]
+    struct R {
+        featured_crate: EncodableFeaturedCrate,
+    }
+    Ok(req.json(&R {
+        featured_crate: (featured, krate.name).into(),
+    }))
+}
+
+/// Handles the `DELETE /admin/featured_crates/:featured_crate_id` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let id = req.params()["featured_crate_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid featured_crate_id"))?;
+    let conn = req.db_conn()?;
+    let crate_name = FeaturedCrate::delete(&conn, id)?.ok_or_else(not_found)?;
+    req.app().response_cache.invalidate_crate(&crate_name);
+
+    ok_true()
+}
@@ -0,0 +1,46 @@
+//! Endpoints for reviewing the bursts of login requests detected by the
+//! login rate limiter
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::models::LoginAnomaly;
+use crate::schema::login_anomalies;
+use crate::views::EncodableLoginAnomaly;
+
+/// Handles the `GET /admin/login_anomalies` route.
+///
+/// Lists the anomalies newest first, optionally only the ones of the address
+/// passed as `?ip=`.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let mut query = login_anomalies::table
+        .order(login_anomalies::detected_at.desc())
+        .into_boxed();
+    if let Some(ip) = req.query().get("ip") {
+        query = query.filter(login_anomalies::ip.eq(ip.clone()));
+    }
+    let query = query.paginate(req)?;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<LoginAnomaly> = query.load(&*conn)?;
+    let total = data.total();
+    let login_anomalies = data.into_iter().map(EncodableLoginAnomaly::from).collect();
+
+    #[derive(Serialize)]
+    struct R {
+        login_anomalies: Vec<EncodableLoginAnomaly>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        login_anomalies,
+        meta: Meta { total },
+    }))
+}
@@ -0,0 +1,55 @@
+//! Endpoints for turning the soft maintenance mode on and off without a
+//! redeploy, see the `maintenance` module
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::maintenance::Maintenance;
+use crate::util::errors::not_found;
+
+/// Handles the `PUT /admin/maintenance_mode` route.
+///
+/// Rejects API requests that change data until the maintenance mode is
+/// turned off again. The optional `message` is shown by `GET /site_metadata`,
+/// and replaces the previous one if the maintenance mode is already on.
+pub fn start(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct MaintenanceRequest {
+        message: Option<String>,
+    }
+
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: MaintenanceRequest = if body.is_empty() {
+        MaintenanceRequest { message: None }
+    } else {
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?
+    };
+    let message = request
+        .message
+        .as_deref()
+        .map(str::trim)
+        .filter(|message| !message.is_empty());
+
+    let conn = req.db_conn()?;
+    Maintenance::start(&conn, admin.user_id(), message)?;
+    req.app().maintenance_mode.invalidate();
+
+    ok_true()
+}
+
+/// Handles the `DELETE /admin/maintenance_mode` route.
+///
+/// Other server processes pick the change up within a few seconds.
+pub fn end(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    if !Maintenance::end(&conn)? {
+        return Err(not_found());
+    }
+    req.app().maintenance_mode.invalidate();
+
+    ok_true()
+}
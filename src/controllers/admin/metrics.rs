@@ -0,0 +1,171 @@
+//! Operational statistics for the admin dashboard
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::count_star;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamp};
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::models::QuarantineStatus;
+use crate::schema::{
+    bulk_yanks, login_anomalies, publish_rate_limit_rejections, users, version_quarantines,
+};
+use crate::util::rfc3339;
+
+/// Background jobs that update the index. The age of the oldest of these
+/// jobs is reported as the index lag.
+const INDEX_JOB_TYPES: &[&str] = &["add_crate", "yank", "bulk_yank"];
+
+#[derive(Serialize, QueryableByName)]
+struct HourlyCount {
+    #[serde(with = "rfc3339")]
+    #[sql_type = "Timestamp"]
+    hour: NaiveDateTime,
+    #[sql_type = "BigInt"]
+    count: i64,
+}
+
+#[derive(Serialize, QueryableByName)]
+struct JobTypeStats {
+    #[sql_type = "Text"]
+    job_type: String,
+    #[sql_type = "BigInt"]
+    queued: i64,
+    #[sql_type = "BigInt"]
+    failing: i64,
+    #[serde(with = "rfc3339::option")]
+    #[sql_type = "Nullable<Timestamp>"]
+    oldest: Option<NaiveDateTime>,
+}
+
+#[derive(Serialize)]
+struct RecentCounts {
+    last_hour: i64,
+    last_day: i64,
+    last_week: i64,
+}
+
+/// Handles the `GET /admin/metrics` route.
+///
+/// All figures are computed from the database on every request, so this
+/// should only be polled at a modest interval.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let now = Utc::now().naive_utc();
+    let conn = req.db_read_only()?;
+
+    let publishes_per_hour: Vec<HourlyCount> = diesel::sql_query(
+        "SELECT date_trunc('hour', created_at) AS hour, COUNT(*) AS count \
+         FROM versions WHERE created_at > $1 GROUP BY 1 ORDER BY 1",
+    )
+    .bind::<Timestamp, _>(now - Duration::days(1))
+    .load(&*conn)?;
+
+    let jobs: Vec<JobTypeStats> = diesel::sql_query(
+        "SELECT job_type, COUNT(*) AS queued, \
+         COUNT(*) FILTER (WHERE retries > 0) AS failing, \
+         MIN(created_at) AS oldest \
+         FROM background_jobs GROUP BY job_type ORDER BY job_type",
+    )
+    .load(&*conn)?;
+
+    let rejections = recent_counts(now, |since| {
+        publish_rate_limit_rejections::table
+            .filter(publish_rate_limit_rejections::time.gt(since))
+            .select(count_star())
+            .get_result(&*conn)
+    })?;
+
+    let anomalies = recent_counts(now, |since| {
+        login_anomalies::table
+            .filter(login_anomalies::detected_at.gt(since))
+            .select(count_star())
+            .get_result(&*conn)
+    })?;
+
+    let new_accounts = recent_counts(now, |since| {
+        users::table
+            .filter(users::created_at.gt(since))
+            .select(count_star())
+            .get_result(&*conn)
+    })?;
+
+    let pending_quarantines = version_quarantines::table
+        .filter(version_quarantines::status.eq(QuarantineStatus::Pending))
+        .select(count_star())
+        .get_result(&*conn)?;
+    let running_bulk_yanks = bulk_yanks::table
+        .filter(bulk_yanks::completed_at.is_null())
+        .select(count_star())
+        .get_result(&*conn)?;
+
+    let index_jobs = jobs
+        .iter()
+        .filter(|stats| INDEX_JOB_TYPES.contains(&&*stats.job_type));
+    let oldest_index_job = index_jobs.clone().filter_map(|stats| stats.oldest).min();
+    let index = Index {
+        pending_jobs: index_jobs.map(|stats| stats.queued).sum(),
+        lag_seconds: oldest_index_job.map_or(0, |oldest| (now - oldest).num_seconds().max(0)),
+    };
+
+    let background_jobs = BackgroundJobs {
+        queued: jobs.iter().map(|stats| stats.queued).sum(),
+        failing: jobs.iter().map(|stats| stats.failing).sum(),
+        by_type: jobs,
+    };
+
+    #[derive(Serialize)]
+    struct BackgroundJobs {
+        queued: i64,
+        failing: i64,
+        by_type: Vec<JobTypeStats>,
+    }
+    #[derive(Serialize)]
+    struct Moderation {
+        pending_quarantines: i64,
+        running_bulk_yanks: i64,
+    }
+    #[derive(Serialize)]
+    struct Index {
+        pending_jobs: i64,
+        lag_seconds: i64,
+    }
+    #[derive(Serialize)]
+    struct R {
+        #[serde(with = "rfc3339")]
+        generated_at: NaiveDateTime,
+        publishes_per_hour: Vec<HourlyCount>,
+        background_jobs: BackgroundJobs,
+        publish_rate_limit_rejections: RecentCounts,
+        login_anomalies: RecentCounts,
+        new_accounts: RecentCounts,
+        moderation: Moderation,
+        index: Index,
+    }
+
+    Ok(req.json(&R {
+        generated_at: now,
+        publishes_per_hour,
+        background_jobs,
+        publish_rate_limit_rejections: rejections,
+        login_anomalies: anomalies,
+        new_accounts,
+        moderation: Moderation {
+            pending_quarantines,
+            running_bulk_yanks,
+        },
+        index,
+    }))
+}
+
+fn recent_counts<F>(now: NaiveDateTime, count_since: F) -> QueryResult<RecentCounts>
+where
+    F: Fn(NaiveDateTime) -> QueryResult<i64>,
+{
+    Ok(RecentCounts {
+        last_hour: count_since(now - Duration::hours(1))?,
+        last_day: count_since(now - Duration::days(1))?,
+        last_week: count_since(now - Duration::weeks(1))?,
+    })
+}
@@ -0,0 +1,141 @@
+//! Endpoints for reviewing versions quarantined by the upload scanners
+
+use super::authenticate_admin;
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::models::{
+    insert_version_owner_action, CrateDependencies, CrateDependents, QuarantineStatus, Version,
+    VersionAction, VersionQuarantine,
+};
+use crate::schema::{crates, version_quarantines, versions};
+use crate::views::EncodableVersionQuarantine;
+
+/// Handles the `GET /admin/quarantine` route.
+///
+/// Lists pending quarantine entries by default. A different set can be
+/// selected with the `status` query parameter.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let query = req.query();
+    let status = query.get("status").map(|s| &s[..]).unwrap_or("pending");
+    let status = QuarantineStatus::from_name(status)
+        .ok_or_else(|| bad_request(&format_args!("invalid quarantine status: {}", status)))?;
+
+    let query = version_quarantines::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(version_quarantines::status.eq(status))
+        .select((
+            version_quarantines::all_columns,
+            crates::name,
+            versions::num,
+        ))
+        .order(version_quarantines::created_at.asc())
+        .paginate(req)?;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<(VersionQuarantine, String, String)> = query.load(&*conn)?;
+    let total = data.total();
+    let quarantines = data
+        .into_iter()
+        .map(|(quarantine, crate_name, num)| {
+            EncodableVersionQuarantine::from(quarantine, crate_name, num)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        quarantines: Vec<EncodableVersionQuarantine>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        quarantines,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `PUT /admin/quarantine/:version_id/release` route.
+///
+/// Marks the findings as false positives and makes the version downloadable.
+/// Versions that were held out of the index are added to it.
+pub fn release(req: &mut dyn RequestExt) -> EndpointResult {
+    review(req, QuarantineStatus::Released)
+}
+
+/// Handles the `PUT /admin/quarantine/:version_id/reject` route.
+///
+/// Confirms the findings and yanks the version. Versions that were held out
+/// of the index are never added to it.
+pub fn reject(req: &mut dyn RequestExt) -> EndpointResult {
+    review(req, QuarantineStatus::Rejected)
+}
+
+fn review(req: &mut dyn RequestExt, status: QuarantineStatus) -> EndpointResult {
+    let authenticated_user = authenticate_admin(req)?;
+    let version_id = req.params()["version_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid version_id"))?;
+
+    let conn = req.db_conn()?;
+    let (response, crate_name) = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        let mut quarantine =
+            VersionQuarantine::review(&conn, version_id, authenticated_user.user_id(), status)?;
+
+        let (version, crate_name): (Version, String) = versions::table
+            .find(version_id)
+            .inner_join(crates::table)
+            .select((versions::all_columns, crates::name))
+            .first(&*conn)?;
+        let num = version.num.to_string();
+
+        if status == QuarantineStatus::Released {
+            quarantine = quarantine.add_held_version_to_index(&conn)?;
+        } else if status == QuarantineStatus::Rejected {
+            insert_version_owner_action(
+                &conn,
+                version.id,
+                authenticated_user.user_id(),
+                authenticated_user.api_token_id(),
+                VersionAction::Yank,
+            )?;
+            if quarantine.is_held() {
+                // The index has no entry to update
+                quarantine = quarantine.forget_held_index_entry(&conn)?;
+                let dependencies = CrateDependencies::load(&conn, version.crate_id)?;
+                diesel::update(&version)
+                    .set(versions::yanked.eq(true))
+                    .execute(&*conn)?;
+                CrateDependents::update_dependencies_of(&conn, &dependencies)?;
+            } else {
+                Job::Yank {
+                    krate: crate_name.clone(),
+                    version,
+                    yanked: true,
+                }
+                .enqueue(&conn)?;
+            }
+        }
+
+        #[derive(Serialize)]
+        struct R {
+            quarantine: EncodableVersionQuarantine,
+        }
+        let response = req.json(&R {
+            quarantine: EncodableVersionQuarantine::from(quarantine, crate_name.clone(), num),
+        });
+        Ok((response, crate_name))
+    })?;
+
+    // Downloads check the quarantine again once the review is committed, and
+    // the crate responses may show the version as yanked now
+    req.app().version_lookups.invalidate_quarantine(version_id);
+    req.app().response_cache.invalidate_crate(&crate_name);
+    Ok(response)
+}
@@ -0,0 +1,97 @@
+//! Endpoints for changing the quotas of a single account, see the `quotas`
+//! module
+
+use super::{authenticate_admin, find_user};
+use crate::controllers::frontend_prelude::*;
+use crate::models::QuotaOverride;
+use crate::quotas::Usage;
+use crate::views::{EncodableQuotaOverride, EncodableQuotas};
+
+#[derive(Deserialize)]
+struct OverrideRequest {
+    storage_bytes: Option<i64>,
+    versions_per_day: Option<i64>,
+    crates: Option<i64>,
+}
+
+/// Handles the `GET /admin/users/:user_id/quotas` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let user = find_user(req, &conn)?;
+    let quota_override = QuotaOverride::find(&conn, user.id)?;
+    respond(req, &conn, user.id, quota_override)
+}
+
+/// Handles the `PUT /admin/users/:user_id/quotas` route.
+///
+/// Creates or replaces the override of the user. Quotas that are left out
+/// are the configured ones.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: OverrideRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    let quotas = [
+        request.storage_bytes,
+        request.versions_per_day,
+        request.crates,
+    ];
+    if quotas.iter().flatten().any(|quota| *quota < 0) {
+        return Err(bad_request("quotas must not be negative"));
+    }
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let quota_override = QuotaOverride::set(
+        &conn,
+        user.id,
+        admin.user_id(),
+        request.storage_bytes,
+        request.versions_per_day,
+        request.crates,
+    )?;
+
+    respond(req, &conn, user.id, Some(quota_override))
+}
+
+/// Handles the `DELETE /admin/users/:user_id/quotas` route.
+///
+/// Removes the override, so that the configured quotas apply to the user
+/// again.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    QuotaOverride::delete(&conn, user.id)?;
+
+    respond(req, &conn, user.id, None)
+}
+
+fn respond(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    user_id: i32,
+    quota_override: Option<QuotaOverride>,
+) -> EndpointResult {
+    let configured = req.app().config.quotas;
+    let quotas = match &quota_override {
+        Some(quota_override) => configured.with_override(quota_override),
+        None => configured,
+    };
+    let usage = Usage::of(conn, user_id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        quotas: EncodableQuotas,
+        quota_override: Option<EncodableQuotaOverride>,
+    }
+    Ok(req.json(&R {
+        quotas: EncodableQuotas::new(quotas, usage),
+        quota_override: quota_override.map(Into::into),
+    }))
+}
@@ -0,0 +1,123 @@
+//! Endpoints for managing overrides of the publish rate limit
+
+use chrono::{NaiveDateTime, Utc};
+
+use super::{authenticate_admin, find_user};
+use crate::controllers::frontend_prelude::*;
+use crate::models::{PublishRateOverride, PublishRateOverrideAction, User};
+use crate::schema::{publish_rate_overrides, users};
+use crate::util::rfc3339;
+use crate::views::{EncodablePublishRateOverride, EncodablePublishRateOverrideAction};
+
+#[derive(Deserialize)]
+struct OverrideRequest {
+    burst: i32,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    expires_at: Option<NaiveDateTime>,
+}
+
+/// Handles the `GET /admin/publish_rate_overrides` route.
+///
+/// Only active overrides are listed, unless `include_expired=true` is passed.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let include_expired = req.query().get("include_expired").map(|s| &s[..]) == Some("true");
+    let now = Utc::now().naive_utc();
+
+    let conn = req.db_read_only()?;
+    let overrides: Vec<(PublishRateOverride, String)> = publish_rate_overrides::table
+        .inner_join(users::table)
+        .select((publish_rate_overrides::all_columns, users::gh_login))
+        .order(users::gh_login)
+        .load(&*conn)?;
+
+    let overrides = overrides
+        .into_iter()
+        .filter(|(rate_override, _)| include_expired || rate_override.is_active(now))
+        .map(|(rate_override, login)| EncodablePublishRateOverride::from(rate_override, login, now))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        publish_rate_overrides: Vec<EncodablePublishRateOverride>,
+    }
+    Ok(req.json(&R {
+        publish_rate_overrides: overrides,
+    }))
+}
+
+/// Handles the `PUT /admin/publish_rate_overrides/:user_id` route.
+///
+/// Creates or replaces the override for the user.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: OverrideRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if request.burst < 0 {
+        return Err(bad_request("burst must not be negative"));
+    }
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let rate_override = PublishRateOverride::set(
+        &conn,
+        user.id,
+        admin.user_id(),
+        request.burst,
+        request.expires_at,
+    )?;
+
+    respond(req, rate_override, user)
+}
+
+/// Handles the `DELETE /admin/publish_rate_overrides/:user_id` route.
+///
+/// Expires the override immediately. The row is kept so that the audit trail
+/// remains meaningful.
+pub fn expire(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let rate_override = PublishRateOverride::expire(&conn, user.id, admin.user_id())?;
+
+    respond(req, rate_override, user)
+}
+
+/// Handles the `GET /admin/publish_rate_overrides/:user_id/actions` route.
+pub fn actions(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let user = find_user(req, &conn)?;
+    let actions = PublishRateOverrideAction::for_user(&conn, user.id)?
+        .into_iter()
+        .map(EncodablePublishRateOverrideAction::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        actions: Vec<EncodablePublishRateOverrideAction>,
+    }
+    Ok(req.json(&R { actions }))
+}
+
+fn respond(req: &dyn RequestExt, rate_override: PublishRateOverride, user: User) -> EndpointResult {
+    let now = Utc::now().naive_utc();
+
+    #[derive(Serialize)]
+    struct R {
+        publish_rate_override: EncodablePublishRateOverride,
+    }
+    Ok(req.json(&R {
+        publish_rate_override: EncodablePublishRateOverride::from(
+            rate_override,
+            user.gh_login,
+            now,
+        ),
+    }))
+}
@@ -0,0 +1,154 @@
+//! Endpoints for rendering the READMEs of existing versions again
+
+use chrono::{NaiveDateTime, Utc};
+
+use super::authenticate_admin;
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::models::{Crate, NewReadmeRerender, ReadmeRerender};
+use crate::schema::readme_rerenders;
+use crate::util::rfc3339;
+use crate::views::EncodableReadmeRerender;
+
+const DEFAULT_BATCH_SIZE: i32 = 100;
+const MAX_BATCH_SIZE: i32 = 1000;
+
+#[derive(Deserialize)]
+struct ReadmeRerenderRequest {
+    #[serde(rename = "crate")]
+    crate_name: Option<String>,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    rendered_before: Option<NaiveDateTime>,
+    batch_size: Option<i32>,
+}
+
+fn find_rerender(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<ReadmeRerender> {
+    let id = req.params()["readme_rerender_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid readme_rerender_id"))?;
+    Ok(ReadmeRerender::find(conn, id)?)
+}
+
+/// Handles the `GET /admin/readme_rerenders` route.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let query = readme_rerenders::table
+        .order(readme_rerenders::id.desc())
+        .paginate(req)?;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<ReadmeRerender> = query.load(&*conn)?;
+    let total = data.total();
+    let readme_rerenders = data
+        .into_iter()
+        .map(EncodableReadmeRerender::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        readme_rerenders: Vec<EncodableReadmeRerender>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        readme_rerenders,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /admin/readme_rerenders/:readme_rerender_id` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let rerender = find_rerender(req, &conn)?;
+
+    respond(req, rerender)
+}
+
+/// Handles the `PUT /admin/readme_rerenders` route.
+///
+/// Renders the READMEs of all versions again, or only those of one crate if
+/// `crate` is given. Versions whose README was rendered after
+/// `rendered_before` (defaulting to now) are skipped, so a re-render can be
+/// repeated to retry the versions that failed.
+///
+/// The versions are rendered by a background job in batches of `batch_size`
+/// versions, whose progress is reported by this record and by the job.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: ReadmeRerenderRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let batch_size = request.batch_size.unwrap_or(DEFAULT_BATCH_SIZE);
+    if !(1..=MAX_BATCH_SIZE).contains(&batch_size) {
+        return Err(bad_request(&format_args!(
+            "the batch size must be between 1 and {}",
+            MAX_BATCH_SIZE
+        )));
+    }
+
+    let conn = req.db_conn()?;
+    let crate_name = match &request.crate_name {
+        Some(crate_name) => Some(Crate::by_name(crate_name).first::<Crate>(&*conn)?.name),
+        None => None,
+    };
+
+    let rerender = conn.transaction(|| -> AppResult<_> {
+        let rerender = NewReadmeRerender {
+            admin_id: admin.user_id(),
+            crate_name: crate_name.as_deref(),
+            rendered_before: request
+                .rendered_before
+                .unwrap_or_else(|| Utc::now().naive_utc()),
+            batch_size,
+        }
+        .create(&conn)?;
+
+        Job::RerenderReadmes {
+            readme_rerender_id: rerender.id,
+        }
+        .enqueue(&conn)?;
+
+        Ok(rerender)
+    })?;
+
+    respond(req, rerender)
+}
+
+/// Handles the `DELETE /admin/readme_rerenders/:readme_rerender_id` route.
+///
+/// The batch that is currently being rendered is finished, but no further
+/// batches are started.
+pub fn cancel(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let rerender = find_rerender(req, &conn)?;
+    if rerender.is_finished() {
+        return Err(bad_request("the re-render has already finished"));
+    }
+    let rerender = rerender.cancel(&conn)?;
+
+    respond(req, rerender)
+}
+
+fn respond(req: &dyn RequestExt, rerender: ReadmeRerender) -> EndpointResult {
+    #[derive(Serialize)]
+    struct R {
+        readme_rerender: EncodableReadmeRerender,
+    }
+    Ok(req.json(&R {
+        readme_rerender: rerender.into(),
+    }))
+}
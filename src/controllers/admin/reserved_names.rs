@@ -0,0 +1,74 @@
+//! Endpoints for managing the names that cannot be used for new crates
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, ReservedCrateName};
+use crate::util::errors::not_found;
+use crate::views::EncodableReservedCrateName;
+
+/// Handles the `GET /admin/reserved_crate_names` route.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_read_only()?;
+    let reserved_crate_names = ReservedCrateName::all(&conn)?
+        .into_iter()
+        .map(EncodableReservedCrateName::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        reserved_crate_names: Vec<EncodableReservedCrateName>,
+    }
+    Ok(req.json(&R {
+        reserved_crate_names,
+    }))
+}
+
+/// Handles the `PUT /admin/reserved_crate_names/:name` route.
+///
+/// The name may contain `*` wildcards. A name that is used by an existing
+/// crate cannot be reserved, while wildcards only affect crates created
+/// afterwards.
+pub fn reserve(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let name = req.params()["name"].to_string();
+    if !ReservedCrateName::valid(&name) {
+        return Err(bad_request(&format_args!(
+            "`{}` is not a valid crate name or pattern",
+            name
+        )));
+    }
+
+    let conn = req.db_conn()?;
+    let in_use: bool =
+        diesel::select(diesel::dsl::exists(Crate::by_name(&name))).get_result(&*conn)?;
+    if in_use {
+        return Err(bad_request(&format_args!(
+            "a crate named `{}` already exists",
+            name
+        )));
+    }
+    let created = ReservedCrateName::reserve(&conn, &name)?;
+
+    #[derive(Serialize)]
+    struct R {
+        ok: bool,
+        created: bool,
+    }
+    Ok(req.json(&R { ok: true, created }))
+}
+
+/// Handles the `DELETE /admin/reserved_crate_names/:name` route.
+pub fn release(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let name = req.params()["name"].to_string();
+    let conn = req.db_conn()?;
+    if !ReservedCrateName::release(&conn, &name)? {
+        return Err(not_found());
+    }
+
+    ok_true()
+}
@@ -0,0 +1,54 @@
+//! Endpoints for inspecting the recurring background jobs
+
+use chrono::Utc;
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::schedule::{ScheduledJobState, SCHEDULED_JOBS};
+use crate::views::EncodableScheduledJob;
+
+/// The number of upcoming runs listed for each job
+const UPCOMING_RUNS: usize = 5;
+
+/// Handles the `GET /admin/scheduled_jobs` route.
+///
+/// Jobs that were added since the background worker last checked for due
+/// jobs are listed with the runs they will have once it does. A `next_run_at`
+/// in the past means that the job is due and will be enqueued shortly. Jobs
+/// that can't run with the current configuration aren't listed.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let now = Utc::now().naive_utc();
+    let conn = req.db_read_only()?;
+    let states = ScheduledJobState::all(&conn)?;
+
+    let scheduled_jobs = SCHEDULED_JOBS
+        .iter()
+        .filter(|scheduled| scheduled.is_enabled())
+        .map(|scheduled| {
+            let upcoming_runs = scheduled.parsed_schedule().upcoming(now, UPCOMING_RUNS);
+            let state = states
+                .iter()
+                .find(|state| state.name == scheduled.name)
+                .filter(|state| state.schedule == scheduled.schedule);
+
+            EncodableScheduledJob {
+                name: scheduled.name.to_string(),
+                schedule: scheduled.schedule.to_string(),
+                last_run_at: state.and_then(|state| state.last_run_at),
+                next_run_at: state
+                    .map(|state| state.next_run_at)
+                    .or_else(|| upcoming_runs.first().copied())
+                    .unwrap_or(now),
+                upcoming_runs,
+            }
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        scheduled_jobs: Vec<EncodableScheduledJob>,
+    }
+    Ok(req.json(&R { scheduled_jobs }))
+}
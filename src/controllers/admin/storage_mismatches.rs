@@ -0,0 +1,89 @@
+//! Endpoints for reviewing the crate files found to be missing or damaged by
+//! the `VerifyStorage` background job
+
+use super::authenticate_admin;
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::helpers::Paginate;
+use crate::models::StorageMismatch;
+use crate::schema::{crates, storage_mismatches, versions};
+use crate::views::EncodableStorageMismatch;
+
+/// Handles the `GET /admin/storage_mismatches` route.
+///
+/// Lists unresolved mismatches by default, or all of them with `?all=true`.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let all = req.query().get("all").map(|s| &s[..]) == Some("true");
+
+    let mut query = storage_mismatches::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .select((storage_mismatches::all_columns, crates::name, versions::num))
+        .order(storage_mismatches::detected_at.desc())
+        .into_boxed();
+    if !all {
+        query = query.filter(storage_mismatches::resolved_at.is_null());
+    }
+    let query = query.paginate(req)?;
+
+    let conn = req.db_read_only()?;
+    let data: Paginated<(StorageMismatch, String, String)> = query.load(&*conn)?;
+    let total = data.total();
+    let storage_mismatches = data
+        .into_iter()
+        .map(|(mismatch, crate_name, num)| {
+            EncodableStorageMismatch::from(mismatch, crate_name, num)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        storage_mismatches: Vec<EncodableStorageMismatch>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+
+    Ok(req.json(&R {
+        storage_mismatches,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `PUT /admin/storage_mismatches/:storage_mismatch_id/resolve` route.
+///
+/// Marks a mismatch as resolved once the crate file has been repaired.
+/// Mismatches are also resolved automatically when the file passes a later
+/// verification.
+pub fn resolve(req: &mut dyn RequestExt) -> EndpointResult {
+    let admin = authenticate_admin(req)?;
+    let id = req.params()["storage_mismatch_id"]
+        .parse::<i32>()
+        .chain_error(|| bad_request("invalid storage_mismatch_id"))?;
+
+    let conn = req.db_conn()?;
+    let mismatch = StorageMismatch::find(&conn, id)?;
+    if mismatch.is_resolved() {
+        return Err(bad_request(
+            "the storage mismatch has already been resolved",
+        ));
+    }
+    let mismatch = mismatch.resolve(&conn, admin.user_id())?;
+
+    let (crate_name, num) = versions::table
+        .find(mismatch.version_id)
+        .inner_join(crates::table)
+        .select((crates::name, versions::num))
+        .first(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        storage_mismatch: EncodableStorageMismatch,
+    }
+    Ok(req.json(&R {
+        storage_mismatch: EncodableStorageMismatch::from(mismatch, crate_name, num),
+    }))
+}
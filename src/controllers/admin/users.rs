@@ -0,0 +1,189 @@
+//! Endpoints for managing user accounts
+
+use chrono::NaiveDateTime;
+
+use super::{authenticate_admin, find_user};
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::user::data_export;
+use crate::email;
+use crate::first_publish_hold;
+use crate::models::{
+    DataExport, EmailEvent, NewNotification, NotificationKind, User, VerifiedPublisher,
+};
+use crate::util::rfc3339;
+use crate::views::EncodableAccountLock;
+
+#[derive(Deserialize)]
+struct LockRequest {
+    reason: String,
+    #[serde(default, deserialize_with = "rfc3339::option::deserialize_strict")]
+    until: Option<NaiveDateTime>,
+}
+
+/// Handles the `PUT /admin/users/:user_id/lock` route.
+///
+/// Locks the account with the given reason. Without an `until` timestamp the
+/// lock never expires, which effectively bans the user. The user is notified
+/// on the website, and by email if they have a verified email address.
+pub fn lock(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let lock_request: LockRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if lock_request.reason.trim().is_empty() {
+        return Err(bad_request("a reason for the lock must be provided"));
+    }
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let user = user.lock(&conn, &lock_request.reason, lock_request.until)?;
+
+    NewNotification {
+        user_id: user.id,
+        kind: NotificationKind::Moderation,
+        title: &format!("Your account was locked: {}", lock_request.reason),
+        crate_name: None,
+    }
+    .create(&conn)?;
+
+    if let Some(email) = user.notification_email(&conn, EmailEvent::Moderation)? {
+        email::send_account_locked_email(
+            &conn,
+            &email,
+            &user.gh_login,
+            &lock_request.reason,
+            lock_request.until,
+        );
+    }
+
+    respond(req, user)
+}
+
+/// Handles the `DELETE /admin/users/:user_id/lock` route.
+pub fn unlock(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let user = user.unlock(&conn)?;
+
+    NewNotification {
+        user_id: user.id,
+        kind: NotificationKind::Moderation,
+        title: "Your account was unlocked",
+        crate_name: None,
+    }
+    .create(&conn)?;
+
+    respond(req, user)
+}
+
+/// Handles the `DELETE /admin/users/:user_id` route.
+///
+/// Deletes the personal data of the user, see `User::anonymize`. Unlike the
+/// self-service deletion this is not blocked by crates that are only owned
+/// by the user. Those crates are left without owners and are returned as
+/// `orphaned_crates`.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let orphaned_crates = conn.transaction(|| -> AppResult<_> {
+        let orphaned_crates = user.sole_owned_crates(&conn)?;
+        user.anonymize(&conn)?;
+        Ok(orphaned_crates)
+    })?;
+
+    #[derive(Serialize)]
+    struct R {
+        orphaned_crates: Vec<String>,
+    }
+    Ok(req.json(&R { orphaned_crates }))
+}
+
+/// Handles the `PUT /admin/users/:user_id/verify_publisher` route.
+///
+/// Exempts the user from holding the versions published by new accounts, and
+/// releases their held versions. See the `first_publish_hold` module for more
+/// documentation.
+pub fn verify_publisher(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let (verified, released_versions) = conn.transaction(|| -> AppResult<_> {
+        let verified = VerifiedPublisher::verify(&conn, user.id, authenticated_user.user_id())?;
+        let released = first_publish_hold::release_held_versions_of(
+            &conn,
+            user.id,
+            authenticated_user.user_id(),
+        )?;
+        Ok((verified, released))
+    })?;
+
+    let version_lookups = &req.app().version_lookups;
+    for &version_id in &released_versions {
+        version_lookups.invalidate_quarantine(version_id);
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        user_id: i32,
+        #[serde(with = "rfc3339")]
+        verified_at: NaiveDateTime,
+        released_versions: usize,
+    }
+    Ok(req.json(&R {
+        user_id: verified.user_id,
+        verified_at: verified.verified_at,
+        released_versions: released_versions.len(),
+    }))
+}
+
+/// Handles the `GET /admin/users/:user_id/data_export` route.
+pub fn show_data_export(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let export = DataExport::latest_for_user(&conn, user.id)?;
+
+    data_export::respond(req, export, &download_path(user.id))
+}
+
+/// Handles the `PUT /admin/users/:user_id/data_export` route.
+pub fn request_data_export(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+    let export = data_export::request_export(&conn, user.id)?;
+
+    data_export::respond(req, Some(export), &download_path(user.id))
+}
+
+/// Handles the `GET /admin/users/:user_id/data_export/download` route.
+pub fn download_data_export(req: &mut dyn RequestExt) -> EndpointResult {
+    authenticate_admin(req)?;
+
+    let _permit = req.db_budget(EndpointFamily::Dumps)?;
+    let conn = req.db_conn()?;
+    let user = find_user(req, &conn)?;
+
+    data_export::download_archive(&conn, user.id)
+}
+
+fn download_path(user_id: i32) -> String {
+    format!("/api/v1/admin/users/{}/data_export/download", user_id)
+}
+
+fn respond(req: &dyn RequestExt, user: User) -> EndpointResult {
+    #[derive(Serialize)]
+    struct R {
+        user: EncodableAccountLock,
+    }
+    Ok(req.json(&R { user: user.into() }))
+}
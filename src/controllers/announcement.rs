@@ -0,0 +1,23 @@
+//! Endpoint for the registry-wide notices of the crates.io team, which are
+//! managed through `/admin/announcements`
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::Announcement;
+use crate::views::EncodableAnnouncement;
+
+/// Handles the `GET /announcements` route.
+///
+/// Lists the announcements that are shown right now, the most severe first.
+pub fn current(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let announcements = Announcement::current(&conn, chrono::Utc::now().naive_utc())?
+        .into_iter()
+        .map(EncodableAnnouncement::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        announcements: Vec<EncodableAnnouncement>,
+    }
+    Ok(req.json(&R { announcements }))
+}
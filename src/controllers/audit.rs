@@ -0,0 +1,226 @@
+//! Auditing a whole dependency tree in one request
+//!
+//! CI jobs can POST their `Cargo.lock` and get the advisories affecting the
+//! locked versions and the versions that were yanked, without cloning the
+//! advisory database. The report covers the advisories of this registry,
+//! which includes the imported RustSec advisories.
+
+use std::collections::{BTreeSet, HashMap};
+use std::io::Read;
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::Advisory;
+use crate::schema::{crates, versions};
+use crate::views::EncodableAdvisory;
+
+/// The largest request body that is accepted
+const MAX_BODY_SIZE: u64 = 4 * 1024 * 1024;
+
+/// How many packages can be audited at once
+const MAX_PACKAGES: usize = 10_000;
+
+/// The `source` of the packages of this registry in a `Cargo.lock`, with the
+/// git and the sparse protocol
+const REGISTRY_SOURCES: &[&str] = &[
+    "registry+https://github.com/rust-lang/crates.io-index",
+    "sparse+https://index.crates.io/",
+];
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageList {
+    packages: Vec<Package>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+struct Package {
+    #[serde(rename = "crate", alias = "name")]
+    krate: String,
+    version: String,
+}
+
+/// Reads the packages to audit from a `Cargo.lock`, or from a JSON object
+/// like `{"packages": [{"name": "serde", "version": "1.0.0"}]}`. Packages of
+/// a lockfile that come from git, a path or another registry are skipped.
+fn parse_packages(body: &str) -> AppResult<BTreeSet<Package>> {
+    if body.trim_start().starts_with('{') {
+        let list: PackageList =
+            serde_json::from_str(body).map_err(|_| bad_request("invalid json request"))?;
+        return Ok(list.packages.into_iter().collect());
+    }
+
+    let lockfile: Lockfile = toml::from_str(body)
+        .map_err(|e| bad_request(&format_args!("invalid Cargo.lock: {}", e)))?;
+    Ok(lockfile
+        .package
+        .into_iter()
+        .filter(|package| {
+            package
+                .source
+                .as_deref()
+                .map_or(false, |source| REGISTRY_SOURCES.contains(&source))
+        })
+        .map(|package| Package {
+            krate: package.name,
+            version: package.version,
+        })
+        .collect())
+}
+
+#[derive(Serialize)]
+struct Finding {
+    #[serde(flatten)]
+    package: Package,
+    advisory: EncodableAdvisory,
+}
+
+/// Handles the `POST /audit` route.
+///
+/// Returns the advisories affecting the given packages, split into
+/// `vulnerabilities` and `warnings` for informational advisories like
+/// `unmaintained`, the packages whose version was `yanked`, and the packages
+/// that aren't published on this registry as `unknown`.
+pub fn audit(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let mut body = String::new();
+    req.body()
+        .take(MAX_BODY_SIZE + 1)
+        .read_to_string(&mut body)?;
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return Err(bad_request(&format_args!(
+            "the request body is larger than the limit of {} bytes",
+            MAX_BODY_SIZE
+        )));
+    }
+    let packages = parse_packages(&body)?;
+    if packages.len() > MAX_PACKAGES {
+        return Err(bad_request(&format_args!(
+            "at most {} packages can be audited at once",
+            MAX_PACKAGES
+        )));
+    }
+
+    let conn = req.db_read_only()?;
+    let names: Vec<&str> = packages.iter().map(|p| p.krate.as_str()).collect();
+    let nums: Vec<&str> = packages.iter().map(|p| p.version.as_str()).collect();
+    let versions: HashMap<Package, (i32, bool)> = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq(any(&names[..])))
+        .filter(versions::num.eq(any(&nums[..])))
+        .select((crates::name, versions::num, versions::id, versions::yanked))
+        .load::<(String, String, i32, bool)>(&*conn)?
+        .into_iter()
+        .map(|(krate, version, id, yanked)| (Package { krate, version }, (id, yanked)))
+        .collect();
+
+    let version_ids: Vec<i32> = versions.values().map(|(id, _)| *id).collect();
+    let mut advisories = Advisory::for_versions(&conn, &version_ids)?;
+    let advisory_ids: Vec<String> = advisories
+        .values()
+        .flatten()
+        .map(|advisory| advisory.id.clone())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect();
+    let affected_versions = Advisory::affected_versions_of(&conn, &advisory_ids)?;
+
+    let mut vulnerabilities = Vec::new();
+    let mut warnings = Vec::new();
+    let mut yanked = Vec::new();
+    let mut unknown = Vec::new();
+    for package in packages {
+        let (id, is_yanked) = match versions.get(&package) {
+            Some(&version) => version,
+            None => {
+                unknown.push(package);
+                continue;
+            }
+        };
+        for advisory in advisories.remove(&id).unwrap_or_default() {
+            let findings = if advisory.informational.is_some() {
+                &mut warnings
+            } else {
+                &mut vulnerabilities
+            };
+            let versions = affected_versions
+                .get(&advisory.id)
+                .cloned()
+                .unwrap_or_default();
+            findings.push(Finding {
+                package: package.clone(),
+                advisory: EncodableAdvisory::from(advisory, versions),
+            });
+        }
+        if is_yanked {
+            yanked.push(package);
+        }
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        vulnerabilities: Vec<Finding>,
+        warnings: Vec<Finding>,
+        yanked: Vec<Package>,
+        unknown: Vec<Package>,
+    }
+    Ok(req.json(&R {
+        vulnerabilities,
+        warnings,
+        yanked,
+        unknown,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_registry_packages_of_a_lockfile_are_audited() {
+        let lockfile = r#"
+            version = 3
+
+            [[package]]
+            name = "app"
+            version = "0.1.0"
+            dependencies = ["serde"]
+
+            [[package]]
+            name = "serde"
+            version = "1.0.125"
+            source = "registry+https://github.com/rust-lang/crates.io-index"
+            checksum = "558dc50e1a5a5fa7112ca2ce4effcb321b0300c0d4ccf0776a9f60cd89031171"
+
+            [[package]]
+            name = "forked"
+            version = "0.2.0"
+            source = "git+https://github.com/example/forked#1234abcd"
+        "#;
+        let packages = parse_packages(lockfile).unwrap();
+        let expected = Package {
+            krate: "serde".into(),
+            version: "1.0.125".into(),
+        };
+        assert_eq!(packages.into_iter().collect::<Vec<_>>(), vec![expected]);
+    }
+
+    #[test]
+    fn packages_can_be_listed_as_json() {
+        let body = r#"{"packages": [{"name": "serde", "version": "1.0.0"}, {"crate": "rand", "version": "0.8.3"}]}"#;
+        assert_eq!(parse_packages(body).unwrap().len(), 2);
+        assert!(parse_packages("[[package]]\nname = 1").is_err());
+    }
+}
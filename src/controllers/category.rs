@@ -1,9 +1,9 @@
 use super::helpers::pagination::*;
 use super::prelude::*;
 
-use crate::models::Category;
+use crate::models::{Category, CategoryStats};
 use crate::schema::categories;
-use crate::views::{EncodableCategory, EncodableCategoryWithSubcategories};
+use crate::views::{EncodableCategory, EncodableCategoryWithSubcategories, EncodableMonthlyStats};
 
 /// Handles the `GET /categories` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
@@ -99,3 +99,23 @@ pub fn slugs(req: &mut dyn RequestExt) -> EndpointResult {
         category_slugs: slugs,
     }))
 }
+
+/// Handles the `GET /categories/:category_id/stats` route.
+///
+/// Lists the crate counts and downloads of the category in every month,
+/// oldest first. The current month is recounted daily.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    let slug = &req.params()["category_id"];
+    let conn = req.db_read_only()?;
+    let cat: Category = Category::by_slug(slug).first(&*conn)?;
+    let stats = CategoryStats::for_category(&conn, cat.id)?
+        .into_iter()
+        .map(EncodableMonthlyStats::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        stats: Vec<EncodableMonthlyStats>,
+    }
+    Ok(req.json(&R { stats }))
+}
@@ -0,0 +1,292 @@
+//! Endpoints for the public lists of crates curated by users
+//!
+//! Every list has a stable URL by its id, and shows the current metadata of
+//! its crates next to the notes of the curator.
+
+use std::collections::HashMap;
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::{Paginate, Paginated};
+use crate::models::{Crate, CrateList, NewCrateList, User};
+use crate::schema::{crate_lists, users};
+use crate::util::errors::forbidden;
+use crate::views::{EncodableCrateList, EncodableCrateListItem};
+
+/// The recent downloads of the crates of a list, which popular lists are
+/// ranked by
+const POPULARITY: &str = "(SELECT COALESCE(SUM(recent_crate_downloads.downloads), 0)::bigint \
+    FROM crate_list_entries \
+    INNER JOIN recent_crate_downloads \
+        ON recent_crate_downloads.crate_id = crate_list_entries.crate_id \
+    WHERE crate_list_entries.crate_list_id = crate_lists.id)";
+
+#[derive(Deserialize)]
+struct ListRequest {
+    crate_list: ListFields,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListFields {
+    name: String,
+    description: Option<String>,
+}
+
+impl ListFields {
+    fn parse(req: &mut dyn RequestExt) -> AppResult<Self> {
+        let mut body = String::new();
+        req.body().read_to_string(&mut body)?;
+        let request: ListRequest =
+            serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+        let mut fields = request.crate_list;
+
+        fields.name = fields.name.trim().to_string();
+        fields.description = fields
+            .description
+            .map(|description| description.trim().to_string())
+            .filter(|description| !description.is_empty());
+        if fields.name.is_empty() {
+            return Err(bad_request("the name of a list must not be empty"));
+        }
+        if fields.name.chars().count() > CrateList::MAX_NAME_LENGTH {
+            return Err(bad_request(&format_args!(
+                "the name of a list must be at most {} characters long",
+                CrateList::MAX_NAME_LENGTH
+            )));
+        }
+        let description_length = fields.description.as_ref().map_or(0, |d| d.chars().count());
+        if description_length > CrateList::MAX_DESCRIPTION_LENGTH {
+            return Err(bad_request(&format_args!(
+                "the description of a list must be at most {} characters long",
+                CrateList::MAX_DESCRIPTION_LENGTH
+            )));
+        }
+        Ok(fields)
+    }
+}
+
+/// Handles the `GET /crate_lists` route.
+///
+/// Lists the lists whose crates have the most recent downloads first, or the
+/// most recently updated lists first with `sort=recent`. With `user_id` only
+/// the lists of that user are included.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::{any, sql};
+    use diesel::sql_types::BigInt;
+
+    let params = req.query();
+    let user_id = match params.get("user_id") {
+        Some(id) => Some(
+            id.parse::<i32>()
+                .map_err(|_| bad_request("invalid user_id"))?,
+        ),
+        None => None,
+    };
+
+    let mut query = crate_lists::table.into_boxed();
+    if let Some(user_id) = user_id {
+        query = query.filter(crate_lists::user_id.eq(user_id));
+    }
+    query = match params.get("sort").map(|s| &**s) {
+        Some("recent") => query.order((crate_lists::updated_at.desc(), crate_lists::id.desc())),
+        Some("popular") | None => {
+            query.order((sql::<BigInt>(POPULARITY).desc(), crate_lists::id.desc()))
+        }
+        Some(_) => return Err(bad_request("`sort` must be `popular` or `recent`")),
+    };
+
+    let query = query.paginate(req)?;
+    let conn = req.db_read_only()?;
+    let data: Paginated<CrateList> = query.load(&*conn)?;
+    let total = data.total();
+    let lists: Vec<CrateList> = data.into_iter().collect();
+
+    let list_ids: Vec<i32> = lists.iter().map(|list| list.id).collect();
+    let owner_ids: Vec<i32> = lists.iter().map(|list| list.user_id).collect();
+    let owners: HashMap<i32, User> = users::table
+        .filter(users::id.eq(any(&owner_ids[..])))
+        .load::<User>(&*conn)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+    let counts = CrateList::entry_counts(&conn, &list_ids)?;
+
+    let crate_lists = lists
+        .into_iter()
+        .filter_map(|list| {
+            let owner = owners.get(&list.user_id).cloned()?;
+            let count = counts.get(&list.id).copied().unwrap_or(0);
+            Some(EncodableCrateList::from(list, owner, count))
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crate_lists: Vec<EncodableCrateList>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+    }
+    Ok(req.json(&R {
+        crate_lists,
+        meta: Meta { total },
+    }))
+}
+
+/// Handles the `GET /crate_lists/:list_id` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let list = find_list(req, &conn)?;
+    let owner = User::find(&conn, list.user_id)?;
+    let crates: Vec<EncodableCrateListItem> = list
+        .items(&conn)?
+        .into_iter()
+        .map(EncodableCrateListItem::from)
+        .collect();
+    let count = crates.len() as i64;
+
+    #[derive(Serialize)]
+    struct R {
+        crate_list: EncodableCrateList,
+        crates: Vec<EncodableCrateListItem>,
+    }
+    Ok(req.json(&R {
+        crate_list: EncodableCrateList::from(list, owner, count),
+        crates,
+    }))
+}
+
+/// Handles the `PUT /crate_lists` route.
+pub fn create(req: &mut dyn RequestExt) -> EndpointResult {
+    let fields = ListFields::parse(req)?;
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+
+    if CrateList::count_for_user(&conn, user.id)? >= CrateList::MAX_PER_USER {
+        return Err(bad_request(&format_args!(
+            "a user can have at most {} lists",
+            CrateList::MAX_PER_USER
+        )));
+    }
+    let list = NewCrateList {
+        user_id: user.id,
+        name: &fields.name,
+        description: fields.description.as_deref(),
+    }
+    .create(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        crate_list: EncodableCrateList,
+    }
+    Ok(req.json(&R {
+        crate_list: EncodableCrateList::from(list, user, 0),
+    }))
+}
+
+/// Handles the `PUT /crate_lists/:list_id` route.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let fields = ListFields::parse(req)?;
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+    let list = find_own_list(req, &conn, &user)?;
+
+    let list: CrateList = diesel::update(&list)
+        .set((
+            crate_lists::name.eq(&fields.name),
+            crate_lists::description.eq(fields.description.as_deref()),
+        ))
+        .get_result(&*conn)?;
+    let count = list.entry_count(&conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        crate_list: EncodableCrateList,
+    }
+    Ok(req.json(&R {
+        crate_list: EncodableCrateList::from(list, user, count),
+    }))
+}
+
+/// Handles the `DELETE /crate_lists/:list_id` route.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+    let list = find_own_list(req, &conn, &user)?;
+
+    diesel::delete(&list).execute(&*conn)?;
+    ok_true()
+}
+
+/// Handles the `PUT /crate_lists/:list_id/crates/:crate_id` route.
+///
+/// Adds the crate to the list with the `note` of the request, or replaces the
+/// note if the crate already is on the list.
+pub fn add_crate(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Default, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct EntryRequest {
+        note: Option<String>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: EntryRequest = if body.trim().is_empty() {
+        EntryRequest::default()
+    } else {
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?
+    };
+    let note = request
+        .note
+        .map(|note| note.trim().to_string())
+        .filter(|note| !note.is_empty());
+    if note.as_ref().map_or(0, |note| note.chars().count()) > CrateList::MAX_NOTE_LENGTH {
+        return Err(bad_request(&format_args!(
+            "a note must be at most {} characters long",
+            CrateList::MAX_NOTE_LENGTH
+        )));
+    }
+
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+    let list = find_own_list(req, &conn, &user)?;
+    let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+
+    if list.entry_count(&conn)? >= CrateList::MAX_ENTRIES {
+        return Err(bad_request(&format_args!(
+            "a list can have at most {} crates",
+            CrateList::MAX_ENTRIES
+        )));
+    }
+    list.add_crate(&conn, krate.id, note.as_deref())?;
+    ok_true()
+}
+
+/// Handles the `DELETE /crate_lists/:list_id/crates/:crate_id` route.
+pub fn remove_crate(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+    let list = find_own_list(req, &conn, &user)?;
+    let krate: Crate = Crate::by_name(&req.params()["crate_id"]).first(&*conn)?;
+
+    list.remove_crate(&conn, krate.id)?;
+    ok_true()
+}
+
+fn find_list(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<CrateList> {
+    let id = req.params()["list_id"]
+        .parse::<i32>()
+        .map_err(|_| bad_request("invalid list_id"))?;
+    Ok(CrateList::find(conn, id)?)
+}
+
+/// Finds the list of the request, which only its owner may change
+fn find_own_list(req: &dyn RequestExt, conn: &PgConnection, user: &User) -> AppResult<CrateList> {
+    let list = find_list(req, conn)?;
+    if list.user_id != user.id {
+        return Err(forbidden());
+    }
+    Ok(list)
+}
@@ -0,0 +1,225 @@
+//! Idempotency keys for publishing, yanking and changing owners
+//!
+//! These requests accept an `Idempotency-Key` header, so that clients like CI
+//! jobs can retry a request whose response they didn't get without applying
+//! it twice. The first request with a key is performed, and its response is
+//! stored in the `idempotency_keys` table. Retries with the same key by the
+//! same user get the stored response with an `Idempotent-Replayed: true`
+//! header instead of being performed again.
+//!
+//! A key belongs to a single request: reusing it for a different method,
+//! path or body is rejected with `422 Unprocessable Entity`, and retrying
+//! while the first request is still being performed with `409 Conflict`.
+//! Server errors and rate limits are not stored, so that the request can be
+//! retried with the same key. Keys expire after `RETENTION_HOURS`.
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use conduit::{Body, Response};
+use hex::ToHex;
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::sync::Arc;
+
+use crate::controllers::prelude::*;
+use crate::schema::{crates, idempotency_keys};
+use crate::util::errors::{
+    bad_request, cargo_err_with_code, custom, custom_with_code, internal, ErrorCode,
+};
+use crate::util::{LimitErrorReader, RequestProxy};
+
+/// How long the responses of requests with an idempotency key are replayed
+pub const RETENTION_HOURS: i64 = 24;
+
+/// How long a request may take before a retry with its key performs it
+/// again, for requests that never finished because the server went away
+const ABANDONED_AFTER_MINUTES: i64 = 10;
+
+const MAX_KEY_LENGTH: usize = 255;
+
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[primary_key(user_id, key)]
+#[table_name = "idempotency_keys"]
+#[changeset_options(treat_none_as_null = "true")]
+struct IdempotencyKey {
+    user_id: i32,
+    key: String,
+    fingerprint: String,
+    /// The stored response, which is `None` while the request is performed
+    response_status: Option<i32>,
+    response_body: Option<Vec<u8>>,
+    created_at: NaiveDateTime,
+}
+
+impl IdempotencyKey {
+    fn is_older_than(&self, age: Duration) -> bool {
+        self.created_at < Utc::now().naive_utc() - age
+    }
+}
+
+/// Performs the request, unless it has an `Idempotency-Key` that the user
+/// already sent, in which case the response of the first request is sent
+pub fn idempotent(
+    req: &mut dyn RequestExt,
+    perform: impl FnOnce(&mut dyn RequestExt) -> EndpointResult,
+) -> EndpointResult {
+    let key = match req.headers().get("idempotency-key") {
+        Some(key) => key
+            .to_str()
+            .ok()
+            .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LENGTH)
+            .ok_or_else(|| {
+                bad_request(&format_args!(
+                    "the `Idempotency-Key` header must be between 1 and {} \
+                     visible ASCII characters",
+                    MAX_KEY_LENGTH
+                ))
+            })?
+            .to_string(),
+        None => return perform(req),
+    };
+    let user_id = req.authenticate()?.user_id();
+
+    let max_body_size = max_body_size(req)?;
+    let mut body = Vec::new();
+    if let Err(e) = LimitErrorReader::new(req.body(), max_body_size).read_to_end(&mut body) {
+        if body.len() as u64 >= max_body_size {
+            return Err(cargo_err_with_code(
+                ErrorCode::UploadTooLarge,
+                &format_args!("max upload size is: {}", max_body_size),
+            ));
+        }
+        return Err(e.into());
+    }
+    let fingerprint = fingerprint(req, &body);
+
+    let app = Arc::clone(req.app());
+    let claim = IdempotencyKey {
+        user_id,
+        key,
+        fingerprint,
+        response_status: None,
+        response_body: None,
+        created_at: Utc::now().naive_utc(),
+    };
+    if let Some(response) = claim_or_replay(&*app.primary_database.get()?, &claim)? {
+        return Ok(response);
+    }
+
+    let result = perform(&mut RequestProxy::replace_body(req, body));
+
+    let response = match &result {
+        Ok(response) => stored_response(response),
+        Err(e) => e.response().as_ref().and_then(stored_response),
+    };
+    let conn = app.primary_database.get()?;
+    let target = idempotency_keys::table.find((claim.user_id, &claim.key));
+    let saved = match response {
+        Some((status, body)) => diesel::update(target)
+            .set((
+                idempotency_keys::response_status.eq(status),
+                idempotency_keys::response_body.eq(body),
+            ))
+            .execute(&*conn),
+        // Let retries perform the request again
+        None => diesel::delete(target).execute(&*conn),
+    };
+    if let Err(e) = saved {
+        error!(error = %e, "Failed to store the response of an idempotent request");
+    }
+
+    result
+}
+
+/// The largest body that is read into memory to fingerprint it, which is
+/// the largest body a publish can have: the metadata, which may be up to
+/// the maximum upload size, and the tarball, which may be bigger for crates
+/// with their own maximum, each following its length.
+fn max_body_size(req: &dyn RequestExt) -> AppResult<u64> {
+    use diesel::dsl::max;
+
+    let conn = req.db_read_only()?;
+    let max_crate_upload_size: Option<i32> = crates::table
+        .select(max(crates::max_upload_size))
+        .first(&*conn)?;
+    let max_upload_size = req.app().config.max_upload_size;
+    let max_tarball_size = max_crate_upload_size.map_or(0, |size| size as u64);
+    Ok(2 * 4 + max_upload_size + max_upload_size.max(max_tarball_size))
+}
+
+/// Claims the key for the request, or returns the stored response if the
+/// key was already used for it
+fn claim_or_replay(conn: &PgConnection, claim: &IdempotencyKey) -> AppResult<Option<AppResponse>> {
+    conn.transaction(|| {
+        let inserted = diesel::insert_into(idempotency_keys::table)
+            .values(claim)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        if inserted == 1 {
+            return Ok(None);
+        }
+
+        let target = idempotency_keys::table.find((claim.user_id, &claim.key));
+        let existing: IdempotencyKey = target.clone().for_update().first(conn)?;
+        let abandoned = existing.response_status.is_none()
+            && existing.is_older_than(Duration::minutes(ABANDONED_AFTER_MINUTES));
+        if abandoned || existing.is_older_than(Duration::hours(RETENTION_HOURS)) {
+            diesel::update(target).set(claim).execute(conn)?;
+            return Ok(None);
+        }
+
+        if existing.fingerprint != claim.fingerprint {
+            return Err(custom_with_code(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                ErrorCode::IdempotencyKeyReused,
+                "the `Idempotency-Key` was already used for a different request",
+            ));
+        }
+        match (existing.response_status, existing.response_body) {
+            (Some(status), Some(body)) => Ok(Some(replay(status, body)?)),
+            _ => Err(custom(
+                StatusCode::CONFLICT,
+                "a request with this `Idempotency-Key` is still being processed, \
+                 please try again later",
+            )),
+        }
+    })
+}
+
+/// Identifies the request that a key was first used for
+fn fingerprint(req: &dyn RequestExt, body: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(req.method().as_str().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(req.path().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(req.query_string().unwrap_or_default().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(body);
+    hasher.finalize().encode_hex()
+}
+
+/// The status and body that retries of the request get, which is `None` for
+/// responses that shouldn't keep the request from being retried
+fn stored_response(response: &AppResponse) -> Option<(i32, Vec<u8>)> {
+    let status = response.status();
+    if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+        return None;
+    }
+    let body = match response.body() {
+        Body::Owned(body) => body.clone(),
+        Body::Static(body) => body.to_vec(),
+        Body::File(_) => return None,
+    };
+    Some((i32::from(status.as_u16()), body))
+}
+
+fn replay(status: i32, body: Vec<u8>) -> AppResult<AppResponse> {
+    let status = StatusCode::from_u16(status as u16).map_err(|e| internal(&e))?;
+    Ok(Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+        .header(header::CONTENT_LENGTH, body.len())
+        .header("idempotent-replayed", "true")
+        .body(Body::from_vec(body))
+        .unwrap()) // Header values are well formed, so should not panic
+}
@@ -7,10 +7,15 @@ use diesel::query_builder::*;
 use diesel::query_dsl::LoadQuery;
 use diesel::sql_types::BigInt;
 use indexmap::IndexMap;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub(crate) enum Page {
     Numeric(u32),
+    /// An opaque cursor pointing right after the last item of the previous
+    /// page, see [`PaginationOptions::seek`]
+    Seek(String),
     Unspecified,
 }
 
@@ -19,7 +24,12 @@ impl Page {
         const MAX_PAGE_BEFORE_SUSPECTED_BOT: u32 = 10;
 
         let params = req.query();
-        if let Some(s) = params.get("page") {
+        if let Some(seek) = params.get("seek") {
+            if params.contains_key("page") {
+                return Err(bad_request("cannot use both `page` and `seek`"));
+            }
+            Ok(Page::Seek(seek.clone()))
+        } else if let Some(s) = params.get("page") {
             let numeric_page = s.parse().map_err(|e| bad_request(&e))?;
             if numeric_page < 1 {
                 return Err(bad_request(&format_args!(
@@ -39,14 +49,25 @@ impl Page {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub(crate) struct PaginationOptions {
     page: Page,
     pub(crate) per_page: u32,
 }
 
 impl PaginationOptions {
+    /// Options of an endpoint that only supports numeric pages
     pub(crate) fn new(req: &mut dyn RequestExt) -> AppResult<Self> {
+        let options = Self::with_seek(req)?;
+        if let Page::Seek(_) = options.page {
+            return Err(bad_request("this endpoint does not support `seek`"));
+        }
+        Ok(options)
+    }
+
+    /// Options of an endpoint that supports keyset pagination with the
+    /// `seek` parameter, in addition to numeric pages
+    pub(crate) fn with_seek(req: &mut dyn RequestExt) -> AppResult<Self> {
         const DEFAULT_PER_PAGE: u32 = 10;
         const MAX_PER_PAGE: u32 = 100;
 
@@ -76,6 +97,68 @@ impl PaginationOptions {
             None
         }
     }
+
+    /// The number of the requested page, counting from 1, for endpoints that
+    /// only support numeric pages. Requests without a page get the first one.
+    pub(crate) fn page_number(&self) -> u32 {
+        match self.page {
+            Page::Numeric(p) => p,
+            Page::Seek(_) | Page::Unspecified => 1,
+        }
+    }
+
+    /// Whether the request asked for a page by number, which keyset paginated
+    /// endpoints keep supporting with an `OFFSET`
+    pub(crate) fn is_numeric(&self) -> bool {
+        matches!(self.page, Page::Numeric(_))
+    }
+
+    /// Decodes the `seek` cursor into the sort key of the last item of the
+    /// previous page
+    pub(crate) fn seek<K: DeserializeOwned>(&self) -> AppResult<Option<K>> {
+        match &self.page {
+            Page::Seek(cursor) => decode_seek(cursor).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// The query parameters of the next page of a keyset paginated endpoint,
+    /// given the sort key of the last item of the current page. Numeric pages
+    /// keep using numbers.
+    pub(crate) fn next_seek_params<K: Serialize>(
+        &self,
+        items_on_page: usize,
+        last_key: Option<K>,
+    ) -> Option<IndexMap<String, String>> {
+        if items_on_page < self.per_page as usize {
+            return None;
+        }
+
+        let mut opts = IndexMap::new();
+        match self.page {
+            Page::Numeric(n) => {
+                opts.insert("page".into(), (n + 1).to_string());
+            }
+            Page::Seek(_) | Page::Unspecified => {
+                opts.insert("seek".into(), encode_seek(last_key?));
+            }
+        }
+        opts.insert("per_page".into(), self.per_page.to_string());
+        Some(opts)
+    }
+}
+
+/// Cursors are the URL safe base64 of the JSON encoded sort key, which
+/// clients should treat as opaque
+fn encode_seek<K: Serialize>(key: K) -> String {
+    let json = serde_json::to_vec(&key).expect("sort keys are always serializable");
+    base64::encode_config(json, base64::URL_SAFE_NO_PAD)
+}
+
+fn decode_seek<K: DeserializeOwned>(cursor: &str) -> AppResult<K> {
+    let invalid = || bad_request("invalid `seek` parameter");
+    let json = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD).map_err(|_| invalid())?;
+    serde_json::from_slice(&json).map_err(|_| invalid())
 }
 
 pub(crate) trait Paginate: Sized {
@@ -85,6 +168,15 @@ pub(crate) trait Paginate: Sized {
             options: PaginationOptions::new(req)?,
         })
     }
+
+    /// Like `paginate`, with options that were read from the request before,
+    /// e.g. because a database connection still borrows the request
+    fn paginate_with(self, options: PaginationOptions) -> PaginatedQuery<Self> {
+        PaginatedQuery {
+            query: self,
+            options,
+        }
+    }
 }
 
 impl<T> Paginate for T {}
@@ -111,19 +203,21 @@ impl<T> Paginated<T> {
         match self.options.page {
             Page::Numeric(n) => opts.insert("page".into(), (n + 1).to_string()),
             Page::Unspecified => opts.insert("page".into(), 2.to_string()),
+            // Rejected by `PaginationOptions::new`
+            Page::Seek(_) => return None,
         };
         Some(opts)
     }
 
     pub(crate) fn prev_page_params(&self) -> Option<IndexMap<String, String>> {
-        if let Page::Numeric(1) | Page::Unspecified = self.options.page {
+        if let Page::Numeric(1) | Page::Unspecified | Page::Seek(_) = self.options.page {
             return None;
         }
 
         let mut opts = IndexMap::new();
         match self.options.page {
             Page::Numeric(n) => opts.insert("page".into(), (n - 1).to_string()),
-            Page::Unspecified => unreachable!(),
+            Page::Unspecified | Page::Seek(_) => unreachable!(),
         };
         Some(opts)
     }
@@ -153,7 +247,7 @@ impl<T> PaginatedQuery<T> {
     where
         Self: LoadQuery<PgConnection, WithCount<U>>,
     {
-        let options = self.options;
+        let options = self.options.clone();
         let records_and_total = self.internal_load(conn)?;
         Ok(Paginated {
             records_and_total,
@@ -192,7 +286,7 @@ where
 
 #[cfg(test)]
 mod tests {
-    use super::{Page, PaginationOptions};
+    use super::{decode_seek, encode_seek, Page, PaginationOptions};
 
     use conduit::StatusCode;
     use conduit_test::MockRequest;
@@ -245,4 +339,35 @@ mod tests {
             .unwrap();
         assert_eq!(per_page_error.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[test]
+    fn page_and_seek_are_exclusive() {
+        let mut req = mock("page=2&seek=WzFd");
+        let error = Page::new(&mut req).unwrap_err().response().unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn seek_is_only_accepted_where_supported() {
+        let mut req = mock("seek=WzFd");
+        let error = PaginationOptions::new(&mut req)
+            .unwrap_err()
+            .response()
+            .unwrap();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+
+        let mut req = mock("seek=WzFd");
+        let options = assert_ok!(PaginationOptions::with_seek(&mut req));
+        assert_eq!(assert_ok!(options.seek::<Vec<i32>>()), Some(vec![1]));
+    }
+
+    #[test]
+    fn seek_cursors_round_trip() {
+        let cursor = encode_seek((10, "serde"));
+        let key: (i32, String) = assert_ok!(decode_seek(&cursor));
+        assert_eq!(key, (10, "serde".to_string()));
+
+        assert_err!(decode_seek::<(i32, String)>("not a cursor"));
+        assert_err!(decode_seek::<(i32, String)>(&encode_seek("serde")));
+    }
 }
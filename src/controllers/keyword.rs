@@ -1,15 +1,36 @@
 use super::prelude::*;
 
-use crate::controllers::helpers::{pagination::Paginated, Paginate};
-use crate::models::Keyword;
-use crate::views::EncodableKeyword;
+use crate::cache::{CacheKey, CachedJson};
+use crate::controllers::helpers::pagination::{Paginated, PaginationOptions};
+use crate::controllers::helpers::Paginate;
+use crate::models::{Keyword, KeywordStats};
+use crate::views::{EncodableKeyword, EncodableMonthlyStats};
 
 /// Handles the `GET /keywords` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
-    use crate::schema::keywords;
+    let options = PaginationOptions::new(req)?;
+    let sort = match req.query().get("sort").map(String::as_str) {
+        Some("crates") => "crates",
+        _ => "alpha",
+    };
+    let key = CacheKey::Keywords {
+        page: options.page_number(),
+        per_page: options.per_page,
+        sort: sort.into(),
+    };
+    let app = req.app().clone();
+    let json = app
+        .response_cache
+        .get_or_insert_with(key, || keywords_json(req, options, sort))?;
+    Ok(json.to_response())
+}
 
-    let query = req.query();
-    let sort = query.get("sort").map(|s| &s[..]).unwrap_or("alpha");
+fn keywords_json(
+    req: &dyn RequestExt,
+    options: PaginationOptions,
+    sort: &str,
+) -> AppResult<CachedJson> {
+    use crate::schema::keywords;
 
     let mut query = keywords::table.into_boxed();
 
@@ -19,7 +40,7 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
         query = query.order(keywords::keyword.asc());
     }
 
-    let query = query.paginate(req)?;
+    let query = query.paginate_with(options);
     let conn = req.db_read_only()?;
     let data: Paginated<Keyword> = query.load(&*conn)?;
     let total = data.total();
@@ -35,7 +56,7 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
         total: Option<i64>,
     }
 
-    Ok(req.json(&R {
+    Ok(CachedJson::new(&R {
         keywords: kws,
         meta: Meta { total: Some(total) },
     }))
@@ -44,13 +65,36 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
 /// Handles the `GET /keywords/:keyword_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let name = &req.params()["keyword_id"];
-    let conn = req.db_read_only()?;
+    let key = CacheKey::Keyword(name.clone());
+    let json = req.app().response_cache.get_or_insert_with(key, || {
+        let conn = req.db_read_only()?;
+        let kw = Keyword::find_by_keyword(&conn, name)?;
 
+        #[derive(Serialize)]
+        struct R {
+            keyword: EncodableKeyword,
+        }
+        Ok(CachedJson::new(&R { keyword: kw.into() }))
+    })?;
+    Ok(json.to_response())
+}
+
+/// Handles the `GET /keywords/:keyword_id/stats` route.
+///
+/// Lists the crate counts and downloads of the keyword in every month,
+/// oldest first. The current month is recounted daily.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = &req.params()["keyword_id"];
+    let conn = req.db_read_only()?;
     let kw = Keyword::find_by_keyword(&conn, name)?;
+    let stats = KeywordStats::for_keyword(&conn, kw.id)?
+        .into_iter()
+        .map(EncodableMonthlyStats::from)
+        .collect();
 
     #[derive(Serialize)]
     struct R {
-        keyword: EncodableKeyword,
+        stats: Vec<EncodableMonthlyStats>,
     }
-    Ok(req.json(&R { keyword: kw.into() }))
+    Ok(req.json(&R { stats }))
 }
@@ -1,6 +1,13 @@
+pub mod activity;
+pub mod advisories;
+pub mod dependency_graph;
 pub mod downloads;
 pub mod follow;
 pub mod metadata;
 pub mod owners;
+pub mod project_links;
 pub mod publish;
+pub mod repository_verification;
 pub mod search;
+pub mod storage;
+pub mod timeline;
@@ -0,0 +1,51 @@
+//! The activity feed of a crate, for the history tab of crate pages and for
+//! bots watching crates
+
+use chrono::NaiveDateTime;
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::PaginationOptions;
+use crate::models::{Crate, CrateActivity};
+use crate::views::EncodableCrateActivity;
+
+/// Handles the `GET /crates/:crate_id/activity` route.
+///
+/// Lists publishes, yanks, README renderings, owner changes and advisories
+/// of the crate newest first, see [`CrateActivity`]. Supports keyset
+/// pagination with the `seek` parameter, see the `next_page` of the response.
+pub fn activity(req: &mut dyn RequestExt) -> EndpointResult {
+    let options = PaginationOptions::with_seek(req)?;
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+
+    let events = CrateActivity::for_crate(
+        &conn,
+        &krate,
+        options.seek::<(NaiveDateTime, String)>()?,
+        i64::from(options.per_page),
+        i64::from(options.offset().unwrap_or(0)),
+    )?;
+    let next_page = options
+        .next_seek_params(
+            events.len(),
+            events
+                .last()
+                .map(|event| (event.occurred_at, event.id.clone())),
+        )
+        .map(|p| req.query_with_params(p));
+
+    #[derive(Serialize)]
+    struct R {
+        activity: Vec<EncodableCrateActivity>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        next_page: Option<String>,
+    }
+    Ok(req.json(&R {
+        activity: events.into_iter().map(Into::into).collect(),
+        meta: Meta { next_page },
+    }))
+}
@@ -0,0 +1,330 @@
+//! Endpoints for the security advisories of a crate
+//!
+//! Advisories are either imported from the RustSec advisory database, or
+//! published by the owners of the affected crate. Only the latter can be
+//! changed through these endpoints.
+
+use chrono::{DateTime, Datelike, Utc};
+use semver::VersionReq;
+
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Advisory, Crate, NewAdvisory, Rights, User};
+use crate::util::errors::not_found;
+use crate::views::{EncodableAdvisory, EncodableOsvAdvisory};
+
+/// Handles the `GET /crates/:crate_id/advisories` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    let advisories = Advisory::for_crate(&conn, &krate.name)?;
+    let mut affected_versions = Advisory::affected_versions(&conn, krate.id)?;
+
+    let advisories = advisories
+        .into_iter()
+        .map(|advisory| {
+            let versions = affected_versions.remove(&advisory.id).unwrap_or_default();
+            EncodableAdvisory::from(advisory, versions)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        advisories: Vec<EncodableAdvisory>,
+    }
+    Ok(req.json(&R { advisories }))
+}
+
+/// Handles the `GET /crates/:crate_id/advisories/osv` route.
+///
+/// Returns the advisories of the crate in the Open Source Vulnerability
+/// format, in the same shape as the responses of the OSV query API.
+pub fn osv(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    let advisories = Advisory::for_crate(&conn, &krate.name)?;
+    let mut affected_versions = Advisory::affected_versions(&conn, krate.id)?;
+
+    let vulns = advisories
+        .into_iter()
+        .map(|advisory| {
+            let versions = affected_versions.remove(&advisory.id).unwrap_or_default();
+            EncodableOsvAdvisory::from(advisory, versions)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        vulns: Vec<EncodableOsvAdvisory>,
+    }
+    Ok(req.json(&R { vulns }))
+}
+
+/// Handles the `GET /advisories/osv` route.
+///
+/// Returns all advisories in the Open Source Vulnerability format, for
+/// scanners that import the advisories of the registry. With the
+/// `modified_since` query parameter, an RFC 3339 timestamp, only the
+/// advisories changed after it are returned, including withdrawn ones, so
+/// that consumers can stay up to date with the `modified` of the last
+/// advisory they received.
+pub fn osv_feed(req: &mut dyn RequestExt) -> EndpointResult {
+    let since = match req.query().get("modified_since") {
+        Some(since) => Some(
+            DateTime::parse_from_rfc3339(since)
+                .map_err(|_| bad_request("`modified_since` must be an RFC 3339 timestamp"))?
+                .naive_utc(),
+        ),
+        None => None,
+    };
+
+    let conn = req.db_read_only()?;
+    let advisories = Advisory::modified_since(&conn, since)?;
+    let ids: Vec<String> = advisories
+        .iter()
+        .map(|advisory| advisory.id.clone())
+        .collect();
+    let mut affected_versions = Advisory::affected_versions_of(&conn, &ids)?;
+
+    let vulns = advisories
+        .into_iter()
+        .map(|advisory| {
+            let versions = affected_versions.remove(&advisory.id).unwrap_or_default();
+            EncodableOsvAdvisory::from(advisory, versions)
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        vulns: Vec<EncodableOsvAdvisory>,
+    }
+    let mut response = req.json(&R { vulns });
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("public, max-age=300"),
+    );
+    Ok(response)
+}
+
+/// Handles the `GET /advisories/osv/:advisory_id` route.
+///
+/// Returns a single advisory in the Open Source Vulnerability format, like
+/// the `vulns` endpoint of the OSV API.
+pub fn osv_show(req: &mut dyn RequestExt) -> EndpointResult {
+    let id = &req.params()["advisory_id"];
+    let conn = req.db_read_only()?;
+    let advisory = Advisory::find(&conn, id)?;
+    let versions = Advisory::affected_versions_of(&conn, &[advisory.id.clone()])?
+        .remove(&advisory.id)
+        .unwrap_or_default();
+    Ok(req.json(&EncodableOsvAdvisory::from(advisory, versions)))
+}
+
+#[derive(Deserialize)]
+struct AdvisoryRequest {
+    advisory: AdvisoryFields,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryFields {
+    title: String,
+    description: Option<String>,
+    url: Option<String>,
+    severity: String,
+    #[serde(default)]
+    affected: Vec<String>,
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+impl AdvisoryFields {
+    fn validate(&self) -> AppResult<()> {
+        if self.title.trim().is_empty() {
+            return Err(bad_request("the title of an advisory must not be empty"));
+        }
+        if !Advisory::SEVERITIES.contains(&self.severity.as_str()) {
+            return Err(bad_request(&format_args!(
+                "invalid severity `{}`, expected one of: {}",
+                self.severity,
+                Advisory::SEVERITIES.join(", ")
+            )));
+        }
+        if let Some(url) = &self.url {
+            let valid = url::Url::parse(url)
+                .map(|url| url.scheme() == "https" || url.scheme() == "http")
+                .unwrap_or(false);
+            if !valid {
+                return Err(bad_request(&format_args!("invalid url `{}`", url)));
+            }
+        }
+        let requirements = self
+            .affected
+            .iter()
+            .chain(&self.patched)
+            .chain(&self.unaffected);
+        for req in requirements {
+            if VersionReq::parse(req).is_err() {
+                return Err(bad_request(&format_args!(
+                    "invalid version requirement `{}`",
+                    req
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_to(self, advisory: &mut NewAdvisory) {
+        advisory.title = self.title.trim().to_string();
+        advisory.description = self.description.filter(|text| !text.trim().is_empty());
+        advisory.url = self.url;
+        advisory.severity = Some(self.severity);
+        advisory.affected = self.affected;
+        advisory.patched = self.patched;
+        advisory.unaffected = self.unaffected;
+    }
+}
+
+fn parse_request(req: &mut dyn RequestExt) -> AppResult<AdvisoryFields> {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: AdvisoryRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    request.advisory.validate()?;
+    Ok(request.advisory)
+}
+
+fn ensure_owner(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    krate: &Crate,
+    user: &User,
+) -> AppResult<()> {
+    if user.rights(req.app(), &krate.owners(conn)?)? < Rights::Publish {
+        return Err(bad_request(
+            "must already be an owner to publish or change advisories",
+        ));
+    }
+    Ok(())
+}
+
+/// Finds an advisory of the crate that was published by an owner
+fn find_owner_advisory(conn: &PgConnection, krate: &Crate, id: &str) -> AppResult<Advisory> {
+    let advisory = Advisory::find(conn, id).optional()?.ok_or_else(not_found)?;
+    let canonical = |name: &str| name.to_lowercase().replace('-', "_");
+    if canonical(&advisory.crate_name) != canonical(&krate.name) {
+        return Err(not_found());
+    }
+    if !advisory.is_owner_published() {
+        return Err(bad_request(
+            "advisories imported from the RustSec advisory database can't be changed",
+        ));
+    }
+    Ok(advisory)
+}
+
+fn advisory_response(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    krate: &Crate,
+    id: &str,
+) -> EndpointResult {
+    let advisory = Advisory::find(conn, id)?;
+    let versions = Advisory::affected_versions(conn, krate.id)?
+        .remove(id)
+        .unwrap_or_default();
+
+    #[derive(Serialize)]
+    struct R {
+        advisory: EncodableAdvisory,
+    }
+    Ok(req.json(&R {
+        advisory: EncodableAdvisory::from(advisory, versions),
+    }))
+}
+
+/// Handles the `PUT /crates/:crate_id/advisories` route.
+///
+/// Publishes an advisory for the crate. The owners of crates that depend on
+/// an affected version are notified by a background job.
+pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
+    let fields = parse_request(req)?;
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let id = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        let today = Utc::today().naive_utc();
+        let mut advisory = NewAdvisory {
+            id: NewAdvisory::next_owner_advisory_id(&conn, today.year())?,
+            crate_name: krate.name.clone(),
+            title: String::new(),
+            url: None,
+            date: today,
+            patched: vec![],
+            unaffected: vec![],
+            informational: None,
+            withdrawn: false,
+            description: None,
+            severity: None,
+            affected: vec![],
+            published_by: Some(user.id),
+        };
+        fields.apply_to(&mut advisory);
+        advisory.save(&conn)?;
+
+        Job::NotifyAdvisory {
+            advisory_id: advisory.id.clone(),
+        }
+        .enqueue(&conn)?;
+        Ok(advisory.id)
+    })?;
+
+    req.app().response_cache.invalidate_crate(&krate.name);
+    advisory_response(req, &conn, &krate, &id)
+}
+
+/// Handles the `PUT /crates/:crate_id/advisories/:advisory_id` route.
+///
+/// Amends an advisory published by an owner. Owners aren't notified again.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let fields = parse_request(req)?;
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let id = &req.params()["advisory_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let mut advisory: NewAdvisory = find_owner_advisory(&conn, &krate, id)?.into();
+    fields.apply_to(&mut advisory);
+    advisory.save(&conn)?;
+
+    req.app().response_cache.invalidate_crate(&krate.name);
+    advisory_response(req, &conn, &krate, id)
+}
+
+/// Handles the `DELETE /crates/:crate_id/advisories/:advisory_id` route.
+///
+/// Withdraws an advisory published by an owner, which is kept but no longer
+/// affects any version.
+pub fn withdraw(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let id = &req.params()["advisory_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let mut advisory: NewAdvisory = find_owner_advisory(&conn, &krate, id)?.into();
+    advisory.withdrawn = true;
+    advisory.save(&conn)?;
+
+    req.app().response_cache.invalidate_crate(&krate.name);
+    advisory_response(req, &conn, &krate, id)
+}
@@ -0,0 +1,161 @@
+//! Endpoint for the dependency graph of a crate
+//!
+//! The graph of the whole registry is exported once a day, see
+//! `docs/DEPENDENCY-GRAPH.md`. This endpoint resolves the subgraph of one
+//! version instead.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{resolve_dependencies, Crate, CrateVersions, DependencyKind, Version};
+use crate::schema::{crates, dependencies, versions};
+use crate::views::{EncodableGraphEdge, EncodableGraphNode};
+
+/// The depth of the graph unless requested otherwise
+const DEFAULT_DEPTH: u32 = 3;
+
+/// The highest depth that can be requested
+const MAX_DEPTH: u32 = 10;
+
+/// How many versions the graph has at most
+const MAX_NODES: usize = 500;
+
+/// Handles the `GET /crates/:crate_id/dependency_graph` route.
+///
+/// Returns the graph of the dependencies of the version given by the
+/// `version` query parameter, or of the highest version of the crate, down to
+/// the `depth` query parameter. Every dependency is resolved like for the
+/// license tree, to the highest version that matches the requirement and
+/// isn't yanked. Optional dependencies are included, dev dependencies are not
+/// since they aren't built by dependents.
+///
+/// Edges of dependencies that resolve to no version point to no node. Once
+/// the graph has `MAX_NODES` nodes, it is returned as `truncated`.
+pub fn dependency_graph(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let depth_limit = match req.query().get("depth") {
+        Some(depth) => depth
+            .parse::<u32>()
+            .ok()
+            .filter(|depth| (1..=MAX_DEPTH).contains(depth))
+            .ok_or_else(|| {
+                bad_request(&format_args!(
+                    "`depth` must be a number from 1 to {}",
+                    MAX_DEPTH
+                ))
+            })?,
+        None => DEFAULT_DEPTH,
+    };
+    let num = req.query().get("version").cloned();
+
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    let num = match num {
+        Some(num) => num,
+        None => {
+            let top_versions = krate.top_versions(&conn)?;
+            let highest = top_versions.highest_stable.or(top_versions.highest);
+            highest.map(|num| num.to_string()).unwrap_or_default()
+        }
+    };
+    let root: Version = krate
+        .all_versions()
+        .filter(versions::num.eq(&num))
+        .first(&*conn)?;
+
+    let mut depths: HashMap<i32, u32> = HashMap::new();
+    depths.insert(root.id, 0);
+    let mut edges = Vec::new();
+    let mut level = vec![root.id];
+    let mut depth = 0;
+    let mut truncated = false;
+    while !level.is_empty() && depth < depth_limit && !truncated {
+        depth += 1;
+        let deps: Vec<(i32, i32, String, DependencyKind, bool)> = dependencies::table
+            .filter(dependencies::version_id.eq(any(&level[..])))
+            .filter(dependencies::kind.ne(DependencyKind::Dev as i32))
+            .select((
+                dependencies::version_id,
+                dependencies::crate_id,
+                dependencies::req,
+                dependencies::kind,
+                dependencies::optional,
+            ))
+            .order(dependencies::id)
+            .load(&*conn)?;
+        let requirements: Vec<(i32, String)> = deps
+            .iter()
+            .map(|(_, crate_id, requirement, _, _)| (*crate_id, requirement.clone()))
+            .collect();
+        let resolved = resolve_dependencies(&conn, &requirements)?;
+
+        let mut next_level = Vec::new();
+        for ((from, crate_id, requirement, kind, optional), to) in deps.into_iter().zip(resolved) {
+            if let Some(to) = to {
+                if !depths.contains_key(&to) {
+                    if depths.len() == MAX_NODES {
+                        truncated = true;
+                        continue;
+                    }
+                    depths.insert(to, depth);
+                    next_level.push(to);
+                }
+            }
+            edges.push((from, crate_id, to, requirement, kind, optional));
+        }
+        level = next_level;
+    }
+
+    let version_ids: Vec<i32> = depths.keys().copied().collect();
+    let mut nodes: Vec<EncodableGraphNode> = versions::table
+        .inner_join(crates::table)
+        .filter(versions::id.eq(any(&version_ids[..])))
+        .select((versions::id, crates::name, versions::num))
+        .load::<(i32, String, String)>(&*conn)?
+        .into_iter()
+        .map(|(id, krate, num)| EncodableGraphNode {
+            id,
+            depth: depths[&id],
+            krate,
+            num,
+        })
+        .collect();
+    nodes.sort_by(|a, b| a.depth.cmp(&b.depth).then(a.krate.cmp(&b.krate)));
+
+    let crate_ids: Vec<i32> = edges.iter().map(|edge| edge.1).collect();
+    let crate_names: HashMap<i32, String> = crates::table
+        .filter(crates::id.eq(any(&crate_ids[..])))
+        .select((crates::id, crates::name))
+        .load::<(i32, String)>(&*conn)?
+        .into_iter()
+        .collect();
+    let node_ids: HashSet<i32> = nodes.iter().map(|node| node.id).collect();
+    let edges = edges
+        .into_iter()
+        .filter(|(from, ..)| node_ids.contains(from))
+        .map(
+            |(from, crate_id, to, requirement, kind, optional)| EncodableGraphEdge {
+                from,
+                to: to.filter(|to| node_ids.contains(to)),
+                krate: crate_names.get(&crate_id).cloned().unwrap_or_default(),
+                req: requirement,
+                kind,
+                optional,
+            },
+        )
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        nodes: Vec<EncodableGraphNode>,
+        edges: Vec<EncodableGraphEdge>,
+        truncated: bool,
+    }
+    Ok(req.json(&R {
+        nodes,
+        edges,
+        truncated,
+    }))
+}
@@ -4,53 +4,65 @@
 //! index or cached metadata which was extracted (client side) from the
 //! `Cargo.toml` file.
 
+use crate::cache::{CacheKey, CachedJson};
 use crate::controllers::frontend_prelude::*;
 use crate::controllers::helpers::pagination::PaginationOptions;
+use crate::controllers::version::encode_versions;
+use diesel::dsl::sql;
+use diesel::sql_types::BigInt;
 
 use crate::models::{
-    Category, Crate, CrateCategory, CrateKeyword, CrateVersions, Keyword, RecentCrateDownloads,
-    TopVersions, User, Version, VersionOwnerAction,
+    Category, Crate, CrateCategory, CrateDependents, CrateKeyword, CrateTrendingScore,
+    CrateVersions, FeaturedCrate, Keyword, MaterializedResponse, ProjectLink, RecentCrateDownloads,
+    RepositoryVerification, TopVersions, User, Version,
 };
 use crate::schema::*;
+use crate::util::errors::not_found;
+use crate::util::{json_file_response, SerializeIter};
 use crate::views::{
-    EncodableCategory, EncodableCrate, EncodableDependency, EncodableKeyword, EncodableVersion,
+    EncodableCategory, EncodableCrate, EncodableDependency, EncodableFeaturedCrate,
+    EncodableKeyword, EncodableTrendingScore, EncodableVersion,
 };
 
 use crate::models::krate::ALL_COLUMNS;
 
+/// How old the summary computed by the `RefreshSummary` job may be before it
+/// is computed on request instead, which only happens if the job isn't run
+const SUMMARY_MAX_AGE_MINUTES: i64 = 10;
+
 /// Handles the `GET /summary` route.
 pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
+    let json = req
+        .app()
+        .response_cache
+        .get_or_insert_with(CacheKey::Summary, || {
+            let conn = req.db_read_only()?;
+            let cutoff =
+                chrono::Utc::now().naive_utc() - chrono::Duration::minutes(SUMMARY_MAX_AGE_MINUTES);
+            match MaterializedResponse::find(&conn, MaterializedResponse::SUMMARY)? {
+                Some(summary) if summary.refreshed_at > cutoff => Ok(summary.body.into()),
+                _ => summary_json(&conn),
+            }
+        })?;
+
+    // The summary is refreshed every minute
+    let mut response = json.to_response();
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("public, max-age=60"),
+    );
+    Ok(response)
+}
+
+pub(crate) fn summary_json(conn: &PgConnection) -> AppResult<CachedJson> {
     use crate::schema::crates::dsl::*;
 
-    let conn = req.db_read_only()?;
-    let num_crates = crates.count().get_result(&*conn)?;
+    let num_crates = crates.count().get_result(conn)?;
     let num_downloads = metadata::table
         .select(metadata::total_downloads)
-        .get_result(&*conn)?;
-
-    let encode_crates = |data: Vec<(Crate, Option<i64>)>| -> AppResult<Vec<_>> {
-        let recent_downloads = data.iter().map(|&(_, s)| s).collect::<Vec<_>>();
+        .get_result(conn)?;
 
-        let krates = data.into_iter().map(|(c, _)| c).collect::<Vec<_>>();
-
-        let versions: Vec<Version> = krates.versions().load(&*conn)?;
-        versions
-            .grouped_by(&krates)
-            .into_iter()
-            .map(TopVersions::from_versions)
-            .zip(krates)
-            .zip(recent_downloads)
-            .map(|((top_versions, krate), recent_downloads)| {
-                Ok(EncodableCrate::from_minimal(
-                    krate,
-                    &top_versions,
-                    None,
-                    false,
-                    recent_downloads,
-                ))
-            })
-            .collect()
-    };
+    let encode_crates = |data| encode_summary_crates(conn, data);
 
     let selection = (ALL_COLUMNS, recent_crate_downloads::downloads.nullable());
 
@@ -59,37 +71,50 @@ pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
         .order(created_at.desc())
         .select(selection)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
     let just_updated = crates
         .left_join(recent_crate_downloads::table)
         .filter(updated_at.ne(created_at))
         .order(updated_at.desc())
         .select(selection)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
     let most_downloaded = crates
         .left_join(recent_crate_downloads::table)
         .then_order_by(downloads.desc())
         .select(selection)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
 
     let most_recently_downloaded = crates
         .inner_join(recent_crate_downloads::table)
         .then_order_by(recent_crate_downloads::downloads.desc())
         .select(selection)
         .limit(10)
-        .load(&*conn)?;
+        .load(conn)?;
+
+    let trending = crates
+        .inner_join(crate_trending_scores::table)
+        .left_join(recent_crate_downloads::table)
+        .order((crate_trending_scores::score.desc(), name.asc()))
+        .select(selection)
+        .limit(10)
+        .load(conn)?;
+
+    let featured = currently_featured(conn)?
+        .into_iter()
+        .map(|(_, krate, recent_downloads)| (krate, recent_downloads))
+        .collect();
 
     let popular_keywords = keywords::table
         .order(keywords::crates_cnt.desc())
         .limit(10)
-        .load(&*conn)?
+        .load(conn)?
         .into_iter()
         .map(Keyword::into)
         .collect();
 
-    let popular_categories = Category::toplevel(&conn, "crates", 10, 0)?
+    let popular_categories = Category::toplevel(conn, "crates", 10, 0)?
         .into_iter()
         .map(Category::into)
         .collect();
@@ -102,77 +127,248 @@ pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
         most_downloaded: Vec<EncodableCrate>,
         most_recently_downloaded: Vec<EncodableCrate>,
         just_updated: Vec<EncodableCrate>,
+        trending: Vec<EncodableCrate>,
+        featured: Vec<EncodableCrate>,
         popular_keywords: Vec<EncodableKeyword>,
         popular_categories: Vec<EncodableCategory>,
     }
-    Ok(req.json(&R {
+    Ok(CachedJson::new(&R {
         num_downloads,
         num_crates,
         new_crates: encode_crates(new_crates)?,
         most_downloaded: encode_crates(most_downloaded)?,
         most_recently_downloaded: encode_crates(most_recently_downloaded)?,
         just_updated: encode_crates(just_updated)?,
+        trending: encode_crates(trending)?,
+        featured: encode_crates(featured)?,
         popular_keywords,
         popular_categories,
     }))
 }
 
+/// Encodes the crates listed by the summary and trending endpoints, along
+/// with their recent downloads
+fn encode_summary_crates(
+    conn: &PgConnection,
+    data: Vec<(Crate, Option<i64>)>,
+) -> AppResult<Vec<EncodableCrate>> {
+    let recent_downloads = data.iter().map(|&(_, s)| s).collect::<Vec<_>>();
+
+    let krates = data.into_iter().map(|(c, _)| c).collect::<Vec<_>>();
+
+    let versions: Vec<Version> = krates.versions().load(conn)?;
+    versions
+        .grouped_by(&krates)
+        .into_iter()
+        .map(TopVersions::from_versions)
+        .zip(krates)
+        .zip(recent_downloads)
+        .map(|((top_versions, krate), recent_downloads)| {
+            Ok(EncodableCrate::from_minimal(
+                krate,
+                &top_versions,
+                None,
+                false,
+                recent_downloads,
+            ))
+        })
+        .collect()
+}
+
+/// How many crates the `trending` endpoint lists
+const TRENDING_CRATES: i64 = 20;
+
+/// Handles the `GET /trending` route.
+///
+/// Lists the crates with the highest trending scores, computed daily by the
+/// `ComputeTrendingScores` job, along with the scores.
+pub fn trending(req: &mut dyn RequestExt) -> EndpointResult {
+    let json = req
+        .app()
+        .response_cache
+        .get_or_insert_with(CacheKey::Trending, || {
+            let conn = req.db_read_only()?;
+            trending_json(&conn)
+        })?;
+
+    let mut response = json.to_response();
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("public, max-age=300"),
+    );
+    Ok(response)
+}
+
+fn trending_json(conn: &PgConnection) -> AppResult<CachedJson> {
+    let data: Vec<(CrateTrendingScore, Crate, Option<i64>)> = crates::table
+        .inner_join(crate_trending_scores::table)
+        .left_join(recent_crate_downloads::table)
+        .order((crate_trending_scores::score.desc(), crates::name.asc()))
+        .select((
+            crate_trending_scores::all_columns,
+            ALL_COLUMNS,
+            recent_crate_downloads::downloads.nullable(),
+        ))
+        .limit(TRENDING_CRATES)
+        .load(conn)?;
+
+    let mut scores = Vec::with_capacity(data.len());
+    let mut krates = Vec::with_capacity(data.len());
+    for (score, krate, recent_downloads) in data {
+        scores.push(EncodableTrendingScore {
+            krate: krate.name.clone(),
+            score: score.score,
+            recent_downloads: score.recent_downloads,
+            baseline_downloads: score.baseline_downloads,
+            new_dependents: score.new_dependents,
+            computed_at: score.computed_at,
+        });
+        krates.push((krate, recent_downloads));
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+        scores: Vec<EncodableTrendingScore>,
+    }
+    Ok(CachedJson::new(&R {
+        crates: encode_summary_crates(conn, krates)?,
+        scores,
+    }))
+}
+
+/// The crates that the crates.io team features today, in the order they were
+/// scheduled
+fn currently_featured(
+    conn: &PgConnection,
+) -> QueryResult<Vec<(FeaturedCrate, Crate, Option<i64>)>> {
+    let today = chrono::Utc::now().naive_utc().date();
+    crates::table
+        .inner_join(featured_crates::table)
+        .left_join(recent_crate_downloads::table)
+        .filter(featured_crates::starts_on.le(today))
+        .filter(featured_crates::ends_on.ge(today))
+        .order((featured_crates::starts_on, featured_crates::id))
+        .select((
+            featured_crates::all_columns,
+            ALL_COLUMNS,
+            recent_crate_downloads::downloads.nullable(),
+        ))
+        .load(conn)
+}
+
+/// Handles the `GET /featured_crates` route.
+///
+/// Lists the crates that are featured today, with the reason they are
+/// featured. The schedule is managed through `/admin/featured_crates`.
+pub fn featured(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let data = currently_featured(&conn)?;
+
+    let mut featured_crates = Vec::with_capacity(data.len());
+    let mut krates = Vec::with_capacity(data.len());
+    for (featured, krate, recent_downloads) in data {
+        featured_crates.push(EncodableFeaturedCrate::from((featured, krate.name.clone())));
+        krates.push((krate, recent_downloads));
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrate>,
+        featured_crates: Vec<EncodableFeaturedCrate>,
+    }
+    Ok(req.json(&R {
+        crates: encode_summary_crates(&conn, krates)?,
+        featured_crates,
+    }))
+}
+
 /// Handles the `GET /crates/:crate_id` route.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let name = &req.params()["crate_id"];
+    let key = CacheKey::Crate(name.clone());
+    if let Some(cached) = req.app().response_cache.get(&key) {
+        return Ok(cached.to_response());
+    }
+
+    let (krate, _) = load_crate_json(req, name)?;
+    Ok(krate.to_response())
+}
+
+/// Builds and caches the responses of the `show` and `versions` endpoints
+fn load_crate_json(req: &dyn RequestExt, name: &str) -> AppResult<(CachedJson, CachedJson)> {
     let conn = req.db_read_only()?;
-    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    let (krate, versions) = crate_json(&conn, name)?;
 
+    let response_cache = &req.app().response_cache;
+    response_cache.insert(CacheKey::Crate(name.into()), &krate);
+    response_cache.insert(CacheKey::Versions(name.into()), &versions);
+    Ok((krate, versions))
+}
+
+/// Builds the responses of the `show` and `versions` endpoints of a crate,
+/// which share most of their queries.
+pub(crate) fn crate_json(conn: &PgConnection, name: &str) -> AppResult<(CachedJson, CachedJson)> {
+    let krate: Crate = Crate::by_name(name).first(conn)?;
+    crate_json_of(conn, krate)
+}
+
+/// Like `crate_json`, for a crate that was already loaded
+fn crate_json_of(conn: &PgConnection, krate: Crate) -> AppResult<(CachedJson, CachedJson)> {
     let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
         .all_versions()
         .left_outer_join(users::table)
         .select((versions::all_columns, users::all_columns.nullable()))
-        .load(&*conn)?;
+        .load(conn)?;
     versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
+
+    // Same as `Crate::top_versions`, without loading the versions again
+    let top_versions = TopVersions::from_date_version_pairs(
+        versions_and_publishers
+            .iter()
+            .filter(|(v, _)| !v.yanked)
+            .map(|(v, _)| (v.updated_at, v.num.clone()))
+            .collect::<Vec<_>>(),
+    );
+
     let versions = versions_and_publishers
-        .iter()
-        .map(|(v, _)| v)
-        .cloned()
-        .collect::<Vec<_>>();
-    let versions_publishers_and_audit_actions = versions_and_publishers
         .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| (v, pb, aas))
-        .collect::<Vec<_>>();
-    let ids = versions_publishers_and_audit_actions
-        .iter()
-        .map(|v| v.0.id)
+        .map(|(v, pb)| (v, &krate.name, pb))
         .collect();
+    let versions = encode_versions(conn, versions)?;
+    let ids = versions.iter().map(|v| v.id).collect();
 
     let kws = CrateKeyword::belonging_to(&krate)
         .inner_join(keywords::table)
         .select(keywords::all_columns)
-        .load(&*conn)?;
+        .load(conn)?;
     let cats = CrateCategory::belonging_to(&krate)
         .inner_join(categories::table)
         .select(categories::all_columns)
-        .load(&*conn)?;
+        .load(conn)?;
     let recent_downloads = RecentCrateDownloads::belonging_to(&krate)
         .select(recent_crate_downloads::downloads)
-        .get_result(&*conn)
+        .get_result(conn)
         .optional()?;
 
     let badges = badges::table
         .filter(badges::crate_id.eq(krate.id))
-        .load(&*conn)?;
-    let top_versions = krate.top_versions(&conn)?;
+        .load(conn)?;
+    let dependents = CrateDependents::for_crate(conn, krate.id)?;
+    let verification = RepositoryVerification::for_crate(conn, krate.id)?;
+    let project_links = ProjectLink::for_crate(conn, krate.id)?;
 
     #[derive(Serialize)]
-    struct R {
+    struct Show<'a> {
         #[serde(rename = "crate")]
         krate: EncodableCrate,
-        versions: Vec<EncodableVersion>,
+        versions: &'a [EncodableVersion],
         keywords: Vec<EncodableKeyword>,
         categories: Vec<EncodableCategory>,
     }
-    Ok(req.json(&R {
+    let show = CachedJson::new(&Show {
         krate: EncodableCrate::from(
-            krate.clone(),
+            krate,
             &top_versions,
             Some(ids),
             Some(&kws),
@@ -180,14 +376,20 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
             Some(badges),
             false,
             recent_downloads,
-        ),
-        versions: versions_publishers_and_audit_actions
-            .into_iter()
-            .map(|(v, pb, aas)| EncodableVersion::from(v, &krate.name, pb, aas))
-            .collect(),
+        )
+        .with_dependents(dependents.as_ref())
+        .with_repository_verification(verification.as_ref())
+        .with_project_links(project_links),
+        versions: &versions,
         keywords: kws.into_iter().map(Keyword::into).collect(),
         categories: cats.into_iter().map(Category::into).collect(),
-    }))
+    });
+
+    #[derive(Serialize)]
+    struct Versions {
+        versions: Vec<EncodableVersion>,
+    }
+    Ok((show, CachedJson::new(&Versions { versions })))
 }
 
 /// Handles the `GET /crates/:crate_id/:version/readme` route.
@@ -212,46 +414,219 @@ pub fn readme(req: &mut dyn RequestExt) -> EndpointResult {
     }
 }
 
+/// Handles the `GET /crates/:crate_id/:version/readme/source` route.
+///
+/// Redirects to the README of the version as it was found in the crate
+/// file, next to the rendered HTML served by the `readme` route. Versions
+/// without a README, and versions whose README was rendered before the
+/// sources were stored, return a 404 until it is rendered again.
+pub fn readme_source(req: &mut dyn RequestExt) -> EndpointResult {
+    let crate_name = &req.params()["crate_id"];
+    let num = &req.params()["version"];
+
+    let conn = req.db_read_only()?;
+    let version: Version = versions::table
+        .inner_join(crates::table)
+        .filter(crates::name.eq(crate_name))
+        .filter(versions::num.eq(num))
+        .select(versions::all_columns)
+        .first(&*conn)?;
+    let file_name = version
+        .readme_source_file_name(&conn)
+        .optional()?
+        .flatten()
+        .ok_or_else(not_found)?;
+
+    let redirect_url = req
+        .app()
+        .config
+        .uploader
+        .readme_source_location(crate_name, num);
+
+    if req.wants_json() {
+        #[derive(Serialize)]
+        struct R {
+            url: String,
+            file_name: String,
+        }
+        Ok(req.json(&R {
+            url: redirect_url,
+            file_name,
+        }))
+    } else {
+        Ok(req.redirect(redirect_url))
+    }
+}
+
 /// Handles the `GET /crates/:crate_id/versions` route.
 // FIXME: Not sure why this is necessary since /crates/:crate_id returns
 // this information already, but ember is definitely requesting it
 pub fn versions(req: &mut dyn RequestExt) -> EndpointResult {
+    let query = req.query();
+    if ["page", "per_page", "seek"]
+        .iter()
+        .any(|p| query.contains_key(*p))
+    {
+        let crate_name = req.params()["crate_id"].clone();
+        return paginated_versions(req, &crate_name);
+    }
+
     let crate_name = &req.params()["crate_id"];
+    let key = CacheKey::Versions(crate_name.clone());
+    if let Some(cached) = req.app().response_cache.get(&key) {
+        return Ok(cached.to_response());
+    }
+
+    // The number of versions is loaded along with the crate, which the
+    // responses need anyway
     let conn = req.db_read_only()?;
-    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
-    let mut versions_and_publishers: Vec<(Version, Option<User>)> = krate
+    let version_count =
+        sql::<BigInt>("(SELECT COUNT(*) FROM versions WHERE versions.crate_id = crates.id)");
+    let (krate, num_versions): (Crate, i64) = crates::table
+        .select((ALL_COLUMNS, version_count))
+        .filter(Crate::with_name(crate_name))
+        .first(&*conn)?;
+    if num_versions > STREAMED_VERSIONS_THRESHOLD {
+        return stream_version_list(&conn, &krate);
+    }
+
+    let (krate_json, versions) = crate_json_of(&conn, krate)?;
+    let response_cache = &req.app().response_cache;
+    response_cache.insert(CacheKey::Crate(crate_name.clone()), &krate_json);
+    response_cache.insert(key, &versions);
+    Ok(versions.to_response())
+}
+
+/// Crates with more versions than this get their version list streamed
+/// instead of built and cached in memory
+const STREAMED_VERSIONS_THRESHOLD: i64 = 1000;
+
+/// How many versions are loaded and encoded at a time while streaming
+const STREAMED_VERSIONS_BATCH_SIZE: usize = 500;
+
+/// Responds with the full version list of crates with thousands of versions
+/// without holding all of the versions in memory. The versions are sorted by
+/// their number, then loaded and encoded a batch at a time while the
+/// response is written.
+///
+/// The other large lists don't need this: reverse dependencies are always
+/// paginated, and data exports are built by a background job and stored as
+/// one archive.
+fn stream_version_list(conn: &PgConnection, krate: &Crate) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let mut ids_and_nums: Vec<(i32, semver::Version)> = krate
         .all_versions()
+        .select((versions::id, versions::num))
+        .load(conn)?;
+    ids_and_nums.sort_by(|a, b| b.1.cmp(&a.1));
+    let ids: Vec<i32> = ids_and_nums.into_iter().map(|(id, _)| id).collect();
+
+    let load_batch = |ids: &[i32]| -> QueryResult<Vec<EncodableVersion>> {
+        let mut versions_and_publishers: Vec<(Version, Option<User>)> = versions::table
+            .filter(versions::id.eq(any(ids)))
+            .left_outer_join(users::table)
+            .select((versions::all_columns, users::all_columns.nullable()))
+            .load(conn)?;
+        versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
+
+        let versions = versions_and_publishers
+            .into_iter()
+            .map(|(v, pb)| (v, &krate.name, pb))
+            .collect();
+        encode_versions(conn, versions)
+    };
+    let versions = ids
+        .chunks(STREAMED_VERSIONS_BATCH_SIZE)
+        .map(load_batch)
+        .flat_map(|batch| match batch {
+            Ok(versions) => versions.into_iter().map(Ok).collect::<Vec<_>>(),
+            Err(e) => vec![Err(e)],
+        });
+
+    #[derive(Serialize)]
+    struct R<T> {
+        versions: T,
+    }
+    let versions = SerializeIter::new(versions);
+    json_file_response(&R { versions })
+}
+
+/// Lists the versions of a crate newest first, a page at a time.
+///
+/// Pages are requested with an opaque `seek` cursor, the id of the last
+/// version of the previous page, which the `next_page` of the response
+/// points to. Numeric pages are still supported.
+fn paginated_versions(req: &mut dyn RequestExt, crate_name: &str) -> EndpointResult {
+    let options = PaginationOptions::with_seek(req)?;
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(crate_name).first(&*conn)?;
+
+    let mut query = versions::table
+        .filter(versions::crate_id.eq(krate.id))
         .left_outer_join(users::table)
         .select((versions::all_columns, users::all_columns.nullable()))
-        .load(&*conn)?;
-    versions_and_publishers.sort_by(|a, b| b.0.num.cmp(&a.0.num));
-    let versions = versions_and_publishers
-        .iter()
-        .map(|(v, _)| v)
-        .cloned()
-        .collect::<Vec<_>>();
+        .order(versions::id.desc())
+        .limit(i64::from(options.per_page))
+        .into_boxed();
+    if let Some(last_id) = options.seek::<i32>()? {
+        query = query.filter(versions::id.lt(last_id));
+    }
+    if let Some(offset) = options.offset() {
+        query = query.offset(i64::from(offset));
+    }
+    let versions_and_publishers: Vec<(Version, Option<User>)> = query.load(&*conn)?;
+    let total = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .count()
+        .get_result(&*conn)?;
+
+    let last_id = versions_and_publishers.last().map(|(v, _)| v.id);
+    let next_page = options
+        .next_seek_params(versions_and_publishers.len(), last_id)
+        .map(|p| req.query_with_params(p));
+
     let versions = versions_and_publishers
         .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, pb), aas)| EncodableVersion::from(v, crate_name, pb, aas))
+        .map(|(v, pb)| (v, &krate.name, pb))
         .collect();
+    let versions = encode_versions(&conn, versions)?;
 
     #[derive(Serialize)]
     struct R {
         versions: Vec<EncodableVersion>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+        next_page: Option<String>,
     }
-    Ok(req.json(&R { versions }))
+    Ok(req.json(&R {
+        versions,
+        meta: Meta { total, next_page },
+    }))
 }
 
 /// Handles the `GET /crates/:crate_id/reverse_dependencies` route.
+///
+/// Supports keyset pagination with the `seek` parameter, see the
+/// `next_page` of the response.
 pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
 
-    let pagination_options = PaginationOptions::new(req)?;
+    let _permit = req.db_budget(EndpointFamily::ReverseDependencies)?;
+    let pagination_options = PaginationOptions::with_seek(req)?;
     let name = &req.params()["crate_id"];
     let conn = req.db_read_only()?;
     let krate: Crate = Crate::by_name(name).first(&*conn)?;
-    let (rev_deps, total) = krate.reverse_dependencies(&*conn, pagination_options)?;
+    let (rev_deps, total) = krate.reverse_dependencies(&*conn, &pagination_options)?;
+    let last_key = rev_deps
+        .last()
+        .map(|dep| (dep.crate_downloads, dep.name.clone()));
+    let next_page = pagination_options
+        .next_seek_params(rev_deps.len(), last_key)
+        .map(|p| req.query_with_params(p));
     let rev_deps: Vec<_> = rev_deps
         .into_iter()
         .map(|dep| EncodableDependency::from_reverse_dep(dep, &krate.name))
@@ -269,18 +644,7 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
             users::all_columns.nullable(),
         ))
         .load(&*conn)?;
-    let versions = versions_and_publishers
-        .iter()
-        .map(|(v, _, _)| v)
-        .cloned()
-        .collect::<Vec<_>>();
-    let versions = versions_and_publishers
-        .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, krate_name, published_by), actions)| {
-            EncodableVersion::from(version, &krate_name, published_by, actions)
-        })
-        .collect();
+    let versions = encode_versions(&conn, versions_and_publishers)?;
 
     #[derive(Serialize)]
     struct R {
@@ -291,10 +655,11 @@ pub fn reverse_dependencies(req: &mut dyn RequestExt) -> EndpointResult {
     #[derive(Serialize)]
     struct Meta {
         total: i64,
+        next_page: Option<String>,
     }
     Ok(req.json(&R {
         dependencies: rev_deps,
         versions,
-        meta: Meta { total },
+        meta: Meta { total, next_page },
     }))
 }
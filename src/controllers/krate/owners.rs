@@ -1,7 +1,12 @@
 //! All routes related to managing owners of a crate
 
+use std::collections::HashMap;
+
+use crate::controllers::helpers::idempotency::idempotent;
 use crate::controllers::prelude::*;
 use crate::models::{Crate, Owner, Rights, Team, User};
+use crate::schema::crates;
+use crate::util::errors::{bad_request, cargo_err_with_code, ErrorCode};
 use crate::views::EncodableOwner;
 
 /// Handles the `GET /crates/:crate_id/owners` route.
@@ -18,6 +23,63 @@ pub fn owners(req: &mut dyn RequestExt) -> EndpointResult {
     Ok(req.json(&R { users: owners }))
 }
 
+/// How many crates the owners can be requested for at once
+const MAX_BATCH_CRATES: usize = 100;
+
+/// Handles the `GET /owners` route.
+///
+/// Returns the owners of the crates named by the `crates[]` query
+/// parameters at once, for list views that show the owners of every crate.
+/// Crates that don't exist are left out of the response.
+pub fn batch(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+    use std::collections::BTreeMap;
+
+    let query = url::form_urlencoded::parse(req.query_string().unwrap_or("").as_bytes());
+    let mut names = query
+        .filter_map(|(key, value)| {
+            if key == "crates[]" {
+                Some(value.into_owned())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<String>>();
+    names.sort();
+    names.dedup();
+    if names.len() > MAX_BATCH_CRATES {
+        return Err(bad_request(&format_args!(
+            "the owners of at most {} crates can be requested at once",
+            MAX_BATCH_CRATES
+        )));
+    }
+
+    let conn = req.db_read_only()?;
+    let crates: Vec<(i32, String)> = crates::table
+        .filter(crates::name.eq(any(&names[..])))
+        .select((crates::id, crates::name))
+        .load(&*conn)?;
+    let crate_ids: Vec<i32> = crates.iter().map(|(id, _)| *id).collect();
+
+    let mut owners: BTreeMap<String, Vec<EncodableOwner>> = BTreeMap::new();
+    let mut names_by_id = HashMap::new();
+    for (id, name) in crates {
+        owners.insert(name.clone(), Vec::new());
+        names_by_id.insert(id, name);
+    }
+    for (crate_id, owner) in Crate::owners_of_crates(&conn, &crate_ids)? {
+        if let Some(crate_owners) = names_by_id.get(&crate_id).and_then(|n| owners.get_mut(n)) {
+            crate_owners.push(owner.into());
+        }
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        owners: BTreeMap<String, Vec<EncodableOwner>>,
+    }
+    Ok(req.json(&R { owners }))
+}
+
 /// Handles the `GET /crates/:crate_id/owner_team` route.
 pub fn owner_team(req: &mut dyn RequestExt) -> EndpointResult {
     let crate_name = &req.params()["crate_id"];
@@ -54,12 +116,12 @@ pub fn owner_user(req: &mut dyn RequestExt) -> EndpointResult {
 
 /// Handles the `PUT /crates/:crate_id/owners` route.
 pub fn add_owners(req: &mut dyn RequestExt) -> EndpointResult {
-    modify_owners(req, true)
+    idempotent(req, |req| modify_owners(req, true))
 }
 
 /// Handles the `DELETE /crates/:crate_id/owners` route.
 pub fn remove_owners(req: &mut dyn RequestExt) -> EndpointResult {
-    modify_owners(req, false)
+    idempotent(req, |req| modify_owners(req, false))
 }
 
 /// Parse the JSON request body of requests to modify the owners of a crate.
@@ -100,12 +162,16 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
             Rights::Full => {}
             // Yes!
             Rights::Publish => {
-                return Err(cargo_err(
+                return Err(cargo_err_with_code(
+                    ErrorCode::NotOwner,
                     "team members don't have permission to modify owners",
                 ));
             }
             Rights::None => {
-                return Err(cargo_err("only owners have permission to modify owners"));
+                return Err(cargo_err_with_code(
+                    ErrorCode::NotOwner,
+                    "only owners have permission to modify owners",
+                ));
             }
         }
 
@@ -115,7 +181,10 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
                 let login_test =
                     |owner: &Owner| owner.login().to_lowercase() == *login.to_lowercase();
                 if owners.iter().any(login_test) {
-                    return Err(cargo_err(&format_args!("`{}` is already an owner", login)));
+                    return Err(cargo_err_with_code(
+                        ErrorCode::AlreadyOwner,
+                        &format_args!("`{}` is already an owner", login),
+                    ));
                 }
                 let msg = krate.owner_add(app, &conn, &user, login)?;
                 msgs.push(msg);
@@ -135,6 +204,8 @@ fn modify_owners(req: &mut dyn RequestExt, add: bool) -> EndpointResult {
             "owners successfully removed".to_owned()
         };
 
+        app.response_cache.invalidate_crate(&krate.name);
+
         #[derive(Serialize)]
         struct R {
             ok: bool,
@@ -0,0 +1,106 @@
+//! Endpoint for the links that owners attach to a crate, like its changelog,
+//! migration guides, chat rooms and funding pages
+//!
+//! The links are returned as `project_links` in the response of
+//! `GET /crates/:crate_id`.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, NewProjectLink, ProjectLink, ProjectLinkKind, Rights};
+use crate::views::EncodableProjectLink;
+
+#[derive(Deserialize)]
+struct LinksRequest {
+    project_links: Vec<LinkRequest>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LinkRequest {
+    kind: String,
+    url: String,
+    label: Option<String>,
+}
+
+impl LinkRequest {
+    fn validate(self, crate_id: i32) -> AppResult<NewProjectLink> {
+        let kind = ProjectLinkKind::from_name(&self.kind)
+            .ok_or_else(|| bad_request(&format_args!("unknown kind of link `{}`", self.kind)))?;
+
+        let url = self.url.trim();
+        if !ProjectLink::is_valid_url(url) {
+            return Err(bad_request(&format_args!(
+                "`{}` is not an http or https URL of at most {} characters",
+                url,
+                ProjectLink::MAX_URL_LENGTH
+            )));
+        }
+
+        let label = self
+            .label
+            .map(|label| {
+                label
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .collect::<String>()
+            })
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty());
+        if label.as_ref().map_or(0, |label| label.chars().count()) > ProjectLink::MAX_LABEL_LENGTH {
+            return Err(bad_request(&format_args!(
+                "the label of a link must be at most {} characters long",
+                ProjectLink::MAX_LABEL_LENGTH
+            )));
+        }
+
+        Ok(NewProjectLink {
+            crate_id,
+            kind,
+            url: url.to_string(),
+            label,
+            from_manifest: false,
+        })
+    }
+}
+
+/// Handles the `PUT /crates/:crate_id/project_links` route.
+///
+/// Replaces the links of the crate with the links of the request, in their
+/// order. Funding links from the manifest of the crate are kept.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: LinksRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if request.project_links.len() > ProjectLink::MAX_PER_CRATE {
+        return Err(bad_request(&format_args!(
+            "a crate can have at most {} links",
+            ProjectLink::MAX_PER_CRATE
+        )));
+    }
+
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    if user.rights(req.app(), &krate.owners(&conn)?)? < Rights::Publish {
+        return Err(bad_request(
+            "must already be an owner to change the links of the crate",
+        ));
+    }
+
+    let links = request
+        .project_links
+        .into_iter()
+        .map(|link| link.validate(krate.id))
+        .collect::<AppResult<Vec<_>>>()?;
+    let links = ProjectLink::replace_all(&conn, krate.id, false, &links)?;
+    req.app().response_cache.invalidate_crate(&krate.name);
+
+    #[derive(Serialize)]
+    struct R {
+        project_links: Vec<EncodableProjectLink>,
+    }
+    Ok(req.json(&R {
+        project_links: links.into_iter().map(ProjectLink::into).collect(),
+    }))
+}
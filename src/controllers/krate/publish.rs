@@ -1,23 +1,27 @@
 //! Functionality related to publishing a new crate or version of a crate.
 
-use hex::ToHex;
+use chrono::Utc;
 use std::sync::Arc;
-use swirl::Job;
 
+use crate::background_jobs::Job;
 use crate::controllers::cargo_prelude::*;
+use crate::controllers::helpers::idempotency::idempotent;
 use crate::git;
 use crate::models::{
-    insert_version_owner_action, Badge, Category, Crate, DependencyKind, Keyword, NewCrate,
-    NewVersion, Rights, VersionAction,
+    insert_version_owner_action, Advisory, Announcement, AnnouncementSeverity, Badge, Category,
+    ChecksumAlgorithm, Crate, CrateDependencies, CrateDependents, DependencyKind, Keyword,
+    NewCrate, NewPendingUpload, NewProjectLink, NewVersion, Owner, ProjectLink, ProjectLinkKind,
+    Rights, VersionAction, VersionChecksum, VersionLicense, VersionPublishOrigin,
 };
-
-use crate::render;
+use crate::quotas::{Quotas, Usage};
 use crate::schema::*;
-use crate::util::errors::{cargo_err, AppResult};
-use crate::util::{read_fill, read_le_u32, Maximums};
+use crate::uploaders::Uploader;
+use crate::util::errors::{cargo_err, cargo_err_with_code, AppResult, ErrorCode, TooManyRequests};
+use crate::util::{ip_class, read_fill, read_le_u32, request_ip, Maximums};
 use crate::views::{
     EncodableCrate, EncodableCrateDependency, EncodableCrateUpload, GoodCrate, PublishWarnings,
 };
+use crate::{license, publish_rate_limit, tasks};
 
 pub const MISSING_RIGHTS_ERROR_MESSAGE: &str =
     "this crate exists but you don't seem to be an owner. \
@@ -37,7 +41,14 @@ pub const WILDCARD_ERROR_MESSAGE: &str = "wildcard (`*`) dependency constraints
 /// Currently blocks the HTTP thread, perhaps some function calls can spawn new
 /// threads and return completion or error through other methods  a `cargo publish
 /// --status` command, via crates.io's front end, or email.
+///
+/// Retries with the same `Idempotency-Key` get the response of the first
+/// request, see the `idempotency` module.
 pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
+    idempotent(req, publish_crate)
+}
+
+fn publish_crate(req: &mut dyn RequestExt) -> EndpointResult {
     let app = Arc::clone(req.app());
 
     // The format of the req.body() of a publish request is as follows:
@@ -50,13 +61,14 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
     // - The metadata is read and interpreted in the parse_new_headers function.
     // - The .crate tarball length is read in this function in order to save the size of the file
     //   in the version record in the database.
-    // - Then the .crate tarball length is passed to the upload_crate function where the actual
-    //   file is read and uploaded.
+    // - Then the .crate tarball length is passed to the read_crate function where the actual
+    //   file is read and verified. It is uploaded once the new version has been committed.
 
     let new_crate = parse_new_headers(req)?;
 
     req.log_metadata("crate_name", new_crate.name.to_string());
     req.log_metadata("crate_version", new_crate.vers.to_string());
+    let ip_class = ip_class(request_ip(req));
 
     let conn = app.primary_database.get()?;
     let ids = req.authenticate()?;
@@ -65,16 +77,21 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
 
     let verified_email_address = user.verified_email(&conn)?;
     let verified_email_address = verified_email_address.ok_or_else(|| {
-        cargo_err(&format!(
-            "A verified email address is required to publish crates to crates.io. \
-             Visit https://{}/me to set and verify your email address.",
-            app.config.domain_name,
-        ))
+        cargo_err_with_code(
+            ErrorCode::EmailNotVerified,
+            &format!(
+                "A verified email address is required to publish crates to crates.io. \
+                 Visit https://{}/me to set and verify your email address.",
+                app.config.domain_name,
+            ),
+        )
     })?;
 
+    let uploader_id = user.id;
+
     // Create a transaction on the database, if there are no errors,
     // commit the transactions to record a new or updated crate.
-    conn.transaction(|| {
+    let result: AppResult<_> = conn.transaction(|| {
         let name = new_crate.name;
         let vers = &*new_crate.vers;
         let links = new_crate.links;
@@ -107,19 +124,23 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         };
 
         let license_file = new_crate.license_file.as_deref();
+        let is_new_crate = Crate::by_name(&name).count().get_result::<i64>(&*conn)? == 0;
         let krate =
             persist.create_or_update(&conn, user.id, Some(&app.config.publish_rate_limit))?;
 
         let owners = krate.owners(&conn)?;
         if user.rights(req.app(), &owners)? < Rights::Publish {
-            return Err(cargo_err(MISSING_RIGHTS_ERROR_MESSAGE));
+            return Err(cargo_err_with_code(
+                ErrorCode::CrateNameTaken,
+                MISSING_RIGHTS_ERROR_MESSAGE,
+            ));
         }
 
         if krate.name != *name {
-            return Err(cargo_err(&format_args!(
-                "crate was previously named `{}`",
-                krate.name
-            )));
+            return Err(cargo_err_with_code(
+                ErrorCode::CrateNameMismatch,
+                &format_args!("crate was previously named `{}`", krate.name),
+            ));
         }
 
         // Length of the .crate tarball, which appears after the metadata in the request body.
@@ -139,22 +160,31 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         );
 
         if content_length > maximums.max_upload_size {
-            return Err(cargo_err(&format_args!(
-                "max upload size is: {}",
-                maximums.max_upload_size
-            )));
+            return Err(cargo_err_with_code(
+                ErrorCode::UploadTooLarge,
+                &format_args!("max upload size is: {}", maximums.max_upload_size),
+            ));
+        }
+
+        // The usage is only computed if the account has any quota, see the `quotas` module
+        let quotas = app.config.quotas.for_user(&conn, user.id)?;
+        if quotas != Quotas::default() {
+            let usage = Usage::of(&conn, user.id)?;
+            quotas.check_publish(&usage, is_new_crate, i64::from(file_length))?;
         }
 
         // This is only redundant for now. Eventually the duplication will be removed.
         let license = new_crate.license.clone();
 
         // Persist the new version of this crate
+        let dependencies = CrateDependencies::load(&conn, krate.id)?;
         let version = NewVersion::new(
             krate.id,
             vers,
             &features,
             license,
             license_file,
+            app.config.invalid_license_policy,
             // Downcast is okay because the file length must be less than the max upload size
             // to get here, and max upload sizes are way less than i32 max
             file_length as i32,
@@ -162,6 +192,27 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         )?
         .save(&conn, &new_crate.authors, &verified_email_address)?;
 
+        if let Some(rust_version) = &new_crate.rust_version {
+            diesel::update(&version)
+                .set(versions::rust_version.eq(&rust_version.0))
+                .execute(&*conn)?;
+        }
+
+        // Valid licenses are stored normalized for the license statistics.
+        // Invalid ones only get here if the policy is to warn about them.
+        let mut other_warnings = Vec::new();
+        if let Some(license) = &new_crate.license {
+            match license::normalize(license) {
+                Ok(normalized) => VersionLicense::new(version.id, normalized).insert(&conn)?,
+                Err(e) => other_warnings.push(format!(
+                    "the license `{}` is not a valid SPDX expression ({}), so it is left out \
+                     of the license statistics; see http://spdx.org/licenses/ for the \
+                     identifiers",
+                    license, e
+                )),
+            }
+        }
+
         insert_version_owner_action(
             &conn,
             version.id,
@@ -169,9 +220,18 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             api_token_id,
             VersionAction::Publish,
         )?;
+        VersionPublishOrigin {
+            version_id: version.id,
+            ip_class: ip_class.into(),
+        }
+        .insert(&conn)?;
 
         // Link this new version to all dependencies
         let git_deps = add_dependencies(&conn, &new_crate.deps, version.id)?;
+        CrateDependents::update_dependencies_of(&conn, &dependencies)?;
+
+        // Versions published after an advisory without a fix are affected, too
+        Advisory::link_new_version(&conn, &krate.name, version.id, &version.num)?;
 
         // Update all keywords for this crate
         Keyword::update_crate(&conn, &krate, &keywords)?;
@@ -185,24 +245,53 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
         let ignored_invalid_badges = Badge::update_crate(&conn, &krate, new_crate.badges.as_ref())?;
         let top_versions = krate.top_versions(&conn)?;
 
+        // Let the other owners know, in case the publisher's account or token
+        // was compromised
+        let has_other_owners = owners
+            .iter()
+            .any(|owner| matches!(owner, Owner::User(owner) if owner.id != user.id));
+        if has_other_owners {
+            Job::NotifyNewVersion {
+                version_id: version.id,
+            }
+            .enqueue(&conn)?;
+        }
+
         if let Some(readme) = new_crate.readme {
-            render::render_and_upload_readme(
-                version.id,
-                readme,
-                new_crate
+            Job::RenderAndUploadReadme {
+                version_id: version.id,
+                text: readme,
+                file_name: new_crate
                     .readme_file
                     .unwrap_or_else(|| String::from("README.md")),
-                repo,
-            )
+                base_url: repo,
+            }
             .enqueue(&conn)?;
         }
 
-        let cksum = app
-            .config
-            .uploader
-            .upload_crate(req, &krate, maximums, vers)?;
-
-        let hex_cksum = cksum.encode_hex::<String>();
+        let (tarball, hex_cksum, manifest) = Uploader::read_crate(req, &krate, maximums, vers)?;
+        diesel::update(&version)
+            .set(versions::checksum.eq(&hex_cksum))
+            .execute(&*conn)?;
+        VersionChecksum::record(&conn, version.id, &tarball)?;
+
+        // The funding links of the manifest replace those of the previously
+        // published version
+        if let Some(manifest) = manifest {
+            let (urls, warnings) = manifest_funding(&manifest);
+            other_warnings.extend(warnings);
+            let links: Vec<_> = urls
+                .into_iter()
+                .map(|url| NewProjectLink {
+                    crate_id: krate.id,
+                    kind: ProjectLinkKind::Funding,
+                    url,
+                    label: None,
+                    from_manifest: true,
+                })
+                .collect();
+            ProjectLink::replace_all(&conn, krate.id, true, &links)?;
+        }
 
         // Register this crate in our local git repo.
         let git_crate = git::Crate {
@@ -213,23 +302,111 @@ pub fn publish(req: &mut dyn RequestExt) -> EndpointResult {
             deps: git_deps,
             yanked: Some(false),
             links,
+            ext: Some(git::IndexExtensions {
+                publisher: Some(user.gh_login.clone()),
+                checksums: ChecksumAlgorithm::ALL
+                    .iter()
+                    .map(|algorithm| (algorithm.as_str().into(), algorithm.digest(&tarball)))
+                    .collect(),
+                yank_reason: None,
+            }),
         };
-        git::add_crate(git_crate).enqueue(&conn)?;
+        let mut jobs = Vec::new();
+
+        // Versions by new accounts are added to the index once they are
+        // released from the quarantine, see `first_publish_hold`
+        match &app.config.first_publish_hold {
+            Some(policy) if policy.applies_to(&conn, &user)? => {
+                let quarantine = policy.hold(&conn, version.id, &git_crate)?;
+                other_warnings.push(match quarantine.release_at {
+                    Some(release_at) => format!(
+                        "versions published by new accounts are held for review and will be \
+                         available after {} UTC",
+                        release_at.format("%Y-%m-%d %H:%M")
+                    ),
+                    None => "versions published by new accounts are held for review and will \
+                             be available once they have been reviewed"
+                        .to_string(),
+                });
+            }
+            _ => jobs.push(Job::AddCrate { krate: git_crate }),
+        }
+
+        // Run the uploaded file through the malware scanners, if any are configured
+        let scanners = &app.config.upload_scanners;
+        if !scanners.is_empty() {
+            jobs.push(Job::ScanVersion {
+                version_id: version.id,
+                krate: krate.name.clone(),
+                version: vers.to_string(),
+                scanners: scanners.clone(),
+            });
+        }
+
+        // The tarball is uploaded once this transaction is committed, and
+        // the jobs above are only enqueued after that, so the index never
+        // refers to a file that isn't in storage.
+        NewPendingUpload {
+            version_id: version.id,
+            tarball: &tarball,
+            jobs: &jobs,
+        }
+        .create(&conn)?;
+
+        // Registry-wide notices, like planned read-only windows, are shown by cargo
+        for announcement in Announcement::current(&conn, Utc::now().naive_utc())? {
+            if announcement.severity >= AnnouncementSeverity::Warning {
+                other_warnings.push(match announcement.url {
+                    Some(url) => format!("{} ({})", announcement.message, url),
+                    None => announcement.message,
+                });
+            }
+        }
 
         // The `other` field on `PublishWarnings` was introduced to handle a temporary warning
-        // that is no longer needed. As such, crates.io currently does not return any `other`
-        // warnings at this time, but if we need to, the field is available.
+        // that is no longer needed. It is now used to tell new accounts that their version
+        // is held, to warn about invalid licenses, and for announcements of the crates.io
+        // team.
         let warnings = PublishWarnings {
             invalid_categories: ignored_invalid_categories,
             invalid_badges: ignored_invalid_badges,
-            other: vec![],
+            other: other_warnings,
         };
 
-        Ok(req.json(&GoodCrate {
+        let crate_name = krate.name.clone();
+        let response = req.json(&GoodCrate {
             krate: EncodableCrate::from_minimal(krate, &top_versions, None, false, None),
             warnings,
-        }))
-    })
+        });
+        Ok((response, version.id, crate_name))
+    });
+
+    if let Err(error) = &result {
+        // Failing to count the rejection, e.g. in read-only mode, mustn't
+        // hide the rate limit from the user
+        if error.is::<TooManyRequests>() {
+            if let Err(e) = publish_rate_limit::record_rejection(uploader_id, &conn) {
+                error!(error = %e, "Failed to record a rate limited publish");
+            }
+        }
+    }
+    let (response, version_id, crate_name) = result?;
+
+    // Invalidating before the commit would let a concurrent request cache
+    // the crate as it was before this version
+    app.response_cache.invalidate_crate(&crate_name);
+    app.response_cache.invalidate_keywords();
+    app.version_lookups.invalidate_crate(&crate_name);
+
+    // The version has been published at this point. If the upload fails, it
+    // is retried by the `upload_pending_crates` job.
+    let uploader = &app.config.uploader;
+    if let Err(error) = tasks::upload_pending_crate(&conn, uploader, app.http_client(), version_id)
+    {
+        req.log_metadata("upload_error", error.to_string());
+    }
+
+    Ok(response)
 }
 
 /// Used by the `krate::new` function.
@@ -243,7 +420,10 @@ fn parse_new_headers(req: &mut dyn RequestExt) -> AppResult<EncodableCrateUpload
 
     let max = req.app().config.max_upload_size;
     if metadata_length > max {
-        return Err(cargo_err(&format_args!("max upload size is: {}", max)));
+        return Err(cargo_err_with_code(
+            ErrorCode::UploadTooLarge,
+            &format_args!("max upload size is: {}", max),
+        ));
     }
     let mut json = vec![0; metadata_length as usize];
     read_fill(req.body(), &mut json)?;
@@ -270,7 +450,7 @@ fn parse_new_headers(req: &mut dyn RequestExt) -> AppResult<EncodableCrateUpload
     }
     if !missing.is_empty() {
         let message = missing_metadata_error_message(&missing);
-        return Err(cargo_err(&message));
+        return Err(cargo_err_with_code(ErrorCode::MissingMetadata, &message));
     }
 
     Ok(new)
@@ -305,7 +485,12 @@ pub fn add_dependencies(
             // Match only identical names to ensure the index always references the original crate name
             let krate:Crate = Crate::by_exact_name(&dep.name)
                 .first(&*conn)
-                .map_err(|_| cargo_err(&format_args!("no known crate named `{}`", &*dep.name)))?;
+                .map_err(|_| {
+                    cargo_err_with_code(
+                        ErrorCode::UnknownDependency,
+                        &format_args!("no known crate named `{}`", &*dep.name),
+                    )
+                })?;
             if semver::VersionReq::parse(&dep.version_req.0) == semver::VersionReq::parse("*") {
                 return Err(cargo_err(WILDCARD_ERROR_MESSAGE));
             }
@@ -355,9 +540,65 @@ pub fn add_dependencies(
     Ok(git_deps)
 }
 
+/// The funding URLs in `package.metadata.funding` of a `Cargo.toml`, which is
+/// either a URL or an array of URLs. Returns the valid URLs, and warnings
+/// about the ignored values.
+fn manifest_funding(manifest: &str) -> (Vec<String>, Vec<String>) {
+    let mut urls = Vec::new();
+    let mut warnings = Vec::new();
+
+    let manifest: toml::Value = match toml::from_str(manifest) {
+        Ok(manifest) => manifest,
+        Err(_) => return (urls, warnings),
+    };
+    let funding = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("funding"));
+    let values = match funding {
+        Some(toml::Value::Array(values)) => values.iter().collect(),
+        Some(value) => vec![value],
+        None => vec![],
+    };
+
+    for value in values {
+        match value.as_str() {
+            Some(url) if ProjectLink::is_valid_url(url) => {
+                if urls.len() < ProjectLink::MAX_PER_CRATE {
+                    urls.push(url.to_string());
+                }
+            }
+            _ => warnings.push(format!(
+                "ignored the value `{}` of `package.metadata.funding`, which is not an http \
+                 or https URL",
+                value
+            )),
+        }
+    }
+    (urls, warnings)
+}
+
 #[cfg(test)]
 mod tests {
-    use super::missing_metadata_error_message;
+    use super::{manifest_funding, missing_metadata_error_message};
+
+    #[test]
+    fn funding_urls_are_read_from_the_manifest() {
+        let manifest = r#"
+            [package]
+            name = "foo"
+
+            [package.metadata]
+            funding = ["https://example.com/sponsor", "ftp://example.com", 1]
+        "#;
+        let (urls, warnings) = manifest_funding(manifest);
+        assert_eq!(urls, vec!["https://example.com/sponsor"]);
+        assert_eq!(warnings.len(), 2);
+
+        let manifest = "[package.metadata]\nfunding = \"https://example.com\"";
+        assert_eq!(manifest_funding(manifest).0, vec!["https://example.com"]);
+        assert!(manifest_funding("[package]\nname = \"foo\"").0.is_empty());
+    }
 
     #[test]
     fn missing_metadata_error_message_test() {
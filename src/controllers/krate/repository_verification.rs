@@ -0,0 +1,110 @@
+//! Endpoints for verifying the repository of a crate
+//!
+//! See [`crate::repository_verification`] for how control of a repository is
+//! proven.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, RepositoryVerification, Rights, User};
+use crate::repository_verification::VerificationMethod;
+use crate::views::EncodableRepositoryVerification;
+
+fn ensure_owner(
+    req: &dyn RequestExt,
+    conn: &PgConnection,
+    krate: &Crate,
+    user: &User,
+) -> AppResult<()> {
+    if user.rights(req.app(), &krate.owners(conn)?)? < Rights::Publish {
+        return Err(bad_request(
+            "must already be an owner to verify the repository",
+        ));
+    }
+    Ok(())
+}
+
+fn verification_response(
+    req: &dyn RequestExt,
+    verification: Option<RepositoryVerification>,
+) -> EndpointResult {
+    let verification = match verification {
+        Some(verification) => {
+            let method = VerificationMethod::for_repository(&verification.repository)?;
+            Some(EncodableRepositoryVerification::from(verification, &method))
+        }
+        None => None,
+    };
+
+    #[derive(Serialize)]
+    struct R {
+        repository_verification: Option<EncodableRepositoryVerification>,
+    }
+    Ok(req.json(&R {
+        repository_verification: verification,
+    }))
+}
+
+/// Handles the `GET /crates/:crate_id/repository_verification` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let verification = RepositoryVerification::for_crate(&conn, krate.id)?;
+    verification_response(req, verification)
+}
+
+/// Handles the `PUT /crates/:crate_id/repository_verification` route.
+///
+/// Starts verifying the current repository of the crate. A verification that
+/// was already started for the same repository is kept, so its token stays
+/// valid.
+pub fn start(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let repository = krate
+        .repository
+        .as_deref()
+        .ok_or_else(|| bad_request("the crate has no repository to verify"))?;
+    VerificationMethod::for_repository(repository)?;
+
+    let verification = match RepositoryVerification::for_crate(&conn, krate.id)? {
+        Some(existing) if existing.repository == repository => existing,
+        _ => RepositoryVerification::start(&conn, krate.id, repository, user.id)?,
+    };
+
+    req.app().response_cache.invalidate_crate(&krate.name);
+    verification_response(req, Some(verification))
+}
+
+/// Handles the `PUT /crates/:crate_id/repository_verification/check` route.
+///
+/// Checks whether the authenticated owner controls the repository. The
+/// result is stored either way, and the verification is returned with
+/// `verified_at` set if the check succeeded.
+pub fn check(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_conn()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    ensure_owner(req, &conn, &krate, &user)?;
+
+    let verification = RepositoryVerification::for_crate(&conn, krate.id)?
+        .filter(|v| krate.repository.as_deref() == Some(v.repository.as_str()))
+        .ok_or_else(|| {
+            bad_request("no verification was started for the repository of the crate")
+        })?;
+
+    let method = VerificationMethod::for_repository(&verification.repository)?;
+    let app = req.app();
+    let verified = method.check(&app.github, &verification, &user)?;
+    let verification = verification.record_check(&conn, verified, user.id)?;
+
+    app.response_cache.invalidate_crate(&krate.name);
+    verification_response(req, Some(verification))
+}
@@ -7,7 +7,7 @@ use crate::controllers::cargo_prelude::*;
 use crate::controllers::helpers::Paginate;
 use crate::controllers::util::AuthenticatedUser;
 use crate::models::{
-    Crate, CrateBadge, CrateOwner, CrateVersions, OwnerKind, TopVersions, Version,
+    Crate, CrateBadge, CrateDependents, CrateOwner, CrateVersions, OwnerKind, TopVersions, Version,
 };
 use crate::schema::*;
 use crate::util::errors::{bad_request, ChainError};
@@ -40,6 +40,8 @@ use crate::models::krate::{canon_crate_name, ALL_COLUMNS};
 pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::sql_types::{Bool, Text};
 
+    let _permit = req.db_budget(EndpointFamily::Search)?;
+
     // Don't require that authentication succeed, because it's only necessary
     // if the "following" param is set.
     let authenticated_user: AppResult<AuthenticatedUser> = req.authenticate();
@@ -57,6 +59,8 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
     );
     let mut query = crates::table
         .left_join(recent_crate_downloads::table)
+        .left_join(crate_dependents::table)
+        .left_join(crate_trending_scores::table)
         .select(selection)
         .into_boxed();
 
@@ -182,6 +186,10 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         query = query.then_order_by(crates::downloads.desc())
     } else if sort == Some("recent-downloads") {
         query = query.then_order_by(recent_crate_downloads::downloads.desc().nulls_last())
+    } else if sort == Some("dependents") {
+        query = query.then_order_by(crate_dependents::direct_dependents.desc().nulls_last())
+    } else if sort == Some("trending") {
+        query = query.then_order_by(crate_trending_scores::score.desc().nulls_last())
     } else if sort == Some("recent-updates") {
         query = query.order(crates::updated_at.desc());
     } else if sort == Some("new") {
@@ -205,6 +213,9 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .collect::<Vec<_>>();
     let crates = data.into_iter().map(|(c, _, _)| c).collect::<Vec<_>>();
 
+    let crate_ids = crates.iter().map(|c| c.id).collect::<Vec<_>>();
+    let dependents = CrateDependents::for_crates(&conn, &crate_ids)?;
+
     let versions: Vec<Version> = crates.versions().load(&*conn)?;
     let versions = versions
         .grouped_by(&crates)
@@ -226,6 +237,7 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
         .zip(badges)
         .map(
             |((((max_version, krate), perfect_match), recent_downloads), badges)| {
+                let dependents = dependents.get(&krate.id);
                 EncodableCrate::from_minimal(
                     krate,
                     &max_version,
@@ -233,6 +245,7 @@ pub fn search(req: &mut dyn RequestExt) -> EndpointResult {
                     perfect_match,
                     Some(recent_downloads),
                 )
+                .with_dependents(dependents)
             },
         )
         .collect();
@@ -0,0 +1,137 @@
+//! Endpoints that show owners how much storage their crates take up
+//!
+//! The storage of a version is its crate file and, if it has a README, the
+//! rendered HTML and the source of the README. The sizes are recorded on
+//! publish and when the README is rendered, so versions published or
+//! rendered before that are not counted.
+
+use std::collections::BTreeMap;
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, CrateOwner, OwnerKind, Rights};
+use crate::schema::{crate_owners, crates, readme_renderings, versions};
+use crate::views::{EncodableCrateStorage, EncodableVersionStorage};
+
+/// The crate id, number, crate file size and README sizes of a version
+type VersionSizes = (i32, String, Option<i32>, Option<i32>, Option<i32>);
+
+fn load_version_sizes(conn: &PgConnection, crate_ids: &[i32]) -> QueryResult<Vec<VersionSizes>> {
+    use diesel::dsl::any;
+
+    versions::table
+        .left_join(readme_renderings::table)
+        .filter(versions::crate_id.eq(any(crate_ids)))
+        .select((
+            versions::crate_id,
+            versions::num,
+            versions::crate_size,
+            readme_renderings::rendered_size.nullable(),
+            readme_renderings::source_size.nullable(),
+        ))
+        .order(versions::id)
+        .load(conn)
+}
+
+fn add_version(storage: &mut EncodableCrateStorage, sizes: &VersionSizes) {
+    let (_, _, crate_size, rendered_size, source_size) = *sizes;
+    let crate_size = i64::from(crate_size.unwrap_or(0));
+    let rendered_size = i64::from(rendered_size.unwrap_or(0));
+    let source_size = i64::from(source_size.unwrap_or(0));
+
+    storage.versions += 1;
+    storage.crate_file_bytes += crate_size;
+    storage.readme_bytes += rendered_size;
+    storage.readme_source_bytes += source_size;
+    storage.total_bytes += crate_size + rendered_size + source_size;
+}
+
+/// Handles the `GET /crates/:crate_id/storage` route.
+///
+/// Lists the storage used by every version of the crate, oldest first.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let user = req.authenticate()?.user();
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    if user.rights(req.app(), &krate.owners(&conn)?)? < Rights::Publish {
+        return Err(bad_request(
+            "must already be an owner to view the storage of a crate",
+        ));
+    }
+
+    let mut storage = EncodableCrateStorage {
+        krate: krate.name,
+        ..Default::default()
+    };
+    let mut versions = Vec::new();
+    for sizes in load_version_sizes(&conn, &[krate.id])? {
+        add_version(&mut storage, &sizes);
+        let (_, num, crate_file_bytes, readme_bytes, readme_source_bytes) = sizes;
+        versions.push(EncodableVersionStorage {
+            num,
+            crate_file_bytes,
+            readme_bytes,
+            readme_source_bytes,
+        });
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        storage: EncodableCrateStorage,
+        versions: Vec<EncodableVersionStorage>,
+    }
+    Ok(req.json(&R { storage, versions }))
+}
+
+/// Handles the `GET /me/storage` route.
+///
+/// Summarizes the storage used by each crate the user owns, along with the
+/// storage quota of the account if it has one, see the `quotas` module.
+pub fn summary(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+
+    let owned: Vec<(i32, String)> = CrateOwner::by_owner_kind(OwnerKind::User)
+        .inner_join(crates::table)
+        .filter(crate_owners::owner_id.eq(user_id))
+        .select((crates::id, crates::name))
+        .load(&*conn)?;
+    let crate_ids: Vec<i32> = owned.iter().map(|(id, _)| *id).collect();
+
+    let mut storage: BTreeMap<i32, EncodableCrateStorage> = owned
+        .into_iter()
+        .map(|(id, krate)| {
+            let storage = EncodableCrateStorage {
+                krate,
+                ..Default::default()
+            };
+            (id, storage)
+        })
+        .collect();
+    for sizes in load_version_sizes(&conn, &crate_ids)? {
+        if let Some(crate_storage) = storage.get_mut(&sizes.0) {
+            add_version(crate_storage, &sizes);
+        }
+    }
+
+    let mut crates: Vec<EncodableCrateStorage> = storage.into_iter().map(|(_, s)| s).collect();
+    crates.sort_by(|a, b| {
+        b.total_bytes
+            .cmp(&a.total_bytes)
+            .then(a.krate.cmp(&b.krate))
+    });
+    let total_bytes = crates.iter().map(|krate| krate.total_bytes).sum();
+    let quotas = req.app().config.quotas.for_user(&conn, user_id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableCrateStorage>,
+        total_bytes: i64,
+        quota_bytes: Option<i64>,
+    }
+    Ok(req.json(&R {
+        crates,
+        total_bytes,
+        quota_bytes: quotas.storage_bytes,
+    }))
+}
@@ -0,0 +1,151 @@
+//! The release history of a crate, annotated for version history pages
+
+use std::collections::HashSet;
+
+use crate::controllers::frontend_prelude::*;
+use crate::controllers::helpers::pagination::PaginationOptions;
+use crate::models::{
+    Advisory, Crate, QuarantineStatus, User, Version, VersionAction, VersionOwnerAction,
+};
+use crate::schema::{bulk_yank_versions, users, version_quarantines, versions};
+use crate::views::{EncodableTimelineEntry, EncodableTimelineYank};
+
+/// Handles the `GET /crates/:crate_id/timeline` route.
+///
+/// Lists the releases of a crate newest first, each with who yanked it and
+/// why, the advisories affecting it and whether it changed the
+/// `rust-version`. Supports keyset pagination with the `seek` parameter, see
+/// the `next_page` of the response.
+pub fn timeline(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let options = PaginationOptions::with_seek(req)?;
+    let name = &req.params()["crate_id"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+
+    // One more version than fits on the page is loaded, to tell whether the
+    // last version of the page changed the `rust-version`
+    let mut query = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .left_outer_join(users::table)
+        .select((versions::all_columns, users::all_columns.nullable()))
+        .order(versions::id.desc())
+        .limit(i64::from(options.per_page) + 1)
+        .into_boxed();
+    if let Some(last_id) = options.seek::<i32>()? {
+        query = query.filter(versions::id.lt(last_id));
+    }
+    if let Some(offset) = options.offset() {
+        query = query.offset(i64::from(offset));
+    }
+    let mut versions: Vec<(Version, Option<User>)> = query.load(&*conn)?;
+    let previous = if versions.len() > options.per_page as usize {
+        versions.pop().map(|(version, _)| version)
+    } else {
+        None
+    };
+    let total = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .count()
+        .get_result(&*conn)?;
+
+    let version_ids: Vec<i32> = versions.iter().map(|(v, _)| v.id).collect();
+    let mut advisories = Advisory::for_versions(&conn, &version_ids)?;
+    let mut actions = VersionOwnerAction::for_version_ids(&conn, &version_ids)?;
+    let yanked_ids: Vec<i32> = versions
+        .iter()
+        .filter(|(v, _)| v.yanked)
+        .map(|(v, _)| v.id)
+        .collect();
+    let bulk_yanked: HashSet<i32> = bulk_yank_versions::table
+        .filter(bulk_yank_versions::version_id.eq(any(&yanked_ids[..])))
+        .select(bulk_yank_versions::version_id)
+        .load::<i32>(&*conn)?
+        .into_iter()
+        .collect();
+    let rejected: HashSet<i32> = version_quarantines::table
+        .filter(version_quarantines::version_id.eq(any(&yanked_ids[..])))
+        .filter(version_quarantines::status.eq(QuarantineStatus::Rejected))
+        .select(version_quarantines::version_id)
+        .load::<i32>(&*conn)?
+        .into_iter()
+        .collect();
+
+    let last_id = versions.last().map(|(v, _)| v.id);
+    let next_page = options
+        .next_seek_params(versions.len(), last_id)
+        .map(|p| req.query_with_params(p));
+
+    // The release before each version is the next one in the list
+    let older_rust_versions: Vec<Option<Option<String>>> = versions
+        .iter()
+        .skip(1)
+        .map(|(v, _)| Some(v.rust_version.clone()))
+        .chain(std::iter::once(previous.map(|v| v.rust_version)))
+        .collect();
+
+    let timeline = versions
+        .into_iter()
+        .zip(older_rust_versions)
+        .map(|((version, published_by), older_rust_version)| {
+            let yank = if version.yanked {
+                let last_yank = actions
+                    .remove(&version.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|(action, _)| action.action == VersionAction::Yank)
+                    .last();
+                let reason = if rejected.contains(&version.id) {
+                    "quarantine"
+                } else if bulk_yanked.contains(&version.id) {
+                    "bulk_yank"
+                } else {
+                    "owner"
+                };
+                Some(EncodableTimelineYank {
+                    yanked_at: last_yank.as_ref().map(|(action, _)| action.time),
+                    yanked_by: last_yank.map(|(_, user)| user.into()),
+                    reason: reason.into(),
+                })
+            } else {
+                None
+            };
+            let rust_version_changed = match older_rust_version {
+                Some(older) => older != version.rust_version,
+                None => false,
+            };
+
+            EncodableTimelineEntry {
+                num: version.num.to_string(),
+                created_at: version.created_at,
+                published_by: published_by.map(User::into),
+                yanked: version.yanked,
+                yank,
+                advisories: advisories
+                    .remove(&version.id)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Advisory::into)
+                    .collect(),
+                rust_version: version.rust_version,
+                rust_version_changed,
+            }
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        timeline: Vec<EncodableTimelineEntry>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        total: i64,
+        next_page: Option<String>,
+    }
+    Ok(req.json(&R {
+        timeline,
+        meta: Meta { total, next_page },
+    }))
+}
@@ -0,0 +1,194 @@
+//! Endpoints for the licenses of crates
+//!
+//! Licenses are counted with the identifiers of their normalized expression,
+//! see the `license` module, so a crate licensed `MIT OR Apache-2.0` counts
+//! for both. Versions whose license couldn't be normalized are counted as
+//! unknown.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::cache::{CacheKey, CachedJson};
+use crate::controllers::frontend_prelude::*;
+use crate::models::{resolve_dependencies, Crate, DependencyKind, VersionLicense};
+use crate::schema::{crates, dependencies, version_licenses, versions};
+use crate::views::{EncodableDependencyLicense, EncodableLicenseUsage};
+
+/// How many crates of a dependency tree are resolved at most
+const MAX_TREE_CRATES: usize = 250;
+
+/// How many license expressions the registry-wide statistics list
+const TOP_EXPRESSIONS: usize = 20;
+
+/// The crate name, number, license and normalized license identifiers of a
+/// version
+type ResolvedVersion = (String, String, Option<String>, Option<Vec<String>>);
+
+/// Handles the `GET /crates/:crate_id/:version/licenses` route.
+///
+/// Resolves the dependency tree of the version and counts the crates of
+/// each license in it. Every dependency is resolved to its highest version
+/// that matches the requirement and isn't yanked, like in a new lockfile.
+/// Dev and optional dependencies are left out since they aren't built by
+/// default, and a crate that is depended on with different requirements is
+/// only resolved for the first one, breadth first.
+pub fn dependency_tree(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let name = &req.params()["crate_id"];
+    let num = &req.params()["version"];
+    let conn = req.db_read_only()?;
+    let krate: Crate = Crate::by_name(name).first(&*conn)?;
+    let version = krate.find_version(&conn, num)?;
+
+    let mut seen = HashSet::new();
+    seen.insert(krate.id);
+    let mut tree: Vec<(i32, u32)> = Vec::new();
+    let mut level = vec![version.id];
+    let mut depth = 0;
+    let mut truncated = false;
+    while !level.is_empty() && !truncated {
+        depth += 1;
+        let mut deps: Vec<(i32, String)> = dependencies::table
+            .filter(dependencies::version_id.eq(any(&level[..])))
+            .filter(dependencies::optional.eq(false))
+            .filter(dependencies::kind.ne(DependencyKind::Dev as i32))
+            .select((dependencies::crate_id, dependencies::req))
+            .order(dependencies::id)
+            .load(&*conn)?;
+        deps.retain(|(crate_id, _)| seen.insert(*crate_id));
+        if tree.len() + deps.len() > MAX_TREE_CRATES {
+            deps.truncate(MAX_TREE_CRATES - tree.len());
+            truncated = true;
+        }
+
+        level = resolve_dependencies(&conn, &deps)?
+            .into_iter()
+            .flatten()
+            .collect();
+        tree.extend(level.iter().map(|version_id| (*version_id, depth)));
+    }
+
+    let version_ids: Vec<i32> = tree.iter().map(|(version_id, _)| *version_id).collect();
+    let mut resolved: HashMap<i32, ResolvedVersion> = versions::table
+        .inner_join(crates::table)
+        .left_join(version_licenses::table)
+        .filter(versions::id.eq(any(&version_ids[..])))
+        .select((
+            versions::id,
+            crates::name,
+            versions::num,
+            versions::license,
+            version_licenses::licenses.nullable(),
+        ))
+        .load::<(i32, String, String, Option<String>, Option<Vec<String>>)>(&*conn)?
+        .into_iter()
+        .map(|(version_id, krate, num, license, licenses)| {
+            (version_id, (krate, num, license, licenses))
+        })
+        .collect();
+
+    let mut counts: HashMap<String, i64> = HashMap::new();
+    let mut unknown = 0;
+    let mut crates = Vec::with_capacity(tree.len());
+    for (version_id, depth) in tree {
+        let (name, num, license, licenses) = match resolved.remove(&version_id) {
+            Some(resolved) => resolved,
+            None => continue,
+        };
+        let licenses = licenses.unwrap_or_default();
+        if licenses.is_empty() {
+            unknown += 1;
+        }
+        for license in &licenses {
+            *counts.entry(license.clone()).or_default() += 1;
+        }
+        crates.push(EncodableDependencyLicense {
+            krate: name,
+            version: num,
+            license,
+            licenses,
+            depth,
+        });
+    }
+
+    #[derive(Serialize)]
+    struct R {
+        licenses: Vec<EncodableLicenseUsage>,
+        unknown: i64,
+        crates: Vec<EncodableDependencyLicense>,
+        truncated: bool,
+    }
+    Ok(req.json(&R {
+        licenses: sorted_usage(counts),
+        unknown,
+        crates,
+        truncated,
+    }))
+}
+
+fn sorted_usage(counts: HashMap<String, i64>) -> Vec<EncodableLicenseUsage> {
+    let mut usage: Vec<EncodableLicenseUsage> = counts
+        .into_iter()
+        .map(|(license, crates)| EncodableLicenseUsage { license, crates })
+        .collect();
+    usage.sort_by(|a, b| b.crates.cmp(&a.crates).then(a.license.cmp(&b.license)));
+    usage
+}
+
+/// Handles the `GET /licenses` route.
+///
+/// Counts the crates of each license and of the most common expressions in
+/// the newest version of every crate that isn't yanked.
+pub fn stats(req: &mut dyn RequestExt) -> EndpointResult {
+    let json = req
+        .app()
+        .response_cache
+        .get_or_insert_with(CacheKey::Licenses, || {
+            let conn = req.db_read_only()?;
+            stats_json(&conn)
+        })?;
+
+    let mut response = json.to_response();
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("public, max-age=300"),
+    );
+    Ok(response)
+}
+
+fn stats_json(conn: &PgConnection) -> AppResult<CachedJson> {
+    let licenses = VersionLicense::license_usage(conn)?
+        .into_iter()
+        .filter_map(|usage| {
+            Some(EncodableLicenseUsage {
+                license: usage.license?,
+                crates: usage.crates,
+            })
+        })
+        .collect();
+
+    let mut unknown = 0;
+    let mut expressions = Vec::new();
+    for usage in VersionLicense::expression_usage(conn)? {
+        match usage.license {
+            Some(license) => expressions.push(EncodableLicenseUsage {
+                license,
+                crates: usage.crates,
+            }),
+            None => unknown = usage.crates,
+        }
+    }
+    expressions.truncate(TOP_EXPRESSIONS);
+
+    #[derive(Serialize)]
+    struct R {
+        licenses: Vec<EncodableLicenseUsage>,
+        expressions: Vec<EncodableLicenseUsage>,
+        unknown: i64,
+    }
+    Ok(CachedJson::new(&R {
+        licenses,
+        expressions,
+        unknown,
+    }))
+}
@@ -0,0 +1,43 @@
+use super::prelude::*;
+
+use prometheus::{Encoder, TextEncoder};
+
+use crate::util::errors::{forbidden, not_found};
+
+/// Handles the `GET /api/private/metrics/:kind` route.
+///
+/// Returns the `service` or `instance` metrics in the Prometheus text
+/// format. Scrapers need to send the `METRICS_AUTHORIZATION_TOKEN` as a
+/// bearer token, and the route doesn't exist if the token isn't configured.
+pub fn prometheus(req: &mut dyn RequestExt) -> EndpointResult {
+    let app = req.app().clone();
+
+    let expected_token = match &app.config.metrics_authorization_token {
+        Some(token) => token,
+        None => return Err(not_found()),
+    };
+    let provided_token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if provided_token != Some(expected_token.as_str()) {
+        return Err(forbidden());
+    }
+
+    let metrics = match &*req.params()["kind"] {
+        "service" => app.service_metrics.gather(&*req.db_read_only()?)?,
+        "instance" => app.instance_metrics.gather(&app),
+        _ => return Err(not_found()),
+    };
+
+    let mut output = Vec::new();
+    let encoder = TextEncoder::new();
+    encoder.encode(&metrics, &mut output)?;
+
+    Ok(conduit::Response::builder()
+        .header(header::CONTENT_TYPE, encoder.format_type())
+        .header(header::CONTENT_LENGTH, output.len())
+        .body(conduit::Body::from_vec(output))
+        .unwrap())
+}
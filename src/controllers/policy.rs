@@ -0,0 +1,170 @@
+//! The publishing policy of the registry as a JSON document
+//!
+//! Publishing tools can check a crate against the policy before uploading
+//! it, instead of relying on the wording of the errors. Everything in the
+//! document is read from the constants and the configuration the publish
+//! endpoint checks against, so the two can't disagree.
+
+use crate::controllers::frontend_prelude::*;
+use crate::license::InvalidLicensePolicy;
+use crate::models::krate::MAX_NAME_LENGTH;
+use crate::models::ReservedCrateName;
+use crate::views::krate_publish::{MAX_CATEGORIES, MAX_KEYWORDS, MAX_KEYWORD_LENGTH};
+
+#[derive(Serialize)]
+struct Policy {
+    crate_names: NamePolicy,
+    keywords: KeywordPolicy,
+    categories: CategoryPolicy,
+    features: FeaturePolicy,
+    uploads: UploadPolicy,
+    rate_limits: RateLimitPolicy,
+    quotas: QuotaPolicy,
+    licenses: LicensePolicy,
+}
+
+#[derive(Serialize)]
+struct NamePolicy {
+    max_length: usize,
+    pattern: &'static str,
+    /// Names that only differ in case, or in `-` and `_`, are the same crate
+    canonicalization: &'static str,
+    /// A `*` in a reserved name matches any sequence of characters
+    reserved: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct KeywordPolicy {
+    max_per_crate: usize,
+    max_length: usize,
+    pattern: &'static str,
+}
+
+#[derive(Serialize)]
+struct CategoryPolicy {
+    max_per_crate: usize,
+    /// The categories must be slugs of `GET /api/v1/category_slugs`
+    slugs_url: &'static str,
+}
+
+#[derive(Serialize)]
+struct FeaturePolicy {
+    name_pattern: &'static str,
+}
+
+#[derive(Serialize)]
+struct UploadPolicy {
+    /// The limit of the compressed crate file, unless raised for a crate
+    max_upload_size: u64,
+    /// The limit of the crate file when decompressed, unless raised for a
+    /// crate
+    max_unpack_size: u64,
+    /// The storage an account may use for the crates it owns, if limited,
+    /// like `QuotaPolicy::storage_bytes`
+    storage_quota: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct RateLimitPolicy {
+    new_crates: PublishRateLimitPolicy,
+    /// Whether new versions by new accounts are held before they are added
+    /// to the index
+    first_publish_hold: Option<FirstPublishHoldPolicy>,
+}
+
+/// New crates of a user are limited by a token bucket, with room for `burst`
+/// crates and refilled by one crate every `refill_seconds`
+#[derive(Serialize)]
+struct PublishRateLimitPolicy {
+    burst: i32,
+    refill_seconds: u64,
+}
+
+#[derive(Serialize)]
+struct FirstPublishHoldPolicy {
+    account_age_seconds: u64,
+    /// `None` if held versions are only released by an admin
+    window_seconds: Option<u64>,
+}
+
+/// The quotas of accounts, which are unlimited if they are `None`. An admin
+/// may have changed them for an account, as shown at `GET /api/v1/me/quotas`.
+#[derive(Serialize)]
+struct QuotaPolicy {
+    storage_bytes: Option<i64>,
+    versions_per_day: Option<i64>,
+    crates: Option<i64>,
+}
+
+#[derive(Serialize)]
+struct LicensePolicy {
+    /// Whether versions with a license that isn't a valid SPDX expression
+    /// are rejected, or published with a warning
+    invalid_license: &'static str,
+}
+
+/// Handles the `GET /policy` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let reserved = ReservedCrateName::all(&conn)?
+        .into_iter()
+        .map(|reserved| reserved.name)
+        .collect();
+    let config = &req.app().config;
+
+    let policy = Policy {
+        crate_names: NamePolicy {
+            max_length: MAX_NAME_LENGTH,
+            pattern: "^[A-Za-z][A-Za-z0-9_-]*$",
+            canonicalization: "lowercase, with `-` replaced by `_`",
+            reserved,
+        },
+        keywords: KeywordPolicy {
+            max_per_crate: MAX_KEYWORDS,
+            max_length: MAX_KEYWORD_LENGTH,
+            pattern: "^[A-Za-z0-9][A-Za-z0-9_-]*$",
+        },
+        categories: CategoryPolicy {
+            max_per_crate: MAX_CATEGORIES,
+            slugs_url: "/api/v1/category_slugs",
+        },
+        features: FeaturePolicy {
+            name_pattern: "^[A-Za-z0-9_+-]+$",
+        },
+        uploads: UploadPolicy {
+            max_upload_size: config.max_upload_size,
+            max_unpack_size: config.max_unpack_size,
+            storage_quota: config.quotas.storage_bytes,
+        },
+        rate_limits: RateLimitPolicy {
+            new_crates: PublishRateLimitPolicy {
+                burst: config.publish_rate_limit.burst,
+                refill_seconds: config.publish_rate_limit.rate.as_secs(),
+            },
+            first_publish_hold: config
+                .first_publish_hold
+                .map(|hold| FirstPublishHoldPolicy {
+                    account_age_seconds: hold.account_age.as_secs(),
+                    window_seconds: hold.window.map(|window| window.as_secs()),
+                }),
+        },
+        quotas: QuotaPolicy {
+            storage_bytes: config.quotas.storage_bytes,
+            versions_per_day: config.quotas.versions_per_day,
+            crates: config.quotas.crates,
+        },
+        licenses: LicensePolicy {
+            invalid_license: match config.invalid_license_policy {
+                InvalidLicensePolicy::Reject => "reject",
+                InvalidLicensePolicy::Warn => "warn",
+            },
+        },
+    };
+
+    let mut response = req.json(&policy);
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        header::HeaderValue::from_static("public, max-age=300"),
+    );
+    Ok(response)
+}
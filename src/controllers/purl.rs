@@ -0,0 +1,212 @@
+//! Resolving [package URLs] of crates, for SBOM tools and scanners that
+//! identify packages by purl
+//!
+//! Crates have purls like `pkg:cargo/serde@1.0.0`. Cargo purls have no
+//! namespace, and qualifiers and subpaths are ignored.
+//!
+//! [package URLs]: https://github.com/package-url/purl-spec
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, CrateVersions, Version, VersionOwnerAction};
+use crate::schema::versions;
+use crate::views::{EncodableCrate, EncodableVersion};
+
+/// How many purls can be resolved at once
+const MAX_BATCH_PURLS: usize = 100;
+
+/// The crate name and version a purl refers to
+#[derive(Debug, PartialEq, Eq)]
+struct CargoPurl {
+    name: String,
+    version: Option<String>,
+}
+
+fn parse_purl(purl: &str) -> Result<CargoPurl, String> {
+    let invalid = || {
+        format!(
+            "`{}` is not a cargo purl like `pkg:cargo/name@version`",
+            purl
+        )
+    };
+
+    let scheme = purl
+        .get(..4)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("pkg:"));
+    let rest = scheme.map(|_| &purl[4..]).ok_or_else(invalid)?;
+    let rest = rest.trim_start_matches('/');
+    let rest = rest.split('#').next().unwrap_or_default();
+    let rest = rest.split('?').next().unwrap_or_default();
+
+    let (path, version) = match rest.rfind('@') {
+        Some(at) => (
+            &rest[..at],
+            Some(percent_decode(&rest[at + 1..]).ok_or_else(invalid)?),
+        ),
+        None => (rest, None),
+    };
+    let mut segments = path.trim_end_matches('/').split('/');
+    let kind = segments.next().unwrap_or_default();
+    let name = segments.next().ok_or_else(invalid)?;
+    if !kind.eq_ignore_ascii_case("cargo") || segments.next().is_some() {
+        return Err(invalid());
+    }
+
+    let name = percent_decode(name).ok_or_else(invalid)?;
+    if !Crate::valid_name(&name) || version.as_deref() == Some("") {
+        return Err(invalid());
+    }
+    Ok(CargoPurl { name, version })
+}
+
+/// Decodes the percent-encoded characters of a purl component, `None` if
+/// they don't decode to UTF-8
+fn percent_decode(component: &str) -> Option<String> {
+    let bytes = component.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = component.get(i + 1..i + 3)?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[derive(Serialize)]
+struct Resolution {
+    purl: String,
+    #[serde(rename = "crate")]
+    krate: Option<EncodableCrate>,
+    /// Only set if the purl has a version
+    version: Option<EncodableVersion>,
+    /// Why the purl couldn't be resolved
+    error: Option<String>,
+}
+
+fn resolve(conn: &PgConnection, purl: &str) -> AppResult<Resolution> {
+    let parsed = parse_purl(purl).map_err(|e| bad_request(&e))?;
+    let krate: Crate = Crate::by_name(&parsed.name).first(conn)?;
+    let version = match &parsed.version {
+        Some(num) => {
+            let version: Version = krate
+                .all_versions()
+                .filter(versions::num.eq(num))
+                .first(conn)?;
+            let published_by = version.published_by(conn);
+            let actions = VersionOwnerAction::by_version(conn, &version)?;
+            Some(EncodableVersion::from(
+                version,
+                &krate.name,
+                published_by,
+                actions,
+            ))
+        }
+        None => None,
+    };
+    let top_versions = krate.top_versions(conn)?;
+
+    Ok(Resolution {
+        purl: purl.to_string(),
+        krate: Some(EncodableCrate::from_minimal(
+            krate,
+            &top_versions,
+            None,
+            false,
+            None,
+        )),
+        version,
+        error: None,
+    })
+}
+
+/// Handles the `GET /purl` route.
+///
+/// Resolves the purl of the `purl` query parameter to the crate, and the
+/// version if it has one.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let purl = req
+        .query()
+        .get("purl")
+        .cloned()
+        .ok_or_else(|| bad_request("missing the `purl` query parameter"))?;
+    let conn = req.db_read_only()?;
+    let resolution = resolve(&conn, &purl)?;
+    Ok(req.json(&resolution))
+}
+
+/// Handles the `GET /purls` route.
+///
+/// Resolves the purls of the `purls[]` query parameters at once. Purls that
+/// can't be resolved are returned with the error instead of failing the
+/// request.
+pub fn batch(req: &mut dyn RequestExt) -> EndpointResult {
+    let query = url::form_urlencoded::parse(req.query_string().unwrap_or("").as_bytes());
+    let purls: Vec<String> = query
+        .filter(|(key, _)| key == "purls[]")
+        .map(|(_, value)| value.into_owned())
+        .collect();
+    if purls.len() > MAX_BATCH_PURLS {
+        return Err(bad_request(&format_args!(
+            "at most {} purls can be resolved at once",
+            MAX_BATCH_PURLS
+        )));
+    }
+
+    let conn = req.db_read_only()?;
+    let results = purls
+        .into_iter()
+        .map(|purl| {
+            resolve(&conn, &purl).unwrap_or_else(|e| Resolution {
+                purl,
+                krate: None,
+                version: None,
+                error: Some(e.to_string()),
+            })
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        results: Vec<Resolution>,
+    }
+    Ok(req.json(&R { results }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_purl, CargoPurl};
+
+    fn purl(name: &str, version: Option<&str>) -> CargoPurl {
+        CargoPurl {
+            name: name.into(),
+            version: version.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn parses_cargo_purls() {
+        assert_eq!(
+            parse_purl("pkg:cargo/serde@1.0.0"),
+            Ok(purl("serde", Some("1.0.0")))
+        );
+        assert_eq!(parse_purl("pkg:cargo/serde"), Ok(purl("serde", None)));
+        assert_eq!(
+            parse_purl("PKG:Cargo/rand_core@0.6.0-pre.1%2Bbuild?arch=x86#src"),
+            Ok(purl("rand_core", Some("0.6.0-pre.1+build")))
+        );
+    }
+
+    #[test]
+    fn rejects_other_purls() {
+        assert!(parse_purl("pkg:npm/left-pad@1.0.0").is_err());
+        assert!(parse_purl("pkg:cargo/namespace/serde@1.0.0").is_err());
+        assert!(parse_purl("cargo/serde@1.0.0").is_err());
+        assert!(parse_purl("pkg:cargo/serde@").is_err());
+        assert!(parse_purl("pkg:cargo/%ZZ").is_err());
+    }
+}
@@ -1,20 +1,49 @@
+use chrono::NaiveDateTime;
+
 use super::prelude::*;
+use crate::util::rfc3339;
 
-/// Returns the JSON representation of the current deployed commit sha.
+/// Returns the JSON representation of the current deployed commit sha, and
+/// whether crates.io currently accepts changes.
 ///
 /// The sha is contained within the `HEROKU_SLUG_COMMIT` environment variable.
 /// If `HEROKU_SLUG_COMMIT` is not set, returns `"unknown"`.
+///
+/// `read_only` is set while the database is read-only or admins turned on
+/// the maintenance mode, which is described by `maintenance`. Requests that
+/// change data fail with a `503 Service Unavailable` response during that
+/// time.
 pub fn show_deployed_sha(req: &mut dyn RequestExt) -> EndpointResult {
     let deployed_sha =
         dotenv::var("HEROKU_SLUG_COMMIT").unwrap_or_else(|_| String::from("unknown"));
 
+    let app = req.app();
+    let maintenance = app
+        .maintenance_mode
+        .current(&app.primary_database)?
+        .map(|maintenance| Maintenance {
+            message: maintenance.message,
+            started_at: maintenance.started_at,
+        });
+    let read_only = app.maintenance_mode.is_read_only() || maintenance.is_some();
+
+    #[derive(Serialize)]
+    struct Maintenance {
+        message: Option<String>,
+        #[serde(with = "rfc3339")]
+        started_at: NaiveDateTime,
+    }
     #[derive(Serialize)]
     struct R<'a> {
         deployed_sha: &'a str,
         commit: &'a str,
+        read_only: bool,
+        maintenance: Option<Maintenance>,
     }
     Ok(req.json(&R {
         deployed_sha: &deployed_sha[..],
         commit: &deployed_sha[..],
+        read_only,
+        maintenance,
     }))
 }
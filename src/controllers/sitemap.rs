@@ -0,0 +1,54 @@
+//! The sitemaps and `robots.txt` for search engines
+//!
+//! The sitemaps are generated once a day by the `generate_sitemaps`
+//! background job, and served from the database.
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::MaterializedResponse;
+use crate::util::errors::not_found;
+
+/// Handles the `GET /robots.txt` route.
+pub fn robots(req: &mut dyn RequestExt) -> EndpointResult {
+    let body = format!(
+        "# http://www.robotstxt.org\n\
+        User-agent: *\n\
+        Disallow:\n\
+        \n\
+        Sitemap: https://{}/sitemap.xml\n",
+        req.app().config.domain_name
+    );
+    Ok(conduit::Response::builder()
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .header(header::CACHE_CONTROL, "public, max-age=86400")
+        .body(conduit::Body::from_vec(body.into_bytes()))
+        .unwrap())
+}
+
+/// Handles the `GET /sitemap.xml` route.
+pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
+    serve(req, MaterializedResponse::SITEMAP_INDEX)
+}
+
+/// Handles the `GET /sitemaps/:name` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let name = format!(
+        "{}{}",
+        MaterializedResponse::SITEMAP_PREFIX,
+        req.params()["name"]
+    );
+    serve(req, &name)
+}
+
+fn serve(req: &mut dyn RequestExt, name: &str) -> EndpointResult {
+    let conn = req.db_read_only()?;
+    let sitemap = MaterializedResponse::find(&conn, name)?.ok_or_else(not_found)?;
+    let last_modified = sitemap.refreshed_at.format("%a, %d %b %Y %H:%M:%S GMT");
+
+    Ok(conduit::Response::builder()
+        .header(header::CONTENT_TYPE, "application/xml; charset=utf-8")
+        .header(header::CACHE_CONTROL, "public, max-age=3600")
+        .header(header::LAST_MODIFIED, last_modified.to_string())
+        .header(header::CONTENT_LENGTH, sitemap.body.len())
+        .body(conduit::Body::from_vec(sitemap.body))
+        .unwrap())
+}
@@ -1,3 +1,10 @@
+pub mod data_export;
+pub mod follow;
 pub mod me;
+pub mod mutes;
+pub mod notifications;
 pub mod other;
+pub mod profile;
+pub mod quotas;
 pub mod session;
+pub mod username;
@@ -0,0 +1,88 @@
+//! Endpoints for exporting the data of the current user
+
+use crate::background_jobs::Job;
+use crate::controllers::frontend_prelude::*;
+use crate::models::DataExport;
+use crate::util::errors::not_found;
+use crate::views::EncodableDataExport;
+
+/// Handles the `GET /me/data_export` route.
+///
+/// Returns the state of the most recently requested export. Once it has
+/// completed, the archive can be downloaded from `download_path`.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let data_export = DataExport::latest_for_user(&conn, user_id)?;
+
+    respond(req, data_export, "/api/v1/me/data_export/download")
+}
+
+/// Handles the `PUT /me/data_export` route.
+///
+/// Starts building a new export in the background, replacing the previous
+/// one. If an export is already in progress it is returned instead.
+pub fn request(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let data_export = request_export(&conn, user_id)?;
+
+    respond(req, Some(data_export), "/api/v1/me/data_export/download")
+}
+
+/// Handles the `GET /me/data_export/download` route.
+pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let _permit = req.db_budget(EndpointFamily::Dumps)?;
+    let conn = req.db_conn()?;
+
+    download_archive(&conn, user_id)
+}
+
+pub(crate) fn request_export(conn: &PgConnection, user_id: i32) -> AppResult<DataExport> {
+    conn.transaction(|| {
+        let (data_export, created) = DataExport::request(conn, user_id)?;
+        if created {
+            Job::ExportUserData {
+                data_export_id: data_export.id,
+            }
+            .enqueue(conn)?;
+        }
+        Ok(data_export)
+    })
+}
+
+pub(crate) fn download_archive(conn: &PgConnection, user_id: i32) -> EndpointResult {
+    let archive = match DataExport::latest_for_user(conn, user_id)? {
+        Some(data_export) => data_export.archive(conn)?,
+        None => None,
+    };
+    let archive = archive.ok_or_else(not_found)?;
+
+    Ok(conduit::Response::builder()
+        .header(header::CONTENT_TYPE, "application/gzip")
+        .header(
+            header::CONTENT_DISPOSITION,
+            "attachment; filename=\"crates-io-data-export.tar.gz\"",
+        )
+        .header(header::CONTENT_LENGTH, archive.len())
+        .body(conduit::Body::from_vec(archive))
+        .unwrap())
+}
+
+pub(crate) fn respond(
+    req: &dyn RequestExt,
+    data_export: Option<DataExport>,
+    download_path: &str,
+) -> EndpointResult {
+    #[derive(Serialize)]
+    struct R {
+        data_export: Option<EncodableDataExport>,
+    }
+    Ok(req.json(&R {
+        data_export: data_export.map(|data_export| {
+            let download_path = data_export.completed_at.map(|_| download_path.to_string());
+            EncodableDataExport::from(data_export, download_path)
+        }),
+    }))
+}
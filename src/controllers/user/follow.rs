@@ -0,0 +1,110 @@
+//! Endpoints for following other users, to see the versions they publish in
+//! `GET /me/updates`
+//!
+//! Users can opt out of being followed with `PUT /me/privacy`, which also
+//! removes their current followers.
+
+use diesel::associations::Identifiable;
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{User, UserFollow};
+use crate::schema::{user_follows, users};
+
+/// Finds the user of the `:user_id` route parameter, which is the login
+fn find_user(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<User> {
+    Ok(User::find_by_login(conn, &req.params()["user_id"])?)
+}
+
+/// Handles the `PUT /users/:user_id/follow` route.
+pub fn follow(req: &mut dyn RequestExt) -> EndpointResult {
+    let follower_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let followed = find_user(req, &conn)?;
+    if followed.id == follower_id {
+        return Err(bad_request("you can't follow yourself"));
+    }
+    if !followed.followable {
+        return Err(bad_request("this user can't be followed"));
+    }
+
+    let follow = UserFollow {
+        follower_id,
+        followed_id: followed.id,
+    };
+    diesel::insert_into(user_follows::table)
+        .values(&follow)
+        .on_conflict_do_nothing()
+        .execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Handles the `DELETE /users/:user_id/follow` route.
+pub fn unfollow(req: &mut dyn RequestExt) -> EndpointResult {
+    let follower_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let followed = find_user(req, &conn)?;
+    let follow = UserFollow {
+        follower_id,
+        followed_id: followed.id,
+    };
+    diesel::delete(&follow).execute(&*conn)?;
+
+    ok_true()
+}
+
+/// Handles the `GET /users/:user_id/following` route.
+pub fn following(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::exists;
+
+    let follower_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+    let followed = find_user(req, &conn)?;
+    let follow = UserFollow {
+        follower_id,
+        followed_id: followed.id,
+    };
+    let following =
+        diesel::select(exists(user_follows::table.find(follow.id()))).get_result(&*conn)?;
+
+    #[derive(Serialize)]
+    struct R {
+        following: bool,
+        followable: bool,
+    }
+    Ok(req.json(&R {
+        following,
+        followable: followed.followable,
+    }))
+}
+
+/// Handles the `PUT /me/privacy` route.
+///
+/// With `{"followable": false}` other users can no longer follow the current
+/// user, and their existing follows are removed.
+pub fn update_privacy(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Privacy {
+        followable: bool,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let privacy: Privacy =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+    conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::update(&user)
+            .set(users::followable.eq(privacy.followable))
+            .execute(&*conn)?;
+        if !privacy.followable {
+            UserFollow::remove_followers(&conn, user.id)?;
+        }
+        Ok(())
+    })?;
+
+    ok_true()
+}
@@ -1,16 +1,26 @@
+use chrono::{NaiveDateTime, Utc};
 use std::collections::HashMap;
 
+use conduit_cookie::RequestSession;
+
 use crate::controllers::frontend_prelude::*;
 
 use crate::controllers::helpers::*;
 use crate::email;
+use crate::util::errors::custom;
 
 use crate::controllers::helpers::pagination::Paginated;
+use crate::controllers::user::profile::ProfileUpdate;
+use crate::controllers::version::encode_versions;
 use crate::models::{
-    CrateOwner, Email, Follow, NewEmail, OwnerKind, User, Version, VersionOwnerAction,
+    CrateOwner, Email, EmailChange, EmailPreferences, Follow, NewEmail, OwnerKind, ProjectLink,
+    ProjectLinkKind, User, UserFollow, Version,
+};
+use crate::schema::{crate_owners, crates, emails, project_links, users, versions};
+use crate::views::{
+    EncodableEmailPreferences, EncodableFundedDependency, EncodableMe, EncodablePrivateUser,
+    EncodableVersion, OwnedCrate,
 };
-use crate::schema::{crate_owners, crates, emails, follows, users, versions};
-use crate::views::{EncodableMe, EncodablePrivateUser, EncodableVersion, OwnedCrate};
 
 /// Handles the `GET /me` route.
 pub fn me(req: &mut dyn RequestExt) -> EndpointResult {
@@ -43,14 +53,60 @@ pub fn me(req: &mut dyn RequestExt) -> EndpointResult {
         })
         .collect();
 
+    let pending_email = EmailChange::pending_for(&conn, user_id)?.map(|change| change.email);
+
     let verified = verified.unwrap_or(false);
     let verification_sent = verified || verification_sent;
     Ok(req.json(&EncodableMe {
         user: EncodablePrivateUser::from(user, email, verified, verification_sent),
         owned_crates,
+        pending_email,
     }))
 }
 
+/// Handles the `DELETE /me` route.
+///
+/// Deletes the personal data of the current user, see `User::anonymize`. The
+/// login has to be repeated in the body as `{"login": "..."}` to confirm, and
+/// API tokens can't be used. Accounts that are the only owner of a crate can't
+/// be deleted until another owner has been added.
+pub fn delete(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    if authenticated_user.api_token_id().is_some() {
+        return Err(bad_request("accounts can't be deleted using an API token"));
+    }
+    let user = authenticated_user.user();
+
+    #[derive(Deserialize)]
+    struct DeleteRequest {
+        login: String,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let delete_request: DeleteRequest =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    if delete_request.login != user.login() {
+        return Err(bad_request("the login does not match the current user"));
+    }
+
+    let conn = req.db_conn()?;
+    let sole_owned_crates = user.sole_owned_crates(&conn)?;
+    if !sole_owned_crates.is_empty() {
+        return Err(bad_request(&format_args!(
+            "you are the only owner of the following crates, please add another owner \
+             before deleting your account: {}",
+            sole_owned_crates.join(", ")
+        )));
+    }
+
+    user.anonymize(&conn)?;
+    drop(conn);
+    req.session_mut().clear();
+
+    ok_true()
+}
+
 /// Handles the `GET /me/updates` route.
 pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
@@ -58,11 +114,14 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     let authenticated_user = req.authenticate()?;
     let user = authenticated_user.user();
 
-    let followed_crates = Follow::belonging_to(&user).select(follows::crate_id);
     let query = versions::table
         .inner_join(crates::table)
         .left_outer_join(users::table)
-        .filter(crates::id.eq(any(followed_crates)))
+        .filter(
+            crates::id
+                .eq(any(Follow::crate_ids_of(user.id)))
+                .or(users::id.eq(any(UserFollow::followed_ids_of(user.id)))),
+        )
         .order(versions::created_at.desc())
         .select((
             versions::all_columns,
@@ -73,19 +132,7 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     let conn = req.db_conn()?;
     let data: Paginated<(Version, String, Option<User>)> = query.load(&*conn)?;
     let more = data.next_page_params().is_some();
-    let versions = data.iter().map(|(v, _, _)| v).cloned().collect::<Vec<_>>();
-    let data = data
-        .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((v, cn, pb), voas)| (v, cn, pb, voas))
-        .collect::<Vec<_>>();
-
-    let versions = data
-        .into_iter()
-        .map(|(version, crate_name, published_by, actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
-        })
-        .collect();
+    let versions = encode_versions(&conn, data.into_iter().collect())?;
 
     #[derive(Serialize)]
     struct R {
@@ -102,6 +149,54 @@ pub fn updates(req: &mut dyn RequestExt) -> EndpointResult {
     }))
 }
 
+/// Handles the `GET /me/funding` route.
+///
+/// Lists the crates that the crates owned or followed by the user depend on,
+/// and that accept funding, with the crates of the user depending on them.
+pub fn funding(req: &mut dyn RequestExt) -> EndpointResult {
+    use diesel::dsl::any;
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+
+    let dependencies = ProjectLink::funded_dependencies(&conn, user_id)?;
+    let crate_ids: Vec<i32> = dependencies.iter().map(|dep| dep.crate_id).collect();
+    let names: HashMap<i32, String> = crates::table
+        .filter(crates::id.eq(any(&crate_ids)))
+        .select((crates::id, crates::name))
+        .load::<(i32, String)>(&*conn)?
+        .into_iter()
+        .collect();
+    let mut links: HashMap<i32, Vec<ProjectLink>> = HashMap::new();
+    for link in project_links::table
+        .filter(project_links::crate_id.eq(any(&crate_ids)))
+        .filter(project_links::kind.eq(ProjectLinkKind::Funding))
+        .order(project_links::id)
+        .load::<ProjectLink>(&*conn)?
+    {
+        links.entry(link.crate_id).or_default().push(link);
+    }
+
+    let crates = dependencies
+        .into_iter()
+        .filter_map(|dep| {
+            let name = names.get(&dep.crate_id)?.clone();
+            let funding = links.remove(&dep.crate_id).unwrap_or_default();
+            Some(EncodableFundedDependency {
+                krate: name,
+                funding: funding.into_iter().map(ProjectLink::into).collect(),
+                dependents: dep.dependents,
+            })
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<EncodableFundedDependency>,
+    }
+    Ok(req.json(&R { crates }))
+}
+
 /// Handles the `PUT /users/:user_id` route.
 pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
     use self::emails::user_id;
@@ -129,21 +224,40 @@ pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
     #[derive(Deserialize)]
     struct User {
         email: Option<String>,
+        #[serde(flatten)]
+        profile: ProfileUpdate,
     }
 
     let user_update: UserUpdate =
         serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    let profile = user_update.user.profile.into_changes()?;
 
+    // Requests only need an email address if they don't change the profile
     let user_email = match &user_update.user.email {
-        Some(email) => email.trim(),
-        None => return Err(bad_request("empty email rejected")),
+        Some(email) => Some(email.trim()),
+        None if profile.is_empty() => return Err(bad_request("empty email rejected")),
+        None => None,
     };
 
-    if user_email.is_empty() {
+    if user_email == Some("") {
         return Err(bad_request("empty email rejected"));
     }
 
     conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        if !profile.is_empty() {
+            diesel::update(&user).set(&profile).execute(&*conn)?;
+        }
+
+        let user_email = match user_email {
+            Some(user_email) => user_email,
+            None => return Ok(()),
+        };
+
+        let current: Option<Email> = Email::belonging_to(&user).first(&*conn).optional()?;
+        if let Some(current) = current.filter(|current| current.verified) {
+            return request_email_change(&conn, &user, &current, user_email);
+        }
+
         let new_email = NewEmail {
             user_id: user.id,
             email: user_email,
@@ -158,7 +272,7 @@ pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
             .get_result(&*conn)
             .map_err(|_| server_error("Error in creating token"))?;
 
-        crate::email::send_user_confirm_email(user_email, &user.gh_login, &token);
+        crate::email::send_user_confirm_email(&conn, user_email, &user.gh_login, &token);
 
         Ok(())
     })?;
@@ -166,20 +280,81 @@ pub fn update_user(req: &mut dyn RequestExt) -> EndpointResult {
     ok_true()
 }
 
+/// Starts changing a verified address, which keeps being used until both the
+/// old and the new address have been confirmed. Setting the current address
+/// again cancels a pending change.
+fn request_email_change(
+    conn: &PgConnection,
+    user: &User,
+    current: &Email,
+    new_email: &str,
+) -> AppResult<()> {
+    if current.email == new_email {
+        return Ok(EmailChange::cancel(conn, user.id)?);
+    }
+
+    let now = Utc::now().naive_utc();
+    let pending = EmailChange::pending_for(conn, user.id)?;
+    if let Some(next) = pending.and_then(|pending| pending.next_request_at(now)) {
+        return Err(resend_too_soon(next));
+    }
+
+    let change = EmailChange::request(conn, user.id, new_email)?;
+    email::send_email_change_emails(
+        conn,
+        &current.email,
+        new_email,
+        &user.gh_login,
+        &change.old_email_token,
+        &change.new_email_token,
+    );
+    Ok(())
+}
+
+fn resend_too_soon(next: NaiveDateTime) -> Box<dyn AppError> {
+    let detail = format!(
+        "A confirmation email was sent recently. Please try again after {} UTC.",
+        next.format("%Y-%m-%d %H:%M:%S")
+    );
+    custom(StatusCode::TOO_MANY_REQUESTS, &detail)
+}
+
 /// Handles the `PUT /confirm/:email_token` route
 pub fn confirm_user_email(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::update;
 
     let conn = req.db_conn()?;
     let req_token = &req.params()["email_token"];
+    let now = Utc::now().naive_utc();
+    let expired = || bad_request("This confirmation link has expired. Please request a new one.");
 
-    let updated_rows = update(emails::table.filter(emails::token.eq(req_token)))
-        .set(emails::verified.eq(true))
-        .execute(&*conn)?;
-
-    if updated_rows == 0 {
-        return Err(bad_request("Email belonging to token not found."));
-    }
+    conn.transaction::<_, Box<dyn AppError>, _>(|| {
+        let email: Option<Email> = emails::table
+            .filter(emails::token.eq(req_token))
+            .first(&*conn)
+            .optional()?;
+        if let Some(email) = email {
+            if email.verified {
+                return Ok(());
+            }
+            if email.token_expired(now) {
+                return Err(expired());
+            }
+            update(&email)
+                .set(emails::verified.eq(true))
+                .execute(&*conn)?;
+            return Ok(());
+        }
+
+        match EmailChange::find_by_token(&conn, req_token)? {
+            Some(change) if change.expired(now) => Err(expired()),
+            Some(change) => {
+                change.confirm(&conn, req_token)?;
+                Ok(())
+            }
+            None => Err(bad_request("Email belonging to token not found.")),
+        }
+    })?;
 
     ok_true()
 }
@@ -202,12 +377,19 @@ pub fn regenerate_token_and_send(req: &mut dyn RequestExt) -> EndpointResult {
     }
 
     conn.transaction(|| {
+        let current: Email = Email::belonging_to(&user)
+            .first(&*conn)
+            .map_err(|_| bad_request("Email could not be found"))?;
+        if let Some(next) = current.next_resend_at(Utc::now().naive_utc()) {
+            return Err(resend_too_soon(next));
+        }
+
         let email: Email = update(Email::belonging_to(&user))
             .set(emails::token.eq(sql("DEFAULT")))
             .get_result(&*conn)
             .map_err(|_| bad_request("Email could not be found"))?;
 
-        email::try_send_user_confirm_email(&email.email, &user.gh_login, &email.token)
+        email::try_send_user_confirm_email(&conn, &email.email, &user.gh_login, &email.token)
             .map_err(|_| server_error("Error in sending email"))
     })?;
 
@@ -266,3 +448,67 @@ pub fn update_email_notifications(req: &mut dyn RequestExt) -> EndpointResult {
 
     ok_true()
 }
+
+/// Handles the `GET /me/email_preferences` route.
+pub fn email_preferences(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let preferences = EmailPreferences::for_user(&conn, user_id)?;
+
+    respond_email_preferences(req, preferences)
+}
+
+/// Handles the `PUT /me/email_preferences` route.
+///
+/// Only the kinds of notifications included in the request are changed.
+/// Which crates send new version notifications can still be chosen with
+/// `PUT /me/email_notifications`.
+pub fn update_email_preferences(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Update {
+        owner_invitations: Option<bool>,
+        new_versions: Option<bool>,
+        security_notices: Option<bool>,
+        moderation: Option<bool>,
+        weekly_digest: Option<bool>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let update: Update =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let preferences = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let current = EmailPreferences::for_user(&conn, user_id)?;
+        let preferences = EmailPreferences {
+            owner_invitations: update
+                .owner_invitations
+                .unwrap_or(current.owner_invitations),
+            new_versions: update.new_versions.unwrap_or(current.new_versions),
+            security_notices: update.security_notices.unwrap_or(current.security_notices),
+            moderation: update.moderation.unwrap_or(current.moderation),
+            weekly_digest: update.weekly_digest.unwrap_or(current.weekly_digest),
+            ..current
+        };
+        preferences.save(&conn)?;
+        Ok(preferences)
+    })?;
+
+    respond_email_preferences(req, preferences)
+}
+
+fn respond_email_preferences(
+    req: &dyn RequestExt,
+    preferences: EmailPreferences,
+) -> EndpointResult {
+    #[derive(Serialize)]
+    struct R {
+        email_preferences: EncodableEmailPreferences,
+    }
+    Ok(req.json(&R {
+        email_preferences: preferences.into(),
+    }))
+}
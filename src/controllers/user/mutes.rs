@@ -0,0 +1,98 @@
+//! Endpoints for muting the notifications and emails about a crate, or
+//! caused by another user, like repeated owner invitations from one account
+//!
+//! Security notices and moderation messages can't be muted.
+
+use std::collections::HashMap;
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{Crate, Mute, User};
+use crate::schema::{crates, users};
+use crate::views::EncodablePublicUser;
+
+fn find_crate_id(req: &dyn RequestExt, conn: &PgConnection) -> AppResult<i32> {
+    let crate_name = &req.params()["crate_id"];
+    Ok(Crate::by_name(crate_name).select(crates::id).first(conn)?)
+}
+
+/// Handles the `GET /me/mutes` route.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+    let mutes = Mute::for_user(&conn, user_id)?;
+
+    let crate_ids: Vec<i32> = mutes.iter().filter_map(|mute| mute.crate_id).collect();
+    let crate_names: HashMap<i32, String> = crates::table
+        .filter(crates::id.eq_any(&crate_ids))
+        .select((crates::id, crates::name))
+        .load::<(i32, String)>(&*conn)?
+        .into_iter()
+        .collect();
+    let user_ids: Vec<i32> = mutes.iter().filter_map(|mute| mute.muted_user_id).collect();
+    let mut muted_users: HashMap<i32, User> = users::table
+        .filter(users::id.eq_any(&user_ids))
+        .load::<User>(&*conn)?
+        .into_iter()
+        .map(|user| (user.id, user))
+        .collect();
+
+    let crates = crate_ids
+        .iter()
+        .filter_map(|id| crate_names.get(id).cloned())
+        .collect();
+    let users = user_ids
+        .iter()
+        .filter_map(|id| muted_users.remove(id))
+        .map(EncodablePublicUser::from)
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        crates: Vec<String>,
+        users: Vec<EncodablePublicUser>,
+    }
+    Ok(req.json(&R { crates, users }))
+}
+
+/// Handles the `PUT /me/mutes/crates/:crate_id` route.
+pub fn mute_crate(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let crate_id = find_crate_id(req, &conn)?;
+    Mute::mute_crate(&conn, user_id, crate_id)?;
+
+    ok_true()
+}
+
+/// Handles the `DELETE /me/mutes/crates/:crate_id` route.
+pub fn unmute_crate(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let crate_id = find_crate_id(req, &conn)?;
+    Mute::unmute_crate(&conn, user_id, crate_id)?;
+
+    ok_true()
+}
+
+/// Handles the `PUT /me/mutes/users/:user_id` route.
+pub fn mute_user(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let muted = User::find_by_login(&conn, &req.params()["user_id"])?;
+    if muted.id == user_id {
+        return Err(bad_request("you can't mute yourself"));
+    }
+    Mute::mute_user(&conn, user_id, muted.id)?;
+
+    ok_true()
+}
+
+/// Handles the `DELETE /me/mutes/users/:user_id` route.
+pub fn unmute_user(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let muted = User::find_by_login(&conn, &req.params()["user_id"])?;
+    Mute::unmute_user(&conn, user_id, muted.id)?;
+
+    ok_true()
+}
@@ -0,0 +1,79 @@
+//! Endpoints for the notifications shown on the website
+
+use crate::controllers::frontend_prelude::*;
+
+use crate::controllers::helpers::pagination::{Paginate, Paginated};
+use crate::models::Notification;
+use crate::schema::notifications;
+use crate::views::EncodableNotification;
+
+/// Handles the `GET /me/notifications` route.
+///
+/// Notifications are listed newest first. With `unread=true` only the unread
+/// ones are included.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let unread_only = req.query().get("unread").map(|s| &**s) == Some("true");
+
+    let mut query = notifications::table
+        .filter(notifications::user_id.eq(user_id))
+        .order((notifications::created_at.desc(), notifications::id.desc()))
+        .into_boxed();
+    if unread_only {
+        query = query.filter(notifications::read_at.is_null());
+    }
+    let query = query.paginate(req)?;
+
+    let conn = req.db_conn()?;
+    let data: Paginated<Notification> = query.load(&*conn)?;
+    let more = data.next_page_params().is_some();
+    let unread = Notification::unread_count(&conn, user_id)?;
+    let notifications = data.into_iter().map(EncodableNotification::from).collect();
+
+    #[derive(Serialize)]
+    struct R {
+        notifications: Vec<EncodableNotification>,
+        meta: Meta,
+    }
+    #[derive(Serialize)]
+    struct Meta {
+        unread: i64,
+        more: bool,
+    }
+    Ok(req.json(&R {
+        notifications,
+        meta: Meta { unread, more },
+    }))
+}
+
+/// Handles the `PUT /me/notifications/read` route.
+///
+/// Marks the notifications with the given `ids` as read, or all of them if
+/// the request has no `ids`.
+pub fn mark_read(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Default, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct MarkRead {
+        ids: Option<Vec<i32>>,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let request: MarkRead = if body.trim().is_empty() {
+        MarkRead::default()
+    } else {
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?
+    };
+
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_conn()?;
+    let marked = Notification::mark_read(&conn, user_id, request.ids.as_deref())?;
+    let unread = Notification::unread_count(&conn, user_id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        marked: usize,
+        unread: i64,
+    }
+    Ok(req.json(&R { marked, unread }))
+}
@@ -1,20 +1,24 @@
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{CrateOwner, OwnerKind, User};
-use crate::schema::{crate_owners, crates, users};
-use crate::util::errors::ChainError;
+use crate::models::{CrateOwner, OwnerKind, User, UsernameChange};
+use crate::schema::{crate_owners, crates};
+use crate::util::errors::{not_found, ChainError};
 use crate::views::EncodablePublicUser;
 
 /// Handles the `GET /users/:user_id` route.
+///
+/// The GitHub login of users that changed their username redirects to the
+/// username, and so do previous usernames for a grace period.
 pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
-    use self::users::dsl::{gh_login, id, users};
-
-    let name = crate::lower(&req.params()["user_id"]);
+    let name = &req.params()["user_id"];
     let conn = req.db_conn()?;
-    let user: User = users
-        .filter(crate::lower(gh_login).eq(name))
-        .order(id.desc())
-        .first(&*conn)?;
+    let user = match User::find_by_login(&conn, name).optional()? {
+        Some(user) => user,
+        None => UsernameChange::find_renamed_user(&conn, name)?.ok_or_else(not_found)?,
+    };
+    if !user.login().eq_ignore_ascii_case(name) {
+        return Ok(req.redirect(format!("/api/v1/users/{}", user.login())));
+    }
 
     #[derive(Serialize)]
     struct R {
@@ -0,0 +1,164 @@
+//! Validation of the profile fields that users edit through
+//! `PUT /users/:user_id`
+//!
+//! The fields are plain text, so control characters are removed and
+//! surrounding whitespace is trimmed. Websites need to be `http` or `https`
+//! URLs, which keeps `javascript:` and similar links off of profile pages.
+
+use serde::{Deserialize, Deserializer};
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::ProfileChanges;
+
+/// The profile fields of a `PUT /users/:user_id` request. Fields that are
+/// left out of the request stay unchanged, while `null` or an empty string
+/// clears them.
+#[derive(Debug, Default, Deserialize)]
+pub struct ProfileUpdate {
+    #[serde(default, deserialize_with = "present")]
+    display_name: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    bio: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    website: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    location: Option<Option<String>>,
+}
+
+/// Distinguishes fields that are `null` from fields that are missing
+fn present<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Option<String>>, D::Error> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+impl ProfileUpdate {
+    pub fn into_changes(self) -> AppResult<ProfileChanges> {
+        let display_name = self
+            .display_name
+            .map(|name| {
+                sanitize(
+                    name,
+                    "display name",
+                    ProfileChanges::MAX_DISPLAY_NAME_LENGTH,
+                    false,
+                )
+            })
+            .transpose()?;
+        let bio = self
+            .bio
+            .map(|bio| sanitize(bio, "bio", ProfileChanges::MAX_BIO_LENGTH, true))
+            .transpose()?;
+        let location = self
+            .location
+            .map(|location| {
+                sanitize(
+                    location,
+                    "location",
+                    ProfileChanges::MAX_LOCATION_LENGTH,
+                    false,
+                )
+            })
+            .transpose()?;
+        let website = self
+            .website
+            .map(|website| {
+                sanitize(
+                    website,
+                    "website",
+                    ProfileChanges::MAX_WEBSITE_LENGTH,
+                    false,
+                )?
+                .map(|website| parse_website(&website))
+                .transpose()
+            })
+            .transpose()?;
+
+        Ok(ProfileChanges {
+            display_name,
+            bio,
+            website,
+            location,
+        })
+    }
+}
+
+/// Removes control characters and surrounding whitespace from a field, with
+/// the exception of line breaks in multi-line fields. Empty fields are
+/// cleared.
+fn sanitize(
+    value: Option<String>,
+    field: &str,
+    max_length: usize,
+    multiline: bool,
+) -> AppResult<Option<String>> {
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    let value: String = value
+        .replace("\r\n", "\n")
+        .chars()
+        .filter(|c| !c.is_control() || (multiline && *c == '\n'))
+        .collect();
+    let value = value.trim();
+
+    if value.chars().count() > max_length {
+        return Err(bad_request(&format_args!(
+            "the {} must be at most {} characters long",
+            field, max_length
+        )));
+    }
+    Ok(Some(value.to_string()).filter(|value| !value.is_empty()))
+}
+
+fn parse_website(website: &str) -> AppResult<String> {
+    let invalid = || bad_request("the website must be an http or https URL");
+
+    let url = url::Url::parse(website).map_err(|_| invalid())?;
+    if !matches!(url.scheme(), "http" | "https") || url.host_str().is_none() {
+        return Err(invalid());
+    }
+    Ok(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(json: &str) -> AppResult<ProfileChanges> {
+        serde_json::from_str::<ProfileUpdate>(json)
+            .unwrap()
+            .into_changes()
+    }
+
+    #[test]
+    fn missing_fields_are_unchanged_and_null_fields_cleared() {
+        let changes = update(r#"{"bio": null, "location": "  "}"#).unwrap();
+        assert_eq!(changes.display_name, None);
+        assert_eq!(changes.website, None);
+        assert_eq!(changes.bio, Some(None));
+        assert_eq!(changes.location, Some(None));
+    }
+
+    #[test]
+    fn control_characters_are_removed() {
+        let changes =
+            update(r#"{"display_name": " Ferris\u0000 ", "bio": "Crab.\r\nRust\u001b."}"#).unwrap();
+        assert_eq!(changes.display_name, Some(Some("Ferris".into())));
+        assert_eq!(changes.bio, Some(Some("Crab.\nRust.".into())));
+    }
+
+    #[test]
+    fn websites_must_be_http_urls() {
+        let changes = update(r#"{"website": "https://example.com"}"#).unwrap();
+        assert_eq!(changes.website, Some(Some("https://example.com/".into())));
+
+        assert!(update(r#"{"website": "javascript:alert(1)"}"#).is_err());
+        assert!(update(r#"{"website": "example.com"}"#).is_err());
+    }
+
+    #[test]
+    fn long_fields_are_rejected() {
+        let bio = "a".repeat(ProfileChanges::MAX_BIO_LENGTH + 1);
+        assert!(update(&format!(r#"{{"bio": "{}"}}"#, bio)).is_err());
+    }
+}
@@ -0,0 +1,22 @@
+//! Endpoint that shows users how much of the quotas of their account they
+//! use, see the `quotas` module
+
+use crate::controllers::frontend_prelude::*;
+use crate::quotas::Usage;
+use crate::views::EncodableQuotas;
+
+/// Handles the `GET /me/quotas` route.
+pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
+    let user_id = req.authenticate()?.user_id();
+    let conn = req.db_read_only()?;
+    let quotas = req.app().config.quotas.for_user(&conn, user_id)?;
+    let usage = Usage::of(&conn, user_id)?;
+
+    #[derive(Serialize)]
+    struct R {
+        quotas: EncodableQuotas,
+    }
+    Ok(req.json(&R {
+        quotas: EncodableQuotas::new(quotas, usage),
+    }))
+}
@@ -4,10 +4,13 @@ use conduit_cookie::RequestSession;
 use oauth2::reqwest::http_client;
 use oauth2::{AuthorizationCode, Scope, TokenResponse};
 
-use crate::github::GithubUser;
-use crate::models::{NewUser, User};
+use crate::github::{fake, GithubUser};
+use crate::login_rate_limit::LoginEndpoint;
+use crate::models::{NewUser, User, UserSession};
 use crate::schema::users;
-use crate::util::errors::ReadOnlyMode;
+use crate::util::errors::{not_found, ReadOnlyMode};
+use crate::util::request_ip;
+use crate::views::EncodableUserSession;
 
 /// Handles the `GET /api/private/session/begin` route.
 ///
@@ -24,7 +27,17 @@ use crate::util::errors::ReadOnlyMode;
 ///     "url": "https://github.com/login/oauth/authorize?client_id=...&state=...&scope=read%3Aorg"
 /// }
 /// ```
+///
+/// In the development mode, the URL points to the `dev_login` page instead.
 pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
+    let ip = request_ip(req).to_string();
+    let rate_limit = req.app().config.login_rate_limit;
+    {
+        let conn = req.db_conn()?;
+        rate_limit.check(&*conn, &ip)?;
+        rate_limit.record(&*conn, &ip, LoginEndpoint::Begin, true)?;
+    }
+
     let (url, state) = req
         .app()
         .github_oauth
@@ -32,6 +45,12 @@ pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
         .add_scope(Scope::new("read:org".to_string()))
         .url();
     let state = state.secret().to_string();
+    let url = if req.app().config.dev_mode {
+        let state = url::form_urlencoded::byte_serialize(state.as_bytes()).collect::<String>();
+        format!("/api/private/session/dev_login?state={}", state)
+    } else {
+        url.to_string()
+    };
     req.session_mut()
         .insert("github_oauth_state".to_string(), state.clone());
 
@@ -40,10 +59,40 @@ pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
         url: String,
         state: String,
     }
-    Ok(req.json(&R {
-        url: url.to_string(),
-        state,
-    }))
+    Ok(req.json(&R { url, state }))
+}
+
+/// Handles the `GET /api/private/session/dev_login` route.
+///
+/// Stands in for the GitHub authorization page in the development mode. It
+/// lists the seed users of the `github::fake` module, and choosing one
+/// continues the login flow with the login as the code.
+pub fn dev_login(req: &mut dyn RequestExt) -> EndpointResult {
+    if !req.app().config.dev_mode {
+        return Err(not_found());
+    }
+
+    let state = req.query().remove("state").unwrap_or_default();
+    let state = url::form_urlencoded::byte_serialize(state.as_bytes()).collect::<String>();
+    let users = fake::SEED_USERS
+        .iter()
+        .map(|(login, _, name)| {
+            format!(
+                "<li><a href=\"/authorize/github?code={}&state={}\">{} ({})</a></li>",
+                login, state, name, login
+            )
+        })
+        .collect::<String>();
+    let body = format!(
+        "<!DOCTYPE html>\n<html><head><title>Log in to crates.io (development)</title></head>\n\
+         <body><h1>Log in as</h1><ul>{}</ul></body></html>\n",
+        users
+    );
+
+    Ok(conduit::Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(conduit::Body::from_vec(body.into_bytes()))
+        .unwrap())
 }
 
 /// Handles the `GET /api/private/session/authorize` route.
@@ -74,7 +123,24 @@ pub fn begin(req: &mut dyn RequestExt) -> EndpointResult {
 ///     }
 /// }
 /// ```
+///
+/// Requests are rate limited per address, see the `login_rate_limit` module.
 pub fn authorize(req: &mut dyn RequestExt) -> EndpointResult {
+    let ip = request_ip(req).to_string();
+    let rate_limit = req.app().config.login_rate_limit;
+    rate_limit.check(&*req.db_conn()?, &ip)?;
+
+    let result = authorize_and_log_in(req);
+    rate_limit.record(
+        &*req.db_conn()?,
+        &ip,
+        LoginEndpoint::Authorize,
+        result.is_ok(),
+    )?;
+    result
+}
+
+fn authorize_and_log_in(req: &mut dyn RequestExt) -> EndpointResult {
     // Parse the url query
     let mut query = req.query();
     let code = query.remove("code").unwrap_or_default();
@@ -91,22 +157,28 @@ pub fn authorize(req: &mut dyn RequestExt) -> EndpointResult {
     }
 
     // Fetch the access token from GitHub using the code we just got
-    let code = AuthorizationCode::new(code);
-    let token = req
-        .app()
-        .github_oauth
-        .exchange_code(code)
-        .request(http_client)
-        .chain_error(|| server_error("Error obtaining token"))?;
-    let token = token.access_token();
+    let token = if req.app().config.dev_mode {
+        fake::exchange_code(&code)?
+    } else {
+        let code = AuthorizationCode::new(code);
+        req.app()
+            .github_oauth
+            .exchange_code(code)
+            .request(http_client)
+            .chain_error(|| server_error("Error obtaining token"))?
+            .access_token()
+            .clone()
+    };
 
     // Fetch the user info from GitHub using the access token we just got and create a user record
-    let ghuser = req.app().github.current_user(token)?;
+    let ghuser = req.app().github.current_user(&token)?;
     let user = save_user_to_database(&ghuser, &token.secret(), &*req.db_conn()?)?;
 
+    // Locked accounts are not allowed to start a new session
+    user.check_account_lock()?;
+
     // Log in by setting a cookie and the middleware authentication
-    req.session_mut()
-        .insert("user_id".to_string(), user.id.to_string());
+    start_session(req, user.id)?;
 
     super::me::me(req)
 }
@@ -140,12 +212,112 @@ fn save_user_to_database(
     })
 }
 
+/// Starts a new session for the user, revoking the session of the cookie.
+///
+/// The cookie always gets a new session token, so a cookie that was planted
+/// in the browser before logging in can't be used once the user is logged in.
+fn start_session(req: &mut dyn RequestExt, user_id: i32) -> AppResult<()> {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let previous_token = req.session_mut().remove(&"session_token".to_string());
+
+    let session = {
+        let conn = req.db_conn()?;
+        if let Some(token) = previous_token {
+            revoke_session_of_token(&conn, &token)?;
+        }
+        UserSession::create(&conn, user_id, &user_agent)?
+    };
+
+    let cookie = req.session_mut();
+    cookie.clear();
+    cookie.insert("session_token".to_string(), session.plaintext);
+    // Only used to tag error reports with the user
+    cookie.insert("user_id".to_string(), user_id.to_string());
+    Ok(())
+}
+
+fn revoke_session_of_token(conn: &PgConnection, token: &str) -> AppResult<()> {
+    if let Some(session) = UserSession::find_active(conn, token)? {
+        UserSession::revoke(conn, session.user_id, session.id)?;
+    }
+    Ok(())
+}
+
 /// Handles the `DELETE /api/private/session` route.
 pub fn logout(req: &mut dyn RequestExt) -> EndpointResult {
-    req.session_mut().remove(&"user_id".to_string());
+    if let Some(token) = req.session_mut().remove(&"session_token".to_string()) {
+        // The cookie is removed even if the session can't be revoked while
+        // the database is in read only mode
+        if let Err(e) = revoke_session_of_token(&*req.db_conn()?, &token) {
+            if !e.is::<ReadOnlyMode>() {
+                return Err(e);
+            }
+        }
+    }
+    req.session_mut().clear();
     Ok(req.json(&true))
 }
 
+/// Handles the `GET /me/sessions` route.
+///
+/// Lists the sessions of the user that weren't revoked, marking the one the
+/// request was made with as `current`.
+pub fn list(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let current = authenticated_user.session_id();
+    let conn = req.db_read_only()?;
+    let sessions = UserSession::active_for_user(&conn, authenticated_user.user_id())?
+        .into_iter()
+        .map(|session| EncodableUserSession::from(session, current))
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        sessions: Vec<EncodableUserSession>,
+    }
+    Ok(req.json(&R { sessions }))
+}
+
+/// Handles the `DELETE /me/sessions/:id` route.
+///
+/// Revokes a session of the user, which logs out the browser using it.
+pub fn revoke(req: &mut dyn RequestExt) -> EndpointResult {
+    let id = req.params()["id"]
+        .parse::<i32>()
+        .map_err(|e| bad_request(&format!("invalid session id: {:?}", e)))?;
+
+    let authenticated_user = req.authenticate()?;
+    let conn = req.db_conn()?;
+    UserSession::revoke(&conn, authenticated_user.user_id(), id)?;
+
+    #[derive(Serialize)]
+    struct R {}
+    Ok(req.json(&R {}))
+}
+
+/// Handles the `DELETE /me/sessions` route.
+///
+/// Revokes all sessions of the user. Cookie authenticated requests get a new
+/// session, so only the other browsers are logged out.
+pub fn revoke_all(req: &mut dyn RequestExt) -> EndpointResult {
+    let authenticated_user = req.authenticate()?;
+    let user_id = authenticated_user.user_id();
+    UserSession::revoke_all(&*req.db_conn()?, user_id)?;
+
+    if authenticated_user.session_id().is_some() {
+        start_session(req, user_id)?;
+    }
+
+    #[derive(Serialize)]
+    struct R {}
+    Ok(req.json(&R {}))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
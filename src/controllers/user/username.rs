@@ -0,0 +1,84 @@
+//! Endpoint for changing the login of a user on crates.io, independently of
+//! their GitHub login
+//!
+//! Previous logins keep redirecting to the user for
+//! `UsernameChange::GRACE_PERIOD_DAYS`, and can't be taken by other users
+//! during that time.
+
+use chrono::{Duration, Utc};
+
+use crate::controllers::frontend_prelude::*;
+use crate::models::{User, UsernameChange};
+use crate::views::EncodablePublicUser;
+
+/// The same limit as for GitHub logins
+const MAX_USERNAME_LENGTH: usize = 39;
+
+/// Usernames follow the rules for GitHub logins, except that they must also
+/// contain an underscore, so they can never be the GitHub login of somebody
+/// else. See `User::is_username`.
+fn validate_username(username: &str) -> AppResult<()> {
+    let valid = username.len() <= MAX_USERNAME_LENGTH
+        && User::is_username(username)
+        && username
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        && !username.starts_with(['-', '_'])
+        && !username.ends_with(['-', '_']);
+    if !valid {
+        return Err(bad_request(&format_args!(
+            "a username must be at most {} characters long, consist of alphanumeric \
+            characters, hyphens and underscores that don't start or end it, and contain \
+            at least one underscore",
+            MAX_USERNAME_LENGTH
+        )));
+    }
+    Ok(())
+}
+
+/// Handles the `PUT /me/username` route.
+pub fn update(req: &mut dyn RequestExt) -> EndpointResult {
+    #[derive(Deserialize)]
+    struct UsernameUpdate {
+        username: String,
+    }
+
+    let mut body = String::new();
+    req.body().read_to_string(&mut body)?;
+    let update: UsernameUpdate =
+        serde_json::from_str(&body).map_err(|_| bad_request("invalid json request"))?;
+    let username = update.username.trim();
+    validate_username(username)?;
+
+    let user = req.authenticate()?.user();
+    let conn = req.db_conn()?;
+
+    if username == user.login() {
+        return Err(bad_request("this already is your username"));
+    }
+    if let Some(last_change) = UsernameChange::last_change(&conn, user.id)? {
+        let next_change = last_change + Duration::days(UsernameChange::DAYS_BETWEEN_CHANGES);
+        if next_change > Utc::now().naive_utc() {
+            return Err(bad_request(&format_args!(
+                "you can change your username again after {}",
+                next_change.format("%Y-%m-%d")
+            )));
+        }
+    }
+    if User::is_username_taken(&conn, username, user.id)?
+        || UsernameChange::is_reserved(&conn, username, user.id)?
+    {
+        return Err(bad_request(&format_args!(
+            "the username `{}` is not available",
+            username
+        )));
+    }
+
+    let user = UsernameChange::change(&conn, &user, username)?;
+
+    #[derive(Serialize)]
+    struct R {
+        user: EncodablePublicUser,
+    }
+    Ok(req.json(&R { user: user.into() }))
+}
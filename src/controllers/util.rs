@@ -1,19 +1,18 @@
-use chrono::Utc;
 use conduit_cookie::RequestSession;
 
 use super::prelude::*;
 
 use crate::middleware::log_request;
-use crate::models::{ApiToken, User};
+use crate::models::{ApiToken, User, UserSession};
 use crate::util::errors::{
-    account_locked, forbidden, internal, AppError, AppResult, ChainError,
-    InsecurelyGeneratedTokenRevoked,
+    forbidden, internal, AppError, AppResult, ChainError, InsecurelyGeneratedTokenRevoked,
 };
 
 #[derive(Debug)]
 pub struct AuthenticatedUser {
     user: User,
     token_id: Option<i32>,
+    session_id: Option<i32>,
 }
 
 impl AuthenticatedUser {
@@ -25,6 +24,15 @@ impl AuthenticatedUser {
         self.token_id
     }
 
+    /// The id of the session of cookie authenticated requests
+    pub fn session_id(&self) -> Option<i32> {
+        self.session_id
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.user.is_admin
+    }
+
     pub fn user(self) -> User {
         self.user
     }
@@ -59,16 +67,22 @@ fn verify_origin(req: &dyn RequestExt) -> AppResult<()> {
 fn authenticate_user(req: &dyn RequestExt) -> AppResult<AuthenticatedUser> {
     let conn = req.db_conn()?;
 
-    let session = req.session();
-    let user_id_from_session = session.get("user_id").and_then(|s| s.parse::<i32>().ok());
+    // Cookies hold the token of a session, which is ignored once the session
+    // was revoked
+    let session_token = req.session().get("session_token");
+    let session = match session_token {
+        Some(token) => UserSession::find_active(&conn, token)?,
+        None => None,
+    };
 
-    if let Some(id) = user_id_from_session {
-        let user = User::find(&conn, id)
-            .chain_error(|| internal("user_id from cookie not found in database"))?;
+    if let Some(session) = session {
+        let user = User::find(&conn, session.user_id)
+            .chain_error(|| internal("user_id from session not found in database"))?;
 
         return Ok(AuthenticatedUser {
             user,
             token_id: None,
+            session_id: Some(session.id),
         });
     }
 
@@ -93,6 +107,7 @@ fn authenticate_user(req: &dyn RequestExt) -> AppResult<AuthenticatedUser> {
         return Ok(AuthenticatedUser {
             user,
             token_id: Some(token.id),
+            session_id: None,
         });
     }
 
@@ -107,19 +122,7 @@ impl<'a> UserAuthenticationExt for dyn RequestExt + 'a {
 
         let authenticated_user = authenticate_user(self)?;
 
-        if let Some(reason) = &authenticated_user.user.account_lock_reason {
-            let still_locked = if let Some(until) = authenticated_user.user.account_lock_until {
-                until > Utc::now().naive_utc()
-            } else {
-                true
-            };
-            if still_locked {
-                return Err(account_locked(
-                    &reason,
-                    authenticated_user.user.account_lock_until,
-                ));
-            }
-        }
+        authenticated_user.user.check_account_lock()?;
 
         log_request::add_custom_metadata(self, "uid", authenticated_user.user_id());
         if let Some(id) = authenticated_user.api_token_id() {
@@ -5,7 +5,34 @@ pub mod yank;
 
 use super::prelude::*;
 
-use crate::models::{Crate, Version};
+use crate::models::{Advisory, Crate, User, Version, VersionChecksum, VersionOwnerAction};
+use crate::views::EncodableVersion;
+
+/// Encodes versions along with their publishers, which are expected to be
+/// loaded with the versions, and their audit actions, which are loaded for
+/// all versions in a single query, like the ids of the advisories affecting them
+/// and their checksums.
+pub(crate) fn encode_versions<N: AsRef<str>>(
+    conn: &PgConnection,
+    versions: Vec<(Version, N, Option<User>)>,
+) -> QueryResult<Vec<EncodableVersion>> {
+    let ids = versions.iter().map(|(v, _, _)| v.id).collect::<Vec<_>>();
+    let mut actions = VersionOwnerAction::for_version_ids(conn, &ids)?;
+    let mut advisories = Advisory::ids_for_versions(conn, &ids)?;
+    let mut checksums = VersionChecksum::for_versions(conn, &ids)?;
+
+    Ok(versions
+        .into_iter()
+        .map(|(version, crate_name, published_by)| {
+            let actions = actions.remove(&version.id).unwrap_or_default();
+            let advisories = advisories.remove(&version.id).unwrap_or_default();
+            let checksums = checksums.remove(&version.id).unwrap_or_default();
+            EncodableVersion::from(version, crate_name.as_ref(), published_by, actions)
+                .with_advisories(advisories)
+                .with_checksums(checksums)
+        })
+        .collect())
+}
 
 fn version_and_crate(
     conn: &PgConnection,
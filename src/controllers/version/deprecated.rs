@@ -7,10 +7,12 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::{Crate, User, Version, VersionOwnerAction};
+use crate::models::{Advisory, Crate, User, Version, VersionChecksum, VersionOwnerAction};
 use crate::schema::*;
 use crate::views::EncodableVersion;
 
+use super::encode_versions;
+
 /// Handles the `GET /versions` route.
 pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
     use diesel::dsl::any;
@@ -32,18 +34,7 @@ pub fn index(req: &mut dyn RequestExt) -> EndpointResult {
         ))
         .filter(versions::id.eq(any(ids)))
         .load(&*conn)?;
-    let versions = versions_and_publishers
-        .iter()
-        .map(|(v, _, _)| v)
-        .cloned()
-        .collect::<Vec<_>>();
-    let versions = versions_and_publishers
-        .into_iter()
-        .zip(VersionOwnerAction::for_versions(&conn, &versions)?.into_iter())
-        .map(|((version, crate_name, published_by), actions)| {
-            EncodableVersion::from(version, &crate_name, published_by, actions)
-        })
-        .collect();
+    let versions = encode_versions(&conn, versions_and_publishers)?;
 
     #[derive(Serialize)]
     struct R {
@@ -70,12 +61,20 @@ pub fn show_by_id(req: &mut dyn RequestExt) -> EndpointResult {
         ))
         .first(&*conn)?;
     let audit_actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::ids_for_versions(&conn, &[version.id])?
+        .remove(&version.id)
+        .unwrap_or_default();
+    let checksums = VersionChecksum::for_versions(&conn, &[version.id])?
+        .remove(&version.id)
+        .unwrap_or_default();
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: EncodableVersion::from(version, &krate.name, published_by, audit_actions),
+        version: EncodableVersion::from(version, &krate.name, published_by, audit_actions)
+            .with_advisories(advisories)
+            .with_checksums(checksums),
     }))
 }
@@ -6,31 +6,59 @@ use crate::controllers::prelude::*;
 
 use chrono::{Duration, NaiveDate, Utc};
 
-use crate::models::{Crate, VersionDownload};
+use crate::middleware::head::HeadRequest;
+use crate::models::{Crate, VersionDownload, VersionQuarantine};
 use crate::schema::*;
+use crate::util::errors::custom;
 use crate::views::EncodableVersionDownload;
 
 use super::{extract_crate_name_and_semver, version_and_crate};
 
 /// Handles the `GET /crates/:crate_id/:version/download` route.
-/// This returns a URL to the location where the crate is stored.
+/// This returns a URL to the location where the crate is stored, which is the
+/// location by checksum unless the crate file is only stored by name. If
+/// download origins are configured, the location is at one of them, picked by
+/// the region of the request.
+///
+/// HEAD requests and requests for a `Range` that doesn't start at the first
+/// byte, which resume an earlier download, are redirected like any other
+/// download, but are not counted again. The client sends the same method and
+/// `Range` to the location it is redirected to.
 pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
     let recorder = req.timing_recorder();
+    let count = req.extensions().find::<HeadRequest>().is_none() && !resumes_download(req);
 
     let crate_name = &req.params()["crate_id"];
     let version = &req.params()["version"];
 
-    let (crate_name, was_counted) = increment_download_counts(req, recorder, crate_name, version)?;
+    let (crate_name, checksum, was_counted) =
+        increment_download_counts(req, recorder, crate_name, version, count)?;
 
-    let redirect_url = req
-        .app()
-        .config
-        .uploader
-        .crate_location(&crate_name, version);
+    let metrics = &req.app().instance_metrics;
+    if count {
+        metrics.downloads_total.inc();
+        if !was_counted {
+            metrics.downloads_not_counted_total.inc();
+        }
+    }
+
+    let app = req.app();
+    let uploader = &app.config.uploader;
+    let region = req
+        .headers()
+        .get(&app.config.download_region_header)
+        .and_then(|region| region.to_str().ok());
+    let origin =
+        app.download_origins
+            .select(&app.primary_database, uploader.download_origins(), region);
+    let redirect_url = match checksum {
+        Some(checksum) => uploader.crate_location_by_checksum(origin, &checksum),
+        None => uploader.crate_location(origin, &crate_name, version),
+    };
 
     // Adding log metadata requires &mut access, so we have to defer this step until
     // after the (immutable) query parameters are no longer used.
-    if !was_counted {
+    if count && !was_counted {
         req.log_metadata("uncounted_dl", "true");
     }
 
@@ -45,10 +73,27 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
     }
 }
 
-/// Increment the download counts for a given crate version.
+/// Whether the request continues a download, as its `Range` doesn't start at
+/// the first byte of the file
+fn resumes_download(req: &dyn RequestExt) -> bool {
+    let range = match req.headers().get(header::RANGE) {
+        Some(range) => range.to_str().unwrap_or_default(),
+        None => return false,
+    };
+    let start = range
+        .trim()
+        .strip_prefix("bytes=")
+        .and_then(|range| range.split('-').next())
+        .map(str::trim);
+    !matches!(start, Some(start) if start.parse() == Ok(0u64))
+}
+
+/// Increment the download counts for a given crate version, unless `count`
+/// is `false`.
 ///
-/// Returns the crate name as stored in the database, or an error if we could
-/// not load the version ID from the database.
+/// Returns the crate name as stored in the database and the checksum the crate
+/// file is stored by, or an error if we could not load the version ID from the
+/// database or if the version is currently quarantined.
 ///
 /// This ignores any errors that occur updating the download count. Failure is
 /// expected if the application is in read only mode, or for API-only mirrors.
@@ -57,28 +102,82 @@ pub fn download(req: &mut dyn RequestExt) -> EndpointResult {
 fn increment_download_counts(
     req: &dyn RequestExt,
     recorder: TimingRecorder,
-    crate_name: &str,
+    requested_name: &str,
     version: &str,
-) -> AppResult<(String, bool)> {
+    count: bool,
+) -> AppResult<(String, Option<String>, bool)> {
     use self::versions::dsl::*;
 
-    let conn = recorder.record("get_conn", || req.db_conn())?;
-
-    let (version_id, crate_name) = recorder.record("get_version", || {
+    let find_version = |conn: &PgConnection| {
         versions
             .inner_join(crates::table)
-            .select((id, crates::name))
-            .filter(Crate::with_name(crate_name))
+            .select((id, crates::name, checksum, stored_by_checksum))
+            .filter(Crate::with_name(requested_name))
             .filter(num.eq(version))
-            .first(&*conn)
-    })?;
+            .first::<(i32, String, Option<String>, bool)>(conn)
+            .map(|(version_id, name, hex_checksum, stored)| {
+                (version_id, name, hex_checksum.filter(|_| stored))
+            })
+    };
+
+    // Most downloads are for versions this server has seen before. Others are
+    // looked up on the replica, unless they were published so recently that
+    // they haven't been replicated yet.
+    let version_lookups = &req.app().version_lookups;
+    let cached = version_lookups.get(requested_name, version);
+    let was_cached = cached.is_some();
+    let found = match cached {
+        Some(found) => Some(found),
+        None => {
+            let conn = recorder.record("get_read_only_conn", || req.db_read_only())?;
+            recorder
+                .record("get_version", || find_version(&conn))
+                .optional()?
+        }
+    };
+
+    let conn = recorder.record("get_conn", || req.db_conn())?;
+
+    let (version_id, crate_name, stored_checksum) = match found {
+        Some(found) => found,
+        None => recorder.record("get_version_from_primary", || find_version(&conn))?,
+    };
+    if !was_cached {
+        version_lookups.insert(
+            requested_name,
+            version,
+            (version_id, crate_name.clone(), stored_checksum.clone()),
+        );
+    }
+
+    let quarantined = match version_lookups.is_quarantined(version_id) {
+        Some(quarantined) => quarantined,
+        None => {
+            let quarantined = recorder.record("check_quarantine", || {
+                VersionQuarantine::is_pending(&conn, version_id)
+            })?;
+            version_lookups.insert_quarantined(version_id, quarantined);
+            quarantined
+        }
+    };
+    if quarantined {
+        return Err(custom(
+            StatusCode::FORBIDDEN,
+            "this version has been flagged by the crates.io malware scanner and is \
+             awaiting review by the crates.io team. Downloads are disabled until then.",
+        ));
+    }
+
+    if !count {
+        return Ok((crate_name, stored_checksum, false));
+    }
 
     // Wrap in a transaction so we don't poison the outer transaction if this
     // fails
     let res = recorder.record("update_count", || {
         conn.transaction(|| VersionDownload::create_or_increment(version_id, &conn))
     });
-    Ok((crate_name, res.is_ok()))
+    Ok((crate_name, stored_checksum, res.is_ok()))
 }
 
 /// Handles the `GET /crates/:crate_id/:version/downloads` route.
@@ -6,9 +6,15 @@
 
 use crate::controllers::frontend_prelude::*;
 
-use crate::models::VersionOwnerAction;
+use crate::models::{
+    Advisory, ApiToken, User, VersionAction, VersionChecksum, VersionOwnerAction,
+    VersionPublishOrigin,
+};
 use crate::schema::*;
-use crate::views::{EncodableDependency, EncodablePublicUser, EncodableVersion};
+use crate::views::{
+    EncodableAuditAction, EncodableDependency, EncodablePublicUser, EncodableVersion,
+    EncodableVersionProvenance,
+};
 
 use super::{extract_crate_name_and_semver, version_and_crate};
 
@@ -65,6 +71,54 @@ pub fn authors(req: &mut dyn RequestExt) -> EndpointResult {
     }))
 }
 
+/// Handles the `GET /crates/:crate_id/:version/provenance` route.
+///
+/// Describes who published the version, with which token and from where,
+/// and what the owners did with it since, so consumers can decide whether to
+/// trust the artifact.
+pub fn provenance(req: &mut dyn RequestExt) -> EndpointResult {
+    let (crate_name, semver) = extract_crate_name_and_semver(req)?;
+    let conn = req.db_read_only()?;
+    let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
+    let published_by = version.published_by(&conn);
+    let actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let origin = VersionPublishOrigin::for_version(&conn, &version)?;
+
+    let publish_token_id = actions
+        .iter()
+        .find(|(action, _)| action.action == VersionAction::Publish)
+        .and_then(|(action, _)| action.api_token_id);
+    let token = match publish_token_id {
+        Some(id) => Some(api_tokens::table.find(id).first::<ApiToken>(&*conn)?),
+        None => None,
+    };
+
+    let actions = actions
+        .into_iter()
+        .map(|(action, user)| EncodableAuditAction {
+            action: action.action.into(),
+            user: user.into(),
+            time: action.time,
+        })
+        .collect();
+
+    #[derive(Serialize)]
+    struct R {
+        provenance: EncodableVersionProvenance,
+    }
+    Ok(req.json(&R {
+        provenance: EncodableVersionProvenance {
+            krate: krate.name,
+            num: version.num.to_string(),
+            published_at: version.created_at,
+            published_by: published_by.map(User::into),
+            token: token.map(ApiToken::into),
+            ip_class: origin.map(|origin| origin.ip_class),
+            actions,
+        },
+    }))
+}
+
 /// Handles the `GET /crates/:crate/:version` route.
 ///
 /// The frontend doesn't appear to hit this endpoint, but our tests do, and it seems to be a useful
@@ -75,12 +129,20 @@ pub fn show(req: &mut dyn RequestExt) -> EndpointResult {
     let (version, krate) = version_and_crate(&conn, crate_name, semver)?;
     let published_by = version.published_by(&conn);
     let actions = VersionOwnerAction::by_version(&conn, &version)?;
+    let advisories = Advisory::ids_for_versions(&conn, &[version.id])?
+        .remove(&version.id)
+        .unwrap_or_default();
+    let checksums = VersionChecksum::for_versions(&conn, &[version.id])?
+        .remove(&version.id)
+        .unwrap_or_default();
 
     #[derive(Serialize)]
     struct R {
         version: EncodableVersion,
     }
     Ok(req.json(&R {
-        version: EncodableVersion::from(version, &krate.name, published_by, actions),
+        version: EncodableVersion::from(version, &krate.name, published_by, actions)
+            .with_advisories(advisories)
+            .with_checksums(checksums),
     }))
 }
@@ -1,12 +1,12 @@
 //! Endpoints for yanking and unyanking specific versions of crates
 
-use swirl::Job;
-
 use super::{extract_crate_name_and_semver, version_and_crate};
+use crate::background_jobs::Job;
 use crate::controllers::cargo_prelude::*;
-use crate::git;
+use crate::controllers::helpers::idempotency::idempotent;
 use crate::models::Rights;
-use crate::models::{insert_version_owner_action, VersionAction};
+use crate::models::{insert_version_owner_action, VersionAction, VersionQuarantine};
+use crate::util::errors::{cargo_err_with_code, ErrorCode};
 
 /// Handles the `DELETE /crates/:crate_id/:version/yank` route.
 /// This does not delete a crate version, it makes the crate
@@ -18,12 +18,12 @@ use crate::models::{insert_version_owner_action, VersionAction};
 /// and the goal of yanking a crate is to prevent crates
 /// beginning to depend on the yanked crate version.
 pub fn yank(req: &mut dyn RequestExt) -> EndpointResult {
-    modify_yank(req, true)
+    idempotent(req, |req| modify_yank(req, true))
 }
 
 /// Handles the `PUT /crates/:crate_id/:version/unyank` route.
 pub fn unyank(req: &mut dyn RequestExt) -> EndpointResult {
-    modify_yank(req, false)
+    idempotent(req, |req| modify_yank(req, false))
 }
 
 /// Changes `yanked` flag on a crate version record
@@ -40,7 +40,17 @@ fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
     let owners = krate.owners(&conn)?;
 
     if user.rights(req.app(), &owners)? < Rights::Publish {
-        return Err(cargo_err("must already be an owner to yank or unyank"));
+        return Err(cargo_err_with_code(
+            ErrorCode::NotOwner,
+            "must already be an owner to yank or unyank",
+        ));
+    }
+    // The index has no entry to update until the version is released
+    if VersionQuarantine::is_held_out_of_index(&conn, version.id)? {
+        return Err(cargo_err_with_code(
+            ErrorCode::VersionHeld,
+            "the version is held for review and can't be yanked or unyanked until then",
+        ));
     }
     let action = if yanked {
         VersionAction::Yank
@@ -50,7 +60,14 @@ fn modify_yank(req: &mut dyn RequestExt, yanked: bool) -> EndpointResult {
 
     insert_version_owner_action(&conn, version.id, user.id, api_token_id, action)?;
 
-    git::yank(krate.name, version, yanked).enqueue(&conn)?;
+    // The cached responses are invalidated by the job once it has yanked or
+    // unyanked the version
+    Job::Yank {
+        krate: krate.name,
+        version,
+        yanked,
+    }
+    .enqueue(&conn)?;
 
     ok_true()
 }
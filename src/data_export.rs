@@ -0,0 +1,248 @@
+//! Export of all data crates.io stores about a user account.
+//!
+//! Exports are requested through `PUT /api/v1/me/data_export` (or the admin
+//! equivalent) and built by the `ExportUserData` background job. The
+//! resulting tarball contains one JSON file per kind of data and is stored in
+//! the `data_exports` table until the user requests a new export or deletes
+//! their account.
+
+use crate::swirl::PerformError;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use flate2::{write::GzEncoder, Compression};
+
+use crate::models::{CrateList, CrateOwner, DataExport, OwnerKind, User, VersionAction};
+use crate::schema::{
+    api_tokens, crate_list_entries, crate_lists, crate_owner_invitations, crate_owners, crates,
+    emails, follows, mutes, user_follows, users, version_owner_actions, versions,
+};
+
+/// The name of the directory inside the tarball.
+const ARCHIVE_ROOT: &str = "crates-io-data-export";
+
+pub fn perform_export_user_data(
+    conn: &PgConnection,
+    data_export_id: i32,
+) -> Result<(), PerformError> {
+    let data_export = DataExport::find(conn, data_export_id)?;
+    let user = User::find(conn, data_export.user_id)?;
+
+    let archive = build_archive(conn, &user)?;
+    data_export.complete(conn, &archive)?;
+
+    Ok(())
+}
+
+/// Collects the data of the user and returns it as a gzipped tarball.
+pub fn build_archive(conn: &PgConnection, user: &User) -> Result<Vec<u8>, PerformError> {
+    let mut archive = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mtime = Utc::now().timestamp() as u64;
+
+    for (name, contents) in collect(conn, user)? {
+        let contents = serde_json::to_vec_pretty(&contents)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        header.set_cksum();
+        archive.append_data(
+            &mut header,
+            format!("{}/{}.json", ARCHIVE_ROOT, name),
+            &contents[..],
+        )?;
+    }
+
+    Ok(archive.into_inner()?.finish()?)
+}
+
+fn collect(
+    conn: &PgConnection,
+    user: &User,
+) -> Result<Vec<(&'static str, serde_json::Value)>, PerformError> {
+    let emails: Vec<(String, bool)> = emails::table
+        .filter(emails::user_id.eq(user.id))
+        .select((emails::email, emails::verified))
+        .load(conn)?;
+    let emails = emails
+        .into_iter()
+        .map(|(email, verified)| json!({ "email": email, "verified": verified }))
+        .collect::<Vec<_>>();
+
+    let api_tokens = api_tokens::table
+        .filter(api_tokens::user_id.eq(user.id))
+        .select((
+            api_tokens::id,
+            api_tokens::name,
+            api_tokens::created_at,
+            api_tokens::last_used_at,
+            api_tokens::revoked,
+        ))
+        .order(api_tokens::id)
+        .load::<(i32, String, NaiveDateTime, Option<NaiveDateTime>, bool)>(conn)?
+        .into_iter()
+        .map(|(id, name, created_at, last_used_at, revoked)| {
+            json!({
+                "id": id,
+                "name": name,
+                "created_at": timestamp(created_at),
+                "last_used_at": last_used_at.map(timestamp),
+                "revoked": revoked,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let owned_crates: Vec<String> = CrateOwner::by_owner_kind(OwnerKind::User)
+        .filter(crate_owners::owner_id.eq(user.id))
+        .inner_join(crates::table)
+        .select(crates::name)
+        .order(crates::name)
+        .load(conn)?;
+
+    let followed_crates: Vec<String> = follows::table
+        .filter(follows::user_id.eq(user.id))
+        .inner_join(crates::table)
+        .select(crates::name)
+        .order(crates::name)
+        .load(conn)?;
+
+    let followed_users: Vec<String> = user_follows::table
+        .filter(user_follows::follower_id.eq(user.id))
+        .inner_join(users::table.on(users::id.eq(user_follows::followed_id)))
+        .select(users::gh_login)
+        .order(users::gh_login)
+        .load(conn)?;
+
+    let muted_crates: Vec<String> = mutes::table
+        .filter(mutes::user_id.eq(user.id))
+        .inner_join(crates::table)
+        .select(crates::name)
+        .order(crates::name)
+        .load(conn)?;
+
+    let muted_users: Vec<String> = mutes::table
+        .filter(mutes::user_id.eq(user.id))
+        .inner_join(users::table.on(users::id.nullable().eq(mutes::muted_user_id)))
+        .select(users::gh_login)
+        .order(users::gh_login)
+        .load(conn)?;
+
+    let crate_lists = crate_lists::table
+        .filter(crate_lists::user_id.eq(user.id))
+        .order(crate_lists::id)
+        .load::<CrateList>(conn)?
+        .into_iter()
+        .map(|list| {
+            let crates: Vec<(String, Option<String>)> = crate_list_entries::table
+                .filter(crate_list_entries::crate_list_id.eq(list.id))
+                .inner_join(crates::table)
+                .select((crates::name, crate_list_entries::note))
+                .order(crate_list_entries::created_at)
+                .load(conn)?;
+            Ok(json!({
+                "id": list.id,
+                "name": list.name,
+                "description": list.description,
+                "created_at": timestamp(list.created_at),
+                "crates": crates
+                    .into_iter()
+                    .map(|(krate, note)| json!({ "crate": krate, "note": note }))
+                    .collect::<Vec<_>>(),
+            }))
+        })
+        .collect::<QueryResult<Vec<_>>>()?;
+
+    let published_versions = versions::table
+        .filter(versions::published_by.eq(user.id))
+        .inner_join(crates::table)
+        .select((crates::name, versions::num, versions::created_at))
+        .order(versions::id)
+        .load::<(String, String, NaiveDateTime)>(conn)?
+        .into_iter()
+        .map(|(krate, num, created_at)| {
+            json!({ "crate": krate, "num": num, "created_at": timestamp(created_at) })
+        })
+        .collect::<Vec<_>>();
+
+    let version_actions = version_owner_actions::table
+        .filter(version_owner_actions::user_id.eq(user.id))
+        .inner_join(versions::table.inner_join(crates::table))
+        .select((
+            crates::name,
+            versions::num,
+            version_owner_actions::action,
+            version_owner_actions::api_token_id,
+            version_owner_actions::time,
+        ))
+        .order(version_owner_actions::id)
+        .load::<(String, String, VersionAction, Option<i32>, NaiveDateTime)>(conn)?
+        .into_iter()
+        .map(|(krate, num, action, api_token_id, time)| {
+            json!({
+                "crate": krate,
+                "num": num,
+                "action": String::from(action),
+                "api_token_id": api_token_id,
+                "time": timestamp(time),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let invitations = crate_owner_invitations::table
+        .filter(
+            crate_owner_invitations::invited_user_id
+                .eq(user.id)
+                .or(crate_owner_invitations::invited_by_user_id.eq(user.id)),
+        )
+        .inner_join(crates::table)
+        .select((
+            crates::name,
+            crate_owner_invitations::invited_user_id,
+            crate_owner_invitations::invited_by_user_id,
+            crate_owner_invitations::created_at,
+        ))
+        .load::<(String, i32, i32, NaiveDateTime)>(conn)?
+        .into_iter()
+        .map(|(krate, invited_user_id, invited_by_user_id, created_at)| {
+            json!({
+                "crate": krate,
+                "invited_user_id": invited_user_id,
+                "invited_by_user_id": invited_by_user_id,
+                "created_at": timestamp(created_at),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let account = json!({
+        "id": user.id,
+        "login": user.gh_login,
+        "name": user.name,
+        "avatar": user.gh_avatar,
+        "display_name": user.display_name,
+        "bio": user.bio,
+        "website": user.website,
+        "location": user.location,
+        "gh_id": user.gh_id,
+        "account_lock_reason": user.account_lock_reason,
+        "account_lock_until": user.account_lock_until.map(timestamp),
+        "followable": user.followable,
+    });
+
+    Ok(vec![
+        ("account", account),
+        ("emails", json!(emails)),
+        ("api_tokens", json!(api_tokens)),
+        ("owned_crates", json!(owned_crates)),
+        ("followed_crates", json!(followed_crates)),
+        ("followed_users", json!(followed_users)),
+        ("muted_crates", json!(muted_crates)),
+        ("muted_users", json!(muted_users)),
+        ("crate_lists", json!(crate_lists)),
+        ("published_versions", json!(published_versions)),
+        ("version_actions", json!(version_actions)),
+        ("crate_owner_invitations", json!(invitations)),
+    ])
+}
+
+fn timestamp(time: NaiveDateTime) -> String {
+    DateTime::<Utc>::from_utc(time, Utc).to_rfc3339()
+}
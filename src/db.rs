@@ -1,12 +1,16 @@
-use conduit::RequestExt;
+use conduit::{RequestExt, StatusCode};
 use diesel::prelude::*;
 use diesel::r2d2::{self, ConnectionManager, CustomizeConnection};
 use parking_lot::{ReentrantMutex, ReentrantMutexGuard};
+use std::collections::HashMap;
 use std::ops::Deref;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use url::Url;
 
 use crate::middleware::app::RequestApp;
+use crate::util::errors::{custom, AppResult};
 use crate::Env;
 
 #[allow(missing_debug_implementations)]
@@ -89,19 +93,287 @@ pub trait RequestTransaction {
 
     /// Obtain a readonly database connection from the replica pool
     ///
-    /// If there is no replica pool, the primary pool is used instead.
+    /// If there is no replica pool, or the replica is currently unavailable
+    /// or lagging too far behind (see [`ReplicaStatus`]), the primary pool is
+    /// used instead.
     fn db_read_only(&self) -> Result<DieselPooledConn<'_>, r2d2::PoolError>;
+
+    /// Reserve one of the connections the endpoint family may hold, for as
+    /// long as the permit is kept
+    ///
+    /// Responds with `503 Service Unavailable` if the family has used up its
+    /// budget, see [`PoolBudgets`].
+    fn db_budget(&self, family: EndpointFamily) -> AppResult<BudgetPermit>;
 }
 
 impl<T: RequestExt + ?Sized> RequestTransaction for T {
     fn db_conn(&self) -> Result<DieselPooledConn<'_>, r2d2::PoolError> {
-        self.app().primary_database.get().map_err(Into::into)
+        get_timed(self, "primary", &self.app().primary_database)
     }
 
     fn db_read_only(&self) -> Result<DieselPooledConn<'_>, r2d2::PoolError> {
-        match &self.app().read_only_replica_database {
-            Some(pool) => pool.get().map_err(Into::into),
-            None => self.app().primary_database.get().map_err(Into::into),
+        let app = self.app();
+        if let Some(replica) = &app.read_only_replica_database {
+            if app.replica_status.use_replica(replica) {
+                match get_timed(self, "replica", replica) {
+                    Ok(conn) => return Ok(conn),
+                    Err(error) => {
+                        warn!(%error, "Failed to connect to the replica, reading from the primary");
+                        app.replica_status.mark_unavailable();
+                    }
+                }
+            }
+        }
+        get_timed(self, "primary", &app.primary_database)
+    }
+
+    fn db_budget(&self, family: EndpointFamily) -> AppResult<BudgetPermit> {
+        let app = self.app();
+        app.pool_budgets.try_acquire(family).ok_or_else(|| {
+            app.instance_metrics
+                .database_budget_rejections_total
+                .with_label_values(&[family.name()])
+                .inc();
+            custom(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Too many requests of this kind are being processed right now. Please try again later.",
+            )
+        })
+    }
+}
+
+/// How often the replica is checked again, both for its replication lag and
+/// after it failed to provide a connection
+pub const REPLICA_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Decides whether read-only connections are taken from the replica.
+///
+/// Reads go to the primary while the replica is more than `max_lag` behind
+/// it, and until the next check after the replica failed to provide a
+/// connection. The replica is checked by at most one request at a time, all
+/// other requests go by the result of the last check.
+#[derive(Debug)]
+pub struct ReplicaStatus {
+    max_lag: Option<Duration>,
+    healthy: AtomicBool,
+    last_check: Mutex<Option<Instant>>,
+}
+
+impl ReplicaStatus {
+    /// The replica's lag is not checked if `max_lag` is `None`
+    pub fn new(max_lag: Option<Duration>) -> Self {
+        Self {
+            max_lag,
+            healthy: AtomicBool::new(true),
+            last_check: Mutex::new(None),
+        }
+    }
+
+    /// The result of the last check
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    /// Returns whether reads should go to the replica, checking it first if
+    /// the last check is older than [`REPLICA_CHECK_INTERVAL`]
+    pub fn use_replica(&self, replica: &DieselPool) -> bool {
+        if let Ok(mut last_check) = self.last_check.try_lock() {
+            if last_check.map_or(true, |checked_at| {
+                checked_at.elapsed() >= REPLICA_CHECK_INTERVAL
+            }) {
+                *last_check = Some(Instant::now());
+                self.healthy.store(self.check(replica), Ordering::Relaxed);
+            }
+        }
+        self.is_healthy()
+    }
+
+    /// Sends reads to the primary until the next check
+    pub fn mark_unavailable(&self) {
+        self.healthy.store(false, Ordering::Relaxed);
+        if let Ok(mut last_check) = self.last_check.lock() {
+            *last_check = Some(Instant::now());
+        }
+    }
+
+    fn check(&self, replica: &DieselPool) -> bool {
+        let max_lag = match self.max_lag {
+            Some(max_lag) => max_lag,
+            None => return true,
+        };
+
+        let lag = replica
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| replication_lag(&conn).map_err(|e| e.to_string()));
+        match lag {
+            Ok(lag) if lag <= max_lag.as_secs_f64() => true,
+            Ok(lag) => {
+                warn!(%lag, "Replica is lagging behind, reading from the primary");
+                false
+            }
+            Err(error) => {
+                warn!(%error, "Failed to check the replica, reading from the primary");
+                false
+            }
+        }
+    }
+}
+
+/// How many seconds the replica connected to is behind its primary.
+///
+/// This is 0 if the replica has replayed everything it received, since the
+/// last replayed transaction may be old if nothing has been written since,
+/// and on connections to the primary itself.
+pub fn replication_lag(conn: &PgConnection) -> QueryResult<f64> {
+    use diesel::dsl::sql;
+    use diesel::sql_types::{Double, Nullable};
+
+    let lag: Option<f64> = diesel::select(sql::<Nullable<Double>>(
+        "CASE WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() THEN 0 \
+         ELSE EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()) END::float8",
+    ))
+    .get_result(conn)?;
+    Ok(lag.unwrap_or(0.0))
+}
+
+/// Obtain a connection from the pool, recording how long that took
+fn get_timed<'a, T: RequestExt + ?Sized>(
+    req: &T,
+    name: &str,
+    pool: &'a DieselPool,
+) -> Result<DieselPooledConn<'a>, r2d2::PoolError> {
+    let metrics = &req.app().instance_metrics;
+    let waiting = metrics.database_conns_waiting.with_label_values(&[name]);
+
+    let start = Instant::now();
+    waiting.inc();
+    let conn = pool.get();
+    waiting.dec();
+    metrics
+        .database_time_to_obtain_connection
+        .with_label_values(&[name])
+        .observe(start.elapsed().as_secs_f64());
+    conn
+}
+
+/// Endpoints with expensive queries, whose share of the connection pools can
+/// be capped with [`PoolBudgets`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndpointFamily {
+    /// `GET /crates`
+    Search,
+    /// `GET /crates/:crate_id/reverse_dependencies`
+    ReverseDependencies,
+    /// The downloads of user data exports
+    Dumps,
+}
+
+impl EndpointFamily {
+    pub const ALL: &'static [EndpointFamily] = &[
+        EndpointFamily::Search,
+        EndpointFamily::ReverseDependencies,
+        EndpointFamily::Dumps,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            EndpointFamily::Search => "search",
+            EndpointFamily::ReverseDependencies => "reverse_dependencies",
+            EndpointFamily::Dumps => "dumps",
+        }
+    }
+}
+
+/// Caps how many requests of an [`EndpointFamily`] may hold a database
+/// connection at the same time, so that a stampede of expensive requests
+/// can't starve cheaper endpoints like downloads of connections.
+///
+/// Budgets are configured with `DB_POOL_BUDGETS`, a comma separated list of
+/// `family=connections` pairs like `search=4,dumps=1`. Families without a
+/// budget are not limited.
+#[derive(Debug, Default)]
+pub struct PoolBudgets {
+    budgets: HashMap<EndpointFamily, Arc<Budget>>,
+}
+
+#[derive(Debug)]
+struct Budget {
+    limit: usize,
+    in_use: AtomicUsize,
+}
+
+impl PoolBudgets {
+    pub fn from_environment() -> Self {
+        match dotenv::var("DB_POOL_BUDGETS") {
+            Ok(budgets) => budgets.parse().expect("couldn't parse DB_POOL_BUDGETS"),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Reserves a connection of the family's budget until the permit is
+    /// dropped, or returns `None` if all of them are in use
+    pub fn try_acquire(&self, family: EndpointFamily) -> Option<BudgetPermit> {
+        let budget = match self.budgets.get(&family) {
+            Some(budget) => budget,
+            None => return Some(BudgetPermit { budget: None }),
+        };
+
+        let in_use = budget.in_use.fetch_add(1, Ordering::SeqCst);
+        if in_use >= budget.limit {
+            budget.in_use.fetch_sub(1, Ordering::SeqCst);
+            return None;
+        }
+        Some(BudgetPermit {
+            budget: Some(budget.clone()),
+        })
+    }
+
+    /// The configured limit and the number of connections in use of every
+    /// family with a budget
+    pub fn usage(&self) -> impl Iterator<Item = (EndpointFamily, usize, usize)> + '_ {
+        self.budgets.iter().map(|(family, budget)| {
+            let in_use = budget.in_use.load(Ordering::SeqCst);
+            (*family, budget.limit, in_use)
+        })
+    }
+}
+
+impl std::str::FromStr for PoolBudgets {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut budgets = HashMap::new();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (name, limit) = match pair.find('=') {
+                Some(pos) => (pair[..pos].trim(), pair[pos + 1..].trim()),
+                None => return Err(format!("expected `family=connections`, got `{}`", pair)),
+            };
+            let family = EndpointFamily::ALL
+                .iter()
+                .copied()
+                .find(|family| family.name() == name)
+                .ok_or_else(|| format!("unknown endpoint family `{}`", name))?;
+            let limit = limit
+                .parse()
+                .map_err(|_| format!("invalid number of connections `{}`", limit))?;
+            let in_use = AtomicUsize::new(0);
+            budgets.insert(family, Arc::new(Budget { limit, in_use }));
+        }
+        Ok(Self { budgets })
+    }
+}
+
+/// Holds one connection of an [`EndpointFamily`]'s budget until dropped
+#[derive(Debug)]
+pub struct BudgetPermit {
+    budget: Option<Arc<Budget>>,
+}
+
+impl Drop for BudgetPermit {
+    fn drop(&mut self) {
+        if let Some(budget) = &self.budget {
+            budget.in_use.fetch_sub(1, Ordering::SeqCst);
         }
     }
 }
@@ -130,3 +402,30 @@ impl CustomizeConnection<PgConnection, r2d2::Error> for ConnectionConfig {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budgets_cap_concurrent_permits() {
+        let budgets: PoolBudgets = "search=2, dumps=0".parse().unwrap();
+
+        let first = assert_some!(budgets.try_acquire(EndpointFamily::Search));
+        let _second = assert_some!(budgets.try_acquire(EndpointFamily::Search));
+        assert_none!(budgets.try_acquire(EndpointFamily::Search));
+        drop(first);
+        assert_some!(budgets.try_acquire(EndpointFamily::Search));
+
+        assert_none!(budgets.try_acquire(EndpointFamily::Dumps));
+        assert_some!(budgets.try_acquire(EndpointFamily::ReverseDependencies));
+    }
+
+    #[test]
+    fn invalid_budgets_are_rejected() {
+        assert_ok!("".parse::<PoolBudgets>());
+        assert_err!("search".parse::<PoolBudgets>());
+        assert_err!("search=many".parse::<PoolBudgets>());
+        assert_err!("publish=1".parse::<PoolBudgets>());
+    }
+}
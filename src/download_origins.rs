@@ -0,0 +1,319 @@
+//! Additional hosts that crate files are downloaded from, like buckets in
+//! other regions or CDNs
+//!
+//! The origins are configured in `DOWNLOAD_ORIGINS`, separated by commas.
+//! Every origin is a base URL, optionally followed by its weight and the
+//! regions it serves, separated by spaces:
+//!
+//! ```text
+//! DOWNLOAD_ORIGINS="https://us.static.crates.io 3 US CA, https://eu.static.crates.io 1 DE FR"
+//! ```
+//!
+//! Every origin must serve the same paths as the primary storage. The region
+//! of a download is the value of the `DOWNLOAD_REGION_HEADER` request header,
+//! the viewer country of CloudFront by default. Downloads from a region that
+//! healthy origins serve are redirected to one of them, and all others to any
+//! healthy origin, picked at random by weight. The weight is 1 if it is not
+//! given, and origins with a weight of 0 only serve their regions. Downloads
+//! are redirected to the primary storage if no origin is healthy.
+//!
+//! The `check_download_origins` job requests `HEALTH_CHECK_PATH` from every
+//! origin and records the results in the `download_origins` table. An
+//! origin is taken out of the rotation after `FAILURES_UNTIL_UNHEALTHY`
+//! failed checks in a row, and put back after the first successful one.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::HashSet;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::db::DieselPool;
+use crate::schema::download_origins;
+use crate::util::errors::AppResult;
+
+/// The file every origin has to serve for its health check
+pub const HEALTH_CHECK_PATH: &str = "health-check.txt";
+
+/// How many checks in a row have to fail before an origin isn't used anymore
+pub const FAILURES_UNTIL_UNHEALTHY: i32 = 3;
+
+/// How long each server process uses the health it last read from the
+/// database
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DownloadOrigin {
+    /// The base URL, without a trailing slash
+    pub url: String,
+    pub weight: u32,
+    /// The regions whose downloads prefer this origin, in upper case
+    pub regions: Vec<String>,
+}
+
+impl DownloadOrigin {
+    /// The URL of the file at the path at this origin
+    pub fn location(&self, path: &str) -> String {
+        format!("{}/{}", self.url, path.trim_start_matches('/'))
+    }
+
+    fn serves(&self, region: &str) -> bool {
+        self.regions
+            .iter()
+            .any(|serves| serves.eq_ignore_ascii_case(region))
+    }
+
+    /// The origins of `DOWNLOAD_ORIGINS`, which panics if they are invalid
+    pub fn from_environment() -> Vec<Self> {
+        match dotenv::var("DOWNLOAD_ORIGINS") {
+            Ok(origins) => Self::parse(&origins).unwrap_or_else(|e| panic!("{}", e)),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Parses the origins in the format of `DOWNLOAD_ORIGINS`
+    pub fn parse(origins: &str) -> Result<Vec<Self>, String> {
+        origins
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(|origin| {
+                let mut fields = origin.split_whitespace();
+                let url = fields.next().unwrap_or_default();
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    return Err(format!(
+                        "`DOWNLOAD_ORIGINS` must start with an http(s) URL, but has `{}`",
+                        origin
+                    ));
+                }
+                let mut fields = fields.peekable();
+                let weight = match fields.peek().map(|weight| weight.parse::<u32>()) {
+                    Some(Ok(weight)) => {
+                        fields.next();
+                        weight
+                    }
+                    _ => 1,
+                };
+                Ok(DownloadOrigin {
+                    url: url.trim_end_matches('/').into(),
+                    weight,
+                    regions: fields.map(str::to_uppercase).collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Picks the origin for a download from the region, out of the origins that
+/// are healthy. The `point` decides which origin is picked by weight, like a
+/// random number.
+pub fn choose<'a>(
+    origins: &'a [DownloadOrigin],
+    region: Option<&str>,
+    is_healthy: impl Fn(&DownloadOrigin) -> bool,
+    point: u32,
+) -> Option<&'a DownloadOrigin> {
+    let healthy = origins
+        .iter()
+        .filter(|origin| is_healthy(origin))
+        .collect::<Vec<_>>();
+    let regional = healthy
+        .iter()
+        .copied()
+        .filter(|origin| region.map_or(false, |region| origin.serves(region)))
+        .collect::<Vec<_>>();
+    let candidates = if regional.is_empty() {
+        healthy
+    } else {
+        regional
+    };
+
+    let total = candidates.iter().map(|origin| origin.weight).sum::<u32>();
+    if total == 0 {
+        return candidates.first().copied();
+    }
+    let mut point = point % total;
+    for origin in candidates {
+        if point < origin.weight {
+            return Some(origin);
+        }
+        point -= origin.weight;
+    }
+    None
+}
+
+/// The result of the last health checks of an origin
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[primary_key(url)]
+#[table_name = "download_origins"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct OriginHealth {
+    pub url: String,
+    pub healthy: bool,
+    pub consecutive_failures: i32,
+    pub last_error: Option<String>,
+    pub checked_at: NaiveDateTime,
+}
+
+impl OriginHealth {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<OriginHealth>> {
+        download_origins::table
+            .order(download_origins::url)
+            .load(conn)
+    }
+
+    /// Records a health check of the origin, with the error if it failed
+    pub fn record(conn: &PgConnection, url: &str, error: Option<String>) -> QueryResult<Self> {
+        let previous_failures = download_origins::table
+            .find(url)
+            .select(download_origins::consecutive_failures)
+            .first::<i32>(conn)
+            .optional()?
+            .unwrap_or(0);
+        let consecutive_failures = match error {
+            Some(_) => previous_failures + 1,
+            None => 0,
+        };
+        let health = OriginHealth {
+            url: url.into(),
+            healthy: consecutive_failures < FAILURES_UNTIL_UNHEALTHY,
+            consecutive_failures,
+            last_error: error,
+            checked_at: chrono::Utc::now().naive_utc(),
+        };
+        diesel::insert_into(download_origins::table)
+            .values(&health)
+            .on_conflict(download_origins::url)
+            .do_update()
+            .set(&health)
+            .get_result(conn)
+    }
+
+    /// Forgets the origins that aren't configured anymore
+    pub fn retain(conn: &PgConnection, urls: Vec<String>) -> QueryResult<usize> {
+        diesel::delete(download_origins::table.filter(download_origins::url.ne_all(urls)))
+            .execute(conn)
+    }
+}
+
+/// The origins that are out of the rotation, kept by the `App`
+#[derive(Debug, Default)]
+pub struct DownloadOriginHealth {
+    cached: RwLock<Option<(Instant, HashSet<String>)>>,
+}
+
+impl DownloadOriginHealth {
+    /// Picks the origin for a download from the region, or `None` to use the
+    /// primary storage. The health is read from the database at most once
+    /// per `REFRESH_INTERVAL`.
+    pub fn select<'a>(
+        &self,
+        pool: &DieselPool,
+        origins: &'a [DownloadOrigin],
+        region: Option<&str>,
+    ) -> Option<&'a DownloadOrigin> {
+        if origins.is_empty() {
+            return None;
+        }
+        let point = rand::random();
+
+        if let Some((read_at, unhealthy)) = &*self.cached.read().unwrap() {
+            if read_at.elapsed() < REFRESH_INTERVAL {
+                return choose(origins, region, |o| !unhealthy.contains(&o.url), point);
+            }
+        }
+
+        let unhealthy = match unhealthy_origins(pool) {
+            Ok(unhealthy) => unhealthy,
+            Err(e) => {
+                // Downloads keep working while the database is unavailable,
+                // with the health that was read last
+                eprintln!("Failed to read the health of the download origins: {}", e);
+                self.cached
+                    .read()
+                    .unwrap()
+                    .as_ref()
+                    .map(|(_, unhealthy)| unhealthy.clone())
+                    .unwrap_or_default()
+            }
+        };
+        let origin = choose(origins, region, |o| !unhealthy.contains(&o.url), point);
+        *self.cached.write().unwrap() = Some((Instant::now(), unhealthy));
+        origin
+    }
+
+    /// Makes the next download read the health from the database
+    pub fn invalidate(&self) {
+        *self.cached.write().unwrap() = None;
+    }
+}
+
+fn unhealthy_origins(pool: &DieselPool) -> AppResult<HashSet<String>> {
+    let conn = pool.get()?;
+    let unhealthy = download_origins::table
+        .filter(download_origins::healthy.eq(false))
+        .select(download_origins::url)
+        .load(&*conn)?;
+    Ok(unhealthy.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn origins() -> Vec<DownloadOrigin> {
+        DownloadOrigin::parse(
+            "https://us.static.crates.io/ 3 us ca, https://eu.static.crates.io DE FR, \
+             https://asia.static.crates.io 0 JP",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn origins_are_parsed() {
+        let origins = origins();
+        assert_eq!(origins[0].url, "https://us.static.crates.io");
+        assert_eq!(origins[0].weight, 3);
+        assert_eq!(origins[0].regions, vec!["US", "CA"]);
+        assert_eq!(origins[1].weight, 1);
+        assert_eq!(origins[1].regions, vec!["DE", "FR"]);
+        assert_eq!(
+            origins[2].location("/crates/foo/foo-1.0.0.crate"),
+            "https://asia.static.crates.io/crates/foo/foo-1.0.0.crate"
+        );
+
+        assert_eq!(DownloadOrigin::parse(""), Ok(vec![]));
+        assert!(DownloadOrigin::parse("static.crates.io 1").is_err());
+    }
+
+    #[test]
+    fn regions_prefer_their_healthy_origins() {
+        let origins = origins();
+        let url = |region, is_healthy: &dyn Fn(&DownloadOrigin) -> bool, point| {
+            choose(&origins, region, is_healthy, point).map(|origin| origin.url.as_str())
+        };
+        let all = |_: &DownloadOrigin| true;
+
+        assert_eq!(
+            url(Some("jp"), &all, 7),
+            Some("https://asia.static.crates.io")
+        );
+        assert_eq!(
+            url(Some("DE"), &all, 1),
+            Some("https://eu.static.crates.io")
+        );
+        // Everyone else is spread across the origins by weight
+        assert_eq!(url(None, &all, 2), Some("https://us.static.crates.io"));
+        assert_eq!(
+            url(Some("BR"), &all, 3),
+            Some("https://eu.static.crates.io")
+        );
+
+        let not_eu = |origin: &DownloadOrigin| !origin.url.contains("eu");
+        assert_eq!(
+            url(Some("DE"), &not_eu, 3),
+            Some("https://us.static.crates.io")
+        );
+        assert_eq!(url(Some("DE"), &|_| false, 3), None);
+    }
+}
@@ -1,11 +1,29 @@
-use std::path::Path;
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use diesel::PgConnection;
+use lazy_static::lazy_static;
+use serde::Serialize;
 
-use crate::util::errors::{server_error, AppResult};
+use crate::background_jobs::Job;
+use crate::swirl::{EnqueueError, PerformError};
+use crate::util::errors::AppResult;
 
-use lettre::transport::file::FileTransport;
-use lettre::transport::smtp::authentication::{Credentials, Mechanism};
-use lettre::transport::smtp::SmtpTransport;
-use lettre::{Message, Transport};
+mod backend;
+mod templates;
+
+pub use self::backend::{
+    from_environment, EmailBackend, FileBackend, OutgoingEmail, SendGridBackend, SmtpBackend,
+};
+pub use self::templates::EmailTemplates;
+
+lazy_static! {
+    static ref BACKEND: Box<dyn EmailBackend> =
+        from_environment().expect("invalid email backend configuration");
+    static ref TEMPLATES: EmailTemplates =
+        EmailTemplates::from_environment().expect("invalid email templates");
+}
+
+/// How long a failed email is retried before it is given up on
+const MAX_RETRY_HOURS: i64 = 48;
 
 #[derive(Debug)]
 pub struct MailgunConfigVars {
@@ -29,24 +47,26 @@ pub fn init_config_vars() -> Option<MailgunConfigVars> {
     }
 }
 
-fn build_email(
-    recipient: &str,
-    subject: &str,
-    body: String,
-    mailgun_config: &Option<MailgunConfigVars>,
-) -> AppResult<Message> {
-    let sender = mailgun_config
-        .as_ref()
-        .map(|s| s.smtp_login.as_str())
-        .unwrap_or("test@localhost");
+/// The content of an email, optionally with an HTML version that mail
+/// clients show instead of the text
+#[derive(Debug, Clone)]
+pub struct EmailBody {
+    pub text: String,
+    pub html: Option<String>,
+}
 
-    let email = Message::builder()
-        .to(recipient.parse()?)
-        .from(sender.parse()?)
-        .subject(subject)
-        .body(body)?;
+impl From<String> for EmailBody {
+    fn from(text: String) -> Self {
+        Self { text, html: None }
+    }
+}
 
-    Ok(email)
+impl EmailBody {
+    /// Renders the text and HTML versions of the email `template`, see
+    /// [`EmailTemplates`]
+    pub fn render<T: Serialize>(template: &str, context: &T) -> AppResult<Self> {
+        TEMPLATES.render(template, context)
+    }
 }
 
 /// Attempts to send a confirmation email. Swallows all errors.
@@ -55,8 +75,8 @@ fn build_email(
 /// have an invalid email set in their GitHub profile, and we should let them sign in even though
 /// we're trying to silently use their invalid address during signup and can't send them an email.
 /// Use `try_send_user_confirm_email` when the user is directly trying to set their email.
-pub fn send_user_confirm_email(email: &str, user_name: &str, token: &str) {
-    let _ = try_send_user_confirm_email(email, user_name, token);
+pub fn send_user_confirm_email(conn: &PgConnection, email: &str, user_name: &str, token: &str) {
+    let _ = try_send_user_confirm_email(conn, email, user_name, token);
 }
 
 /// Attempts to send a confirmation email and returns errors.
@@ -64,22 +84,61 @@ pub fn send_user_confirm_email(email: &str, user_name: &str, token: &str) {
 /// For use in cases where we want to fail if an email is bad because the user is directly trying
 /// to set their email correctly, as opposed to us silently trying to use the email from their
 /// GitHub profile during signup.
-pub fn try_send_user_confirm_email(email: &str, user_name: &str, token: &str) -> AppResult<()> {
+pub fn try_send_user_confirm_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    token: &str,
+) -> AppResult<()> {
     // Create a URL with token string as path to send to user
     // If user clicks on path, look email/user up in database,
     // make sure tokens match
 
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        token: &'a str,
+    }
+
     let subject = "Please confirm your email address";
-    let body = format!(
-        "Hello {}! Welcome to Crates.io. Please click the
-link below to verify your email address. Thank you!\n
-https://{}/confirm/{}",
-        user_name,
-        crate::config::domain_name(),
-        token
-    );
+    let body = EmailBody::render("confirm-email", &Context { user_name, token })?;
+    send_email(conn, email, subject, body)
+}
 
-    send_email(email, subject, body)
+/// Attempts to send the confirmations of an email address change to both the
+/// old and the new address. Swallows all errors.
+pub fn send_email_change_emails(
+    conn: &PgConnection,
+    old_email: &str,
+    new_email: &str,
+    user_name: &str,
+    old_email_token: &str,
+    new_email_token: &str,
+) {
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        new_email: &'a str,
+        token: &'a str,
+        expires_in_hours: i64,
+    }
+
+    let subject = "Please confirm the change of your email address";
+    let emails = [
+        ("email-change-old", old_email, old_email_token),
+        ("email-change-new", new_email, new_email_token),
+    ];
+    for &(template, email, token) in &emails {
+        let context = Context {
+            user_name,
+            new_email,
+            token,
+            expires_in_hours: crate::models::Email::TOKEN_MAX_AGE_HOURS,
+        };
+        if let Ok(body) = EmailBody::render(template, &context) {
+            let _ = send_email(conn, email, subject, body);
+        }
+    }
 }
 
 /// Attempts to send a crate owner invitation email. Swallows all errors.
@@ -87,66 +146,336 @@ https://{}/confirm/{}",
 /// Whether or not the email is sent, the invitation entry will be created in
 /// the database and the user will see the invitation when they visit
 /// https://crates.io/me/pending-invites/.
-pub fn send_owner_invite_email(email: &str, user_name: &str, crate_name: &str, token: &str) {
+pub fn send_owner_invite_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    crate_name: &str,
+    token: &str,
+) {
+    #[derive(Serialize)]
+    struct Context<'a> {
+        inviter: &'a str,
+        crate_name: &'a str,
+        token: &'a str,
+    }
+
     let subject = "Crate ownership invitation";
-    let body = format!(
-        "{} has invited you to become an owner of the crate {}!\n
-Visit https://{domain}/accept-invite/{} to accept this invitation,
-or go to https://{domain}/me/pending-invites to manage all of your crate ownership invitations.",
-        user_name,
+    let context = Context {
+        inviter: user_name,
         crate_name,
         token,
-        domain = crate::config::domain_name()
-    );
+    };
+    if let Ok(body) = EmailBody::render("owner-invite", &context) {
+        let _ = send_email(conn, email, subject, body);
+    }
+}
+
+/// Attempts to notify a user that their account has been locked. Swallows all errors.
+pub fn send_account_locked_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    reason: &str,
+    until: Option<NaiveDateTime>,
+) {
+    let subject = "Your crates.io account has been locked";
+    let duration = match until {
+        Some(until) => format!("until {}", until.format("%Y-%m-%d at %H:%M:%S UTC")),
+        None => "indefinitely".to_string(),
+    };
+
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        duration: &'a str,
+        reason: &'a str,
+    }
 
-    let _ = send_email(email, subject, body);
+    let context = Context {
+        user_name,
+        duration: &duration,
+        reason,
+    };
+    if let Ok(body) = EmailBody::render("account-locked", &context) {
+        let _ = send_email(conn, email, subject, body);
+    }
 }
 
-fn send_email(recipient: &str, subject: &str, body: String) -> AppResult<()> {
-    let mailgun_config = init_config_vars();
-    let email = build_email(recipient, subject, body, &mailgun_config)?;
+/// Queues an email notifying a crate owner that versions of their crate were
+/// yanked by the crates.io team, see `queue_email`.
+pub fn queue_bulk_yank_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    crate_name: &str,
+    versions: &[String],
+    reason: &str,
+) -> Result<(), EnqueueError> {
+    let subject = format!("Versions of {} have been yanked", crate_name);
 
-    match mailgun_config {
-        Some(mailgun_config) => {
-            let transport = SmtpTransport::relay(&mailgun_config.smtp_server)
-                .unwrap()
-                .credentials(Credentials::new(
-                    mailgun_config.smtp_login,
-                    mailgun_config.smtp_password,
-                ))
-                .authentication(vec![Mechanism::Plain])
-                .build();
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        crate_name: &'a str,
+        versions: &'a [String],
+        reason: &'a str,
+    }
 
-            let result = transport.send(&email);
-            result.map_err(|_| server_error("Error in sending email"))?;
-        }
-        None => {
-            let sender = FileTransport::new(Path::new("/tmp"));
-            let result = sender.send(&email);
-            result.map_err(|_| server_error("Email file could not be generated"))?;
-        }
+    let context = Context {
+        user_name,
+        crate_name,
+        versions,
+        reason,
+    };
+    match EmailBody::render("bulk-yank", &context) {
+        Ok(body) => queue_email(conn, email, &subject, body),
+        Err(_) => Ok(()),
+    }
+}
+
+/// Attempts to notify a crate owner that a new version of their crate was
+/// published by another owner. Swallows all errors.
+pub fn send_new_version_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    crate_name: &str,
+    version: &str,
+    publisher: &str,
+    token_name: Option<&str>,
+    published_at: NaiveDateTime,
+) {
+    let subject = format!("{} {} has been published", crate_name, version);
+
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        crate_name: &'a str,
+        version: &'a str,
+        publisher: &'a str,
+        token_name: Option<&'a str>,
+        published_at: String,
+    }
+
+    let context = Context {
+        user_name,
+        crate_name,
+        version,
+        publisher,
+        token_name,
+        published_at: published_at.format("%Y-%m-%d at %H:%M:%S UTC").to_string(),
+    };
+    if let Ok(body) = EmailBody::render("new-version", &context) {
+        let _ = send_email(conn, email, &subject, body);
+    }
+}
+
+/// Attempts to notify an owner of crates that depend on an affected version
+/// about a new security advisory. Swallows all errors.
+pub fn send_advisory_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    advisory_id: &str,
+    title: &str,
+    affected_crate: &str,
+    dependents: &[&str],
+    date: NaiveDate,
+) {
+    let subject = format!("Security advisory {} for {}", advisory_id, affected_crate);
+
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        advisory_id: &'a str,
+        title: &'a str,
+        affected_crate: &'a str,
+        dependents: &'a [&'a str],
+        date: NaiveDate,
+    }
+
+    let context = Context {
+        user_name,
+        advisory_id,
+        title,
+        affected_crate,
+        dependents,
+        date,
+    };
+    if let Ok(body) = EmailBody::render("advisory", &context) {
+        let _ = send_email(conn, email, &subject, body);
+    }
+}
+
+/// What happened to the crates a user follows since their previous digest
+#[derive(Debug, Default, Serialize)]
+pub struct WeeklyDigest {
+    pub new_versions: Vec<DigestVersion>,
+    pub yanked_versions: Vec<DigestVersion>,
+    pub advisories: Vec<DigestAdvisory>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DigestVersion {
+    pub crate_name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DigestAdvisory {
+    pub crate_name: String,
+    pub id: String,
+    pub title: String,
+}
+
+impl WeeklyDigest {
+    pub fn is_empty(&self) -> bool {
+        self.new_versions.is_empty()
+            && self.yanked_versions.is_empty()
+            && self.advisories.is_empty()
+    }
+}
+
+/// Attempts to send the weekly digest of the crates a user follows.
+/// Swallows all errors.
+pub fn send_weekly_digest_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    digest: &WeeklyDigest,
+) {
+    let _ = try_send_weekly_digest_email(conn, email, user_name, digest);
+}
+
+fn try_send_weekly_digest_email(
+    conn: &PgConnection,
+    email: &str,
+    user_name: &str,
+    digest: &WeeklyDigest,
+) -> AppResult<()> {
+    let subject = "Your weekly crates.io digest";
+    let body = render_weekly_digest(user_name, digest)?;
+    send_email(conn, email, subject, body)
+}
+
+fn render_weekly_digest(user_name: &str, digest: &WeeklyDigest) -> AppResult<EmailBody> {
+    #[derive(Serialize)]
+    struct Context<'a> {
+        user_name: &'a str,
+        #[serde(flatten)]
+        digest: &'a WeeklyDigest,
+    }
+
+    EmailBody::render("weekly-digest", &Context { user_name, digest })
+}
+
+/// Sends an email with the configured backend.
+///
+/// Invalid recipients are rejected right away. If the backend fails, the
+/// email is retried by a background job instead, so the caller can treat it
+/// as sent.
+fn send_email(
+    conn: &PgConnection,
+    recipient: &str,
+    subject: &str,
+    body: impl Into<EmailBody>,
+) -> AppResult<()> {
+    backend::validate_recipient(recipient)?;
+
+    let email = OutgoingEmail::new(recipient, subject, body.into());
+    if let Err(error) = BACKEND.send(&email) {
+        warn!(%error, "Failed to send email, retrying in the background");
+        let enqueued_at = Utc::now().naive_utc();
+        Job::SendEmail { email, enqueued_at }.enqueue(conn)?;
     }
 
     Ok(())
 }
 
+/// Queues an email for the `SendEmail` job instead of sending it right away,
+/// for jobs that notify users within a transaction. The email is only sent
+/// if the transaction commits, so a job that fails and is retried doesn't
+/// send it twice. Invalid recipients are skipped.
+fn queue_email(
+    conn: &PgConnection,
+    recipient: &str,
+    subject: &str,
+    body: impl Into<EmailBody>,
+) -> Result<(), EnqueueError> {
+    if let Err(error) = backend::validate_recipient(recipient) {
+        warn!(%error, "Not sending email to an invalid recipient");
+        return Ok(());
+    }
+
+    let email = OutgoingEmail::new(recipient, subject, body.into());
+    let enqueued_at = Utc::now().naive_utc();
+    Job::SendEmail { email, enqueued_at }.enqueue(conn)
+}
+
+/// Retries sending an email. The job fails, and is retried again later, until
+/// the email is sent or it has been retried for `MAX_RETRY_HOURS`.
+pub fn perform_send_email(
+    email: OutgoingEmail,
+    enqueued_at: NaiveDateTime,
+) -> Result<(), PerformError> {
+    let error = match BACKEND.send(&email) {
+        Ok(()) => return Ok(()),
+        Err(error) => error,
+    };
+
+    let retrying_for = Utc::now().naive_utc() - enqueued_at;
+    if retrying_for > chrono::Duration::hours(MAX_RETRY_HOURS) {
+        error!(%error, subject = %email.subject, "Giving up on sending email");
+        return Ok(());
+    }
+    Err(error.into())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn sending_to_invalid_email_fails() {
-        let result = send_email(
-            "String.Format(\"{0}.{1}@live.com\", FirstName, LastName)",
-            "test",
-            "test".to_string(),
-        );
+        let result =
+            backend::validate_recipient("String.Format(\"{0}.{1}@live.com\", FirstName, LastName)");
         assert_err!(result);
     }
 
     #[test]
     fn sending_to_valid_email_succeeds() {
-        let result = send_email("someone@example.com", "test", "test".to_string());
+        assert_ok!(backend::validate_recipient("someone@example.com"));
+
+        let email = OutgoingEmail::new("someone@example.com", "test", "test".to_string().into());
+        let result = FileBackend::new("/tmp").send(&email);
         assert_ok!(result);
     }
+
+    #[test]
+    fn weekly_digests_are_rendered_as_text_and_html() {
+        let digest = WeeklyDigest {
+            new_versions: vec![DigestVersion {
+                crate_name: "foo".into(),
+                version: "1.1.0".into(),
+            }],
+            yanked_versions: vec![],
+            advisories: vec![DigestAdvisory {
+                crate_name: "bar".into(),
+                id: "RUSTSEC-2021-0001".into(),
+                title: "Use after free in <Bar as Drop>".into(),
+            }],
+        };
+
+        let body = assert_ok!(render_weekly_digest("someone", &digest));
+        assert!(body.text.contains("\nNew versions:\n- foo 1.1.0: https://"));
+        assert!(!body.text.contains("Yanked versions"));
+        assert!(body
+            .text
+            .contains("Use after free in <Bar as Drop> (RUSTSEC-2021-0001)"));
+
+        let html = assert_some!(body.html);
+        assert!(html.contains("Use after free in &lt;Bar as Drop&gt;"));
+        assert!(!html.contains("Yanked versions"));
+    }
 }
@@ -0,0 +1,261 @@
+//! The services that deliver emails.
+//!
+//! The backend is picked by the `EMAIL_BACKEND` environment variable, see
+//! [`from_environment`]. Without it, emails are sent over SMTP if the Mailgun
+//! variables are set and written to files otherwise.
+
+use anyhow::{anyhow, Context};
+use lettre::message::{header, Mailbox, MultiPart, SinglePart};
+use lettre::transport::file::FileTransport;
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::SmtpTransport;
+use lettre::{Message, Transport};
+use reqwest::blocking::Client;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use super::{init_config_vars, EmailBody};
+
+/// An email that is ready to be delivered.
+///
+/// It is stored in the data of a background job when sending it fails, so it
+/// can be retried later.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingEmail {
+    pub recipient: String,
+    pub subject: String,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+impl OutgoingEmail {
+    pub fn new(recipient: &str, subject: &str, body: EmailBody) -> Self {
+        Self {
+            recipient: recipient.to_string(),
+            subject: subject.to_string(),
+            text: body.text,
+            html: body.html,
+        }
+    }
+
+    fn to_message(&self, sender: &str) -> anyhow::Result<Message> {
+        let builder = Message::builder()
+            .to(self.recipient.parse()?)
+            .from(sender.parse()?)
+            .subject(&self.subject);
+
+        let message = match &self.html {
+            Some(html) => builder.multipart(
+                MultiPart::alternative()
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(header::ContentType(
+                                "text/plain; charset=utf-8".parse().unwrap(),
+                            ))
+                            .body(self.text.clone()),
+                    )
+                    .singlepart(
+                        SinglePart::builder()
+                            .header(header::ContentType(
+                                "text/html; charset=utf-8".parse().unwrap(),
+                            ))
+                            .body(html.clone()),
+                    ),
+            )?,
+            None => builder.body(self.text.clone())?,
+        };
+
+        Ok(message)
+    }
+}
+
+/// A service that delivers emails.
+///
+/// Errors returned by `send` are treated as transient, so the email is
+/// retried later. Invalid recipients are rejected before an email reaches a
+/// backend.
+pub trait EmailBackend: Send + Sync {
+    fn send(&self, email: &OutgoingEmail) -> anyhow::Result<()>;
+}
+
+/// Sends emails with an SMTP server like the one of Mailgun.
+///
+/// The transport keeps a pool of connections to the server, so it is created
+/// once and shared by all emails.
+#[allow(missing_debug_implementations)]
+pub struct SmtpBackend {
+    sender: String,
+    transport: SmtpTransport,
+}
+
+impl SmtpBackend {
+    /// Connects to `server`, using the `login` as the sender address
+    pub fn new(server: &str, login: String, password: String) -> anyhow::Result<Self> {
+        let transport = SmtpTransport::relay(server)?
+            .credentials(Credentials::new(login.clone(), password))
+            .authentication(vec![Mechanism::Plain])
+            .build();
+
+        Ok(Self {
+            sender: login,
+            transport,
+        })
+    }
+}
+
+impl EmailBackend for SmtpBackend {
+    fn send(&self, email: &OutgoingEmail) -> anyhow::Result<()> {
+        let message = email.to_message(&self.sender)?;
+        self.transport
+            .send(&message)
+            .context("Error in sending email")?;
+        Ok(())
+    }
+}
+
+/// Writes emails to files instead of sending them, for development and tests
+#[derive(Debug)]
+pub struct FileBackend {
+    transport: FileTransport,
+}
+
+impl FileBackend {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            transport: FileTransport::new(directory.into()),
+        }
+    }
+}
+
+impl EmailBackend for FileBackend {
+    fn send(&self, email: &OutgoingEmail) -> anyhow::Result<()> {
+        let message = email.to_message("test@localhost")?;
+        self.transport
+            .send(&message)
+            .context("Email file could not be generated")?;
+        Ok(())
+    }
+}
+
+/// Sends emails with the v3 mail send API of SendGrid, or another provider
+/// that implements the same API
+#[derive(Debug)]
+pub struct SendGridBackend {
+    client: Client,
+    endpoint: String,
+    api_key: String,
+    sender: String,
+}
+
+impl SendGridBackend {
+    const DEFAULT_ENDPOINT: &'static str = "https://api.sendgrid.com/v3/mail/send";
+
+    pub fn new(api_key: String, sender: String, endpoint: Option<String>) -> anyhow::Result<Self> {
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        Ok(Self {
+            client,
+            endpoint: endpoint.unwrap_or_else(|| Self::DEFAULT_ENDPOINT.to_string()),
+            api_key,
+            sender,
+        })
+    }
+
+    fn request_body(&self, email: &OutgoingEmail) -> serde_json::Value {
+        let mut content = vec![json!({ "type": "text/plain", "value": email.text })];
+        if let Some(html) = &email.html {
+            content.push(json!({ "type": "text/html", "value": html }));
+        }
+
+        json!({
+            "personalizations": [{ "to": [{ "email": email.recipient }] }],
+            "from": { "email": self.sender },
+            "subject": email.subject,
+            "content": content,
+        })
+    }
+}
+
+impl EmailBackend for SendGridBackend {
+    fn send(&self, email: &OutgoingEmail) -> anyhow::Result<()> {
+        self.client
+            .post(&self.endpoint)
+            .bearer_auth(&self.api_key)
+            .json(&self.request_body(email))
+            .send()?
+            .error_for_status()
+            .context("Error in sending email")?;
+        Ok(())
+    }
+}
+
+/// Checks that an address can be sent to before the email is handed to a
+/// backend, where a failure would be retried
+pub(super) fn validate_recipient(recipient: &str) -> Result<(), lettre::address::AddressError> {
+    recipient.parse::<Mailbox>().map(|_| ())
+}
+
+/// Creates the backend configured by the environment.
+///
+/// `EMAIL_BACKEND` is one of:
+///
+/// - `smtp`, configured by `MAILGUN_SMTP_LOGIN`, `MAILGUN_SMTP_PASSWORD` and
+///   `MAILGUN_SMTP_SERVER`
+/// - `sendgrid`, configured by `SENDGRID_API_KEY`, `EMAIL_FROM` and
+///   optionally `SENDGRID_API_URL`
+/// - `file`, which writes emails to `EMAIL_FILE_DIR` or `/tmp`
+pub fn from_environment() -> anyhow::Result<Box<dyn EmailBackend>> {
+    let kind = match dotenv::var("EMAIL_BACKEND") {
+        Ok(kind) => kind,
+        Err(_) if init_config_vars().is_some() => "smtp".into(),
+        Err(_) => "file".into(),
+    };
+
+    let backend: Box<dyn EmailBackend> = match kind.as_str() {
+        "smtp" => {
+            let config = init_config_vars().ok_or_else(|| {
+                anyhow!("the smtp email backend needs the MAILGUN_SMTP_* variables")
+            })?;
+            Box::new(SmtpBackend::new(
+                &config.smtp_server,
+                config.smtp_login,
+                config.smtp_password,
+            )?)
+        }
+        "sendgrid" => Box::new(SendGridBackend::new(
+            dotenv::var("SENDGRID_API_KEY").context("SENDGRID_API_KEY is not set")?,
+            dotenv::var("EMAIL_FROM").context("EMAIL_FROM is not set")?,
+            dotenv::var("SENDGRID_API_URL").ok(),
+        )?),
+        "file" => Box::new(FileBackend::new(
+            dotenv::var("EMAIL_FILE_DIR").unwrap_or_else(|_| "/tmp".into()),
+        )),
+        kind => return Err(anyhow!("unknown email backend `{}`", kind)),
+    };
+
+    Ok(backend)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sendgrid_requests_contain_both_versions() {
+        let backend = SendGridBackend::new("key".into(), "noreply@crates.io".into(), None).unwrap();
+        let email = OutgoingEmail {
+            recipient: "someone@example.com".into(),
+            subject: "test".into(),
+            text: "text".into(),
+            html: Some("<p>html</p>".into()),
+        };
+
+        let body = backend.request_body(&email);
+        assert_eq!(
+            body["personalizations"][0]["to"][0]["email"],
+            "someone@example.com"
+        );
+        assert_eq!(body["from"]["email"], "noreply@crates.io");
+        assert_eq!(body["content"][0]["type"], "text/plain");
+        assert_eq!(body["content"][1]["value"], "<p>html</p>");
+    }
+}
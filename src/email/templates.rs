@@ -0,0 +1,193 @@
+//! The Handlebars templates emails are rendered from.
+//!
+//! Every email has a `<name>.txt.hbs` and a `<name>.html.hbs` template in
+//! `src/email/templates`, which are built into the binary. The HTML version is
+//! wrapped in `layout.html.hbs`, which gets the rendered email as `content`.
+//!
+//! Deployments can brand their emails by setting `EMAIL_TEMPLATE_DIR` to a
+//! directory with files of the same names, which replace the built-in
+//! templates one by one.
+
+use anyhow::Context;
+use handlebars::Handlebars;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+use super::EmailBody;
+use crate::util::errors::AppResult;
+
+macro_rules! templates {
+    ($($name:literal),* $(,)?) => {
+        &[$(($name, include_str!(concat!("templates/", $name, ".hbs")))),*]
+    };
+}
+
+/// The file names of the built-in templates without the `.hbs` extension,
+/// and their contents
+const BUILT_IN: &[(&str, &str)] = templates![
+    "layout.html",
+    "account-locked.html",
+    "account-locked.txt",
+    "advisory.html",
+    "advisory.txt",
+    "bulk-yank.html",
+    "bulk-yank.txt",
+    "confirm-email.html",
+    "confirm-email.txt",
+    "email-change-new.html",
+    "email-change-new.txt",
+    "email-change-old.html",
+    "email-change-old.txt",
+    "new-version.html",
+    "new-version.txt",
+    "owner-invite.html",
+    "owner-invite.txt",
+    "weekly-digest.html",
+    "weekly-digest.txt",
+];
+
+#[derive(Debug)]
+pub struct EmailTemplates {
+    html: Handlebars<'static>,
+    text: Handlebars<'static>,
+}
+
+impl EmailTemplates {
+    /// Loads the built-in templates, replacing the ones that have a file in
+    /// `override_dir`
+    pub fn load(override_dir: Option<&Path>) -> anyhow::Result<Self> {
+        let mut html = Handlebars::new();
+        html.set_strict_mode(true);
+        let mut text = Handlebars::new();
+        text.set_strict_mode(true);
+        text.register_escape_fn(handlebars::no_escape);
+
+        for &(name, built_in) in BUILT_IN {
+            let file_name = format!("{}.hbs", name);
+            let path = override_dir
+                .map(|dir| dir.join(&file_name))
+                .filter(|path| path.exists());
+            let source = match path {
+                Some(path) => fs::read_to_string(&path)
+                    .with_context(|| format!("could not read `{}`", path.display()))?,
+                None => built_in.to_string(),
+            };
+
+            let registry = if name.ends_with(".html") {
+                &mut html
+            } else {
+                &mut text
+            };
+            registry
+                .register_template_string(name, source)
+                .with_context(|| format!("invalid email template `{}`", file_name))?;
+        }
+
+        Ok(Self { html, text })
+    }
+
+    /// Loads the templates, with the ones in `EMAIL_TEMPLATE_DIR` if it is set
+    pub fn from_environment() -> anyhow::Result<Self> {
+        let dir = dotenv::var("EMAIL_TEMPLATE_DIR").ok();
+        Self::load(dir.as_deref().map(Path::new))
+    }
+
+    /// Renders both versions of the email `name`. Besides the fields of the
+    /// `context`, templates can use the `domain` of the deployment.
+    /// Values are escaped in the HTML version only.
+    pub fn render<T: Serialize>(&self, name: &str, context: &T) -> AppResult<EmailBody> {
+        #[derive(Serialize)]
+        struct WithDomain<'a, T> {
+            domain: &'a str,
+            #[serde(flatten)]
+            context: &'a T,
+        }
+
+        #[derive(Serialize)]
+        struct Layout<'a> {
+            domain: &'a str,
+            content: &'a str,
+        }
+
+        let domain = crate::config::domain_name();
+        let context = WithDomain {
+            domain: &domain,
+            context,
+        };
+        let text = self.text.render(&format!("{}.txt", name), &context)?;
+        let content = self.html.render(&format!("{}.html", name), &context)?;
+        let html = self.html.render(
+            "layout.html",
+            &Layout {
+                domain: &domain,
+                content: &content,
+            },
+        )?;
+
+        Ok(EmailBody {
+            text,
+            html: Some(html),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Confirm<'a> {
+        user_name: &'a str,
+        token: &'a str,
+    }
+
+    #[test]
+    fn html_is_wrapped_in_the_layout() {
+        let templates = EmailTemplates::load(None).unwrap();
+        let context = Confirm {
+            user_name: "<someone>",
+            token: "abc",
+        };
+
+        let body = assert_ok!(templates.render("confirm-email", &context));
+        assert!(body.text.starts_with("Hello <someone>!"));
+        assert!(body.text.contains("/confirm/abc"));
+
+        let html = assert_some!(body.html);
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<p>Hello &lt;someone&gt;!"));
+    }
+
+    #[test]
+    fn templates_can_be_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("confirm-email.txt.hbs"),
+            "Welcome, {{user_name}}!",
+        )
+        .unwrap();
+        fs::write(
+            dir.path().join("layout.html.hbs"),
+            "<main>{{{content}}}</main>",
+        )
+        .unwrap();
+
+        let templates = EmailTemplates::load(Some(dir.path())).unwrap();
+        let context = Confirm {
+            user_name: "someone",
+            token: "abc",
+        };
+        let body = assert_ok!(templates.render("confirm-email", &context));
+        assert_eq!(body.text, "Welcome, someone!");
+        let html = assert_some!(body.html);
+        assert!(html.starts_with("<main><p>Hello someone!"));
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("advisory.html.hbs"), "{{#if}}").unwrap();
+        assert_err!(EmailTemplates::load(Some(dir.path())));
+    }
+}
@@ -0,0 +1,204 @@
+//! Features that are rolled out gradually without a deploy
+//!
+//! Admins create flags through `PUT /admin/feature_flags/:name`. Code that is
+//! behind a flag asks the `FeatureFlags` of the `App` whether the flag is
+//! enabled for the current user:
+//!
+//! ```ignore
+//! let app = req.app();
+//! if app.feature_flags.is_enabled(&app.primary_database, "new_search_ranking", user_id)? {
+//!     // ...
+//! }
+//! ```
+//!
+//! A flag is enabled for everyone if `enabled` is set. Otherwise it is
+//! enabled for the users in `user_ids`, and for `percentage` percent of the
+//! other users. Which users fall into the percentage depends on the flag
+//! name and the user id, so it stays the same across servers and requests,
+//! and raising the percentage only adds users. Anonymous requests only see
+//! flags that are enabled for everyone, and flags that don't exist are
+//! disabled.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::db::DieselPool;
+use crate::schema::feature_flags;
+use crate::util::errors::AppResult;
+
+/// How long each server process uses the flags it last read from the
+/// database
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct FeatureFlag {
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub percentage: i32,
+    pub user_ids: Vec<i32>,
+    /// The admin that last changed the flag
+    pub updated_by: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable, AsChangeset)]
+#[table_name = "feature_flags"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewFeatureFlag<'a> {
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+    pub enabled: bool,
+    pub percentage: i32,
+    pub user_ids: &'a [i32],
+    pub updated_by: i32,
+}
+
+impl NewFeatureFlag<'_> {
+    /// Creates the flag, or replaces its settings if it exists
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<FeatureFlag> {
+        diesel::insert_into(feature_flags::table)
+            .values(self)
+            .on_conflict(feature_flags::name)
+            .do_update()
+            .set((self, feature_flags::updated_at.eq(diesel::dsl::now)))
+            .get_result(conn)
+    }
+}
+
+impl FeatureFlag {
+    pub const MAX_NAME_LENGTH: usize = 64;
+
+    /// Whether the name only consists of lowercase letters, digits and
+    /// underscores
+    pub fn valid_name(name: &str) -> bool {
+        !name.is_empty()
+            && name.len() <= Self::MAX_NAME_LENGTH
+            && name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+    }
+
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<FeatureFlag>> {
+        feature_flags::table.order(feature_flags::name).load(conn)
+    }
+
+    /// Removes the flag, returning `false` if there was none
+    pub fn delete(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        diesel::delete(feature_flags::table.find(name))
+            .execute(conn)
+            .map(|rows| rows > 0)
+    }
+
+    /// Whether the flag is enabled for the user, or for anonymous requests
+    /// if there is no user
+    pub fn is_enabled_for(&self, user_id: Option<i32>) -> bool {
+        if self.enabled {
+            return true;
+        }
+        match user_id {
+            Some(user_id) => {
+                self.user_ids.contains(&user_id)
+                    || i32::from(bucket(&self.name, user_id)) < self.percentage
+            }
+            None => false,
+        }
+    }
+}
+
+/// The bucket between 0 and 99 that the user falls into for the flag
+fn bucket(name: &str, user_id: i32) -> u8 {
+    let hash = Sha256::digest(format!("{}:{}", name, user_id).as_bytes());
+    let value = u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]]);
+    (value % 100) as u8
+}
+
+/// The flags of the application, kept by the `App`
+#[derive(Debug, Default)]
+pub struct FeatureFlags {
+    cached: RwLock<Option<(Instant, HashMap<String, FeatureFlag>)>>,
+}
+
+impl FeatureFlags {
+    /// Whether the flag is enabled for the user. The flags are read from the
+    /// database at most once per `REFRESH_INTERVAL`.
+    pub fn is_enabled(
+        &self,
+        pool: &DieselPool,
+        name: &str,
+        user_id: Option<i32>,
+    ) -> AppResult<bool> {
+        if let Some((read_at, flags)) = &*self.cached.read().unwrap() {
+            if read_at.elapsed() < REFRESH_INTERVAL {
+                return Ok(flags
+                    .get(name)
+                    .map_or(false, |flag| flag.is_enabled_for(user_id)));
+            }
+        }
+
+        let conn = pool.get()?;
+        let flags = FeatureFlag::all(&conn)?
+            .into_iter()
+            .map(|flag| (flag.name.clone(), flag))
+            .collect::<HashMap<_, _>>();
+        let enabled = flags
+            .get(name)
+            .map_or(false, |flag| flag.is_enabled_for(user_id));
+        *self.cached.write().unwrap() = Some((Instant::now(), flags));
+        Ok(enabled)
+    }
+
+    /// Makes the next check read the flags from the database, after they
+    /// were changed through this server
+    pub fn invalidate(&self) {
+        *self.cached.write().unwrap() = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag(percentage: i32) -> FeatureFlag {
+        FeatureFlag {
+            name: "new_search_ranking".into(),
+            description: None,
+            enabled: false,
+            percentage,
+            user_ids: vec![7],
+            updated_by: 1,
+            updated_at: NaiveDateTime::from_timestamp(0, 0),
+        }
+    }
+
+    #[test]
+    fn percentages_only_add_users() {
+        let enabled = |percentage| {
+            (1..=1000)
+                .filter(|&user_id| flag(percentage).is_enabled_for(Some(user_id)))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(enabled(0), vec![7]);
+        assert_eq!(enabled(100).len(), 1000);
+        let ten = enabled(10);
+        let fifty = enabled(50);
+        assert!(ten.len() > 50 && ten.len() < 150);
+        assert!(ten.iter().all(|user_id| fifty.contains(user_id)));
+    }
+
+    #[test]
+    fn anonymous_requests_only_see_flags_enabled_for_everyone() {
+        assert!(!flag(100).is_enabled_for(None));
+        let everyone = FeatureFlag {
+            enabled: true,
+            ..flag(0)
+        };
+        assert!(everyone.is_enabled_for(None));
+        assert!(everyone.is_enabled_for(Some(1)));
+    }
+}
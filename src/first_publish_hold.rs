@@ -0,0 +1,128 @@
+//! Holding the versions published by new accounts out of the index.
+//!
+//! Malware is mostly uploaded from accounts created just before. When the
+//! policy is enabled, versions published by accounts younger than
+//! `FIRST_PUBLISH_HOLD_ACCOUNT_AGE_HOURS` are placed in the quarantine before
+//! they are added to the index, with a finding of the `first_publish`
+//! scanner. The index entry of the version is stored with the quarantine
+//! entry and only added to the index once the version is released:
+//!
+//! * automatically after `FIRST_PUBLISH_HOLD_WINDOW_HOURS` (24 by default),
+//!   by the `ReleaseHeldVersions` background job. With a window of 0 the
+//!   versions are held until an admin releases them.
+//! * by an admin, with the endpoints reviewing the quarantine.
+//! * when an admin verifies the account at
+//!   `PUT /admin/users/:user_id/verify_publisher`, which releases all of its
+//!   held versions and exempts the account from the policy.
+//!
+//! Versions that a scanner flagged while they were held are only released
+//! by a review. Rejected versions are yanked without ever being added to the
+//! index.
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::git;
+use crate::models::{Finding, QuarantineStatus, User, VerifiedPublisher, VersionQuarantine};
+use crate::swirl::EnqueueError;
+
+/// The scanner of the findings of held versions
+pub const SCANNER: &str = "first_publish";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirstPublishHold {
+    /// Versions by accounts younger than this are held
+    pub account_age: std::time::Duration,
+    /// How long versions are held, or `None` to hold them until they are
+    /// released by an admin
+    pub window: Option<std::time::Duration>,
+}
+
+impl FirstPublishHold {
+    /// The policy configured by the environment variables described in the
+    /// module documentation, `None` if it isn't enabled
+    pub fn from_environment() -> Option<Self> {
+        let account_age = dotenv::var("FIRST_PUBLISH_HOLD_ACCOUNT_AGE_HOURS").ok()?;
+        let account_age = hours(&account_age, "FIRST_PUBLISH_HOLD_ACCOUNT_AGE_HOURS");
+        let window = match dotenv::var("FIRST_PUBLISH_HOLD_WINDOW_HOURS") {
+            Ok(window) => hours(&window, "FIRST_PUBLISH_HOLD_WINDOW_HOURS"),
+            Err(_) => std::time::Duration::from_secs(60 * 60 * 24),
+        };
+
+        Some(Self {
+            account_age,
+            window: Some(window).filter(|window| *window > std::time::Duration::from_secs(0)),
+        })
+    }
+
+    /// Whether versions published by the user are held
+    pub fn applies_to(&self, conn: &PgConnection, user: &User) -> QueryResult<bool> {
+        let account_age = Duration::from_std(self.account_age).unwrap();
+        let is_new = match user.created_at {
+            Some(created_at) => created_at > Utc::now().naive_utc() - account_age,
+            None => false,
+        };
+        Ok(is_new && !VerifiedPublisher::is_verified(conn, user.id)?)
+    }
+
+    /// Holds a version that was just published, instead of adding the index
+    /// entry to the index
+    pub fn hold(
+        &self,
+        conn: &PgConnection,
+        version_id: i32,
+        index_entry: &git::Crate,
+    ) -> QueryResult<VersionQuarantine> {
+        let finding = Finding {
+            scanner: SCANNER.into(),
+            rule: "new-account".into(),
+            path: None,
+            message: "Published by a new account".into(),
+        };
+        VersionQuarantine::hold(conn, version_id, &[finding], index_entry, self.release_at())
+    }
+
+    /// When a version held now is released
+    pub fn release_at(&self) -> Option<NaiveDateTime> {
+        self.window
+            .map(|window| Utc::now().naive_utc() + Duration::from_std(window).unwrap())
+    }
+}
+
+fn hours(value: &str, var: &str) -> std::time::Duration {
+    let hours: u64 = value
+        .parse()
+        .unwrap_or_else(|_| panic!("{} was not a valid number", var));
+    std::time::Duration::from_secs(60 * 60 * hours)
+}
+
+/// Releases the held versions of a user, like after the account was verified.
+///
+/// Versions with findings of other scanners stay in the quarantine. Returns
+/// the IDs of the released versions.
+pub fn release_held_versions_of(
+    conn: &PgConnection,
+    user_id: i32,
+    reviewer_id: i32,
+) -> Result<Vec<i32>, EnqueueError> {
+    let mut released = Vec::new();
+    for quarantine in VersionQuarantine::pending_published_by(conn, user_id)? {
+        let only_held = quarantine
+            .findings()
+            .iter()
+            .all(|finding| finding.scanner == SCANNER);
+        if !quarantine.is_held() || !only_held {
+            continue;
+        }
+
+        let quarantine = VersionQuarantine::review(
+            conn,
+            quarantine.version_id,
+            reviewer_id,
+            QuarantineStatus::Released,
+        )?;
+        quarantine.add_held_version_to_index(conn)?;
+        released.push(quarantine.version_id);
+    }
+    Ok(released)
+}
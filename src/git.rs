@@ -1,15 +1,20 @@
 #![allow(missing_debug_implementations)]
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::{self, OpenOptions};
 use std::path::{Path, PathBuf};
-use swirl::PerformError;
 use tempfile::{Builder, TempDir};
 use url::Url;
 
 use crate::background_jobs::Environment;
-use crate::models::{DependencyKind, Version};
-use crate::schema::versions;
+use crate::email;
+use crate::models::{
+    BulkYank, CrateDependencies, CrateDependents, DependencyKind, EmailEvent, NewNotification,
+    NotificationKind, Owner, Version,
+};
+use crate::schema::{crates, versions};
+use crate::swirl::PerformError;
+use diesel::PgConnection;
 
 static DEFAULT_GIT_SSH_USERNAME: &str = "git";
 
@@ -64,6 +69,31 @@ pub struct Crate {
     pub yanked: Option<bool>,
     #[serde(default)]
     pub links: Option<String>,
+    /// Metadata that cargo doesn't use, which is only written to the index
+    /// if `INDEX_EXTENSIONS` is set, see `IndexExtensions`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ext: Option<IndexExtensions>,
+}
+
+/// Extension fields of an index entry for registries whose clients can make
+/// use of more metadata than cargo, such as private registries running a
+/// fork of cargo. Cargo ignores unknown fields, so entries with extensions
+/// still work with it.
+///
+/// The fields are recorded for every version, but only written to the index
+/// if the background worker runs with `INDEX_EXTENSIONS` set. Entries
+/// rewritten without it, e.g. on yanks, lose their extensions.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
+pub struct IndexExtensions {
+    /// The login of the user who published the version
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>,
+    /// The checksums of the crate file, by algorithm
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub checksums: BTreeMap<String, String>,
+    /// Why the version was yanked, if an administrator yanked it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub yank_reason: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -82,6 +112,8 @@ pub struct Dependency {
 pub struct RepositoryConfig {
     pub index_location: Url,
     pub credentials: Credentials,
+    /// Whether index entries include their `IndexExtensions`
+    pub index_extensions: bool,
 }
 
 impl RepositoryConfig {
@@ -93,6 +125,8 @@ impl RepositoryConfig {
         let ssh_key = dotenv::var("GIT_SSH_KEY");
         let ssh_url = dotenv::var("GIT_SSH_REPO_URL");
 
+        let index_extensions = dotenv::var("INDEX_EXTENSIONS").is_ok();
+
         match (username, password, http_url, ssh_key, ssh_url) {
             (extra_user, extra_pass, extra_http_url, Ok(encoded_key), Ok(ssh_url)) => {
                 if let (Ok(_), Ok(_), Ok(_)) = (extra_user, extra_pass, extra_http_url) {
@@ -115,6 +149,7 @@ impl RepositoryConfig {
                 Self {
                     index_location,
                     credentials,
+                    index_extensions,
                 }
             }
             (Ok(username), Ok(password), Ok(http_url), Err(_), Err(_)) => {
@@ -124,6 +159,7 @@ impl RepositoryConfig {
                 Self {
                     index_location,
                     credentials,
+                    index_extensions,
                 }
             }
             (_, _, Ok(http_url), _, _) => {
@@ -133,6 +169,7 @@ impl RepositoryConfig {
                 Self {
                     index_location,
                     credentials,
+                    index_extensions,
                 }
             }
             _ => panic!("must have `GIT_REPO_URL` defined"),
@@ -144,6 +181,7 @@ pub struct Repository {
     checkout_path: TempDir,
     repository: git2::Repository,
     credentials: Credentials,
+    index_extensions: bool,
 }
 
 impl Repository {
@@ -167,6 +205,7 @@ impl Repository {
             checkout_path,
             repository,
             credentials: repository_config.credentials.clone(),
+            index_extensions: repository_config.index_extensions,
         })
     }
 
@@ -186,6 +225,15 @@ impl Repository {
         }
     }
 
+    /// Serializes the crate as a line of its index file, dropping the
+    /// extensions unless they are enabled
+    fn index_line(&self, krate: &mut Crate) -> serde_json::Result<String> {
+        if !self.index_extensions {
+            krate.ext = None;
+        }
+        serde_json::to_string(krate)
+    }
+
     fn perform_commit_and_push(&self, msg: &str, modified_file: &Path) -> Result<(), PerformError> {
         // git add $file
         let mut index = self.repository.index()?;
@@ -265,17 +313,29 @@ impl Repository {
     }
 }
 
-#[swirl::background_job]
-pub fn add_crate(env: &Environment, krate: Crate) -> Result<(), PerformError> {
+pub fn perform_add_crate(env: &Environment, mut krate: Crate) -> Result<(), PerformError> {
     use std::io::prelude::*;
 
     let repo = env.lock_index()?;
     let dst = repo.index_file(&krate.name);
 
+    // The job is retried if the worker stops after pushing the commit but
+    // before the job is deleted, in which case the version is already there
+    if let Ok(existing) = fs::read_to_string(&dst) {
+        let already_added = existing
+            .lines()
+            .filter_map(|line| serde_json::from_str::<Crate>(line).ok())
+            .any(|existing| existing.vers == krate.vers);
+        if already_added {
+            println!("`{}#{}` is already in the index", krate.name, krate.vers);
+            return Ok(());
+        }
+    }
+
     // Add the crate to its relevant file
     fs::create_dir_all(dst.parent().unwrap())?;
     let mut file = OpenOptions::new().append(true).create(true).open(&dst)?;
-    serde_json::to_writer(&mut file, &krate)?;
+    file.write_all(repo.index_line(&mut krate)?.as_bytes())?;
     file.write_all(b"\n")?;
 
     let message: String = format!("Updating crate `{}#{}`", krate.name, krate.vers);
@@ -287,8 +347,7 @@ pub fn add_crate(env: &Environment, krate: Crate) -> Result<(), PerformError> {
 /// file, deserlialise the crate from JSON, change the yank boolean to
 /// `true` or `false`, write all the lines back out, and commit and
 /// push the changes.
-#[swirl::background_job]
-pub fn yank(
+pub fn perform_yank(
     conn: &PgConnection,
     env: &Environment,
     krate: String,
@@ -300,7 +359,7 @@ pub fn yank(
     let repo = env.lock_index()?;
     let dst = repo.index_file(&krate);
 
-    conn.transaction(|| {
+    conn.transaction::<_, PerformError, _>(|| {
         let yanked_in_db: bool = versions::table
             .find(version.id)
             .select(versions::yanked)
@@ -323,7 +382,12 @@ pub fn yank(
                     return Ok(line.to_string());
                 }
                 git_crate.yanked = Some(yanked);
-                Ok(serde_json::to_string(&git_crate)?)
+                // Yanks by the owners have no reason, and unyanked versions
+                // shouldn't keep the reason of an earlier yank
+                if let Some(ext) = &mut git_crate.ext {
+                    ext.yank_reason = None;
+                }
+                Ok(repo.index_line(&mut git_crate)?)
             })
             .collect::<Result<Vec<_>, PerformError>>();
         let new = new?.join("\n") + "\n";
@@ -338,10 +402,136 @@ pub fn yank(
 
         repo.commit_and_push(&message, &repo.relative_index_file(&krate))?;
 
+        let dependencies = CrateDependencies::load(conn, version.crate_id)?;
         diesel::update(&version)
             .set(versions::yanked.eq(yanked))
             .execute(&*conn)?;
+        CrateDependents::update_dependencies_of(conn, &dependencies)?;
 
+        Ok(())
+    })?;
+
+    env.response_cache.invalidate_crate(&krate);
+    Ok(())
+}
+
+/// Performs a bulk yank requested by an administrator.
+///
+/// All matching versions of a crate are yanked in the database and in the
+/// index in one commit per crate. Versions that are already yanked are
+/// skipped, so the job can safely be retried after a partial failure. Once
+/// everything has been yanked, the verified owners of every affected crate
+/// are notified of all versions yanked by the request, including the ones
+/// yanked by earlier attempts.
+pub fn perform_bulk_yank(
+    conn: &PgConnection,
+    env: &Environment,
+    bulk_yank_id: i32,
+) -> Result<(), PerformError> {
+    use diesel::prelude::*;
+
+    let bulk_yank = BulkYank::find(conn, bulk_yank_id)?;
+    if bulk_yank.completed_at.is_some() {
+        return Ok(());
+    }
+
+    let mut by_crate: BTreeMap<String, Vec<Version>> = BTreeMap::new();
+    for (version, krate) in bulk_yank.pending_versions(conn)? {
+        by_crate.entry(krate).or_default().push(version);
+    }
+
+    {
+        let repo = env.lock_index()?;
+        for (krate, versions) in &by_crate {
+            conn.transaction(|| {
+                bulk_yank.yank_versions(conn, versions)?;
+
+                let dst = repo.index_file(krate);
+                let version_nums = versions
+                    .iter()
+                    .map(|version| version.num.to_string())
+                    .collect::<Vec<_>>();
+
+                let prev = fs::read_to_string(&dst)?;
+                let new = prev
+                    .lines()
+                    .map(|line| {
+                        let mut git_crate = serde_json::from_str::<Crate>(line)
+                            .map_err(|_| format!("couldn't decode: `{}`", line))?;
+                        if !version_nums.contains(&git_crate.vers) {
+                            return Ok(line.to_string());
+                        }
+                        git_crate.yanked = Some(true);
+                        git_crate
+                            .ext
+                            .get_or_insert_with(Default::default)
+                            .yank_reason = Some(bulk_yank.reason.clone());
+                        Ok(repo.index_line(&mut git_crate)?)
+                    })
+                    .collect::<Result<Vec<_>, PerformError>>();
+                let new = new?.join("\n") + "\n";
+                fs::write(&dst, new.as_bytes())?;
+
+                let message = format!(
+                    "Yanking crate `{}#{}` (bulk yank #{})",
+                    krate,
+                    version_nums.join(", "),
+                    bulk_yank.id
+                );
+                repo.commit_and_push(&message, &repo.relative_index_file(krate))
+            })?;
+            env.response_cache.invalidate_crate(krate);
+        }
+    }
+
+    // The owners are notified of the versions yanked by earlier attempts of
+    // this job, too, and only once the request was completed, so that a retry
+    // notifies all of them exactly once
+    conn.transaction(|| {
+        bulk_yank.complete(conn)?;
+
+        let mut by_crate: BTreeMap<String, Vec<Version>> = BTreeMap::new();
+        for (version, krate) in bulk_yank.yanked_versions(conn)? {
+            by_crate.entry(krate).or_default().push(version);
+        }
+        for (krate, versions) in &by_crate {
+            let krate = crate::models::Crate::by_name(krate).first::<crate::models::Crate>(conn)?;
+            let version_nums = versions
+                .iter()
+                .map(|version| version.num.to_string())
+                .collect::<Vec<_>>();
+
+            let title = format!(
+                "The crates.io team yanked {} {}: {}",
+                krate.name,
+                version_nums.join(", "),
+                bulk_yank.reason
+            );
+            for owner in krate.owners(conn)? {
+                if let Owner::User(user) = owner {
+                    NewNotification {
+                        user_id: user.id,
+                        kind: NotificationKind::SecurityNotice,
+                        title: &title,
+                        crate_name: Some(&krate.name),
+                    }
+                    .create(conn)?;
+
+                    if let Some(address) =
+                        user.notification_email(conn, EmailEvent::SecurityNotices)?
+                    {
+                        email::queue_bulk_yank_email(
+                            conn,
+                            &address,
+                            user.login(),
+                            &krate.name,
+                            &version_nums,
+                            &bulk_yank.reason,
+                        )?;
+                    }
+                }
+            }
+        }
         Ok(())
     })
 }
@@ -1,24 +1,64 @@
 //! This module implements functionality for interacting with GitHub.
 
+use conduit::StatusCode;
 use oauth2::AccessToken;
 use reqwest::{self, header};
 
 use serde::de::DeserializeOwned;
+use serde_json::Value;
 
 use std::str;
 
-use crate::util::errors::{cargo_err, internal, not_found, AppError, AppResult};
+use crate::db::DieselPool;
+use crate::util::errors::{cargo_err, custom, internal, not_found, AppError, AppResult};
 use reqwest::blocking::Client;
 
+use self::cache::{CachedResponse, CircuitBreaker, GitHubCache};
+
+pub mod cache;
+pub mod fake;
+
+/// The GitHub API used outside of tests
+pub const DEFAULT_BASE_URL: &str = "https://api.github.com";
+
 #[derive(Debug)]
 pub struct GitHubClient {
     base_url: String,
     client: Option<Client>,
+    /// Whether requests are answered by the `fake` module
+    fake: bool,
+    /// Where responses are cached, see the `cache` module
+    cache: Option<GitHubCache>,
+    breaker: CircuitBreaker,
 }
 
 impl GitHubClient {
     pub fn new(client: Option<Client>, base_url: String) -> Self {
-        Self { client, base_url }
+        Self {
+            client,
+            base_url,
+            fake: false,
+            cache: None,
+            breaker: CircuitBreaker::default(),
+        }
+    }
+
+    /// Caches the responses in the database of the pool
+    pub fn with_cache(mut self, pool: DieselPool) -> Self {
+        self.cache = Some(GitHubCache::new(pool));
+        self
+    }
+
+    /// A client that answers requests with the seed data of the `fake`
+    /// module instead of calling GitHub, for `DEV_MODE`
+    pub fn fake() -> Self {
+        Self {
+            client: None,
+            base_url: String::new(),
+            fake: true,
+            cache: None,
+            breaker: CircuitBreaker::default(),
+        }
     }
 
     pub fn current_user(&self, auth: &AccessToken) -> AppResult<GithubUser> {
@@ -54,6 +94,16 @@ impl GitHubClient {
         self.request(&url, auth)
     }
 
+    pub fn repository(
+        &self,
+        owner: &str,
+        name: &str,
+        auth: &AccessToken,
+    ) -> AppResult<GitHubRepository> {
+        let url = format!("/repos/{}/{}", owner, name);
+        self.request(&url, auth)
+    }
+
     /// Does all the nonsense for sending a GET to Github. Doesn't handle parsing
     /// because custom error-code handling may be desirable. Use
     /// `parse_github_response` to handle the "common" processing of responses.
@@ -61,19 +111,91 @@ impl GitHubClient {
     where
         T: DeserializeOwned,
     {
-        let url = format!("{}{}", self.base_url, url);
-        info!("GITHUB HTTP: {}", url);
+        if self.fake {
+            return fake::request(url, auth);
+        }
 
-        self.client()
-            .get(&url)
+        let body = self.cached_request(url, auth)?;
+        serde_json::from_value(body).map_err(Into::into)
+    }
+
+    /// Sends the GET unless there is a fresh cached response, and falls back
+    /// to the cached response if GitHub is down
+    fn cached_request(&self, url: &str, auth: &AccessToken) -> AppResult<Value> {
+        let key = cache::key(url, auth);
+        let cached = self.cache.as_ref().and_then(|cache| cache.find(&key));
+        if let Some(cached) = &cached {
+            if cached.is_fresh(cache::ttl(url)) {
+                return Ok(cached.body.clone());
+            }
+        }
+        if !self.breaker.allows_requests() {
+            return match cached {
+                Some(cached) => Ok(cached.body),
+                None => Err(custom(
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    "GitHub is unavailable at the moment, please try again later",
+                )),
+            };
+        }
+
+        let full_url = format!("{}{}", self.base_url, url);
+        info!("GITHUB HTTP: {}", full_url);
+
+        let mut request = self
+            .client()
+            .get(&full_url)
             .header(header::ACCEPT, "application/vnd.github.v3+json")
             .header(header::AUTHORIZATION, format!("token {}", auth.secret()))
-            .header(header::USER_AGENT, "crates.io (https://crates.io)")
-            .send()?
+            .header(header::USER_AGENT, "crates.io (https://crates.io)");
+        if let Some(etag) = cached.as_ref().and_then(|cached| cached.etag.as_deref()) {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        let response = request.send().and_then(|response| {
+            if response.status().is_server_error() {
+                response.error_for_status()
+            } else {
+                Ok(response)
+            }
+        });
+        let response = match response {
+            Ok(response) => {
+                self.breaker.record_success();
+                response
+            }
+            Err(e) => {
+                self.breaker.record_failure();
+                return match cached {
+                    Some(cached) => {
+                        warn!(url, error = %e, "Using a stale GitHub response");
+                        Ok(cached.body)
+                    }
+                    None if e.status().is_some() => Err(handle_error_response(&e)),
+                    None => Err(e.into()),
+                };
+            }
+        };
+
+        if let (Some(cached), Some(cache)) = (cached, &self.cache) {
+            if response.status() == reqwest::StatusCode::NOT_MODIFIED {
+                cache.save(&CachedResponse::new(key, cached.etag, cached.body.clone()));
+                return Ok(cached.body);
+            }
+        }
+
+        let response = response
             .error_for_status()
-            .map_err(|e| handle_error_response(&e))?
-            .json()
-            .map_err(Into::into)
+            .map_err(|e| handle_error_response(&e))?;
+        let etag = response
+            .headers()
+            .get(header::ETAG)
+            .and_then(|etag| etag.to_str().ok())
+            .map(String::from);
+        let body: Value = response.json()?;
+        if let Some(cache) = &self.cache {
+            cache.save(&CachedResponse::new(key, etag, body.clone()));
+        }
+        Ok(body)
     }
 
     /// Returns a client for making HTTP requests to upload crate files.
@@ -139,6 +261,20 @@ pub struct GitHubTeamMembership {
     pub state: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct GitHubRepository {
+    pub id: i64,
+    pub full_name: String,
+    /// The permissions of the authenticated user, if they have any
+    pub permissions: Option<GitHubRepositoryPermissions>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GitHubRepositoryPermissions {
+    pub admin: bool,
+    pub push: bool,
+}
+
 pub fn team_url(login: &str) -> String {
     let mut login_pieces = login.split(':');
     login_pieces.next();
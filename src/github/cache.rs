@@ -0,0 +1,221 @@
+//! Responses of the GitHub API, kept in the `github_responses` table, and a
+//! circuit breaker for GitHub outages
+//!
+//! Responses are cached by the URL and the access token they were requested
+//! with, as what GitHub shows depends on who asks. Only a hash of both is
+//! stored. A cached response is used without asking GitHub while it is
+//! younger than the TTL of its endpoint. Older responses are revalidated with
+//! their `ETag`, which GitHub answers with `304 Not Modified` without counting
+//! it against the rate limit if nothing changed.
+//!
+//! After `FAILURES_UNTIL_OPEN` requests in a row failed because GitHub was
+//! unreachable or answered with a server error, no requests are sent for
+//! `OPEN_DURATION`. Meanwhile, and whenever a request fails, cached responses
+//! are used regardless of their age, so that owner operations of users that
+//! were looked up before keep working. Everything else fails with
+//! `503 Service Unavailable` until GitHub is back.
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use hex::ToHex;
+use oauth2::AccessToken;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::db::DieselPool;
+use crate::schema::github_responses;
+
+/// How many failed requests in a row open the circuit breaker
+const FAILURES_UNTIL_OPEN: u32 = 5;
+
+/// How long no requests are sent to GitHub once the circuit breaker is open
+const OPEN_DURATION: Duration = Duration::from_secs(30);
+
+/// How long a response of the endpoint is used without asking GitHub
+pub fn ttl(url: &str) -> Duration {
+    let minutes = |minutes: u64| Duration::from_secs(minutes * 60);
+    let segments = url.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        // The profile is read when logging in, which should see the current
+        // name and email
+        ["user"] => Duration::from_secs(0),
+        // Membership decides who may publish for a team, so removing a
+        // member must not take long to apply
+        ["organizations", .., "memberships", _] => minutes(1),
+        ["repos", ..] => minutes(5),
+        ["orgs", ..] => minutes(60),
+        _ => Duration::from_secs(0),
+    }
+}
+
+/// The key of a response in the `github_responses` table
+pub fn key(url: &str, auth: &AccessToken) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(auth.secret().as_bytes());
+    hasher.update(b"\n");
+    hasher.update(url.as_bytes());
+    hasher.finalize().encode_hex()
+}
+
+#[derive(Debug, Clone, Queryable, Insertable, AsChangeset)]
+#[primary_key(key)]
+#[table_name = "github_responses"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct CachedResponse {
+    pub key: String,
+    pub etag: Option<String>,
+    pub body: Value,
+    /// When GitHub last confirmed the response
+    pub fetched_at: NaiveDateTime,
+}
+
+impl CachedResponse {
+    pub fn new(key: String, etag: Option<String>, body: Value) -> Self {
+        Self {
+            key,
+            etag,
+            body,
+            fetched_at: Utc::now().naive_utc(),
+        }
+    }
+
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        let age = Utc::now().naive_utc() - self.fetched_at;
+        age.to_std().map_or(true, |age| age < ttl)
+    }
+
+    pub fn find(conn: &PgConnection, key: &str) -> QueryResult<Option<Self>> {
+        github_responses::table.find(key).first(conn).optional()
+    }
+
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(github_responses::table)
+            .values(self)
+            .on_conflict(github_responses::key)
+            .do_update()
+            .set(self)
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+/// Reads and writes the cached responses. Failing to use the database only
+/// logs an error, and the response is requested from GitHub.
+#[derive(Clone)]
+pub struct GitHubCache {
+    pool: DieselPool,
+}
+
+impl std::fmt::Debug for GitHubCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GitHubCache").finish()
+    }
+}
+
+impl GitHubCache {
+    pub fn new(pool: DieselPool) -> Self {
+        Self { pool }
+    }
+
+    pub fn find(&self, key: &str) -> Option<CachedResponse> {
+        let result = self
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| CachedResponse::find(&conn, key).map_err(|e| e.to_string()));
+        result.unwrap_or_else(|e| {
+            error!(error = %e, "Failed to read a cached GitHub response");
+            None
+        })
+    }
+
+    pub fn save(&self, response: &CachedResponse) {
+        let result = self
+            .pool
+            .get()
+            .map_err(|e| e.to_string())
+            .and_then(|conn| response.save(&conn).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            error!(error = %e, "Failed to cache a GitHub response");
+        }
+    }
+}
+
+/// Stops sending requests to GitHub for a while when it is down
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    /// Whether requests may be sent. Once the breaker was open for
+    /// `OPEN_DURATION`, the next request is let through to check whether
+    /// GitHub is back.
+    pub fn allows_requests(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state
+            .open_until
+            .map_or(true, |open_until| Instant::now() >= open_until)
+    }
+
+    pub fn record_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::default();
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= FAILURES_UNTIL_OPEN {
+            if state.open_until.is_none() {
+                warn!("GitHub is failing, no requests are sent for a while");
+            }
+            state.open_until = Some(Instant::now() + OPEN_DURATION);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn memberships_expire_before_organizations() {
+        assert_eq!(ttl("/user"), Duration::from_secs(0));
+        assert_eq!(
+            ttl("/organizations/1/team/2/memberships/alice"),
+            Duration::from_secs(60)
+        );
+        assert_eq!(ttl("/orgs/rust-lang/teams/core"), Duration::from_secs(3600));
+        assert_ne!(
+            key("/user", &AccessToken::new("a".into())),
+            key("/user", &AccessToken::new("b".into()))
+        );
+    }
+
+    #[test]
+    fn failures_in_a_row_open_the_breaker() {
+        let breaker = CircuitBreaker::default();
+        for _ in 1..FAILURES_UNTIL_OPEN {
+            breaker.record_failure();
+        }
+        assert!(breaker.allows_requests());
+        breaker.record_success();
+        for _ in 1..FAILURES_UNTIL_OPEN {
+            breaker.record_failure();
+        }
+        assert!(breaker.allows_requests());
+
+        breaker.record_failure();
+        assert!(!breaker.allows_requests());
+        breaker.record_success();
+        assert!(breaker.allows_requests());
+    }
+}
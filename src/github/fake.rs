@@ -0,0 +1,141 @@
+//! A built-in stand-in for GitHub, used instead of the real API while
+//! `DEV_MODE` is set
+//!
+//! Logging in shows a page listing the `SEED_USERS`, and the chosen login is
+//! used as the OAuth code. The access token of a seed user is its login with
+//! `TOKEN_PREFIX` in front. The API only knows the seed users, the
+//! `SEED_ORG` with its `SEED_TEAMS`, and a repository with every name for
+//! each seed user and the org, so that the publish, ownership and team flows
+//! work offline.
+
+use oauth2::AccessToken;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::util::errors::{bad_request, not_found, AppResult};
+
+const TOKEN_PREFIX: &str = "dev-";
+
+/// The users that can log in, with their GitHub ids and names
+pub const SEED_USERS: &[(&str, i32, &str)] = &[
+    ("alice", 1001, "Alice Developer"),
+    ("bob", 1002, "Bob Developer"),
+    ("carol", 1003, "Carol Developer"),
+];
+
+/// The GitHub organization, with its id
+const SEED_ORG: (&str, i32) = ("crates-dev", 2001);
+
+/// The teams of the organization, with their ids and members
+const SEED_TEAMS: &[(&str, i32, &[&str])] = &[
+    ("core", 3001, &["alice", "bob"]),
+    ("docs", 3002, &["carol"]),
+];
+
+/// Exchanges the OAuth code, which is the login of a seed user, for its
+/// access token
+pub fn exchange_code(code: &str) -> AppResult<AccessToken> {
+    if !SEED_USERS.iter().any(|(login, ..)| *login == code) {
+        return Err(bad_request("unknown development user"));
+    }
+    Ok(AccessToken::new(format!("{}{}", TOKEN_PREFIX, code)))
+}
+
+/// Answers a GET request to the GitHub API, like `GitHubClient::request`
+pub fn request<T>(url: &str, auth: &AccessToken) -> AppResult<T>
+where
+    T: DeserializeOwned,
+{
+    let response = respond(url, auth).ok_or_else(not_found)?;
+    serde_json::from_value(response).map_err(Into::into)
+}
+
+fn respond(url: &str, auth: &AccessToken) -> Option<Value> {
+    let login = auth.secret().strip_prefix(TOKEN_PREFIX)?;
+    let (login, gh_id, name) = *SEED_USERS.iter().find(|(seed, ..)| *seed == login)?;
+    let (org_name, org_id) = SEED_ORG;
+    let org = json!({ "id": org_id, "avatar_url": null });
+
+    let segments = url.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["user"] => Some(json!({
+            "id": gh_id,
+            "login": login,
+            "name": name,
+            "email": format!("{}@crates-dev.test", login),
+            "avatar_url": null,
+        })),
+        ["orgs", name] if name.eq_ignore_ascii_case(org_name) => Some(org),
+        ["orgs", name, "teams", team] if name.eq_ignore_ascii_case(org_name) => {
+            let (team, team_id, _) = SEED_TEAMS
+                .iter()
+                .find(|(seed, ..)| seed.eq_ignore_ascii_case(team))?;
+            Some(json!({ "id": team_id, "name": team, "organization": org }))
+        }
+        ["organizations", id, "team", team_id, "memberships", member] => {
+            let (_, _, members) = SEED_TEAMS
+                .iter()
+                .find(|(_, seed, _)| seed.to_string() == *team_id)
+                .filter(|_| org_id.to_string() == *id)?;
+            members
+                .iter()
+                .find(|seed| seed.eq_ignore_ascii_case(member))
+                .map(|_| json!({ "state": "active" }))
+        }
+        ["repos", owner, repository] => {
+            let permissions = if owner.eq_ignore_ascii_case(login) {
+                json!({ "admin": true, "push": true })
+            } else if owner.eq_ignore_ascii_case(org_name) {
+                let member = SEED_TEAMS
+                    .iter()
+                    .any(|(_, _, members)| members.contains(&login));
+                json!({ "admin": false, "push": member })
+            } else {
+                return None;
+            };
+            let full_name = format!("{}/{}", owner, repository);
+            // A made-up id that stays the same for the repository
+            let id = full_name.bytes().fold(0i64, |id, byte| {
+                id.wrapping_mul(31).wrapping_add(i64::from(byte)) & i64::from(i32::MAX)
+            });
+            Some(json!({
+                "id": id,
+                "full_name": full_name,
+                "permissions": permissions,
+            }))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::github::{GitHubTeam, GitHubTeamMembership, GithubUser};
+
+    #[test]
+    fn seed_users_can_log_in() {
+        assert!(exchange_code("mallory").is_err());
+        let token = exchange_code("alice").unwrap();
+
+        let user: GithubUser = request("/user", &token).unwrap();
+        assert_eq!(user.login, "alice");
+        assert_eq!(user.id, 1001);
+        assert!(request::<GithubUser>("/user", &AccessToken::new("alice".into())).is_err());
+    }
+
+    #[test]
+    fn teams_have_seed_members() {
+        let token = exchange_code("carol").unwrap();
+
+        let team: GitHubTeam = request("/orgs/crates-dev/teams/core", &token).unwrap();
+        assert_eq!(team.id, 3001);
+        assert_eq!(team.organization.id, 2001);
+
+        let url = "/organizations/2001/team/3001/memberships/alice";
+        let membership: GitHubTeamMembership = request(url, &token).unwrap();
+        assert_eq!(membership.state, "active");
+        let url = "/organizations/2001/team/3001/memberships/carol";
+        assert!(request::<GitHubTeamMembership>(url, &token).is_err());
+    }
+}
@@ -6,6 +6,7 @@
 
 #![warn(clippy::all, rust_2018_idioms)]
 #![warn(missing_debug_implementations, missing_copy_implementations)]
+#![recursion_limit = "256"]
 
 #[cfg(test)]
 #[macro_use]
@@ -34,15 +35,32 @@ pub mod admin;
 mod app;
 pub mod background_jobs;
 pub mod boot;
+pub mod cache;
 mod config;
+pub mod data_export;
 pub mod db;
+pub mod download_origins;
 pub mod email;
+pub mod feature_flags;
+pub mod first_publish_hold;
 pub mod git;
 pub mod github;
+pub mod license;
+pub mod logging;
+pub mod login_rate_limit;
+pub mod maintenance;
+pub mod metrics;
 pub mod middleware;
 mod publish_rate_limit;
+pub mod quotas;
 pub mod render;
+pub mod repository_verification;
+pub mod scanning;
+pub mod schedule;
 pub mod schema;
+pub mod server;
+pub mod storage;
+pub mod swirl;
 pub mod tasks;
 mod test_util;
 pub mod uploaders;
@@ -0,0 +1,140 @@
+//! Normalizing the `license` field of published versions.
+//!
+//! Cargo passes the `license` field of the manifest through unchanged, so it
+//! comes in many spellings of the same SPDX expression: `MIT/Apache-2.0`,
+//! `MIT or Apache-2.0` and `MIT OR Apache-2.0` all mean the same. On publish
+//! the expression is normalized to the SPDX syntax, validated, and the
+//! license and exception identifiers it's made of are stored with the
+//! version for the license statistics.
+//!
+//! Versions with an expression that isn't valid SPDX are rejected by
+//! default. With `INVALID_LICENSE_POLICY=warn` they are published with a
+//! warning instead, and aren't counted in the statistics.
+
+use license_exprs::validate_license_expr;
+
+/// What happens to versions whose license isn't a valid SPDX expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidLicensePolicy {
+    Reject,
+    Warn,
+}
+
+impl InvalidLicensePolicy {
+    /// The policy configured by `INVALID_LICENSE_POLICY`, rejecting invalid
+    /// licenses if it isn't set
+    pub fn from_environment() -> Self {
+        match dotenv::var("INVALID_LICENSE_POLICY") {
+            Ok(policy) if policy == "warn" => Self::Warn,
+            Ok(policy) if policy == "reject" => Self::Reject,
+            Ok(policy) => panic!(
+                "INVALID_LICENSE_POLICY was not `warn` or `reject`: {}",
+                policy
+            ),
+            Err(_) => Self::Reject,
+        }
+    }
+}
+
+/// A license expression in the SPDX syntax, with the identifiers it refers to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedLicense {
+    pub expression: String,
+    /// The license identifiers, in the order they first appear
+    pub licenses: Vec<String>,
+    /// The identifiers of exceptions added with `WITH`
+    pub exceptions: Vec<String>,
+}
+
+/// Normalizes a license expression, or returns why it isn't valid.
+///
+/// The legacy `/` separator is replaced with `OR`, operators are uppercased
+/// and the whitespace is collapsed. Identifiers are left as they are, since
+/// SPDX identifiers are matched case sensitively by the validation.
+pub fn normalize(license: &str) -> Result<NormalizedLicense, String> {
+    let spaced = license
+        .replace('/', " OR ")
+        .replace('(', " ( ")
+        .replace(')', " ) ");
+
+    let mut expression = String::with_capacity(license.len());
+    let mut licenses: Vec<String> = Vec::new();
+    let mut exceptions: Vec<String> = Vec::new();
+    let mut after_with = false;
+    for token in spaced.split_whitespace() {
+        let token = match token.to_uppercase().as_str() {
+            "AND" | "OR" | "WITH" => token.to_uppercase(),
+            _ => token.to_string(),
+        };
+        if !expression.is_empty() && !expression.ends_with('(') && token != ")" {
+            expression.push(' ');
+        }
+        expression.push_str(&token);
+
+        match token.as_str() {
+            "AND" | "OR" | "(" | ")" => {}
+            "WITH" => {
+                after_with = true;
+                continue;
+            }
+            _ if after_with => push_unique(&mut exceptions, token),
+            _ => push_unique(&mut licenses, token),
+        }
+        after_with = false;
+    }
+
+    if licenses.is_empty() {
+        return Err("the license expression is empty".into());
+    }
+    // The validation predates grouping, so the parentheses are left out
+    let ungrouped = expression.replace('(', "").replace(')', "");
+    validate_license_expr(&ungrouped).map_err(|e| e.to_string())?;
+
+    Ok(NormalizedLicense {
+        expression,
+        licenses,
+        exceptions,
+    })
+}
+
+fn push_unique(identifiers: &mut Vec<String>, identifier: String) {
+    if !identifiers.contains(&identifier) {
+        identifiers.push(identifier);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::normalize;
+
+    #[test]
+    fn normalizes_separators_and_operators() {
+        for license in &[
+            "MIT/Apache-2.0",
+            "MIT or Apache-2.0",
+            " MIT  OR\tApache-2.0 ",
+        ] {
+            let normalized = normalize(license).unwrap();
+            assert_eq!(normalized.expression, "MIT OR Apache-2.0");
+            assert_eq!(normalized.licenses, vec!["MIT", "Apache-2.0"]);
+            assert!(normalized.exceptions.is_empty());
+        }
+    }
+
+    #[test]
+    fn collects_exceptions_separately() {
+        let normalized = normalize("(GPL-2.0 with Classpath-exception-2.0) OR MIT").unwrap();
+        assert_eq!(
+            normalized.expression,
+            "(GPL-2.0 WITH Classpath-exception-2.0) OR MIT"
+        );
+        assert_eq!(normalized.licenses, vec!["GPL-2.0", "MIT"]);
+        assert_eq!(normalized.exceptions, vec!["Classpath-exception-2.0"]);
+    }
+
+    #[test]
+    fn rejects_unknown_identifiers() {
+        assert!(normalize("MIT OR Not-A-License").is_err());
+        assert!(normalize("").is_err());
+    }
+}
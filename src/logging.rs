@@ -0,0 +1,65 @@
+//! Log output of the server and the background worker.
+//!
+//! The format is configured with the `LOG_FORMAT` environment variable. The
+//! default `logfmt` writes the request logs as `key=value` pairs in the
+//! format of Heroku's router, while `json` writes one JSON object per line,
+//! for log aggregation pipelines that parse structured logs.
+
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Logfmt,
+    Json,
+}
+
+impl Default for LogFormat {
+    fn default() -> Self {
+        LogFormat::Logfmt
+    }
+}
+
+impl LogFormat {
+    pub fn from_environment() -> Self {
+        match dotenv::var("LOG_FORMAT") {
+            Ok(format) => format
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid LOG_FORMAT `{}`", format)),
+            Err(_) => LogFormat::default(),
+        }
+    }
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "logfmt" => Ok(LogFormat::Logfmt),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Initializes the `tracing` subscriber in the given format, with the
+/// filter from `RUST_LOG`.
+pub fn init(format: LogFormat) {
+    let builder = tracing_subscriber::fmt().with_env_filter(EnvFilter::from_default_env());
+    match format {
+        LogFormat::Logfmt => builder.init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_log_format() {
+        assert_eq!("logfmt".parse(), Ok(LogFormat::Logfmt));
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("JSON".parse::<LogFormat>(), Err(()));
+    }
+}
@@ -0,0 +1,175 @@
+//! Rate limiting of the GitHub OAuth login flow.
+//!
+//! Every request to the `begin` and `authorize` steps of the flow is recorded
+//! with the address of the client. Addresses that made more than
+//! `max_attempts` requests within `window` are rejected until the oldest of
+//! them leaves the window.
+//!
+//! Bursts of failed exchanges (like guessed codes or replayed states) and of
+//! successfully created sessions (like many accounts logging in from one
+//! address) are recorded as anomalies, which are listed for admins at
+//! `/admin/login_anomalies` and counted in the admin metrics.
+
+use chrono::{Duration, NaiveDateTime};
+use diesel::dsl::{count_star, now, sql, IntervalDsl};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Timestamp};
+
+use crate::models::LoginAnomaly;
+use crate::schema::{login_anomalies, login_attempts};
+use crate::util::errors::{custom, AppError, AppResult, ReadOnlyMode};
+use conduit::StatusCode;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoginRateLimit {
+    pub window: std::time::Duration,
+    /// Requests allowed per address within the window
+    pub max_attempts: i64,
+    /// Failed exchanges per address within the window that are an anomaly
+    pub failure_threshold: i64,
+    /// Sessions created per address within the window that are an anomaly
+    pub session_threshold: i64,
+}
+
+impl Default for LoginRateLimit {
+    fn default() -> Self {
+        Self {
+            window: std::time::Duration::from_secs(60) * 10,
+            max_attempts: 30,
+            failure_threshold: 10,
+            session_threshold: 10,
+        }
+    }
+}
+
+/// The steps of the login flow, as recorded in `login_attempts.endpoint`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginEndpoint {
+    Begin,
+    Authorize,
+}
+
+impl LoginEndpoint {
+    fn as_str(self) -> &'static str {
+        match self {
+            LoginEndpoint::Begin => "begin",
+            LoginEndpoint::Authorize => "authorize",
+        }
+    }
+}
+
+impl LoginRateLimit {
+    /// Rejects the request if the address made too many requests recently.
+    ///
+    /// Nothing is checked while the database is in read only mode, since no
+    /// attempts are recorded then either.
+    pub fn check(&self, conn: &PgConnection, ip: &str) -> AppResult<()> {
+        let recent = login_attempts::table
+            .filter(login_attempts::ip.eq(ip))
+            .filter(login_attempts::time.gt(now - self.window_interval()));
+        let (attempts, oldest): (i64, Option<NaiveDateTime>) = recent
+            .select(sql::<(BigInt, Nullable<Timestamp>)>(
+                "COUNT(*), MIN(login_attempts.time)",
+            ))
+            .get_result(conn)?;
+
+        match oldest {
+            Some(oldest) if attempts >= self.max_attempts => {
+                let retry_after = oldest + Duration::from_std(self.window).unwrap();
+                Err(too_many_attempts(retry_after))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Records a request to `endpoint`, and an anomaly if it completes a
+    /// burst of failures or sessions from the address
+    pub fn record(
+        &self,
+        conn: &PgConnection,
+        ip: &str,
+        endpoint: LoginEndpoint,
+        succeeded: bool,
+    ) -> AppResult<()> {
+        let result = conn.transaction::<_, Box<dyn AppError>, _>(|| {
+            diesel::insert_into(login_attempts::table)
+                .values((
+                    login_attempts::ip.eq(ip),
+                    login_attempts::endpoint.eq(endpoint.as_str()),
+                    login_attempts::succeeded.eq(succeeded),
+                ))
+                .execute(conn)?;
+
+            if endpoint == LoginEndpoint::Authorize {
+                let (kind, threshold) = if succeeded {
+                    (LoginAnomaly::SESSION_BURST, self.session_threshold)
+                } else {
+                    (LoginAnomaly::FAILED_EXCHANGES, self.failure_threshold)
+                };
+                self.detect_anomaly(conn, ip, kind, succeeded, threshold)?;
+            }
+            Ok(())
+        });
+
+        // Logging in keeps working in read only mode, without rate limits
+        result.or_else(|e| {
+            if e.is::<ReadOnlyMode>() {
+                Ok(())
+            } else {
+                Err(e)
+            }
+        })
+    }
+
+    /// Records an anomaly when the number of matching attempts reaches the
+    /// threshold, once per window and address
+    fn detect_anomaly(
+        &self,
+        conn: &PgConnection,
+        ip: &str,
+        kind: &str,
+        succeeded: bool,
+        threshold: i64,
+    ) -> QueryResult<()> {
+        let attempts: i64 = login_attempts::table
+            .filter(login_attempts::ip.eq(ip))
+            .filter(login_attempts::endpoint.eq(LoginEndpoint::Authorize.as_str()))
+            .filter(login_attempts::succeeded.eq(succeeded))
+            .filter(login_attempts::time.gt(now - self.window_interval()))
+            .select(count_star())
+            .get_result(conn)?;
+        if attempts < threshold {
+            return Ok(());
+        }
+
+        let already_recorded: bool = diesel::select(diesel::dsl::exists(
+            login_anomalies::table
+                .filter(login_anomalies::ip.eq(ip))
+                .filter(login_anomalies::kind.eq(kind))
+                .filter(login_anomalies::detected_at.gt(now - self.window_interval())),
+        ))
+        .get_result(conn)?;
+        if !already_recorded {
+            diesel::insert_into(login_anomalies::table)
+                .values((
+                    login_anomalies::ip.eq(ip),
+                    login_anomalies::kind.eq(kind),
+                    login_anomalies::attempts.eq(attempts as i32),
+                ))
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    fn window_interval(&self) -> diesel::data_types::PgInterval {
+        (self.window.as_millis() as i64).milliseconds()
+    }
+}
+
+fn too_many_attempts(retry_after: NaiveDateTime) -> Box<dyn AppError> {
+    let detail = format!(
+        "There were too many login attempts from your address. Please try again after {} UTC.",
+        retry_after.format("%Y-%m-%d %H:%M:%S")
+    );
+    custom(StatusCode::TOO_MANY_REQUESTS, &detail)
+}
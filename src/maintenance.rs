@@ -0,0 +1,108 @@
+//! Whether crates.io currently accepts changes
+//!
+//! There are two ways to stop changes: the `READ_ONLY_MODE` environment
+//! variable makes the connections to the primary database read-only, which
+//! needs a redeploy to turn on and off. Admins can instead turn on the soft
+//! maintenance mode through `PUT /admin/maintenance_mode`, which is stored in
+//! the database and makes the `MaintenanceMode` middleware reject API
+//! requests that change data, apart from the admin endpoints.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::db::DieselPool;
+use crate::schema::maintenance_mode;
+use crate::util::errors::AppResult;
+
+/// How long each server process uses the maintenance mode it last read from
+/// the database
+const REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The soft maintenance mode, while it is turned on
+#[derive(Debug, Clone, PartialEq, Eq, Queryable)]
+pub struct Maintenance {
+    pub message: Option<String>,
+    pub admin_id: i32,
+    pub started_at: NaiveDateTime,
+}
+
+impl Maintenance {
+    pub fn find(conn: &PgConnection) -> QueryResult<Option<Maintenance>> {
+        maintenance_mode::table
+            .select((
+                maintenance_mode::message,
+                maintenance_mode::admin_id,
+                maintenance_mode::started_at,
+            ))
+            .first(conn)
+            .optional()
+    }
+
+    /// Turns on the maintenance mode, or replaces its message if it is on
+    pub fn start(conn: &PgConnection, admin_id: i32, message: Option<&str>) -> QueryResult<()> {
+        diesel::insert_into(maintenance_mode::table)
+            .values((
+                maintenance_mode::admin_id.eq(admin_id),
+                maintenance_mode::message.eq(message),
+            ))
+            .on_conflict(maintenance_mode::id)
+            .do_update()
+            .set(maintenance_mode::message.eq(message))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Turns off the maintenance mode, returning `false` if it was off
+    pub fn end(conn: &PgConnection) -> QueryResult<bool> {
+        diesel::delete(maintenance_mode::table)
+            .execute(conn)
+            .map(|rows| rows > 0)
+    }
+}
+
+/// The read-only state of the application, kept by the `App`
+#[derive(Debug)]
+pub struct MaintenanceMode {
+    read_only: bool,
+    cached: RwLock<Option<(Instant, Option<Maintenance>)>>,
+}
+
+impl MaintenanceMode {
+    /// `read_only` is whether the primary database is read-only because of
+    /// `READ_ONLY_MODE`
+    pub fn new(read_only: bool) -> Self {
+        Self {
+            read_only,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Whether the primary database is read-only, independently of the soft
+    /// maintenance mode
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// The soft maintenance mode, if it is on. The state is read from the
+    /// database at most once per `REFRESH_INTERVAL`.
+    pub fn current(&self, pool: &DieselPool) -> AppResult<Option<Maintenance>> {
+        if let Some((read_at, maintenance)) = &*self.cached.read().unwrap() {
+            if read_at.elapsed() < REFRESH_INTERVAL {
+                return Ok(maintenance.clone());
+            }
+        }
+
+        let conn = pool.get()?;
+        let maintenance = Maintenance::find(&conn)?;
+        *self.cached.write().unwrap() = Some((Instant::now(), maintenance.clone()));
+        Ok(maintenance)
+    }
+
+    /// Makes the next check read the state from the database, after it was
+    /// changed through this server
+    pub fn invalidate(&self) {
+        *self.cached.write().unwrap() = None;
+    }
+}
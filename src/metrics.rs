@@ -0,0 +1,64 @@
+//! Prometheus metrics, exposed by the `/api/private/metrics/:kind` route.
+//!
+//! There are two kinds of metrics:
+//!
+//! - Service metrics are computed from the database when they are scraped,
+//!   so they are the same no matter which server answers the request. These
+//!   cover the state of the background jobs, which run in a separate process.
+//! - Instance metrics are collected by each server process, like the request
+//!   counters and the state of the database connection pools.
+
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts, Registry,
+};
+
+pub use self::instance::InstanceMetrics;
+pub use self::service::ServiceMetrics;
+
+mod instance;
+mod service;
+
+fn counter(registry: &Registry, name: &str, help: &str) -> prometheus::Result<IntCounter> {
+    let counter = IntCounter::new(name, help)?;
+    registry.register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+fn counter_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<IntCounterVec> {
+    let counter = IntCounterVec::new(Opts::new(name, help), labels)?;
+    registry.register(Box::new(counter.clone()))?;
+    Ok(counter)
+}
+
+fn gauge(registry: &Registry, name: &str, help: &str) -> prometheus::Result<IntGauge> {
+    let gauge = IntGauge::new(name, help)?;
+    registry.register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+fn gauge_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<IntGaugeVec> {
+    let gauge = IntGaugeVec::new(Opts::new(name, help), labels)?;
+    registry.register(Box::new(gauge.clone()))?;
+    Ok(gauge)
+}
+
+fn histogram_vec(
+    registry: &Registry,
+    name: &str,
+    help: &str,
+    labels: &[&str],
+) -> prometheus::Result<HistogramVec> {
+    let histogram = HistogramVec::new(HistogramOpts::new(name, help), labels)?;
+    registry.register(Box::new(histogram.clone()))?;
+    Ok(histogram)
+}
@@ -0,0 +1,151 @@
+use prometheus::proto::MetricFamily;
+use prometheus::{HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Registry};
+
+use super::{counter, counter_vec, gauge, gauge_vec, histogram_vec};
+use crate::db::DieselPool;
+use crate::App;
+
+/// Metrics collected by the current server process.
+///
+/// These are reset whenever the server is restarted, and each server
+/// reports its own values.
+#[allow(missing_debug_implementations)]
+pub struct InstanceMetrics {
+    registry: Registry,
+
+    pub requests_total: IntCounter,
+    pub requests_in_flight: IntGauge,
+    pub responses_by_status_code_total: IntCounterVec,
+    /// Labelled with the route pattern, like `/crates/:crate_id`
+    pub response_times: HistogramVec,
+
+    database_idle_conns: IntGaugeVec,
+    database_used_conns: IntGaugeVec,
+    database_max_conns: IntGaugeVec,
+    /// Labelled with the pool, either `primary` or `replica`
+    pub database_conns_waiting: IntGaugeVec,
+    /// Labelled with the pool, either `primary` or `replica`
+    pub database_time_to_obtain_connection: HistogramVec,
+    database_budget_limit: IntGaugeVec,
+    database_budget_in_use: IntGaugeVec,
+    /// Labelled with the endpoint family, like `search`
+    pub database_budget_rejections_total: IntCounterVec,
+
+    pub downloads_total: IntCounter,
+    pub downloads_not_counted_total: IntCounter,
+}
+
+impl InstanceMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("cratesio_instance".into()), None)?;
+        Ok(Self {
+            requests_total: counter(&registry, "requests_total", "Number of requests")?,
+            requests_in_flight: gauge(
+                &registry,
+                "requests_in_flight",
+                "Number of requests being processed",
+            )?,
+            responses_by_status_code_total: counter_vec(
+                &registry,
+                "responses_by_status_code_total",
+                "Number of responses by status code",
+                &["status"],
+            )?,
+            response_times: histogram_vec(
+                &registry,
+                "response_times",
+                "Response times in seconds by endpoint",
+                &["endpoint"],
+            )?,
+            database_idle_conns: gauge_vec(
+                &registry,
+                "database_idle_conns",
+                "Number of idle database connections in the pool",
+                &["pool"],
+            )?,
+            database_used_conns: gauge_vec(
+                &registry,
+                "database_used_conns",
+                "Number of used database connections in the pool",
+                &["pool"],
+            )?,
+            database_max_conns: gauge_vec(
+                &registry,
+                "database_max_conns",
+                "Maximum number of database connections in the pool",
+                &["pool"],
+            )?,
+            database_conns_waiting: gauge_vec(
+                &registry,
+                "database_conns_waiting",
+                "Number of requests waiting for a database connection",
+                &["pool"],
+            )?,
+            database_time_to_obtain_connection: histogram_vec(
+                &registry,
+                "database_time_to_obtain_connection",
+                "Time in seconds to obtain a database connection from the pool",
+                &["pool"],
+            )?,
+            database_budget_limit: gauge_vec(
+                &registry,
+                "database_budget_limit",
+                "Maximum number of database connections an endpoint family may hold",
+                &["family"],
+            )?,
+            database_budget_in_use: gauge_vec(
+                &registry,
+                "database_budget_in_use",
+                "Number of database connections held by an endpoint family",
+                &["family"],
+            )?,
+            database_budget_rejections_total: counter_vec(
+                &registry,
+                "database_budget_rejections_total",
+                "Number of requests rejected because their endpoint family used up its budget",
+                &["family"],
+            )?,
+            downloads_total: counter(&registry, "downloads_total", "Number of crate downloads")?,
+            downloads_not_counted_total: counter(
+                &registry,
+                "downloads_not_counted_total",
+                "Number of crate downloads that could not be counted",
+            )?,
+            registry,
+        })
+    }
+
+    pub fn gather(&self, app: &App) -> Vec<MetricFamily> {
+        self.refresh_pool("primary", &app.primary_database);
+        if let Some(pool) = &app.read_only_replica_database {
+            self.refresh_pool("replica", pool);
+        }
+        for (family, limit, in_use) in app.pool_budgets.usage() {
+            let labels = [family.name()];
+            self.database_budget_limit
+                .with_label_values(&labels)
+                .set(limit as i64);
+            self.database_budget_in_use
+                .with_label_values(&labels)
+                .set(in_use as i64);
+        }
+        self.registry.gather()
+    }
+
+    fn refresh_pool(&self, name: &str, pool: &DieselPool) {
+        // The connection of the test pool is not pooled
+        if let DieselPool::Pool(pool) = pool {
+            let state = pool.state();
+            let used = state.connections - state.idle_connections;
+            self.database_idle_conns
+                .with_label_values(&[name])
+                .set(state.idle_connections.into());
+            self.database_used_conns
+                .with_label_values(&[name])
+                .set(used.into());
+            self.database_max_conns
+                .with_label_values(&[name])
+                .set(pool.max_size().into());
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Bool, Text};
+use prometheus::proto::MetricFamily;
+use prometheus::{IntGauge, IntGaugeVec, Registry};
+
+use super::{gauge, gauge_vec};
+use crate::schema::{crates, versions};
+
+/// Metrics computed from the database every time they are scraped.
+#[allow(missing_debug_implementations)]
+pub struct ServiceMetrics {
+    registry: Registry,
+
+    background_jobs: IntGaugeVec,
+    background_jobs_failing: IntGaugeVec,
+    background_jobs_oldest_age_seconds: IntGaugeVec,
+    background_job_runs_last_hour: IntGaugeVec,
+    background_job_run_seconds_last_hour: IntGaugeVec,
+
+    crates_total: IntGauge,
+    versions_total: IntGauge,
+}
+
+#[derive(QueryableByName)]
+struct QueuedJobs {
+    #[sql_type = "Text"]
+    job_type: String,
+    #[sql_type = "Text"]
+    queue: String,
+    #[sql_type = "BigInt"]
+    queued: i64,
+    #[sql_type = "BigInt"]
+    failing: i64,
+    #[sql_type = "BigInt"]
+    oldest_age_seconds: i64,
+}
+
+#[derive(QueryableByName)]
+struct RecentRuns {
+    #[sql_type = "Text"]
+    job_type: String,
+    #[sql_type = "Bool"]
+    succeeded: bool,
+    #[sql_type = "BigInt"]
+    runs: i64,
+    #[sql_type = "BigInt"]
+    seconds: i64,
+}
+
+impl ServiceMetrics {
+    pub fn new() -> prometheus::Result<Self> {
+        let registry = Registry::new_custom(Some("cratesio_service".into()), None)?;
+        Ok(Self {
+            background_jobs: gauge_vec(
+                &registry,
+                "background_jobs",
+                "Number of queued background jobs",
+                &["job_type"],
+            )?,
+            background_jobs_failing: gauge_vec(
+                &registry,
+                "background_jobs_failing",
+                "Number of queued background jobs that failed at least once",
+                &["job_type"],
+            )?,
+            background_jobs_oldest_age_seconds: gauge_vec(
+                &registry,
+                "background_jobs_oldest_age_seconds",
+                "Age of the oldest queued background job in seconds",
+                &["queue"],
+            )?,
+            background_job_runs_last_hour: gauge_vec(
+                &registry,
+                "background_job_runs_last_hour",
+                "Number of background job runs that finished in the last hour",
+                &["job_type", "succeeded"],
+            )?,
+            background_job_run_seconds_last_hour: gauge_vec(
+                &registry,
+                "background_job_run_seconds_last_hour",
+                "Total duration of the background job runs that finished in the last hour",
+                &["job_type", "succeeded"],
+            )?,
+            crates_total: gauge(&registry, "crates_total", "Number of crates")?,
+            versions_total: gauge(&registry, "versions_total", "Number of versions")?,
+            registry,
+        })
+    }
+
+    pub fn gather(&self, conn: &PgConnection) -> QueryResult<Vec<MetricFamily>> {
+        let queued: Vec<QueuedJobs> = diesel::sql_query(
+            "SELECT job_type, queue, COUNT(*) AS queued, \
+             COUNT(*) FILTER (WHERE retries > 0) AS failing, \
+             EXTRACT(EPOCH FROM NOW() - MIN(created_at))::BIGINT AS oldest_age_seconds \
+             FROM background_jobs GROUP BY job_type, queue",
+        )
+        .load(conn)?;
+
+        // Job types and queues without any jobs are not reported at all,
+        // instead of keeping the values of the previous scrape
+        self.background_jobs.reset();
+        self.background_jobs_failing.reset();
+        self.background_jobs_oldest_age_seconds.reset();
+        for jobs in &queued {
+            let job_type = &[jobs.job_type.as_str()];
+            self.background_jobs
+                .with_label_values(job_type)
+                .add(jobs.queued);
+            self.background_jobs_failing
+                .with_label_values(job_type)
+                .add(jobs.failing);
+            let oldest = self
+                .background_jobs_oldest_age_seconds
+                .with_label_values(&[&jobs.queue]);
+            oldest.set(oldest.get().max(jobs.oldest_age_seconds));
+        }
+
+        let runs: Vec<RecentRuns> = diesel::sql_query(
+            "SELECT job_type, succeeded, COUNT(*) AS runs, \
+             COALESCE(SUM(EXTRACT(EPOCH FROM finished_at - started_at)), 0)::BIGINT AS seconds \
+             FROM background_job_runs \
+             WHERE finished_at > NOW() - INTERVAL '1 hour' \
+             GROUP BY job_type, succeeded",
+        )
+        .load(conn)?;
+
+        self.background_job_runs_last_hour.reset();
+        self.background_job_run_seconds_last_hour.reset();
+        for run in &runs {
+            let labels = &[
+                run.job_type.as_str(),
+                if run.succeeded { "true" } else { "false" },
+            ];
+            self.background_job_runs_last_hour
+                .with_label_values(labels)
+                .set(run.runs);
+            self.background_job_run_seconds_last_hour
+                .with_label_values(labels)
+                .set(run.seconds);
+        }
+
+        self.crates_total
+            .set(crates::table.count().get_result(conn)?);
+        self.versions_total
+            .set(versions::table.count().get_result(conn)?);
+
+        Ok(self.registry.gather())
+    }
+}
@@ -9,21 +9,29 @@ use self::ember_html::EmberHtml;
 use self::head::Head;
 use self::known_error_to_json::KnownErrorToJson;
 use self::log_connection_pool_status::LogConnectionPoolStatus;
+use self::session_cookie::SESSION_COOKIE_NAME;
 use self::static_or_continue::StaticOrContinue;
 
 pub mod app;
 mod balance_capacity;
 mod block_traffic;
+mod compress;
 mod debug;
 mod ember_html;
 mod ensure_well_formed_500;
-mod head;
+pub mod head;
 mod known_error_to_json;
 mod log_connection_pool_status;
 pub mod log_request;
+mod maintenance_mode;
 mod normalize_path;
+mod range_requests;
+mod request_id;
 mod require_user_agent;
+pub mod security_headers;
+pub mod session_cookie;
 mod static_or_continue;
+mod update_metrics;
 
 use conduit_conditional_get::ConditionalGet;
 use conduit_cookie::{Middleware as Cookie, SessionMiddleware};
@@ -40,9 +48,14 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
     let config = app.config.clone();
     let env = config.env;
 
+    m.add(update_metrics::UpdateMetrics::new(&app));
+
+    // Added early so that it compresses the responses after all other middleware is done
+    m.add(compress::Compress);
+
     if env != Env::Test {
         m.add(ensure_well_formed_500::EnsureWellFormed500);
-        m.add(log_request::LogRequests::default());
+        m.add(log_request::LogRequests::new(config.log_format));
     }
 
     if env == Env::Development {
@@ -60,12 +73,14 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
 
     m.add(normalize_path::NormalizePath);
     m.add(ConditionalGet);
+    m.add(config.security_headers.clone());
 
     m.add(Cookie::new());
+    m.add(config.session_cookie);
     m.add(SessionMiddleware::new(
-        "cargo_session",
+        SESSION_COOKIE_NAME,
         cookie::Key::derive_from(app.session_key.as_bytes()),
-        env == Env::Production,
+        config.session_cookie.secure(),
     ));
 
     m.add(AppMiddleware::new(app));
@@ -102,11 +117,13 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
     }
 
     if env == Env::Development {
-        // Locally serve crates and readmes
+        // Locally serve crates and readmes, with support for resuming downloads
         m.around(StaticOrContinue::new("local_uploads"));
+        m.around(range_requests::RangeRequests::default());
     }
 
     m.around(Head::default());
+    m.around(maintenance_mode::MaintenanceMode::default());
 
     for (header, blocked_values) in config.blocked_traffic {
         m.around(block_traffic::BlockTraffic::new(header, blocked_values));
@@ -114,5 +131,8 @@ pub fn build_middleware(app: Arc<App>, endpoints: RouteBuilder) -> MiddlewareBui
 
     m.around(require_user_agent::RequireUserAgent::default());
 
+    // Runs first, so that even blocked requests have a request ID
+    m.around(request_id::AssignRequestId::default());
+
     m
 }
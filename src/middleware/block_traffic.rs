@@ -9,6 +9,7 @@
 //! examples). Values of the headers must match exactly.
 
 use super::prelude::*;
+use crate::util::request_id;
 use crate::App;
 use std::sync::Arc;
 
@@ -59,11 +60,7 @@ impl Handler for BlockTraffic {
                  or email help@crates.io \
                  and provide the request id {}",
                 domain_name,
-                // Heroku should always set this header
-                req.headers()
-                    .get("x-request-id")
-                    .map(|val| val.to_str().unwrap_or_default())
-                    .unwrap_or_default()
+                request_id::for_request(req)
             );
 
             Response::builder()
@@ -0,0 +1,286 @@
+//! Compresses JSON and text responses with brotli or gzip, whichever the client prefers
+//!
+//! Responses smaller than `MIN_SIZE`, parts of files for `Range` requests, and responses that
+//! already have a `Content-Encoding` are sent as they are. Responses from a file, like the version
+//! lists of crates with thousands of versions, are compressed into another temporary file, so that
+//! they are never held in memory.
+
+use super::prelude::*;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Seek, SeekFrom, Write};
+
+/// Responses smaller than this are not worth compressing
+const MIN_SIZE: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Brotli,
+    Gzip,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Brotli => "br",
+            Encoding::Gzip => "gzip",
+        }
+    }
+
+    /// Compresses everything read from `body` into `output`
+    fn compress_into<R: Read, W: Write>(self, body: &mut R, output: W) -> io::Result<W> {
+        match self {
+            Encoding::Brotli => {
+                let mut encoder = brotli::CompressorWriter::new(output, 4096, 5, 22);
+                io::copy(body, &mut encoder)?;
+                Ok(encoder.into_inner())
+            }
+            Encoding::Gzip => {
+                let mut encoder = GzEncoder::new(output, Compression::default());
+                io::copy(body, &mut encoder)?;
+                encoder.finish()
+            }
+        }
+    }
+
+    fn compress(self, body: &[u8]) -> io::Result<(u64, Body)> {
+        let compressed = self.compress_into(&mut &*body, Vec::new())?;
+        Ok((compressed.len() as u64, Body::from_vec(compressed)))
+    }
+
+    /// Compresses the rest of the file into a temporary file
+    fn compress_file(self, file: &mut File) -> io::Result<(u64, Body)> {
+        let output = BufWriter::new(tempfile::tempfile()?);
+        let mut compressed = self
+            .compress_into(file, output)?
+            .into_inner()
+            .map_err(|e| io::Error::new(e.error().kind(), e.to_string()))?;
+        let len = compressed.seek(SeekFrom::End(0))?;
+        compressed.seek(SeekFrom::Start(0))?;
+        Ok((len, Body::File(compressed)))
+    }
+}
+
+#[derive(Default)]
+pub struct Compress;
+
+impl Middleware for Compress {
+    fn after(&self, req: &mut dyn RequestExt, res: AfterResult) -> AfterResult {
+        let mut res = res?;
+
+        let encoding = match req
+            .headers()
+            .get(header::ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .and_then(preferred_encoding)
+        {
+            Some(encoding) => encoding,
+            None => return Ok(res),
+        };
+
+        let headers = res.headers();
+        let compressible = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map_or(false, is_compressible);
+        // Parts of files are sent as they are, so that their ranges match the file
+        let partial = res.status() == StatusCode::PARTIAL_CONTENT;
+        if !compressible || partial || headers.contains_key(header::CONTENT_ENCODING) {
+            return Ok(res);
+        }
+
+        let compressed = match res.body_mut() {
+            Body::Static(body) if body.len() >= MIN_SIZE => Some(encoding.compress(body)),
+            Body::Owned(body) if body.len() >= MIN_SIZE => Some(encoding.compress(body)),
+            Body::File(file) if remaining_len(file) >= MIN_SIZE as u64 => {
+                Some(encoding.compress_file(file))
+            }
+            _ => None,
+        };
+        let (len, body) = match compressed {
+            Some(compressed) => compressed.map_err(box_error)?,
+            None => return Ok(res),
+        };
+
+        let headers = res.headers_mut();
+        let content_encoding = header::HeaderValue::from_static(encoding.name());
+        headers.insert(header::CONTENT_ENCODING, content_encoding);
+        headers.insert(header::CONTENT_LENGTH, len.into());
+        let vary = header::HeaderValue::from_static("Accept-Encoding");
+        headers.append(header::VARY, vary);
+        *res.body_mut() = body;
+        Ok(res)
+    }
+}
+
+/// The number of bytes after the current position of the file, or 0 if that
+/// is unknown
+fn remaining_len(mut file: &File) -> u64 {
+    let len = file.metadata().map_or(0, |metadata| metadata.len());
+    let position = file.seek(SeekFrom::Current(0)).unwrap_or(len);
+    len.saturating_sub(position)
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    content_type.starts_with("application/json") || content_type.starts_with("text/")
+}
+
+/// Picks brotli over gzip if the `Accept-Encoding` header allows both. Quality values other than
+/// `q=0` are not compared.
+fn preferred_encoding(accept_encoding: &str) -> Option<Encoding> {
+    let mut brotli = false;
+    let mut gzip = false;
+    for item in accept_encoding.split(',') {
+        let mut params = item.split(';');
+        let name = params.next().unwrap_or_default().trim();
+        let refused = params.any(|param| {
+            param
+                .trim()
+                .strip_prefix("q=")
+                .and_then(|q| q.trim().parse::<f32>().ok())
+                .map_or(false, |q| q == 0.0)
+        });
+        if refused {
+            continue;
+        }
+
+        if name.eq_ignore_ascii_case("br") {
+            brotli = true;
+        } else if name.eq_ignore_ascii_case("gzip") || name == "*" {
+            gzip = true;
+        }
+    }
+
+    if brotli {
+        Some(Encoding::Brotli)
+    } else if gzip {
+        Some(Encoding::Gzip)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use conduit::{Handler, HttpResult, Method};
+    use conduit_middleware::MiddlewareBuilder;
+    use conduit_test::MockRequest;
+    use flate2::read::GzDecoder;
+
+    fn json_body(_: &mut dyn RequestExt) -> HttpResult {
+        let body = format!("[{}]", vec!["\"crates.io\""; 200].join(","));
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+            .header(header::CONTENT_LENGTH, body.len())
+            .body(Body::from_vec(body.into_bytes()))
+    }
+
+    fn call(accept_encoding: Option<&str>) -> Response<Body> {
+        let mut middleware = MiddlewareBuilder::new(json_body);
+        middleware.add(Compress);
+
+        let mut req = MockRequest::new(Method::GET, "/");
+        if let Some(accept_encoding) = accept_encoding {
+            req.header(header::ACCEPT_ENCODING, accept_encoding);
+        }
+        middleware.call(&mut req).unwrap()
+    }
+
+    fn body(response: Response<Body>) -> Vec<u8> {
+        match response.into_body() {
+            Body::Owned(body) => body,
+            Body::File(mut file) => {
+                let mut body = Vec::new();
+                file.read_to_end(&mut body).unwrap();
+                body
+            }
+            Body::Static(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn brotli_is_preferred() {
+        assert_eq!(
+            preferred_encoding("gzip, deflate, br"),
+            Some(Encoding::Brotli)
+        );
+        assert_eq!(
+            preferred_encoding("gzip;q=1.0, br;q=0"),
+            Some(Encoding::Gzip)
+        );
+        assert_eq!(preferred_encoding("*"), Some(Encoding::Gzip));
+        assert_eq!(preferred_encoding("identity, deflate"), None);
+    }
+
+    #[test]
+    fn json_responses_are_gzipped() {
+        let response = call(Some("gzip"));
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert_eq!(response.headers()[header::VARY], "Accept-Encoding");
+
+        let compressed = body(response);
+        let mut decompressed = String::new();
+        GzDecoder::new(&*compressed)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert!(decompressed.starts_with("[\"crates.io\","));
+    }
+
+    #[test]
+    fn json_responses_are_compressed_with_brotli() {
+        let response = call(Some("br"));
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "br");
+
+        let compressed = body(response);
+        let mut decompressed = String::new();
+        brotli::Decompressor::new(&*compressed, 4096)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert!(decompressed.starts_with("[\"crates.io\","));
+    }
+
+    #[test]
+    fn file_responses_are_compressed_into_a_file() {
+        fn file_body(_: &mut dyn RequestExt) -> HttpResult {
+            let body = format!("[{}]", vec!["\"crates.io\""; 200].join(","));
+            let mut file = tempfile::tempfile().unwrap();
+            file.write_all(body.as_bytes()).unwrap();
+            file.seek(SeekFrom::Start(0)).unwrap();
+            Response::builder()
+                .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+                .header(header::CONTENT_LENGTH, body.len())
+                .body(Body::File(file))
+        }
+
+        let mut middleware = MiddlewareBuilder::new(file_body);
+        middleware.add(Compress);
+        let mut req = MockRequest::new(Method::GET, "/");
+        req.header(header::ACCEPT_ENCODING, "gzip");
+        let response = middleware.call(&mut req).unwrap();
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+        assert!(matches!(response.body(), Body::File(_)));
+
+        let length: usize = response.headers()[header::CONTENT_LENGTH]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        let compressed = body(response);
+        assert_eq!(compressed.len(), length);
+        let mut decompressed = String::new();
+        GzDecoder::new(&*compressed)
+            .read_to_string(&mut decompressed)
+            .unwrap();
+        assert!(decompressed.starts_with("[\"crates.io\","));
+    }
+
+    #[test]
+    fn responses_are_not_compressed_without_accept_encoding() {
+        let response = call(None);
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    }
+}
@@ -50,7 +50,10 @@ impl Handler for EmberHtml {
         let api_handler = self.api_handler.as_ref().unwrap();
 
         // The "/git/" prefix is only used in development (when within a docker container)
-        if req.path().starts_with("/api/") || req.path().starts_with("/git/") {
+        if req.path().starts_with("/api/")
+            || req.path().starts_with("/git/")
+            || is_crawler_path(req.path())
+        {
             api_handler.call(req)
         } else {
             if let Some(client) = &self.fastboot_client {
@@ -80,6 +83,11 @@ impl Handler for EmberHtml {
     }
 }
 
+/// The `robots.txt` and the sitemaps, which are served by the backend
+fn is_crawler_path(path: &str) -> bool {
+    path == "/robots.txt" || path == "/sitemap.xml" || path.starts_with("/sitemaps/")
+}
+
 /// Proxy to the fastboot server in development mode
 ///
 /// This handler is somewhat hacky, and is not intended for usage in production.
@@ -5,6 +5,11 @@ use super::prelude::*;
 use crate::util::RequestProxy;
 use conduit::Method;
 
+/// Marks requests that were HEAD requests before they were proxied as GET
+/// requests, for handlers that must not act on them, like counting downloads
+#[derive(Debug, Clone, Copy)]
+pub struct HeadRequest;
+
 // Can't derive debug because of Handler.
 #[allow(missing_debug_implementations)]
 #[derive(Default)]
@@ -21,6 +26,7 @@ impl AroundMiddleware for Head {
 impl Handler for Head {
     fn call(&self, req: &mut dyn RequestExt) -> AfterResult {
         if req.method() == Method::HEAD {
+            req.mut_extensions().insert(HeadRequest);
             let mut req = RequestProxy::rewrite_method(req, Method::GET);
             self.handler.as_ref().unwrap().call(&mut req).map(|mut r| {
                 *r.body_mut() = Body::empty();
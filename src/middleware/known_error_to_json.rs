@@ -45,7 +45,7 @@ mod tests {
         assert_eq!(parts.status, StatusCode::NOT_FOUND);
         assert!(matches!(
             body,
-            Body::Owned(vec) if vec == br#"{"errors":[{"detail":"Not Found"}]}"#
+            Body::Owned(vec) if vec == br#"{"errors":[{"detail":"Not Found","code":"not_found"}]}"#
         ));
     }
 }
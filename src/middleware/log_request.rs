@@ -1,8 +1,13 @@
 //! Log all requests in a format similar to Heroku's router, but with additional
 //! information that we care about like User-Agent
+//!
+//! With `LOG_FORMAT=json`, each request is logged as a JSON object instead,
+//! with the custom metadata in a nested `metadata` object.
 
 use super::prelude::*;
+use crate::logging::LogFormat;
 use crate::util::request_header;
+use crate::util::request_id::{self, RequestId};
 
 use conduit::{header, Host, RequestExt, Scheme, StatusCode};
 use conduit_cookie::RequestSession;
@@ -18,8 +23,15 @@ const SLOW_REQUEST_THRESHOLD_MS: u64 = 1000;
 
 const FILTERED_HEADERS: &[&str] = &["Authorization", "Cookie", "X-Real-Ip", "X-Forwarded-For"];
 
-#[derive(Default)]
-pub(super) struct LogRequests();
+pub(super) struct LogRequests {
+    format: LogFormat,
+}
+
+impl LogRequests {
+    pub(super) fn new(format: LogFormat) -> Self {
+        Self { format }
+    }
+}
 
 struct OriginalPath(String);
 
@@ -56,14 +68,15 @@ impl Middleware for LogRequests {
         // This will only trucate for requests lasting > 500 million years
         let response_time = response_time as u64;
 
-        println!(
-            "{}",
-            RequestLine {
-                req,
-                res: &res,
-                response_time,
-            }
-        );
+        let line = RequestLine {
+            req,
+            res: &res,
+            response_time,
+        };
+        match self.format {
+            LogFormat::Logfmt => println!("{}", line),
+            LogFormat::Json => println!("{}", line.to_json()),
+        }
 
         report_to_sentry(req, &res, response_time);
 
@@ -173,11 +186,7 @@ fn report_to_sentry(req: &dyn RequestExt, res: &AfterResult, response_time: u64)
             }));
         }
 
-        if let Some(request_id) = req
-            .headers()
-            .get("x-request-id")
-            .and_then(|value| value.to_str().ok())
-        {
+        if let Some(RequestId(request_id)) = req.extensions().find::<RequestId>() {
             scope.set_tag("request.id", request_id);
         }
 
@@ -231,7 +240,7 @@ impl Display for RequestLine<'_> {
         line.add_field("at", at)?;
         line.add_field("method", self.req.method())?;
         line.add_quoted_field("path", FullPath(self.req))?;
-        line.add_field("request_id", request_header(self.req, "x-request-id"))?;
+        line.add_field("request_id", request_id::for_request(self.req))?;
         line.add_quoted_field("fwd", request_header(self.req, "x-real-ip"))?;
         line.add_field("service", TimeMs(self.response_time))?;
         line.add_field("status", status.as_str())?;
@@ -266,6 +275,60 @@ impl Display for RequestLine<'_> {
     }
 }
 
+impl RequestLine<'_> {
+    fn to_json(&self) -> serde_json::Value {
+        let status = match self.res {
+            Ok(resp) => resp.status(),
+            Err(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+
+        let mut line = json!({
+            "at": if self.res.is_ok() { "info" } else { "error" },
+            "method": self.req.method().as_str(),
+            "path": FullPath(self.req).to_string(),
+            "request_id": request_id::for_request(self.req),
+            "fwd": request_header(self.req, "x-real-ip"),
+            "service_ms": self.response_time,
+            "status": status.as_u16(),
+            "user_agent": request_header(self.req, header::USER_AGENT),
+        });
+
+        if let Some(metadata) = self.req.extensions().find::<CustomMetadata>() {
+            let metadata: serde_json::Map<_, _> = metadata
+                .entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.clone().into()))
+                .collect();
+            line["metadata"] = metadata.into();
+        }
+
+        if let Err(err) = self.res {
+            line["error"] = err.to_string().into();
+        }
+
+        if self.response_time > SLOW_REQUEST_THRESHOLD_MS {
+            line["slow"] = true.into();
+
+            if let Some(timings) = self.req.extensions().find::<TimingRecorder>() {
+                let timings: serde_json::Map<_, _> = timings
+                    .sections
+                    .borrow()
+                    .iter()
+                    .map(|(section, duration)| {
+                        (
+                            section.to_string(),
+                            (duration.as_secs_f64() * 1000.0).into(),
+                        )
+                    })
+                    .collect();
+                line["timings_ms"] = timings.into();
+            }
+        }
+
+        line
+    }
+}
+
 struct FullPath<'a>(&'a dyn RequestExt);
 
 impl<'a> Display for FullPath<'a> {
@@ -340,3 +403,45 @@ impl<'f, 'g> LogLine<'f, 'g> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conduit::Method;
+    use conduit_test::MockRequest;
+
+    #[test]
+    fn json_request_line() {
+        let mut req = MockRequest::new(Method::GET, "/api/v1/crates");
+        req.header(header::USER_AGENT, "cargo 1.50.0");
+        req.mut_extensions().insert(RequestId("abc123".into()));
+        assert_ok!(LogRequests::new(LogFormat::Json).before(&mut req));
+        add_custom_metadata(&mut req, "uncounted_dl", true);
+
+        let res: AfterResult = Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Body::empty())
+            .unwrap());
+        let line = RequestLine {
+            req: &req,
+            res: &res,
+            response_time: 12,
+        };
+
+        assert_eq!(
+            line.to_json(),
+            json!({
+                "at": "info",
+                "method": "GET",
+                "path": "/api/v1/crates",
+                "request_id": "abc123",
+                "fwd": "",
+                "service_ms": 12,
+                "status": 404,
+                "user_agent": "cargo 1.50.0",
+                "metadata": { "uncounted_dl": "true" },
+            })
+        );
+        assert!(line.to_string().contains("request_id=abc123"));
+    }
+}
@@ -0,0 +1,51 @@
+//! Middleware that rejects API requests that change data while admins turned
+//! on the maintenance mode, see the `maintenance` module
+//!
+//! The admin endpoints keep working, so that the maintenance mode can be
+//! turned off again. Downloads are still counted.
+
+use super::prelude::*;
+use crate::util::errors::{AppError, ReadOnlyMode};
+use crate::App;
+use std::sync::Arc;
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub struct MaintenanceMode {
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl AroundMiddleware for MaintenanceMode {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+fn changes_data(req: &dyn RequestExt) -> bool {
+    use conduit::Method;
+
+    let path = req.path();
+    !matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS)
+        && path.starts_with("/api/")
+        && !path.starts_with("/api/v1/admin/")
+}
+
+impl Handler for MaintenanceMode {
+    fn call(&self, req: &mut dyn RequestExt) -> AfterResult {
+        if changes_data(req) {
+            let app = req.extensions().find::<Arc<App>>().expect("Missing app");
+            // If the state can't be read, the database errors of the endpoint
+            // are more helpful than failing here
+            let in_maintenance = matches!(
+                app.maintenance_mode.current(&app.primary_database),
+                Ok(Some(_))
+            );
+            if in_maintenance {
+                super::log_request::add_custom_metadata(req, "cause", "maintenance mode");
+                return Ok(ReadOnlyMode.response().unwrap());
+            }
+        }
+        self.handler.as_ref().unwrap().call(req)
+    }
+}
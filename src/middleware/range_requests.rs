@@ -0,0 +1,230 @@
+//! Middleware that serves parts of static files for `Range` requests
+//!
+//! Crate files that are served by the application itself, like the ones of
+//! the local uploader, are advertised with `Accept-Ranges: bytes`, so that
+//! interrupted downloads can be resumed. A single range is supported, both
+//! `bytes=start-end` and the suffix form `bytes=-length`. Requests for several
+//! ranges, or with an `If-Range` that doesn't match the `ETag` or
+//! `Last-Modified` of the file, get the whole file. Ranges that start after
+//! the end of the file are answered with `416 Range Not Satisfiable`.
+//!
+//! Responses that are not read from a file, like the API responses, are sent
+//! as they are.
+
+use super::prelude::*;
+
+use conduit::header::HeaderValue;
+use std::io::{Read, Seek, SeekFrom};
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub struct RangeRequests {
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl AroundMiddleware for RangeRequests {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for RangeRequests {
+    fn call(&self, req: &mut dyn RequestExt) -> AfterResult {
+        let mut res = self.handler.as_ref().unwrap().call(req)?;
+        let length = match res.body() {
+            Body::File(file) if res.status() == StatusCode::OK => {
+                file.metadata().map_err(box_error)?.len()
+            }
+            _ => return Ok(res),
+        };
+
+        let range = req
+            .headers()
+            .get(header::RANGE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|_| if_range_matches(req, &res))
+            .and_then(|range| parse_range(range, length));
+        let range = match range {
+            Some(range) => range,
+            None => {
+                let bytes = HeaderValue::from_static("bytes");
+                res.headers_mut().insert(header::ACCEPT_RANGES, bytes);
+                return Ok(res);
+            }
+        };
+
+        let (start, end) = match range {
+            Ok(range) => range,
+            Err(Unsatisfiable) => {
+                return Response::builder()
+                    .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                    .header(header::CONTENT_RANGE, format!("bytes */{}", length))
+                    .body(Body::empty())
+                    .map_err(box_error);
+            }
+        };
+        let mut part = Vec::new();
+        if let Body::File(file) = res.body_mut() {
+            file.seek(SeekFrom::Start(start)).map_err(box_error)?;
+            file.take(end - start + 1)
+                .read_to_end(&mut part)
+                .map_err(box_error)?;
+        }
+
+        *res.status_mut() = StatusCode::PARTIAL_CONTENT;
+        let headers = res.headers_mut();
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        let content_range = format!("bytes {}-{}/{}", start, end, length);
+        headers.insert(
+            header::CONTENT_RANGE,
+            HeaderValue::from_str(&content_range).map_err(box_error)?,
+        );
+        headers.insert(header::CONTENT_LENGTH, part.len().into());
+        *res.body_mut() = Body::from_vec(part);
+        Ok(res)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Unsatisfiable;
+
+/// A range is only served if the `If-Range` validator, if there is one, is
+/// still the `ETag` or `Last-Modified` of the file
+fn if_range_matches(req: &dyn RequestExt, res: &Response<Body>) -> bool {
+    match req.headers().get(header::IF_RANGE) {
+        Some(if_range) => [header::ETAG, header::LAST_MODIFIED]
+            .iter()
+            .any(|validator| res.headers().get(validator) == Some(if_range)),
+        None => true,
+    }
+}
+
+/// Parses a `Range` header into the first and last byte of a file with the
+/// length. Returns `None` for ranges that are ignored, in which case the
+/// whole file is sent.
+fn parse_range(range: &str, length: u64) -> Option<Result<(u64, u64), Unsatisfiable>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    if range.contains(',') {
+        return None;
+    }
+    let (start, end) = range.split_at(range.find('-')?);
+    let (start, end) = (start.trim(), end[1..].trim());
+
+    if start.is_empty() {
+        let suffix = end.parse::<u64>().ok()?;
+        if suffix == 0 || length == 0 {
+            return Some(Err(Unsatisfiable));
+        }
+        return Some(Ok((length.saturating_sub(suffix), length - 1)));
+    }
+
+    let start = start.parse::<u64>().ok()?;
+    let end = match end {
+        "" => None,
+        end => Some(end.parse::<u64>().ok()?),
+    };
+    if matches!(end, Some(end) if end < start) {
+        return None;
+    }
+    if start >= length {
+        return Some(Err(Unsatisfiable));
+    }
+    let end = end.map_or(length - 1, |end| end.min(length - 1));
+    Some(Ok((start, end)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use conduit::{HttpResult, Method};
+    use conduit_middleware::MiddlewareBuilder;
+    use conduit_test::MockRequest;
+    use std::io::Write;
+
+    fn crate_file(_: &mut dyn RequestExt) -> HttpResult {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"0123456789").unwrap();
+        file.seek(SeekFrom::Start(0)).unwrap();
+        Response::builder()
+            .header(header::CONTENT_TYPE, "application/x-tar")
+            .header(header::LAST_MODIFIED, "Wed, 21 Oct 2015 07:28:00 GMT")
+            .body(Body::File(file))
+    }
+
+    fn call(headers: &[(header::HeaderName, &str)]) -> Response<Body> {
+        let mut middleware = MiddlewareBuilder::new(crate_file);
+        middleware.around(RangeRequests::default());
+
+        let mut req = MockRequest::new(Method::GET, "/crates/foo/foo-1.0.0.crate");
+        for (name, value) in headers {
+            req.header(name.clone(), value);
+        }
+        middleware.call(&mut req).unwrap()
+    }
+
+    fn body(response: Response<Body>) -> Vec<u8> {
+        match response.into_body() {
+            Body::Owned(body) => body,
+            Body::File(mut file) => {
+                let mut body = Vec::new();
+                file.read_to_end(&mut body).unwrap();
+                body
+            }
+            Body::Static(body) => body.to_vec(),
+        }
+    }
+
+    #[test]
+    fn ranges_are_parsed() {
+        assert_eq!(parse_range("bytes=0-4", 10), Some(Ok((0, 4))));
+        assert_eq!(parse_range("bytes=5-", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range("bytes=5-100", 10), Some(Ok((5, 9))));
+        assert_eq!(parse_range("bytes=-3", 10), Some(Ok((7, 9))));
+        assert_eq!(parse_range("bytes=-30", 10), Some(Ok((0, 9))));
+        assert_eq!(parse_range("bytes=10-", 10), Some(Err(Unsatisfiable)));
+        assert_eq!(parse_range("bytes=-0", 10), Some(Err(Unsatisfiable)));
+        assert_eq!(parse_range("bytes=4-2", 10), None);
+        assert_eq!(parse_range("bytes=0-1,4-5", 10), None);
+        assert_eq!(parse_range("lines=0-1", 10), None);
+        assert_eq!(parse_range("bytes=a-b", 10), None);
+    }
+
+    #[test]
+    fn whole_files_advertise_ranges() {
+        let response = call(&[]);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ACCEPT_RANGES], "bytes");
+        assert_eq!(body(response), b"0123456789");
+    }
+
+    #[test]
+    fn ranges_of_files_are_served() {
+        let response = call(&[(header::RANGE, "bytes=4-")]);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 4-9/10");
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "6");
+        assert_eq!(body(response), b"456789");
+
+        let response = call(&[(header::RANGE, "bytes=12-")]);
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes */10");
+    }
+
+    #[test]
+    fn outdated_if_range_gets_the_whole_file() {
+        let response = call(&[
+            (header::RANGE, "bytes=4-"),
+            (header::IF_RANGE, "Thu, 22 Oct 2015 07:28:00 GMT"),
+        ]);
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response), b"0123456789");
+
+        let response = call(&[
+            (header::RANGE, "bytes=4-"),
+            (header::IF_RANGE, "Wed, 21 Oct 2015 07:28:00 GMT"),
+        ]);
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    }
+}
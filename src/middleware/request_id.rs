@@ -0,0 +1,41 @@
+//! Assign a request ID to every request, see `util::request_id`
+
+use super::prelude::*;
+
+use crate::util::request_id::{self, RequestId};
+
+/// IDs sent by clients that are longer than this are replaced
+const MAX_LENGTH: usize = 200;
+
+// Can't derive debug because of Handler.
+#[allow(missing_debug_implementations)]
+#[derive(Default)]
+pub(super) struct AssignRequestId {
+    handler: Option<Box<dyn Handler>>,
+}
+
+impl AroundMiddleware for AssignRequestId {
+    fn with_handler(&mut self, handler: Box<dyn Handler>) {
+        self.handler = Some(handler);
+    }
+}
+
+impl Handler for AssignRequestId {
+    fn call(&self, req: &mut dyn RequestExt) -> AfterResult {
+        let id = req
+            .headers()
+            .get(request_id::HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty() && value.len() <= MAX_LENGTH)
+            .map(String::from)
+            .unwrap_or_else(request_id::generate);
+        req.mut_extensions().insert(RequestId(id.clone()));
+
+        let handler = self.handler.as_ref().unwrap();
+        let mut res = request_id::with(Some(&id), || handler.call(req));
+        if let (Ok(response), Ok(value)) = (&mut res, id.parse::<header::HeaderValue>()) {
+            response.headers_mut().insert(request_id::HEADER, value);
+        }
+        res
+    }
+}
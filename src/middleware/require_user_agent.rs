@@ -10,7 +10,7 @@
 use super::prelude::*;
 use std::env;
 
-use crate::util::request_header;
+use crate::util::{request_header, request_id};
 
 // Can't derive debug because of Handler.
 #[allow(missing_debug_implementations)]
@@ -37,7 +37,7 @@ impl Handler for RequireUserAgent {
             super::log_request::add_custom_metadata(req, "cause", "no user agent");
             let body = format!(
                 include_str!("no_user_agent_message.txt"),
-                request_header(req, "x-request-id"),
+                request_id::for_request(req),
             );
 
             Response::builder()
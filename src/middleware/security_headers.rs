@@ -0,0 +1,281 @@
+//! Middleware that adds security related headers to every response
+//!
+//! By default, responses get a `Content-Security-Policy` for the frontend,
+//! `X-Content-Type-Options`, `X-Frame-Options`, `Referrer-Policy` and, in
+//! production, `Strict-Transport-Security`. The API gets a stricter policy,
+//! since it never serves content that needs to load anything, while rendered
+//! READMEs get one that allows their inline styles and images from anywhere.
+//!
+//! The defaults can be changed with the `CONTENT_SECURITY_POLICY`,
+//! `STRICT_TRANSPORT_SECURITY`, `REFERRER_POLICY` and `FRAME_OPTIONS`
+//! environment variables, where an empty value disables the header.
+//! `CONTENT_SECURITY_POLICY_OVERRIDES` sets the policy for paths starting with
+//! a prefix, as `|` separated `prefix=policy` pairs, for example
+//! `/readmes/=default-src 'none'; img-src *|/docs/=default-src 'self'`.
+//!
+//! Headers set by the endpoints themselves are left alone.
+
+use super::prelude::*;
+
+use conduit::header::{HeaderName, HeaderValue};
+
+use crate::Env;
+
+const API_POLICY: &str = "default-src 'none'; frame-ancestors 'none'";
+const README_POLICY: &str =
+    "default-src 'none'; img-src * data:; style-src 'unsafe-inline'; sandbox";
+
+#[derive(Clone, Debug)]
+pub struct SecurityHeaders {
+    /// The headers added to every response, unless they are overridden
+    defaults: Vec<(HeaderName, String)>,
+    /// Headers replacing the defaults for paths starting with a prefix. The
+    /// first matching prefix is used, and empty values remove the header.
+    overrides: Vec<(String, Vec<(HeaderName, String)>)>,
+}
+
+impl SecurityHeaders {
+    /// The built-in headers. `storage_host` is the host crate files and
+    /// READMEs are served from, which the frontend fetches from.
+    pub fn new(env: Env, storage_host: Option<&str>) -> Self {
+        let mut connect_src =
+            "'self' *.ingest.sentry.io https://docs.rs https://play.rust-lang.org".to_string();
+        if let Some(host) = storage_host {
+            connect_src.push_str(" https://");
+            connect_src.push_str(host);
+        }
+        let frontend_policy = format!(
+            "default-src 'self'; connect-src {}; \
+             script-src 'self' 'unsafe-eval' 'sha256-n1+BB7Ckjcal1Pr7QNBh/dKRTtBQsIytFodRiIosXdE='; \
+             style-src 'self' 'unsafe-inline' https://code.cdn.mozilla.net; \
+             font-src https://code.cdn.mozilla.net; img-src *; object-src 'none'",
+            connect_src
+        );
+
+        let mut defaults = vec![
+            (header::CONTENT_SECURITY_POLICY, frontend_policy),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".into()),
+            (header::X_FRAME_OPTIONS, "SAMEORIGIN".into()),
+            (
+                header::REFERRER_POLICY,
+                "strict-origin-when-cross-origin".into(),
+            ),
+        ];
+        if env == Env::Production {
+            defaults.push((header::STRICT_TRANSPORT_SECURITY, "max-age=31536000".into()));
+        }
+
+        Self {
+            defaults,
+            overrides: vec![
+                ("/api/".into(), vec![csp(API_POLICY)]),
+                ("/readmes/".into(), vec![csp(README_POLICY)]),
+            ],
+        }
+    }
+
+    /// The built-in headers, changed by the environment variables described
+    /// in the module documentation
+    pub fn from_environment(env: Env, storage_host: Option<&str>) -> Self {
+        let mut headers = Self::new(env, storage_host);
+
+        let variables = [
+            ("CONTENT_SECURITY_POLICY", header::CONTENT_SECURITY_POLICY),
+            (
+                "STRICT_TRANSPORT_SECURITY",
+                header::STRICT_TRANSPORT_SECURITY,
+            ),
+            ("REFERRER_POLICY", header::REFERRER_POLICY),
+            ("FRAME_OPTIONS", header::X_FRAME_OPTIONS),
+        ];
+        for (variable, name) in variables.iter() {
+            if let Ok(value) = dotenv::var(variable) {
+                headers = headers.with_default(name.clone(), value);
+            }
+        }
+
+        if let Ok(overrides) = dotenv::var("CONTENT_SECURITY_POLICY_OVERRIDES") {
+            // Newer overrides come first, so they take precedence over the
+            // built-in ones
+            let mut parsed = parse_overrides(&overrides);
+            parsed.append(&mut headers.overrides);
+            headers.overrides = parsed;
+        }
+
+        headers
+    }
+
+    /// Replaces the default value of a header. An empty value removes it.
+    pub fn with_default(mut self, name: HeaderName, value: impl Into<String>) -> Self {
+        self.defaults.retain(|(existing, _)| *existing != name);
+        self.defaults.push((name, value.into()));
+        self
+    }
+
+    /// Replaces headers for the paths starting with `prefix`, taking
+    /// precedence over the overrides added before
+    pub fn with_override(
+        mut self,
+        prefix: impl Into<String>,
+        headers: Vec<(HeaderName, String)>,
+    ) -> Self {
+        self.overrides.insert(0, (prefix.into(), headers));
+        self
+    }
+
+    /// The headers of a response for `path`, leaving out the ones that are
+    /// removed by an override
+    fn headers_for<'a>(&'a self, path: &str) -> Vec<(&'a HeaderName, &'a str)> {
+        let overrides = self
+            .overrides
+            .iter()
+            .find(|(prefix, _)| path.starts_with(prefix.as_str()))
+            .map(|(_, headers)| headers.as_slice())
+            .unwrap_or_default();
+
+        let defaults = self
+            .defaults
+            .iter()
+            .filter(|(name, _)| !overrides.iter().any(|(replaced, _)| replaced == name));
+
+        overrides
+            .iter()
+            .chain(defaults)
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(name, value)| (name, value.as_str()))
+            .collect()
+    }
+}
+
+impl Middleware for SecurityHeaders {
+    fn after(&self, req: &mut dyn RequestExt, res: AfterResult) -> AfterResult {
+        let mut res = res?;
+
+        for (name, value) in self.headers_for(req.path()) {
+            if res.headers().contains_key(name) {
+                continue;
+            }
+            if let Ok(value) = HeaderValue::from_str(value) {
+                res.headers_mut().insert(name.clone(), value);
+            }
+        }
+
+        Ok(res)
+    }
+}
+
+fn csp(policy: &str) -> (HeaderName, String) {
+    (header::CONTENT_SECURITY_POLICY, policy.into())
+}
+
+fn parse_overrides(overrides: &str) -> Vec<(String, Vec<(HeaderName, String)>)> {
+    overrides
+        .split('|')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let mut parts = entry.splitn(2, '=');
+            let prefix = parts.next().unwrap_or_default().trim();
+            let policy = parts.next().unwrap_or_else(|| {
+                panic!(
+                    "invalid CONTENT_SECURITY_POLICY_OVERRIDES entry `{}`, expected `prefix=policy`",
+                    entry
+                )
+            });
+            (prefix.to_string(), vec![csp(policy.trim())])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use conduit::{Handler, HttpResult, Method};
+    use conduit_middleware::MiddlewareBuilder;
+    use conduit_test::MockRequest;
+
+    fn empty(_: &mut dyn RequestExt) -> HttpResult {
+        Ok(Response::new(Body::empty()))
+    }
+
+    fn with_frame_options(_: &mut dyn RequestExt) -> HttpResult {
+        Response::builder()
+            .header(header::X_FRAME_OPTIONS, "DENY")
+            .body(Body::empty())
+    }
+
+    fn call(
+        headers: SecurityHeaders,
+        handler: fn(&mut dyn RequestExt) -> HttpResult,
+        path: &str,
+    ) -> Response<Body> {
+        let mut middleware = MiddlewareBuilder::new(handler);
+        middleware.add(headers);
+        let mut req = MockRequest::new(Method::GET, path);
+        middleware.call(&mut req).unwrap()
+    }
+
+    #[test]
+    fn frontend_responses_get_all_headers() {
+        let headers = SecurityHeaders::new(Env::Production, Some("static.crates.io"));
+        let response = call(headers, empty, "/crates/serde");
+        let headers = response.headers();
+
+        let policy = headers[header::CONTENT_SECURITY_POLICY].to_str().unwrap();
+        assert!(policy.starts_with("default-src 'self'"));
+        assert!(policy.contains("https://static.crates.io"));
+        assert_eq!(headers[header::X_CONTENT_TYPE_OPTIONS], "nosniff");
+        assert_eq!(headers[header::X_FRAME_OPTIONS], "SAMEORIGIN");
+        assert_eq!(
+            headers[header::REFERRER_POLICY],
+            "strict-origin-when-cross-origin"
+        );
+        assert_eq!(
+            headers[header::STRICT_TRANSPORT_SECURITY],
+            "max-age=31536000"
+        );
+    }
+
+    #[test]
+    fn routes_can_override_headers() {
+        let headers = SecurityHeaders::new(Env::Development, None).with_override(
+            "/api/v1/crates/",
+            vec![(header::X_FRAME_OPTIONS, String::new())],
+        );
+
+        let response = call(headers.clone(), empty, "/api/v1/summary");
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            API_POLICY
+        );
+        assert!(!response
+            .headers()
+            .contains_key(header::STRICT_TRANSPORT_SECURITY));
+
+        let response = call(headers.clone(), empty, "/api/v1/crates/serde");
+        assert!(!response.headers().contains_key(header::X_FRAME_OPTIONS));
+
+        let response = call(headers, empty, "/readmes/serde/serde-1.0.0.html");
+        assert_eq!(
+            response.headers()[header::CONTENT_SECURITY_POLICY],
+            README_POLICY
+        );
+    }
+
+    #[test]
+    fn headers_of_the_endpoint_are_kept() {
+        let headers = SecurityHeaders::new(Env::Development, None);
+        let response = call(headers, with_frame_options, "/");
+        assert_eq!(response.headers()[header::X_FRAME_OPTIONS], "DENY");
+    }
+
+    #[test]
+    fn overrides_are_parsed() {
+        let parsed =
+            parse_overrides("/readmes/=default-src 'none'; img-src *|/docs/=default-src 'self'");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].0, "/readmes/");
+        assert_eq!(parsed[0].1[0].1, "default-src 'none'; img-src *");
+        assert_eq!(parsed[1].0, "/docs/");
+    }
+}
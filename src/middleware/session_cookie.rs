@@ -0,0 +1,85 @@
+//! Middleware that sets the attributes of the session cookie
+//!
+//! `conduit_cookie` marks the session cookie as `HttpOnly`, and as `Secure`
+//! if it is told to. This adds the `SameSite` attribute, so that browsers
+//! don't send the cookie along with requests started by other sites.
+//!
+//! Both attributes can be changed with the `SESSION_COOKIE_SAME_SITE` (one of
+//! `strict`, `lax` and `none`) and `SESSION_COOKIE_SECURE` (`true` or `false`)
+//! environment variables. By default the cookie is `SameSite=Lax`, and
+//! `Secure` in production.
+
+use super::prelude::*;
+
+use conduit_cookie::RequestCookies;
+use cookie::SameSite;
+
+use crate::Env;
+
+/// The name of the cookie holding the session
+pub const SESSION_COOKIE_NAME: &str = "cargo_session";
+
+#[derive(Clone, Copy, Debug)]
+pub struct SessionCookie {
+    same_site: SameSite,
+    secure: bool,
+}
+
+impl SessionCookie {
+    pub fn new(env: Env) -> Self {
+        Self {
+            same_site: SameSite::Lax,
+            secure: env == Env::Production,
+        }
+    }
+
+    /// The default attributes, changed by the environment variables described
+    /// in the module documentation
+    pub fn from_environment(env: Env) -> Self {
+        let mut cookie = Self::new(env);
+
+        if let Ok(value) = dotenv::var("SESSION_COOKIE_SAME_SITE") {
+            cookie.same_site = match &*value.to_lowercase() {
+                "strict" => SameSite::Strict,
+                "lax" => SameSite::Lax,
+                "none" => SameSite::None,
+                _ => panic!("invalid SESSION_COOKIE_SAME_SITE `{}`", value),
+            };
+        }
+        if let Ok(value) = dotenv::var("SESSION_COOKIE_SECURE") {
+            cookie.secure = value
+                .parse()
+                .unwrap_or_else(|_| panic!("invalid SESSION_COOKIE_SECURE `{}`", value));
+        }
+
+        // Browsers reject `SameSite=None` cookies that aren't `Secure`
+        if cookie.same_site == SameSite::None && !cookie.secure {
+            panic!("SESSION_COOKIE_SAME_SITE=none requires a secure session cookie");
+        }
+        cookie
+    }
+
+    /// Whether the cookie is only sent over HTTPS
+    pub fn secure(&self) -> bool {
+        self.secure
+    }
+}
+
+impl Middleware for SessionCookie {
+    fn after(&self, req: &mut dyn RequestExt, res: AfterResult) -> AfterResult {
+        // This runs after the session middleware signed the cookie, and before
+        // the cookie middleware turns the changed cookies into headers
+        let jar = req.cookies_mut();
+        let cookie = jar
+            .delta()
+            .find(|cookie| cookie.name() == SESSION_COOKIE_NAME)
+            .cloned();
+        if let Some(mut cookie) = cookie {
+            cookie.set_same_site(self.same_site);
+            cookie.set_secure(self.secure);
+            jar.add(cookie);
+        }
+
+        res
+    }
+}
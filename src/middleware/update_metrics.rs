@@ -0,0 +1,53 @@
+//! Record the number and duration of the requests in the instance metrics
+
+use super::prelude::*;
+use crate::app::App;
+
+use conduit_router::RoutePattern;
+use std::sync::Arc;
+
+pub(super) struct UpdateMetrics {
+    app: Arc<App>,
+}
+
+impl UpdateMetrics {
+    pub(super) fn new(app: &Arc<App>) -> Self {
+        Self { app: app.clone() }
+    }
+}
+
+impl Middleware for UpdateMetrics {
+    fn before(&self, _: &mut dyn RequestExt) -> BeforeResult {
+        self.app.instance_metrics.requests_in_flight.inc();
+        Ok(())
+    }
+
+    fn after(&self, req: &mut dyn RequestExt, res: AfterResult) -> AfterResult {
+        let metrics = &self.app.instance_metrics;
+
+        metrics.requests_in_flight.dec();
+        metrics.requests_total.inc();
+
+        // Requests that don't match any route are grouped together, so that
+        // the number of endpoints doesn't grow with the number of paths
+        let endpoint = match req.extensions().find::<RoutePattern>() {
+            Some(pattern) => pattern.pattern(),
+            None => "<unknown>",
+        };
+        metrics
+            .response_times
+            .with_label_values(&[endpoint])
+            .observe(req.elapsed().as_secs_f64());
+
+        let status = match &res {
+            Ok(response) => response.status().as_u16(),
+            Err(_) => 500,
+        };
+        metrics
+            .responses_by_status_code_total
+            .with_label_values(&[&status.to_string()])
+            .inc();
+
+        res
+    }
+}
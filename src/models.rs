@@ -1,35 +1,99 @@
-pub use self::action::{insert_version_owner_action, VersionAction, VersionOwnerAction};
+pub use self::action::{
+    insert_version_owner_action, VersionAction, VersionOwnerAction, VersionPublishOrigin,
+};
+pub use self::advisory::{Advisory, NewAdvisory};
+pub use self::announcement::{Announcement, AnnouncementSeverity, NewAnnouncement};
 pub use self::badge::{Badge, CrateBadge, MaintenanceStatus};
+pub use self::bulk_yank::{BulkYank, NewBulkYank};
 pub use self::category::{Category, CrateCategory, NewCategory};
+pub use self::crate_activity::CrateActivity;
+pub use self::crate_dependents::{CrateDependencies, CrateDependents};
+pub use self::crate_list::{CrateList, CrateListEntry, CrateListItem, NewCrateList};
 pub use self::crate_owner_invitation::{CrateOwnerInvitation, NewCrateOwnerInvitation};
-pub use self::dependency::{Dependency, DependencyKind, ReverseDependency};
+pub use self::crate_trending_score::CrateTrendingScore;
+pub use self::data_export::DataExport;
+pub use self::database_dump::{DatabaseDump, NewDatabaseDump};
+pub use self::dependency::{resolve_dependencies, Dependency, DependencyKind, ReverseDependency};
 pub use self::download::VersionDownload;
-pub use self::email::{Email, NewEmail};
-pub use self::follow::Follow;
+pub use self::email::{Email, EmailChange, NewEmail};
+pub use self::email_preferences::{EmailEvent, EmailPreferences};
+pub use self::featured_crate::{FeaturedCrate, NewFeaturedCrate};
+pub use self::follow::{Follow, UserFollow};
 pub use self::keyword::{CrateKeyword, Keyword};
 pub use self::krate::{Crate, CrateVersions, NewCrate, RecentCrateDownloads};
+pub use self::login_anomaly::LoginAnomaly;
+pub use self::materialized_response::MaterializedResponse;
+pub use self::monthly_stats::{CategoryStats, KeywordStats};
+pub use self::mute::Mute;
+pub use self::notification::{NewNotification, Notification, NotificationKind};
 pub use self::owner::{CrateOwner, Owner, OwnerKind};
+pub use self::pending_upload::{NewPendingUpload, PendingUpload};
+pub use self::project_link::{FundedDependency, NewProjectLink, ProjectLink, ProjectLinkKind};
+pub use self::publish_rate_override::{
+    PublishRateOverride, PublishRateOverrideAction, PublishRateOverrideActionKind,
+};
+pub use self::quarantine::{Finding, QuarantineStatus, VersionQuarantine};
+pub use self::quota_override::QuotaOverride;
+pub use self::readme_rerender::{NewReadmeRerender, ReadmeRerender};
+pub use self::repository_verification::RepositoryVerification;
+pub use self::reserved_name::ReservedCrateName;
 pub use self::rights::Rights;
+pub use self::storage_mismatch::{NewStorageMismatch, StorageMismatch, StorageMismatchKind};
 pub use self::team::{NewTeam, Team};
 pub use self::token::{ApiToken, CreatedApiToken};
-pub use self::user::{NewUser, User};
+pub use self::user::{NewUser, ProfileChanges, User};
+pub use self::user_session::{CreatedUserSession, UserSession};
+pub use self::username_change::UsernameChange;
+pub use self::verified_publisher::VerifiedPublisher;
 pub use self::version::{NewVersion, TopVersions, Version};
+pub use self::version_checksum::{ChecksumAlgorithm, VersionChecksum};
+pub use self::version_license::{LicenseUsage, VersionLicense};
 
 pub mod helpers;
 
 mod action;
+mod advisory;
+mod announcement;
 mod badge;
+mod bulk_yank;
 pub mod category;
+mod crate_activity;
+mod crate_dependents;
+mod crate_list;
 mod crate_owner_invitation;
+mod crate_trending_score;
+mod data_export;
+mod database_dump;
 pub mod dependency;
 mod download;
 mod email;
+mod email_preferences;
+mod featured_crate;
 mod follow;
 mod keyword;
 pub mod krate;
+mod login_anomaly;
+mod materialized_response;
+pub mod monthly_stats;
+mod mute;
+mod notification;
 mod owner;
+mod pending_upload;
+mod project_link;
+mod publish_rate_override;
+mod quarantine;
+mod quota_override;
+mod readme_rerender;
+mod repository_verification;
+mod reserved_name;
 mod rights;
+mod storage_mismatch;
 mod team;
 mod token;
 pub mod user;
+mod user_session;
+mod username_change;
+mod verified_publisher;
 mod version;
+mod version_checksum;
+mod version_license;
@@ -6,6 +6,7 @@ use diesel::{
     serialize::{self, Output, ToSql},
     sql_types::Integer,
 };
+use std::collections::HashMap;
 use std::io::Write;
 
 use crate::models::{ApiToken, User, Version};
@@ -84,15 +85,29 @@ impl VersionOwnerAction {
             .load(conn)
     }
 
-    pub fn for_versions(
+    /// Loads the actions of many versions in a single query, ordered like
+    /// `by_version` does
+    pub fn for_version_ids(
         conn: &PgConnection,
-        versions: &[Version],
-    ) -> QueryResult<Vec<Vec<(Self, User)>>> {
-        Ok(Self::belonging_to(versions)
+        version_ids: &[i32],
+    ) -> QueryResult<HashMap<i32, Vec<(Self, User)>>> {
+        use diesel::dsl::any;
+        use version_owner_actions::dsl::version_id;
+
+        let actions: Vec<(Self, User)> = version_owner_actions::table
+            .filter(version_id.eq(any(version_ids)))
             .inner_join(users::table)
             .order(version_owner_actions::dsl::id)
-            .load(conn)?
-            .grouped_by(versions))
+            .load(conn)?;
+
+        let mut by_version: HashMap<i32, Vec<(Self, User)>> = HashMap::new();
+        for (action, user) in actions {
+            by_version
+                .entry(action.version_id)
+                .or_default()
+                .push((action, user));
+        }
+        Ok(by_version)
     }
 }
 
@@ -114,3 +129,32 @@ pub fn insert_version_owner_action(
         ))
         .get_result(conn)
 }
+
+/// Where the request that published a version came from
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable)]
+#[belongs_to(Version)]
+#[primary_key(version_id)]
+#[table_name = "version_publish_origins"]
+pub struct VersionPublishOrigin {
+    pub version_id: i32,
+    /// The class of the client address as returned by `util::ip_class`.
+    /// The address itself isn't stored.
+    pub ip_class: String,
+}
+
+impl VersionPublishOrigin {
+    pub fn insert(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(version_publish_origins::table)
+            .values(self)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Returns `None` for versions published before origins were recorded
+    pub fn for_version(conn: &PgConnection, version: &Version) -> QueryResult<Option<Self>> {
+        version_publish_origins::table
+            .find(version.id)
+            .first(conn)
+            .optional()
+    }
+}
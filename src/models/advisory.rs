@@ -0,0 +1,410 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use semver::VersionReq;
+use std::collections::HashMap;
+
+use crate::models::krate::canon_crate_name;
+use crate::models::Crate;
+use crate::schema::{advisories, crates, version_advisories, versions};
+
+/// A security advisory, either of the [RustSec advisory database] and
+/// imported by the `SyncAdvisories` background job, or published by an
+/// owner of the affected crate.
+///
+/// [RustSec advisory database]: https://github.com/rustsec/advisory-db
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "advisories"]
+pub struct Advisory {
+    /// The RustSec id, like `RUSTSEC-2021-0001`, or an id like
+    /// `CRATESIO-2021-0001` for advisories published by owners
+    pub id: String,
+    pub crate_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    /// Version requirements of the versions containing a fix
+    pub patched: Vec<String>,
+    /// Version requirements of the versions that were never affected
+    pub unaffected: Vec<String>,
+    /// The kind of an informational advisory, like `unmaintained`, which
+    /// doesn't describe a vulnerability
+    pub informational: Option<String>,
+    /// Withdrawn advisories are kept, but don't affect any version
+    pub withdrawn: bool,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub description: Option<String>,
+    /// One of `Advisory::SEVERITIES`, only set for advisories published by owners
+    pub severity: Option<String>,
+    /// Version requirements of the affected versions. All other versions are
+    /// unaffected. If empty, every version that is neither patched nor
+    /// unaffected is affected.
+    pub affected: Vec<String>,
+    /// The owner who published the advisory, if it wasn't imported
+    pub published_by: Option<i32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Insertable, AsChangeset)]
+#[table_name = "advisories"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct NewAdvisory {
+    pub id: String,
+    pub crate_name: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: NaiveDate,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub informational: Option<String>,
+    pub withdrawn: bool,
+    pub description: Option<String>,
+    pub severity: Option<String>,
+    pub affected: Vec<String>,
+    pub published_by: Option<i32>,
+}
+
+impl From<Advisory> for NewAdvisory {
+    fn from(advisory: Advisory) -> Self {
+        Self {
+            id: advisory.id,
+            crate_name: advisory.crate_name,
+            title: advisory.title,
+            url: advisory.url,
+            date: advisory.date,
+            patched: advisory.patched,
+            unaffected: advisory.unaffected,
+            informational: advisory.informational,
+            withdrawn: advisory.withdrawn,
+            description: advisory.description,
+            severity: advisory.severity,
+            affected: advisory.affected,
+            published_by: advisory.published_by,
+        }
+    }
+}
+
+impl Advisory {
+    /// The prefix of the ids of advisories published by owners
+    pub const OWNER_ID_PREFIX: &'static str = "CRATESIO";
+
+    /// The severities an owner can give an advisory
+    pub const SEVERITIES: &'static [&'static str] = &["low", "medium", "high", "critical"];
+
+    pub fn find(conn: &PgConnection, id: &str) -> QueryResult<Self> {
+        advisories::table.find(id).first(conn)
+    }
+
+    /// Whether the advisory was published by an owner, and can be changed by
+    /// the owners of the crate
+    pub fn is_owner_published(&self) -> bool {
+        self.id.starts_with(Self::OWNER_ID_PREFIX)
+    }
+
+    /// The advisories of a crate, newest first
+    pub fn for_crate(conn: &PgConnection, crate_name: &str) -> QueryResult<Vec<Self>> {
+        advisories::table
+            .filter(canon_crate_name(advisories::crate_name).eq(canon_crate_name(crate_name)))
+            .order((advisories::date.desc(), advisories::id.desc()))
+            .load(conn)
+    }
+
+    /// All advisories, including withdrawn ones, that were changed after
+    /// `since` if given, least recently changed first
+    pub fn modified_since(
+        conn: &PgConnection,
+        since: Option<NaiveDateTime>,
+    ) -> QueryResult<Vec<Self>> {
+        let mut query = advisories::table
+            .order((advisories::updated_at, advisories::id))
+            .into_boxed();
+        if let Some(since) = since {
+            query = query.filter(advisories::updated_at.gt(since));
+        }
+        query.load(conn)
+    }
+
+    /// The numbers of the versions each of the given advisories affects
+    pub fn affected_versions_of(
+        conn: &PgConnection,
+        advisory_ids: &[String],
+    ) -> QueryResult<HashMap<String, Vec<String>>> {
+        use diesel::dsl::any;
+
+        let links: Vec<(String, String)> = version_advisories::table
+            .inner_join(versions::table)
+            .filter(version_advisories::advisory_id.eq(any(advisory_ids)))
+            .select((version_advisories::advisory_id, versions::num))
+            .order(versions::id)
+            .load(conn)?;
+
+        let mut affected_versions: HashMap<String, Vec<String>> = HashMap::new();
+        for (advisory_id, num) in links {
+            affected_versions.entry(advisory_id).or_default().push(num);
+        }
+        Ok(affected_versions)
+    }
+
+    /// The ids of the advisories affecting each of the given versions
+    pub fn ids_for_versions(
+        conn: &PgConnection,
+        version_ids: &[i32],
+    ) -> QueryResult<HashMap<i32, Vec<String>>> {
+        use diesel::dsl::any;
+
+        let links: Vec<(i32, String)> = version_advisories::table
+            .filter(version_advisories::version_id.eq(any(version_ids)))
+            .select((
+                version_advisories::version_id,
+                version_advisories::advisory_id,
+            ))
+            .order(version_advisories::advisory_id)
+            .load(conn)?;
+
+        let mut ids = HashMap::new();
+        for (version_id, advisory_id) in links {
+            ids.entry(version_id)
+                .or_insert_with(Vec::new)
+                .push(advisory_id);
+        }
+        Ok(ids)
+    }
+
+    /// The advisories affecting each of the given versions, by id
+    pub fn for_versions(
+        conn: &PgConnection,
+        version_ids: &[i32],
+    ) -> QueryResult<HashMap<i32, Vec<Self>>> {
+        use diesel::dsl::any;
+
+        let links: Vec<(i32, Self)> = version_advisories::table
+            .inner_join(advisories::table)
+            .filter(version_advisories::version_id.eq(any(version_ids)))
+            .select((version_advisories::version_id, advisories::all_columns))
+            .order(version_advisories::advisory_id)
+            .load(conn)?;
+
+        let mut advisories = HashMap::new();
+        for (version_id, advisory) in links {
+            advisories
+                .entry(version_id)
+                .or_insert_with(Vec::new)
+                .push(advisory);
+        }
+        Ok(advisories)
+    }
+
+    /// Links a newly published version to the advisories of its crate that
+    /// affect it
+    pub fn link_new_version(
+        conn: &PgConnection,
+        crate_name: &str,
+        version_id: i32,
+        num: &semver::Version,
+    ) -> QueryResult<()> {
+        let affecting: Vec<_> = advisories::table
+            .filter(canon_crate_name(advisories::crate_name).eq(canon_crate_name(crate_name)))
+            .filter(advisories::withdrawn.eq(false))
+            .load::<Self>(conn)?
+            .into_iter()
+            .filter(|advisory| {
+                affects(
+                    &advisory.affected,
+                    &advisory.patched,
+                    &advisory.unaffected,
+                    num,
+                )
+            })
+            .map(|advisory| {
+                (
+                    version_advisories::version_id.eq(version_id),
+                    version_advisories::advisory_id.eq(advisory.id),
+                )
+            })
+            .collect();
+
+        if !affecting.is_empty() {
+            diesel::insert_into(version_advisories::table)
+                .values(&affecting)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+
+    /// The numbers of the versions of a crate each of its advisories affects
+    pub fn affected_versions(
+        conn: &PgConnection,
+        crate_id: i32,
+    ) -> QueryResult<HashMap<String, Vec<String>>> {
+        let links: Vec<(String, String)> = version_advisories::table
+            .inner_join(versions::table)
+            .filter(versions::crate_id.eq(crate_id))
+            .select((version_advisories::advisory_id, versions::num))
+            .order(versions::id)
+            .load(conn)?;
+
+        let mut affected_versions: HashMap<String, Vec<String>> = HashMap::new();
+        for (advisory_id, num) in links {
+            affected_versions.entry(advisory_id).or_default().push(num);
+        }
+        Ok(affected_versions)
+    }
+}
+
+impl NewAdvisory {
+    /// The next id for an advisory published by an owner in `year`
+    pub fn next_owner_advisory_id(conn: &PgConnection, year: i32) -> QueryResult<String> {
+        use diesel::dsl::{select, sql};
+        use diesel::sql_types::BigInt;
+
+        let number: i64 =
+            select(sql::<BigInt>("nextval('owner_advisory_number_seq')")).get_result(conn)?;
+        Ok(format!(
+            "{}-{}-{:04}",
+            Advisory::OWNER_ID_PREFIX,
+            year,
+            number
+        ))
+    }
+
+    pub fn affects(&self, version: &semver::Version) -> bool {
+        !self.withdrawn && affects(&self.affected, &self.patched, &self.unaffected, version)
+    }
+
+    /// Inserts or updates the advisory and links it to the versions it
+    /// affects, returning whether the advisory is new
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<bool> {
+        use diesel::dsl::{exists, select};
+
+        conn.transaction(|| {
+            let existed: bool =
+                select(exists(advisories::table.find(&self.id))).get_result(conn)?;
+
+            diesel::insert_into(advisories::table)
+                .values(self)
+                .on_conflict(advisories::id)
+                .do_update()
+                .set(self)
+                .execute(conn)?;
+
+            self.link_versions(conn)?;
+            Ok(!existed)
+        })
+    }
+
+    /// Replaces the links of the advisory, which change when an advisory is
+    /// amended or withdrawn
+    fn link_versions(&self, conn: &PgConnection) -> QueryResult<()> {
+        let candidates: Vec<(i32, String)> = versions::table
+            .inner_join(crates::table)
+            .filter(Crate::with_name(&self.crate_name))
+            .select((versions::id, versions::num))
+            .load(conn)?;
+
+        let affected: Vec<_> = candidates
+            .into_iter()
+            .filter(|(_, num)| {
+                semver::Version::parse(num)
+                    .map(|num| self.affects(&num))
+                    .unwrap_or(false)
+            })
+            .map(|(version_id, _)| {
+                (
+                    version_advisories::version_id.eq(version_id),
+                    version_advisories::advisory_id.eq(&self.id),
+                )
+            })
+            .collect();
+
+        diesel::delete(version_advisories::table)
+            .filter(version_advisories::advisory_id.eq(&self.id))
+            .execute(conn)?;
+        if !affected.is_empty() {
+            diesel::insert_into(version_advisories::table)
+                .values(&affected)
+                .execute(conn)?;
+        }
+        Ok(())
+    }
+}
+
+/// A version is affected if it matches one of the affected requirements, or
+/// there are none, unless it matches one of the patched or unaffected
+/// requirements. Requirements that fail to parse match nothing, so that a
+/// malformed patched requirement flags too many versions rather than too few.
+/// The requirements of advisories published by owners are validated.
+fn affects(
+    affected: &[String],
+    patched: &[String],
+    unaffected: &[String],
+    version: &semver::Version,
+) -> bool {
+    let matches = |req: &String| {
+        VersionReq::parse(req)
+            .map(|req| req.matches(version))
+            .unwrap_or(false)
+    };
+
+    (affected.is_empty() || affected.iter().any(matches))
+        && !patched.iter().chain(unaffected).any(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn advisory(patched: &[&str], unaffected: &[&str]) -> NewAdvisory {
+        NewAdvisory {
+            id: "RUSTSEC-2021-0001".into(),
+            crate_name: "foo".into(),
+            title: "Memory corruption in foo".into(),
+            url: None,
+            date: NaiveDate::from_ymd(2021, 1, 1),
+            patched: patched.iter().map(|req| req.to_string()).collect(),
+            unaffected: unaffected.iter().map(|req| req.to_string()).collect(),
+            informational: None,
+            withdrawn: false,
+            description: None,
+            severity: None,
+            affected: vec![],
+            published_by: None,
+        }
+    }
+
+    fn version(num: &str) -> semver::Version {
+        semver::Version::parse(num).unwrap()
+    }
+
+    #[test]
+    fn versions_without_fix_are_affected() {
+        let advisory = advisory(&[">= 1.2.1", "^1.1.5"], &["< 1.0.0"]);
+        assert!(advisory.affects(&version("1.0.0")));
+        assert!(advisory.affects(&version("1.1.4")));
+        assert!(advisory.affects(&version("1.2.0")));
+        assert!(!advisory.affects(&version("1.1.5")));
+        assert!(!advisory.affects(&version("1.2.1")));
+        assert!(!advisory.affects(&version("2.0.0")));
+        assert!(!advisory.affects(&version("0.9.0")));
+    }
+
+    #[test]
+    fn only_versions_in_affected_ranges_are_affected() {
+        let mut advisory = advisory(&[">= 1.2.1"], &[]);
+        advisory.affected = vec![">= 1.1.0, < 1.2.1".into(), "=0.9.3".into()];
+        assert!(advisory.affects(&version("1.1.0")));
+        assert!(advisory.affects(&version("1.2.0")));
+        assert!(advisory.affects(&version("0.9.3")));
+        assert!(!advisory.affects(&version("1.0.0")));
+        assert!(!advisory.affects(&version("0.9.4")));
+        assert!(!advisory.affects(&version("1.2.1")));
+    }
+
+    #[test]
+    fn withdrawn_advisories_affect_nothing() {
+        let mut advisory = advisory(&[], &[]);
+        assert!(advisory.affects(&version("1.0.0")));
+
+        advisory.withdrawn = true;
+        assert!(!advisory.affects(&version("1.0.0")));
+    }
+}
@@ -0,0 +1,138 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::schema::announcements;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum AnnouncementSeverity {
+    Info = 0,
+    /// Something users should act on or plan for, like a read-only window
+    Warning = 1,
+    /// An ongoing outage or incident
+    Critical = 2,
+}
+
+impl AnnouncementSeverity {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "info" => Some(AnnouncementSeverity::Info),
+            "warning" => Some(AnnouncementSeverity::Warning),
+            "critical" => Some(AnnouncementSeverity::Critical),
+            _ => None,
+        }
+    }
+}
+
+impl From<AnnouncementSeverity> for &'static str {
+    fn from(severity: AnnouncementSeverity) -> Self {
+        match severity {
+            AnnouncementSeverity::Info => "info",
+            AnnouncementSeverity::Warning => "warning",
+            AnnouncementSeverity::Critical => "critical",
+        }
+    }
+}
+
+impl From<AnnouncementSeverity> for String {
+    fn from(severity: AnnouncementSeverity) -> Self {
+        let string: &'static str = severity.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for AnnouncementSeverity {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(AnnouncementSeverity::Info),
+            1 => Ok(AnnouncementSeverity::Warning),
+            2 => Ok(AnnouncementSeverity::Critical),
+            n => Err(format!("unknown announcement severity: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for AnnouncementSeverity {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A registry-wide notice by the crates.io team, like a planned read-only
+/// window, that is shown from `starts_at` until `ends_at`, or until it is
+/// removed if it has no end.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Identifiable)]
+pub struct Announcement {
+    pub id: i32,
+    pub message: String,
+    /// A link to more details, like a blog post or the status page
+    pub url: Option<String>,
+    pub severity: AnnouncementSeverity,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: Option<NaiveDateTime>,
+    /// The admin that created the announcement
+    pub admin_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "announcements"]
+pub struct NewAnnouncement<'a> {
+    pub message: &'a str,
+    pub url: Option<&'a str>,
+    pub severity: AnnouncementSeverity,
+    pub starts_at: NaiveDateTime,
+    pub ends_at: Option<NaiveDateTime>,
+    pub admin_id: i32,
+}
+
+impl NewAnnouncement<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<Announcement> {
+        diesel::insert_into(announcements::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+impl Announcement {
+    pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+    /// All announcements, the latest first
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Announcement>> {
+        announcements::table
+            .order((announcements::starts_at.desc(), announcements::id.desc()))
+            .load(conn)
+    }
+
+    /// The announcements that are shown at the time, the most severe first
+    pub fn current(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<Vec<Announcement>> {
+        announcements::table
+            .filter(announcements::starts_at.le(now))
+            .filter(
+                announcements::ends_at
+                    .is_null()
+                    .or(announcements::ends_at.gt(now)),
+            )
+            .order((
+                announcements::severity.desc(),
+                announcements::starts_at.desc(),
+            ))
+            .load(conn)
+    }
+
+    /// Removes the announcement, returning `false` if there was none
+    pub fn delete(conn: &PgConnection, id: i32) -> QueryResult<bool> {
+        diesel::delete(announcements::table.find(id))
+            .execute(conn)
+            .map(|rows| rows > 0)
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::{
+    insert_version_owner_action, CrateDependencies, CrateDependents, Version, VersionAction,
+};
+use crate::schema::{bulk_yank_versions, bulk_yanks, crates, version_owner_actions, versions};
+
+/// An audited request by an administrator to yank every version published by
+/// a user, or by one of their API tokens, within a time window.
+///
+/// The yanking itself is done by the `BulkYank` background job, which
+/// sets `completed_at` once all matching versions have been yanked.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+pub struct BulkYank {
+    pub id: i32,
+    pub admin_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub since: NaiveDateTime,
+    pub until: NaiveDateTime,
+    pub reason: String,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub versions_yanked: Option<i32>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "bulk_yanks"]
+pub struct NewBulkYank<'a> {
+    pub admin_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    pub since: NaiveDateTime,
+    pub until: NaiveDateTime,
+    pub reason: &'a str,
+}
+
+impl NewBulkYank<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<BulkYank> {
+        diesel::insert_into(bulk_yanks::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+impl BulkYank {
+    pub fn find(conn: &PgConnection, id: i32) -> QueryResult<Self> {
+        bulk_yanks::table.find(id).first(conn)
+    }
+
+    /// Loads the versions that are not yanked yet, together with the name of
+    /// their crate.
+    ///
+    /// If the request is restricted to an API token, versions are matched
+    /// through the publish entries of the audit log, since `published_by` only
+    /// records the user.
+    pub fn pending_versions(&self, conn: &PgConnection) -> QueryResult<Vec<(Version, String)>> {
+        let mut query = versions::table
+            .inner_join(crates::table)
+            .filter(versions::yanked.eq(false))
+            .select((versions::all_columns, crates::name))
+            .order((crates::name, versions::id))
+            .into_boxed();
+
+        query = match self.api_token_id {
+            Some(api_token_id) => query.filter(
+                versions::id.eq_any(
+                    version_owner_actions::table
+                        .filter(version_owner_actions::api_token_id.eq(api_token_id))
+                        .filter(version_owner_actions::action.eq(VersionAction::Publish))
+                        .filter(version_owner_actions::time.between(self.since, self.until))
+                        .select(version_owner_actions::version_id),
+                ),
+            ),
+            None => query
+                .filter(versions::published_by.eq(self.user_id))
+                .filter(versions::created_at.between(self.since, self.until)),
+        };
+
+        query.load(conn)
+    }
+
+    /// Marks the versions as yanked in the database, recording the yank both
+    /// in the version audit log and as part of this bulk yank.
+    ///
+    /// This does not touch the index, which is the responsibility of the
+    /// caller.
+    pub fn yank_versions(&self, conn: &PgConnection, versions: &[Version]) -> QueryResult<()> {
+        let version_ids = versions.iter().map(|v| v.id).collect::<Vec<_>>();
+
+        let mut crate_ids = versions.iter().map(|v| v.crate_id).collect::<Vec<_>>();
+        crate_ids.sort_unstable();
+        crate_ids.dedup();
+        let dependencies = crate_ids
+            .into_iter()
+            .map(|crate_id| CrateDependencies::load(conn, crate_id))
+            .collect::<QueryResult<Vec<_>>>()?;
+
+        diesel::update(versions::table.filter(versions::id.eq_any(&version_ids)))
+            .set(versions::yanked.eq(true))
+            .execute(conn)?;
+
+        for dependencies in &dependencies {
+            CrateDependents::update_dependencies_of(conn, dependencies)?;
+        }
+
+        for version_id in &version_ids {
+            insert_version_owner_action(
+                conn,
+                *version_id,
+                self.admin_id,
+                None,
+                VersionAction::Yank,
+            )?;
+        }
+
+        let entries = version_ids
+            .iter()
+            .map(|version_id| {
+                (
+                    bulk_yank_versions::bulk_yank_id.eq(self.id),
+                    bulk_yank_versions::version_id.eq(*version_id),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(bulk_yank_versions::table)
+            .values(&entries)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        Ok(())
+    }
+
+    /// Loads the versions yanked by this request so far, together with the
+    /// name of their crate.
+    pub fn yanked_versions(&self, conn: &PgConnection) -> QueryResult<Vec<(Version, String)>> {
+        bulk_yank_versions::table
+            .filter(bulk_yank_versions::bulk_yank_id.eq(self.id))
+            .inner_join(versions::table.inner_join(crates::table))
+            .select((versions::all_columns, crates::name))
+            .order((crates::name, versions::id))
+            .load(conn)
+    }
+
+    /// Records that the job has finished, along with the total number of
+    /// versions yanked by this request.
+    pub fn complete(&self, conn: &PgConnection) -> QueryResult<Self> {
+        use diesel::dsl::{count_star, now};
+
+        let yanked: i64 = bulk_yank_versions::table
+            .filter(bulk_yank_versions::bulk_yank_id.eq(self.id))
+            .select(count_star())
+            .get_result(conn)?;
+
+        diesel::update(self)
+            .set((
+                bulk_yanks::completed_at.eq(now.nullable()),
+                bulk_yanks::versions_yanked.eq(yanked as i32),
+            ))
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,56 @@
+-- Recounts the crates and downloads of every category in each month from
+-- the month of $1 up to the current one.
+--
+-- Crates count towards the categories they are in now, since changes to
+-- the categories of a crate aren't recorded.
+INSERT INTO category_stats (category_id, month, crates_cnt, new_crates, downloads, updated_at)
+SELECT categories.id,
+    months.month,
+    -- Crates created before the end of the month
+    (
+        SELECT COUNT(*)
+        FROM crates_categories
+        INNER JOIN crates
+          ON crates.id = crates_categories.crate_id
+        WHERE crates_categories.category_id = categories.id
+          AND crates.created_at < months.month + interval '1 month'
+    ),
+    -- Crates created during the month
+    (
+        SELECT COUNT(*)
+        FROM crates_categories
+        INNER JOIN crates
+          ON crates.id = crates_categories.crate_id
+        WHERE crates_categories.category_id = categories.id
+          AND crates.created_at >= months.month
+          AND crates.created_at < months.month + interval '1 month'
+    ),
+    (
+        SELECT COALESCE(SUM(version_downloads.downloads), 0)
+        FROM version_downloads
+        INNER JOIN versions
+          ON versions.id = version_downloads.version_id
+        INNER JOIN crates_categories
+          ON crates_categories.crate_id = versions.crate_id
+        WHERE crates_categories.category_id = categories.id
+          AND version_downloads.date >= months.month
+          AND version_downloads.date < months.month + interval '1 month'
+    ),
+    CURRENT_TIMESTAMP
+FROM categories
+CROSS JOIN (
+    SELECT generate_series(
+        date_trunc('month', $1::date::timestamp),
+        date_trunc('month', CURRENT_DATE::timestamp),
+        interval '1 month'
+    )::date AS month
+) months
+ON CONFLICT (category_id, month) DO UPDATE
+SET crates_cnt = EXCLUDED.crates_cnt,
+    new_crates = EXCLUDED.new_crates,
+    downloads = EXCLUDED.downloads,
+    updated_at = EXCLUDED.updated_at
+-- Keep `updated_at` of unchanged months for incremental database dumps
+WHERE category_stats.crates_cnt <> EXCLUDED.crates_cnt
+   OR category_stats.new_crates <> EXCLUDED.new_crates
+   OR category_stats.downloads <> EXCLUDED.downloads
@@ -0,0 +1,61 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Integer, Nullable, Text, Timestamp};
+
+use crate::models::Crate;
+
+/// Something that happened to a crate, assembled from the tables recording
+/// publishes, yanks, README renderings, owners and advisories.
+///
+/// The kinds are `publish`, `yank`, `unyank`, `readme_rendered`,
+/// `owner_added`, `owner_removed` and `advisory`. Owners are only recorded
+/// with when they were added and, if they were removed since, when that
+/// happened, so an owner that was removed and added again shows up once.
+#[derive(Debug, Clone, QueryableByName)]
+pub struct CrateActivity {
+    #[sql_type = "Text"]
+    pub kind: String,
+    /// Unique within the activity of a crate
+    #[sql_type = "Text"]
+    pub id: String,
+    #[sql_type = "Timestamp"]
+    pub occurred_at: NaiveDateTime,
+    /// The version number of version events
+    #[sql_type = "Nullable<Text>"]
+    pub version: Option<String>,
+    /// The login of the user that did it, if known
+    #[sql_type = "Nullable<Text>"]
+    pub actor: Option<String>,
+    /// The owner of owner events, the advisory id of advisories and the
+    /// README file of renderings
+    #[sql_type = "Nullable<Text>"]
+    pub subject: Option<String>,
+    /// The title of advisories
+    #[sql_type = "Nullable<Text>"]
+    pub detail: Option<String>,
+}
+
+impl CrateActivity {
+    /// The activity of a crate newest first, after the `(occurred_at, id)`
+    /// of the last event of the previous page if given
+    pub fn for_crate(
+        conn: &PgConnection,
+        krate: &Crate,
+        after: Option<(NaiveDateTime, String)>,
+        limit: i64,
+        offset: i64,
+    ) -> QueryResult<Vec<Self>> {
+        let (after_time, after_id) = match after {
+            Some((time, id)) => (Some(time), Some(id)),
+            None => (None, None),
+        };
+        diesel::sql_query(include_str!("crate_activity.sql"))
+            .bind::<Integer, _>(krate.id)
+            .bind::<Text, _>(&krate.name)
+            .bind::<Nullable<Timestamp>, _>(after_time)
+            .bind::<Nullable<Text>, _>(after_id)
+            .bind::<BigInt, _>(limit)
+            .bind::<BigInt, _>(offset)
+            .load(conn)
+    }
+}
@@ -0,0 +1,46 @@
+SELECT activity.*, actors.gh_login AS actor
+FROM (
+    SELECT 'publish' AS kind, 'version:' || versions.id AS id, versions.created_at AS occurred_at,
+        versions.num AS version, versions.published_by AS actor_id,
+        NULL::varchar AS subject, NULL::text AS detail
+    FROM versions
+    WHERE versions.crate_id = $1::integer
+  UNION ALL
+    SELECT CASE version_owner_actions.action WHEN 1 THEN 'yank' ELSE 'unyank' END,
+        'action:' || version_owner_actions.id, version_owner_actions.time,
+        versions.num, version_owner_actions.user_id, NULL, NULL
+    FROM version_owner_actions
+    INNER JOIN versions ON versions.id = version_owner_actions.version_id
+    WHERE versions.crate_id = $1::integer AND version_owner_actions.action IN (1, 2)
+  UNION ALL
+    SELECT 'readme_rendered', 'readme:' || readme_renderings.version_id,
+        readme_renderings.rendered_at, versions.num, NULL, readme_renderings.source_file_name, NULL
+    FROM readme_renderings
+    INNER JOIN versions ON versions.id = readme_renderings.version_id
+    WHERE versions.crate_id = $1::integer
+  UNION ALL
+    SELECT 'owner_added', 'owner_added:' || crate_owners.owner_kind || ':' || crate_owners.owner_id,
+        crate_owners.created_at, NULL, crate_owners.created_by,
+        COALESCE(users.gh_login, teams.login), NULL
+    FROM crate_owners
+    LEFT JOIN users ON crate_owners.owner_kind = 0 AND users.id = crate_owners.owner_id
+    LEFT JOIN teams ON crate_owners.owner_kind = 1 AND teams.id = crate_owners.owner_id
+    WHERE crate_owners.crate_id = $1::integer
+  UNION ALL
+    SELECT 'owner_removed', 'owner_removed:' || crate_owners.owner_kind || ':' || crate_owners.owner_id,
+        crate_owners.updated_at, NULL, NULL,
+        COALESCE(users.gh_login, teams.login), NULL
+    FROM crate_owners
+    LEFT JOIN users ON crate_owners.owner_kind = 0 AND users.id = crate_owners.owner_id
+    LEFT JOIN teams ON crate_owners.owner_kind = 1 AND teams.id = crate_owners.owner_id
+    WHERE crate_owners.crate_id = $1::integer AND crate_owners.deleted
+  UNION ALL
+    SELECT 'advisory', 'advisory:' || advisories.id, advisories.created_at, NULL,
+        advisories.published_by, advisories.id, advisories.title
+    FROM advisories
+    WHERE canon_crate_name(advisories.crate_name) = canon_crate_name($2::text)
+) activity
+LEFT JOIN users actors ON actors.id = activity.actor_id
+WHERE $3::timestamp IS NULL OR (activity.occurred_at, activity.id) < ($3::timestamp, $4::text)
+ORDER BY activity.occurred_at DESC, activity.id DESC
+LIMIT $5::bigint OFFSET $6::bigint
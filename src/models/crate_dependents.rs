@@ -0,0 +1,146 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, Integer, Nullable};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::models::Crate;
+use crate::schema::{crate_dependents, dependencies, versions};
+
+/// How many crates depend on a crate, counted ahead of time because
+/// counting reverse dependencies on demand is expensive.
+///
+/// The counts of the crates a crate depends on are updated whenever one of
+/// its versions is published, yanked or unyanked, by the changes in its
+/// [`CrateDependencies`]. The `ReconcileDependents` background job recounts
+/// everything periodically to fix any drift.
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id)]
+#[table_name = "crate_dependents"]
+pub struct CrateDependents {
+    pub crate_id: i32,
+    /// Crates whose newest version depends on this crate
+    pub direct_dependents: i32,
+    /// Crates with any non-yanked version that depends on this crate
+    pub total_dependents: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+impl CrateDependents {
+    pub fn for_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<Self>> {
+        crate_dependents::table
+            .find(crate_id)
+            .first(conn)
+            .optional()
+    }
+
+    pub fn for_crates(conn: &PgConnection, crate_ids: &[i32]) -> QueryResult<HashMap<i32, Self>> {
+        use diesel::dsl::any;
+
+        let counts: Vec<Self> = crate_dependents::table
+            .filter(crate_dependents::crate_id.eq(any(crate_ids)))
+            .load(conn)?;
+        Ok(counts.into_iter().map(|c| (c.crate_id, c)).collect())
+    }
+
+    /// Updates the dependents of the crates that `crate_id` started or
+    /// stopped depending on since `before` was loaded, after one of its
+    /// versions was published or (un)yanked.
+    ///
+    /// Their counts are increased or decreased by one instead of recounted,
+    /// which would keep the rows of popular crates locked for the rest of
+    /// the transaction.
+    pub fn update_dependencies_of(
+        conn: &PgConnection,
+        before: &CrateDependencies,
+    ) -> QueryResult<()> {
+        let after = CrateDependencies::load(conn, before.crate_id)?;
+
+        let mut changes = BTreeMap::<i32, (i32, i32)>::new();
+        for id in after.direct.difference(&before.direct) {
+            changes.entry(*id).or_default().0 += 1;
+        }
+        for id in before.direct.difference(&after.direct) {
+            changes.entry(*id).or_default().0 -= 1;
+        }
+        for id in after.total.difference(&before.total) {
+            changes.entry(*id).or_default().1 += 1;
+        }
+        for id in before.total.difference(&after.total) {
+            changes.entry(*id).or_default().1 -= 1;
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let (crate_ids, changes): (Vec<i32>, Vec<(i32, i32)>) = changes.into_iter().unzip();
+        let (direct, total): (Vec<i32>, Vec<i32>) = changes.into_iter().unzip();
+        diesel::sql_query(include_str!("crate_dependents_changes.sql"))
+            .bind::<Array<Integer>, _>(crate_ids)
+            .bind::<Array<Integer>, _>(direct)
+            .bind::<Array<Integer>, _>(total)
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Recounts the dependents of all crates
+    pub fn reconcile(conn: &PgConnection) -> QueryResult<()> {
+        diesel::sql_query(include_str!("crate_dependents.sql"))
+            .bind::<Nullable<Array<Integer>>, _>(None::<Vec<i32>>)
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+/// The crates the versions of a crate depend on, as counted by
+/// [`CrateDependents`]. Loaded before and after its versions change, to
+/// update the counts of the crates it started or stopped depending on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CrateDependencies {
+    crate_id: i32,
+    /// Dependencies of the newest non-yanked version
+    direct: HashSet<i32>,
+    /// Dependencies of any non-yanked version
+    total: HashSet<i32>,
+}
+
+impl CrateDependencies {
+    pub fn load(conn: &PgConnection, crate_id: i32) -> QueryResult<Self> {
+        let total = dependencies::table
+            .inner_join(versions::table)
+            .filter(versions::crate_id.eq(crate_id))
+            .filter(versions::yanked.eq(false))
+            .select(dependencies::crate_id)
+            .distinct()
+            .load(conn)?
+            .into_iter()
+            .collect();
+
+        // Ordered like the newest versions in `crate_dependents.sql`
+        let newest_version: Option<i32> = versions::table
+            .filter(versions::crate_id.eq(crate_id))
+            .filter(versions::yanked.eq(false))
+            .select(versions::id)
+            .order(sql::<Integer>(
+                "to_semver_no_prerelease(num) DESC NULLS LAST, id DESC",
+            ))
+            .first(conn)
+            .optional()?;
+        let direct = match newest_version {
+            Some(version_id) => dependencies::table
+                .filter(dependencies::version_id.eq(version_id))
+                .select(dependencies::crate_id)
+                .load(conn)?
+                .into_iter()
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        Ok(Self {
+            crate_id,
+            direct,
+            total,
+        })
+    }
+}
@@ -0,0 +1,47 @@
+-- Recounts the dependents of the crates in $1, or of all crates if $1 is NULL
+INSERT INTO crate_dependents (crate_id, direct_dependents, total_dependents, updated_at)
+SELECT crates.id,
+    -- Crates whose newest version depends on the crate, like the
+    -- `reverse_dependencies` endpoint
+    (
+        SELECT COUNT(DISTINCT versions.crate_id)
+        FROM dependencies
+        INNER JOIN (
+            SELECT versions.id, versions.crate_id,
+            row_number() OVER (
+                PARTITION BY crate_id
+                ORDER BY to_semver_no_prerelease(num) DESC NULLS LAST
+            ) rn
+            FROM versions
+            WHERE NOT yanked
+            AND crate_id = ANY(
+                SELECT versions.crate_id
+                FROM versions
+                INNER JOIN dependencies
+                ON dependencies.version_id = versions.id
+                WHERE dependencies.crate_id = crates.id
+            )
+        ) versions
+          ON versions.id = dependencies.version_id
+        WHERE dependencies.crate_id = crates.id
+          AND rn = 1
+    ),
+    -- Crates with any version that depends on the crate
+    (
+        SELECT COUNT(DISTINCT versions.crate_id)
+        FROM dependencies
+        INNER JOIN versions
+          ON versions.id = dependencies.version_id
+        WHERE dependencies.crate_id = crates.id
+          AND NOT versions.yanked
+    ),
+    CURRENT_TIMESTAMP
+FROM crates
+WHERE $1::integer[] IS NULL OR crates.id = ANY($1)
+ON CONFLICT (crate_id) DO UPDATE
+SET direct_dependents = EXCLUDED.direct_dependents,
+    total_dependents = EXCLUDED.total_dependents,
+    updated_at = EXCLUDED.updated_at
+-- Keep `updated_at` of unchanged counts for incremental database dumps
+WHERE crate_dependents.direct_dependents <> EXCLUDED.direct_dependents
+   OR crate_dependents.total_dependents <> EXCLUDED.total_dependents
@@ -0,0 +1,18 @@
+-- Adds the changes in $2 and $3 to the direct and total dependents of the
+-- crates in $1. Crates without counts yet start at 0.
+WITH changes (crate_id, direct, total) AS (
+    SELECT * FROM unnest($1::integer[], $2::integer[], $3::integer[])
+), inserted AS (
+    INSERT INTO crate_dependents (crate_id, direct_dependents, total_dependents, updated_at)
+    SELECT crate_id, GREATEST(direct, 0), GREATEST(total, 0), CURRENT_TIMESTAMP
+    FROM changes
+    ORDER BY crate_id
+    ON CONFLICT (crate_id) DO NOTHING
+)
+-- Only sees the rows that existed before the insert above
+UPDATE crate_dependents
+SET direct_dependents = GREATEST(crate_dependents.direct_dependents + changes.direct, 0),
+    total_dependents = GREATEST(crate_dependents.total_dependents + changes.total, 0),
+    updated_at = CURRENT_TIMESTAMP
+FROM changes
+WHERE crate_dependents.crate_id = changes.crate_id
@@ -0,0 +1,171 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use std::collections::HashMap;
+
+use crate::models::User;
+use crate::schema::{crate_list_entries, crate_lists, crates, recent_crate_downloads};
+
+/// A public list of crates curated by a user, like "awesome embedded"
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+pub struct CrateList {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// Also changed when crates are added to or removed from the list
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Insertable)]
+#[table_name = "crate_lists"]
+pub struct NewCrateList<'a> {
+    pub user_id: i32,
+    pub name: &'a str,
+    pub description: Option<&'a str>,
+}
+
+/// A crate of a list, with the note of the curator
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(CrateList)]
+#[table_name = "crate_list_entries"]
+#[primary_key(crate_list_id, crate_id)]
+pub struct CrateListEntry {
+    pub crate_list_id: i32,
+    pub crate_id: i32,
+    pub note: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+/// A crate of a list with the current metadata of the crate
+#[derive(Debug, Clone, Queryable)]
+pub struct CrateListItem {
+    pub name: String,
+    pub description: Option<String>,
+    pub downloads: i32,
+    pub recent_downloads: Option<i64>,
+    pub note: Option<String>,
+    pub added_at: NaiveDateTime,
+}
+
+impl CrateList {
+    /// How many lists a user can have
+    pub const MAX_PER_USER: i64 = 100;
+
+    /// How many crates a list can have
+    pub const MAX_ENTRIES: i64 = 500;
+
+    pub const MAX_NAME_LENGTH: usize = 64;
+    pub const MAX_DESCRIPTION_LENGTH: usize = 1000;
+    pub const MAX_NOTE_LENGTH: usize = 500;
+
+    pub fn find(conn: &PgConnection, id: i32) -> QueryResult<Self> {
+        crate_lists::table.find(id).first(conn)
+    }
+
+    pub fn count_for_user(conn: &PgConnection, user_id: i32) -> QueryResult<i64> {
+        crate_lists::table
+            .filter(crate_lists::user_id.eq(user_id))
+            .count()
+            .get_result(conn)
+    }
+
+    /// The number of crates of each of the given lists
+    pub fn entry_counts(conn: &PgConnection, list_ids: &[i32]) -> QueryResult<HashMap<i32, i64>> {
+        use diesel::dsl::any;
+
+        let list_ids: Vec<i32> = crate_list_entries::table
+            .filter(crate_list_entries::crate_list_id.eq(any(list_ids)))
+            .select(crate_list_entries::crate_list_id)
+            .load(conn)?;
+
+        let mut counts = HashMap::new();
+        for list_id in list_ids {
+            *counts.entry(list_id).or_insert(0) += 1;
+        }
+        Ok(counts)
+    }
+
+    /// The crates of the list, in the order they were added
+    pub fn items(&self, conn: &PgConnection) -> QueryResult<Vec<CrateListItem>> {
+        crate_list_entries::table
+            .filter(crate_list_entries::crate_list_id.eq(self.id))
+            .inner_join(crates::table.left_join(recent_crate_downloads::table))
+            .select((
+                crates::name,
+                crates::description,
+                crates::downloads,
+                recent_crate_downloads::downloads.nullable(),
+                crate_list_entries::note,
+                crate_list_entries::created_at,
+            ))
+            .order(crate_list_entries::created_at)
+            .load(conn)
+    }
+
+    pub fn entry_count(&self, conn: &PgConnection) -> QueryResult<i64> {
+        CrateListEntry::belonging_to(self).count().get_result(conn)
+    }
+
+    /// Adds a crate to the list, or replaces the note of a crate already on
+    /// the list
+    pub fn add_crate(
+        &self,
+        conn: &PgConnection,
+        crate_id: i32,
+        note: Option<&str>,
+    ) -> QueryResult<()> {
+        use diesel::pg::upsert::excluded;
+
+        conn.transaction(|| {
+            diesel::insert_into(crate_list_entries::table)
+                .values((
+                    crate_list_entries::crate_list_id.eq(self.id),
+                    crate_list_entries::crate_id.eq(crate_id),
+                    crate_list_entries::note.eq(note),
+                ))
+                .on_conflict((
+                    crate_list_entries::crate_list_id,
+                    crate_list_entries::crate_id,
+                ))
+                .do_update()
+                .set(crate_list_entries::note.eq(excluded(crate_list_entries::note)))
+                .execute(conn)?;
+            self.touch(conn)
+        })
+    }
+
+    /// Removes a crate from the list, returning whether it was on the list
+    pub fn remove_crate(&self, conn: &PgConnection, crate_id: i32) -> QueryResult<bool> {
+        conn.transaction(|| {
+            let removed = diesel::delete(
+                crate_list_entries::table
+                    .filter(crate_list_entries::crate_list_id.eq(self.id))
+                    .filter(crate_list_entries::crate_id.eq(crate_id)),
+            )
+            .execute(conn)?;
+            if removed > 0 {
+                self.touch(conn)?;
+            }
+            Ok(removed > 0)
+        })
+    }
+
+    fn touch(&self, conn: &PgConnection) -> QueryResult<()> {
+        use diesel::dsl::now;
+
+        diesel::update(self)
+            .set(crate_lists::updated_at.eq(now))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+impl<'a> NewCrateList<'a> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<CrateList> {
+        diesel::insert_into(crate_lists::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
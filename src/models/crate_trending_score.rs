@@ -0,0 +1,63 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Double, Integer};
+
+use crate::models::Crate;
+use crate::schema::crate_trending_scores;
+
+/// How many days of downloads and new dependents count as recent
+const RECENT_DAYS: i32 = 7;
+
+/// How many days before the recent ones the downloads are compared to
+const BASELINE_DAYS: i32 = 28;
+
+/// Crates with fewer recent downloads aren't scored, so that a handful of
+/// downloads of a new crate doesn't make it trend
+const MIN_RECENT_DOWNLOADS: i64 = 100;
+
+/// Added to the expected downloads before taking the square root, so that
+/// crates without baseline downloads don't divide by zero
+const SMOOTHING: f64 = 10.0;
+
+/// How much every crate that started depending on a crate adds to its score
+const NEW_DEPENDENT_WEIGHT: f64 = 5.0;
+
+/// How much a crate is trending, computed daily by the
+/// `ComputeTrendingScores` background job.
+///
+/// Crates trend when their downloads grow relative to their baseline, and
+/// when other crates start depending on them. Only crates with a positive
+/// score are stored.
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id)]
+#[table_name = "crate_trending_scores"]
+pub struct CrateTrendingScore {
+    pub crate_id: i32,
+    pub score: f64,
+    /// Downloads in the last [`RECENT_DAYS`] days
+    pub recent_downloads: i64,
+    /// Downloads in the [`BASELINE_DAYS`] days before
+    pub baseline_downloads: i64,
+    /// Crates that started depending on this crate in the last
+    /// [`RECENT_DAYS`] days
+    pub new_dependents: i32,
+    pub computed_at: NaiveDateTime,
+}
+
+impl CrateTrendingScore {
+    /// Replaces all scores with ones computed from the current downloads and
+    /// dependencies, returning how many crates are trending
+    pub fn recompute(conn: &PgConnection) -> QueryResult<usize> {
+        conn.transaction(|| {
+            diesel::delete(crate_trending_scores::table).execute(conn)?;
+            diesel::sql_query(include_str!("crate_trending_score.sql"))
+                .bind::<Integer, _>(RECENT_DAYS)
+                .bind::<Integer, _>(BASELINE_DAYS)
+                .bind::<BigInt, _>(MIN_RECENT_DOWNLOADS)
+                .bind::<Double, _>(SMOOTHING)
+                .bind::<Double, _>(NEW_DEPENDENT_WEIGHT)
+                .execute(conn)
+        })
+    }
+}
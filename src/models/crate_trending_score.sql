@@ -0,0 +1,59 @@
+-- Scores how much more each crate was downloaded in the last $1 days than
+-- expected from the $2 days before, plus $5 for every crate that started
+-- depending on it in the last $1 days.
+--
+-- The growth is divided by the square root of the expected downloads, so
+-- that small crates doubling their downloads don't outrank large crates
+-- gaining many more downloads. $4 keeps crates without baseline downloads
+-- from dividing by zero. Only crates with at least $3 recent downloads and
+-- a positive score are inserted.
+INSERT INTO crate_trending_scores (
+    crate_id, score, recent_downloads, baseline_downloads, new_dependents, computed_at
+)
+SELECT crate_id, score, recent_downloads, baseline_downloads, new_dependents, CURRENT_TIMESTAMP
+FROM (
+    SELECT downloads.crate_id,
+        downloads.recent_downloads,
+        downloads.baseline_downloads,
+        COALESCE(new_dependents.count, 0) AS new_dependents,
+        (downloads.recent_downloads - downloads.expected_downloads)
+            / sqrt(downloads.expected_downloads + $4::float8)
+            + $5::float8 * COALESCE(new_dependents.count, 0) AS score
+    FROM (
+        SELECT crate_id,
+            recent_downloads,
+            baseline_downloads,
+            baseline_downloads * $1::integer / $2::integer::float8 AS expected_downloads
+        FROM (
+            SELECT versions.crate_id,
+                SUM(CASE WHEN version_downloads.date > CURRENT_DATE - $1::integer
+                    THEN version_downloads.downloads ELSE 0 END) AS recent_downloads,
+                SUM(CASE WHEN version_downloads.date <= CURRENT_DATE - $1::integer
+                    THEN version_downloads.downloads ELSE 0 END) AS baseline_downloads
+            FROM version_downloads
+            INNER JOIN versions
+              ON versions.id = version_downloads.version_id
+            WHERE version_downloads.date > CURRENT_DATE - ($1::integer + $2::integer)
+            GROUP BY versions.crate_id
+        ) sums
+    ) downloads
+    LEFT JOIN (
+        -- Crates whose first non-yanked version depending on the crate was
+        -- published recently
+        SELECT dependency_id, COUNT(*) AS count
+        FROM (
+            SELECT dependencies.crate_id AS dependency_id
+            FROM dependencies
+            INNER JOIN versions
+              ON versions.id = dependencies.version_id
+            WHERE NOT versions.yanked
+              AND versions.crate_id <> dependencies.crate_id
+            GROUP BY dependencies.crate_id, versions.crate_id
+            HAVING MIN(versions.created_at) > CURRENT_TIMESTAMP - make_interval(days => $1::integer)
+        ) first_dependencies
+        GROUP BY dependency_id
+    ) new_dependents
+      ON new_dependents.dependency_id = downloads.crate_id
+    WHERE downloads.recent_downloads >= $3::bigint
+) scores
+WHERE score > 0
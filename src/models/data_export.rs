@@ -0,0 +1,98 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::data_exports;
+
+/// A request by a user, or by an admin on their behalf, for an archive of all
+/// the data crates.io stores about their account.
+///
+/// The archive itself is built by the `ExportUserData` background
+/// job and is only loaded when it is downloaded.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+pub struct DataExport {
+    pub id: i32,
+    pub user_id: i32,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+}
+
+type AllColumns = (
+    data_exports::id,
+    data_exports::user_id,
+    data_exports::created_at,
+    data_exports::completed_at,
+);
+
+const ALL_COLUMNS: AllColumns = (
+    data_exports::id,
+    data_exports::user_id,
+    data_exports::created_at,
+    data_exports::completed_at,
+);
+
+impl DataExport {
+    /// Requests a new export for the user.
+    ///
+    /// If an export is still being built it is returned instead. Otherwise any
+    /// previous exports are removed, so that at most one archive is stored per
+    /// user.
+    pub fn request(conn: &PgConnection, user_id: i32) -> QueryResult<(Self, bool)> {
+        conn.transaction(|| {
+            let pending = data_exports::table
+                .select(ALL_COLUMNS)
+                .filter(data_exports::user_id.eq(user_id))
+                .filter(data_exports::completed_at.is_null())
+                .first(conn)
+                .optional()?;
+            if let Some(pending) = pending {
+                return Ok((pending, false));
+            }
+
+            diesel::delete(data_exports::table.filter(data_exports::user_id.eq(user_id)))
+                .execute(conn)?;
+
+            let export = diesel::insert_into(data_exports::table)
+                .values(data_exports::user_id.eq(user_id))
+                .returning(ALL_COLUMNS)
+                .get_result(conn)?;
+            Ok((export, true))
+        })
+    }
+
+    pub fn find(conn: &PgConnection, id: i32) -> QueryResult<Self> {
+        data_exports::table.find(id).select(ALL_COLUMNS).first(conn)
+    }
+
+    /// Returns the most recently requested export of the user, if any.
+    pub fn latest_for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Option<Self>> {
+        data_exports::table
+            .select(ALL_COLUMNS)
+            .filter(data_exports::user_id.eq(user_id))
+            .order(data_exports::id.desc())
+            .first(conn)
+            .optional()
+    }
+
+    /// Stores the finished archive.
+    pub fn complete(&self, conn: &PgConnection, archive: &[u8]) -> QueryResult<Self> {
+        use diesel::dsl::now;
+
+        diesel::update(self)
+            .set((
+                data_exports::completed_at.eq(now.nullable()),
+                data_exports::archive.eq(archive),
+            ))
+            .returning(ALL_COLUMNS)
+            .get_result(conn)
+    }
+
+    /// Loads the archive, which is `None` until the export has completed.
+    pub fn archive(&self, conn: &PgConnection) -> QueryResult<Option<Vec<u8>>> {
+        data_exports::table
+            .find(self.id)
+            .select(data_exports::archive)
+            .first(conn)
+    }
+}
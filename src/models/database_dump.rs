@@ -0,0 +1,79 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::database_dumps;
+
+/// A public database dump that was uploaded by the `DumpDb`,
+/// `DumpDbIncremental` or `DumpDownloadHistory` background job.
+///
+/// An incremental dump contains the rows that changed after `since`, which
+/// is the time of the previous full or incremental dump. The download history
+/// is uploaded separately and isn't part of that chain.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+pub struct DatabaseDump {
+    pub id: i32,
+    pub target_name: String,
+    pub incremental: bool,
+    pub since: Option<NaiveDateTime>,
+    pub dumped_at: NaiveDateTime,
+    pub size: i64,
+    pub download_history: bool,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "database_dumps"]
+pub struct NewDatabaseDump<'a> {
+    pub target_name: &'a str,
+    pub incremental: bool,
+    pub since: Option<NaiveDateTime>,
+    pub dumped_at: NaiveDateTime,
+    pub size: i64,
+    pub download_history: bool,
+}
+
+impl NewDatabaseDump<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<DatabaseDump> {
+        diesel::insert_into(database_dumps::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+impl DatabaseDump {
+    /// The most recent full or incremental dump
+    pub fn latest(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        database_dumps::table
+            .filter(database_dumps::download_history.eq(false))
+            .order(database_dumps::dumped_at.desc())
+            .first(conn)
+            .optional()
+    }
+
+    /// The most recent full dump
+    pub fn latest_full(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        database_dumps::table
+            .filter(database_dumps::incremental.eq(false))
+            .filter(database_dumps::download_history.eq(false))
+            .order(database_dumps::dumped_at.desc())
+            .first(conn)
+            .optional()
+    }
+
+    /// The most recent dump of the download history
+    pub fn latest_download_history(conn: &PgConnection) -> QueryResult<Option<Self>> {
+        database_dumps::table
+            .filter(database_dumps::download_history.eq(true))
+            .order(database_dumps::dumped_at.desc())
+            .first(conn)
+            .optional()
+    }
+
+    /// The incremental dumps created after `time`, oldest first
+    pub fn incremental_since(conn: &PgConnection, time: NaiveDateTime) -> QueryResult<Vec<Self>> {
+        database_dumps::table
+            .filter(database_dumps::incremental.eq(true))
+            .filter(database_dumps::dumped_at.gt(time))
+            .order(database_dumps::dumped_at)
+            .load(conn)
+    }
+}
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
 use diesel::deserialize::{self, FromSql};
 use diesel::pg::Pg;
+use diesel::prelude::*;
 use diesel::sql_types::Integer;
+use semver::VersionReq;
 
 use crate::models::{Crate, Version};
 use crate::schema::*;
@@ -52,3 +56,45 @@ impl FromSql<Integer, Pg> for DependencyKind {
         }
     }
 }
+
+/// Resolves dependencies, given as the crate ID and version requirement, to
+/// the highest version of the crate that matches the requirement and isn't
+/// yanked, like in a new lockfile.
+///
+/// Returns the version ID for every dependency in the same order, or `None`
+/// if no version matches.
+pub fn resolve_dependencies(
+    conn: &PgConnection,
+    deps: &[(i32, String)],
+) -> QueryResult<Vec<Option<i32>>> {
+    use diesel::dsl::any;
+
+    let crate_ids: Vec<i32> = deps.iter().map(|(crate_id, _)| *crate_id).collect();
+    let candidates: Vec<(i32, i32, String)> = versions::table
+        .filter(versions::crate_id.eq(any(&crate_ids[..])))
+        .filter(versions::yanked.eq(false))
+        .select((versions::id, versions::crate_id, versions::num))
+        .load(conn)?;
+    let mut candidates_by_crate: HashMap<i32, Vec<(i32, semver::Version)>> = HashMap::new();
+    for (version_id, crate_id, num) in candidates {
+        if let Ok(num) = semver::Version::parse(&num) {
+            candidates_by_crate
+                .entry(crate_id)
+                .or_default()
+                .push((version_id, num));
+        }
+    }
+
+    Ok(deps
+        .iter()
+        .map(|(crate_id, requirement)| {
+            let requirement = VersionReq::parse(requirement).ok()?;
+            candidates_by_crate
+                .get(crate_id)?
+                .iter()
+                .filter(|(_, num)| requirement.matches(num))
+                .max_by(|(_, a), (_, b)| a.cmp(b))
+                .map(|(version_id, _)| *version_id)
+        })
+        .collect())
+}
@@ -1,7 +1,8 @@
-use chrono::NaiveDateTime;
+use chrono::{Duration, NaiveDateTime};
+use diesel::prelude::*;
 
-use crate::models::User;
-use crate::schema::emails;
+use crate::models::{User, UserSession};
+use crate::schema::{email_changes, emails};
 
 #[derive(Debug, Queryable, AsChangeset, Identifiable, Associations)]
 #[belongs_to(User)]
@@ -14,9 +15,133 @@ pub struct Email {
     pub token_generated_at: Option<NaiveDateTime>,
 }
 
+impl Email {
+    /// How long the token sent to an address can be used to confirm it
+    pub const TOKEN_MAX_AGE_HOURS: i64 = 48;
+
+    /// How long a user has to wait before another confirmation email is sent
+    pub const RESEND_INTERVAL_MINUTES: i64 = 5;
+
+    /// Whether the token can no longer be used to confirm the address. Tokens
+    /// of users created before tokens were timestamped count as expired.
+    pub fn token_expired(&self, now: NaiveDateTime) -> bool {
+        self.token_generated_at.map_or(true, |generated_at| {
+            generated_at < now - Duration::hours(Self::TOKEN_MAX_AGE_HOURS)
+        })
+    }
+
+    /// When another confirmation email can be sent, or `None` if one can be
+    /// sent right away
+    pub fn next_resend_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        let next = self.token_generated_at? + Duration::minutes(Self::RESEND_INTERVAL_MINUTES);
+        Some(next).filter(|next| *next > now)
+    }
+}
+
 #[derive(Debug, Insertable, AsChangeset)]
 #[table_name = "emails"]
 pub struct NewEmail<'a> {
     pub user_id: i32,
     pub email: &'a str,
 }
+
+/// A requested change of a verified email address.
+///
+/// The old address stays in use until the change has been confirmed with the
+/// tokens sent to both the old and the new address, so a stolen session or
+/// a single leaked email can't take over the account.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[primary_key(user_id)]
+#[belongs_to(User)]
+pub struct EmailChange {
+    pub user_id: i32,
+    pub email: String,
+    pub old_email_token: String,
+    pub new_email_token: String,
+    pub old_email_confirmed: bool,
+    pub new_email_confirmed: bool,
+    pub created_at: NaiveDateTime,
+}
+
+impl EmailChange {
+    /// Starts changing the address of the user to `email`, replacing any
+    /// change that is still pending
+    pub fn request(conn: &PgConnection, user_id: i32, email: &str) -> QueryResult<Self> {
+        diesel::delete(email_changes::table.find(user_id)).execute(conn)?;
+        diesel::insert_into(email_changes::table)
+            .values((
+                email_changes::user_id.eq(user_id),
+                email_changes::email.eq(email),
+            ))
+            .get_result(conn)
+    }
+
+    pub fn pending_for(conn: &PgConnection, user_id: i32) -> QueryResult<Option<Self>> {
+        email_changes::table.find(user_id).first(conn).optional()
+    }
+
+    pub fn find_by_token(conn: &PgConnection, token: &str) -> QueryResult<Option<Self>> {
+        email_changes::table
+            .filter(
+                email_changes::old_email_token
+                    .eq(token)
+                    .or(email_changes::new_email_token.eq(token)),
+            )
+            .first(conn)
+            .optional()
+    }
+
+    pub fn cancel(conn: &PgConnection, user_id: i32) -> QueryResult<()> {
+        diesel::delete(email_changes::table.find(user_id)).execute(conn)?;
+        Ok(())
+    }
+
+    pub fn expired(&self, now: NaiveDateTime) -> bool {
+        self.created_at < now - Duration::hours(Email::TOKEN_MAX_AGE_HOURS)
+    }
+
+    /// When the change can be requested again, or `None` if it can be
+    /// requested right away
+    pub fn next_request_at(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        Some(self.created_at + Duration::minutes(Email::RESEND_INTERVAL_MINUTES))
+            .filter(|next| *next > now)
+    }
+
+    /// Records the confirmation of the address that `token` was sent to.
+    /// Once both addresses have been confirmed, the new address replaces the
+    /// old one as the verified address of the user and `true` is returned.
+    ///
+    /// Like other changes to the account's security, this logs the user out
+    /// of all browsers.
+    pub fn confirm(mut self, conn: &PgConnection, token: &str) -> QueryResult<bool> {
+        if token == self.old_email_token {
+            self.old_email_confirmed = true;
+        } else if token == self.new_email_token {
+            self.new_email_confirmed = true;
+        }
+
+        if !(self.old_email_confirmed && self.new_email_confirmed) {
+            diesel::update(email_changes::table.find(self.user_id))
+                .set((
+                    email_changes::old_email_confirmed.eq(self.old_email_confirmed),
+                    email_changes::new_email_confirmed.eq(self.new_email_confirmed),
+                ))
+                .execute(conn)?;
+            return Ok(false);
+        }
+
+        // Changing the address makes the `trigger_emails_reconfirm` trigger
+        // generate a new token and reset `verified`, so the address is
+        // verified separately.
+        let user_email = emails::table.filter(emails::user_id.eq(self.user_id));
+        diesel::update(user_email.clone())
+            .set(emails::email.eq(&self.email))
+            .execute(conn)?;
+        diesel::update(user_email)
+            .set(emails::verified.eq(true))
+            .execute(conn)?;
+        Self::cancel(conn, self.user_id)?;
+        UserSession::revoke_all(conn, self.user_id)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::email_preferences;
+
+/// The kinds of notification emails, which users can turn off one by one.
+///
+/// Emails a user asked for, like the confirmation of their address, are
+/// always sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmailEvent {
+    /// Someone invited the user to become an owner of a crate
+    OwnerInvitations,
+    /// A new version of a crate owned by the user was published
+    NewVersions,
+    /// Versions of a crate owned by the user were yanked because they were
+    /// published with compromised credentials
+    SecurityNotices,
+    /// The crates.io team took action on the user's account
+    Moderation,
+    /// The weekly summary of what happened to the crates the user follows,
+    /// which users have to opt into
+    WeeklyDigest,
+}
+
+/// The notification emails a user wants to receive. Users without a row
+/// receive all of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Queryable, Identifiable, Insertable, AsChangeset)]
+#[primary_key(user_id)]
+#[table_name = "email_preferences"]
+pub struct EmailPreferences {
+    pub user_id: i32,
+    pub owner_invitations: bool,
+    pub new_versions: bool,
+    pub security_notices: bool,
+    pub moderation: bool,
+    pub weekly_digest: bool,
+    /// When the last weekly digest was sent, which is kept when the
+    /// preferences are saved
+    pub weekly_digest_sent_at: Option<NaiveDateTime>,
+}
+
+impl EmailPreferences {
+    pub fn default_for(user_id: i32) -> Self {
+        Self {
+            user_id,
+            owner_invitations: true,
+            new_versions: true,
+            security_notices: true,
+            moderation: true,
+            weekly_digest: false,
+            weekly_digest_sent_at: None,
+        }
+    }
+
+    pub fn for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Self> {
+        let preferences = email_preferences::table
+            .find(user_id)
+            .first(conn)
+            .optional()?;
+        Ok(preferences.unwrap_or_else(|| Self::default_for(user_id)))
+    }
+
+    pub fn allows(&self, event: EmailEvent) -> bool {
+        match event {
+            EmailEvent::OwnerInvitations => self.owner_invitations,
+            EmailEvent::NewVersions => self.new_versions,
+            EmailEvent::SecurityNotices => self.security_notices,
+            EmailEvent::Moderation => self.moderation,
+            EmailEvent::WeeklyDigest => self.weekly_digest,
+        }
+    }
+
+    pub fn save(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(email_preferences::table)
+            .values(self)
+            .on_conflict(email_preferences::user_id)
+            .do_update()
+            .set(self)
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+impl User {
+    /// The verified email address to send a notification to, or `None` if
+    /// there is none or the user turned off this kind of notification
+    pub fn notification_email(
+        &self,
+        conn: &PgConnection,
+        event: EmailEvent,
+    ) -> QueryResult<Option<String>> {
+        if !EmailPreferences::for_user(conn, self.id)?.allows(event) {
+            return Ok(None);
+        }
+        self.verified_email(conn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_allowed_by_default() {
+        let preferences = EmailPreferences::default_for(1);
+        assert!(preferences.allows(EmailEvent::OwnerInvitations));
+        assert!(preferences.allows(EmailEvent::NewVersions));
+        assert!(preferences.allows(EmailEvent::SecurityNotices));
+        assert!(preferences.allows(EmailEvent::Moderation));
+        assert!(!preferences.allows(EmailEvent::WeeklyDigest));
+
+        let preferences = EmailPreferences {
+            new_versions: false,
+            ..preferences
+        };
+        assert!(!preferences.allows(EmailEvent::NewVersions));
+        assert!(preferences.allows(EmailEvent::Moderation));
+    }
+}
@@ -0,0 +1,86 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+
+use crate::models::Crate;
+use crate::schema::{crates, featured_crates};
+
+/// A crate that the crates.io team features on the front page from
+/// `starts_on` until `ends_on`, both inclusive, like the crate of the week.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+pub struct FeaturedCrate {
+    pub id: i32,
+    pub crate_id: i32,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    /// Why the crate is featured
+    pub description: Option<String>,
+    /// The admin that scheduled the crate
+    pub admin_id: i32,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "featured_crates"]
+pub struct NewFeaturedCrate<'a> {
+    pub crate_id: i32,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    pub description: Option<&'a str>,
+    pub admin_id: i32,
+}
+
+impl NewFeaturedCrate<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<FeaturedCrate> {
+        diesel::insert_into(featured_crates::table)
+            .values(self)
+            .get_result(conn)
+    }
+
+    /// Whether the crate is already featured on one of the days
+    pub fn overlaps(&self, conn: &PgConnection) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            featured_crates::table
+                .filter(featured_crates::crate_id.eq(self.crate_id))
+                .filter(featured_crates::starts_on.le(self.ends_on))
+                .filter(featured_crates::ends_on.ge(self.starts_on)),
+        ))
+        .get_result(conn)
+    }
+}
+
+impl FeaturedCrate {
+    /// How many days a crate is featured if no end is given
+    pub const DEFAULT_DAYS: i64 = 7;
+
+    pub const MAX_DESCRIPTION_LENGTH: usize = 500;
+
+    /// All scheduled crates with their names, the ones featured last first
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<(FeaturedCrate, String)>> {
+        featured_crates::table
+            .inner_join(crates::table)
+            .select((featured_crates::all_columns, crates::name))
+            .order((
+                featured_crates::starts_on.desc(),
+                featured_crates::id.desc(),
+            ))
+            .load(conn)
+    }
+
+    /// Removes the scheduled crate, returning its name if it existed
+    pub fn delete(conn: &PgConnection, id: i32) -> QueryResult<Option<String>> {
+        let deleted: Option<FeaturedCrate> = diesel::delete(featured_crates::table.find(id))
+            .get_result(conn)
+            .optional()?;
+        match deleted {
+            Some(featured) => crates::table
+                .find(featured.crate_id)
+                .select(crates::name)
+                .first(conn)
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+}
@@ -1,5 +1,16 @@
+use diesel::dsl;
+use diesel::prelude::*;
+
 use crate::models::User;
-use crate::schema::follows;
+use crate::schema::{follows, user_follows};
+
+type CrateIdsOf =
+    dsl::Select<dsl::Filter<follows::table, dsl::Eq<follows::user_id, i32>>, follows::crate_id>;
+
+type FollowedIdsOf = dsl::Select<
+    dsl::Filter<user_follows::table, dsl::Eq<user_follows::follower_id, i32>>,
+    user_follows::followed_id,
+>;
 
 #[derive(Insertable, Queryable, Identifiable, Associations, Clone, Copy, Debug)]
 #[belongs_to(User)]
@@ -9,3 +20,39 @@ pub struct Follow {
     pub user_id: i32,
     pub crate_id: i32,
 }
+
+impl Follow {
+    /// The ids of the crates a user follows, as a subquery for the updates
+    /// of the followed crates
+    pub fn crate_ids_of(user_id: i32) -> CrateIdsOf {
+        follows::table
+            .filter(follows::user_id.eq(user_id))
+            .select(follows::crate_id)
+    }
+}
+
+/// A user following another user, to see the versions they publish in their
+/// updates. Users that aren't `followable` can't be followed.
+#[derive(Insertable, Queryable, Identifiable, Clone, Copy, Debug)]
+#[primary_key(follower_id, followed_id)]
+#[table_name = "user_follows"]
+pub struct UserFollow {
+    pub follower_id: i32,
+    pub followed_id: i32,
+}
+
+impl UserFollow {
+    /// The ids of the users a user follows, as a subquery for the versions
+    /// they published
+    pub fn followed_ids_of(follower_id: i32) -> FollowedIdsOf {
+        user_follows::table
+            .filter(user_follows::follower_id.eq(follower_id))
+            .select(user_follows::followed_id)
+    }
+
+    /// Removes all followers of a user
+    pub fn remove_followers(conn: &PgConnection, followed_id: i32) -> QueryResult<usize> {
+        diesel::delete(user_follows::table.filter(user_follows::followed_id.eq(followed_id)))
+            .execute(conn)
+    }
+}
@@ -0,0 +1,31 @@
+-- The crates that the newest versions of the crates owned or followed by the
+-- user in $1 depend on, and that have links of the kind in $2
+WITH owned_crates AS (
+    SELECT crate_id
+    FROM crate_owners
+    WHERE owner_id = $1 AND owner_kind = 0 AND NOT deleted
+), user_crates AS (
+    SELECT crate_id FROM owned_crates
+    UNION
+    SELECT crate_id FROM follows WHERE user_id = $1
+), newest_versions AS (
+    SELECT DISTINCT ON (crate_id) id, crate_id
+    FROM versions
+    WHERE NOT yanked
+      AND crate_id IN (SELECT crate_id FROM user_crates)
+    ORDER BY crate_id, to_semver_no_prerelease(num) DESC NULLS LAST
+)
+SELECT dependencies.crate_id,
+    array_agg(DISTINCT crates.name ORDER BY crates.name) AS dependents
+FROM newest_versions
+INNER JOIN dependencies ON dependencies.version_id = newest_versions.id
+INNER JOIN crates ON crates.id = newest_versions.crate_id
+WHERE dependencies.crate_id NOT IN (SELECT crate_id FROM owned_crates)
+  AND EXISTS (
+    SELECT 1
+    FROM project_links
+    WHERE project_links.crate_id = dependencies.crate_id
+      AND project_links.kind = $2
+  )
+GROUP BY dependencies.crate_id
+ORDER BY count(DISTINCT crates.name) DESC, dependencies.crate_id
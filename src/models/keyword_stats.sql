@@ -0,0 +1,56 @@
+-- Recounts the crates and downloads of every keyword in each month from
+-- the month of $1 up to the current one.
+--
+-- Crates count towards the keywords they have now, since changes to
+-- the keywords of a crate aren't recorded.
+INSERT INTO keyword_stats (keyword_id, month, crates_cnt, new_crates, downloads, updated_at)
+SELECT keywords.id,
+    months.month,
+    -- Crates created before the end of the month
+    (
+        SELECT COUNT(*)
+        FROM crates_keywords
+        INNER JOIN crates
+          ON crates.id = crates_keywords.crate_id
+        WHERE crates_keywords.keyword_id = keywords.id
+          AND crates.created_at < months.month + interval '1 month'
+    ),
+    -- Crates created during the month
+    (
+        SELECT COUNT(*)
+        FROM crates_keywords
+        INNER JOIN crates
+          ON crates.id = crates_keywords.crate_id
+        WHERE crates_keywords.keyword_id = keywords.id
+          AND crates.created_at >= months.month
+          AND crates.created_at < months.month + interval '1 month'
+    ),
+    (
+        SELECT COALESCE(SUM(version_downloads.downloads), 0)
+        FROM version_downloads
+        INNER JOIN versions
+          ON versions.id = version_downloads.version_id
+        INNER JOIN crates_keywords
+          ON crates_keywords.crate_id = versions.crate_id
+        WHERE crates_keywords.keyword_id = keywords.id
+          AND version_downloads.date >= months.month
+          AND version_downloads.date < months.month + interval '1 month'
+    ),
+    CURRENT_TIMESTAMP
+FROM keywords
+CROSS JOIN (
+    SELECT generate_series(
+        date_trunc('month', $1::date::timestamp),
+        date_trunc('month', CURRENT_DATE::timestamp),
+        interval '1 month'
+    )::date AS month
+) months
+ON CONFLICT (keyword_id, month) DO UPDATE
+SET crates_cnt = EXCLUDED.crates_cnt,
+    new_crates = EXCLUDED.new_crates,
+    downloads = EXCLUDED.downloads,
+    updated_at = EXCLUDED.updated_at
+-- Keep `updated_at` of unchanged months for incremental database dumps
+WHERE keyword_stats.crates_cnt <> EXCLUDED.crates_cnt
+   OR keyword_stats.new_crates <> EXCLUDED.new_crates
+   OR keyword_stats.downloads <> EXCLUDED.downloads
@@ -10,10 +10,11 @@ use crate::controllers::helpers::pagination::*;
 use crate::email;
 use crate::models::version::TopVersions;
 use crate::models::{
-    Badge, CrateOwner, CrateOwnerInvitation, NewCrateOwnerInvitation, Owner, OwnerKind,
-    ReverseDependency, User, Version,
+    Badge, CrateOwner, CrateOwnerInvitation, EmailEvent, Mute, NewCrateOwnerInvitation,
+    NewNotification, NotificationKind, Owner, OwnerKind, ReservedCrateName, ReverseDependency,
+    User, Version,
 };
-use crate::util::errors::{cargo_err, AppResult};
+use crate::util::errors::{cargo_err, cargo_err_with_code, AppResult, ErrorCode};
 
 use crate::models::helpers::with_count::*;
 use crate::publish_rate_limit::PublishRateLimit;
@@ -154,16 +155,18 @@ impl<'a> NewCrate<'a> {
     }
 
     fn ensure_name_not_reserved(&self, conn: &PgConnection) -> AppResult<()> {
-        use crate::schema::reserved_crate_names::dsl::*;
         use diesel::dsl::exists;
         use diesel::select;
 
-        let reserved_name: bool = select(exists(
-            reserved_crate_names.filter(canon_crate_name(name).eq(canon_crate_name(self.name))),
-        ))
-        .get_result(conn)?;
-        if reserved_name {
-            Err(cargo_err("cannot upload a crate with a reserved name"))
+        // Wildcard reservations don't apply to crates that already exist.
+        // Exact reservations can't match an existing crate at all, as the
+        // database prevents reserving names that are in use.
+        let existing: bool = select(exists(Crate::by_name(self.name))).get_result(conn)?;
+        if !existing && ReservedCrateName::matches(conn, self.name)? {
+            Err(cargo_err_with_code(
+                ErrorCode::CrateNameReserved,
+                "cannot upload a crate with a reserved name",
+            ))
         } else {
             Ok(())
         }
@@ -320,6 +323,34 @@ impl Crate {
         Ok(users.chain(teams).collect())
     }
 
+    /// The owners of several crates at once, with the id of the crate they
+    /// own. Users are listed before teams, like in [`Crate::owners`].
+    pub fn owners_of_crates(
+        conn: &PgConnection,
+        crate_ids: &[i32],
+    ) -> QueryResult<Vec<(i32, Owner)>> {
+        use diesel::dsl::any;
+
+        let users = CrateOwner::by_owner_kind(OwnerKind::User)
+            .filter(crate_owners::crate_id.eq(any(crate_ids)))
+            .inner_join(users::table)
+            .select((crate_owners::crate_id, users::all_columns))
+            .order((crate_owners::crate_id, users::id))
+            .load(conn)?
+            .into_iter()
+            .map(|(crate_id, user)| (crate_id, Owner::User(user)));
+        let teams = CrateOwner::by_owner_kind(OwnerKind::Team)
+            .filter(crate_owners::crate_id.eq(any(crate_ids)))
+            .inner_join(teams::table)
+            .select((crate_owners::crate_id, teams::all_columns))
+            .order((crate_owners::crate_id, teams::id))
+            .load(conn)?
+            .into_iter()
+            .map(|(crate_id, team)| (crate_id, Owner::Team(team)));
+
+        Ok(users.chain(teams).collect())
+    }
+
     pub fn owner_add(
         &self,
         app: &App,
@@ -345,9 +376,26 @@ impl Crate {
                         .get_result(conn)
                         .optional()?;
 
-                if let Some(ownership_invitation) = maybe_inserted {
-                    if let Ok(Some(email)) = user.verified_email(&conn) {
+                // Users who muted the crate or the inviter still see the
+                // invitation in `/me/crate_owner_invitations`, but aren't
+                // notified about it
+                let muted = Mute::is_muted(conn, user.id, self.id, req_user.id)?;
+                if let Some(ownership_invitation) = maybe_inserted.filter(|_| !muted) {
+                    NewNotification {
+                        user_id: user.id,
+                        kind: NotificationKind::OwnerInvitation,
+                        title: &format!(
+                            "{} invited you to become an owner of {}",
+                            req_user.gh_login, self.name
+                        ),
+                        crate_name: Some(&self.name),
+                    }
+                    .create(conn)?;
+
+                    let email = user.notification_email(&conn, EmailEvent::OwnerInvitations);
+                    if let Ok(Some(email)) = email {
                         email::send_owner_invite_email(
+                            conn,
                             &email.as_str(),
                             &req_user.gh_login.as_str(),
                             &self.name.as_str(),
@@ -411,17 +459,25 @@ impl Crate {
     pub(crate) fn reverse_dependencies(
         &self,
         conn: &PgConnection,
-        options: PaginationOptions,
+        options: &PaginationOptions,
     ) -> AppResult<(Vec<ReverseDependency>, i64)> {
         use diesel::sql_query;
-        use diesel::sql_types::{BigInt, Integer};
+        use diesel::sql_types::{BigInt, Integer, Nullable};
 
+        // Ordered by `(crate_downloads DESC, crate_name)`, the sort key of
+        // the `seek` parameter
+        let (after_downloads, after_name) = match options.seek::<(i32, String)>()? {
+            Some((downloads, name)) => (Some(downloads), Some(name)),
+            None => (None, None),
+        };
         let offset = options.offset().unwrap_or_default();
         let rows: Vec<WithCount<ReverseDependency>> =
             sql_query(include_str!("krate_reverse_dependencies.sql"))
                 .bind::<Integer, _>(self.id)
                 .bind::<BigInt, _>(i64::from(offset))
                 .bind::<BigInt, _>(i64::from(options.per_page))
+                .bind::<Nullable<Integer>, _>(after_downloads)
+                .bind::<Nullable<Text>, _>(after_name)
                 .load(conn)?;
 
         Ok(rows.records_and_total())
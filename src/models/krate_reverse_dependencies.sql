@@ -1,4 +1,5 @@
--- Apply pagination to the whole thing
+-- Apply pagination to the whole thing, after counting all of it
+SELECT * FROM (
 SELECT *, COUNT(*) OVER () as total FROM (
     -- Multple dependencies can exist, make it distinct
     SELECT DISTINCT ON (crate_downloads, crate_name)
@@ -31,7 +32,13 @@ SELECT *, COUNT(*) OVER () as total FROM (
       ON crates.id = versions.crate_id
     WHERE dependencies.crate_id = $1
       AND rn = 1
-    ORDER BY crate_downloads DESC
+    ORDER BY crate_downloads DESC, crate_name ASC
 ) t
+) t
+-- Keyset pagination: only the crates after the last one of the previous page
+WHERE $4::integer IS NULL
+   OR crate_downloads < $4
+   OR (crate_downloads = $4 AND crate_name > $5)
+ORDER BY crate_downloads DESC, crate_name ASC
 OFFSET $2
 LIMIT $3
@@ -0,0 +1,10 @@
+SELECT newest.expression AS license, COUNT(*) AS crates
+FROM (
+    SELECT DISTINCT ON (versions.crate_id) version_licenses.expression
+    FROM versions
+    LEFT JOIN version_licenses ON version_licenses.version_id = versions.id
+    WHERE NOT versions.yanked
+    ORDER BY versions.crate_id, versions.id DESC
+) newest
+GROUP BY newest.expression
+ORDER BY crates DESC, license
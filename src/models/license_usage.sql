@@ -0,0 +1,11 @@
+SELECT license, COUNT(*) AS crates
+FROM (
+    SELECT DISTINCT ON (versions.crate_id) version_licenses.licenses
+    FROM versions
+    LEFT JOIN version_licenses ON version_licenses.version_id = versions.id
+    WHERE NOT versions.yanked
+    ORDER BY versions.crate_id, versions.id DESC
+) newest
+CROSS JOIN LATERAL unnest(newest.licenses) AS license
+GROUP BY license
+ORDER BY crates DESC, license
@@ -0,0 +1,25 @@
+use chrono::NaiveDateTime;
+
+use crate::schema::login_anomalies;
+
+/// A burst of requests to the login flow from a single address, detected by
+/// the login rate limiter
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[table_name = "login_anomalies"]
+pub struct LoginAnomaly {
+    pub id: i32,
+    pub ip: String,
+    /// One of `LoginAnomaly::FAILED_EXCHANGES` and `LoginAnomaly::SESSION_BURST`
+    pub kind: String,
+    /// The number of attempts within the rate limit window when the anomaly
+    /// was detected
+    pub attempts: i32,
+    pub detected_at: NaiveDateTime,
+}
+
+impl LoginAnomaly {
+    /// Many OAuth callbacks that failed, like guessed codes or replayed states
+    pub const FAILED_EXCHANGES: &'static str = "failed_exchanges";
+    /// Many sessions created, possibly for different accounts
+    pub const SESSION_BURST: &'static str = "session_burst";
+}
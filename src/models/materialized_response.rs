@@ -0,0 +1,61 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::materialized_responses;
+
+/// A response body that is expensive to compute, so it is computed by a
+/// background job and stored here for the endpoint to serve.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+#[primary_key(name)]
+pub struct MaterializedResponse {
+    pub name: String,
+    pub body: Vec<u8>,
+    pub refreshed_at: NaiveDateTime,
+}
+
+impl MaterializedResponse {
+    /// The response body of `GET /summary`
+    pub const SUMMARY: &'static str = "summary";
+
+    /// The sitemap index of `GET /sitemap.xml`
+    pub const SITEMAP_INDEX: &'static str = "sitemap.xml";
+
+    /// The prefix of the sitemaps of `GET /sitemaps/:name`
+    pub const SITEMAP_PREFIX: &'static str = "sitemaps/";
+
+    pub fn find(conn: &PgConnection, name: &str) -> QueryResult<Option<Self>> {
+        materialized_responses::table
+            .find(name)
+            .first(conn)
+            .optional()
+    }
+
+    /// Deletes the response bodies whose name starts with `prefix`
+    pub fn delete_with_prefix(conn: &PgConnection, prefix: &str) -> QueryResult<usize> {
+        diesel::delete(
+            materialized_responses::table
+                .filter(materialized_responses::name.like(format!("{}%", prefix))),
+        )
+        .execute(conn)
+    }
+
+    /// Stores a newly computed response body, replacing the previous one
+    pub fn store(conn: &PgConnection, name: &str, body: &[u8]) -> QueryResult<()> {
+        use diesel::dsl::now;
+        use diesel::pg::upsert::excluded;
+
+        diesel::insert_into(materialized_responses::table)
+            .values((
+                materialized_responses::name.eq(name),
+                materialized_responses::body.eq(body),
+            ))
+            .on_conflict(materialized_responses::name)
+            .do_update()
+            .set((
+                materialized_responses::body.eq(excluded(materialized_responses::body)),
+                materialized_responses::refreshed_at.eq(now),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+}
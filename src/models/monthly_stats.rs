@@ -0,0 +1,88 @@
+//! Monthly statistics of categories and keywords, maintained by the
+//! `RefreshMonthlyStats` background job.
+
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use diesel::prelude::*;
+use diesel::sql_types::Date;
+
+use crate::models::{Category, Keyword};
+use crate::schema::{category_stats, crates, keyword_stats};
+
+/// The crates and downloads of a category in a month
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Category)]
+#[primary_key(category_id, month)]
+#[table_name = "category_stats"]
+pub struct CategoryStats {
+    pub category_id: i32,
+    /// The first day of the month
+    pub month: NaiveDate,
+    /// Crates in the category created before the end of the month
+    pub crates_cnt: i32,
+    /// Crates in the category created during the month
+    pub new_crates: i32,
+    /// Downloads of the crates in the category during the month
+    pub downloads: i64,
+    pub updated_at: NaiveDateTime,
+}
+
+/// The crates and downloads of a keyword in a month
+#[derive(Debug, Clone, Copy, Queryable, Identifiable, Associations)]
+#[belongs_to(Keyword)]
+#[primary_key(keyword_id, month)]
+#[table_name = "keyword_stats"]
+pub struct KeywordStats {
+    pub keyword_id: i32,
+    /// The first day of the month
+    pub month: NaiveDate,
+    /// Crates with the keyword created before the end of the month
+    pub crates_cnt: i32,
+    /// Crates with the keyword created during the month
+    pub new_crates: i32,
+    /// Downloads of the crates with the keyword during the month
+    pub downloads: i64,
+    pub updated_at: NaiveDateTime,
+}
+
+impl CategoryStats {
+    /// The statistics of a category, oldest month first
+    pub fn for_category(conn: &PgConnection, category_id: i32) -> QueryResult<Vec<Self>> {
+        category_stats::table
+            .filter(category_stats::category_id.eq(category_id))
+            .order(category_stats::month)
+            .load(conn)
+    }
+}
+
+impl KeywordStats {
+    /// The statistics of a keyword, oldest month first
+    pub fn for_keyword(conn: &PgConnection, keyword_id: i32) -> QueryResult<Vec<Self>> {
+        keyword_stats::table
+            .filter(keyword_stats::keyword_id.eq(keyword_id))
+            .order(keyword_stats::month)
+            .load(conn)
+    }
+}
+
+/// Recounts the statistics of all categories and keywords from the month of
+/// `since` up to the current month
+pub fn refresh_monthly_stats(conn: &PgConnection, since: NaiveDate) -> QueryResult<()> {
+    conn.transaction(|| {
+        diesel::sql_query(include_str!("category_stats.sql"))
+            .bind::<Date, _>(since)
+            .execute(conn)?;
+        diesel::sql_query(include_str!("keyword_stats.sql"))
+            .bind::<Date, _>(since)
+            .execute(conn)?;
+        Ok(())
+    })
+}
+
+/// The month the statistics start at, the month the first crate was created
+pub fn first_month(conn: &PgConnection) -> QueryResult<Option<NaiveDate>> {
+    use diesel::dsl::min;
+
+    let created_at: Option<NaiveDateTime> =
+        crates::table.select(min(crates::created_at)).first(conn)?;
+    Ok(created_at.map(|created_at| created_at.date().with_day(1).unwrap()))
+}
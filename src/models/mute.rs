@@ -0,0 +1,90 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::mutes;
+
+/// A crate or a user whose notifications a user doesn't want to receive,
+/// neither in the notification list nor by email.
+///
+/// Mutes only cover notifications that are caused by other users: owner
+/// invitations and new versions. Security notices and moderation messages
+/// are always delivered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct Mute {
+    pub id: i32,
+    pub user_id: i32,
+    pub crate_id: Option<i32>,
+    pub muted_user_id: Option<i32>,
+    pub created_at: NaiveDateTime,
+}
+
+impl Mute {
+    /// The crates and users that the user muted, oldest first
+    pub fn for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Vec<Mute>> {
+        mutes::table
+            .filter(mutes::user_id.eq(user_id))
+            .order(mutes::id)
+            .load(conn)
+    }
+
+    /// Whether the user muted the crate that a notification is about, or the
+    /// user that caused it
+    pub fn is_muted(
+        conn: &PgConnection,
+        user_id: i32,
+        crate_id: i32,
+        from_user_id: i32,
+    ) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            mutes::table.filter(mutes::user_id.eq(user_id)).filter(
+                mutes::crate_id
+                    .eq(crate_id)
+                    .or(mutes::muted_user_id.eq(from_user_id)),
+            ),
+        ))
+        .get_result(conn)
+    }
+
+    pub fn mute_crate(conn: &PgConnection, user_id: i32, crate_id: i32) -> QueryResult<usize> {
+        diesel::insert_into(mutes::table)
+            .values((mutes::user_id.eq(user_id), mutes::crate_id.eq(crate_id)))
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    pub fn unmute_crate(conn: &PgConnection, user_id: i32, crate_id: i32) -> QueryResult<usize> {
+        diesel::delete(
+            mutes::table
+                .filter(mutes::user_id.eq(user_id))
+                .filter(mutes::crate_id.eq(crate_id)),
+        )
+        .execute(conn)
+    }
+
+    pub fn mute_user(conn: &PgConnection, user_id: i32, muted_user_id: i32) -> QueryResult<usize> {
+        diesel::insert_into(mutes::table)
+            .values((
+                mutes::user_id.eq(user_id),
+                mutes::muted_user_id.eq(muted_user_id),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)
+    }
+
+    pub fn unmute_user(
+        conn: &PgConnection,
+        user_id: i32,
+        muted_user_id: i32,
+    ) -> QueryResult<usize> {
+        diesel::delete(
+            mutes::table
+                .filter(mutes::user_id.eq(user_id))
+                .filter(mutes::muted_user_id.eq(muted_user_id)),
+        )
+        .execute(conn)
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::models::User;
+use crate::schema::notifications;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum NotificationKind {
+    /// Someone invited the user to become an owner of a crate
+    OwnerInvitation = 0,
+    /// Another owner published a new version of a crate owned by the user
+    NewVersion = 1,
+    /// Versions of a crate owned by the user, or one of its dependencies, are
+    /// affected by a security issue
+    SecurityNotice = 2,
+    /// The crates.io team took action on the user's account
+    Moderation = 3,
+}
+
+impl From<NotificationKind> for &'static str {
+    fn from(kind: NotificationKind) -> Self {
+        match kind {
+            NotificationKind::OwnerInvitation => "owner_invitation",
+            NotificationKind::NewVersion => "new_version",
+            NotificationKind::SecurityNotice => "security_notice",
+            NotificationKind::Moderation => "moderation",
+        }
+    }
+}
+
+impl FromSql<Integer, Pg> for NotificationKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(NotificationKind::OwnerInvitation),
+            1 => Ok(NotificationKind::NewVersion),
+            2 => Ok(NotificationKind::SecurityNotice),
+            3 => Ok(NotificationKind::Moderation),
+            n => Err(format!("unknown notification kind: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for NotificationKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A notification shown on the website, created for the same events as the
+/// notification emails.
+///
+/// Unlike emails, notifications are created regardless of the email
+/// preferences of the user and whether they have a verified email address.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+pub struct Notification {
+    pub id: i32,
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub title: String,
+    /// The crate the notification is about, if any
+    pub crate_name: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub read_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, Insertable)]
+#[table_name = "notifications"]
+pub struct NewNotification<'a> {
+    pub user_id: i32,
+    pub kind: NotificationKind,
+    pub title: &'a str,
+    pub crate_name: Option<&'a str>,
+}
+
+impl NewNotification<'_> {
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<Notification> {
+        diesel::insert_into(notifications::table)
+            .values(self)
+            .get_result(conn)
+    }
+}
+
+impl Notification {
+    pub fn unread_count(conn: &PgConnection, user_id: i32) -> QueryResult<i64> {
+        notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::read_at.is_null())
+            .count()
+            .get_result(conn)
+    }
+
+    /// Marks the given notifications of the user as read, or all of them if
+    /// `ids` is `None`. Returns how many notifications were unread.
+    pub fn mark_read(conn: &PgConnection, user_id: i32, ids: Option<&[i32]>) -> QueryResult<usize> {
+        use diesel::dsl::{any, now};
+
+        let unread = notifications::table
+            .filter(notifications::user_id.eq(user_id))
+            .filter(notifications::read_at.is_null());
+
+        match ids {
+            Some(ids) => diesel::update(unread.filter(notifications::id.eq(any(ids))))
+                .set(notifications::read_at.eq(now.nullable()))
+                .execute(conn),
+            None => diesel::update(unread)
+                .set(notifications::read_at.eq(now.nullable()))
+                .execute(conn),
+        }
+    }
+}
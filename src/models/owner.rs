@@ -5,7 +5,7 @@ use crate::app::App;
 use crate::util::errors::{cargo_err, AppResult};
 
 use crate::models::{Crate, Team, User};
-use crate::schema::{crate_owners, users};
+use crate::schema::crate_owners;
 
 #[derive(Insertable, Associations, Identifiable, Debug, Clone, Copy)]
 #[belongs_to(Crate)]
@@ -55,7 +55,7 @@ impl Owner {
     /// up-to-date GitHub ID. Fails out if the user isn't found in the
     /// database, the team isn't found on GitHub, or if the user isn't a member
     /// of the team on GitHub.
-    /// May be a user's GH login or a full team name. This is case
+    /// May be a user's login on crates.io or a full team name. This is case
     /// sensitive.
     pub fn find_or_create_by_login(
         app: &App,
@@ -68,13 +68,13 @@ impl Owner {
                 app, conn, name, req_user,
             )?))
         } else {
-            users::table
-                .filter(crate::lower(users::gh_login).eq(name.to_lowercase()))
-                .filter(users::gh_id.ne(-1))
-                .order(users::gh_id.desc())
-                .first(conn)
+            User::find_by_login(conn, name)
+                .ok()
+                .filter(|user| user.gh_id != -1)
                 .map(Owner::User)
-                .map_err(|_| cargo_err(&format_args!("could not find user with login `{}`", name)))
+                .ok_or_else(|| {
+                    cargo_err(&format_args!("could not find user with login `{}`", name))
+                })
         }
     }
 
@@ -87,7 +87,7 @@ impl Owner {
 
     pub fn login(&self) -> &str {
         match *self {
-            Owner::User(ref user) => &user.gh_login,
+            Owner::User(ref user) => user.login(),
             Owner::Team(ref team) => &team.login,
         }
     }
@@ -0,0 +1,107 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::background_jobs::Job;
+use crate::schema::pending_uploads;
+use crate::swirl::EnqueueError;
+
+/// The tarball of a published version that hasn't been uploaded to storage
+/// yet, along with the jobs that have to wait for the upload.
+///
+/// The row is inserted in the same transaction as the version, so a version
+/// can't be committed without a record of its upload. Once the tarball is
+/// in storage the jobs are enqueued and the row is deleted, again in a
+/// single transaction. Uploading the same tarball twice is harmless, so the
+/// upload can be retried until this has happened.
+#[derive(Debug, Queryable, Identifiable)]
+#[primary_key(version_id)]
+pub struct PendingUpload {
+    pub version_id: i32,
+    pub tarball: Vec<u8>,
+    pub jobs: serde_json::Value,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct NewPendingUpload<'a> {
+    pub version_id: i32,
+    pub tarball: &'a [u8],
+    /// Enqueued once the tarball is in storage
+    pub jobs: &'a [Job],
+}
+
+impl NewPendingUpload<'_> {
+    pub fn create(&self, conn: &PgConnection) -> Result<(), EnqueueError> {
+        let jobs = serde_json::to_value(self.jobs)?;
+
+        diesel::insert_into(pending_uploads::table)
+            .values((
+                pending_uploads::version_id.eq(self.version_id),
+                pending_uploads::tarball.eq(self.tarball),
+                pending_uploads::jobs.eq(jobs),
+            ))
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+impl PendingUpload {
+    /// Locks the pending upload of a version for the rest of the transaction.
+    ///
+    /// Returns `None` if the tarball has already been uploaded, or if another
+    /// worker is uploading it right now.
+    pub fn lock(conn: &PgConnection, version_id: i32) -> QueryResult<Option<Self>> {
+        pending_uploads::table
+            .find(version_id)
+            .for_update()
+            .skip_locked()
+            .first(conn)
+            .optional()
+    }
+
+    /// The versions whose tarballs have been waiting since before `time`
+    pub fn created_before(conn: &PgConnection, time: NaiveDateTime) -> QueryResult<Vec<i32>> {
+        pending_uploads::table
+            .filter(pending_uploads::created_at.lt(time))
+            .select(pending_uploads::version_id)
+            .order(pending_uploads::created_at)
+            .load(conn)
+    }
+
+    /// Enqueues a job once the tarball of a version is in storage. The job is
+    /// enqueued right away if it has already been uploaded.
+    pub fn enqueue_after_upload(
+        conn: &PgConnection,
+        version_id: i32,
+        job: Job,
+    ) -> Result<(), EnqueueError> {
+        let pending = pending_uploads::table
+            .find(version_id)
+            .for_update()
+            .first::<Self>(conn)
+            .optional()?;
+        let pending = match pending {
+            Some(pending) => pending,
+            None => return job.enqueue(conn),
+        };
+
+        let mut jobs: Vec<Job> = serde_json::from_value(pending.jobs.clone())?;
+        jobs.push(job);
+        diesel::update(&pending)
+            .set(pending_uploads::jobs.eq(serde_json::to_value(&jobs)?))
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Enqueues the jobs that were waiting for the upload and deletes the
+    /// row. Must be called in the transaction that locked the row.
+    pub fn finish(self, conn: &PgConnection) -> Result<(), EnqueueError> {
+        let jobs: Vec<Job> = serde_json::from_value(self.jobs.clone())?;
+        for job in &jobs {
+            job.enqueue(conn)?;
+        }
+
+        diesel::delete(&self).execute(conn)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::{Array, Integer, Text},
+};
+use std::io::Write;
+
+use crate::models::Crate;
+use crate::schema::project_links;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum ProjectLinkKind {
+    Changelog = 0,
+    MigrationGuide = 1,
+    /// A chat room or forum of the project
+    Chat = 2,
+    /// A page for sponsoring the project
+    Funding = 3,
+}
+
+impl ProjectLinkKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "changelog" => Some(ProjectLinkKind::Changelog),
+            "migration_guide" => Some(ProjectLinkKind::MigrationGuide),
+            "chat" => Some(ProjectLinkKind::Chat),
+            "funding" => Some(ProjectLinkKind::Funding),
+            _ => None,
+        }
+    }
+}
+
+impl From<ProjectLinkKind> for &'static str {
+    fn from(kind: ProjectLinkKind) -> Self {
+        match kind {
+            ProjectLinkKind::Changelog => "changelog",
+            ProjectLinkKind::MigrationGuide => "migration_guide",
+            ProjectLinkKind::Chat => "chat",
+            ProjectLinkKind::Funding => "funding",
+        }
+    }
+}
+
+impl From<ProjectLinkKind> for String {
+    fn from(kind: ProjectLinkKind) -> Self {
+        let string: &'static str = kind.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for ProjectLinkKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(ProjectLinkKind::Changelog),
+            1 => Ok(ProjectLinkKind::MigrationGuide),
+            2 => Ok(ProjectLinkKind::Chat),
+            3 => Ok(ProjectLinkKind::Funding),
+            n => Err(format!("unknown project link kind: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for ProjectLinkKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A link that the owners attached to a crate, like its changelog or a page
+/// for sponsoring it, which is shown next to the README.
+#[derive(Debug, Clone, PartialEq, Eq, Queryable, Identifiable, Associations)]
+#[belongs_to(Crate)]
+pub struct ProjectLink {
+    pub id: i32,
+    pub crate_id: i32,
+    pub kind: ProjectLinkKind,
+    pub url: String,
+    pub label: Option<String>,
+    pub created_at: NaiveDateTime,
+    /// Funding links from `package.metadata.funding` of the newest published
+    /// `Cargo.toml`, which can't be changed through the API
+    pub from_manifest: bool,
+}
+
+#[derive(Debug, Clone, Insertable)]
+#[table_name = "project_links"]
+pub struct NewProjectLink {
+    pub crate_id: i32,
+    pub kind: ProjectLinkKind,
+    pub url: String,
+    pub label: Option<String>,
+    pub from_manifest: bool,
+}
+
+/// A crate that a crate owned or followed by a user depends on, and that
+/// accepts funding
+#[derive(Debug, QueryableByName)]
+pub struct FundedDependency {
+    #[sql_type = "Integer"]
+    pub crate_id: i32,
+    /// The crates of the user that depend on the crate
+    #[sql_type = "Array<Text>"]
+    pub dependents: Vec<String>,
+}
+
+impl ProjectLink {
+    /// How many links a crate can have
+    pub const MAX_PER_CRATE: usize = 20;
+
+    pub const MAX_URL_LENGTH: usize = 500;
+    pub const MAX_LABEL_LENGTH: usize = 64;
+
+    /// Whether the URL is an `http` or `https` URL that can be linked to
+    pub fn is_valid_url(url: &str) -> bool {
+        url.len() <= Self::MAX_URL_LENGTH
+            && url::Url::parse(url)
+                .map(|url| matches!(url.scheme(), "http" | "https") && url.host_str().is_some())
+                .unwrap_or(false)
+    }
+
+    /// The links of the crate, in the order the owners set them
+    pub fn for_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Vec<ProjectLink>> {
+        project_links::table
+            .filter(project_links::crate_id.eq(crate_id))
+            .order(project_links::id)
+            .load(conn)
+    }
+
+    /// Replaces the links of the crate that are either set through the API or
+    /// come from the manifest, depending on `from_manifest`
+    pub fn replace_all(
+        conn: &PgConnection,
+        crate_id: i32,
+        from_manifest: bool,
+        links: &[NewProjectLink],
+    ) -> QueryResult<Vec<ProjectLink>> {
+        conn.transaction(|| {
+            diesel::delete(
+                project_links::table
+                    .filter(project_links::crate_id.eq(crate_id))
+                    .filter(project_links::from_manifest.eq(from_manifest)),
+            )
+            .execute(conn)?;
+            if !links.is_empty() {
+                diesel::insert_into(project_links::table)
+                    .values(links)
+                    .execute(conn)?;
+            }
+            Self::for_crate(conn, crate_id)
+        })
+    }
+
+    /// The crates that the newest versions of the crates owned or followed by
+    /// the user depend on, and that have funding links. The user's own crates
+    /// are left out.
+    pub fn funded_dependencies(
+        conn: &PgConnection,
+        user_id: i32,
+    ) -> QueryResult<Vec<FundedDependency>> {
+        diesel::sql_query(include_str!("funded_dependencies.sql"))
+            .bind::<Integer, _>(user_id)
+            .bind::<Integer, _>(ProjectLinkKind::Funding as i32)
+            .load(conn)
+    }
+}
@@ -0,0 +1,186 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::models::User;
+use crate::schema::{publish_rate_override_actions, publish_rate_overrides};
+
+/// An override of the publish rate limit burst for a single user.
+///
+/// Overrides with an `expires_at` timestamp in the past are ignored by the
+/// rate limiter, but kept around for reference.
+#[derive(Debug, Clone, Copy, PartialEq, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+#[primary_key(user_id)]
+pub struct PublishRateOverride {
+    pub user_id: i32,
+    pub burst: i32,
+    pub expires_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum PublishRateOverrideActionKind {
+    Set = 0,
+    Expire = 1,
+}
+
+impl From<PublishRateOverrideActionKind> for &'static str {
+    fn from(action: PublishRateOverrideActionKind) -> Self {
+        match action {
+            PublishRateOverrideActionKind::Set => "set",
+            PublishRateOverrideActionKind::Expire => "expire",
+        }
+    }
+}
+
+impl From<PublishRateOverrideActionKind> for String {
+    fn from(action: PublishRateOverrideActionKind) -> Self {
+        let string: &'static str = action.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for PublishRateOverrideActionKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(PublishRateOverrideActionKind::Set),
+            1 => Ok(PublishRateOverrideActionKind::Expire),
+            n => Err(format!("unknown rate limit override action: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for PublishRateOverrideActionKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// An entry in the audit trail of changes to publish rate limit overrides.
+#[derive(Debug, Clone, Copy, Queryable, Identifiable)]
+pub struct PublishRateOverrideAction {
+    pub id: i32,
+    pub user_id: i32,
+    pub admin_id: i32,
+    pub action: PublishRateOverrideActionKind,
+    pub burst: Option<i32>,
+    pub expires_at: Option<NaiveDateTime>,
+    pub time: NaiveDateTime,
+}
+
+impl PublishRateOverride {
+    /// Creates or replaces the override for a user, recording the change in
+    /// the audit trail.
+    pub fn set(
+        conn: &PgConnection,
+        user_id: i32,
+        admin_id: i32,
+        burst: i32,
+        expires_at: Option<NaiveDateTime>,
+    ) -> QueryResult<Self> {
+        use self::publish_rate_overrides::dsl;
+        use diesel::pg::upsert::excluded;
+
+        conn.transaction(|| {
+            let rate_override = diesel::insert_into(publish_rate_overrides::table)
+                .values((
+                    dsl::user_id.eq(user_id),
+                    dsl::burst.eq(burst),
+                    dsl::expires_at.eq(expires_at),
+                ))
+                .on_conflict(dsl::user_id)
+                .do_update()
+                .set((
+                    dsl::burst.eq(excluded(dsl::burst)),
+                    dsl::expires_at.eq(excluded(dsl::expires_at)),
+                ))
+                .get_result(conn)?;
+
+            insert_action(
+                conn,
+                user_id,
+                admin_id,
+                PublishRateOverrideActionKind::Set,
+                Some(burst),
+                expires_at,
+            )?;
+
+            Ok(rate_override)
+        })
+    }
+
+    /// Expires the override for a user immediately, recording the change in
+    /// the audit trail.
+    ///
+    /// Returns `NotFound` if the user has no active override.
+    pub fn expire(conn: &PgConnection, user_id: i32, admin_id: i32) -> QueryResult<Self> {
+        use self::publish_rate_overrides::dsl;
+        use diesel::dsl::now;
+
+        conn.transaction(|| {
+            let rate_override: Self = diesel::update(
+                publish_rate_overrides::table.find(user_id).filter(
+                    dsl::expires_at
+                        .is_null()
+                        .or(dsl::expires_at.gt(now.nullable())),
+                ),
+            )
+            .set(dsl::expires_at.eq(now.nullable()))
+            .get_result(conn)?;
+
+            insert_action(
+                conn,
+                user_id,
+                admin_id,
+                PublishRateOverrideActionKind::Expire,
+                None,
+                rate_override.expires_at,
+            )?;
+
+            Ok(rate_override)
+        })
+    }
+
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        self.expires_at.map_or(true, |expires_at| expires_at > now)
+    }
+}
+
+impl PublishRateOverrideAction {
+    pub fn for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Vec<Self>> {
+        publish_rate_override_actions::table
+            .filter(publish_rate_override_actions::user_id.eq(user_id))
+            .order(publish_rate_override_actions::id)
+            .load(conn)
+    }
+}
+
+fn insert_action(
+    conn: &PgConnection,
+    user_id_: i32,
+    admin_id_: i32,
+    action_: PublishRateOverrideActionKind,
+    burst_: Option<i32>,
+    expires_at_: Option<NaiveDateTime>,
+) -> QueryResult<PublishRateOverrideAction> {
+    use self::publish_rate_override_actions::dsl::*;
+
+    diesel::insert_into(publish_rate_override_actions)
+        .values((
+            user_id.eq(user_id_),
+            admin_id.eq(admin_id_),
+            action.eq(action_),
+            burst.eq(burst_),
+            expires_at.eq(expires_at_),
+        ))
+        .get_result(conn)
+}
@@ -0,0 +1,272 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::background_jobs::Job;
+use crate::git;
+use crate::models::{PendingUpload, User, Version};
+use crate::schema::{version_quarantines, versions};
+use crate::swirl::EnqueueError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum QuarantineStatus {
+    /// The version was flagged by a scanner and is waiting for an admin
+    Pending = 0,
+    /// An admin reviewed the findings and made the version available again
+    Released = 1,
+    /// An admin confirmed the findings; the version has been yanked
+    Rejected = 2,
+}
+
+impl QuarantineStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "pending" => Some(QuarantineStatus::Pending),
+            "released" => Some(QuarantineStatus::Released),
+            "rejected" => Some(QuarantineStatus::Rejected),
+            _ => None,
+        }
+    }
+}
+
+impl From<QuarantineStatus> for &'static str {
+    fn from(status: QuarantineStatus) -> Self {
+        match status {
+            QuarantineStatus::Pending => "pending",
+            QuarantineStatus::Released => "released",
+            QuarantineStatus::Rejected => "rejected",
+        }
+    }
+}
+
+impl From<QuarantineStatus> for String {
+    fn from(status: QuarantineStatus) -> Self {
+        let string: &'static str = status.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for QuarantineStatus {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(QuarantineStatus::Pending),
+            1 => Ok(QuarantineStatus::Released),
+            2 => Ok(QuarantineStatus::Rejected),
+            n => Err(format!("unknown quarantine status: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for QuarantineStatus {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A single issue reported by an upload scanner.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Finding {
+    /// The name of the scanner that reported the issue
+    pub scanner: String,
+    /// A short machine readable identifier for the matched rule
+    pub rule: String,
+    /// The path of the offending file inside the tarball, if any
+    pub path: Option<String>,
+    /// A human readable explanation for the reviewer
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[belongs_to(User, foreign_key = "reviewed_by")]
+#[primary_key(version_id)]
+pub struct VersionQuarantine {
+    pub version_id: i32,
+    pub status: QuarantineStatus,
+    pub findings: serde_json::Value,
+    pub created_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    pub reviewed_at: Option<NaiveDateTime>,
+    /// The index entry of a version that was held out of the index, which is
+    /// added once the version is released
+    pub held_index_entry: Option<serde_json::Value>,
+    /// When a held version is released without a review
+    pub release_at: Option<NaiveDateTime>,
+}
+
+impl VersionQuarantine {
+    /// Places the version in the quarantine, or replaces the findings of an
+    /// existing quarantine entry and marks it as pending again.
+    ///
+    /// Versions with findings are never released without a review, even if
+    /// they were held with a release time before.
+    pub fn quarantine(
+        conn: &PgConnection,
+        version_id: i32,
+        findings: &[Finding],
+    ) -> QueryResult<Self> {
+        use self::version_quarantines::dsl;
+        use diesel::pg::upsert::excluded;
+
+        let findings = serde_json::to_value(findings).expect("findings are always serializable");
+
+        diesel::insert_into(version_quarantines::table)
+            .values((dsl::version_id.eq(version_id), dsl::findings.eq(findings)))
+            .on_conflict(dsl::version_id)
+            .do_update()
+            .set((
+                dsl::status.eq(QuarantineStatus::Pending),
+                dsl::findings.eq(excluded(dsl::findings)),
+                dsl::reviewed_by.eq(None::<i32>),
+                dsl::reviewed_at.eq(None::<NaiveDateTime>),
+                dsl::release_at.eq(None::<NaiveDateTime>),
+            ))
+            .get_result(conn)
+    }
+
+    /// Places a version that was just published in the quarantine, before it
+    /// was added to the index. The index entry is added when it is released,
+    /// by an admin or automatically at `release_at`.
+    pub fn hold(
+        conn: &PgConnection,
+        version_id: i32,
+        findings: &[Finding],
+        index_entry: &git::Crate,
+        release_at: Option<NaiveDateTime>,
+    ) -> QueryResult<Self> {
+        use self::version_quarantines::dsl;
+
+        let findings = serde_json::to_value(findings).expect("findings are always serializable");
+        let index_entry =
+            serde_json::to_value(index_entry).expect("index entries are always serializable");
+
+        diesel::insert_into(version_quarantines::table)
+            .values((
+                dsl::version_id.eq(version_id),
+                dsl::findings.eq(findings),
+                dsl::held_index_entry.eq(index_entry),
+                dsl::release_at.eq(release_at),
+            ))
+            .get_result(conn)
+    }
+
+    /// Returns `true` if the version is currently held back for review.
+    pub fn is_pending(conn: &PgConnection, version_id: i32) -> QueryResult<bool> {
+        use self::version_quarantines::dsl;
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            version_quarantines::table
+                .find(version_id)
+                .filter(dsl::status.eq(QuarantineStatus::Pending)),
+        ))
+        .get_result(conn)
+    }
+
+    /// Whether the version is held out of the index, see `hold`
+    pub fn is_held_out_of_index(conn: &PgConnection, version_id: i32) -> QueryResult<bool> {
+        use self::version_quarantines::dsl;
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            version_quarantines::table
+                .find(version_id)
+                .filter(dsl::held_index_entry.is_not_null()),
+        ))
+        .get_result(conn)
+    }
+
+    /// Records the decision of an admin on a pending quarantine entry.
+    ///
+    /// Returns `NotFound` if there is no pending entry for the version.
+    pub fn review(
+        conn: &PgConnection,
+        version_id: i32,
+        reviewer_id: i32,
+        new_status: QuarantineStatus,
+    ) -> QueryResult<Self> {
+        use self::version_quarantines::dsl;
+        use diesel::dsl::now;
+
+        diesel::update(
+            version_quarantines::table
+                .find(version_id)
+                .filter(dsl::status.eq(QuarantineStatus::Pending)),
+        )
+        .set((
+            dsl::status.eq(new_status),
+            dsl::reviewed_by.eq(reviewer_id),
+            dsl::reviewed_at.eq(now.nullable()),
+        ))
+        .get_result(conn)
+    }
+
+    /// Releases the held versions whose release time has passed, and returns
+    /// their quarantine entries.
+    pub fn release_due(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<Vec<Self>> {
+        use self::version_quarantines::dsl;
+
+        diesel::update(
+            version_quarantines::table
+                .filter(dsl::status.eq(QuarantineStatus::Pending))
+                .filter(dsl::release_at.le(now)),
+        )
+        .set((
+            dsl::status.eq(QuarantineStatus::Released),
+            dsl::reviewed_at.eq(now),
+        ))
+        .get_results(conn)
+    }
+
+    /// The pending quarantine entries of the versions published by a user
+    pub fn pending_published_by(conn: &PgConnection, user_id: i32) -> QueryResult<Vec<Self>> {
+        version_quarantines::table
+            .inner_join(versions::table)
+            .filter(version_quarantines::status.eq(QuarantineStatus::Pending))
+            .filter(versions::published_by.eq(user_id))
+            .select(version_quarantines::all_columns)
+            .load(conn)
+    }
+
+    /// Adds a version that was held out of the index to it, after it was
+    /// released.
+    ///
+    /// If the crate file hasn't been uploaded yet, the index is only updated
+    /// after the upload, like for versions that weren't held.
+    pub fn add_held_version_to_index(&self, conn: &PgConnection) -> Result<Self, EnqueueError> {
+        let index_entry = match &self.held_index_entry {
+            Some(index_entry) => index_entry,
+            None => return Ok(self.clone()),
+        };
+        let job = Job::AddCrate {
+            krate: serde_json::from_value(index_entry.clone())?,
+        };
+        PendingUpload::enqueue_after_upload(conn, self.version_id, job)?;
+        Ok(self.forget_held_index_entry(conn)?)
+    }
+
+    /// Removes the index entry of a held version, which is never added to the
+    /// index, like after it was rejected
+    pub fn forget_held_index_entry(&self, conn: &PgConnection) -> QueryResult<Self> {
+        diesel::update(self)
+            .set(version_quarantines::held_index_entry.eq(None::<serde_json::Value>))
+            .get_result(conn)
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.held_index_entry.is_some()
+    }
+
+    pub fn findings(&self) -> Vec<Finding> {
+        serde_json::from_value(self.findings.clone()).unwrap_or_default()
+    }
+}
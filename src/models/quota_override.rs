@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::quota_overrides;
+
+/// The quotas of a single user that an admin changed, see the `quotas`
+/// module. Quotas that are `None` are the configured ones.
+#[derive(Debug, Clone, Copy, PartialEq, Queryable, Identifiable, Associations)]
+#[belongs_to(User)]
+#[primary_key(user_id)]
+pub struct QuotaOverride {
+    pub user_id: i32,
+    pub storage_bytes: Option<i64>,
+    pub versions_per_day: Option<i64>,
+    pub crates: Option<i64>,
+    /// The admin who last changed the override
+    pub admin_id: i32,
+    pub updated_at: NaiveDateTime,
+}
+
+impl QuotaOverride {
+    pub fn find(conn: &PgConnection, user_id: i32) -> QueryResult<Option<Self>> {
+        quota_overrides::table.find(user_id).first(conn).optional()
+    }
+
+    /// Creates or replaces the override for a user
+    pub fn set(
+        conn: &PgConnection,
+        user_id: i32,
+        admin_id: i32,
+        storage_bytes: Option<i64>,
+        versions_per_day: Option<i64>,
+        crates: Option<i64>,
+    ) -> QueryResult<Self> {
+        use self::quota_overrides::dsl;
+        use diesel::dsl::now;
+        use diesel::pg::upsert::excluded;
+
+        diesel::insert_into(quota_overrides::table)
+            .values((
+                dsl::user_id.eq(user_id),
+                dsl::storage_bytes.eq(storage_bytes),
+                dsl::versions_per_day.eq(versions_per_day),
+                dsl::crates.eq(crates),
+                dsl::admin_id.eq(admin_id),
+            ))
+            .on_conflict(dsl::user_id)
+            .do_update()
+            .set((
+                dsl::storage_bytes.eq(excluded(dsl::storage_bytes)),
+                dsl::versions_per_day.eq(excluded(dsl::versions_per_day)),
+                dsl::crates.eq(excluded(dsl::crates)),
+                dsl::admin_id.eq(excluded(dsl::admin_id)),
+                dsl::updated_at.eq(now),
+            ))
+            .get_result(conn)
+    }
+
+    /// Removes the override, so that the configured quotas apply again.
+    ///
+    /// Returns `NotFound` if the user has no override.
+    pub fn delete(conn: &PgConnection, user_id: i32) -> QueryResult<Self> {
+        diesel::delete(quota_overrides::table.find(user_id)).get_result(conn)
+    }
+}
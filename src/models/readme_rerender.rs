@@ -0,0 +1,138 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::{count_star, now};
+use diesel::prelude::*;
+
+use crate::background_jobs::JobProgress;
+use crate::schema::{crates, readme_renderings, readme_rerenders, versions};
+
+/// A request by an administrator to render the READMEs of many versions
+/// again, usually after a change to the renderer.
+///
+/// The rendering is done by the `RerenderReadmes` background job, in batches
+/// of `batch_size` versions. Every batch records its progress and enqueues
+/// the job for the next one, so a re-render continues where it left off if
+/// the worker is restarted.
+#[derive(Debug, Clone, Queryable, Identifiable)]
+pub struct ReadmeRerender {
+    pub id: i32,
+    pub admin_id: i32,
+    pub crate_name: Option<String>,
+    pub rendered_before: NaiveDateTime,
+    pub batch_size: i32,
+    pub total_versions: i32,
+    pub processed_versions: i32,
+    pub failed_versions: i32,
+    pub last_version_id: i32,
+    pub created_at: NaiveDateTime,
+    pub completed_at: Option<NaiveDateTime>,
+    pub cancelled_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+pub struct NewReadmeRerender<'a> {
+    pub admin_id: i32,
+    pub crate_name: Option<&'a str>,
+    pub rendered_before: NaiveDateTime,
+    pub batch_size: i32,
+}
+
+impl NewReadmeRerender<'_> {
+    /// Creates the re-render, along with the number of versions it matches
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<ReadmeRerender> {
+        let mut query = versions::table
+            .inner_join(crates::table)
+            .left_outer_join(readme_renderings::table)
+            .filter(
+                readme_renderings::rendered_at
+                    .lt(self.rendered_before)
+                    .or(readme_renderings::version_id.is_null()),
+            )
+            .select(count_star())
+            .into_boxed();
+        if let Some(crate_name) = self.crate_name {
+            query = query.filter(crates::name.eq(crate_name));
+        }
+        let total_versions: i64 = query.get_result(conn)?;
+
+        diesel::insert_into(readme_rerenders::table)
+            .values((
+                readme_rerenders::admin_id.eq(self.admin_id),
+                readme_rerenders::crate_name.eq(self.crate_name),
+                readme_rerenders::rendered_before.eq(self.rendered_before),
+                readme_rerenders::batch_size.eq(self.batch_size),
+                readme_rerenders::total_versions.eq(total_versions as i32),
+            ))
+            .get_result(conn)
+    }
+}
+
+impl ReadmeRerender {
+    pub fn find(conn: &PgConnection, id: i32) -> QueryResult<Self> {
+        readme_rerenders::table.find(id).first(conn)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.completed_at.is_some() || self.cancelled_at.is_some()
+    }
+
+    pub fn progress(&self) -> JobProgress {
+        JobProgress {
+            completed: (self.processed_versions + self.failed_versions).into(),
+            total: self.total_versions.into(),
+        }
+    }
+
+    /// Loads the ID, crate name and number of the next versions to render
+    pub fn next_batch(&self, conn: &PgConnection) -> QueryResult<Vec<(i32, String, String)>> {
+        let mut query = versions::table
+            .inner_join(crates::table)
+            .left_outer_join(readme_renderings::table)
+            .filter(versions::id.gt(self.last_version_id))
+            .filter(
+                readme_renderings::rendered_at
+                    .lt(self.rendered_before)
+                    .or(readme_renderings::version_id.is_null()),
+            )
+            .select((versions::id, crates::name, versions::num))
+            .order(versions::id)
+            .limit(self.batch_size.into())
+            .into_boxed();
+        if let Some(crate_name) = &self.crate_name {
+            query = query.filter(crates::name.eq(crate_name));
+        }
+        query.load(conn)
+    }
+
+    /// Records the outcome of a batch, and whether it was the last one
+    pub fn record_batch(
+        &self,
+        conn: &PgConnection,
+        last_version_id: i32,
+        processed: i32,
+        failed: i32,
+        complete: bool,
+    ) -> QueryResult<Self> {
+        let rerender: Self = diesel::update(self)
+            .set((
+                readme_rerenders::last_version_id.eq(last_version_id),
+                readme_rerenders::processed_versions
+                    .eq(readme_rerenders::processed_versions + processed),
+                readme_rerenders::failed_versions.eq(readme_rerenders::failed_versions + failed),
+            ))
+            .get_result(conn)?;
+
+        if !complete {
+            return Ok(rerender);
+        }
+        diesel::update(&rerender)
+            .set(readme_rerenders::completed_at.eq(now.nullable()))
+            .get_result(conn)
+    }
+
+    /// Stops the re-render after the batch that is currently running
+    pub fn cancel(&self, conn: &PgConnection) -> QueryResult<Self> {
+        diesel::update(self)
+            .set(readme_rerenders::cancelled_at.eq(now.nullable()))
+            .get_result(conn)
+    }
+}
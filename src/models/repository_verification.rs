@@ -0,0 +1,127 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::pg::upsert::excluded;
+use diesel::prelude::*;
+
+use crate::models::Crate;
+use crate::schema::repository_verifications;
+
+/// A verification that the owners of a crate control the repository it
+/// links to, see [`crate::repository_verification`].
+///
+/// Each crate has at most one verification, which is replaced when an owner
+/// starts verifying another repository.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(Crate)]
+#[primary_key(crate_id)]
+pub struct RepositoryVerification {
+    pub crate_id: i32,
+    /// The repository URL of the crate when the verification was started
+    pub repository: String,
+    /// The token that proves control of repositories verified with a file
+    pub token: String,
+    /// The owner whose access to the repository is checked
+    pub requested_by: i32,
+    pub created_at: NaiveDateTime,
+    pub verified_at: Option<NaiveDateTime>,
+    /// The last time a check had a result
+    pub last_checked_at: Option<NaiveDateTime>,
+}
+
+impl RepositoryVerification {
+    pub fn for_crate(conn: &PgConnection, crate_id: i32) -> QueryResult<Option<Self>> {
+        repository_verifications::table
+            .find(crate_id)
+            .first(conn)
+            .optional()
+    }
+
+    /// Starts verifying `repository`, replacing any previous verification of
+    /// the crate with a new, unverified one
+    pub fn start(
+        conn: &PgConnection,
+        crate_id: i32,
+        repository: &str,
+        requested_by: i32,
+    ) -> QueryResult<Self> {
+        diesel::insert_into(repository_verifications::table)
+            .values((
+                repository_verifications::crate_id.eq(crate_id),
+                repository_verifications::repository.eq(repository),
+                repository_verifications::requested_by.eq(requested_by),
+            ))
+            .on_conflict(repository_verifications::crate_id)
+            .do_update()
+            .set((
+                repository_verifications::repository
+                    .eq(excluded(repository_verifications::repository)),
+                repository_verifications::token.eq(excluded(repository_verifications::token)),
+                repository_verifications::requested_by
+                    .eq(excluded(repository_verifications::requested_by)),
+                repository_verifications::created_at.eq(now),
+                repository_verifications::verified_at.eq(None::<NaiveDateTime>),
+                repository_verifications::last_checked_at.eq(None::<NaiveDateTime>),
+            ))
+            .get_result(conn)
+    }
+
+    /// Stores the result of a check done on behalf of `checked_by`, who
+    /// becomes the owner whose access is re-checked if it succeeded
+    pub fn record_check(
+        &self,
+        conn: &PgConnection,
+        verified: bool,
+        checked_by: i32,
+    ) -> QueryResult<Self> {
+        let checked_at = Utc::now().naive_utc();
+        let (verified_at, requested_by) = if verified {
+            (Some(self.verified_at.unwrap_or(checked_at)), checked_by)
+        } else {
+            (None, self.requested_by)
+        };
+
+        diesel::update(self)
+            .set((
+                repository_verifications::verified_at.eq(verified_at),
+                repository_verifications::requested_by.eq(requested_by),
+                repository_verifications::last_checked_at.eq(checked_at),
+            ))
+            .get_result(conn)
+    }
+
+    /// Clears a verification without a check, like when the crate no longer
+    /// links to the repository
+    pub fn revoke(&self, conn: &PgConnection) -> QueryResult<Self> {
+        diesel::update(self)
+            .set(repository_verifications::verified_at.eq(None::<NaiveDateTime>))
+            .get_result(conn)
+    }
+
+    /// Verified repositories that weren't checked since `checked_before`,
+    /// the ones checked the longest time ago first
+    pub fn due_for_recheck(
+        conn: &PgConnection,
+        checked_before: NaiveDateTime,
+        limit: i64,
+    ) -> QueryResult<Vec<Self>> {
+        repository_verifications::table
+            .filter(repository_verifications::verified_at.is_not_null())
+            .filter(
+                repository_verifications::last_checked_at
+                    .lt(checked_before)
+                    .or(repository_verifications::last_checked_at.is_null()),
+            )
+            .order(
+                repository_verifications::last_checked_at
+                    .asc()
+                    .nulls_first(),
+            )
+            .limit(limit)
+            .load(conn)
+    }
+
+    /// Whether the crate links to the verified repository
+    pub fn is_verified_for(&self, repository: Option<&str>) -> bool {
+        self.verified_at.is_some() && repository == Some(self.repository.as_str())
+    }
+}
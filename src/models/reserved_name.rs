@@ -0,0 +1,71 @@
+use diesel::dsl::exists;
+use diesel::prelude::*;
+use diesel::select;
+
+use crate::models::krate::canon_crate_name;
+use crate::models::Crate;
+use crate::schema::reserved_crate_names;
+
+/// A crate name, or a pattern of crate names, that cannot be used for new
+/// crates.
+///
+/// A `*` in the name matches any sequence of characters, so `rustc_*`
+/// reserves every name starting with `rustc_` (or `rustc-`). Wildcard
+/// reservations only apply to crates created after they were added.
+#[derive(Debug, Clone, Queryable)]
+pub struct ReservedCrateName {
+    pub name: String,
+}
+
+impl ReservedCrateName {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        reserved_crate_names::table
+            .order(reserved_crate_names::name)
+            .load(conn)
+    }
+
+    /// Returns whether a new crate with the given name would be rejected.
+    pub fn matches(conn: &PgConnection, crate_name: &str) -> QueryResult<bool> {
+        select(exists(
+            reserved_crate_names::table.filter(
+                canon_crate_name(crate_name)
+                    .like(reserved_crate_name_pattern(reserved_crate_names::name)),
+            ),
+        ))
+        .get_result(conn)
+    }
+
+    /// Returns whether the string can be stored as a reservation.
+    ///
+    /// Replacing the wildcards must yield a valid crate name, and a pattern
+    /// consisting only of wildcards is rejected.
+    pub fn valid(name: &str) -> bool {
+        name.chars().any(|c| c != '*') && Crate::valid_name(&name.replace('*', "a"))
+    }
+
+    pub fn is_wildcard(&self) -> bool {
+        self.name.contains('*')
+    }
+
+    /// Adds the reservation, returning `false` if an equivalent one exists.
+    pub fn reserve(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        diesel::insert_into(reserved_crate_names::table)
+            .values(reserved_crate_names::name.eq(name))
+            .on_conflict_do_nothing()
+            .execute(conn)
+            .map(|rows| rows > 0)
+    }
+
+    /// Removes the reservation, returning `false` if there was none.
+    pub fn release(conn: &PgConnection, name: &str) -> QueryResult<bool> {
+        diesel::delete(
+            reserved_crate_names::table
+                .filter(canon_crate_name(reserved_crate_names::name).eq(canon_crate_name(name))),
+        )
+        .execute(conn)
+        .map(|rows| rows > 0)
+    }
+}
+
+use diesel::sql_types::Text;
+sql_function!(fn reserved_crate_name_pattern(x: Text) -> Text);
@@ -0,0 +1,138 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::{
+    deserialize::{self, FromSql},
+    pg::Pg,
+    serialize::{self, Output, ToSql},
+    sql_types::Integer,
+};
+use std::io::Write;
+
+use crate::models::{User, Version};
+use crate::schema::storage_mismatches;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromSqlRow, AsExpression)]
+#[repr(i32)]
+#[sql_type = "Integer"]
+pub enum StorageMismatchKind {
+    /// The crate file of the version is not in storage
+    Missing = 0,
+    /// The checksum of the stored file differs from the one in the database
+    Checksum = 1,
+    /// The size of the stored file differs from the one in the database.
+    /// Only checked for versions without a recorded checksum.
+    Size = 2,
+}
+
+impl From<StorageMismatchKind> for &'static str {
+    fn from(kind: StorageMismatchKind) -> Self {
+        match kind {
+            StorageMismatchKind::Missing => "missing",
+            StorageMismatchKind::Checksum => "checksum",
+            StorageMismatchKind::Size => "size",
+        }
+    }
+}
+
+impl From<StorageMismatchKind> for String {
+    fn from(kind: StorageMismatchKind) -> Self {
+        let string: &'static str = kind.into();
+
+        string.into()
+    }
+}
+
+impl FromSql<Integer, Pg> for StorageMismatchKind {
+    fn from_sql(bytes: Option<&[u8]>) -> deserialize::Result<Self> {
+        match <i32 as FromSql<Integer, Pg>>::from_sql(bytes)? {
+            0 => Ok(StorageMismatchKind::Missing),
+            1 => Ok(StorageMismatchKind::Checksum),
+            2 => Ok(StorageMismatchKind::Size),
+            n => Err(format!("unknown storage mismatch kind: {}", n).into()),
+        }
+    }
+}
+
+impl ToSql<Integer, Pg> for StorageMismatchKind {
+    fn to_sql<W: Write>(&self, out: &mut Output<'_, W, Pg>) -> serialize::Result {
+        ToSql::<Integer, Pg>::to_sql(&(*self as i32), out)
+    }
+}
+
+/// A version whose crate file in storage doesn't match the database, as
+/// found by the `VerifyStorage` background job.
+///
+/// A version has at most one unresolved mismatch. It is resolved by an
+/// admin once the file has been repaired, or automatically when the file
+/// passes a later verification.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[belongs_to(User, foreign_key = "resolved_by")]
+#[table_name = "storage_mismatches"]
+pub struct StorageMismatch {
+    pub id: i32,
+    pub version_id: i32,
+    pub kind: StorageMismatchKind,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    pub detected_at: NaiveDateTime,
+    pub resolved_by: Option<i32>,
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Insertable)]
+#[table_name = "storage_mismatches"]
+pub struct NewStorageMismatch<'a> {
+    pub version_id: i32,
+    pub kind: StorageMismatchKind,
+    pub expected: Option<&'a str>,
+    pub actual: Option<&'a str>,
+}
+
+impl NewStorageMismatch<'_> {
+    /// Records the mismatch, unless the version already has an unresolved one
+    pub fn create(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(storage_mismatches::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+}
+
+impl StorageMismatch {
+    pub fn find(conn: &PgConnection, id: i32) -> QueryResult<Self> {
+        storage_mismatches::table.find(id).first(conn)
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.resolved_at.is_some()
+    }
+
+    /// Marks the mismatch as resolved by an admin
+    pub fn resolve(&self, conn: &PgConnection, admin_id: i32) -> QueryResult<Self> {
+        use diesel::dsl::now;
+
+        diesel::update(self)
+            .set((
+                storage_mismatches::resolved_by.eq(admin_id),
+                storage_mismatches::resolved_at.eq(now.nullable()),
+            ))
+            .get_result(conn)
+    }
+
+    /// Resolves the unresolved mismatch of a version whose file has passed
+    /// verification, returning whether there was one
+    pub fn resolve_verified(conn: &PgConnection, version_id: i32) -> QueryResult<bool> {
+        use diesel::dsl::now;
+
+        let updated = diesel::update(
+            storage_mismatches::table
+                .filter(storage_mismatches::version_id.eq(version_id))
+                .filter(storage_mismatches::resolved_at.is_null()),
+        )
+        .set(storage_mismatches::resolved_at.eq(now.nullable()))
+        .execute(conn)?;
+        Ok(updated > 0)
+    }
+}
@@ -24,6 +24,8 @@ pub struct ApiToken {
     pub last_used_at: Option<NaiveDateTime>,
     #[serde(skip)]
     pub revoked: bool,
+    #[serde(skip)]
+    kdf_iterations: Option<i32>,
 }
 
 impl ApiToken {
@@ -35,7 +37,8 @@ impl ApiToken {
             .values((
                 api_tokens::user_id.eq(user_id),
                 api_tokens::name.eq(name),
-                api_tokens::token.eq(token.sha256()),
+                api_tokens::token.eq(&token.hash().hash),
+                api_tokens::kdf_iterations.eq(token.hash().kdf_iterations),
             ))
             .get_result(conn)?;
 
@@ -45,25 +48,46 @@ impl ApiToken {
         })
     }
 
+    /// Finds the token and records that it was used. Tokens that are stored
+    /// with a hash from previous settings are re-hashed with the current ones.
     pub fn find_by_api_token(conn: &PgConnection, token_: &str) -> AppResult<ApiToken> {
         use crate::schema::api_tokens::dsl::*;
-        use diesel::{dsl::now, update};
+        use diesel::{
+            dsl::{any, now},
+            update,
+        };
 
         let token_ = SecureToken::parse(SecureTokenKind::Api, token_)
             .ok_or_else(InsecurelyGeneratedTokenRevoked::boxed)?;
+        let candidates: Vec<Vec<u8>> = token_
+            .candidates()
+            .iter()
+            .map(|candidate| candidate.hash.clone())
+            .collect();
 
         let tokens = api_tokens
             .filter(revoked.eq(false))
-            .filter(token.eq(token_.sha256()));
+            .filter(token.eq(any(candidates)));
 
         // If the database is in read only mode, we can't update last_used_at.
         // Try updating in a new transaction, if that fails, fall back to reading
         conn.transaction(|| {
-            update(tokens)
+            let model: ApiToken = update(tokens.clone())
                 .set(last_used_at.eq(now.nullable()))
+                .get_result(conn)?;
+
+            let current = token_.hash();
+            if model.kdf_iterations == current.kdf_iterations {
+                return Ok(model);
+            }
+            update(&model)
+                .set((
+                    token.eq(&current.hash),
+                    kdf_iterations.eq(current.kdf_iterations),
+                ))
                 .get_result(conn)
         })
-        .or_else(|_| tokens.first(conn))
+        .or_else(|_: diesel::result::Error| tokens.first(conn))
         .map_err(Into::into)
     }
 }
@@ -99,6 +123,7 @@ mod tests {
             name: "".to_string(),
             created_at: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 11),
             last_used_at: Some(NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12)),
+            kdf_iterations: None,
         };
         let json = serde_json::to_string(&tok).unwrap();
         assert_some!(json
@@ -1,12 +1,27 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDateTime, Utc};
 use diesel::prelude::*;
 use std::borrow::Cow;
 
 use crate::app::App;
-use crate::util::errors::AppResult;
+use crate::util::errors::{account_locked, AppResult};
 
-use crate::models::{ApiToken, Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, Rights};
-use crate::schema::{crate_owners, emails, users};
+use crate::models::{
+    ApiToken, Crate, CrateOwner, Email, NewEmail, Owner, OwnerKind, Rights, UserSession,
+};
+use crate::schema::{
+    api_tokens, crate_owner_invitations, crate_owners, crates, data_exports, emails, follows,
+    mutes, user_follows, user_sessions, username_history, users, versions, versions_published_by,
+};
+
+// `lower` for the nullable `username` column
+sql_function! {
+    #[sql_name = "lower"]
+    fn lower_nullable(x: diesel::sql_types::Nullable<diesel::sql_types::Text>)
+        -> diesel::sql_types::Nullable<diesel::sql_types::Text>;
+}
+
+/// The lock reason of accounts that have been deleted through `User::anonymize`.
+pub const DELETED_ACCOUNT_REASON: &str = "This account has been deleted";
 
 /// The model representing a row in the `users` database table.
 #[derive(Clone, Debug, PartialEq, Eq, Queryable, Identifiable, AsChangeset, Associations)]
@@ -19,6 +34,42 @@ pub struct User {
     pub gh_id: i32,
     pub account_lock_reason: Option<String>,
     pub account_lock_until: Option<NaiveDateTime>,
+    pub is_admin: bool,
+    pub created_at: Option<NaiveDateTime>,
+    /// Whether other users can follow the user to see their new releases
+    pub followable: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub location: Option<String>,
+    /// The login on crates.io, if the user changed it from their GitHub login
+    pub username: Option<String>,
+}
+
+/// Changes to the profile fields that users edit themselves, in addition to
+/// the data mirrored from GitHub. Fields that are `None` are left unchanged,
+/// and `Some(None)` clears a field.
+#[derive(Debug, Default, Clone, PartialEq, Eq, AsChangeset)]
+#[table_name = "users"]
+pub struct ProfileChanges {
+    pub display_name: Option<Option<String>>,
+    pub bio: Option<Option<String>>,
+    pub website: Option<Option<String>>,
+    pub location: Option<Option<String>>,
+}
+
+impl ProfileChanges {
+    pub const MAX_DISPLAY_NAME_LENGTH: usize = 64;
+    pub const MAX_BIO_LENGTH: usize = 300;
+    pub const MAX_WEBSITE_LENGTH: usize = 200;
+    pub const MAX_LOCATION_LENGTH: usize = 100;
+
+    pub fn is_empty(&self) -> bool {
+        self.display_name.is_none()
+            && self.bio.is_none()
+            && self.website.is_none()
+            && self.location.is_none()
+    }
 }
 
 /// Represents a new user record insertable to the `users` table
@@ -97,7 +148,7 @@ impl<'a> NewUser<'a> {
                     .optional()?;
 
                 if let Some(token) = token {
-                    crate::email::send_user_confirm_email(user_email, &user.gh_login, &token);
+                    crate::email::send_user_confirm_email(conn, user_email, &user.gh_login, &token);
                 }
             }
 
@@ -111,6 +162,53 @@ impl User {
         users::table.find(id).first(conn)
     }
 
+    /// Whether the login is a username chosen on crates.io rather than a
+    /// GitHub login. Usernames contain an underscore, which GitHub logins
+    /// can't, so a username never shadows the GitHub login of another user,
+    /// including users that sign up after the username was taken.
+    pub fn is_username(login: &str) -> bool {
+        login.contains('_')
+    }
+
+    /// Finds a user by their username or their GitHub login, ignoring case.
+    /// Users that changed their username can still be found by their GitHub
+    /// login.
+    pub fn find_by_login(conn: &PgConnection, login: &str) -> QueryResult<User> {
+        let login = login.to_lowercase();
+        if Self::is_username(&login) {
+            users::table
+                .filter(lower_nullable(users::username).eq(&login))
+                .first(conn)
+        } else {
+            users::table
+                .filter(crate::lower(users::gh_login).eq(&login))
+                .order(users::id.desc())
+                .first(conn)
+        }
+    }
+
+    /// Whether another user currently has the username, ignoring case
+    pub fn is_username_taken(
+        conn: &PgConnection,
+        username: &str,
+        user_id: i32,
+    ) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            users::table
+                .filter(users::id.ne(user_id))
+                .filter(lower_nullable(users::username).eq(username.to_lowercase())),
+        ))
+        .get_result(conn)
+    }
+
+    /// The login shown on crates.io, which is the GitHub login unless the
+    /// user changed their username.
+    pub fn login(&self) -> &str {
+        self.username.as_deref().unwrap_or(&self.gh_login)
+    }
+
     /// Queries the database for a user with a certain `api_token` value.
     pub fn find_by_api_token(conn: &PgConnection, token: &str) -> AppResult<User> {
         let api_token = ApiToken::find_by_api_token(conn, token)?;
@@ -157,6 +255,166 @@ impl User {
         Ok(best)
     }
 
+    /// Returns an error if the account is currently locked.
+    ///
+    /// Locks without an expiry date never end, which is how accounts are banned.
+    pub fn check_account_lock(&self) -> AppResult<()> {
+        if let Some(reason) = &self.account_lock_reason {
+            let still_locked = if let Some(until) = self.account_lock_until {
+                until > Utc::now().naive_utc()
+            } else {
+                true
+            };
+            if still_locked {
+                return Err(account_locked(reason, self.account_lock_until));
+            }
+        }
+        Ok(())
+    }
+
+    /// Locks the account, blocking login, publishing and the use of API tokens.
+    ///
+    /// The sessions of the user are revoked, so the browsers that were logged
+    /// in stay logged out once the lock is lifted or expires.
+    pub fn lock(
+        &self,
+        conn: &PgConnection,
+        reason: &str,
+        until: Option<NaiveDateTime>,
+    ) -> QueryResult<User> {
+        conn.transaction(|| {
+            UserSession::revoke_all(conn, self.id)?;
+            diesel::update(self)
+                .set((
+                    users::account_lock_reason.eq(reason),
+                    users::account_lock_until.eq(until),
+                ))
+                .get_result(conn)
+        })
+    }
+
+    pub fn unlock(&self, conn: &PgConnection) -> QueryResult<User> {
+        diesel::update(self)
+            .set((
+                users::account_lock_reason.eq(None::<String>),
+                users::account_lock_until.eq(None::<NaiveDateTime>),
+            ))
+            .get_result(conn)
+    }
+
+    /// Returns the names of the crates that have no owner besides this user.
+    pub fn sole_owned_crates(&self, conn: &PgConnection) -> QueryResult<Vec<String>> {
+        let owned: Vec<(i32, String)> = CrateOwner::by_owner_kind(OwnerKind::User)
+            .filter(crate_owners::owner_id.eq(self.id))
+            .inner_join(crates::table)
+            .select((crates::id, crates::name))
+            .order(crates::name)
+            .load(conn)?;
+
+        let crate_ids = owned.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+        let co_owned: Vec<i32> = crate_owners::table
+            .filter(crate_owners::crate_id.eq_any(&crate_ids))
+            .filter(crate_owners::deleted.eq(false))
+            .filter(
+                crate_owners::owner_id
+                    .ne(self.id)
+                    .or(crate_owners::owner_kind.ne(OwnerKind::User as i32)),
+            )
+            .select(crate_owners::crate_id)
+            .load(conn)?;
+
+        Ok(owned
+            .into_iter()
+            .filter(|(id, _)| !co_owned.contains(id))
+            .map(|(_, name)| name)
+            .collect())
+    }
+
+    /// Deletes the personal data of the account.
+    ///
+    /// The `users` row itself is kept, with its GitHub identity and profile
+    /// information removed, so that `published_by` and the version audit log
+    /// still point at a (deleted) account. Crates are not deleted; the user is
+    /// only removed from their owners. The account is locked permanently, and
+    /// signing in with the same GitHub account afterwards creates a new user.
+    pub fn anonymize(&self, conn: &PgConnection) -> QueryResult<User> {
+        conn.transaction(|| {
+            diesel::delete(emails::table.filter(emails::user_id.eq(self.id))).execute(conn)?;
+            diesel::delete(follows::table.filter(follows::user_id.eq(self.id))).execute(conn)?;
+            diesel::delete(
+                user_follows::table.filter(
+                    user_follows::follower_id
+                        .eq(self.id)
+                        .or(user_follows::followed_id.eq(self.id)),
+                ),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                mutes::table.filter(
+                    mutes::user_id
+                        .eq(self.id)
+                        .or(mutes::muted_user_id.eq(self.id)),
+                ),
+            )
+            .execute(conn)?;
+            diesel::delete(data_exports::table.filter(data_exports::user_id.eq(self.id)))
+                .execute(conn)?;
+            diesel::delete(username_history::table.filter(username_history::user_id.eq(self.id)))
+                .execute(conn)?;
+            diesel::delete(user_sessions::table.filter(user_sessions::user_id.eq(self.id)))
+                .execute(conn)?;
+            diesel::delete(
+                crate_owner_invitations::table.filter(
+                    crate_owner_invitations::invited_user_id
+                        .eq(self.id)
+                        .or(crate_owner_invitations::invited_by_user_id.eq(self.id)),
+                ),
+            )
+            .execute(conn)?;
+            diesel::delete(
+                versions_published_by::table.filter(
+                    versions_published_by::version_id.eq_any(
+                        versions::table
+                            .filter(versions::published_by.eq(self.id))
+                            .select(versions::id),
+                    ),
+                ),
+            )
+            .execute(conn)?;
+
+            // API tokens are referenced by the version audit log
+            diesel::update(api_tokens::table.filter(api_tokens::user_id.eq(self.id)))
+                .set(api_tokens::revoked.eq(true))
+                .execute(conn)?;
+
+            diesel::update(
+                crate_owners::table
+                    .filter(crate_owners::owner_id.eq(self.id))
+                    .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32)),
+            )
+            .set(crate_owners::deleted.eq(true))
+            .execute(conn)?;
+
+            diesel::update(self)
+                .set((
+                    // `-1` is excluded from the unique index on `gh_id`
+                    users::gh_id.eq(-1),
+                    users::gh_login.eq(format!("deleted-user-{}", self.id)),
+                    users::gh_access_token.eq(""),
+                    users::name.eq(None::<String>),
+                    users::gh_avatar.eq(None::<String>),
+                    users::display_name.eq(None::<String>),
+                    users::bio.eq(None::<String>),
+                    users::website.eq(None::<String>),
+                    users::location.eq(None::<String>),
+                    users::username.eq(None::<String>),
+                    users::account_lock_reason.eq(DELETED_ACCOUNT_REASON),
+                    users::account_lock_until.eq(None::<NaiveDateTime>),
+                ))
+                .get_result(conn)
+        })
+    }
+
     /// Queries the database for the verified emails
     /// belonging to a given user
     pub fn verified_email(&self, conn: &PgConnection) -> QueryResult<Option<String>> {
@@ -0,0 +1,134 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::now;
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::user_sessions;
+use crate::util::token::{SecureToken, SecureTokenKind};
+
+/// How long `last_used_at` of a session may lag behind, so that not every
+/// request made with a cookie writes to the database
+const LAST_USED_AT_PRECISION_MINUTES: i64 = 5;
+
+/// A browser session, created when a user logs in.
+///
+/// The session cookie only holds the token of the session, so sessions can be
+/// listed and revoked by their user, and are deleted with the account.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+pub struct UserSession {
+    pub id: i32,
+    pub user_id: i32,
+    // Nothing should ever access the hash of the token.
+    hashed_token: Vec<u8>,
+    pub user_agent: String,
+    pub created_at: NaiveDateTime,
+    pub last_used_at: NaiveDateTime,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+impl UserSession {
+    /// Starts a new session for the user
+    pub fn create(
+        conn: &PgConnection,
+        user_id: i32,
+        user_agent: &str,
+    ) -> QueryResult<CreatedUserSession> {
+        let token = SecureToken::generate(SecureTokenKind::Session);
+
+        let model = diesel::insert_into(user_sessions::table)
+            .values((
+                user_sessions::user_id.eq(user_id),
+                user_sessions::hashed_token.eq(&token.hash().hash),
+                user_sessions::user_agent.eq(user_agent),
+            ))
+            .get_result(conn)?;
+
+        Ok(CreatedUserSession {
+            plaintext: token.plaintext().into(),
+            model,
+        })
+    }
+
+    /// Finds the session with the token from a cookie, unless it was revoked,
+    /// and records that it was used if it wasn't used in the last
+    /// `LAST_USED_AT_PRECISION_MINUTES`
+    pub fn find_active(conn: &PgConnection, token: &str) -> QueryResult<Option<UserSession>> {
+        let token = match SecureToken::parse(SecureTokenKind::Session, token) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+
+        let session: UserSession = match user_sessions::table
+            .filter(user_sessions::revoked_at.is_null())
+            .filter(user_sessions::hashed_token.eq(&token.hash().hash))
+            .first(conn)
+            .optional()?
+        {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+
+        let precision = Duration::minutes(LAST_USED_AT_PRECISION_MINUTES);
+        if Utc::now().naive_utc() - session.last_used_at < precision {
+            return Ok(Some(session));
+        }
+
+        // If the database is in read only mode, we can't update last_used_at.
+        // Try updating in a new transaction, if that fails, keep the session
+        // as it was read
+        let updated = conn.transaction(|| {
+            diesel::update(&session)
+                .set(user_sessions::last_used_at.eq(now))
+                .get_result(conn)
+        });
+        Ok(Some(updated.unwrap_or(session)))
+    }
+
+    /// The sessions of the user that weren't revoked, most recently used first
+    pub fn active_for_user(conn: &PgConnection, user_id: i32) -> QueryResult<Vec<UserSession>> {
+        user_sessions::table
+            .filter(user_sessions::user_id.eq(user_id))
+            .filter(user_sessions::revoked_at.is_null())
+            .order(user_sessions::last_used_at.desc())
+            .load(conn)
+    }
+
+    /// Revokes a session, which logs the browser that uses it out
+    pub fn revoke(conn: &PgConnection, user_id: i32, id: i32) -> QueryResult<usize> {
+        diesel::update(
+            user_sessions::table
+                .filter(user_sessions::id.eq(id))
+                .filter(user_sessions::user_id.eq(user_id))
+                .filter(user_sessions::revoked_at.is_null()),
+        )
+        .set(user_sessions::revoked_at.eq(now.nullable()))
+        .execute(conn)
+    }
+
+    /// Revokes all sessions of the user
+    pub fn revoke_all(conn: &PgConnection, user_id: i32) -> QueryResult<usize> {
+        diesel::update(
+            user_sessions::table
+                .filter(user_sessions::user_id.eq(user_id))
+                .filter(user_sessions::revoked_at.is_null()),
+        )
+        .set(user_sessions::revoked_at.eq(now.nullable()))
+        .execute(conn)
+    }
+}
+
+pub struct CreatedUserSession {
+    pub model: UserSession,
+    pub plaintext: String,
+}
+
+// Use a custom implementation of Debug to hide the plaintext token.
+impl std::fmt::Debug for CreatedUserSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreatedUserSession")
+            .field("model", &self.model)
+            .field("plaintext", &"(sensitive)")
+            .finish()
+    }
+}
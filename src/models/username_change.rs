@@ -0,0 +1,82 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+use crate::models::User;
+use crate::schema::{username_history, users};
+
+/// A previous login of a user. For a grace period after the change, lookups
+/// by the previous login are redirected to the user, and nobody else can take
+/// the login.
+#[derive(Clone, Debug, PartialEq, Eq, Identifiable, Queryable, Associations)]
+#[belongs_to(User)]
+#[table_name = "username_history"]
+pub struct UsernameChange {
+    pub id: i32,
+    pub user_id: i32,
+    pub username: String,
+    pub changed_at: NaiveDateTime,
+}
+
+impl UsernameChange {
+    /// How long previous logins redirect to the user
+    pub const GRACE_PERIOD_DAYS: i64 = 90;
+
+    /// How long users have to wait before changing their username again
+    pub const DAYS_BETWEEN_CHANGES: i64 = 30;
+
+    fn grace_period_start() -> NaiveDateTime {
+        Utc::now().naive_utc() - Duration::days(Self::GRACE_PERIOD_DAYS)
+    }
+
+    /// Finds the user that used the login until recently
+    pub fn find_renamed_user(conn: &PgConnection, login: &str) -> QueryResult<Option<User>> {
+        username_history::table
+            .inner_join(users::table)
+            .filter(crate::lower(username_history::username).eq(login.to_lowercase()))
+            .filter(username_history::changed_at.gt(Self::grace_period_start()))
+            .order(username_history::changed_at.desc())
+            .select(users::all_columns)
+            .first(conn)
+            .optional()
+    }
+
+    /// Whether the login is a previous login of another user that is still
+    /// in its grace period
+    pub fn is_reserved(conn: &PgConnection, login: &str, user_id: i32) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+
+        diesel::select(exists(
+            username_history::table
+                .filter(crate::lower(username_history::username).eq(login.to_lowercase()))
+                .filter(username_history::user_id.ne(user_id))
+                .filter(username_history::changed_at.gt(Self::grace_period_start())),
+        ))
+        .get_result(conn)
+    }
+
+    /// Changes the login of the user, and records the current one so that it
+    /// keeps pointing to the user for the grace period
+    pub fn change(conn: &PgConnection, user: &User, username: &str) -> QueryResult<User> {
+        conn.transaction(|| {
+            diesel::insert_into(username_history::table)
+                .values((
+                    username_history::user_id.eq(user.id),
+                    username_history::username.eq(user.login()),
+                ))
+                .execute(conn)?;
+            diesel::update(user)
+                .set(users::username.eq(username))
+                .get_result(conn)
+        })
+    }
+
+    /// When the user last changed their username, if they ever did
+    pub fn last_change(conn: &PgConnection, user_id: i32) -> QueryResult<Option<NaiveDateTime>> {
+        use diesel::dsl::max;
+
+        username_history::table
+            .filter(username_history::user_id.eq(user_id))
+            .select(max(username_history::changed_at))
+            .get_result(conn)
+    }
+}
@@ -0,0 +1,35 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+
+use crate::schema::verified_publishers;
+
+/// A user whose publishes an admin vouched for, so they are never held out of
+/// the index by [`crate::first_publish_hold`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Identifiable, Queryable)]
+#[primary_key(user_id)]
+pub struct VerifiedPublisher {
+    pub user_id: i32,
+    pub verified_by: i32,
+    pub verified_at: NaiveDateTime,
+}
+
+impl VerifiedPublisher {
+    /// Marks the user as verified. Verifying a user twice keeps the first
+    /// verification.
+    pub fn verify(conn: &PgConnection, user_id: i32, verified_by: i32) -> QueryResult<Self> {
+        diesel::insert_into(verified_publishers::table)
+            .values((
+                verified_publishers::user_id.eq(user_id),
+                verified_publishers::verified_by.eq(verified_by),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        verified_publishers::table.find(user_id).first(conn)
+    }
+
+    pub fn is_verified(conn: &PgConnection, user_id: i32) -> QueryResult<bool> {
+        use diesel::dsl::exists;
+
+        diesel::select(exists(verified_publishers::table.find(user_id))).get_result(conn)
+    }
+}
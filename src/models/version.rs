@@ -3,7 +3,8 @@ use std::collections::HashMap;
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 
-use crate::util::errors::{cargo_err, AppResult};
+use crate::license::{self, InvalidLicensePolicy};
+use crate::util::errors::{cargo_err, cargo_err_with_code, AppResult, ErrorCode};
 
 use crate::models::{Crate, Dependency, User};
 use crate::schema::*;
@@ -23,6 +24,15 @@ pub struct Version {
     pub license: Option<String>,
     pub crate_size: Option<i32>,
     pub published_by: Option<i32>,
+    /// The hex encoded SHA256 checksum of the crate file, which is missing
+    /// for versions published before it was recorded
+    pub checksum: Option<String>,
+    /// Whether the crate file is stored by its checksum, see
+    /// `Uploader::crate_location_by_checksum`
+    pub stored_by_checksum: bool,
+    /// The `rust-version` of the manifest, for versions published with a
+    /// Cargo recent enough to send it
+    pub rust_version: Option<String>,
 }
 
 #[derive(Insertable, Debug)]
@@ -100,6 +110,48 @@ impl Version {
             .execute(conn)
     }
 
+    /// Records the path of the README whose source was stored with the
+    /// rendering, `None` if the version has no README.
+    pub fn record_readme_source(
+        version_id_: i32,
+        file_name: Option<&str>,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        use crate::schema::readme_renderings::dsl::*;
+
+        diesel::update(readme_renderings.find(version_id_))
+            .set(source_file_name.eq(file_name))
+            .execute(conn)
+    }
+
+    /// Records how many bytes the rendered HTML and the source of the README
+    /// of a version take up in storage, `None` if the version has no README.
+    pub fn record_readme_sizes(
+        version_id_: i32,
+        sizes: Option<(i32, i32)>,
+        conn: &PgConnection,
+    ) -> QueryResult<usize> {
+        use crate::schema::readme_renderings::dsl::*;
+
+        diesel::update(readme_renderings.find(version_id_))
+            .set((
+                rendered_size.eq(sizes.map(|(rendered, _)| rendered)),
+                source_size.eq(sizes.map(|(_, source)| source)),
+            ))
+            .execute(conn)
+    }
+
+    /// The path of the README in the crate file, if its source is stored.
+    /// Returns `NotFound` if the README of the version was never rendered.
+    pub fn readme_source_file_name(&self, conn: &PgConnection) -> QueryResult<Option<String>> {
+        use crate::schema::readme_renderings::dsl::*;
+
+        readme_renderings
+            .find(self.id)
+            .select(source_file_name)
+            .first(conn)
+    }
+
     /// Gets the User who ran `cargo publish` for this version, if recorded.
     /// Not for use when you have a group of versions you need the publishers for.
     pub fn published_by(&self, conn: &PgConnection) -> Option<User> {
@@ -117,6 +169,7 @@ impl NewVersion {
         features: &HashMap<String, Vec<String>>,
         license: Option<String>,
         license_file: Option<&str>,
+        invalid_license_policy: InvalidLicensePolicy,
         crate_size: i32,
         published_by: i32,
     ) -> AppResult<Self> {
@@ -131,7 +184,7 @@ impl NewVersion {
             published_by,
         };
 
-        new_version.validate_license(license_file)?;
+        new_version.validate_license(license_file, invalid_license_policy)?;
 
         Ok(new_version)
     }
@@ -152,11 +205,10 @@ impl NewVersion {
                 .filter(crate_id.eq(self.crate_id))
                 .filter(num.eq(&self.num));
             if select(exists(already_uploaded)).get_result(conn)? {
-                return Err(cargo_err(&format_args!(
-                    "crate version `{}` is already \
-                     uploaded",
-                    self.num
-                )));
+                return Err(cargo_err_with_code(
+                    ErrorCode::VersionExists,
+                    &format_args!("crate version `{}` is already uploaded", self.num),
+                ));
             }
 
             let version: Version = insert_into(versions).values(self).get_result(conn)?;
@@ -180,17 +232,22 @@ impl NewVersion {
         })
     }
 
-    fn validate_license(&mut self, license_file: Option<&str>) -> AppResult<()> {
+    fn validate_license(
+        &mut self,
+        license_file: Option<&str>,
+        invalid_license_policy: InvalidLicensePolicy,
+    ) -> AppResult<()> {
         if let Some(ref license) = self.license {
-            for part in license.split('/') {
-                license_exprs::validate_license_expr(part).map_err(|e| {
-                    cargo_err(&format_args!(
+            match license::normalize(license) {
+                Err(e) if invalid_license_policy == InvalidLicensePolicy::Reject => {
+                    return Err(cargo_err(&format_args!(
                         "{}; see http://opensource.org/licenses \
                          for options, and http://spdx.org/licenses/ \
                          for their identifiers",
                         e
-                    ))
-                })?;
+                    )));
+                }
+                _ => {}
             }
         } else if license_file.is_some() {
             // If no license is given, but a license file is given, flag this
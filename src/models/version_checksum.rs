@@ -0,0 +1,115 @@
+//! Checksums of crate files with more than one hash algorithm
+//!
+//! The SHA256 checksum in `versions.checksum` is what cargo verifies, and
+//! what files are stored by. Every version also has a checksum for each of
+//! the `ChecksumAlgorithm`s in the `version_checksums` table, so that
+//! clients can move to a newer algorithm while SHA256 keeps working. New
+//! versions get all of them when they are published, and the
+//! `backfill_checksums` job adds the ones that older versions are missing.
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use hex::ToHex;
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::models::Version;
+use crate::schema::version_checksums;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChecksumAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl ChecksumAlgorithm {
+    /// The algorithms every version has a checksum for
+    pub const ALL: &'static [ChecksumAlgorithm] =
+        &[ChecksumAlgorithm::Sha256, ChecksumAlgorithm::Sha512];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChecksumAlgorithm::Sha256 => "sha256",
+            ChecksumAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    /// The hex encoded checksum of the crate file
+    pub fn digest(self, tarball: &[u8]) -> String {
+        match self {
+            ChecksumAlgorithm::Sha256 => Sha256::digest(tarball).encode_hex(),
+            ChecksumAlgorithm::Sha512 => Sha512::digest(tarball).encode_hex(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Queryable, Identifiable, Associations)]
+#[belongs_to(Version)]
+#[primary_key(version_id, algorithm)]
+#[table_name = "version_checksums"]
+pub struct VersionChecksum {
+    pub version_id: i32,
+    pub algorithm: String,
+    pub digest: String,
+    pub created_at: NaiveDateTime,
+}
+
+impl VersionChecksum {
+    /// Records the checksums of the crate file of the version with all
+    /// algorithms. Checksums that were already recorded are kept.
+    pub fn record(conn: &PgConnection, version_id: i32, tarball: &[u8]) -> QueryResult<()> {
+        let checksums = ChecksumAlgorithm::ALL
+            .iter()
+            .map(|algorithm| {
+                (
+                    version_checksums::version_id.eq(version_id),
+                    version_checksums::algorithm.eq(algorithm.as_str()),
+                    version_checksums::digest.eq(algorithm.digest(tarball)),
+                )
+            })
+            .collect::<Vec<_>>();
+        diesel::insert_into(version_checksums::table)
+            .values(&checksums)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// The checksums of the versions, by algorithm
+    pub fn for_versions(
+        conn: &PgConnection,
+        version_ids: &[i32],
+    ) -> QueryResult<HashMap<i32, BTreeMap<String, String>>> {
+        let checksums: Vec<(i32, String, String)> = version_checksums::table
+            .filter(version_checksums::version_id.eq_any(version_ids))
+            .select((
+                version_checksums::version_id,
+                version_checksums::algorithm,
+                version_checksums::digest,
+            ))
+            .load(conn)?;
+
+        let mut by_version = HashMap::<i32, BTreeMap<String, String>>::new();
+        for (version_id, algorithm, digest) in checksums {
+            by_version
+                .entry(version_id)
+                .or_default()
+                .insert(algorithm, digest);
+        }
+        Ok(by_version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digests_are_hex_encoded() {
+        assert_eq!(
+            ChecksumAlgorithm::Sha256.digest(b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(ChecksumAlgorithm::Sha512.digest(b"").len(), 128);
+    }
+}
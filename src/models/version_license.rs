@@ -0,0 +1,102 @@
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable, Text};
+
+use crate::license::{self, NormalizedLicense};
+use crate::models::Version;
+use crate::schema::{version_licenses, versions};
+
+/// The normalized license expression of a version, see the `license`
+/// module.
+///
+/// Versions without a license, with a `license_file` only or with an
+/// expression that isn't valid SPDX have none.
+#[derive(Debug, Clone, Queryable, Identifiable, Associations, Insertable)]
+#[belongs_to(Version)]
+#[primary_key(version_id)]
+#[table_name = "version_licenses"]
+pub struct VersionLicense {
+    pub version_id: i32,
+    pub expression: String,
+    pub licenses: Vec<String>,
+    pub exceptions: Vec<String>,
+}
+
+/// How many crates use a license, or a license expression
+#[derive(Debug, Clone, QueryableByName)]
+pub struct LicenseUsage {
+    /// `None` for the crates without a normalized license
+    #[sql_type = "Nullable<Text>"]
+    pub license: Option<String>,
+    #[sql_type = "BigInt"]
+    pub crates: i64,
+}
+
+impl VersionLicense {
+    pub fn new(version_id: i32, license: NormalizedLicense) -> Self {
+        Self {
+            version_id,
+            expression: license.expression,
+            licenses: license.licenses,
+            exceptions: license.exceptions,
+        }
+    }
+
+    pub fn insert(&self, conn: &PgConnection) -> QueryResult<()> {
+        diesel::insert_into(version_licenses::table)
+            .values(self)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(())
+    }
+
+    /// Normalizes the licenses of up to `limit` versions published before
+    /// licenses were normalized, starting after the version `after`.
+    ///
+    /// Returns the id of the last version that was looked at, and how many
+    /// of the licenses were valid.
+    pub fn normalize_existing(
+        conn: &PgConnection,
+        after: i32,
+        limit: i64,
+    ) -> QueryResult<Option<(i32, usize)>> {
+        let pending: Vec<(i32, Option<String>)> = versions::table
+            .left_join(version_licenses::table)
+            .filter(version_licenses::version_id.nullable().is_null())
+            .filter(versions::id.gt(after))
+            .filter(versions::license.is_not_null())
+            .select((versions::id, versions::license))
+            .order(versions::id)
+            .limit(limit)
+            .load(conn)?;
+
+        let last = match pending.last() {
+            Some((id, _)) => *id,
+            None => return Ok(None),
+        };
+        let normalized: Vec<VersionLicense> = pending
+            .into_iter()
+            .filter_map(|(id, expression)| {
+                let license = license::normalize(&expression?).ok()?;
+                Some(VersionLicense::new(id, license))
+            })
+            .collect();
+        diesel::insert_into(version_licenses::table)
+            .values(&normalized)
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+        Ok(Some((last, normalized.len())))
+    }
+
+    /// How many crates use each license in the newest version that isn't
+    /// yanked, most used first
+    pub fn license_usage(conn: &PgConnection) -> QueryResult<Vec<LicenseUsage>> {
+        diesel::sql_query(include_str!("license_usage.sql")).load(conn)
+    }
+
+    /// How many crates use each license expression in the newest version
+    /// that isn't yanked, most used first. Crates without a normalized
+    /// license are counted in the entry without an expression.
+    pub fn expression_usage(conn: &PgConnection) -> QueryResult<Vec<LicenseUsage>> {
+        diesel::sql_query(include_str!("license_expression_usage.sql")).load(conn)
+    }
+}
@@ -3,7 +3,7 @@ use diesel::data_types::PgInterval;
 use diesel::prelude::*;
 use std::time::Duration;
 
-use crate::schema::{publish_limit_buckets, publish_rate_overrides};
+use crate::schema::{publish_limit_buckets, publish_rate_limit_rejections, publish_rate_overrides};
 use crate::util::errors::{AppResult, TooManyRequests};
 
 #[derive(Debug, Clone, Copy)]
@@ -70,6 +70,11 @@ impl PublishRateLimit {
 
         let burst: i32 = publish_rate_overrides::table
             .find(uploader)
+            .filter(
+                publish_rate_overrides::expires_at
+                    .is_null()
+                    .or(publish_rate_overrides::expires_at.gt(now)),
+            )
             .select(publish_rate_overrides::burst)
             .first(conn)
             .optional()?
@@ -101,6 +106,17 @@ impl PublishRateLimit {
     }
 }
 
+/// Records that a publish of the user was rejected by the rate limiter.
+///
+/// The rejection happens inside the publish transaction, which is rolled
+/// back, so this has to be called afterwards.
+pub fn record_rejection(uploader: i32, conn: &PgConnection) -> QueryResult<()> {
+    diesel::insert_into(publish_rate_limit_rejections::table)
+        .values(publish_rate_limit_rejections::user_id.eq(uploader))
+        .execute(conn)?;
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -318,6 +334,41 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn expired_override_is_ignored() -> QueryResult<()> {
+        let conn = pg_connection();
+        let now = now();
+
+        let rate = PublishRateLimit {
+            rate: Duration::from_secs(1),
+            burst: 10,
+        };
+        let user_id = new_user(&conn, "user1")?;
+        let other_user_id = new_user(&conn, "user2")?;
+
+        diesel::insert_into(publish_rate_overrides::table)
+            .values(&vec![
+                (
+                    publish_rate_overrides::user_id.eq(user_id),
+                    publish_rate_overrides::burst.eq(20),
+                    publish_rate_overrides::expires_at.eq(now - chrono::Duration::days(1)),
+                ),
+                (
+                    publish_rate_overrides::user_id.eq(other_user_id),
+                    publish_rate_overrides::burst.eq(20),
+                    publish_rate_overrides::expires_at.eq(now + chrono::Duration::days(1)),
+                ),
+            ])
+            .execute(&conn)?;
+
+        let bucket = rate.take_token(user_id, now, &conn)?;
+        let other_bucket = rate.take_token(other_user_id, now, &conn)?;
+
+        assert_eq!(10, bucket.tokens);
+        assert_eq!(20, other_bucket.tokens);
+        Ok(())
+    }
+
     fn new_user(conn: &PgConnection, gh_login: &str) -> QueryResult<i32> {
         use crate::models::NewUser;
 
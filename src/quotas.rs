@@ -0,0 +1,231 @@
+//! Quotas of the storage, versions and crates of every account.
+//!
+//! An account may use up to `STORAGE_QUOTA_BYTES` of storage for the crates
+//! it owns, counted like at `GET /me/storage`, publish up to
+//! `VERSIONS_PER_DAY_QUOTA` versions in 24 hours and own up to
+//! `CRATES_QUOTA` crates. Quotas that aren't configured are unlimited.
+//!
+//! The quotas are checked on publish, which fails with the
+//! `storage_quota_exceeded`, `version_quota_exceeded` or
+//! `crate_quota_exceeded` error code if the new version would exceed one of
+//! them. Crates the user only publishes to, but doesn't own, count towards
+//! the versions, but not the storage of the user.
+//!
+//! Admins can change the quotas of a single account with
+//! `PUT /admin/users/:user_id/quotas`. The quotas of the override replace the
+//! configured ones, and those left out still apply. Users see their usage
+//! and quotas at `GET /me/quotas`.
+
+use diesel::dsl::{count_star, now, sql, IntervalDsl};
+use diesel::prelude::*;
+use diesel::sql_types::{BigInt, Nullable};
+
+use crate::models::{OwnerKind, QuotaOverride};
+use crate::schema::{crate_owners, readme_renderings, versions};
+use crate::util::errors::{cargo_err_with_code, AppResult, ErrorCode};
+
+/// The limits of an account, which are unlimited if they are `None`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Quotas {
+    pub storage_bytes: Option<i64>,
+    pub versions_per_day: Option<i64>,
+    pub crates: Option<i64>,
+}
+
+impl Quotas {
+    /// The quotas configured by the environment variables described in the
+    /// module documentation
+    pub fn from_environment() -> Self {
+        Self {
+            storage_bytes: quota("STORAGE_QUOTA_BYTES"),
+            versions_per_day: quota("VERSIONS_PER_DAY_QUOTA"),
+            crates: quota("CRATES_QUOTA"),
+        }
+    }
+
+    /// The quotas of the user, which are these unless an admin changed them
+    pub fn for_user(&self, conn: &PgConnection, user_id: i32) -> QueryResult<Self> {
+        Ok(match QuotaOverride::find(conn, user_id)? {
+            Some(quota_override) => self.with_override(&quota_override),
+            None => *self,
+        })
+    }
+
+    pub fn with_override(&self, quota_override: &QuotaOverride) -> Self {
+        Self {
+            storage_bytes: quota_override.storage_bytes.or(self.storage_bytes),
+            versions_per_day: quota_override.versions_per_day.or(self.versions_per_day),
+            crates: quota_override.crates.or(self.crates),
+        }
+    }
+
+    /// Checks that the user may publish a version with a crate file of
+    /// `crate_size` bytes. The usage doesn't include the version yet, but
+    /// includes its crate if `new_crate` was just created.
+    pub fn check_publish(&self, usage: &Usage, new_crate: bool, crate_size: i64) -> AppResult<()> {
+        if let Some(quota) = self.storage_bytes {
+            if usage.storage_bytes + crate_size > quota {
+                return Err(cargo_err_with_code(
+                    ErrorCode::StorageQuotaExceeded,
+                    &format_args!(
+                        "this version would exceed the storage quota of your account: \
+                         your crates use {} of {} bytes, and the crate file has {} bytes",
+                        usage.storage_bytes, quota, crate_size
+                    ),
+                ));
+            }
+        }
+        if let Some(quota) = self.versions_per_day {
+            if usage.versions_per_day >= quota {
+                return Err(cargo_err_with_code(
+                    ErrorCode::VersionQuotaExceeded,
+                    &format_args!(
+                        "your account may publish {} versions in 24 hours, please try again later",
+                        quota
+                    ),
+                ));
+            }
+        }
+        if let Some(quota) = self.crates.filter(|_| new_crate) {
+            if usage.crates > quota {
+                return Err(cargo_err_with_code(
+                    ErrorCode::CrateQuotaExceeded,
+                    &format_args!("your account may own at most {} crates", quota),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn quota(var: &str) -> Option<i64> {
+    dotenv::var(var).ok().map(|s| {
+        s.parse()
+            .unwrap_or_else(|_| panic!("{} was not a valid number", var))
+    })
+}
+
+/// What an account uses of each of its quotas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Usage {
+    pub storage_bytes: i64,
+    /// The versions published in the last 24 hours
+    pub versions_per_day: i64,
+    pub crates: i64,
+}
+
+impl Usage {
+    pub fn of(conn: &PgConnection, user_id: i32) -> QueryResult<Self> {
+        let owned = crate_owners::table
+            .filter(crate_owners::owner_id.eq(user_id))
+            .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+            .filter(crate_owners::deleted.eq(false))
+            .select(crate_owners::crate_id);
+
+        let crates = owned.clone().count().get_result(conn)?;
+        let (crate_files, readmes, readme_sources): (Option<i64>, Option<i64>, Option<i64>) =
+            versions::table
+                .left_join(readme_renderings::table)
+                .filter(versions::crate_id.eq_any(owned))
+                .select(
+                    sql::<(Nullable<BigInt>, Nullable<BigInt>, Nullable<BigInt>)>(
+                        "SUM(versions.crate_size), \
+                     SUM(readme_renderings.rendered_size), \
+                     SUM(readme_renderings.source_size)",
+                    ),
+                )
+                .get_result(conn)?;
+        let versions_per_day = versions::table
+            .filter(versions::published_by.eq(user_id))
+            .filter(versions::created_at.gt(now - 1.day()))
+            .select(count_star())
+            .get_result(conn)?;
+
+        Ok(Self {
+            storage_bytes: crate_files.unwrap_or(0)
+                + readmes.unwrap_or(0)
+                + readme_sources.unwrap_or(0),
+            versions_per_day,
+            crates,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(storage_bytes: i64, versions_per_day: i64, crates: i64) -> Usage {
+        Usage {
+            storage_bytes,
+            versions_per_day,
+            crates,
+        }
+    }
+
+    #[test]
+    fn unconfigured_quotas_are_unlimited() {
+        let quotas = Quotas::default();
+        assert!(quotas
+            .check_publish(&usage(i64::MAX / 2, 1000, 1000), true, 100)
+            .is_ok());
+    }
+
+    #[test]
+    fn publishes_exceeding_a_quota_fail() {
+        let quotas = Quotas {
+            storage_bytes: Some(1000),
+            versions_per_day: Some(5),
+            crates: Some(2),
+        };
+        let code = |usage: Usage, new_crate, crate_size| {
+            quotas
+                .check_publish(&usage, new_crate, crate_size)
+                .err()
+                .map(|e| e.code())
+        };
+
+        assert_eq!(code(usage(900, 4, 2), true, 100), None);
+        assert_eq!(
+            code(usage(900, 0, 0), false, 101),
+            Some(ErrorCode::StorageQuotaExceeded)
+        );
+        assert_eq!(
+            code(usage(0, 5, 0), false, 1),
+            Some(ErrorCode::VersionQuotaExceeded)
+        );
+        assert_eq!(
+            code(usage(0, 0, 3), true, 1),
+            Some(ErrorCode::CrateQuotaExceeded)
+        );
+        // Crates owned before the quota was lowered can still be published to
+        assert_eq!(code(usage(0, 0, 3), false, 1), None);
+    }
+
+    #[test]
+    fn overrides_replace_the_quotas_they_set() {
+        use chrono::NaiveDate;
+
+        let quotas = Quotas {
+            storage_bytes: Some(1000),
+            versions_per_day: Some(5),
+            crates: None,
+        };
+        let quota_override = QuotaOverride {
+            user_id: 1,
+            storage_bytes: Some(5000),
+            versions_per_day: None,
+            crates: Some(10),
+            admin_id: 2,
+            updated_at: NaiveDate::from_ymd(2021, 4, 28).and_hms(9, 30, 15),
+        };
+        assert_eq!(
+            quotas.with_override(&quota_override),
+            Quotas {
+                storage_bytes: Some(5000),
+                versions_per_day: Some(5),
+                crates: Some(10),
+            }
+        );
+    }
+}
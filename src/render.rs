@@ -1,15 +1,23 @@
 //! Render README files to HTML.
 
+use crate::swirl::PerformError;
 use ammonia::{Builder, UrlRelative, UrlRelativeEvaluate};
 use comrak::nodes::{AstNode, NodeValue};
+use diesel::PgConnection;
 use htmlescape::encode_minimal;
 use std::borrow::Cow;
 use std::path::Path;
-use swirl::PerformError;
+use std::time::Duration;
 use url::Url;
 
-use crate::background_jobs::Environment;
-use crate::models::Version;
+use crate::background_jobs::{Environment, Job};
+use crate::models::{ReadmeRerender, Version};
+use crate::scanning::CrateContents;
+
+/// The pause between the batches of a README re-render, so that the
+/// re-render doesn't put too much load on the crate file storage. The next
+/// batch is enqueued to run after it, so no worker waits in the meantime.
+const RERENDER_BATCH_INTERVAL: Duration = Duration::from_secs(5);
 
 /// Context for markdown to HTML rendering.
 #[allow(missing_debug_implementations)]
@@ -261,8 +269,7 @@ pub fn readme_to_html(text: &str, filename: &str, base_url: Option<&str>) -> Str
     encode_minimal(text).replace("\n", "<br>\n")
 }
 
-#[swirl::background_job]
-pub fn render_and_upload_readme(
+pub fn perform_render_and_upload_readme(
     conn: &PgConnection,
     env: &Environment,
     version_id: i32,
@@ -277,6 +284,9 @@ pub fn render_and_upload_readme(
 
     conn.transaction(|| {
         Version::record_readme_rendering(version_id, &conn)?;
+        Version::record_readme_source(version_id, Some(&file_name), &conn)?;
+        let sizes = (rendered.len() as i32, text.len() as i32);
+        Version::record_readme_sizes(version_id, Some(sizes), &conn)?;
         let (crate_name, vers): (String, String) = versions::table
             .find(version_id)
             .inner_join(crates::table)
@@ -284,10 +294,97 @@ pub fn render_and_upload_readme(
             .first(&*conn)?;
         env.uploader
             .upload_readme(env.http_client(), &crate_name, &vers, rendered)?;
+        env.uploader
+            .upload_readme_source(env.http_client(), &crate_name, &vers, text)?;
         Ok(())
     })
 }
 
+/// Renders the next batch of versions of a README re-render, and enqueues
+/// the job again if there are more versions left.
+pub fn perform_rerender_readmes(
+    conn: &PgConnection,
+    env: &Environment,
+    readme_rerender_id: i32,
+) -> Result<(), PerformError> {
+    use diesel::prelude::*;
+
+    let rerender = ReadmeRerender::find(conn, readme_rerender_id)?;
+    if rerender.is_finished() {
+        return Ok(());
+    }
+    let batch = rerender.next_batch(conn)?;
+    let mut processed = 0;
+    let mut failed = 0;
+    for (version_id, krate, version) in &batch {
+        let result = conn.transaction(|| rerender_readme(conn, env, *version_id, krate, version));
+        match result {
+            Ok(()) => processed += 1,
+            Err(e) => {
+                warn!(krate = %krate, version = %version, error = %e, "Failed to re-render README");
+                failed += 1;
+            }
+        }
+    }
+
+    let last_version_id = batch.last().map_or(rerender.last_version_id, |v| v.0);
+    let complete = batch.len() < rerender.batch_size as usize;
+    rerender.record_batch(conn, last_version_id, processed, failed, complete)?;
+    if !complete {
+        Job::RerenderReadmes { readme_rerender_id }.enqueue_after(conn, RERENDER_BATCH_INTERVAL)?;
+    }
+    Ok(())
+}
+
+/// Renders the README of a version again, from its uploaded crate file.
+///
+/// Versions without a README only have their rendering time updated, so they
+/// are not picked up by later re-renders.
+fn rerender_readme(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    krate: &str,
+    version: &str,
+) -> Result<(), PerformError> {
+    let tarball = env
+        .uploader
+        .download_crate(env.http_client(), krate, version)?;
+    let contents = CrateContents::from_tarball(tarball)?;
+
+    match readme_source(&contents) {
+        Some((text, file_name, base_url)) => {
+            perform_render_and_upload_readme(conn, env, version_id, text, file_name, base_url)
+        }
+        None => {
+            Version::record_readme_rendering(version_id, conn)?;
+            Version::record_readme_source(version_id, None, conn)?;
+            Version::record_readme_sizes(version_id, None, conn)?;
+            Ok(())
+        }
+    }
+}
+
+/// Finds the README of a crate file through its manifest, returning its
+/// contents, its path and the repository URL of the crate.
+fn readme_source(contents: &CrateContents) -> Option<(String, String, Option<String>)> {
+    let manifest = contents
+        .file("Cargo.toml")
+        .and_then(|file| std::str::from_utf8(&file.content).ok())
+        .and_then(|manifest| manifest.parse::<toml::Value>().ok())?;
+    let package = manifest.get("package")?;
+
+    let path = package.get("readme")?.as_str()?;
+    let path = path.trim_start_matches("./");
+    let text = String::from_utf8_lossy(&contents.file(path)?.content).into_owned();
+    let repository = package
+        .get("repository")
+        .and_then(|repository| repository.as_str())
+        .map(String::from);
+
+    Some((text, path.to_string(), repository))
+}
+
 /// Helper function to build a new `HashSet` from the items slice.
 fn hashset<T>(items: &[T]) -> std::collections::HashSet<T>
 where
@@ -521,4 +618,47 @@ mod tests {
             "<h1 align=\"center\">foo-bar</h1>\n<h5 align=\"center\">Hello World!</h5>\n"
         );
     }
+
+    fn crate_contents(files: &[(&str, &str)]) -> CrateContents {
+        use crate::scanning::CrateFile;
+
+        let files = files
+            .iter()
+            .map(|(path, content)| CrateFile {
+                path: path.to_string(),
+                content: content.as_bytes().to_vec(),
+            })
+            .collect();
+        CrateContents {
+            tarball: Vec::new(),
+            files,
+        }
+    }
+
+    #[test]
+    fn readme_source_is_found_through_the_manifest() {
+        let contents = crate_contents(&[
+            (
+                "Cargo.toml",
+                "[package]\nreadme = \"./docs/README.md\"\nrepository = \"https://github.com/rust-lang/foo\"\n",
+            ),
+            ("docs/README.md", "# foo"),
+        ]);
+        let (text, file_name, base_url) = readme_source(&contents).unwrap();
+        assert_eq!(text, "# foo");
+        assert_eq!(file_name, "docs/README.md");
+        assert_some_eq!(base_url, "https://github.com/rust-lang/foo");
+    }
+
+    #[test]
+    fn readme_source_requires_a_readme_field() {
+        let contents = crate_contents(&[
+            ("Cargo.toml", "[package]\nname = \"foo\"\n"),
+            ("README.md", "# foo"),
+        ]);
+        assert_none!(readme_source(&contents));
+
+        let contents = crate_contents(&[("Cargo.toml", "[package]\nreadme = \"README.md\"\n")]);
+        assert_none!(readme_source(&contents));
+    }
 }
@@ -0,0 +1,299 @@
+//! Verification that the owners of a crate control the repository it links to.
+//!
+//! An owner starts verifying the `repository` URL of the crate, which is then
+//! checked in one of two ways:
+//!
+//! * Repositories on GitHub are verified if the owner checking them has push
+//!   access to the repository, according to the GitHub API.
+//! * Repositories on other hosts are verified if the host serves the token of
+//!   the verification on a line of the file at [`WELL_KNOWN_PATH`].
+//!
+//! The file is only fetched from hosts that resolve to public addresses, and
+//! redirects aren't followed, so that a crate can't make crates.io send
+//! requests to its internal network.
+//!
+//! The crate shows a verified repository for as long as it still links to the
+//! verified URL. The `RecheckRepositoryVerifications` background job repeats
+//! the checks daily with the owner that last verified the repository, and
+//! clears the verification when a check fails.
+
+use oauth2::AccessToken;
+use reqwest::blocking::Client;
+use reqwest::{header, redirect, StatusCode};
+use std::io::Read;
+use std::net::{IpAddr, SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use url::{Host, Url};
+
+use crate::github::GitHubClient;
+use crate::models::{RepositoryVerification, User};
+use crate::util::errors::{bad_request, internal, AppResult, NotFound};
+
+/// Where hosts other than GitHub serve the tokens of the verifications of
+/// their repositories
+pub const WELL_KNOWN_PATH: &str = "/.well-known/crates-io-verification.txt";
+
+/// Files larger than this are not searched for the token
+const MAX_WELL_KNOWN_FILE_SIZE: u64 = 64 * 1024;
+
+/// How long fetching the file may take, including connecting to the host
+const WELL_KNOWN_FILE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How the control of a repository is proven
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VerificationMethod {
+    GitHub { owner: String, name: String },
+    WellKnownFile { url: String },
+}
+
+impl VerificationMethod {
+    /// The method for a repository URL, which must be `https` on a public host
+    pub fn for_repository(repository: &str) -> AppResult<Self> {
+        let url = Url::parse(repository)
+            .map_err(|_| bad_request(&format_args!("invalid repository URL `{}`", repository)))?;
+        if url.scheme() != "https" {
+            return Err(bad_request(
+                "only repositories with an `https` URL can be verified",
+            ));
+        }
+        let host = match url.host() {
+            Some(Host::Domain(host)) if !host.eq_ignore_ascii_case("localhost") => {
+                host.to_lowercase()
+            }
+            _ => {
+                return Err(bad_request(
+                    "only repositories on a public host can be verified",
+                ))
+            }
+        };
+
+        if host == "github.com" || host == "www.github.com" {
+            let mut segments = url
+                .path_segments()
+                .into_iter()
+                .flatten()
+                .filter(|segment| !segment.is_empty());
+            match (segments.next(), segments.next()) {
+                (Some(owner), Some(name)) => Ok(VerificationMethod::GitHub {
+                    owner: owner.to_string(),
+                    name: name.trim_end_matches(".git").to_string(),
+                }),
+                _ => Err(bad_request(
+                    "GitHub repositories must have a URL like `https://github.com/<owner>/<name>`",
+                )),
+            }
+        } else {
+            let authority = match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host,
+            };
+            Ok(VerificationMethod::WellKnownFile {
+                url: format!("https://{}{}", authority, WELL_KNOWN_PATH),
+            })
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationMethod::GitHub { .. } => "github",
+            VerificationMethod::WellKnownFile { .. } => "well_known_file",
+        }
+    }
+
+    /// The file that has to contain the token, if this method uses one
+    pub fn well_known_url(&self) -> Option<&str> {
+        match self {
+            VerificationMethod::GitHub { .. } => None,
+            VerificationMethod::WellKnownFile { url } => Some(url),
+        }
+    }
+
+    /// Checks whether `user` controls the repository of the verification.
+    ///
+    /// Errors are only returned when the check had no result, like when the
+    /// host could not be reached.
+    pub fn check(
+        &self,
+        github: &GitHubClient,
+        verification: &RepositoryVerification,
+        user: &User,
+    ) -> AppResult<bool> {
+        match self {
+            VerificationMethod::GitHub { owner, name } => {
+                // Anonymized accounts no longer have a token to check with
+                if user.gh_access_token.is_empty() {
+                    return Ok(false);
+                }
+                let token = AccessToken::new(user.gh_access_token.clone());
+                match github.repository(owner, name, &token) {
+                    Ok(repository) => Ok(repository
+                        .permissions
+                        .map(|permissions| permissions.admin || permissions.push)
+                        .unwrap_or(false)),
+                    Err(e) if e.is::<NotFound>() => Ok(false),
+                    Err(e) => Err(e),
+                }
+            }
+            VerificationMethod::WellKnownFile { url } => {
+                let response = well_known_file_client(url)?
+                    .get(url)
+                    .header(header::USER_AGENT, "crates.io (https://crates.io)")
+                    .send()?;
+                match response.status() {
+                    StatusCode::NOT_FOUND | StatusCode::GONE => return Ok(false),
+                    status if !status.is_success() => {
+                        return Err(internal(&format_args!(
+                            "got status {} fetching `{}`",
+                            status, url
+                        )));
+                    }
+                    _ => {}
+                }
+
+                let mut body = String::new();
+                response
+                    .take(MAX_WELL_KNOWN_FILE_SIZE)
+                    .read_to_string(&mut body)?;
+                Ok(body.lines().any(|line| line.trim() == verification.token))
+            }
+        }
+    }
+}
+
+/// A client that only connects to the public addresses the host of `url`
+/// resolves to.
+///
+/// The addresses are resolved once and passed to the client, so the host
+/// can't resolve to a different address by the time the request is sent.
+fn well_known_file_client(url: &str) -> AppResult<Client> {
+    let url = Url::parse(url).map_err(|e| internal(&e))?;
+    let host = url.host_str().unwrap_or_default();
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs: Vec<SocketAddr> = (host, port)
+        .to_socket_addrs()
+        .map_err(|e| internal(&format_args!("could not resolve `{}`: {}", host, e)))?
+        .collect();
+    if addrs.is_empty() || !addrs.iter().all(|addr| is_public(addr.ip())) {
+        return Err(bad_request(&format_args!(
+            "`{}` does not resolve to a public address",
+            host
+        )));
+    }
+
+    let mut builder = Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(WELL_KNOWN_FILE_TIMEOUT);
+    for addr in addrs {
+        builder = builder.resolve(host, addr);
+    }
+    Ok(builder.build()?)
+}
+
+/// Whether the address is reachable from the internet, rather than private,
+/// loopback, link-local or otherwise reserved
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // 0.0.0.0/8 and the shared address space 100.64.0.0/10
+                || a == 0
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            let first = ip.segments()[0];
+            // IPv4 addresses in IPv6 must be public IPv4 addresses too
+            ip.to_ipv4().map_or(true, |ip| is_public(ip.into()))
+                && !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    // Unique local addresses, fc00::/7
+                    || (first & 0xfe00) == 0xfc00
+                    // Link-local addresses, fe80::/10
+                    || (first & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_repositories_are_checked_with_the_api() {
+        let method = VerificationMethod::for_repository("https://github.com/rust-lang/crates.io");
+        assert_eq!(
+            assert_ok!(method),
+            VerificationMethod::GitHub {
+                owner: "rust-lang".into(),
+                name: "crates.io".into()
+            }
+        );
+
+        let method = VerificationMethod::for_repository("https://github.com/serde-rs/serde.git/");
+        assert_eq!(
+            assert_ok!(method),
+            VerificationMethod::GitHub {
+                owner: "serde-rs".into(),
+                name: "serde".into()
+            }
+        );
+
+        assert_err!(VerificationMethod::for_repository(
+            "https://github.com/rust-lang"
+        ));
+    }
+
+    #[test]
+    fn other_repositories_are_checked_with_a_file_on_their_host() {
+        let method = assert_ok!(VerificationMethod::for_repository(
+            "https://git.example.com:8443/some/repo"
+        ));
+        assert_eq!(
+            method.well_known_url(),
+            Some("https://git.example.com:8443/.well-known/crates-io-verification.txt")
+        );
+    }
+
+    #[test]
+    fn only_https_urls_on_public_hosts_can_be_verified() {
+        assert_err!(VerificationMethod::for_repository("http://github.com/a/b"));
+        assert_err!(VerificationMethod::for_repository("https://localhost/a/b"));
+        assert_err!(VerificationMethod::for_repository("https://127.0.0.1/a/b"));
+        assert_err!(VerificationMethod::for_repository("not a url"));
+    }
+
+    #[test]
+    fn only_public_addresses_are_fetched_from() {
+        for ip in &[
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "127.0.0.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::ffff:10.0.0.1",
+            "fd00::1",
+            "fe80::1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{} is not public", ip);
+        }
+        for ip in &["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public(ip.parse().unwrap()), "{} is public", ip);
+        }
+    }
+
+    #[test]
+    fn hosts_resolving_to_private_addresses_are_rejected() {
+        let url = "https://localhost.:8443/.well-known/crates-io-verification.txt";
+        assert_err!(well_known_file_client(url));
+    }
+}
@@ -40,14 +40,26 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/crates/:crate_id/:version/readme",
         C(krate::metadata::readme),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/readme/source",
+        C(krate::metadata::readme_source),
+    );
     api_router.get(
         "/crates/:crate_id/:version/dependencies",
         C(version::metadata::dependencies),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/licenses",
+        C(license::dependency_tree),
+    );
     api_router.get(
         "/crates/:crate_id/:version/downloads",
         C(version::downloads::downloads),
     );
+    api_router.get(
+        "/crates/:crate_id/:version/provenance",
+        C(version::metadata::provenance),
+    );
     api_router.get(
         "/crates/:crate_id/:version/authors",
         C(version::metadata::authors),
@@ -62,21 +74,105 @@ pub fn build_router(app: &App) -> RouteBuilder {
     api_router.get("/crates/:crate_id/following", C(krate::follow::following));
     api_router.get("/crates/:crate_id/owner_team", C(krate::owners::owner_team));
     api_router.get("/crates/:crate_id/owner_user", C(krate::owners::owner_user));
+    api_router.get("/owners", C(krate::owners::batch));
     api_router.get(
         "/crates/:crate_id/reverse_dependencies",
         C(krate::metadata::reverse_dependencies),
     );
+    api_router.get(
+        "/crates/:crate_id/dependency_graph",
+        C(krate::dependency_graph::dependency_graph),
+    );
+    api_router.put(
+        "/crates/:crate_id/project_links",
+        C(krate::project_links::update),
+    );
+    api_router.get("/crates/:crate_id/storage", C(krate::storage::show));
+    api_router.get("/crates/:crate_id/timeline", C(krate::timeline::timeline));
+    api_router.get("/crates/:crate_id/activity", C(krate::activity::activity));
+    api_router.get("/crates/:crate_id/advisories", C(krate::advisories::list));
+    api_router.put(
+        "/crates/:crate_id/advisories",
+        C(krate::advisories::publish),
+    );
+    api_router.get(
+        "/crates/:crate_id/advisories/osv",
+        C(krate::advisories::osv),
+    );
+    api_router.put(
+        "/crates/:crate_id/advisories/:advisory_id",
+        C(krate::advisories::update),
+    );
+    api_router.delete(
+        "/crates/:crate_id/advisories/:advisory_id",
+        C(krate::advisories::withdraw),
+    );
+    api_router.get(
+        "/crates/:crate_id/repository_verification",
+        C(krate::repository_verification::show),
+    );
+    api_router.put(
+        "/crates/:crate_id/repository_verification",
+        C(krate::repository_verification::start),
+    );
+    api_router.put(
+        "/crates/:crate_id/repository_verification/check",
+        C(krate::repository_verification::check),
+    );
+    api_router.get("/advisories/osv", C(krate::advisories::osv_feed));
+    api_router.get(
+        "/advisories/osv/:advisory_id",
+        C(krate::advisories::osv_show),
+    );
+    api_router.post("/audit", C(audit::audit));
+    api_router.get("/purl", C(purl::show));
+    api_router.get("/purls", C(purl::batch));
+    api_router.get("/crate_lists", C(crate_list::index));
+    api_router.put("/crate_lists", C(crate_list::create));
+    api_router.get("/crate_lists/:list_id", C(crate_list::show));
+    api_router.put("/crate_lists/:list_id", C(crate_list::update));
+    api_router.delete("/crate_lists/:list_id", C(crate_list::delete));
+    api_router.put(
+        "/crate_lists/:list_id/crates/:crate_id",
+        C(crate_list::add_crate),
+    );
+    api_router.delete(
+        "/crate_lists/:list_id/crates/:crate_id",
+        C(crate_list::remove_crate),
+    );
     api_router.get("/keywords", C(keyword::index));
     api_router.get("/keywords/:keyword_id", C(keyword::show));
+    api_router.get("/keywords/:keyword_id/stats", C(keyword::stats));
     api_router.get("/categories", C(category::index));
     api_router.get("/categories/:category_id", C(category::show));
+    api_router.get("/categories/:category_id/stats", C(category::stats));
     api_router.get("/category_slugs", C(category::slugs));
     api_router.get("/users/:user_id", C(user::other::show));
     api_router.put("/users/:user_id", C(user::me::update_user));
     api_router.get("/users/:user_id/stats", C(user::other::stats));
+    api_router.put("/users/:user_id/follow", C(user::follow::follow));
+    api_router.delete("/users/:user_id/follow", C(user::follow::unfollow));
+    api_router.get("/users/:user_id/following", C(user::follow::following));
     api_router.get("/teams/:team_id", C(team::show_team));
     api_router.get("/me", C(user::me::me));
+    api_router.delete("/me", C(user::me::delete));
     api_router.get("/me/updates", C(user::me::updates));
+    api_router.get("/me/funding", C(user::me::funding));
+    api_router.put("/me/privacy", C(user::follow::update_privacy));
+    api_router.get("/me/mutes", C(user::mutes::list));
+    api_router.put("/me/mutes/crates/:crate_id", C(user::mutes::mute_crate));
+    api_router.delete("/me/mutes/crates/:crate_id", C(user::mutes::unmute_crate));
+    api_router.put("/me/mutes/users/:user_id", C(user::mutes::mute_user));
+    api_router.delete("/me/mutes/users/:user_id", C(user::mutes::unmute_user));
+    api_router.put("/me/username", C(user::username::update));
+    api_router.get("/me/storage", C(krate::storage::summary));
+    api_router.get("/me/quotas", C(user::quotas::show));
+    api_router.get("/me/data_export", C(user::data_export::show));
+    api_router.put("/me/data_export", C(user::data_export::request));
+    api_router.get("/me/data_export/download", C(user::data_export::download));
+    api_router.get("/me/sessions", C(user::session::list));
+    api_router.delete("/me/sessions", C(user::session::revoke_all));
+    api_router.delete("/me/sessions/:id", C(user::session::revoke));
     api_router.get("/me/tokens", C(token::list));
     api_router.put("/me/tokens", C(token::new));
     api_router.delete("/me/tokens/:id", C(token::revoke));
@@ -96,13 +192,157 @@ pub fn build_router(app: &App) -> RouteBuilder {
         "/me/email_notifications",
         C(user::me::update_email_notifications),
     );
+    api_router.get("/me/email_preferences", C(user::me::email_preferences));
+    api_router.put(
+        "/me/email_preferences",
+        C(user::me::update_email_preferences),
+    );
+    api_router.get("/me/notifications", C(user::notifications::list));
+    api_router.put("/me/notifications/read", C(user::notifications::mark_read));
     api_router.get("/summary", C(krate::metadata::summary));
+    api_router.get("/trending", C(krate::metadata::trending));
+    api_router.get("/featured_crates", C(krate::metadata::featured));
+    api_router.get("/announcements", C(announcement::current));
+    api_router.get("/licenses", C(license::stats));
     api_router.put("/confirm/:email_token", C(user::me::confirm_user_email));
     api_router.put(
         "/users/:user_id/resend",
         C(user::me::regenerate_token_and_send),
     );
     api_router.get("/site_metadata", C(site_metadata::show_deployed_sha));
+    api_router.get("/policy", C(policy::show));
+
+    // Routes used by crates.io administrators
+    api_router.get("/admin/config", C(admin::config::show));
+    api_router.get("/admin/metrics", C(admin::metrics::show));
+    api_router.get("/admin/login_anomalies", C(admin::login_anomalies::index));
+    api_router.put("/admin/maintenance_mode", C(admin::maintenance_mode::start));
+    api_router.delete("/admin/maintenance_mode", C(admin::maintenance_mode::end));
+    api_router.get("/admin/quarantine", C(admin::quarantine::index));
+    api_router.put(
+        "/admin/quarantine/:version_id/release",
+        C(admin::quarantine::release),
+    );
+    api_router.put(
+        "/admin/quarantine/:version_id/reject",
+        C(admin::quarantine::reject),
+    );
+    api_router.put("/admin/users/:user_id/lock", C(admin::users::lock));
+    api_router.delete("/admin/users/:user_id/lock", C(admin::users::unlock));
+    api_router.delete("/admin/users/:user_id", C(admin::users::delete));
+    api_router.put(
+        "/admin/users/:user_id/verify_publisher",
+        C(admin::users::verify_publisher),
+    );
+    api_router.get(
+        "/admin/users/:user_id/data_export",
+        C(admin::users::show_data_export),
+    );
+    api_router.put(
+        "/admin/users/:user_id/data_export",
+        C(admin::users::request_data_export),
+    );
+    api_router.get(
+        "/admin/users/:user_id/data_export/download",
+        C(admin::users::download_data_export),
+    );
+    api_router.get("/admin/users/:user_id/quotas", C(admin::quotas::show));
+    api_router.put("/admin/users/:user_id/quotas", C(admin::quotas::update));
+    api_router.delete("/admin/users/:user_id/quotas", C(admin::quotas::delete));
+    api_router.get(
+        "/admin/publish_rate_overrides",
+        C(admin::rate_limits::index),
+    );
+    api_router.put(
+        "/admin/publish_rate_overrides/:user_id",
+        C(admin::rate_limits::update),
+    );
+    api_router.delete(
+        "/admin/publish_rate_overrides/:user_id",
+        C(admin::rate_limits::expire),
+    );
+    api_router.get(
+        "/admin/publish_rate_overrides/:user_id/actions",
+        C(admin::rate_limits::actions),
+    );
+    api_router.get(
+        "/admin/reserved_crate_names",
+        C(admin::reserved_names::index),
+    );
+    api_router.put(
+        "/admin/reserved_crate_names/:name",
+        C(admin::reserved_names::reserve),
+    );
+    api_router.delete(
+        "/admin/reserved_crate_names/:name",
+        C(admin::reserved_names::release),
+    );
+    api_router.get("/admin/announcements", C(admin::announcements::index));
+    api_router.put("/admin/announcements", C(admin::announcements::create));
+    api_router.delete(
+        "/admin/announcements/:announcement_id",
+        C(admin::announcements::delete),
+    );
+    api_router.get("/admin/feature_flags", C(admin::feature_flags::index));
+    api_router.put(
+        "/admin/feature_flags/:name",
+        C(admin::feature_flags::update),
+    );
+    api_router.delete(
+        "/admin/feature_flags/:name",
+        C(admin::feature_flags::delete),
+    );
+    api_router.get("/admin/featured_crates", C(admin::featured_crates::index));
+    api_router.put("/admin/featured_crates", C(admin::featured_crates::create));
+    api_router.delete(
+        "/admin/featured_crates/:featured_crate_id",
+        C(admin::featured_crates::delete),
+    );
+    api_router.get("/admin/readme_rerenders", C(admin::readme_rerenders::index));
+    api_router.put(
+        "/admin/readme_rerenders",
+        C(admin::readme_rerenders::create),
+    );
+    api_router.get(
+        "/admin/readme_rerenders/:readme_rerender_id",
+        C(admin::readme_rerenders::show),
+    );
+    api_router.delete(
+        "/admin/readme_rerenders/:readme_rerender_id",
+        C(admin::readme_rerenders::cancel),
+    );
+    api_router.get("/admin/scheduled_jobs", C(admin::scheduled_jobs::index));
+    api_router.get("/admin/background_jobs", C(admin::background_jobs::index));
+    api_router.get(
+        "/admin/background_jobs/stats",
+        C(admin::background_jobs::stats),
+    );
+    api_router.get(
+        "/admin/background_jobs/:job_id",
+        C(admin::background_jobs::show),
+    );
+    api_router.delete(
+        "/admin/background_jobs/:job_id",
+        C(admin::background_jobs::cancel),
+    );
+    api_router.put(
+        "/admin/background_jobs/:job_id/retry",
+        C(admin::background_jobs::retry),
+    );
+    api_router.get(
+        "/admin/storage_mismatches",
+        C(admin::storage_mismatches::index),
+    );
+    api_router.put(
+        "/admin/storage_mismatches/:storage_mismatch_id/resolve",
+        C(admin::storage_mismatches::resolve),
+    );
+    api_router.get("/admin/bulk_yanks", C(admin::bulk_yanks::index));
+    api_router.put("/admin/bulk_yanks", C(admin::bulk_yanks::create));
+    api_router.get(
+        "/admin/bulk_yanks/:bulk_yank_id",
+        C(admin::bulk_yanks::show),
+    );
     let api_router = Arc::new(api_router);
 
     let mut router = RouteBuilder::new();
@@ -115,14 +355,26 @@ pub fn build_router(app: &App) -> RouteBuilder {
     router.head("/api/v1/*path", R(Arc::clone(&api_router)));
     router.delete("/api/v1/*path", R(api_router));
 
+    // Search engines
+    router.get("/robots.txt", C(sitemap::robots));
+    router.get("/sitemap.xml", C(sitemap::index));
+    router.get("/sitemaps/:name", C(sitemap::show));
+
     // Session management
     router.get("/api/private/session/begin", C(user::session::begin));
+    router.get(
+        "/api/private/session/dev_login",
+        C(user::session::dev_login),
+    );
     router.get(
         "/api/private/session/authorize",
         C(user::session::authorize),
     );
     router.delete("/api/private/session", C(user::session::logout));
 
+    // Metrics
+    router.get("/api/private/metrics/:kind", C(metrics::prometheus));
+
     // Only serve the local checkout of the git index in development mode.
     // In production, for crates.io, cargo gets the index from
     // https://github.com/rust-lang/crates.io-index directly.
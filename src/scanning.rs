@@ -0,0 +1,417 @@
+//! Scanning of uploaded crate files for malicious content.
+//!
+//! After a version has been published, the `scan_version` background job
+//! downloads the crate file again and runs it through all configured
+//! scanners. If any of them reports a finding, the version is placed in the
+//! `version_quarantines` table and downloads are refused until an admin has
+//! reviewed the findings through the `/api/v1/admin/quarantine` endpoints.
+//!
+//! Scanners are configured through the `UPLOAD_SCANNERS` environment
+//! variable, which is a comma separated list of scanner names:
+//!
+//! - `patterns`: the built-in `PatternScanner`
+//! - `external`: POSTs the crate file to the URL in `UPLOAD_SCANNER_URL`,
+//!   see `ExternalScanner` for the expected response format
+
+use std::io::Read;
+use std::path::PathBuf;
+
+use crate::swirl::PerformError;
+use anyhow::Context;
+use diesel::PgConnection;
+use flate2::read::GzDecoder;
+use reqwest::blocking::Client;
+
+use crate::background_jobs::Environment;
+use crate::models::{Finding, VersionQuarantine};
+
+/// Files larger than this are skipped by the content based scanners.
+const MAX_SCANNED_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Strings of base64 or hex characters longer than this are reported as
+/// potentially obfuscated payloads.
+const MAX_ENCODED_BLOB_LEN: usize = 256;
+
+/// Identifies a scanner and its settings so that it can be passed to the
+/// background job.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ScannerConfig {
+    Patterns,
+    External { url: String },
+}
+
+impl ScannerConfig {
+    /// Reads the list of scanners from the `UPLOAD_SCANNERS` and
+    /// `UPLOAD_SCANNER_URL` environment variables.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an unknown scanner is listed, or if the `external` scanner is
+    /// enabled without `UPLOAD_SCANNER_URL` being set.
+    pub fn from_environment() -> Vec<Self> {
+        let names = dotenv::var("UPLOAD_SCANNERS").unwrap_or_default();
+        names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| match name {
+                "patterns" => ScannerConfig::Patterns,
+                "external" => ScannerConfig::External {
+                    url: dotenv::var("UPLOAD_SCANNER_URL")
+                        .expect("UPLOAD_SCANNER_URL must be set to use the external scanner"),
+                },
+                other => panic!("unknown upload scanner `{}` in UPLOAD_SCANNERS", other),
+            })
+            .collect()
+    }
+
+    fn build<'a>(&'a self, http_client: &'a Client) -> Box<dyn Scanner + 'a> {
+        match self {
+            ScannerConfig::Patterns => Box::new(PatternScanner),
+            ScannerConfig::External { url } => Box::new(ExternalScanner {
+                url,
+                client: http_client,
+            }),
+        }
+    }
+}
+
+/// The uploaded crate file, along with the files it contains.
+#[derive(Debug)]
+pub struct CrateContents {
+    /// The compressed crate file, as uploaded by cargo
+    pub tarball: Vec<u8>,
+    /// All regular files that are smaller than `MAX_SCANNED_FILE_SIZE`
+    pub files: Vec<CrateFile>,
+}
+
+#[derive(Debug)]
+pub struct CrateFile {
+    /// The path of the file, without the `{name}-{version}/` prefix
+    pub path: String,
+    pub content: Vec<u8>,
+}
+
+impl CrateContents {
+    pub fn from_tarball(tarball: Vec<u8>) -> anyhow::Result<Self> {
+        let mut files = Vec::new();
+
+        let mut archive = tar::Archive::new(GzDecoder::new(&*tarball));
+        for entry in archive.entries()? {
+            let entry = entry?;
+            if !entry.header().entry_type().is_file() || entry.size() > MAX_SCANNED_FILE_SIZE {
+                continue;
+            }
+
+            // Strip the `{name}-{version}/` prefix, which is verified on upload
+            let path = entry.path()?.components().skip(1).collect::<PathBuf>();
+            let path = path.to_string_lossy().into_owned();
+
+            let mut content = Vec::with_capacity(entry.size() as usize);
+            entry
+                .take(MAX_SCANNED_FILE_SIZE)
+                .read_to_end(&mut content)?;
+            files.push(CrateFile { path, content });
+        }
+
+        Ok(Self { tarball, files })
+    }
+
+    pub fn file(&self, path: &str) -> Option<&CrateFile> {
+        self.files.iter().find(|file| file.path == path)
+    }
+
+    /// Returns the build script of the crate, if it has one.
+    fn build_script(&self) -> Option<&CrateFile> {
+        let manifest = self
+            .file("Cargo.toml")
+            .and_then(|file| std::str::from_utf8(&file.content).ok())
+            .and_then(|manifest| manifest.parse::<toml::Value>().ok());
+
+        let path = match manifest
+            .as_ref()
+            .and_then(|m| m.get("package")?.get("build"))
+        {
+            Some(toml::Value::String(path)) => path.as_str(),
+            Some(toml::Value::Boolean(false)) => return None,
+            _ => "build.rs",
+        };
+
+        self.file(path)
+    }
+}
+
+/// A check that is run against every uploaded crate file.
+pub trait Scanner {
+    /// The name which is recorded with each finding
+    fn name(&self) -> &'static str;
+
+    fn scan(&self, contents: &CrateContents) -> anyhow::Result<Vec<Finding>>;
+}
+
+/// Looks for patterns that are common in malicious crates, but rare in
+/// legitimate ones: build scripts that access the network or spawn processes,
+/// and large encoded blobs that are typically used to hide a payload.
+#[derive(Debug, Clone, Copy)]
+pub struct PatternScanner;
+
+const BUILD_SCRIPT_RULES: &[(&str, &[&str], &str)] = &[
+    (
+        "build-script-network",
+        &[
+            "std::net::",
+            "TcpStream",
+            "UdpSocket",
+            "reqwest::",
+            "ureq::",
+            "curl::",
+            "hyper::",
+        ],
+        "build script accesses the network at install time",
+    ),
+    (
+        "build-script-process",
+        &[
+            "Command::new(\"curl\"",
+            "Command::new(\"wget\"",
+            "Command::new(\"sh\"",
+            "Command::new(\"bash\"",
+            "Command::new(\"powershell\"",
+        ],
+        "build script spawns a shell or a download tool",
+    ),
+];
+
+impl PatternScanner {
+    fn finding(&self, rule: &str, path: &str, message: &str) -> Finding {
+        Finding {
+            scanner: self.name().into(),
+            rule: rule.into(),
+            path: Some(path.into()),
+            message: message.into(),
+        }
+    }
+
+    fn longest_encoded_blob(content: &str) -> usize {
+        content
+            .split(|c: char| !(c.is_ascii_alphanumeric() || c == '+' || c == '/' || c == '='))
+            .map(str::len)
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+impl Scanner for PatternScanner {
+    fn name(&self) -> &'static str {
+        "patterns"
+    }
+
+    fn scan(&self, contents: &CrateContents) -> anyhow::Result<Vec<Finding>> {
+        let mut findings = Vec::new();
+
+        if let Some(build_script) = contents.build_script() {
+            let source = String::from_utf8_lossy(&build_script.content);
+            for (rule, patterns, message) in BUILD_SCRIPT_RULES {
+                if patterns.iter().any(|pattern| source.contains(pattern)) {
+                    findings.push(self.finding(rule, &build_script.path, message));
+                }
+            }
+        }
+
+        for file in contents.files.iter().filter(|f| f.path.ends_with(".rs")) {
+            let source = String::from_utf8_lossy(&file.content);
+            if Self::longest_encoded_blob(&source) > MAX_ENCODED_BLOB_LEN {
+                findings.push(self.finding(
+                    "encoded-blob",
+                    &file.path,
+                    "source file contains a large encoded blob",
+                ));
+            }
+        }
+
+        Ok(findings)
+    }
+}
+
+/// Sends the crate file to an external scanning service.
+///
+/// The crate file is sent as the body of a `POST` request, and the service is
+/// expected to respond with a JSON body of the form
+/// `{"findings": [{"rule": "...", "path": "...", "message": "..."}]}`.
+#[derive(Debug)]
+pub struct ExternalScanner<'a> {
+    url: &'a str,
+    client: &'a Client,
+}
+
+impl Scanner for ExternalScanner<'_> {
+    fn name(&self) -> &'static str {
+        "external"
+    }
+
+    fn scan(&self, contents: &CrateContents) -> anyhow::Result<Vec<Finding>> {
+        #[derive(Deserialize)]
+        struct ExternalFinding {
+            rule: String,
+            path: Option<String>,
+            message: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Response {
+            findings: Vec<ExternalFinding>,
+        }
+
+        let response: Response = self
+            .client
+            .post(self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-tar")
+            .body(contents.tarball.clone())
+            .send()?
+            .error_for_status()?
+            .json()?;
+
+        Ok(response
+            .findings
+            .into_iter()
+            .map(|f| Finding {
+                scanner: self.name().into(),
+                rule: f.rule,
+                path: f.path,
+                message: f.message,
+            })
+            .collect())
+    }
+}
+
+/// Runs all scanners against the contents and collects their findings.
+pub fn scan(
+    scanners: &[ScannerConfig],
+    http_client: &Client,
+    contents: &CrateContents,
+) -> anyhow::Result<Vec<Finding>> {
+    let mut findings = Vec::new();
+    for config in scanners {
+        let scanner = config.build(http_client);
+        let mut result = scanner
+            .scan(contents)
+            .with_context(|| format!("scanner `{}` failed", scanner.name()))?;
+        findings.append(&mut result);
+    }
+    Ok(findings)
+}
+
+/// Scans an uploaded crate file, and quarantines the version if any of the
+/// scanners reported a finding.
+pub fn perform_scan_version(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    krate: String,
+    version: String,
+    scanners: Vec<ScannerConfig>,
+) -> Result<(), PerformError> {
+    let tarball = env
+        .uploader
+        .download_crate(env.http_client(), &krate, &version)
+        .with_context(|| format!("failed to download `{}#{}`", krate, version))?;
+    let contents = CrateContents::from_tarball(tarball)?;
+
+    let findings = scan(&scanners, env.http_client(), &contents)?;
+    if !findings.is_empty() {
+        warn!(
+            krate = %krate,
+            version = %version,
+            findings = findings.len(),
+            "Quarantining flagged version"
+        );
+        VersionQuarantine::quarantine(conn, version_id, &findings)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(files: &[(&str, &str)]) -> CrateContents {
+        CrateContents {
+            tarball: Vec::new(),
+            files: files
+                .iter()
+                .map(|(path, content)| CrateFile {
+                    path: (*path).into(),
+                    content: content.as_bytes().to_vec(),
+                })
+                .collect(),
+        }
+    }
+
+    fn rules(contents: &CrateContents) -> Vec<String> {
+        PatternScanner
+            .scan(contents)
+            .unwrap()
+            .into_iter()
+            .map(|f| f.rule)
+            .collect()
+    }
+
+    #[test]
+    fn clean_crate_has_no_findings() {
+        let contents = contents(&[
+            ("Cargo.toml", "[package]\nname = \"foo\"\n"),
+            (
+                "build.rs",
+                "fn main() { println!(\"cargo:rerun-if-changed=build.rs\"); }",
+            ),
+            ("src/lib.rs", "pub fn foo() {}"),
+        ]);
+        assert!(rules(&contents).is_empty());
+    }
+
+    #[test]
+    fn build_script_network_access_is_reported() {
+        let contents = contents(&[
+            ("Cargo.toml", "[package]\nname = \"foo\"\n"),
+            (
+                "build.rs",
+                "fn main() { std::net::TcpStream::connect(\"1.2.3.4:80\"); }",
+            ),
+        ]);
+        assert_eq!(rules(&contents), vec!["build-script-network"]);
+    }
+
+    #[test]
+    fn custom_build_script_path_is_used() {
+        let contents = contents(&[
+            (
+                "Cargo.toml",
+                "[package]\nname = \"foo\"\nbuild = \"tools/build.rs\"\n",
+            ),
+            ("build.rs", "fn main() {}"),
+            (
+                "tools/build.rs",
+                "fn main() { Command::new(\"curl\").arg(\"http://x\"); }",
+            ),
+        ]);
+        assert_eq!(rules(&contents), vec!["build-script-process"]);
+    }
+
+    #[test]
+    fn disabled_build_script_is_ignored() {
+        let contents = contents(&[
+            ("Cargo.toml", "[package]\nname = \"foo\"\nbuild = false\n"),
+            ("build.rs", "fn main() { reqwest::get(\"http://x\"); }"),
+        ]);
+        assert!(rules(&contents).is_empty());
+    }
+
+    #[test]
+    fn encoded_blobs_are_reported() {
+        let blob = format!("const PAYLOAD: &str = \"{}\";", "QUJD".repeat(100));
+        let contents = contents(&[("src/lib.rs", &blob)]);
+        assert_eq!(rules(&contents), vec!["encoded-blob"]);
+    }
+}
@@ -0,0 +1,515 @@
+//! Recurring background jobs.
+//!
+//! Every entry of [`SCHEDULED_JOBS`] enqueues a background job whenever its
+//! cron expression matches. The time of the next run is stored in the
+//! `scheduled_jobs` table, and the background worker checks for due jobs on
+//! every iteration of its main loop.
+//!
+//! If the worker was down while one or more runs were due, the job is
+//! enqueued once as soon as the worker is back, and the next run is computed
+//! from the current time. Runs are skipped while a job of the same type is
+//! still queued, so a slow job doesn't pile up behind itself.
+//!
+//! Jobs that need settings which aren't configured, like the database dumps
+//! without `READ_ONLY_REPLICA_URL`, are left out of the schedule.
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Timelike};
+use diesel::dsl::{exists, select};
+use diesel::prelude::*;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::background_jobs::Job;
+use crate::schema::{background_jobs, scheduled_jobs};
+use crate::swirl::EnqueueError;
+
+/// A job that is enqueued on a recurring schedule
+#[allow(missing_debug_implementations)]
+pub struct ScheduledJob {
+    /// Identifies the schedule in the `scheduled_jobs` table
+    pub name: &'static str,
+    /// A cron expression in UTC, see [`Schedule`]
+    pub schedule: &'static str,
+    /// Returns `None` if the job can't run with the current configuration,
+    /// in which case it is left out of the schedule
+    pub job: fn() -> Option<Job>,
+}
+
+/// The database the dumps are generated from
+fn replica_url() -> Option<String> {
+    dotenv::var("READ_ONLY_REPLICA_URL").ok()
+}
+
+pub const SCHEDULED_JOBS: &[ScheduledJob] = &[
+    ScheduledJob {
+        name: "update_downloads",
+        schedule: "*/10 * * * *",
+        job: || Some(Job::UpdateDownloads {}),
+    },
+    ScheduledJob {
+        name: "upload_pending_crates",
+        schedule: "*/5 * * * *",
+        job: || Some(Job::UploadPendingCrates {}),
+    },
+    ScheduledJob {
+        name: "release_held_versions",
+        schedule: "*/10 * * * *",
+        job: || Some(Job::ReleaseHeldVersions {}),
+    },
+    ScheduledJob {
+        name: "refresh_summary",
+        schedule: "* * * * *",
+        job: || Some(Job::RefreshSummary {}),
+    },
+    ScheduledJob {
+        name: "check_download_origins",
+        schedule: "* * * * *",
+        job: || Some(Job::CheckDownloadOrigins {}),
+    },
+    ScheduledJob {
+        name: "dump_db",
+        schedule: "0 3 * * 0",
+        job: || {
+            Some(Job::DumpDb {
+                database_url: replica_url()?,
+                target_name: String::from("db-dump.tar.gz"),
+            })
+        },
+    },
+    ScheduledJob {
+        name: "dump_db_incremental",
+        schedule: "0 3 * * 1-6",
+        job: || {
+            Some(Job::DumpDbIncremental {
+                database_url: replica_url()?,
+            })
+        },
+    },
+    ScheduledJob {
+        name: "dump_download_history",
+        schedule: "0 6 * * 0",
+        job: || {
+            Some(Job::DumpDownloadHistory {
+                database_url: replica_url()?,
+            })
+        },
+    },
+    ScheduledJob {
+        name: "verify_storage",
+        schedule: "40 * * * *",
+        job: || Some(Job::VerifyStorage {}),
+    },
+    ScheduledJob {
+        name: "backfill_checksums",
+        schedule: "20 * * * *",
+        job: || Some(Job::BackfillChecksums {}),
+    },
+    ScheduledJob {
+        name: "reconcile_dependents",
+        schedule: "15 2 * * *",
+        job: || Some(Job::ReconcileDependents {}),
+    },
+    ScheduledJob {
+        name: "compute_trending_scores",
+        schedule: "45 2 * * *",
+        job: || Some(Job::ComputeTrendingScores {}),
+    },
+    ScheduledJob {
+        name: "refresh_monthly_stats",
+        schedule: "15 4 * * *",
+        job: || Some(Job::RefreshMonthlyStats { backfill: false }),
+    },
+    ScheduledJob {
+        name: "recheck_repository_verifications",
+        schedule: "45 5 * * *",
+        job: || Some(Job::RecheckRepositoryVerifications {}),
+    },
+    ScheduledJob {
+        name: "sync_advisories",
+        schedule: "20 */6 * * *",
+        job: || Some(Job::SyncAdvisories {}),
+    },
+    ScheduledJob {
+        name: "send_weekly_digests",
+        schedule: "0 9 * * 1",
+        job: || Some(Job::SendWeeklyDigests {}),
+    },
+    ScheduledJob {
+        name: "export_dependency_graph",
+        schedule: "0 5 * * *",
+        job: || Some(Job::ExportDependencyGraph {}),
+    },
+    ScheduledJob {
+        name: "generate_sitemaps",
+        schedule: "30 3 * * *",
+        job: || Some(Job::GenerateSitemaps {}),
+    },
+    ScheduledJob {
+        name: "clean_up_stale_data",
+        schedule: "30 4 * * *",
+        job: || Some(Job::CleanUpStaleData {}),
+    },
+];
+
+impl ScheduledJob {
+    pub fn find(name: &str) -> Option<&'static ScheduledJob> {
+        SCHEDULED_JOBS
+            .iter()
+            .find(|scheduled| scheduled.name == name)
+    }
+
+    pub fn parsed_schedule(&self) -> Schedule {
+        self.schedule
+            .parse()
+            .unwrap_or_else(|e| panic!("invalid schedule for `{}`: {}", self.name, e))
+    }
+
+    pub fn job(&self) -> Option<Job> {
+        (self.job)()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.job().is_some()
+    }
+}
+
+/// The persisted state of a [`ScheduledJob`]
+#[derive(Debug, Clone, Queryable)]
+pub struct ScheduledJobState {
+    pub name: String,
+    pub schedule: String,
+    pub last_run_at: Option<NaiveDateTime>,
+    pub next_run_at: NaiveDateTime,
+}
+
+impl ScheduledJobState {
+    pub fn all(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        scheduled_jobs::table.order(scheduled_jobs::name).load(conn)
+    }
+}
+
+/// Stores the next run of every scheduled job that was added, or whose
+/// schedule was changed, since the last call. Jobs that aren't enabled are
+/// removed from the schedule.
+pub fn sync_scheduled_jobs(conn: &PgConnection, now: NaiveDateTime) -> QueryResult<()> {
+    for scheduled in SCHEDULED_JOBS {
+        if !scheduled.is_enabled() {
+            diesel::delete(scheduled_jobs::table.find(scheduled.name)).execute(conn)?;
+            continue;
+        }
+
+        let next_run_at = match scheduled.parsed_schedule().next_after(now) {
+            Some(next_run_at) => next_run_at,
+            None => continue,
+        };
+
+        diesel::insert_into(scheduled_jobs::table)
+            .values((
+                scheduled_jobs::name.eq(scheduled.name),
+                scheduled_jobs::schedule.eq(scheduled.schedule),
+                scheduled_jobs::next_run_at.eq(next_run_at),
+            ))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        diesel::update(
+            scheduled_jobs::table
+                .find(scheduled.name)
+                .filter(scheduled_jobs::schedule.ne(scheduled.schedule)),
+        )
+        .set((
+            scheduled_jobs::schedule.eq(scheduled.schedule),
+            scheduled_jobs::next_run_at.eq(next_run_at),
+        ))
+        .execute(conn)?;
+    }
+    Ok(())
+}
+
+/// Enqueues every scheduled job that is due, and returns their names.
+///
+/// The rows of due jobs are locked, so that multiple workers can call this
+/// at the same time without enqueueing a job twice.
+pub fn enqueue_due_jobs(
+    conn: &PgConnection,
+    now: NaiveDateTime,
+) -> Result<Vec<&'static str>, EnqueueError> {
+    conn.transaction(|| {
+        sync_scheduled_jobs(conn, now)?;
+
+        let due: Vec<String> = scheduled_jobs::table
+            .select(scheduled_jobs::name)
+            .filter(scheduled_jobs::next_run_at.le(now))
+            .for_update()
+            .skip_locked()
+            .load(conn)?;
+
+        let mut enqueued = Vec::new();
+        let due = due
+            .iter()
+            .filter_map(|name| ScheduledJob::find(name))
+            .filter_map(|scheduled| Some((scheduled, scheduled.job()?)));
+        for (scheduled, job) in due {
+            let (job_type, _) = job.to_parts()?;
+            let already_queued: bool = select(exists(
+                background_jobs::table.filter(background_jobs::job_type.eq(&job_type)),
+            ))
+            .get_result(conn)?;
+
+            if !already_queued {
+                job.enqueue(conn)?;
+                diesel::update(scheduled_jobs::table.find(scheduled.name))
+                    .set(scheduled_jobs::last_run_at.eq(now))
+                    .execute(conn)?;
+                enqueued.push(scheduled.name);
+            }
+
+            // Runs that were missed while the worker was down are not made up
+            // for, the job is enqueued once and continues on its schedule
+            let next_run_at = scheduled.parsed_schedule().next_after(now);
+            diesel::update(scheduled_jobs::table.find(scheduled.name))
+                .set(scheduled_jobs::next_run_at.eq(next_run_at.unwrap_or(now)))
+                .execute(conn)?;
+        }
+        Ok(enqueued)
+    })
+}
+
+/// A cron expression with the five fields minute, hour, day of month, month
+/// and day of week.
+///
+/// Each field is either `*`, a number, a range like `1-5`, or a comma
+/// separated list of those. Any of them can be followed by a step like `/10`.
+/// Days of the week start with `0` for Sunday, and `7` is accepted as
+/// Sunday as well. If both the day of month and the day of week are
+/// restricted, a time matches if either of them matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleError(String);
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for ScheduleError {}
+
+impl FromStr for Schedule {
+    type Err = ScheduleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        if fields.len() != 5 {
+            return Err(ScheduleError(format!(
+                "expected 5 fields, found {}",
+                fields.len()
+            )));
+        }
+
+        let mut days_of_week = parse_field(fields[4], 0, 7)?;
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week = (days_of_week & !(1 << 7)) | 1;
+        }
+
+        Ok(Schedule {
+            minutes: parse_field(fields[0], 0, 59)?,
+            hours: parse_field(fields[1], 0, 23)?,
+            days_of_month: parse_field(fields[2], 1, 31)?,
+            months: parse_field(fields[3], 1, 12)?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+}
+
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, ScheduleError> {
+    let invalid = || ScheduleError(format!("invalid field `{}`", field));
+    let parse = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let mut parts = part.splitn(2, '/');
+        let range = parts.next().unwrap_or_default();
+        let step = parts.next().map(parse).transpose()?;
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some(dash) = range.find('-') {
+            (parse(&range[..dash])?, parse(&range[dash + 1..])?)
+        } else {
+            let value = parse(range)?;
+            (value, if step.is_some() { max } else { value })
+        };
+
+        let step = step.unwrap_or(1);
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            bits |= 1u64 << value;
+        }
+    }
+    Ok(bits)
+}
+
+fn matches(bits: u64, value: u32) -> bool {
+    bits & (1u64 << value) != 0
+}
+
+impl Schedule {
+    /// Returns the first time after `time` that matches the schedule, or
+    /// `None` if the schedule never matches (like on February 31st).
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let mut next = time.date().and_hms(time.hour(), time.minute(), 0) + Duration::minutes(1);
+        // Every valid schedule matches at least once within four years
+        let limit = next + Duration::days(4 * 366);
+
+        while next <= limit {
+            let date = next.date();
+            if !matches(self.months, date.month()) {
+                let (year, month) = match date.month() {
+                    12 => (date.year() + 1, 1),
+                    month => (date.year(), month + 1),
+                };
+                next = NaiveDate::from_ymd(year, month, 1).and_hms(0, 0, 0);
+            } else if !self.matches_day(date) {
+                next = date.succ().and_hms(0, 0, 0);
+            } else if !matches(self.hours, next.hour()) {
+                next = date.and_hms(next.hour(), 0, 0) + Duration::hours(1);
+            } else if !matches(self.minutes, next.minute()) {
+                next += Duration::minutes(1);
+            } else {
+                return Some(next);
+            }
+        }
+        None
+    }
+
+    /// Returns the next `count` times after `time` that match the schedule
+    pub fn upcoming(&self, time: NaiveDateTime, count: usize) -> Vec<NaiveDateTime> {
+        let mut runs = Vec::with_capacity(count);
+        let mut time = time;
+        while runs.len() < count {
+            match self.next_after(time) {
+                Some(next) => {
+                    runs.push(next);
+                    time = next;
+                }
+                None => break,
+            }
+        }
+        runs
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day_of_month = matches(self.days_of_month, date.day());
+        let day_of_week = matches(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (false, false) => day_of_month || day_of_week,
+            (true, false) => day_of_week,
+            _ => day_of_month,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(schedule: &str, after: &str) -> Option<NaiveDateTime> {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(time(after))
+    }
+
+    #[test]
+    fn all_scheduled_jobs_are_valid() {
+        for scheduled in SCHEDULED_JOBS {
+            let schedule = scheduled.parsed_schedule();
+            assert_some!(schedule.next_after(time("2021-01-01 00:00")));
+        }
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert_err!("* * * *".parse::<Schedule>());
+        assert_err!("60 * * * *".parse::<Schedule>());
+        assert_err!("* 24 * * *".parse::<Schedule>());
+        assert_err!("* * 0 * *".parse::<Schedule>());
+        assert_err!("*/0 * * * *".parse::<Schedule>());
+        assert_err!("5-1 * * * *".parse::<Schedule>());
+        assert_err!("a * * * *".parse::<Schedule>());
+    }
+
+    #[test]
+    fn next_run() {
+        assert_eq!(
+            next("* * * * *", "2021-02-23 10:15"),
+            Some(time("2021-02-23 10:16"))
+        );
+        assert_eq!(
+            next("*/10 * * * *", "2021-02-23 10:15"),
+            Some(time("2021-02-23 10:20"))
+        );
+        assert_eq!(
+            next("0 3 * * *", "2021-02-23 10:15"),
+            Some(time("2021-02-24 03:00"))
+        );
+        assert_eq!(
+            next("0 0 1 * *", "2021-12-15 00:00"),
+            Some(time("2022-01-01 00:00"))
+        );
+        assert_eq!(
+            next("15,45 9-17 * * 1-5", "2021-02-26 17:50"),
+            Some(time("2021-03-01 09:15"))
+        );
+        assert_eq!(
+            next("0 0 29 2 *", "2021-03-01 00:00"),
+            Some(time("2024-02-29 00:00"))
+        );
+        assert_eq!(next("0 0 31 2 *", "2021-03-01 00:00"), None);
+    }
+
+    #[test]
+    fn sunday_can_be_zero_or_seven() {
+        // 2021-02-28 is a Sunday
+        assert_eq!(
+            next("0 12 * * 7", "2021-02-23 00:00"),
+            Some(time("2021-02-28 12:00"))
+        );
+        assert_eq!(
+            next("0 12 * * 0", "2021-02-23 00:00"),
+            Some(time("2021-02-28 12:00"))
+        );
+    }
+
+    #[test]
+    fn restricted_day_of_month_and_week_match_either() {
+        // The 1st of the month, or any Sunday
+        assert_eq!(
+            next("0 0 1 * 0", "2021-02-23 00:00"),
+            Some(time("2021-02-28 00:00"))
+        );
+        assert_eq!(
+            next("0 0 1 * 0", "2021-02-28 00:00"),
+            Some(time("2021-03-01 00:00"))
+        );
+    }
+}
@@ -4,128 +4,100 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `api_tokens` table.
+    /// Representation of the `advisories` table.
     ///
     /// (Automatically generated by Diesel.)
-    api_tokens (id) {
-        /// The `id` column of the `api_tokens` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `user_id` column of the `api_tokens` table.
+    advisories (id) {
+        /// The `id` column of the `advisories` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `token` column of the `api_tokens` table.
+        id -> Varchar,
+        /// The `crate_name` column of the `advisories` table.
         ///
-        /// Its SQL type is `Bytea`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        token -> Bytea,
-        /// The `name` column of the `api_tokens` table.
+        crate_name -> Varchar,
+        /// The `title` column of the `advisories` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Varchar,
-        /// The `created_at` column of the `api_tokens` table.
+        title -> Text,
+        /// The `url` column of the `advisories` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Nullable<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `last_used_at` column of the `api_tokens` table.
+        url -> Nullable<Text>,
+        /// The `date` column of the `advisories` table.
         ///
-        /// Its SQL type is `Nullable<Timestamp>`.
+        /// Its SQL type is `Date`.
         ///
         /// (Automatically generated by Diesel.)
-        last_used_at -> Nullable<Timestamp>,
-        /// The `revoked` column of the `api_tokens` table.
+        date -> Date,
+        /// The `patched` column of the `advisories` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Array<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        revoked -> Bool,
-    }
-}
-
-table! {
-    use diesel::sql_types::*;
-    use diesel_full_text_search::{TsVector as Tsvector};
-
-    /// Representation of the `background_jobs` table.
-    ///
-    /// (Automatically generated by Diesel.)
-    background_jobs (id) {
-        /// The `id` column of the `background_jobs` table.
+        patched -> Array<Text>,
+        /// The `unaffected` column of the `advisories` table.
         ///
-        /// Its SQL type is `Int8`.
+        /// Its SQL type is `Array<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int8,
-        /// The `job_type` column of the `background_jobs` table.
+        unaffected -> Array<Text>,
+        /// The `informational` column of the `advisories` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        job_type -> Text,
-        /// The `data` column of the `background_jobs` table.
+        informational -> Nullable<Varchar>,
+        /// The `withdrawn` column of the `advisories` table.
         ///
-        /// Its SQL type is `Jsonb`.
+        /// Its SQL type is `Bool`.
         ///
         /// (Automatically generated by Diesel.)
-        data -> Jsonb,
-        /// The `retries` column of the `background_jobs` table.
+        withdrawn -> Bool,
+        /// The `created_at` column of the `advisories` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        retries -> Int4,
-        /// The `last_retry` column of the `background_jobs` table.
+        created_at -> Timestamp,
+        /// The `updated_at` column of the `advisories` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        last_retry -> Timestamp,
-        /// The `created_at` column of the `background_jobs` table.
+        updated_at -> Timestamp,
+        /// The `description` column of the `advisories` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Nullable<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-    }
-}
-
-table! {
-    use diesel::sql_types::*;
-    use diesel_full_text_search::{TsVector as Tsvector};
-
-    /// Representation of the `badges` table.
-    ///
-    /// (Automatically generated by Diesel.)
-    badges (crate_id, badge_type) {
-        /// The `crate_id` column of the `badges` table.
+        description -> Nullable<Text>,
+        /// The `severity` column of the `advisories` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `badge_type` column of the `badges` table.
+        severity -> Nullable<Varchar>,
+        /// The `affected` column of the `advisories` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Array<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        badge_type -> Varchar,
-        /// The `attributes` column of the `badges` table.
+        affected -> Array<Text>,
+        /// The `published_by` column of the `advisories` table.
         ///
-        /// Its SQL type is `Jsonb`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        attributes -> Jsonb,
+        published_by -> Nullable<Int4>,
     }
 }
 
@@ -133,41 +105,53 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `categories` table.
+    /// Representation of the `announcements` table.
     ///
     /// (Automatically generated by Diesel.)
-    categories (id) {
-        /// The `id` column of the `categories` table.
+    announcements (id) {
+        /// The `id` column of the `announcements` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `category` column of the `categories` table.
+        /// The `message` column of the `announcements` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        category -> Varchar,
-        /// The `slug` column of the `categories` table.
+        message -> Varchar,
+        /// The `url` column of the `announcements` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Nullable<Varchar>`.
         ///
         /// (Automatically generated by Diesel.)
-        slug -> Varchar,
-        /// The `description` column of the `categories` table.
+        url -> Nullable<Varchar>,
+        /// The `severity` column of the `announcements` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        description -> Varchar,
-        /// The `crates_cnt` column of the `categories` table.
+        severity -> Int4,
+        /// The `starts_at` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        starts_at -> Timestamp,
+        /// The `ends_at` column of the `announcements` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ends_at -> Nullable<Timestamp>,
+        /// The `admin_id` column of the `announcements` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crates_cnt -> Int4,
-        /// The `created_at` column of the `categories` table.
+        admin_id -> Int4,
+        /// The `created_at` column of the `announcements` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
@@ -180,46 +164,58 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owner_invitations` table.
+    /// Representation of the `api_tokens` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owner_invitations (invited_user_id, crate_id) {
-        /// The `invited_user_id` column of the `crate_owner_invitations` table.
+    api_tokens (id) {
+        /// The `id` column of the `api_tokens` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        invited_user_id -> Int4,
-        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
+        id -> Int4,
+        /// The `user_id` column of the `api_tokens` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        invited_by_user_id -> Int4,
-        /// The `crate_id` column of the `crate_owner_invitations` table.
+        user_id -> Int4,
+        /// The `token` column of the `api_tokens` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Bytea`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `created_at` column of the `crate_owner_invitations` table.
+        token -> Bytea,
+        /// The `name` column of the `api_tokens` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `created_at` column of the `api_tokens` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
         created_at -> Timestamp,
-        /// The `token` column of the `crate_owner_invitations` table.
+        /// The `last_used_at` column of the `api_tokens` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Nullable<Timestamp>`.
         ///
         /// (Automatically generated by Diesel.)
-        token -> Text,
-        /// The `token_generated_at` column of the `crate_owner_invitations` table.
+        last_used_at -> Nullable<Timestamp>,
+        /// The `revoked` column of the `api_tokens` table.
         ///
-        /// Its SQL type is `Nullable<Timestamp>`.
+        /// Its SQL type is `Bool`.
         ///
         /// (Automatically generated by Diesel.)
-        token_generated_at -> Nullable<Timestamp>,
+        revoked -> Bool,
+        /// The `kdf_iterations` column of the `api_tokens` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kdf_iterations -> Nullable<Int4>,
     }
 }
 
@@ -227,58 +223,70 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crate_owners` table.
+    /// Representation of the `background_job_runs` table.
     ///
     /// (Automatically generated by Diesel.)
-    crate_owners (crate_id, owner_id, owner_kind) {
-        /// The `crate_id` column of the `crate_owners` table.
+    background_job_runs (id) {
+        /// The `id` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Int8`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `owner_id` column of the `crate_owners` table.
+        id -> Int8,
+        /// The `job_id` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Int8`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_id -> Int4,
-        /// The `created_at` column of the `crate_owners` table.
+        job_id -> Int8,
+        /// The `job_type` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `created_by` column of the `crate_owners` table.
+        job_type -> Text,
+        /// The `queue` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        created_by -> Nullable<Int4>,
-        /// The `deleted` column of the `crate_owners` table.
+        queue -> Text,
+        /// The `enqueued_at` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        deleted -> Bool,
-        /// The `updated_at` column of the `crate_owners` table.
+        enqueued_at -> Timestamp,
+        /// The `started_at` column of the `background_job_runs` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `owner_kind` column of the `crate_owners` table.
+        started_at -> Timestamp,
+        /// The `finished_at` column of the `background_job_runs` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        owner_kind -> Int4,
-        /// The `email_notifications` column of the `crate_owners` table.
+        finished_at -> Timestamp,
+        /// The `succeeded` column of the `background_job_runs` table.
         ///
         /// Its SQL type is `Bool`.
         ///
         /// (Automatically generated by Diesel.)
-        email_notifications -> Bool,
+        succeeded -> Bool,
+        /// The `error` column of the `background_job_runs` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        error -> Nullable<Text>,
+        /// The `request_id` column of the `background_job_runs` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        request_id -> Nullable<Text>,
     }
 }
 
@@ -286,82 +294,76 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates` table.
+    /// Representation of the `background_jobs` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates (id) {
-        /// The `id` column of the `crates` table.
-        ///
-        /// Its SQL type is `Int4`.
-        ///
-        /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `name` column of the `crates` table.
+    background_jobs (id) {
+        /// The `id` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Int8`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Varchar,
-        /// The `updated_at` column of the `crates` table.
+        id -> Int8,
+        /// The `job_type` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        updated_at -> Timestamp,
-        /// The `created_at` column of the `crates` table.
+        job_type -> Text,
+        /// The `data` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Timestamp`.
+        /// Its SQL type is `Jsonb`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
-        /// The `downloads` column of the `crates` table.
+        data -> Jsonb,
+        /// The `retries` column of the `background_jobs` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        downloads -> Int4,
-        /// The `description` column of the `crates` table.
+        retries -> Int4,
+        /// The `last_retry` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        description -> Nullable<Varchar>,
-        /// The `homepage` column of the `crates` table.
+        last_retry -> Timestamp,
+        /// The `created_at` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        homepage -> Nullable<Varchar>,
-        /// The `documentation` column of the `crates` table.
+        created_at -> Timestamp,
+        /// The `queue` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Text`.
         ///
         /// (Automatically generated by Diesel.)
-        documentation -> Nullable<Varchar>,
-        /// The `readme` column of the `crates` table.
+        queue -> Text,
+        /// The `priority` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Int2`.
         ///
         /// (Automatically generated by Diesel.)
-        readme -> Nullable<Varchar>,
-        /// The `textsearchable_index_col` column of the `crates` table.
+        priority -> Int2,
+        /// The `last_error` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Tsvector`.
+        /// Its SQL type is `Nullable<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        textsearchable_index_col -> Tsvector,
-        /// The `repository` column of the `crates` table.
+        last_error -> Nullable<Text>,
+        /// The `request_id` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Nullable<Text>`.
         ///
         /// (Automatically generated by Diesel.)
-        repository -> Nullable<Varchar>,
-        /// The `max_upload_size` column of the `crates` table.
+        request_id -> Nullable<Text>,
+        /// The `run_at` column of the `background_jobs` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        max_upload_size -> Nullable<Int4>,
+        run_at -> Timestamp,
     }
 }
 
@@ -369,22 +371,28 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_categories` table.
+    /// Representation of the `badges` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_categories (crate_id, category_id) {
-        /// The `crate_id` column of the `crates_categories` table.
+    badges (crate_id, badge_type) {
+        /// The `crate_id` column of the `badges` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         crate_id -> Int4,
-        /// The `category_id` column of the `crates_categories` table.
+        /// The `badge_type` column of the `badges` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        category_id -> Int4,
+        badge_type -> Varchar,
+        /// The `attributes` column of the `badges` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attributes -> Jsonb,
     }
 }
 
@@ -392,22 +400,22 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `crates_keywords` table.
+    /// Representation of the `bulk_yank_versions` table.
     ///
     /// (Automatically generated by Diesel.)
-    crates_keywords (crate_id, keyword_id) {
-        /// The `crate_id` column of the `crates_keywords` table.
+    bulk_yank_versions (bulk_yank_id, version_id) {
+        /// The `bulk_yank_id` column of the `bulk_yank_versions` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `keyword_id` column of the `crates_keywords` table.
+        bulk_yank_id -> Int4,
+        /// The `version_id` column of the `bulk_yank_versions` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword_id -> Int4,
+        version_id -> Int4,
     }
 }
 
@@ -415,64 +423,70 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `dependencies` table.
+    /// Representation of the `bulk_yanks` table.
     ///
     /// (Automatically generated by Diesel.)
-    dependencies (id) {
-        /// The `id` column of the `dependencies` table.
+    bulk_yanks (id) {
+        /// The `id` column of the `bulk_yanks` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `version_id` column of the `dependencies` table.
+        /// The `admin_id` column of the `bulk_yanks` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `crate_id` column of the `dependencies` table.
+        admin_id -> Int4,
+        /// The `user_id` column of the `bulk_yanks` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
-        /// The `req` column of the `dependencies` table.
+        user_id -> Int4,
+        /// The `api_token_id` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Varchar`.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
         /// (Automatically generated by Diesel.)
-        req -> Varchar,
-        /// The `optional` column of the `dependencies` table.
+        api_token_id -> Nullable<Int4>,
+        /// The `since` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        optional -> Bool,
-        /// The `default_features` column of the `dependencies` table.
+        since -> Timestamp,
+        /// The `until` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        default_features -> Bool,
-        /// The `features` column of the `dependencies` table.
+        until -> Timestamp,
+        /// The `reason` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Array<Text>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        features -> Array<Text>,
-        /// The `target` column of the `dependencies` table.
+        reason -> Varchar,
+        /// The `created_at` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        target -> Nullable<Varchar>,
-        /// The `kind` column of the `dependencies` table.
+        created_at -> Timestamp,
+        /// The `completed_at` column of the `bulk_yanks` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Nullable<Timestamp>`.
         ///
         /// (Automatically generated by Diesel.)
-        kind -> Int4,
+        completed_at -> Nullable<Timestamp>,
+        /// The `versions_yanked` column of the `bulk_yanks` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        versions_yanked -> Nullable<Int4>,
     }
 }
 
@@ -480,46 +494,46 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `emails` table.
+    /// Representation of the `categories` table.
     ///
     /// (Automatically generated by Diesel.)
-    emails (id) {
-        /// The `id` column of the `emails` table.
+    categories (id) {
+        /// The `id` column of the `categories` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `user_id` column of the `emails` table.
+        /// The `category` column of the `categories` table.
         ///
-        /// Its SQL type is `Int4`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `email` column of the `emails` table.
+        category -> Varchar,
+        /// The `slug` column of the `categories` table.
         ///
         /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        email -> Varchar,
-        /// The `verified` column of the `emails` table.
+        slug -> Varchar,
+        /// The `description` column of the `categories` table.
         ///
-        /// Its SQL type is `Bool`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        verified -> Bool,
-        /// The `token` column of the `emails` table.
+        description -> Varchar,
+        /// The `crates_cnt` column of the `categories` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        token -> Text,
-        /// The `token_generated_at` column of the `emails` table.
+        crates_cnt -> Int4,
+        /// The `created_at` column of the `categories` table.
         ///
-        /// Its SQL type is `Nullable<Timestamp>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        token_generated_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
     }
 }
 
@@ -527,22 +541,46 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `follows` table.
+    /// Representation of the `category_stats` table.
     ///
     /// (Automatically generated by Diesel.)
-    follows (user_id, crate_id) {
-        /// The `user_id` column of the `follows` table.
+    category_stats (category_id, month) {
+        /// The `category_id` column of the `category_stats` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `crate_id` column of the `follows` table.
+        category_id -> Int4,
+        /// The `month` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        month -> Date,
+        /// The `crates_cnt` column of the `category_stats` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crate_id -> Int4,
+        crates_cnt -> Int4,
+        /// The `new_crates` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_crates -> Int4,
+        /// The `downloads` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+        /// The `updated_at` column of the `category_stats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
     }
 }
 
@@ -550,34 +588,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `keywords` table.
+    /// Representation of the `crate_dependents` table.
     ///
     /// (Automatically generated by Diesel.)
-    keywords (id) {
-        /// The `id` column of the `keywords` table.
+    crate_dependents (crate_id) {
+        /// The `crate_id` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        id -> Int4,
-        /// The `keyword` column of the `keywords` table.
+        crate_id -> Int4,
+        /// The `direct_dependents` column of the `crate_dependents` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        keyword -> Text,
-        /// The `crates_cnt` column of the `keywords` table.
+        direct_dependents -> Int4,
+        /// The `total_dependents` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        crates_cnt -> Int4,
-        /// The `created_at` column of the `keywords` table.
+        total_dependents -> Int4,
+        /// The `updated_at` column of the `crate_dependents` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -585,16 +623,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `metadata` table.
+    /// Representation of the `crate_list_entries` table.
     ///
     /// (Automatically generated by Diesel.)
-    metadata (total_downloads) {
-        /// The `total_downloads` column of the `metadata` table.
+    crate_list_entries (crate_list_id, crate_id) {
+        /// The `crate_list_id` column of the `crate_list_entries` table.
         ///
-        /// Its SQL type is `Int8`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        total_downloads -> Int8,
+        crate_list_id -> Int4,
+        /// The `crate_id` column of the `crate_list_entries` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `note` column of the `crate_list_entries` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        note -> Nullable<Varchar>,
+        /// The `created_at` column of the `crate_list_entries` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -602,28 +658,46 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_limit_buckets` table.
+    /// Representation of the `crate_lists` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_limit_buckets (user_id) {
-        /// The `user_id` column of the `publish_limit_buckets` table.
+    crate_lists (id) {
+        /// The `id` column of the `crate_lists` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `tokens` column of the `publish_limit_buckets` table.
+        id -> Int4,
+        /// The `user_id` column of the `crate_lists` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        tokens -> Int4,
-        /// The `last_refill` column of the `publish_limit_buckets` table.
+        user_id -> Int4,
+        /// The `name` column of the `crate_lists` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `description` column of the `crate_lists` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `created_at` column of the `crate_lists` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        last_refill -> Timestamp,
+        created_at -> Timestamp,
+        /// The `updated_at` column of the `crate_lists` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
     }
 }
 
@@ -631,22 +705,46 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `publish_rate_overrides` table.
+    /// Representation of the `crate_owner_invitations` table.
     ///
     /// (Automatically generated by Diesel.)
-    publish_rate_overrides (user_id) {
-        /// The `user_id` column of the `publish_rate_overrides` table.
+    crate_owner_invitations (invited_user_id, crate_id) {
+        /// The `invited_user_id` column of the `crate_owner_invitations` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        user_id -> Int4,
-        /// The `burst` column of the `publish_rate_overrides` table.
+        invited_user_id -> Int4,
+        /// The `invited_by_user_id` column of the `crate_owner_invitations` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        burst -> Int4,
+        invited_by_user_id -> Int4,
+        /// The `crate_id` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `created_at` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `token` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `token_generated_at` column of the `crate_owner_invitations` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token_generated_at -> Nullable<Timestamp>,
     }
 }
 
@@ -654,40 +752,1829 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `readme_renderings` table.
+    /// Representation of the `crate_owners` table.
     ///
     /// (Automatically generated by Diesel.)
-    readme_renderings (version_id) {
-        /// The `version_id` column of the `readme_renderings` table.
+    crate_owners (crate_id, owner_id, owner_kind) {
+        /// The `crate_id` column of the `crate_owners` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        version_id -> Int4,
-        /// The `rendered_at` column of the `readme_renderings` table.
+        crate_id -> Int4,
+        /// The `owner_id` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_id -> Int4,
+        /// The `created_at` column of the `crate_owners` table.
         ///
         /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        rendered_at -> Timestamp,
-    }
-}
-
-table! {
-    /// Representation of the `recent_crate_downloads` view.
-    ///
-    /// This data represents the downloads in the last 90 days.
-    /// This view does not contain realtime data.
-    /// It is refreshed by the `update-downloads` script.
-    recent_crate_downloads (crate_id) {
-        /// The `crate_id` column of the `recent_crate_downloads` view.
+        created_at -> Timestamp,
+        /// The `created_by` column of the `crate_owners` table.
         ///
-        /// Its SQL type is `Integer`.
-        crate_id -> Integer,
-        /// The `downloads` column of the `recent_crate_downloads` table.
+        /// Its SQL type is `Nullable<Int4>`.
         ///
-        /// Its SQL type is `BigInt`.
-        downloads -> BigInt,
+        /// (Automatically generated by Diesel.)
+        created_by -> Nullable<Int4>,
+        /// The `deleted` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted -> Bool,
+        /// The `updated_at` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `owner_kind` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_kind -> Int4,
+        /// The `email_notifications` column of the `crate_owners` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email_notifications -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crate_trending_scores` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crate_trending_scores (crate_id) {
+        /// The `crate_id` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `score` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Float8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        score -> Float8,
+        /// The `recent_downloads` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        recent_downloads -> Int8,
+        /// The `baseline_downloads` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        baseline_downloads -> Int8,
+        /// The `new_dependents` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_dependents -> Int4,
+        /// The `computed_at` column of the `crate_trending_scores` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        computed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates (id) {
+        /// The `id` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `name` column of the `crates` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `updated_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+        /// The `created_at` column of the `crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `downloads` column of the `crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int4,
+        /// The `description` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `homepage` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        homepage -> Nullable<Varchar>,
+        /// The `documentation` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        documentation -> Nullable<Varchar>,
+        /// The `readme` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        readme -> Nullable<Varchar>,
+        /// The `textsearchable_index_col` column of the `crates` table.
+        ///
+        /// Its SQL type is `Tsvector`.
+        ///
+        /// (Automatically generated by Diesel.)
+        textsearchable_index_col -> Tsvector,
+        /// The `repository` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository -> Nullable<Varchar>,
+        /// The `max_upload_size` column of the `crates` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        max_upload_size -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_categories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_categories (crate_id, category_id) {
+        /// The `crate_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `category_id` column of the `crates_categories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        category_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `crates_keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    crates_keywords (crate_id, keyword_id) {
+        /// The `crate_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `keyword_id` column of the `crates_keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `data_exports` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    data_exports (id) {
+        /// The `id` column of the `data_exports` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `data_exports` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `created_at` column of the `data_exports` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `completed_at` column of the `data_exports` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        completed_at -> Nullable<Timestamp>,
+        /// The `archive` column of the `data_exports` table.
+        ///
+        /// Its SQL type is `Nullable<Bytea>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        archive -> Nullable<Bytea>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `database_dumps` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    database_dumps (id) {
+        /// The `id` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `target_name` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target_name -> Text,
+        /// The `incremental` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        incremental -> Bool,
+        /// The `since` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        since -> Nullable<Timestamp>,
+        /// The `dumped_at` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        dumped_at -> Timestamp,
+        /// The `size` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        size -> Int8,
+        /// The `download_history` column of the `database_dumps` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        download_history -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `deleted_rows` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    deleted_rows (id) {
+        /// The `id` column of the `deleted_rows` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int8,
+        /// The `table_name` column of the `deleted_rows` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        table_name -> Varchar,
+        /// The `key` column of the `deleted_rows` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        key -> Jsonb,
+        /// The `deleted_at` column of the `deleted_rows` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        deleted_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `dependencies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    dependencies (id) {
+        /// The `id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `crate_id` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `req` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        req -> Varchar,
+        /// The `optional` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        optional -> Bool,
+        /// The `default_features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        default_features -> Bool,
+        /// The `features` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        features -> Array<Text>,
+        /// The `target` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        target -> Nullable<Varchar>,
+        /// The `kind` column of the `dependencies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `download_origins` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    download_origins (url) {
+        /// The `url` column of the `download_origins` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `healthy` column of the `download_origins` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        healthy -> Bool,
+        /// The `consecutive_failures` column of the `download_origins` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        consecutive_failures -> Int4,
+        /// The `last_error` column of the `download_origins` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_error -> Nullable<Varchar>,
+        /// The `checked_at` column of the `download_origins` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checked_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `email_changes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    email_changes (user_id) {
+        /// The `user_id` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `old_email_token` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        old_email_token -> Text,
+        /// The `new_email_token` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_email_token -> Text,
+        /// The `old_email_confirmed` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        old_email_confirmed -> Bool,
+        /// The `new_email_confirmed` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_email_confirmed -> Bool,
+        /// The `created_at` column of the `email_changes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `email_preferences` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    email_preferences (user_id) {
+        /// The `user_id` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `owner_invitations` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        owner_invitations -> Bool,
+        /// The `new_versions` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_versions -> Bool,
+        /// The `security_notices` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        security_notices -> Bool,
+        /// The `moderation` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        moderation -> Bool,
+        /// The `weekly_digest` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        weekly_digest -> Bool,
+        /// The `weekly_digest_sent_at` column of the `email_preferences` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        weekly_digest_sent_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `emails` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    emails (id) {
+        /// The `id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `emails` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `email` column of the `emails` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        email -> Varchar,
+        /// The `verified` column of the `emails` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified -> Bool,
+        /// The `token` column of the `emails` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Text,
+        /// The `token_generated_at` column of the `emails` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token_generated_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `feature_flags` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    feature_flags (name) {
+        /// The `name` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `description` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `enabled` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        enabled -> Bool,
+        /// The `percentage` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        percentage -> Int4,
+        /// The `user_ids` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Array<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_ids -> Array<Int4>,
+        /// The `updated_by` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_by -> Int4,
+        /// The `updated_at` column of the `feature_flags` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `featured_crates` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    featured_crates (id) {
+        /// The `id` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `starts_on` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        starts_on -> Date,
+        /// The `ends_on` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ends_on -> Date,
+        /// The `description` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        description -> Nullable<Varchar>,
+        /// The `admin_id` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Int4,
+        /// The `created_at` column of the `featured_crates` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `follows` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    follows (user_id, crate_id) {
+        /// The `user_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `github_responses` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    github_responses (key) {
+        /// The `key` column of the `github_responses` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        key -> Varchar,
+        /// The `etag` column of the `github_responses` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        etag -> Nullable<Varchar>,
+        /// The `body` column of the `github_responses` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        body -> Jsonb,
+        /// The `fetched_at` column of the `github_responses` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fetched_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `idempotency_keys` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    idempotency_keys (user_id, key) {
+        /// The `user_id` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `key` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        key -> Varchar,
+        /// The `fingerprint` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        fingerprint -> Varchar,
+        /// The `response_status` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        response_status -> Nullable<Int4>,
+        /// The `response_body` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Nullable<Bytea>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        response_body -> Nullable<Bytea>,
+        /// The `created_at` column of the `idempotency_keys` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keyword_stats` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keyword_stats (keyword_id, month) {
+        /// The `keyword_id` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword_id -> Int4,
+        /// The `month` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Date`.
+        ///
+        /// (Automatically generated by Diesel.)
+        month -> Date,
+        /// The `crates_cnt` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `new_crates` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        new_crates -> Int4,
+        /// The `downloads` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        downloads -> Int8,
+        /// The `updated_at` column of the `keyword_stats` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `keywords` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    keywords (id) {
+        /// The `id` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `keyword` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        keyword -> Text,
+        /// The `crates_cnt` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates_cnt -> Int4,
+        /// The `created_at` column of the `keywords` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `login_anomalies` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    login_anomalies (id) {
+        /// The `id` column of the `login_anomalies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `ip` column of the `login_anomalies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ip -> Varchar,
+        /// The `kind` column of the `login_anomalies` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Varchar,
+        /// The `attempts` column of the `login_anomalies` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        attempts -> Int4,
+        /// The `detected_at` column of the `login_anomalies` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        detected_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `login_attempts` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    login_attempts (id) {
+        /// The `id` column of the `login_attempts` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `ip` column of the `login_attempts` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ip -> Varchar,
+        /// The `endpoint` column of the `login_attempts` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        endpoint -> Varchar,
+        /// The `succeeded` column of the `login_attempts` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        succeeded -> Bool,
+        /// The `time` column of the `login_attempts` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `maintenance_mode` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    maintenance_mode (id) {
+        /// The `id` column of the `maintenance_mode` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Bool,
+        /// The `message` column of the `maintenance_mode` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        message -> Nullable<Varchar>,
+        /// The `admin_id` column of the `maintenance_mode` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Int4,
+        /// The `started_at` column of the `maintenance_mode` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        started_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `materialized_responses` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    materialized_responses (name) {
+        /// The `name` column of the `materialized_responses` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Varchar,
+        /// The `body` column of the `materialized_responses` table.
+        ///
+        /// Its SQL type is `Bytea`.
+        ///
+        /// (Automatically generated by Diesel.)
+        body -> Bytea,
+        /// The `refreshed_at` column of the `materialized_responses` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        refreshed_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `metadata` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    metadata (total_downloads) {
+        /// The `total_downloads` column of the `metadata` table.
+        ///
+        /// Its SQL type is `Int8`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_downloads -> Int8,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `mutes` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    mutes (id) {
+        /// The `id` column of the `mutes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `mutes` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `crate_id` column of the `mutes` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Nullable<Int4>,
+        /// The `muted_user_id` column of the `mutes` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        muted_user_id -> Nullable<Int4>,
+        /// The `created_at` column of the `mutes` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `notifications` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    notifications (id) {
+        /// The `id` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `kind` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `title` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        title -> Text,
+        /// The `crate_name` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Nullable<Varchar>,
+        /// The `created_at` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `read_at` column of the `notifications` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        read_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `pending_uploads` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    pending_uploads (version_id) {
+        /// The `version_id` column of the `pending_uploads` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `tarball` column of the `pending_uploads` table.
+        ///
+        /// Its SQL type is `Bytea`.
+        ///
+        /// (Automatically generated by Diesel.)
+        tarball -> Bytea,
+        /// The `jobs` column of the `pending_uploads` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        jobs -> Jsonb,
+        /// The `created_at` column of the `pending_uploads` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `project_links` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    project_links (id) {
+        /// The `id` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `crate_id` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `kind` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `url` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        url -> Varchar,
+        /// The `label` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        label -> Nullable<Varchar>,
+        /// The `created_at` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `from_manifest` column of the `project_links` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        from_manifest -> Bool,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_limit_buckets` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_limit_buckets (user_id) {
+        /// The `user_id` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `tokens` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        tokens -> Int4,
+        /// The `last_refill` column of the `publish_limit_buckets` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_refill -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_rate_limit_rejections` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_limit_rejections (id) {
+        /// The `id` column of the `publish_rate_limit_rejections` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `publish_rate_limit_rejections` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `time` column of the `publish_rate_limit_rejections` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_rate_override_actions` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_override_actions (id) {
+        /// The `id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `user_id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `admin_id` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Int4,
+        /// The `action` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        action -> Int4,
+        /// The `burst` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        burst -> Nullable<Int4>,
+        /// The `expires_at` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Nullable<Timestamp>,
+        /// The `time` column of the `publish_rate_override_actions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        time -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `publish_rate_overrides` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    publish_rate_overrides (user_id) {
+        /// The `user_id` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `burst` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        burst -> Int4,
+        /// The `expires_at` column of the `publish_rate_overrides` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expires_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `quota_overrides` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    quota_overrides (user_id) {
+        /// The `user_id` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `storage_bytes` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        storage_bytes -> Nullable<Int8>,
+        /// The `versions_per_day` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        versions_per_day -> Nullable<Int8>,
+        /// The `crates` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Nullable<Int8>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crates -> Nullable<Int8>,
+        /// The `admin_id` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Int4,
+        /// The `updated_at` column of the `quota_overrides` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `readme_renderings` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    readme_renderings (version_id) {
+        /// The `version_id` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `rendered_at` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_at -> Timestamp,
+        /// The `source_file_name` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        source_file_name -> Nullable<Varchar>,
+        /// The `rendered_size` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_size -> Nullable<Int4>,
+        /// The `source_size` column of the `readme_renderings` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        source_size -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `readme_rerenders` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    readme_rerenders (id) {
+        /// The `id` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `admin_id` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        admin_id -> Int4,
+        /// The `crate_name` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_name -> Nullable<Text>,
+        /// The `rendered_before` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rendered_before -> Timestamp,
+        /// The `batch_size` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        batch_size -> Int4,
+        /// The `total_versions` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        total_versions -> Int4,
+        /// The `processed_versions` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        processed_versions -> Int4,
+        /// The `failed_versions` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        failed_versions -> Int4,
+        /// The `last_version_id` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_version_id -> Int4,
+        /// The `created_at` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `completed_at` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        completed_at -> Nullable<Timestamp>,
+        /// The `cancelled_at` column of the `readme_rerenders` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        cancelled_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    /// Representation of the `recent_crate_downloads` view.
+    ///
+    /// This data represents the downloads in the last 90 days.
+    /// This view does not contain realtime data.
+    /// It is refreshed by the `update-downloads` script.
+    recent_crate_downloads (crate_id) {
+        /// The `crate_id` column of the `recent_crate_downloads` view.
+        ///
+        /// Its SQL type is `Integer`.
+        crate_id -> Integer,
+        /// The `downloads` column of the `recent_crate_downloads` table.
+        ///
+        /// Its SQL type is `BigInt`.
+        downloads -> BigInt,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `repository_verifications` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    repository_verifications (crate_id) {
+        /// The `crate_id` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        crate_id -> Int4,
+        /// The `repository` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        repository -> Varchar,
+        /// The `token` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        token -> Varchar,
+        /// The `requested_by` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        requested_by -> Int4,
+        /// The `created_at` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `verified_at` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified_at -> Nullable<Timestamp>,
+        /// The `last_checked_at` column of the `repository_verifications` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_checked_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `reserved_crate_names` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    reserved_crate_names (name) {
+        /// The `name` column of the `reserved_crate_names` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Text,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `scheduled_jobs` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    scheduled_jobs (name) {
+        /// The `name` column of the `scheduled_jobs` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Text,
+        /// The `schedule` column of the `scheduled_jobs` table.
+        ///
+        /// Its SQL type is `Text`.
+        ///
+        /// (Automatically generated by Diesel.)
+        schedule -> Text,
+        /// The `last_run_at` column of the `scheduled_jobs` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_run_at -> Nullable<Timestamp>,
+        /// The `next_run_at` column of the `scheduled_jobs` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        next_run_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `storage_mismatches` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    storage_mismatches (id) {
+        /// The `id` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `version_id` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `kind` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        kind -> Int4,
+        /// The `expected` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expected -> Nullable<Text>,
+        /// The `actual` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Nullable<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        actual -> Nullable<Text>,
+        /// The `detected_at` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        detected_at -> Timestamp,
+        /// The `resolved_by` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        resolved_by -> Nullable<Int4>,
+        /// The `resolved_at` column of the `storage_mismatches` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        resolved_at -> Nullable<Timestamp>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `teams` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    teams (id) {
+        /// The `id` column of the `teams` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        id -> Int4,
+        /// The `login` column of the `teams` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        login -> Varchar,
+        /// The `github_id` column of the `teams` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        github_id -> Int4,
+        /// The `name` column of the `teams` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        name -> Nullable<Varchar>,
+        /// The `avatar` column of the `teams` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        avatar -> Nullable<Varchar>,
+        /// The `org_id` column of the `teams` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        org_id -> Nullable<Int4>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `user_follows` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    user_follows (follower_id, followed_id) {
+        /// The `follower_id` column of the `user_follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        follower_id -> Int4,
+        /// The `followed_id` column of the `user_follows` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        followed_id -> Int4,
+        /// The `created_at` column of the `user_follows` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
     }
 }
 
@@ -695,16 +2582,52 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `reserved_crate_names` table.
+    /// Representation of the `user_sessions` table.
     ///
     /// (Automatically generated by Diesel.)
-    reserved_crate_names (name) {
-        /// The `name` column of the `reserved_crate_names` table.
+    user_sessions (id) {
+        /// The `id` column of the `user_sessions` table.
         ///
-        /// Its SQL type is `Text`.
+        /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        name -> Text,
+        id -> Int4,
+        /// The `user_id` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `hashed_token` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Bytea`.
+        ///
+        /// (Automatically generated by Diesel.)
+        hashed_token -> Bytea,
+        /// The `user_agent` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_agent -> Varchar,
+        /// The `created_at` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `last_used_at` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        last_used_at -> Timestamp,
+        /// The `revoked_at` column of the `user_sessions` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        revoked_at -> Nullable<Timestamp>,
     }
 }
 
@@ -712,46 +2635,34 @@ table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
 
-    /// Representation of the `teams` table.
+    /// Representation of the `username_history` table.
     ///
     /// (Automatically generated by Diesel.)
-    teams (id) {
-        /// The `id` column of the `teams` table.
+    username_history (id) {
+        /// The `id` column of the `username_history` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
         id -> Int4,
-        /// The `login` column of the `teams` table.
-        ///
-        /// Its SQL type is `Varchar`.
-        ///
-        /// (Automatically generated by Diesel.)
-        login -> Varchar,
-        /// The `github_id` column of the `teams` table.
+        /// The `user_id` column of the `username_history` table.
         ///
         /// Its SQL type is `Int4`.
         ///
         /// (Automatically generated by Diesel.)
-        github_id -> Int4,
-        /// The `name` column of the `teams` table.
-        ///
-        /// Its SQL type is `Nullable<Varchar>`.
-        ///
-        /// (Automatically generated by Diesel.)
-        name -> Nullable<Varchar>,
-        /// The `avatar` column of the `teams` table.
+        user_id -> Int4,
+        /// The `username` column of the `username_history` table.
         ///
-        /// Its SQL type is `Nullable<Varchar>`.
+        /// Its SQL type is `Varchar`.
         ///
         /// (Automatically generated by Diesel.)
-        avatar -> Nullable<Varchar>,
-        /// The `org_id` column of the `teams` table.
+        username -> Varchar,
+        /// The `changed_at` column of the `username_history` table.
         ///
-        /// Its SQL type is `Nullable<Int4>`.
+        /// Its SQL type is `Timestamp`.
         ///
         /// (Automatically generated by Diesel.)
-        org_id -> Nullable<Int4>,
+        changed_at -> Timestamp,
     }
 }
 
@@ -811,6 +2722,106 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         account_lock_until -> Nullable<Timestamp>,
+        /// The `is_admin` column of the `users` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        is_admin -> Bool,
+        /// The `created_at` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Nullable<Timestamp>,
+        /// The `followable` column of the `users` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        followable -> Bool,
+        /// The `display_name` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        display_name -> Nullable<Varchar>,
+        /// The `bio` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        bio -> Nullable<Varchar>,
+        /// The `website` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        website -> Nullable<Varchar>,
+        /// The `location` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        location -> Nullable<Varchar>,
+        /// The `username` column of the `users` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        username -> Nullable<Varchar>,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `verified_publishers` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    verified_publishers (user_id) {
+        /// The `user_id` column of the `verified_publishers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        user_id -> Int4,
+        /// The `verified_by` column of the `verified_publishers` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified_by -> Int4,
+        /// The `verified_at` column of the `verified_publishers` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        verified_at -> Timestamp,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_advisories` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_advisories (version_id, advisory_id) {
+        /// The `version_id` column of the `version_advisories` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `advisory_id` column of the `version_advisories` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        advisory_id -> Varchar,
     }
 }
 
@@ -843,6 +2854,41 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_checksums` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_checksums (version_id, algorithm) {
+        /// The `version_id` column of the `version_checksums` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `algorithm` column of the `version_checksums` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        algorithm -> Varchar,
+        /// The `digest` column of the `version_checksums` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        digest -> Varchar,
+        /// The `created_at` column of the `version_checksums` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -884,6 +2930,41 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_licenses` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_licenses (version_id) {
+        /// The `version_id` column of the `version_licenses` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `expression` column of the `version_licenses` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        expression -> Varchar,
+        /// The `licenses` column of the `version_licenses` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        licenses -> Array<Text>,
+        /// The `exceptions` column of the `version_licenses` table.
+        ///
+        /// Its SQL type is `Array<Text>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        exceptions -> Array<Text>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -931,6 +3012,88 @@ table! {
     }
 }
 
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_publish_origins` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_publish_origins (version_id) {
+        /// The `version_id` column of the `version_publish_origins` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `ip_class` column of the `version_publish_origins` table.
+        ///
+        /// Its SQL type is `Varchar`.
+        ///
+        /// (Automatically generated by Diesel.)
+        ip_class -> Varchar,
+    }
+}
+
+table! {
+    use diesel::sql_types::*;
+    use diesel_full_text_search::{TsVector as Tsvector};
+
+    /// Representation of the `version_quarantines` table.
+    ///
+    /// (Automatically generated by Diesel.)
+    version_quarantines (version_id) {
+        /// The `version_id` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        version_id -> Int4,
+        /// The `status` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Int4`.
+        ///
+        /// (Automatically generated by Diesel.)
+        status -> Int4,
+        /// The `findings` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Jsonb`.
+        ///
+        /// (Automatically generated by Diesel.)
+        findings -> Jsonb,
+        /// The `created_at` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Timestamp`.
+        ///
+        /// (Automatically generated by Diesel.)
+        created_at -> Timestamp,
+        /// The `reviewed_by` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Nullable<Int4>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reviewed_by -> Nullable<Int4>,
+        /// The `reviewed_at` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        reviewed_at -> Nullable<Timestamp>,
+        /// The `held_index_entry` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Nullable<Jsonb>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        held_index_entry -> Nullable<Jsonb>,
+        /// The `release_at` column of the `version_quarantines` table.
+        ///
+        /// Its SQL type is `Nullable<Timestamp>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        release_at -> Nullable<Timestamp>,
+    }
+}
+
 table! {
     use diesel::sql_types::*;
     use diesel_full_text_search::{TsVector as Tsvector};
@@ -1005,6 +3168,24 @@ table! {
         ///
         /// (Automatically generated by Diesel.)
         published_by -> Nullable<Int4>,
+        /// The `checksum` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        checksum -> Nullable<Varchar>,
+        /// The `stored_by_checksum` column of the `versions` table.
+        ///
+        /// Its SQL type is `Bool`.
+        ///
+        /// (Automatically generated by Diesel.)
+        stored_by_checksum -> Bool,
+        /// The `rust_version` column of the `versions` table.
+        ///
+        /// Its SQL type is `Nullable<Varchar>`.
+        ///
+        /// (Automatically generated by Diesel.)
+        rust_version -> Nullable<Varchar>,
     }
 }
 
@@ -1031,59 +3212,144 @@ table! {
     }
 }
 
+joinable!(advisories -> users (published_by));
+joinable!(announcements -> users (admin_id));
 joinable!(api_tokens -> users (user_id));
 joinable!(badges -> crates (crate_id));
+joinable!(bulk_yank_versions -> bulk_yanks (bulk_yank_id));
+joinable!(bulk_yank_versions -> versions (version_id));
+joinable!(bulk_yanks -> api_tokens (api_token_id));
+joinable!(category_stats -> categories (category_id));
+joinable!(crate_dependents -> crates (crate_id));
+joinable!(crate_list_entries -> crate_lists (crate_list_id));
+joinable!(crate_list_entries -> crates (crate_id));
+joinable!(crate_lists -> users (user_id));
 joinable!(crate_owner_invitations -> crates (crate_id));
 joinable!(crate_owners -> crates (crate_id));
 joinable!(crate_owners -> teams (owner_id));
 joinable!(crate_owners -> users (owner_id));
+joinable!(crate_trending_scores -> crates (crate_id));
 joinable!(crates_categories -> categories (category_id));
 joinable!(crates_categories -> crates (crate_id));
 joinable!(crates_keywords -> crates (crate_id));
 joinable!(crates_keywords -> keywords (keyword_id));
+joinable!(data_exports -> users (user_id));
 joinable!(dependencies -> crates (crate_id));
 joinable!(dependencies -> versions (version_id));
+joinable!(email_changes -> users (user_id));
+joinable!(email_preferences -> users (user_id));
 joinable!(emails -> users (user_id));
+joinable!(feature_flags -> users (updated_by));
+joinable!(featured_crates -> crates (crate_id));
+joinable!(featured_crates -> users (admin_id));
 joinable!(follows -> crates (crate_id));
 joinable!(follows -> users (user_id));
+joinable!(idempotency_keys -> users (user_id));
+joinable!(keyword_stats -> keywords (keyword_id));
+joinable!(maintenance_mode -> users (admin_id));
+joinable!(mutes -> crates (crate_id));
+joinable!(notifications -> users (user_id));
+joinable!(pending_uploads -> versions (version_id));
+joinable!(project_links -> crates (crate_id));
 joinable!(publish_limit_buckets -> users (user_id));
+joinable!(publish_rate_limit_rejections -> users (user_id));
 joinable!(publish_rate_overrides -> users (user_id));
+joinable!(quota_overrides -> users (user_id));
 joinable!(readme_renderings -> versions (version_id));
+joinable!(readme_rerenders -> users (admin_id));
 joinable!(recent_crate_downloads -> crates (crate_id));
+joinable!(repository_verifications -> crates (crate_id));
+joinable!(repository_verifications -> users (requested_by));
+joinable!(storage_mismatches -> users (resolved_by));
+joinable!(storage_mismatches -> versions (version_id));
+joinable!(user_sessions -> users (user_id));
+joinable!(username_history -> users (user_id));
+joinable!(version_advisories -> advisories (advisory_id));
+joinable!(version_advisories -> versions (version_id));
 joinable!(version_authors -> versions (version_id));
+joinable!(version_checksums -> versions (version_id));
 joinable!(version_downloads -> versions (version_id));
+joinable!(version_licenses -> versions (version_id));
 joinable!(version_owner_actions -> api_tokens (api_token_id));
 joinable!(version_owner_actions -> users (user_id));
 joinable!(version_owner_actions -> versions (version_id));
+joinable!(version_publish_origins -> versions (version_id));
+joinable!(version_quarantines -> users (reviewed_by));
+joinable!(version_quarantines -> versions (version_id));
 joinable!(versions -> crates (crate_id));
 joinable!(versions -> users (published_by));
 joinable!(versions_published_by -> versions (version_id));
 
 allow_tables_to_appear_in_same_query!(
+    advisories,
+    announcements,
     api_tokens,
+    background_job_runs,
     background_jobs,
     badges,
+    bulk_yank_versions,
+    bulk_yanks,
     categories,
+    category_stats,
+    crate_dependents,
+    crate_list_entries,
+    crate_lists,
     crate_owner_invitations,
     crate_owners,
+    crate_trending_scores,
     crates,
     crates_categories,
     crates_keywords,
+    data_exports,
+    database_dumps,
+    deleted_rows,
     dependencies,
+    download_origins,
+    email_changes,
+    email_preferences,
     emails,
+    feature_flags,
+    featured_crates,
     follows,
+    github_responses,
+    idempotency_keys,
+    keyword_stats,
     keywords,
+    login_anomalies,
+    login_attempts,
+    maintenance_mode,
+    materialized_responses,
     metadata,
+    mutes,
+    notifications,
+    pending_uploads,
+    project_links,
     publish_limit_buckets,
+    publish_rate_limit_rejections,
+    publish_rate_override_actions,
     publish_rate_overrides,
+    quota_overrides,
     readme_renderings,
+    readme_rerenders,
     recent_crate_downloads,
+    repository_verifications,
     reserved_crate_names,
+    scheduled_jobs,
+    storage_mismatches,
     teams,
+    user_follows,
+    user_sessions,
+    username_history,
     users,
+    verified_publishers,
+    version_advisories,
     version_authors,
+    version_checksums,
     version_downloads,
+    version_licenses,
     version_owner_actions,
+    version_publish_origins,
+    version_quarantines,
     versions,
     versions_published_by,
 );
@@ -0,0 +1,331 @@
+//! The hyper service that runs the conduit handler of the app.
+//!
+//! Connections are accepted and request and response bodies are transferred
+//! asynchronously by tokio. The middleware and the endpoints still run
+//! synchronously, on one of tokio's blocking threads, so `SERVER_THREADS`
+//! bounds the number of requests that are handled at a time.
+//!
+//! Unlike `conduit_hyper`, which buffers both bodies in memory, the body of a
+//! request is read by the endpoint while it is uploaded, and a response body
+//! from a file is sent in chunks of [`FILE_CHUNK_SIZE`] without holding a
+//! blocking thread.
+
+use std::convert::Infallible;
+use std::io::{self, Cursor, Read};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use conduit::{header, Handler, HeaderMap, Host, Method, RequestExt, Scheme, StatusCode, Version};
+use hyper::body::{Bytes, HttpBody};
+use percent_encoding::percent_decode;
+use tokio::io::AsyncReadExt;
+use tokio::runtime::Handle;
+
+/// The size of the chunks a response body from a file is sent in
+pub const FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Handles a request with the conduit `handler` on a blocking thread.
+///
+/// Errors returned by the handler and panics are logged and answered with a
+/// `500 Internal Server Error`.
+pub async fn serve<H: Handler>(
+    handler: Arc<H>,
+    request: hyper::Request<hyper::Body>,
+    remote_addr: SocketAddr,
+) -> Result<hyper::Response<hyper::Body>, Infallible> {
+    let runtime = Handle::current();
+    let response = tokio::task::spawn_blocking(move || {
+        let mut request = ConduitRequest::new(request, remote_addr, runtime);
+        handler.call(&mut request)
+    })
+    .await;
+
+    Ok(match response {
+        Ok(Ok(response)) => into_hyper_response(response),
+        Ok(Err(error)) => {
+            error!(%error, "Unhandled error in the request handler");
+            server_error()
+        }
+        Err(error) => {
+            error!(%error, "The request handler panicked");
+            server_error()
+        }
+    })
+}
+
+fn into_hyper_response(response: conduit::Response<conduit::Body>) -> hyper::Response<hyper::Body> {
+    let (parts, body) = response.into_parts();
+    let body = match body {
+        conduit::Body::Static(bytes) => hyper::Body::from(bytes),
+        conduit::Body::Owned(bytes) => hyper::Body::from(bytes),
+        conduit::Body::File(file) => stream_file(file),
+    };
+    hyper::Response::from_parts(parts, body)
+}
+
+/// Sends the rest of the file from its current position, which the `Range`
+/// requests middleware may have moved
+fn stream_file(file: std::fs::File) -> hyper::Body {
+    let (mut sender, body) = hyper::Body::channel();
+    tokio::spawn(async move {
+        let mut file = tokio::fs::File::from_std(file);
+        let mut buffer = vec![0; FILE_CHUNK_SIZE];
+        loop {
+            match file.read(&mut buffer).await {
+                Ok(0) => break,
+                Ok(read) => {
+                    let chunk = Bytes::copy_from_slice(&buffer[..read]);
+                    if sender.send_data(chunk).await.is_err() {
+                        // The client closed the connection
+                        break;
+                    }
+                }
+                Err(error) => {
+                    warn!(%error, "Failed to read a response body from its file");
+                    sender.abort();
+                    break;
+                }
+            }
+        }
+    });
+    body
+}
+
+fn server_error() -> hyper::Response<hyper::Body> {
+    hyper::Response::builder()
+        .status(StatusCode::INTERNAL_SERVER_ERROR)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(hyper::Body::from("Internal Server Error"))
+        .unwrap() // Header values are well formed, so should not panic
+}
+
+/// A request as seen by the conduit handler, reading its body from hyper
+struct ConduitRequest {
+    parts: http::request::Parts,
+    path: String,
+    remote_addr: SocketAddr,
+    body: BodyReader,
+    extensions: conduit::Extensions,
+}
+
+impl ConduitRequest {
+    fn new(request: hyper::Request<hyper::Body>, remote_addr: SocketAddr, runtime: Handle) -> Self {
+        let (parts, body) = request.into_parts();
+        let path = percent_decode(parts.uri.path().as_bytes())
+            .decode_utf8_lossy()
+            .into_owned();
+        Self {
+            parts,
+            path,
+            remote_addr,
+            body: BodyReader {
+                body,
+                chunk: Cursor::new(Bytes::new()),
+                runtime,
+            },
+            extensions: conduit::Extensions::new(),
+        }
+    }
+}
+
+impl RequestExt for ConduitRequest {
+    fn method(&self) -> &Method {
+        &self.parts.method
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn path_mut(&mut self) -> &mut String {
+        &mut self.path
+    }
+
+    fn http_version(&self) -> Version {
+        self.parts.version
+    }
+
+    /// TLS is terminated before the requests reach the server
+    fn scheme(&self) -> Scheme {
+        Scheme::Http
+    }
+
+    fn host(&self) -> Host<'_> {
+        let host = self
+            .parts
+            .headers
+            .get(header::HOST)
+            .and_then(|host| host.to_str().ok())
+            .unwrap_or_default();
+        Host::Name(host)
+    }
+
+    fn virtual_root(&self) -> Option<&str> {
+        None
+    }
+
+    fn query_string(&self) -> Option<&str> {
+        self.parts.uri.query()
+    }
+
+    fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    /// The `Content-Length` the client sent. Hyper ends the body after this
+    /// many bytes.
+    fn content_length(&self) -> Option<u64> {
+        self.parts
+            .headers
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok())
+            .and_then(|length| length.parse().ok())
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        &self.parts.headers
+    }
+
+    fn body(&mut self) -> &mut dyn Read {
+        &mut self.body
+    }
+
+    fn extensions(&self) -> &conduit::Extensions {
+        &self.extensions
+    }
+
+    fn mut_extensions(&mut self) -> &mut conduit::Extensions {
+        &mut self.extensions
+    }
+}
+
+/// Reads the request body from hyper as it arrives, blocking the thread of
+/// the handler until the next chunk is received
+struct BodyReader {
+    body: hyper::Body,
+    chunk: Cursor<Bytes>,
+    runtime: Handle,
+}
+
+impl Read for BodyReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = Read::read(&mut self.chunk, buf)?;
+            if read > 0 || buf.is_empty() {
+                return Ok(read);
+            }
+            match self.runtime.block_on(self.body.data()) {
+                Some(Ok(chunk)) => self.chunk = Cursor::new(chunk),
+                Some(Err(error)) => return Err(io::Error::new(io::ErrorKind::Other, error)),
+                None => return Ok(0),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use conduit::{box_error, Body, HandlerResult, Response};
+    use std::io::{Seek, SeekFrom, Write};
+
+    /// Echoes the decoded path, the query string and the body of the request
+    struct Echo;
+
+    impl Handler for Echo {
+        fn call(&self, req: &mut dyn RequestExt) -> HandlerResult {
+            let mut body = Vec::new();
+            req.body().read_to_end(&mut body).map_err(box_error)?;
+            let echo = format!(
+                "{} {} {:?} {}",
+                req.method(),
+                req.path(),
+                req.query_string(),
+                String::from_utf8_lossy(&body)
+            );
+            Ok(Response::new(Body::from_vec(echo.into_bytes())))
+        }
+    }
+
+    /// Responds with a file whose first byte was already read
+    struct FromFile;
+
+    impl Handler for FromFile {
+        fn call(&self, _req: &mut dyn RequestExt) -> HandlerResult {
+            let mut file = tempfile::tempfile().map_err(box_error)?;
+            file.write_all(&vec![b'x'; FILE_CHUNK_SIZE * 2 + 10])
+                .map_err(box_error)?;
+            file.seek(SeekFrom::Start(1)).map_err(box_error)?;
+            Ok(Response::new(Body::File(file)))
+        }
+    }
+
+    struct Failing;
+
+    impl Handler for Failing {
+        fn call(&self, _req: &mut dyn RequestExt) -> HandlerResult {
+            Err(box_error(io::Error::new(io::ErrorKind::Other, "failed")))
+        }
+    }
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    fn request(body: hyper::Body) -> hyper::Request<hyper::Body> {
+        hyper::Request::post("/api/v1/crates/foo%2Bbar?page=2")
+            .body(body)
+            .unwrap()
+    }
+
+    async fn response_body(response: hyper::Response<hyper::Body>) -> Vec<u8> {
+        hyper::body::to_bytes(response.into_body())
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[test]
+    fn request_bodies_are_read_as_they_arrive() {
+        let addr = ([127, 0, 0, 1], 8888).into();
+        runtime().block_on(async {
+            let (mut sender, body) = hyper::Body::channel();
+            let response = tokio::spawn(serve(Arc::new(Echo), request(body), addr));
+            sender.send_data("hello ".into()).await.unwrap();
+            sender.send_data("world".into()).await.unwrap();
+            drop(sender);
+
+            let response = response.await.unwrap().unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(
+                response_body(response).await,
+                b"POST /api/v1/crates/foo+bar Some(\"page=2\") hello world"
+            );
+        });
+    }
+
+    #[test]
+    fn file_bodies_are_streamed_from_their_position() {
+        let addr = ([127, 0, 0, 1], 8888).into();
+        runtime().block_on(async {
+            let response = serve(Arc::new(FromFile), request(hyper::Body::empty()), addr)
+                .await
+                .unwrap();
+            assert_eq!(response_body(response).await.len(), FILE_CHUNK_SIZE * 2 + 9);
+        });
+    }
+
+    #[test]
+    fn handler_errors_are_server_errors() {
+        let addr = ([127, 0, 0, 1], 8888).into();
+        runtime().block_on(async {
+            let response = serve(Arc::new(Failing), request(hyper::Body::empty()), addr)
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        });
+    }
+}
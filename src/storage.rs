@@ -0,0 +1,341 @@
+//! The backends that crate files, readmes and database dumps are stored in
+//!
+//! The `Uploader` writes every file to its primary backend, and to a
+//! secondary backend if one is configured, so that the files are stored
+//! twice. Files the application reads itself, like crate files for
+//! rendering readmes and scanning, are read from the secondary backend if
+//! the primary backend fails. Downloads by cargo are always redirected to
+//! the primary backend.
+//!
+//! The primary backend is S3 if `S3_BUCKET` is set, Google Cloud Storage if
+//! `GCS_BUCKET` is set and the `local_uploads` directory otherwise, which is
+//! only allowed during development. `STORAGE_SECONDARY` can be set to `s3`,
+//! `gcs` or `local` to choose the secondary backend, which uses the same
+//! variables.
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::{Body, Client};
+use reqwest::header;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// The content of a file that is stored
+pub type Content = Box<dyn Read + Send>;
+
+pub trait Storage: fmt::Debug + Send + Sync {
+    /// The name of the kind of backend, as used by `STORAGE_SECONDARY`
+    fn kind(&self) -> &'static str;
+
+    /// The host files are served from, unless the application serves them
+    /// itself
+    fn host(&self) -> Option<String>;
+
+    /// The URL files are downloaded from, which doesn't check whether the
+    /// file exists
+    fn location(&self, path: &str) -> String;
+
+    /// Stores the file, returning where it was stored
+    fn put(
+        &self,
+        client: &Client,
+        path: &str,
+        content: Content,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>>;
+
+    fn get(&self, client: &Client, path: &str) -> Result<Vec<u8>>;
+}
+
+/// The backend that is written to besides the primary backend, if
+/// `STORAGE_SECONDARY` is set
+pub fn secondary_from_environment(api_protocol: &str) -> Option<Box<dyn Storage>> {
+    let kind = dotenv::var("STORAGE_SECONDARY").ok()?;
+    let storage: Box<dyn Storage> = match &*kind {
+        "s3" => Box::new(S3::from_environment(api_protocol)),
+        "gcs" => Box::new(Gcs::from_environment()),
+        "local" => Box::new(FileSystem::local_uploads()),
+        _ => panic!("STORAGE_SECONDARY must be `s3`, `gcs` or `local`"),
+    };
+    Some(storage)
+}
+
+/// Files in a directory, served by the application itself
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    root: PathBuf,
+}
+
+impl FileSystem {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// The `local_uploads` directory in the working directory, which the
+    /// application serves during development
+    pub fn local_uploads() -> Self {
+        Self::new(PathBuf::from("local_uploads"))
+    }
+}
+
+impl Storage for FileSystem {
+    fn kind(&self) -> &'static str {
+        "local"
+    }
+
+    fn host(&self) -> Option<String> {
+        None
+    }
+
+    fn location(&self, path: &str) -> String {
+        format!("/{}", path)
+    }
+
+    fn put(
+        &self,
+        _client: &Client,
+        path: &str,
+        mut content: Content,
+        _content_length: u64,
+        _content_type: &str,
+        _extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>> {
+        let filename = std::env::current_dir()?.join(&self.root).join(path);
+        if let Some(dir) = filename.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(&filename)?;
+        std::io::copy(&mut content, &mut file)?;
+        Ok(filename.to_str().map(String::from))
+    }
+
+    fn get(&self, _client: &Client, path: &str) -> Result<Vec<u8>> {
+        let filename = std::env::current_dir()?.join(&self.root).join(path);
+        let mut body = Vec::new();
+        File::open(filename)?.read_to_end(&mut body)?;
+        Ok(body)
+    }
+}
+
+/// An S3 bucket, optionally served through a CDN.
+/// For test usage with `TestApp::with_proxy()`, the recording proxy is used.
+#[derive(Debug, Clone)]
+pub struct S3 {
+    pub bucket: s3::Bucket,
+    pub cdn: Option<String>,
+}
+
+impl S3 {
+    /// The bucket of `S3_BUCKET` in `S3_REGION`, with the credentials in
+    /// `S3_ACCESS_KEY` and `S3_SECRET_KEY` if they are set
+    pub fn from_environment(api_protocol: &str) -> Self {
+        Self {
+            bucket: s3::Bucket::new(
+                crate::env("S3_BUCKET"),
+                dotenv::var("S3_REGION").ok(),
+                dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
+                dotenv::var("S3_SECRET_KEY").unwrap_or_default(),
+                api_protocol,
+            ),
+            cdn: dotenv::var("S3_CDN").ok(),
+        }
+    }
+}
+
+impl Storage for S3 {
+    fn kind(&self) -> &'static str {
+        "s3"
+    }
+
+    fn host(&self) -> Option<String> {
+        Some(self.cdn.clone().unwrap_or_else(|| self.bucket.host()))
+    }
+
+    fn location(&self, path: &str) -> String {
+        let host = self.cdn.clone().unwrap_or_else(|| self.bucket.host());
+        format!("https://{}/{}", host, path)
+    }
+
+    fn put(
+        &self,
+        client: &Client,
+        path: &str,
+        content: Content,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>> {
+        self.bucket.put(
+            client,
+            path,
+            content,
+            content_length,
+            content_type,
+            extra_headers,
+        )?;
+        Ok(Some(String::from(path)))
+    }
+
+    fn get(&self, client: &Client, path: &str) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        let mut response = client
+            .get(&self.location(path))
+            .send()?
+            .error_for_status()?;
+        response.read_to_end(&mut body)?;
+        Ok(body)
+    }
+}
+
+/// The Google Cloud Storage API
+const GCS_HOST: &str = "storage.googleapis.com";
+
+/// The endpoint of the metadata server of Google Cloud instances that hands
+/// out access tokens for their service account
+const GCS_METADATA_TOKEN_URL: &str =
+    "http://metadata.google.internal/computeMetadata/v1/instance/service-accounts/default/token";
+
+/// A Google Cloud Storage bucket, optionally served through a CDN.
+///
+/// Requests are authorized with the OAuth access token in
+/// `GCS_ACCESS_TOKEN`, or otherwise with a token of the service account of
+/// the instance, which is requested from the metadata server.
+#[derive(Debug)]
+pub struct Gcs {
+    bucket: String,
+    cdn: Option<String>,
+    access_token: Option<String>,
+    /// The token from the metadata server, with when it expires
+    cached_token: Mutex<Option<(String, Instant)>>,
+}
+
+impl Gcs {
+    pub fn new(bucket: String, cdn: Option<String>, access_token: Option<String>) -> Self {
+        Self {
+            bucket,
+            cdn,
+            access_token,
+            cached_token: Mutex::new(None),
+        }
+    }
+
+    /// The bucket of `GCS_BUCKET`, served through `GCS_CDN` if it is set
+    pub fn from_environment() -> Self {
+        Self::new(
+            crate::env("GCS_BUCKET"),
+            dotenv::var("GCS_CDN").ok(),
+            dotenv::var("GCS_ACCESS_TOKEN").ok(),
+        )
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("https://{}/{}/{}", GCS_HOST, self.bucket, path)
+    }
+
+    fn access_token(&self, client: &Client) -> Result<String> {
+        if let Some(token) = &self.access_token {
+            return Ok(token.clone());
+        }
+
+        let mut cached = self.cached_token.lock().unwrap();
+        if let Some((token, expires_at)) = &*cached {
+            if Instant::now() < *expires_at {
+                return Ok(token.clone());
+            }
+        }
+
+        #[derive(Deserialize)]
+        struct Token {
+            access_token: String,
+            expires_in: u64,
+        }
+        let token: Token = client
+            .get(GCS_METADATA_TOKEN_URL)
+            .header("Metadata-Flavor", "Google")
+            .send()?
+            .error_for_status()?
+            .json()
+            .context("failed to get an access token from the metadata server")?;
+        // Refreshed a minute early, so that it doesn't expire during a request
+        let expires_at = Instant::now() + Duration::from_secs(token.expires_in.saturating_sub(60));
+        *cached = Some((token.access_token.clone(), expires_at));
+        Ok(token.access_token)
+    }
+}
+
+impl Storage for Gcs {
+    fn kind(&self) -> &'static str {
+        "gcs"
+    }
+
+    fn host(&self) -> Option<String> {
+        Some(self.cdn.clone().unwrap_or_else(|| GCS_HOST.into()))
+    }
+
+    fn location(&self, path: &str) -> String {
+        match &self.cdn {
+            Some(cdn) => format!("https://{}/{}", cdn, path),
+            None => self.url(path),
+        }
+    }
+
+    fn put(
+        &self,
+        client: &Client,
+        path: &str,
+        content: Content,
+        content_length: u64,
+        content_type: &str,
+        extra_headers: header::HeaderMap,
+    ) -> Result<Option<String>> {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        client
+            .put(&self.url(path))
+            .bearer_auth(self.access_token(client)?)
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::USER_AGENT, "crates.io (https://crates.io)")
+            .headers(extra_headers)
+            .body(Body::sized(content, content_length))
+            .send()?
+            .error_for_status()
+            .map_err(|e| anyhow!("failed to upload `{}` to Google Cloud Storage: {}", path, e))?;
+        Ok(Some(String::from(path)))
+    }
+
+    fn get(&self, client: &Client, path: &str) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        let mut response = client
+            .get(&self.url(path))
+            .bearer_auth(self.access_token(client)?)
+            .send()?
+            .error_for_status()?;
+        response.read_to_end(&mut body)?;
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gcs_files_are_served_from_the_cdn() {
+        let gcs = Gcs::new("crates-io".into(), None, Some("token".into()));
+        assert_eq!(
+            gcs.location("crates/foo/foo-1.0.0.crate"),
+            "https://storage.googleapis.com/crates-io/crates/foo/foo-1.0.0.crate"
+        );
+        assert_eq!(gcs.host().as_deref(), Some("storage.googleapis.com"));
+
+        let gcs = Gcs::new("crates-io".into(), Some("static.crates.io".into()), None);
+        assert_eq!(
+            gcs.location("crates/foo/foo-1.0.0.crate"),
+            "https://static.crates.io/crates/foo/foo-1.0.0.crate"
+        );
+    }
+}
@@ -0,0 +1,16 @@
+//! The runner for background jobs, originally vendored from the `swirl` crate.
+//!
+//! Jobs are loaded from the `background_jobs` table one at a time, with the
+//! row locked for as long as the job is running. Every [`Queue`] has its own
+//! limit on the number of jobs that run concurrently, and within a queue jobs
+//! with a higher priority are picked first.
+//!
+//! [`Queue`]: crate::background_jobs::Queue
+
+mod errors;
+mod runner;
+mod storage;
+
+pub use self::errors::{EnqueueError, FailedJobsError, FetchError, PerformError};
+pub use self::runner::{Runner, RunnerBuilder};
+pub use self::storage::{lock_idle_job, running_job_ids};
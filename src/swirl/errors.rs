@@ -0,0 +1,121 @@
+use diesel::r2d2::PoolError;
+use diesel::result::Error as DieselError;
+use std::error::Error;
+use std::fmt;
+
+/// An error occurred queueing the job
+#[derive(Debug)]
+pub enum EnqueueError {
+    /// An error occurred serializing the job
+    SerializationError(serde_json::error::Error),
+
+    /// An error occurred inserting the job into the database
+    DatabaseError(DieselError),
+}
+
+impl From<serde_json::error::Error> for EnqueueError {
+    fn from(e: serde_json::error::Error) -> Self {
+        EnqueueError::SerializationError(e)
+    }
+}
+
+impl From<DieselError> for EnqueueError {
+    fn from(e: DieselError) -> Self {
+        EnqueueError::DatabaseError(e)
+    }
+}
+
+impl fmt::Display for EnqueueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EnqueueError::SerializationError(e) => e.fmt(f),
+            EnqueueError::DatabaseError(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for EnqueueError {}
+
+/// An error occurred performing the job
+pub type PerformError = Box<dyn Error>;
+
+/// An error occurred while attempting to fetch jobs from the queue
+#[derive(Debug)]
+pub enum FetchError {
+    /// We could not acquire a database connection from the pool.
+    ///
+    /// Either the connection pool is too small, or new connections cannot be
+    /// established.
+    NoDatabaseConnection(PoolError),
+
+    /// Could not execute the query to load a job from the database.
+    FailedLoadingJob(DieselError),
+
+    /// No message was received from the worker thread.
+    ///
+    /// Either the thread pool is too small, or jobs have hung indefinitely
+    NoMessageReceived,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::NoDatabaseConnection(e) => {
+                write!(f, "Timed out acquiring a database connection. ")?;
+                write!(f, "Try increasing the connection pool size: ")?;
+                write!(f, "{}", e)?;
+            }
+            FetchError::FailedLoadingJob(e) => {
+                write!(f, "An error occurred loading a job from the database: ")?;
+                write!(f, "{}", e)?;
+            }
+            FetchError::NoMessageReceived => {
+                write!(f, "No message was received from the worker thread. ")?;
+                write!(f, "Try increasing the thread pool size or timeout period.")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Error for FetchError {}
+
+/// An error returned by `Runner::check_for_failed_jobs`. Only used in tests.
+#[derive(Debug)]
+pub enum FailedJobsError {
+    /// Jobs failed to run
+    JobsFailed(
+        /// The number of failed jobs
+        i64,
+    ),
+
+    /// The failed jobs could not be counted
+    DatabaseError(DieselError),
+
+    /// We could not acquire a database connection to count the failed jobs
+    NoDatabaseConnection(PoolError),
+}
+
+impl From<DieselError> for FailedJobsError {
+    fn from(e: DieselError) -> Self {
+        FailedJobsError::DatabaseError(e)
+    }
+}
+
+impl From<PoolError> for FailedJobsError {
+    fn from(e: PoolError) -> Self {
+        FailedJobsError::NoDatabaseConnection(e)
+    }
+}
+
+impl fmt::Display for FailedJobsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailedJobsError::JobsFailed(x) => write!(f, "{} jobs failed", x),
+            FailedJobsError::DatabaseError(e) => e.fmt(f),
+            FailedJobsError::NoDatabaseConnection(e) => e.fmt(f),
+        }
+    }
+}
+
+impl Error for FailedJobsError {}
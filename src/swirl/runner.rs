@@ -0,0 +1,332 @@
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel::r2d2::PoolError;
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, SyncSender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use super::errors::*;
+use super::storage;
+use crate::background_jobs::{Environment, Job, Queue};
+use crate::db::DieselPool;
+use crate::util::request_id;
+
+/// The default amount of time to wait for a worker thread to pick up a job
+const DEFAULT_JOB_START_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The message sent by a worker thread after looking for its first job
+enum Event {
+    Working,
+    NoJobAvailable,
+    ErrorLoadingJob(diesel::result::Error),
+    FailedToAcquireConnection(PoolError),
+}
+
+#[allow(missing_debug_implementations)]
+pub struct RunnerBuilder {
+    environment: Environment,
+    connection_pool: Option<DieselPool>,
+    concurrency: HashMap<Queue, usize>,
+    job_start_timeout: Option<Duration>,
+}
+
+impl RunnerBuilder {
+    /// Sets the connection pool used by the worker threads. Every running job
+    /// holds one connection, so the pool should have at least as many
+    /// connections as the sum of the concurrency of all queues.
+    pub fn connection_pool(mut self, connection_pool: DieselPool) -> Self {
+        self.connection_pool = Some(connection_pool);
+        self
+    }
+
+    /// Sets the maximum number of jobs from the queue that run at the same
+    /// time. Defaults to `Queue::default_concurrency`.
+    ///
+    /// # Panics
+    ///
+    /// If `threads` is 0, as the jobs of the queue would never run.
+    pub fn concurrency(mut self, queue: Queue, threads: usize) -> Self {
+        assert!(threads > 0, "the concurrency of a queue must be at least 1");
+        self.concurrency.insert(queue, threads);
+        self
+    }
+
+    /// Sets how long to wait for a worker thread to report that it picked up
+    /// a job before giving up.
+    pub fn job_start_timeout(mut self, timeout: Duration) -> Self {
+        self.job_start_timeout = Some(timeout);
+        self
+    }
+
+    pub fn build(self) -> Runner {
+        let concurrency = self.concurrency;
+        let queues = Queue::ALL
+            .iter()
+            .map(|&queue| QueueWorkers {
+                queue,
+                max_threads: concurrency
+                    .get(&queue)
+                    .copied()
+                    .unwrap_or_else(|| queue.default_concurrency()),
+                active_threads: Arc::new(AtomicUsize::new(0)),
+            })
+            .collect();
+
+        Runner {
+            connection_pool: self
+                .connection_pool
+                .expect("a connection pool is required to build a runner"),
+            environment: Arc::new(self.environment),
+            queues,
+            job_start_timeout: self.job_start_timeout.unwrap_or(DEFAULT_JOB_START_TIMEOUT),
+        }
+    }
+}
+
+struct QueueWorkers {
+    queue: Queue,
+    max_threads: usize,
+    active_threads: Arc<AtomicUsize>,
+}
+
+impl QueueWorkers {
+    fn available_threads(&self) -> usize {
+        self.max_threads
+            .saturating_sub(self.active_threads.load(Ordering::SeqCst))
+    }
+}
+
+/// Decrements the number of active threads of a queue when the worker thread
+/// exits, even if it panicked.
+struct ActiveThread(Arc<AtomicUsize>);
+
+impl ActiveThread {
+    fn start(active_threads: &Arc<AtomicUsize>) -> Self {
+        active_threads.fetch_add(1, Ordering::SeqCst);
+        Self(active_threads.clone())
+    }
+}
+
+impl Drop for ActiveThread {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+#[allow(missing_debug_implementations)]
+pub struct Runner {
+    connection_pool: DieselPool,
+    environment: Arc<Environment>,
+    queues: Vec<QueueWorkers>,
+    job_start_timeout: Duration,
+}
+
+impl Runner {
+    pub fn builder(environment: Environment) -> RunnerBuilder {
+        RunnerBuilder {
+            environment,
+            connection_pool: None,
+            concurrency: HashMap::new(),
+            job_start_timeout: None,
+        }
+    }
+
+    /// The total number of jobs that can run at the same time
+    pub fn max_threads(&self) -> usize {
+        self.queues.iter().map(|workers| workers.max_threads).sum()
+    }
+
+    /// Starts workers for every queue that has jobs waiting and threads
+    /// available.
+    ///
+    /// Each worker keeps running jobs from its queue until the queue is
+    /// empty. This function returns as soon as the new workers have picked up
+    /// their first job, and does not wait for any jobs to complete.
+    pub fn run_all_pending_jobs(&self) -> Result<(), FetchError> {
+        for workers in &self.queues {
+            self.start_workers(workers)?;
+        }
+        Ok(())
+    }
+
+    fn start_workers(&self, workers: &QueueWorkers) -> Result<(), FetchError> {
+        let available_threads = workers.available_threads();
+        if available_threads == 0 {
+            return Ok(());
+        }
+
+        let (sender, receiver) = sync_channel(available_threads);
+        for _ in 0..available_threads {
+            self.spawn_worker(workers, sender.clone());
+        }
+
+        for _ in 0..available_threads {
+            match receiver.recv_timeout(self.job_start_timeout) {
+                Ok(Event::Working) => {}
+                Ok(Event::NoJobAvailable) => return Ok(()),
+                Ok(Event::ErrorLoadingJob(e)) => return Err(FetchError::FailedLoadingJob(e)),
+                Ok(Event::FailedToAcquireConnection(e)) => {
+                    return Err(FetchError::NoDatabaseConnection(e));
+                }
+                Err(_) => return Err(FetchError::NoMessageReceived),
+            }
+        }
+        Ok(())
+    }
+
+    fn spawn_worker(&self, workers: &QueueWorkers, sender: SyncSender<Event>) {
+        let queue = workers.queue;
+        let connection_pool = self.connection_pool.clone();
+        let environment = self.environment.clone();
+        let active_thread = ActiveThread::start(&workers.active_threads);
+
+        thread::spawn(move || {
+            let _active_thread = active_thread;
+            let mut sender = Some(sender);
+            loop {
+                match run_next_job(&connection_pool, &environment, queue, &mut sender) {
+                    Ok(true) => {}
+                    Ok(false) => break,
+                    // The job is unlocked again and retried by the next worker
+                    Err(e) if sender.is_some() => {
+                        notify(&mut sender, Event::ErrorLoadingJob(e));
+                        break;
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to update job: {:?}", e);
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Waits for all running jobs to complete, and returns an error if any
+    /// jobs have failed. Only used in tests.
+    pub fn check_for_failed_jobs(&self) -> Result<(), FailedJobsError> {
+        self.wait_for_jobs();
+        let conn = self.connection_pool.get()?;
+        let failed_jobs = storage::failed_job_count(&conn)?;
+        if failed_jobs == 0 {
+            Ok(())
+        } else {
+            Err(FailedJobsError::JobsFailed(failed_jobs))
+        }
+    }
+
+    fn wait_for_jobs(&self) {
+        let is_running = |workers: &QueueWorkers| workers.active_threads.load(Ordering::SeqCst) > 0;
+        while self.queues.iter().any(is_running) {
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// Sends the event if no event has been sent by this worker yet. The runner
+/// only waits for the first event of each worker, and may have stopped
+/// listening already.
+fn notify(sender: &mut Option<SyncSender<Event>>, event: Event) {
+    if let Some(sender) = sender.take() {
+        let _ = sender.send(event);
+    }
+}
+
+/// Locks and runs the next job on the queue, returning whether a job was found.
+///
+/// Errors are returned if the state of the job couldn't be updated after it
+/// ran, in which case the worker stops.
+fn run_next_job(
+    connection_pool: &DieselPool,
+    environment: &Environment,
+    queue: Queue,
+    sender: &mut Option<SyncSender<Event>>,
+) -> Result<bool, diesel::result::Error> {
+    use diesel::result::Error::RollbackTransaction;
+
+    let conn = match connection_pool.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            notify(sender, Event::FailedToAcquireConnection(e));
+            return Ok(false);
+        }
+    };
+
+    let mut found_job = false;
+    let job_run_result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        let job = match storage::find_next_unlocked_job(&conn, queue).optional() {
+            Ok(Some(job)) => {
+                notify(sender, Event::Working);
+                job
+            }
+            Ok(None) => {
+                notify(sender, Event::NoJobAvailable);
+                return Ok(());
+            }
+            Err(e) => {
+                notify(sender, Event::ErrorLoadingJob(e));
+                return Err(RollbackTransaction);
+            }
+        };
+        found_job = true;
+
+        let job_id = job.id;
+        let started_at = Utc::now().naive_utc();
+        let result = Job::from_parts(&job.job_type, job.data.clone()).and_then(|parsed| {
+            // Jobs enqueued by this job belong to the same request
+            request_id::with(job.request_id.as_deref(), || {
+                // The job runs in a savepoint, so that its changes are rolled back
+                // if it fails while the retry count is still updated
+                conn.transaction(|| {
+                    catch_unwind(AssertUnwindSafe(|| parsed.perform(environment, &conn)))
+                        .map_err(|e| try_to_extract_panic_info(&e))
+                        .and_then(|r| r)
+                })
+            })
+        });
+
+        let finished_at = Utc::now().naive_utc();
+        match result {
+            Ok(()) => {
+                storage::delete_successful_job(&conn, job_id)?;
+                storage::record_run(&conn, &job, queue, started_at, finished_at, None)?;
+            }
+            Err(e) => {
+                eprintln!(
+                    "Job {} failed to run: {} (request_id={})",
+                    job_id,
+                    e,
+                    job.request_id.as_deref().unwrap_or("none")
+                );
+                let error = e.to_string();
+                storage::update_failed_job(&conn, job_id, &error);
+                let _ =
+                    storage::record_run(&conn, &job, queue, started_at, finished_at, Some(&error));
+            }
+        }
+        Ok(())
+    });
+
+    match job_run_result {
+        Ok(()) | Err(RollbackTransaction) => Ok(found_job),
+        Err(e) => Err(e),
+    }
+}
+
+/// Try to figure out what's in the box, and print it if we can.
+///
+/// The payload of a panic is documented as "commonly but not always
+/// `&'static str` or `String`", so we try both and give up otherwise.
+fn try_to_extract_panic_info(info: &(dyn Any + Send + 'static)) -> PerformError {
+    if let Some(x) = info.downcast_ref::<&'static str>() {
+        format!("job panicked: {}", x).into()
+    } else if let Some(x) = info.downcast_ref::<String>() {
+        format!("job panicked: {}", x).into()
+    } else {
+        "job panicked".into()
+    }
+}
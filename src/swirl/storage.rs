@@ -0,0 +1,137 @@
+use chrono::NaiveDateTime;
+use diesel::dsl::now;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Integer, Interval};
+
+use crate::background_jobs::Queue;
+use crate::schema::{background_job_runs, background_jobs};
+
+#[derive(Queryable, Debug, Clone)]
+pub(super) struct BackgroundJob {
+    pub(super) id: i64,
+    pub(super) job_type: String,
+    pub(super) data: serde_json::Value,
+    pub(super) created_at: NaiveDateTime,
+    pub(super) request_id: Option<String>,
+}
+
+/// Failed jobs are retried with an exponential backoff
+fn retriable() -> Box<dyn BoxableExpression<background_jobs::table, Pg, SqlType = Bool>> {
+    use diesel::dsl::IntervalDsl;
+
+    sql_function!(fn power(x: Integer, y: Integer) -> Integer);
+
+    Box::new(
+        background_jobs::last_retry
+            .lt(now - 1.minute().into_sql::<Interval>() * power(2, background_jobs::retries)),
+    )
+}
+
+/// Finds the next job on the queue that is unlocked, and ready to be retried.
+/// If a row is found, it will be locked.
+pub(super) fn find_next_unlocked_job(
+    conn: &PgConnection,
+    queue: Queue,
+) -> QueryResult<BackgroundJob> {
+    background_jobs::table
+        .select((
+            background_jobs::id,
+            background_jobs::job_type,
+            background_jobs::data,
+            background_jobs::created_at,
+            background_jobs::request_id,
+        ))
+        .filter(background_jobs::queue.eq(queue.name()))
+        .filter(background_jobs::run_at.le(now))
+        .filter(retriable())
+        .order((background_jobs::priority.desc(), background_jobs::id))
+        .for_update()
+        .skip_locked()
+        .first::<BackgroundJob>(conn)
+}
+
+/// The number of jobs that have failed at least once
+pub(super) fn failed_job_count(conn: &PgConnection) -> QueryResult<i64> {
+    background_jobs::table
+        .count()
+        .filter(background_jobs::retries.gt(0))
+        .get_result(conn)
+}
+
+/// Deletes a job that has successfully completed running
+pub(super) fn delete_successful_job(conn: &PgConnection, job_id: i64) -> QueryResult<()> {
+    diesel::delete(background_jobs::table.find(job_id)).execute(conn)?;
+    Ok(())
+}
+
+/// Marks that we just tried and failed to run a job.
+///
+/// Ignores any database errors that may have occurred. If the DB has gone
+/// away, we assume that just trying again with a new connection will succeed.
+pub(super) fn update_failed_job(conn: &PgConnection, job_id: i64, error: &str) {
+    let _ = diesel::update(background_jobs::table.find(job_id))
+        .set((
+            background_jobs::retries.eq(background_jobs::retries + 1),
+            background_jobs::last_retry.eq(now),
+            background_jobs::last_error.eq(error),
+        ))
+        .execute(conn);
+}
+
+/// Records an attempt to run a job, for the latency statistics of its type
+pub(super) fn record_run(
+    conn: &PgConnection,
+    job: &BackgroundJob,
+    queue: Queue,
+    started_at: NaiveDateTime,
+    finished_at: NaiveDateTime,
+    error: Option<&str>,
+) -> QueryResult<()> {
+    diesel::insert_into(background_job_runs::table)
+        .values((
+            background_job_runs::job_id.eq(job.id),
+            background_job_runs::job_type.eq(&job.job_type),
+            background_job_runs::queue.eq(queue.name()),
+            background_job_runs::enqueued_at.eq(job.created_at),
+            background_job_runs::started_at.eq(started_at),
+            background_job_runs::finished_at.eq(finished_at),
+            background_job_runs::succeeded.eq(error.is_none()),
+            background_job_runs::error.eq(error),
+            background_job_runs::request_id.eq(&job.request_id),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// The IDs of all jobs that are currently being run by a worker.
+///
+/// Workers hold a lock on the row of a job while running it, so these are
+/// the rows that cannot be locked right now. This takes a short lived lock
+/// on every other row, and must not be used on a read-only replica.
+pub fn running_job_ids(conn: &PgConnection) -> QueryResult<Vec<i64>> {
+    use diesel::dsl::sql;
+
+    // Diesel can't build a subquery with a locking clause
+    conn.transaction(|| {
+        background_jobs::table
+            .select(background_jobs::id)
+            .filter(sql::<Bool>(
+                "id NOT IN (SELECT id FROM background_jobs FOR KEY SHARE SKIP LOCKED)",
+            ))
+            .load(conn)
+    })
+}
+
+/// Locks the job, unless it is currently being run by a worker.
+///
+/// Returns `None` if the job is running, or doesn't exist.
+pub fn lock_idle_job(conn: &PgConnection, job_id: i64) -> QueryResult<Option<i64>> {
+    background_jobs::table
+        .find(job_id)
+        .select(background_jobs::id)
+        .for_update()
+        .skip_locked()
+        .first(conn)
+        .optional()
+}
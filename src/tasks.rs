@@ -1,5 +1,39 @@
+mod backfill_checksums;
+mod check_download_origins;
+mod clean_up_stale_data;
+mod compute_trending_scores;
 pub mod dump_db;
+mod export_dependency_graph;
+mod generate_sitemaps;
+mod normalize_version_licenses;
+mod notify_new_version;
+mod recheck_repository_verifications;
+mod reconcile_dependents;
+mod refresh_monthly_stats;
+mod refresh_summary;
+mod release_held_versions;
+mod send_weekly_digests;
+mod sync_advisories;
 mod update_downloads;
+mod upload_pending_crates;
+mod verify_storage;
 
-pub use dump_db::dump_db;
-pub use update_downloads::update_downloads;
+pub use backfill_checksums::perform_backfill_checksums;
+pub use check_download_origins::perform_check_download_origins;
+pub use clean_up_stale_data::perform_clean_up_stale_data;
+pub use compute_trending_scores::perform_compute_trending_scores;
+pub use dump_db::{perform_dump_db, perform_dump_db_incremental, perform_dump_download_history};
+pub use export_dependency_graph::perform_export_dependency_graph;
+pub use generate_sitemaps::perform_generate_sitemaps;
+pub use normalize_version_licenses::perform_normalize_version_licenses;
+pub use notify_new_version::perform_notify_new_version;
+pub use recheck_repository_verifications::perform_recheck_repository_verifications;
+pub use reconcile_dependents::perform_reconcile_dependents;
+pub use refresh_monthly_stats::perform_refresh_monthly_stats;
+pub use refresh_summary::perform_refresh_summary;
+pub use release_held_versions::perform_release_held_versions;
+pub use send_weekly_digests::perform_send_weekly_digests;
+pub use sync_advisories::{import_advisories, perform_notify_advisory, perform_sync_advisories};
+pub use update_downloads::perform_update_downloads;
+pub use upload_pending_crates::{perform_upload_pending_crates, upload_pending_crate};
+pub use verify_storage::perform_verify_storage;
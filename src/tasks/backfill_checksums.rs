@@ -0,0 +1,93 @@
+use diesel::prelude::*;
+
+use crate::background_jobs::Environment;
+use crate::models::{ChecksumAlgorithm, VersionChecksum};
+use crate::schema::{crates, pending_uploads, version_checksums, versions};
+use crate::swirl::PerformError;
+
+/// How many versions get their checksums on every run of the job
+const BATCH_SIZE: i64 = 500;
+
+no_arg_sql_function!(random, diesel::sql_types::Double);
+
+/// Adds the checksums of all `ChecksumAlgorithm`s to versions that were
+/// published before the algorithm was added, by downloading their crate
+/// files. The job is scheduled, and only has work to do while versions are
+/// missing checksums, so adding an algorithm only takes adding it to
+/// `ChecksumAlgorithm::ALL`.
+///
+/// A file is only used if it matches the SHA256 checksum recorded when it
+/// was published, as a checksum of a tampered file would vouch for it.
+/// Versions without a checksum, or whose crate file is still waiting to be
+/// uploaded, are skipped.
+pub fn perform_backfill_checksums(
+    conn: &PgConnection,
+    env: &Environment,
+) -> Result<(), PerformError> {
+    let algorithms = ChecksumAlgorithm::ALL
+        .iter()
+        .map(|algorithm| algorithm.as_str())
+        .collect::<Vec<_>>();
+    let recorded = version_checksums::table
+        .filter(version_checksums::version_id.eq(versions::id))
+        .filter(version_checksums::algorithm.eq_any(algorithms))
+        .count()
+        .single_value();
+
+    // Picked at random so that files that repeatedly fail to download
+    // don't hold up the others
+    let missing: Vec<(i32, String, String, Option<String>, bool)> = versions::table
+        .inner_join(crates::table)
+        .filter(versions::checksum.is_not_null())
+        .filter(recorded.lt(ChecksumAlgorithm::ALL.len() as i64))
+        .filter(diesel::dsl::not(diesel::dsl::exists(
+            pending_uploads::table.filter(pending_uploads::version_id.eq(versions::id)),
+        )))
+        .select((
+            versions::id,
+            crates::name,
+            versions::num,
+            versions::checksum,
+            versions::stored_by_checksum,
+        ))
+        .order(random)
+        .limit(BATCH_SIZE)
+        .load(conn)?;
+
+    let mut backfilled = 0;
+    for (version_id, crate_name, num, checksum, stored_by_checksum) in missing {
+        let checksum = match checksum {
+            Some(checksum) => checksum,
+            None => continue,
+        };
+        let download = if stored_by_checksum {
+            env.uploader
+                .download_crate_by_checksum(env.http_client(), &checksum)
+        } else {
+            env.uploader
+                .download_crate(env.http_client(), &crate_name, &num)
+        };
+        let tarball = match download {
+            Ok(tarball) => tarball,
+            Err(e) => {
+                eprintln!("Failed to download `{}#{}`: {}", crate_name, num, e);
+                continue;
+            }
+        };
+
+        // Mismatches are recorded by the `verify_storage` job
+        if ChecksumAlgorithm::Sha256.digest(&tarball) != checksum {
+            warn!(
+                krate = %crate_name,
+                version = %num,
+                "Crate file does not match its checksum, not backfilling"
+            );
+            continue;
+        }
+        VersionChecksum::record(conn, version_id, &tarball)?;
+        backfilled += 1;
+    }
+
+    println!("Backfilled the checksums of {} versions", backfilled);
+    Ok(())
+}
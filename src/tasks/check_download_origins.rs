@@ -0,0 +1,43 @@
+use diesel::prelude::*;
+use std::time::Duration;
+
+use crate::background_jobs::Environment;
+use crate::download_origins::{OriginHealth, HEALTH_CHECK_PATH};
+use crate::swirl::PerformError;
+
+/// How long an origin may take to answer its health check
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Requests the health check file from every download origin and records
+/// which ones are up, so that downloads fail over to the other origins. See
+/// the `download_origins` module for more documentation.
+pub fn perform_check_download_origins(
+    conn: &PgConnection,
+    env: &Environment,
+) -> Result<(), PerformError> {
+    let origins = env.uploader.download_origins();
+    for origin in origins {
+        let error = env
+            .http_client()
+            .get(&origin.location(HEALTH_CHECK_PATH))
+            .timeout(TIMEOUT)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .err()
+            .map(|e| e.to_string());
+
+        let health = OriginHealth::record(conn, &origin.url, error)?;
+        if !health.healthy {
+            warn!(
+                origin = %origin.url,
+                failures = health.consecutive_failures,
+                error = health.last_error.as_deref().unwrap_or_default(),
+                "Download origin is unhealthy"
+            );
+        }
+    }
+
+    let urls = origins.iter().map(|origin| origin.url.clone()).collect();
+    OriginHealth::retain(conn, urls)?;
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use crate::controllers::helpers::idempotency;
+use crate::models::Email;
+use crate::schema::{
+    background_job_runs, data_exports, deleted_rows, email_changes, github_responses,
+    idempotency_keys, login_anomalies, login_attempts, notifications,
+    publish_rate_limit_rejections, user_sessions,
+};
+use crate::swirl::PerformError;
+
+/// How long rejected publishes are kept around for the admin metrics
+const REJECTION_RETENTION_DAYS: i64 = 90;
+
+/// How long the runs of background jobs are kept for their latency statistics
+const JOB_RUN_RETENTION_DAYS: i64 = 30;
+
+/// How long a completed data export can be downloaded before its archive is
+/// deleted. Users can request a new export at any time.
+const DATA_EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// How long login attempts are kept. They only matter for the rate limit
+/// window, which is much shorter.
+const LOGIN_ATTEMPT_RETENTION_DAYS: i64 = 1;
+
+/// How long detected login anomalies are kept for review
+const LOGIN_ANOMALY_RETENTION_DAYS: i64 = 90;
+
+/// How long sessions are kept after they were revoked or last used. Sessions
+/// that weren't used for this long have to log in again.
+const SESSION_RETENTION_DAYS: i64 = 90;
+
+/// How long notifications are kept after they have been read
+const READ_NOTIFICATION_RETENTION_DAYS: i64 = 90;
+
+/// How long the keys of deleted rows are kept for the incremental database
+/// dumps. Consumers that are further behind restore a full dump instead.
+const DELETED_ROW_RETENTION_DAYS: i64 = 30;
+
+/// How long cached GitHub responses are kept after GitHub last confirmed
+/// them. They are only used past their TTL while GitHub is down.
+const GITHUB_RESPONSE_RETENTION_DAYS: i64 = 7;
+
+pub fn perform_clean_up_stale_data(conn: &PgConnection) -> Result<(), PerformError> {
+    let now = Utc::now().naive_utc();
+
+    let rejections = diesel::delete(publish_rate_limit_rejections::table.filter(
+        publish_rate_limit_rejections::time.lt(now - Duration::days(REJECTION_RETENTION_DAYS)),
+    ))
+    .execute(conn)?;
+    println!("Deleted {} publish rate limit rejections", rejections);
+
+    let exports =
+        diesel::delete(data_exports::table.filter(
+            data_exports::completed_at.lt(now - Duration::days(DATA_EXPORT_RETENTION_DAYS)),
+        ))
+        .execute(conn)?;
+    println!("Deleted {} data exports", exports);
+
+    let runs =
+        diesel::delete(background_job_runs::table.filter(
+            background_job_runs::finished_at.lt(now - Duration::days(JOB_RUN_RETENTION_DAYS)),
+        ))
+        .execute(conn)?;
+    println!("Deleted {} background job runs", runs);
+
+    let tombstones = diesel::delete(
+        deleted_rows::table
+            .filter(deleted_rows::deleted_at.lt(now - Duration::days(DELETED_ROW_RETENTION_DAYS))),
+    )
+    .execute(conn)?;
+    println!("Deleted {} keys of deleted rows", tombstones);
+
+    let read_notifications =
+        diesel::delete(notifications::table.filter(
+            notifications::read_at.lt(now - Duration::days(READ_NOTIFICATION_RETENTION_DAYS)),
+        ))
+        .execute(conn)?;
+    println!("Deleted {} read notifications", read_notifications);
+
+    let email_changes =
+        diesel::delete(email_changes::table.filter(
+            email_changes::created_at.lt(now - Duration::hours(Email::TOKEN_MAX_AGE_HOURS)),
+        ))
+        .execute(conn)?;
+    println!("Deleted {} expired email changes", email_changes);
+
+    let login_attempts = diesel::delete(
+        login_attempts::table
+            .filter(login_attempts::time.lt(now - Duration::days(LOGIN_ATTEMPT_RETENTION_DAYS))),
+    )
+    .execute(conn)?;
+    println!("Deleted {} login attempts", login_attempts);
+
+    let login_anomalies = diesel::delete(login_anomalies::table.filter(
+        login_anomalies::detected_at.lt(now - Duration::days(LOGIN_ANOMALY_RETENTION_DAYS)),
+    ))
+    .execute(conn)?;
+    println!("Deleted {} login anomalies", login_anomalies);
+
+    let session_cutoff = now - Duration::days(SESSION_RETENTION_DAYS);
+    let sessions = diesel::delete(
+        user_sessions::table.filter(
+            user_sessions::last_used_at
+                .lt(session_cutoff)
+                .or(user_sessions::revoked_at.lt(session_cutoff)),
+        ),
+    )
+    .execute(conn)?;
+    println!("Deleted {} user sessions", sessions);
+
+    let github_responses = diesel::delete(github_responses::table.filter(
+        github_responses::fetched_at.lt(now - Duration::days(GITHUB_RESPONSE_RETENTION_DAYS)),
+    ))
+    .execute(conn)?;
+    println!("Deleted {} cached GitHub responses", github_responses);
+
+    let idempotency_keys = diesel::delete(idempotency_keys::table.filter(
+        idempotency_keys::created_at.lt(now - Duration::hours(idempotency::RETENTION_HOURS)),
+    ))
+    .execute(conn)?;
+    println!("Deleted {} expired idempotency keys", idempotency_keys);
+
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use diesel::prelude::*;
+
+use crate::models::CrateTrendingScore;
+use crate::swirl::PerformError;
+
+/// Recomputes the trending scores of all crates from the downloads and
+/// dependencies of the last weeks
+pub fn perform_compute_trending_scores(conn: &PgConnection) -> Result<(), PerformError> {
+    let trending = CrateTrendingScore::recompute(conn)?;
+    println!("Computed the trending scores of {} crates", trending);
+    Ok(())
+}
@@ -3,22 +3,42 @@ use std::{
     path::{Path, PathBuf},
 };
 
+use chrono::{Duration, NaiveDateTime};
+use diesel::PgConnection;
+
+use crate::models::{DatabaseDump, NewDatabaseDump};
+use crate::swirl::PerformError;
+use crate::util::rfc3339;
 use crate::{background_jobs::Environment, uploaders::Uploader};
 use reqwest::header;
-use swirl::PerformError;
+
+/// The name of the manifest listing the available dumps.
+pub const MANIFEST_NAME: &str = "db-dump-manifest.json";
+
+/// The path the download history is uploaded to, replacing the previous one.
+pub const DOWNLOAD_HISTORY_NAME: &str = "db-dump-download-history.tar.gz";
+
+/// How long incremental dumps are listed in the manifest.
+const MANIFEST_DAYS: i64 = 30;
+
+/// Incremental dumps also include the rows changed shortly before the
+/// previous dump, in case these changes were not yet visible on the replica
+/// the previous dump was taken from. Importing a row twice is harmless.
+const INCREMENTAL_OVERLAP_MINUTES: i64 = 60;
 
 /// Create CSV dumps of the public information in the database, wrap them in a
 /// tarball and upload to S3.
-#[swirl::background_job]
-pub fn dump_db(
+pub fn perform_dump_db(
+    conn: &PgConnection,
     env: &Environment,
     database_url: String,
     target_name: String,
 ) -> Result<(), PerformError> {
+    let formats = DumpFormat::from_environment()?;
     let directory = DumpDirectory::create()?;
 
     println!("Begin exporting database");
-    directory.populate(&database_url)?;
+    directory.populate(&database_url, None, &formats)?;
 
     println!("Creating tarball");
     let tarball = DumpTarball::create(&directory.export_dir)?;
@@ -26,6 +46,151 @@ pub fn dump_db(
     println!("Uploading tarball");
     let size = tarball.upload(&target_name, &env.uploader)?;
     println!("Database dump uploaded {} bytes to {}.", size, &target_name);
+
+    directory.record(conn, &target_name, None, size, false)?;
+    upload_manifest(conn, &env.uploader)
+}
+
+/// Create a dump of the rows that changed since the previous dump, and upload
+/// it next to the full dumps.
+///
+/// Incremental dumps are applied on top of the previous dump by running their
+/// `import.sql` script, which also deletes the rows recorded in the
+/// `deleted_rows` table since then. This is skipped if there is no previous
+/// dump yet.
+pub fn perform_dump_db_incremental(
+    conn: &PgConnection,
+    env: &Environment,
+    database_url: String,
+) -> Result<(), PerformError> {
+    let previous = match DatabaseDump::latest(conn)? {
+        Some(previous) => previous,
+        None => {
+            println!("Skipping incremental database dump, there is no previous dump");
+            return Ok(());
+        }
+    };
+
+    let formats = DumpFormat::from_environment()?;
+    let directory = DumpDirectory::create()?;
+    let target_name = format!(
+        "db-dump-incremental/{}.tar.gz",
+        directory.timestamp.format("%Y-%m-%d-%H%M%S")
+    );
+
+    println!("Begin exporting changes since {}", previous.dumped_at);
+    let since = previous.dumped_at - Duration::minutes(INCREMENTAL_OVERLAP_MINUTES);
+    directory.populate(&database_url, Some(since), &formats)?;
+
+    println!("Creating tarball");
+    let tarball = DumpTarball::create(&directory.export_dir)?;
+
+    println!("Uploading tarball");
+    let size = tarball.upload(&target_name, &env.uploader)?;
+    println!(
+        "Incremental database dump uploaded {} bytes to {}.",
+        size, &target_name
+    );
+
+    directory.record(conn, &target_name, Some(previous.dumped_at), size, false)?;
+    upload_manifest(conn, &env.uploader)
+}
+
+/// Export the downloads of every version per day as Parquet files, wrap them
+/// in a tarball and upload it next to the database dumps.
+///
+/// The database dumps contain the same rows as CSV, which are slow to load
+/// and query at this size. The history is uploaded on its own so that users
+/// of the dumps who don't need it don't have to download it twice.
+pub fn perform_dump_download_history(
+    conn: &PgConnection,
+    env: &Environment,
+    database_url: String,
+) -> Result<(), PerformError> {
+    let directory = DumpDirectory::create()?;
+
+    println!("Begin exporting the download history");
+    directory.populate_download_history(&database_url)?;
+
+    println!("Creating tarball");
+    let tarball = DumpTarball::create(&directory.export_dir)?;
+
+    println!("Uploading tarball");
+    let size = tarball.upload(DOWNLOAD_HISTORY_NAME, &env.uploader)?;
+    println!(
+        "Download history uploaded {} bytes to {}.",
+        size, DOWNLOAD_HISTORY_NAME
+    );
+
+    directory.record(conn, DOWNLOAD_HISTORY_NAME, None, size, true)?;
+    upload_manifest(conn, &env.uploader)
+}
+
+/// The manifest mirrors follow to find out which dumps to download.
+///
+/// It lists the most recent full dump, and the incremental dumps of the last
+/// `MANIFEST_DAYS` days. Every incremental dump applies on top of the dump
+/// that was created at its `since` time. The most recent download history is
+/// listed separately.
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    pub full: Option<ManifestEntry>,
+    pub incremental: Vec<ManifestEntry>,
+    pub download_history: Option<ManifestEntry>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ManifestEntry {
+    pub path: String,
+    #[serde(with = "rfc3339")]
+    pub timestamp: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub since: Option<NaiveDateTime>,
+    pub size: i64,
+}
+
+impl From<DatabaseDump> for ManifestEntry {
+    fn from(dump: DatabaseDump) -> Self {
+        Self {
+            path: dump.target_name,
+            timestamp: dump.dumped_at,
+            since: dump.since,
+            size: dump.size,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn load(conn: &PgConnection, now: NaiveDateTime) -> Result<Self, PerformError> {
+        let full = DatabaseDump::latest_full(conn)?.map(ManifestEntry::from);
+        let incremental =
+            DatabaseDump::incremental_since(conn, now - Duration::days(MANIFEST_DAYS))?
+                .into_iter()
+                .map(ManifestEntry::from)
+                .collect();
+        let download_history =
+            DatabaseDump::latest_download_history(conn)?.map(ManifestEntry::from);
+        Ok(Self {
+            full,
+            incremental,
+            download_history,
+        })
+    }
+}
+
+fn upload_manifest(conn: &PgConnection, uploader: &Uploader) -> Result<(), PerformError> {
+    let manifest = Manifest::load(conn, chrono::Utc::now().naive_utc())?;
+    let body = serde_json::to_vec_pretty(&manifest)?;
+    let content_length = body.len() as u64;
+    uploader.upload(
+        &reqwest::blocking::Client::new(),
+        MANIFEST_NAME,
+        std::io::Cursor::new(body),
+        content_length,
+        "application/json",
+        header::HeaderMap::new(),
+    )?;
+    println!("Database dump manifest uploaded to {}.", MANIFEST_NAME);
     Ok(())
 }
 
@@ -51,11 +216,19 @@ impl DumpDirectory {
         })
     }
 
-    pub fn populate(&self, database_url: &str) -> Result<(), PerformError> {
+    /// Export the database, or only the rows changed after `since` for an
+    /// incremental dump, as CSV and the other `formats`.
+    pub fn populate(
+        &self,
+        database_url: &str,
+        since: Option<NaiveDateTime>,
+        formats: &[DumpFormat],
+    ) -> Result<(), PerformError> {
         self.add_readme()?;
-        self.add_metadata()?;
+        self.add_metadata(since)?;
         self.dump_schema(database_url)?;
-        self.dump_db(database_url)
+        self.dump_db(database_url, since, formats)?;
+        formats::export_formats(&self.export_dir, database_url, formats)
     }
 
     fn add_readme(&self) -> Result<(), PerformError> {
@@ -66,14 +239,17 @@ impl DumpDirectory {
         Ok(())
     }
 
-    fn add_metadata(&self) -> Result<(), PerformError> {
+    fn add_metadata(&self, since: Option<NaiveDateTime>) -> Result<(), PerformError> {
         #[derive(Serialize)]
         struct Metadata<'a> {
             timestamp: &'a chrono::DateTime<chrono::Utc>,
+            #[serde(with = "rfc3339::option")]
+            since: Option<NaiveDateTime>,
             crates_io_commit: String,
         }
         let metadata = Metadata {
             timestamp: &self.timestamp,
+            since,
             crates_io_commit: dotenv::var("HEROKU_SLUG_COMMIT")
                 .unwrap_or_else(|_| "unknown".to_owned()),
         };
@@ -82,6 +258,37 @@ impl DumpDirectory {
         Ok(())
     }
 
+    /// Export the download history instead of the database, for the
+    /// `DumpDownloadHistory` job.
+    pub fn populate_download_history(&self, database_url: &str) -> Result<(), PerformError> {
+        use std::io::Write;
+
+        let mut readme = File::create(self.export_dir.join("README.md"))?;
+        readme.write_all(include_bytes!("dump_db/readme_for_download_history.md"))?;
+        self.add_metadata(None)?;
+
+        let data_dir = self.export_dir.join("data");
+        std::fs::create_dir(&data_dir)?;
+        let export_script = self.export_dir.join("export.sql");
+        std::fs::write(
+            &export_script,
+            include_str!("dump_db/dump-download-history.sql"),
+        )?;
+        run_psql(&export_script, database_url)?;
+
+        let parquet_script = self.export_dir.join("export-parquet.sql");
+        std::fs::write(
+            &parquet_script,
+            include_str!("dump_db/dump-download-history-parquet.sql"),
+        )?;
+        formats::run_duckdb(&parquet_script)?;
+
+        // The CSV files are only an intermediate step
+        std::fs::remove_file(data_dir.join("version_downloads.csv"))?;
+        std::fs::remove_file(data_dir.join("crate_downloads.csv"))?;
+        Ok(())
+    }
+
     pub fn dump_schema(&self, database_url: &str) -> Result<(), PerformError> {
         let schema_sql = File::create(self.export_dir.join("schema.sql"))?;
         let status = std::process::Command::new("pg_dump")
@@ -98,13 +305,37 @@ impl DumpDirectory {
         Ok(())
     }
 
-    pub fn dump_db(&self, database_url: &str) -> Result<(), PerformError> {
+    pub fn dump_db(
+        &self,
+        database_url: &str,
+        since: Option<NaiveDateTime>,
+        formats: &[DumpFormat],
+    ) -> Result<(), PerformError> {
         let export_script = self.export_dir.join("export.sql");
         let import_script = self.export_dir.join("import.sql");
-        gen_scripts::gen_scripts(&export_script, &import_script)?;
+        gen_scripts::gen_scripts(&export_script, &import_script, since, formats)?;
         std::fs::create_dir(self.export_dir.join("data"))?;
         run_psql(&export_script, database_url)
     }
+
+    fn record(
+        &self,
+        conn: &PgConnection,
+        target_name: &str,
+        since: Option<NaiveDateTime>,
+        size: u64,
+        download_history: bool,
+    ) -> Result<DatabaseDump, PerformError> {
+        let dump = NewDatabaseDump {
+            target_name,
+            incremental: since.is_some(),
+            since,
+            dumped_at: self.timestamp.naive_utc(),
+            size: size as i64,
+            download_history,
+        };
+        Ok(dump.create(conn)?)
+    }
 }
 
 impl Drop for DumpDirectory {
@@ -175,4 +406,7 @@ impl Drop for DumpTarball {
 }
 
 mod configuration;
+mod formats;
 mod gen_scripts;
+
+pub use formats::DumpFormat;
@@ -23,6 +23,18 @@ pub(super) struct TableConfig {
     pub columns: BTreeMap<String, ColumnVisibility>,
     #[serde(default)]
     pub column_defaults: BTreeMap<String, String>,
+    pub incremental: Option<IncrementalConfig>,
+}
+
+/// The rows of a table that changed since the previous dump. The `filter`
+/// field is a SQL expression like `TableConfig::filter`, in which `{since}`
+/// is replaced by the time of the previous dump. On import, all rows with
+/// the same values in the `key` columns as one of the changed rows are
+/// replaced.
+#[derive(Clone, Debug, Deserialize)]
+pub(super) struct IncrementalConfig {
+    pub key: Vec<String>,
+    pub filter: String,
 }
 
 /// Maps table names to the respective configurations. Used to load `dump_db.toml`.
@@ -0,0 +1,2 @@
+COPY (SELECT * FROM read_csv('data/version_downloads.csv', header=true, delim=',', quote='"', escape='"', columns={'version_id': 'INTEGER', 'date': 'DATE', 'downloads': 'INTEGER'})) TO 'data/version_downloads.parquet' (FORMAT PARQUET, COMPRESSION ZSTD);
+COPY (SELECT * FROM read_csv('data/crate_downloads.csv', header=true, delim=',', quote='"', escape='"', columns={'crate_id': 'INTEGER', 'date': 'DATE', 'downloads': 'BIGINT'})) TO 'data/crate_downloads.parquet' (FORMAT PARQUET, COMPRESSION ZSTD);
@@ -0,0 +1,4 @@
+BEGIN ISOLATION LEVEL REPEATABLE READ, READ ONLY;
+    \copy (SELECT version_id, date, downloads FROM version_downloads ORDER BY date, version_id) TO 'data/version_downloads.csv' WITH CSV HEADER
+    \copy (SELECT versions.crate_id, version_downloads.date, SUM(version_downloads.downloads)::bigint AS downloads FROM version_downloads INNER JOIN versions ON versions.id = version_downloads.version_id GROUP BY versions.crate_id, version_downloads.date ORDER BY version_downloads.date, versions.crate_id) TO 'data/crate_downloads.csv' WITH CSV HEADER
+COMMIT;
@@ -0,0 +1,234 @@
+use std::{collections::BTreeMap, fs::File, io::Write, path::Path};
+
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Text};
+
+use crate::swirl::PerformError;
+use crate::tasks::dump_db::configuration::{ColumnVisibility, VisibilityConfig};
+
+/// The formats the tables are exported in besides CSV. The CSV files are
+/// always included, since the import scripts read them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DumpFormat {
+    /// Newline delimited JSON, with an object per row
+    Ndjson,
+    /// Apache Parquet, converted from the CSV files by the `duckdb` CLI
+    Parquet,
+}
+
+impl DumpFormat {
+    /// Reads the formats from the comma separated `DB_DUMP_EXTRA_FORMATS`,
+    /// like `ndjson,parquet`. No extra formats are exported by default.
+    pub fn from_environment() -> Result<Vec<Self>, PerformError> {
+        match dotenv::var("DB_DUMP_EXTRA_FORMATS") {
+            Ok(formats) => Self::parse_list(&formats),
+            Err(_) => Ok(Vec::new()),
+        }
+    }
+
+    fn parse_list(formats: &str) -> Result<Vec<Self>, PerformError> {
+        formats
+            .split(',')
+            .map(str::trim)
+            .filter(|format| !format.is_empty())
+            .map(|format| match format {
+                "ndjson" => Ok(DumpFormat::Ndjson),
+                "parquet" => Ok(DumpFormat::Parquet),
+                other => Err(format!("unknown database dump format `{}`", other).into()),
+            })
+            .collect()
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            DumpFormat::Ndjson => "ndjson",
+            DumpFormat::Parquet => "parquet",
+        }
+    }
+}
+
+/// The schema of an exported table, as listed in `schemas.json`
+#[derive(Debug, Serialize)]
+struct TableSchema<'a> {
+    name: &'a str,
+    /// The columns in the order of the CSV files
+    columns: Vec<ColumnSchema>,
+    /// The path of the file of each format
+    files: BTreeMap<&'static str, String>,
+}
+
+#[derive(Debug, Serialize, QueryableByName)]
+struct ColumnSchema {
+    #[sql_type = "Text"]
+    #[serde(skip)]
+    table_name: String,
+    #[sql_type = "Text"]
+    #[serde(rename = "name")]
+    column_name: String,
+    /// The PostgreSQL type, like `integer` or `character varying`
+    #[sql_type = "Text"]
+    #[serde(rename = "type")]
+    data_type: String,
+    #[sql_type = "Bool"]
+    nullable: bool,
+}
+
+impl ColumnSchema {
+    /// Loads the columns of all tables in the `public` schema.
+    fn load(conn: &PgConnection) -> QueryResult<Vec<Self>> {
+        diesel::sql_query(
+            "SELECT c.relname::text AS table_name, a.attname::text AS column_name, \
+                format_type(a.atttypid, a.atttypmod) AS data_type, \
+                NOT a.attnotnull AS nullable \
+            FROM pg_attribute a \
+            INNER JOIN pg_class c ON c.oid = a.attrelid \
+            INNER JOIN pg_namespace n ON n.oid = c.relnamespace \
+            WHERE n.nspname = 'public' AND c.relkind = 'r' \
+                AND a.attnum > 0 AND NOT a.attisdropped",
+        )
+        .load(conn)
+    }
+
+    /// The DuckDB type the column is read as for the Parquet files. Types
+    /// without an equivalent, like arrays and JSON, are kept as text.
+    fn duckdb_type(&self) -> &'static str {
+        match self.data_type.as_str() {
+            "smallint" => "SMALLINT",
+            "integer" => "INTEGER",
+            "bigint" => "BIGINT",
+            "boolean" => "BOOLEAN",
+            "real" => "FLOAT",
+            "double precision" => "DOUBLE",
+            "date" => "DATE",
+            "timestamp without time zone" => "TIMESTAMP",
+            numeric if numeric.starts_with("numeric") => "DOUBLE",
+            _ => "VARCHAR",
+        }
+    }
+}
+
+/// Writes `schemas.json`, describing the columns of every exported table and
+/// the files it was exported to, and converts the CSV files to Parquet if
+/// one of the `formats`.
+pub(super) fn export_formats(
+    export_dir: &Path,
+    database_url: &str,
+    formats: &[DumpFormat],
+) -> Result<(), PerformError> {
+    let conn = PgConnection::establish(database_url)?;
+    let mut columns_by_table: BTreeMap<String, BTreeMap<String, ColumnSchema>> = BTreeMap::new();
+    for column in ColumnSchema::load(&conn)? {
+        columns_by_table
+            .entry(column.table_name.clone())
+            .or_default()
+            .insert(column.column_name.clone(), column);
+    }
+
+    let config = VisibilityConfig::get();
+    let mut tables = Vec::new();
+    for table in config.topological_sort() {
+        let mut db_columns = columns_by_table.remove(table).unwrap_or_default();
+        let columns: Vec<ColumnSchema> = config.0[table]
+            .columns
+            .iter()
+            .filter(|&(_, &vis)| vis == ColumnVisibility::Public)
+            .filter_map(|(column, _)| db_columns.remove(column))
+            .collect();
+        if columns.is_empty() {
+            continue;
+        }
+
+        let mut files = BTreeMap::new();
+        files.insert("csv", format!("data/{}.csv", table));
+        for format in formats {
+            files.insert(
+                format.extension(),
+                format!("data/{}.{}", table, format.extension()),
+            );
+        }
+        tables.push(TableSchema {
+            name: table,
+            columns,
+            files,
+        });
+    }
+
+    if formats.contains(&DumpFormat::Parquet) {
+        convert_to_parquet(export_dir, &tables)?;
+    }
+
+    #[derive(Serialize)]
+    struct Schemas<'a> {
+        formats: Vec<&'static str>,
+        tables: Vec<TableSchema<'a>>,
+    }
+    let mut all_formats = vec!["csv"];
+    all_formats.extend(formats.iter().map(|format| format.extension()));
+    let file = File::create(export_dir.join("schemas.json"))?;
+    serde_json::to_writer_pretty(
+        file,
+        &Schemas {
+            formats: all_formats,
+            tables,
+        },
+    )?;
+    Ok(())
+}
+
+/// Converts the CSV files to Parquet with the `duckdb` CLI. The script is
+/// kept in the archive as `export-parquet.sql`, like the `psql` script.
+fn convert_to_parquet(export_dir: &Path, tables: &[TableSchema<'_>]) -> Result<(), PerformError> {
+    let script_path = export_dir.join("export-parquet.sql");
+    let mut script = File::create(&script_path)?;
+    for table in tables {
+        let columns = table
+            .columns
+            .iter()
+            .map(|column| format!("'{}': '{}'", column.column_name, column.duckdb_type()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        writeln!(
+            script,
+            "COPY (SELECT * FROM read_csv('data/{name}.csv', header=true, delim=',', \
+                quote='\"', escape='\"', columns={{{columns}}})) \
+            TO 'data/{name}.parquet' (FORMAT PARQUET);",
+            name = table.name,
+            columns = columns,
+        )?;
+    }
+    drop(script);
+    run_duckdb(&script_path)
+}
+
+/// Runs a `duckdb` script in the directory of the script, on an in-memory
+/// database.
+pub(super) fn run_duckdb(script: &Path) -> Result<(), PerformError> {
+    let output = std::process::Command::new("duckdb")
+        .current_dir(script.parent().unwrap())
+        .stdin(File::open(script)?)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?
+        .wait_with_output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Error while executing duckdb: {}", stderr).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_parsed_from_a_list() {
+        assert_eq!(
+            DumpFormat::parse_list("ndjson, parquet").unwrap(),
+            vec![DumpFormat::Ndjson, DumpFormat::Parquet]
+        );
+        assert_eq!(DumpFormat::parse_list("").unwrap(), vec![]);
+        assert!(DumpFormat::parse_list("ndjson,xml").is_err());
+    }
+}
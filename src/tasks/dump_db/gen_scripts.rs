@@ -1,13 +1,25 @@
 use std::{collections::BTreeMap, fs::File, path::Path};
 
+use chrono::NaiveDateTime;
+
+use crate::swirl::PerformError;
 use crate::tasks::dump_db::configuration::{ColumnVisibility, TableConfig, VisibilityConfig};
-use swirl::PerformError;
+use crate::tasks::dump_db::DumpFormat;
 
-pub fn gen_scripts(export_script: &Path, import_script: &Path) -> Result<(), PerformError> {
+/// Generate the export and import scripts. If `since` is given, the scripts
+/// are for an incremental dump containing the rows changed after that time.
+/// The export script also writes the NDJSON files if one of the `formats`.
+pub fn gen_scripts(
+    export_script: &Path,
+    import_script: &Path,
+    since: Option<NaiveDateTime>,
+    formats: &[DumpFormat],
+) -> Result<(), PerformError> {
     let config = VisibilityConfig::get();
     let export_sql = File::create(export_script)?;
     let import_sql = File::create(import_script)?;
-    config.gen_psql_scripts(export_sql, import_sql)
+    let ndjson = formats.contains(&DumpFormat::Ndjson);
+    config.gen_psql_scripts(export_sql, import_sql, since, ndjson)
 }
 
 /// Subset of the configuration data to be passed on to the Handlbars template.
@@ -17,10 +29,15 @@ struct HandlebarsTableContext<'a> {
     filter: Option<String>,
     columns: String,
     column_defaults: BTreeMap<&'a str, &'a str>,
+    key: Option<String>,
 }
 
 impl TableConfig {
-    fn handlebars_context<'a>(&'a self, name: &'a str) -> Option<HandlebarsTableContext<'a>> {
+    fn handlebars_context<'a>(
+        &'a self,
+        name: &'a str,
+        since: Option<NaiveDateTime>,
+    ) -> Option<HandlebarsTableContext<'a>> {
         let columns = self
             .columns
             .iter()
@@ -31,7 +48,27 @@ impl TableConfig {
         if columns.is_empty() {
             None
         } else {
-            let filter = self.filter.as_ref().map(|s| s.replace('\n', " "));
+            let mut filter = self.filter.as_ref().map(|s| s.replace('\n', " "));
+            let mut key = None;
+            if let (Some(since), Some(incremental)) = (since, &self.incremental) {
+                let since = format!("'{}'::timestamp", since.format("%Y-%m-%d %H:%M:%S"));
+                let changed = incremental
+                    .filter
+                    .replace('\n', " ")
+                    .replace("{since}", &since);
+                filter = Some(match filter {
+                    Some(filter) => format!("({}) AND ({})", filter, changed),
+                    None => changed,
+                });
+                key = Some(
+                    incremental
+                        .key
+                        .iter()
+                        .map(|col| format!("\"{}\"", col))
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                );
+            }
             let column_defaults = self
                 .column_defaults
                 .iter()
@@ -42,6 +79,7 @@ impl TableConfig {
                 filter,
                 columns,
                 column_defaults,
+                key,
             })
         }
     }
@@ -51,23 +89,58 @@ impl TableConfig {
 #[derive(Debug, Serialize)]
 struct HandlebarsContext<'a> {
     tables: Vec<HandlebarsTableContext<'a>>,
+    ndjson: bool,
+    /// The filter of the `deleted_rows` exported by an incremental dump
+    deleted_rows: Option<String>,
 }
 
 impl VisibilityConfig {
-    fn handlebars_context(&self) -> HandlebarsContext<'_> {
-        let tables = self
+    fn handlebars_context(
+        &self,
+        since: Option<NaiveDateTime>,
+        ndjson: bool,
+    ) -> HandlebarsContext<'_> {
+        let tables: Vec<_> = self
             .topological_sort()
             .into_iter()
-            .filter_map(|table| self.0[table].handlebars_context(table))
+            .filter_map(|table| self.0[table].handlebars_context(table, since))
             .collect();
-        HandlebarsContext { tables }
+        let deleted_rows = since.map(|since| {
+            let keyed_tables = tables
+                .iter()
+                .filter(|table| table.key.is_some())
+                .map(|table| format!("'{}'", table.name))
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!(
+                "deleted_at >= '{}'::timestamp AND table_name IN ({})",
+                since.format("%Y-%m-%d %H:%M:%S"),
+                keyed_tables
+            )
+        });
+        HandlebarsContext {
+            tables,
+            ndjson,
+            deleted_rows,
+        }
     }
 
-    fn gen_psql_scripts<W>(&self, export_sql: W, import_sql: W) -> Result<(), PerformError>
+    fn gen_psql_scripts<W>(
+        &self,
+        export_sql: W,
+        import_sql: W,
+        since: Option<NaiveDateTime>,
+        ndjson: bool,
+    ) -> Result<(), PerformError>
     where
         W: std::io::Write,
     {
-        let context = self.handlebars_context();
+        let context = self.handlebars_context(since, ndjson);
+        let import_template = if since.is_some() {
+            include_str!("dump-import-incremental.sql.hbs")
+        } else {
+            include_str!("dump-import.sql.hbs")
+        };
         let mut handlebars = handlebars::Handlebars::new();
         handlebars.register_escape_fn(handlebars::no_escape);
         handlebars.render_template_to_write(
@@ -75,11 +148,7 @@ impl VisibilityConfig {
             &context,
             export_sql,
         )?;
-        handlebars.render_template_to_write(
-            include_str!("dump-import.sql.hbs"),
-            &context,
-            import_sql,
-        )?;
+        handlebars.render_template_to_write(import_template, &context, import_sql)?;
         Ok(())
     }
 }
@@ -136,6 +205,85 @@ mod tests {
         );
     }
 
+    #[test]
+    fn incremental_filters() {
+        let config = VisibilityConfig::get();
+        let since = chrono::NaiveDate::from_ymd(2021, 2, 26).and_hms(3, 0, 0);
+
+        let versions = config.0["versions"]
+            .handlebars_context("versions", Some(since))
+            .unwrap();
+        assert_eq!(versions.key.as_deref(), Some("\"id\""));
+        let filter = versions.filter.unwrap();
+        assert!(filter.contains("updated_at >= '2021-02-26 03:00:00'::timestamp"));
+        assert!(!filter.contains("{since}"));
+
+        // Tables without an incremental filter are included in full
+        let crate_owners = config.0["crate_owners"]
+            .handlebars_context("crate_owners", Some(since))
+            .unwrap();
+        assert_eq!(crate_owners.key, None);
+        assert_eq!(crate_owners.filter.as_deref(), Some("NOT deleted"));
+
+        let full = config.0["versions"]
+            .handlebars_context("versions", None)
+            .unwrap();
+        assert_eq!(full.key, None);
+        assert_eq!(full.filter, None);
+    }
+
+    #[test]
+    fn ndjson_files_are_only_exported_if_requested() {
+        let config = VisibilityConfig::get();
+        let export = |ndjson| {
+            let mut export_sql = Vec::new();
+            config
+                .gen_psql_scripts(&mut export_sql, &mut Vec::new(), None, ndjson)
+                .unwrap();
+            String::from_utf8(export_sql).unwrap()
+        };
+
+        let with_ndjson = export(true);
+        assert!(with_ndjson.contains("TO 'data/crates.csv'"));
+        assert!(with_ndjson.contains("TO 'data/crates.ndjson'"));
+        assert!(with_ndjson.contains("WHERE NOT deleted) t) TO 'data/crate_owners.ndjson'"));
+        assert!(!export(false).contains(".ndjson"));
+    }
+
+    #[test]
+    fn incremental_dumps_delete_the_deleted_rows() {
+        let config = VisibilityConfig::get();
+        let since = chrono::NaiveDate::from_ymd(2021, 2, 26).and_hms(3, 0, 0);
+        let scripts = |since| {
+            let mut export_sql = Vec::new();
+            let mut import_sql = Vec::new();
+            config
+                .gen_psql_scripts(&mut export_sql, &mut import_sql, since, false)
+                .unwrap();
+            (
+                String::from_utf8(export_sql).unwrap(),
+                String::from_utf8(import_sql).unwrap(),
+            )
+        };
+
+        let (export, import) = scripts(Some(since));
+        let tombstones = export
+            .lines()
+            .find(|line| line.contains("TO 'data/deleted_rows.csv'"))
+            .unwrap();
+        assert!(tombstones.contains("deleted_at >= '2021-02-26 03:00:00'::timestamp"));
+        assert!(tombstones.contains("'crates'"));
+        assert!(!tombstones.contains("'crate_owners'"));
+        assert!(import.contains(
+            "DELETE FROM \"crates\" WHERE (\"id\") IN (SELECT \"id\" FROM \
+             (SELECT (jsonb_populate_record(NULL::\"crates\", key)).* FROM \
+             \"deleted_rows_changes\" WHERE table_name = 'crates') deleted);"
+        ));
+
+        let (export, _) = scripts(None);
+        assert!(!export.contains("deleted_rows"));
+    }
+
     mod information_schema {
         table! {
             information_schema.columns (table_schema, table_name, column_name) {
@@ -0,0 +1,172 @@
+//! The dependency graph export, see `docs/DEPENDENCY-GRAPH.md` for its
+//! format.
+
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use reqwest::header;
+
+use crate::background_jobs::Environment;
+use crate::models::DependencyKind;
+use crate::schema::{crates, dependencies, versions};
+use crate::swirl::PerformError;
+use crate::util::rfc3339;
+
+/// The path the export is uploaded to, replacing the previous export.
+const GRAPH_EXPORT_NAME: &str = "dependency-graph.ndjson.gz";
+
+/// Bumped on changes to the format that existing consumers can't ignore.
+const FORMAT_VERSION: u32 = 1;
+
+/// How many versions are exported per query
+const BATCH_SIZE: i64 = 10_000;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Line<'a> {
+    Header {
+        format_version: u32,
+        #[serde(with = "rfc3339")]
+        generated_at: NaiveDateTime,
+    },
+    Version {
+        id: i32,
+        #[serde(rename = "crate")]
+        krate: &'a str,
+        num: &'a str,
+        yanked: bool,
+        #[serde(with = "rfc3339")]
+        created_at: NaiveDateTime,
+    },
+    Dependency {
+        version_id: i32,
+        #[serde(rename = "crate")]
+        krate: &'a str,
+        req: &'a str,
+        kind: DependencyKind,
+        optional: bool,
+        target: Option<&'a str>,
+    },
+}
+
+/// Exports every version and its dependencies, and uploads the export over
+/// the previous one.
+pub fn perform_export_dependency_graph(
+    conn: &PgConnection,
+    env: &Environment,
+) -> Result<(), PerformError> {
+    let mut writer = BufWriter::new(GzEncoder::new(
+        tempfile::tempfile()?,
+        Compression::default(),
+    ));
+    let generated_at = chrono::Utc::now().naive_utc();
+    write_line(
+        &mut writer,
+        &Line::Header {
+            format_version: FORMAT_VERSION,
+            generated_at,
+        },
+    )?;
+
+    let mut last_id = 0;
+    let mut exported = 0;
+    loop {
+        let batch: Vec<(i32, String, String, bool, NaiveDateTime)> = versions::table
+            .inner_join(crates::table)
+            .filter(versions::id.gt(last_id))
+            .select((
+                versions::id,
+                crates::name,
+                versions::num,
+                versions::yanked,
+                versions::created_at,
+            ))
+            .order(versions::id)
+            .limit(BATCH_SIZE)
+            .load(conn)?;
+        let (first_id, batch_last_id) = match (batch.first(), batch.last()) {
+            (Some(first), Some(last)) => (first.0, last.0),
+            _ => break,
+        };
+
+        let deps: Vec<(i32, String, String, DependencyKind, bool, Option<String>)> =
+            dependencies::table
+                .inner_join(crates::table)
+                .filter(dependencies::version_id.between(first_id, batch_last_id))
+                .select((
+                    dependencies::version_id,
+                    crates::name,
+                    dependencies::req,
+                    dependencies::kind,
+                    dependencies::optional,
+                    dependencies::target,
+                ))
+                .order((dependencies::version_id, dependencies::id))
+                .load(conn)?;
+        let mut deps = deps.iter().peekable();
+
+        for (id, krate, num, yanked, created_at) in &batch {
+            write_line(
+                &mut writer,
+                &Line::Version {
+                    id: *id,
+                    krate,
+                    num,
+                    yanked: *yanked,
+                    created_at: *created_at,
+                },
+            )?;
+            // Skips the dependencies of versions deleted since the query above
+            while deps.next_if(|dep| dep.0 < *id).is_some() {}
+            while let Some((version_id, krate, req, kind, optional, target)) =
+                deps.next_if(|dep| dep.0 == *id)
+            {
+                write_line(
+                    &mut writer,
+                    &Line::Dependency {
+                        version_id: *version_id,
+                        krate,
+                        req,
+                        kind: *kind,
+                        optional: *optional,
+                        target: target.as_deref(),
+                    },
+                )?;
+            }
+        }
+        exported += batch.len();
+        last_id = batch_last_id;
+    }
+
+    let mut file = writer.into_inner().map_err(|e| e.to_string())?.finish()?;
+    let content_length = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+    upload(env, file, content_length)?;
+    println!(
+        "Exported the dependency graph of {} versions, {} bytes",
+        exported, content_length
+    );
+    Ok(())
+}
+
+fn write_line<W: Write>(writer: &mut W, line: &Line<'_>) -> Result<(), PerformError> {
+    serde_json::to_writer(&mut *writer, line)?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+fn upload(env: &Environment, file: File, content_length: u64) -> Result<(), PerformError> {
+    env.uploader.upload(
+        &reqwest::blocking::Client::new(),
+        GRAPH_EXPORT_NAME,
+        file,
+        content_length,
+        "application/gzip",
+        header::HeaderMap::new(),
+    )?;
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use chrono::NaiveDateTime;
+use diesel::prelude::*;
+use diesel::sql_types::{Text, Timestamp};
+use std::fmt::Write;
+
+use crate::models::MaterializedResponse;
+use crate::swirl::PerformError;
+
+/// How many URLs a sitemap has at most, the limit of the sitemap protocol
+const URLS_PER_SITEMAP: usize = 50_000;
+
+/// A page of the website, identified by the last segment of its path
+#[derive(Debug, QueryableByName)]
+struct Page {
+    #[sql_type = "Text"]
+    segment: String,
+    #[sql_type = "Timestamp"]
+    lastmod: NaiveDateTime,
+}
+
+/// The kinds of pages listed in the sitemaps, by the path the pages are
+/// under, with the query loading them. Keywords, categories and users are
+/// last modified when the last of their crates was updated.
+const SECTIONS: &[(&str, &str)] = &[
+    (
+        "crates",
+        "SELECT name AS segment, updated_at AS lastmod FROM crates ORDER BY name",
+    ),
+    (
+        "keywords",
+        "SELECT keywords.keyword AS segment, max(crates.updated_at) AS lastmod \
+        FROM keywords \
+        INNER JOIN crates_keywords ON crates_keywords.keyword_id = keywords.id \
+        INNER JOIN crates ON crates.id = crates_keywords.crate_id \
+        GROUP BY keywords.keyword ORDER BY keywords.keyword",
+    ),
+    (
+        "categories",
+        "SELECT categories.slug AS segment, max(crates.updated_at) AS lastmod \
+        FROM categories \
+        INNER JOIN crates_categories ON crates_categories.category_id = categories.id \
+        INNER JOIN crates ON crates.id = crates_categories.crate_id \
+        GROUP BY categories.slug ORDER BY categories.slug",
+    ),
+    (
+        "users",
+        "SELECT COALESCE(users.username, users.gh_login) AS segment, \
+            max(crates.updated_at) AS lastmod \
+        FROM users \
+        INNER JOIN crate_owners ON crate_owners.owner_id = users.id \
+            AND crate_owners.owner_kind = 0 AND NOT crate_owners.deleted \
+        INNER JOIN crates ON crates.id = crate_owners.crate_id \
+        GROUP BY segment ORDER BY segment",
+    ),
+];
+
+/// Generates the sitemaps of the crate, keyword, category and user pages, and
+/// the sitemap index listing them, and stores them for `GET /sitemap.xml` and
+/// `GET /sitemaps/:name` to serve
+pub fn perform_generate_sitemaps(conn: &PgConnection) -> Result<(), PerformError> {
+    let base_url = format!("https://{}", crate::config::domain_name());
+
+    let mut sitemaps = Vec::new();
+    for (path, query) in SECTIONS {
+        let pages: Vec<Page> = diesel::sql_query(*query).load(conn)?;
+        for (i, chunk) in pages.chunks(URLS_PER_SITEMAP).enumerate() {
+            let name = format!("{}-{}.xml", path, i + 1);
+            let lastmod = chunk.iter().map(|page| page.lastmod).max();
+            let body = url_set(&base_url, path, chunk);
+            sitemaps.push((name, lastmod, body));
+        }
+    }
+
+    let mut index = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <sitemapindex xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for (name, lastmod, _) in &sitemaps {
+        index.push_str("<sitemap><loc>");
+        index.push_str(&escape(&format!("{}/sitemaps/{}", base_url, name)));
+        index.push_str("</loc>");
+        if let Some(lastmod) = lastmod {
+            write!(index, "<lastmod>{}</lastmod>", w3c_datetime(*lastmod)).unwrap();
+        }
+        index.push_str("</sitemap>\n");
+    }
+    index.push_str("</sitemapindex>\n");
+
+    // Replace the sitemaps at once, so that no sitemap of a previous run is
+    // left over when there are fewer pages of a kind
+    conn.transaction(|| {
+        MaterializedResponse::delete_with_prefix(conn, MaterializedResponse::SITEMAP_PREFIX)?;
+        for (name, _, body) in &sitemaps {
+            let name = format!("{}{}", MaterializedResponse::SITEMAP_PREFIX, name);
+            MaterializedResponse::store(conn, &name, body.as_bytes())?;
+        }
+        MaterializedResponse::store(conn, MaterializedResponse::SITEMAP_INDEX, index.as_bytes())
+    })?;
+    Ok(())
+}
+
+fn url_set(base_url: &str, path: &str, pages: &[Page]) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+        <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+    );
+    for page in pages {
+        let loc = format!("{}/{}/{}", base_url, path, page.segment);
+        writeln!(
+            xml,
+            "<url><loc>{}</loc><lastmod>{}</lastmod></url>",
+            escape(&loc),
+            w3c_datetime(page.lastmod)
+        )
+        .unwrap();
+    }
+    xml.push_str("</urlset>\n");
+    xml
+}
+
+fn w3c_datetime(time: NaiveDateTime) -> String {
+    time.format("%Y-%m-%dT%H:%M:%S+00:00").to_string()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,22 @@
+use diesel::prelude::*;
+
+use crate::models::VersionLicense;
+use crate::swirl::PerformError;
+
+/// How many versions are normalized in every transaction
+const BATCH_SIZE: i64 = 1000;
+
+/// Normalizes the licenses of the versions published before licenses were
+/// normalized on publish. Only needs to run once, by `enqueue-job`, but is
+/// safe to run again since normalized versions are skipped.
+pub fn perform_normalize_version_licenses(conn: &PgConnection) -> Result<(), PerformError> {
+    let (mut after, mut normalized) = (0, 0);
+    while let Some((last, valid)) =
+        conn.transaction(|| VersionLicense::normalize_existing(conn, after, BATCH_SIZE))?
+    {
+        after = last;
+        normalized += valid;
+    }
+    println!("Normalized the licenses of {} versions", normalized);
+    Ok(())
+}
@@ -0,0 +1,87 @@
+use diesel::prelude::*;
+
+use crate::email;
+use crate::models::{
+    EmailEvent, Mute, NewNotification, NotificationKind, OwnerKind, User, VersionAction,
+};
+use crate::schema::{api_tokens, crate_owners, crates, users, version_owner_actions, versions};
+use crate::swirl::PerformError;
+
+/// Notifies the other owners of a crate about a newly published version, so
+/// that a publish with a compromised account or token doesn't go unnoticed.
+///
+/// Owners who turned off the notifications of the crate, or all new version
+/// notifications, only get a notification on the website. Owners who muted
+/// the crate or the publisher get neither. Team owners are not notified.
+pub fn perform_notify_new_version(
+    conn: &PgConnection,
+    version_id: i32,
+) -> Result<(), PerformError> {
+    let (crate_id, crate_name, num, published_by, created_at) = versions::table
+        .find(version_id)
+        .inner_join(crates::table)
+        .select((
+            crates::id,
+            crates::name,
+            versions::num,
+            versions::published_by,
+            versions::created_at,
+        ))
+        .first::<(i32, String, String, Option<i32>, chrono::NaiveDateTime)>(conn)?;
+
+    let publisher_id = match published_by {
+        Some(publisher_id) => publisher_id,
+        None => return Ok(()),
+    };
+    let publisher = User::find(conn, publisher_id)?;
+
+    let token_name: Option<String> = version_owner_actions::table
+        .filter(version_owner_actions::version_id.eq(version_id))
+        .filter(version_owner_actions::action.eq(VersionAction::Publish))
+        .inner_join(api_tokens::table)
+        .select(api_tokens::name)
+        .first(conn)
+        .optional()?;
+
+    let owners: Vec<(User, bool)> = crate_owners::table
+        .filter(crate_owners::crate_id.eq(crate_id))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .filter(crate_owners::owner_id.ne(publisher_id))
+        .inner_join(users::table)
+        .select((users::all_columns, crate_owners::email_notifications))
+        .load(conn)?;
+
+    let title = format!("{} published {} {}", publisher.gh_login, crate_name, num);
+    for (owner, email_notifications) in owners {
+        if Mute::is_muted(conn, owner.id, crate_id, publisher_id)? {
+            continue;
+        }
+
+        NewNotification {
+            user_id: owner.id,
+            kind: NotificationKind::NewVersion,
+            title: &title,
+            crate_name: Some(&crate_name),
+        }
+        .create(conn)?;
+
+        if !email_notifications {
+            continue;
+        }
+        if let Some(address) = owner.notification_email(conn, EmailEvent::NewVersions)? {
+            email::send_new_version_email(
+                conn,
+                &address,
+                &owner.gh_login,
+                &crate_name,
+                &num,
+                &publisher.gh_login,
+                token_name.as_deref(),
+                created_at,
+            );
+        }
+    }
+
+    Ok(())
+}
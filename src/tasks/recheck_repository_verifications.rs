@@ -0,0 +1,109 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use crate::background_jobs::Environment;
+use crate::github::{self, GitHubClient};
+use crate::models::{Crate, Owner, RepositoryVerification, User};
+use crate::repository_verification::VerificationMethod;
+use crate::schema::crates;
+use crate::swirl::PerformError;
+
+/// How long a successful check is trusted before the repository is checked
+/// again
+const RECHECK_INTERVAL_HOURS: i64 = 24;
+
+/// How long checks may keep failing without a result, like when the host is
+/// down, before the verification is cleared anyway
+const MAX_INCONCLUSIVE_DAYS: i64 = 7;
+
+/// How many repositories are checked on every run of the job
+const BATCH_SIZE: i64 = 500;
+
+/// Repeats the checks of verified repositories that weren't checked within
+/// the last day, with the owner that last verified them.
+///
+/// Verifications are cleared when the check fails, when the crate links to
+/// another repository or when that owner is no longer an owner. Responses
+/// cached by the servers keep showing the previous state until they expire.
+pub fn perform_recheck_repository_verifications(
+    conn: &PgConnection,
+    env: &Environment,
+) -> Result<(), PerformError> {
+    let github = GitHubClient::new(
+        Some(env.http_client().clone()),
+        github::DEFAULT_BASE_URL.to_string(),
+    );
+    let now = Utc::now().naive_utc();
+    let due = RepositoryVerification::due_for_recheck(
+        conn,
+        now - Duration::hours(RECHECK_INTERVAL_HOURS),
+        BATCH_SIZE,
+    )?;
+
+    let (mut revoked, mut inconclusive) = (0, 0);
+    for verification in &due {
+        let krate: Crate = Crate::all()
+            .filter(crates::id.eq(verification.crate_id))
+            .first(conn)?;
+        let owner = krate
+            .owners(conn)?
+            .into_iter()
+            .find_map(|owner| match owner {
+                Owner::User(user) if user.id == verification.requested_by => Some(user),
+                _ => None,
+            });
+
+        let verified = match owner {
+            Some(owner)
+                if krate.repository.as_deref() == Some(verification.repository.as_str()) =>
+            {
+                check(&github, verification, &owner)
+            }
+            _ => Ok(false),
+        };
+
+        match verified {
+            Ok(verified) => {
+                verification.record_check(conn, verified, verification.requested_by)?;
+                if !verified {
+                    revoked += 1;
+                }
+            }
+            Err(e) => {
+                inconclusive += 1;
+                println!(
+                    "Could not check the repository `{}` of {}: {}",
+                    verification.repository, krate.name, e
+                );
+                let last_result = verification
+                    .last_checked_at
+                    .or(verification.verified_at)
+                    .unwrap_or(now);
+                if last_result < now - Duration::days(MAX_INCONCLUSIVE_DAYS) {
+                    verification.revoke(conn)?;
+                    revoked += 1;
+                }
+            }
+        }
+    }
+
+    println!(
+        "Rechecked {} repositories, revoked {} verifications, {} checks had no result",
+        due.len(),
+        revoked,
+        inconclusive
+    );
+    Ok(())
+}
+
+fn check(
+    github: &GitHubClient,
+    verification: &RepositoryVerification,
+    owner: &User,
+) -> Result<bool, PerformError> {
+    let method =
+        VerificationMethod::for_repository(&verification.repository).map_err(|e| e.to_string())?;
+    method
+        .check(github, verification, owner)
+        .map_err(|e| e.to_string().into())
+}
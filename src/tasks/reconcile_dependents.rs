@@ -0,0 +1,11 @@
+use diesel::prelude::*;
+
+use crate::models::CrateDependents;
+use crate::swirl::PerformError;
+
+/// Recounts the dependents of all crates, fixing counts that drifted from the
+/// targeted refreshes on publish and yank, like after a crate was deleted
+pub fn perform_reconcile_dependents(conn: &PgConnection) -> Result<(), PerformError> {
+    CrateDependents::reconcile(conn)?;
+    Ok(())
+}
@@ -0,0 +1,30 @@
+use chrono::{Datelike, Duration, Utc};
+use diesel::prelude::*;
+
+use crate::models::monthly_stats::{first_month, refresh_monthly_stats};
+use crate::swirl::PerformError;
+
+/// Recounts the monthly statistics of categories and keywords.
+///
+/// The previous month is recounted along with the current one, since the
+/// downloads of its last day are only counted after it ended. With
+/// `backfill` all months since the first crate was published are recounted,
+/// like after the statistics were introduced.
+pub fn perform_refresh_monthly_stats(
+    conn: &PgConnection,
+    backfill: bool,
+) -> Result<(), PerformError> {
+    let since = if backfill {
+        match first_month(conn)? {
+            Some(month) => month,
+            None => return Ok(()),
+        }
+    } else {
+        let this_month = Utc::today().naive_utc().with_day(1).unwrap();
+        (this_month - Duration::days(1)).with_day(1).unwrap()
+    };
+
+    refresh_monthly_stats(conn, since)?;
+    println!("Refreshed the monthly statistics since {}", since);
+    Ok(())
+}
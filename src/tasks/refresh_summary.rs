@@ -0,0 +1,13 @@
+use diesel::prelude::*;
+
+use crate::controllers::krate::metadata;
+use crate::models::MaterializedResponse;
+use crate::swirl::PerformError;
+
+/// Computes the response of `GET /summary` and stores it for the endpoint to
+/// serve until the next run
+pub fn perform_refresh_summary(conn: &PgConnection) -> Result<(), PerformError> {
+    let json = metadata::summary_json(conn).map_err(|e| e.to_string())?;
+    MaterializedResponse::store(conn, MaterializedResponse::SUMMARY, json.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::models::VersionQuarantine;
+use crate::swirl::PerformError;
+
+/// Releases the versions of new accounts whose hold has expired and adds them
+/// to the index, see [`crate::first_publish_hold`]
+pub fn perform_release_held_versions(conn: &PgConnection) -> Result<(), PerformError> {
+    let released = conn.transaction::<_, PerformError, _>(|| {
+        let released = VersionQuarantine::release_due(conn, Utc::now().naive_utc())?;
+        for quarantine in &released {
+            quarantine.add_held_version_to_index(conn)?;
+        }
+        Ok(released.len())
+    })?;
+
+    println!("Released {} held versions", released);
+    Ok(())
+}
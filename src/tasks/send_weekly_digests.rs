@@ -0,0 +1,115 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::dsl::any;
+use diesel::prelude::*;
+
+use crate::email::{self, DigestAdvisory, DigestVersion, WeeklyDigest};
+use crate::models::{EmailEvent, EmailPreferences, Follow, User, VersionAction};
+use crate::schema::{advisories, crates, email_preferences, version_owner_actions, versions};
+use crate::swirl::PerformError;
+
+/// How far back the first digest of a user reaches
+const DIGEST_PERIOD_DAYS: i64 = 7;
+
+/// How long after a digest the next one may be sent, which is a bit less than
+/// a week so that a job running late doesn't skip a week
+const MIN_DIGEST_INTERVAL_HOURS: i64 = 6 * 24;
+
+/// Emails the users who opted into the weekly digest about the new versions,
+/// yanks and advisories of the crates they follow since their previous
+/// digest.
+///
+/// Users with nothing to report don't get an email. The time of the digest
+/// is recorded either way, so a retried job doesn't send a digest twice.
+pub fn perform_send_weekly_digests(conn: &PgConnection) -> Result<(), PerformError> {
+    let now = Utc::now().naive_utc();
+    let period_start = now - Duration::days(DIGEST_PERIOD_DAYS);
+
+    let due: Vec<EmailPreferences> = email_preferences::table
+        .filter(email_preferences::weekly_digest.eq(true))
+        .filter(
+            email_preferences::weekly_digest_sent_at
+                .is_null()
+                .or(email_preferences::weekly_digest_sent_at
+                    .lt(now - Duration::hours(MIN_DIGEST_INTERVAL_HOURS))),
+        )
+        .load(conn)?;
+
+    let mut sent = 0;
+    for preferences in due {
+        let since = preferences
+            .weekly_digest_sent_at
+            .map_or(period_start, |sent_at| sent_at.max(period_start));
+
+        let user = User::find(conn, preferences.user_id)?;
+        let digest = weekly_digest(conn, user.id, since)?;
+        if !digest.is_empty() {
+            if let Some(address) = user.notification_email(conn, EmailEvent::WeeklyDigest)? {
+                email::send_weekly_digest_email(conn, &address, &user.gh_login, &digest);
+                sent += 1;
+            }
+        }
+
+        diesel::update(email_preferences::table.find(user.id))
+            .set(email_preferences::weekly_digest_sent_at.eq(now))
+            .execute(conn)?;
+    }
+
+    println!("Sent {} weekly digests", sent);
+    Ok(())
+}
+
+/// Collects what happened to the crates the user follows since `since`
+fn weekly_digest(
+    conn: &PgConnection,
+    user_id: i32,
+    since: NaiveDateTime,
+) -> QueryResult<WeeklyDigest> {
+    let new_versions: Vec<(String, String)> = versions::table
+        .inner_join(crates::table)
+        .filter(crates::id.eq(any(Follow::crate_ids_of(user_id))))
+        .filter(versions::created_at.ge(since))
+        .filter(versions::yanked.eq(false))
+        .order((crates::name, versions::created_at))
+        .select((crates::name, versions::num))
+        .load(conn)?;
+
+    let yanked_versions: Vec<(String, String)> = version_owner_actions::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(crates::id.eq(any(Follow::crate_ids_of(user_id))))
+        .filter(version_owner_actions::action.eq(VersionAction::Yank))
+        .filter(version_owner_actions::time.ge(since))
+        .filter(versions::yanked.eq(true))
+        .order((crates::name, versions::num))
+        .select((crates::name, versions::num))
+        .distinct()
+        .load(conn)?;
+
+    let followed_names: Vec<String> = crates::table
+        .filter(crates::id.eq(any(Follow::crate_ids_of(user_id))))
+        .select(crates::name)
+        .load(conn)?;
+    let advisories: Vec<(String, String, String)> = advisories::table
+        .filter(advisories::crate_name.eq(any(followed_names)))
+        .filter(advisories::created_at.ge(since))
+        .filter(advisories::withdrawn.eq(false))
+        .order((advisories::crate_name, advisories::id))
+        .select((advisories::crate_name, advisories::id, advisories::title))
+        .load(conn)?;
+
+    let to_version = |(crate_name, version): (String, String)| DigestVersion {
+        crate_name,
+        version,
+    };
+    Ok(WeeklyDigest {
+        new_versions: new_versions.into_iter().map(to_version).collect(),
+        yanked_versions: yanked_versions.into_iter().map(to_version).collect(),
+        advisories: advisories
+            .into_iter()
+            .map(|(crate_name, id, title)| DigestAdvisory {
+                crate_name,
+                id,
+                title,
+            })
+            .collect(),
+    })
+}
@@ -0,0 +1,378 @@
+use chrono::{Duration, NaiveDate, Utc};
+use diesel::prelude::*;
+use semver::VersionReq;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::email;
+use crate::models::{
+    Advisory, Crate, EmailEvent, NewAdvisory, NewNotification, NotificationKind, OwnerKind, User,
+};
+use crate::schema::{crate_owners, crates, dependencies, users, versions};
+use crate::swirl::PerformError;
+
+/// The advisory database that is imported if `ADVISORY_DB_URL` is not set
+const DEFAULT_ADVISORY_DB_URL: &str = "https://github.com/rustsec/advisory-db";
+
+/// Only advisories published this recently are emailed about, so that the
+/// first import doesn't send an email for every advisory ever published
+const NOTIFY_MAX_AGE_DAYS: i64 = 30;
+
+/// Imports the advisories of the RustSec advisory database and links them to
+/// the versions they affect.
+///
+/// Responses cached by the servers keep showing the previous advisories of a
+/// version until they expire.
+pub fn perform_sync_advisories(conn: &PgConnection) -> Result<(), PerformError> {
+    let url =
+        dotenv::var("ADVISORY_DB_URL").unwrap_or_else(|_| DEFAULT_ADVISORY_DB_URL.to_string());
+    let checkout = tempfile::Builder::new().prefix("advisory-db").tempdir()?;
+    git2::build::RepoBuilder::new().clone(&url, checkout.path())?;
+
+    let advisories = load_advisories(checkout.path())?;
+    import_advisories(conn, &advisories)
+}
+
+/// Saves the advisories, and notifies the owners of crates that depend on an
+/// affected version about the ones that are new.
+///
+/// Informational advisories, like the ones about unmaintained crates, are
+/// saved without notifying anyone.
+pub fn import_advisories(
+    conn: &PgConnection,
+    advisories: &[NewAdvisory],
+) -> Result<(), PerformError> {
+    let notify_since = Utc::now().naive_utc().date() - Duration::days(NOTIFY_MAX_AGE_DAYS);
+
+    let mut created = 0;
+    for advisory in advisories {
+        if !advisory.save(conn)? {
+            continue;
+        }
+        created += 1;
+
+        let notify = advisory.informational.is_none()
+            && !advisory.withdrawn
+            && advisory.date >= notify_since;
+        if notify {
+            notify_dependents(conn, advisory)?;
+        }
+    }
+
+    println!(
+        "Imported {} advisories, {} of them new",
+        advisories.len(),
+        created
+    );
+    Ok(())
+}
+
+/// Notifies the owners of crates that depend on a version affected by an
+/// advisory published by an owner
+pub fn perform_notify_advisory(conn: &PgConnection, advisory_id: &str) -> Result<(), PerformError> {
+    let advisory = Advisory::find(conn, advisory_id)?;
+    if advisory.withdrawn {
+        return Ok(());
+    }
+    notify_dependents(conn, &advisory.into())
+}
+
+/// Notifies the owners of crates whose newest version depends on an affected
+/// version, once per owner for all of their crates
+fn notify_dependents(conn: &PgConnection, advisory: &NewAdvisory) -> Result<(), PerformError> {
+    use diesel::dsl::any;
+
+    let crate_id: i32 = match Crate::by_name(&advisory.crate_name)
+        .select(crates::id)
+        .first(conn)
+        .optional()?
+    {
+        Some(crate_id) => crate_id,
+        None => return Ok(()),
+    };
+
+    let affected: Vec<semver::Version> = versions::table
+        .filter(versions::crate_id.eq(crate_id))
+        .select(versions::num)
+        .load::<String>(conn)?
+        .iter()
+        .filter_map(|num| semver::Version::parse(num).ok())
+        .filter(|num| advisory.affects(num))
+        .collect();
+    if affected.is_empty() {
+        return Ok(());
+    }
+
+    let dependencies: Vec<(i32, String, String, String)> = dependencies::table
+        .inner_join(versions::table.inner_join(crates::table))
+        .filter(dependencies::crate_id.eq(crate_id))
+        .filter(versions::yanked.eq(false))
+        .select((crates::id, crates::name, versions::num, dependencies::req))
+        .load(conn)?;
+
+    let dependents = newest_dependents(dependencies, &affected);
+    if dependents.is_empty() {
+        return Ok(());
+    }
+
+    let dependent_ids: Vec<i32> = dependents.keys().copied().collect();
+    let owners: Vec<(i32, User)> = crate_owners::table
+        .filter(crate_owners::crate_id.eq(any(dependent_ids)))
+        .filter(crate_owners::deleted.eq(false))
+        .filter(crate_owners::owner_kind.eq(OwnerKind::User as i32))
+        .inner_join(users::table)
+        .select((crate_owners::crate_id, users::all_columns))
+        .load(conn)?;
+
+    let mut crates_by_owner: BTreeMap<i32, (User, Vec<&str>)> = BTreeMap::new();
+    for (dependent_id, owner) in owners {
+        let name = dependents[&dependent_id].as_str();
+        crates_by_owner
+            .entry(owner.id)
+            .or_insert_with(|| (owner, Vec::new()))
+            .1
+            .push(name);
+    }
+
+    for (_, (owner, mut crate_names)) in crates_by_owner {
+        crate_names.sort_unstable();
+
+        NewNotification {
+            user_id: owner.id,
+            kind: NotificationKind::SecurityNotice,
+            title: &format!(
+                "{} for {} affects {}: {}",
+                advisory.id,
+                advisory.crate_name,
+                crate_names.join(", "),
+                advisory.title
+            ),
+            crate_name: Some(&advisory.crate_name),
+        }
+        .create(conn)?;
+
+        if let Some(address) = owner.notification_email(conn, EmailEvent::SecurityNotices)? {
+            email::send_advisory_email(
+                conn,
+                &address,
+                &owner.gh_login,
+                &advisory.id,
+                &advisory.title,
+                &advisory.crate_name,
+                &crate_names,
+                advisory.date,
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Picks the crates whose newest non-yanked version depends on one of the
+/// affected versions from `(crate id, crate name, version, requirement)` rows
+fn newest_dependents(
+    dependencies: Vec<(i32, String, String, String)>,
+    affected: &[semver::Version],
+) -> HashMap<i32, String> {
+    let mut newest: HashMap<i32, (semver::Version, String, String)> = HashMap::new();
+    for (crate_id, name, num, req) in dependencies {
+        let num = match semver::Version::parse(&num) {
+            Ok(num) => num,
+            Err(_) => continue,
+        };
+        match newest.get(&crate_id) {
+            Some((newest_num, _, _)) if *newest_num >= num => {}
+            _ => {
+                newest.insert(crate_id, (num, name, req));
+            }
+        }
+    }
+
+    newest
+        .into_iter()
+        .filter(|(_, (_, _, req))| {
+            VersionReq::parse(req)
+                .map(|req| affected.iter().any(|version| req.matches(version)))
+                .unwrap_or(false)
+        })
+        .map(|(crate_id, (_, name, _))| (crate_id, name))
+        .collect()
+}
+
+/// Reads the advisories of a checkout of the advisory database, which are
+/// stored as `crates/<crate name>/<advisory id>.md`. Advisories that fail to
+/// parse are skipped.
+fn load_advisories(path: &Path) -> Result<Vec<NewAdvisory>, PerformError> {
+    let mut advisories = Vec::new();
+    for crate_dir in fs::read_dir(path.join("crates"))? {
+        let crate_dir = crate_dir?.path();
+        if !crate_dir.is_dir() {
+            continue;
+        }
+
+        for file in fs::read_dir(&crate_dir)? {
+            let file = file?.path();
+            if file.extension().map_or(true, |extension| extension != "md") {
+                continue;
+            }
+
+            match parse_advisory(&fs::read_to_string(&file)?) {
+                Ok(advisory) => advisories.push(advisory),
+                Err(e) => eprintln!("Skipping advisory `{}`: {}", file.display(), e),
+            }
+        }
+    }
+
+    advisories.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(advisories)
+}
+
+#[derive(Deserialize)]
+struct FrontMatter {
+    advisory: Metadata,
+    #[serde(default)]
+    versions: Versions,
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    id: String,
+    package: String,
+    date: NaiveDate,
+    url: Option<String>,
+    informational: Option<String>,
+    withdrawn: Option<String>,
+    /// Older advisories have their title in the front matter
+    title: Option<String>,
+}
+
+#[derive(Default, Deserialize)]
+struct Versions {
+    #[serde(default)]
+    patched: Vec<String>,
+    #[serde(default)]
+    unaffected: Vec<String>,
+}
+
+/// Parses an advisory, which is a Markdown file starting with a fenced TOML
+/// block of metadata and followed by the title as the first heading
+fn parse_advisory(contents: &str) -> Result<NewAdvisory, PerformError> {
+    let front_matter = contents
+        .trim_start()
+        .strip_prefix("```toml")
+        .ok_or("the advisory does not start with a TOML block")?;
+    let end = front_matter
+        .find("\n```")
+        .ok_or("the TOML block is not closed")?;
+    let FrontMatter { advisory, versions } = toml::from_str(&front_matter[..end])?;
+
+    let markdown = &front_matter[end + 4..];
+    let heading = markdown
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string());
+    // The description is everything after the heading
+    let description = markdown
+        .find("\n# ")
+        .and_then(|start| markdown[start + 1..].splitn(2, '\n').nth(1))
+        .map(str::trim)
+        .filter(|description| !description.is_empty())
+        .map(String::from);
+    let title = heading
+        .or(advisory.title)
+        .ok_or("the advisory has no title")?;
+
+    Ok(NewAdvisory {
+        id: advisory.id,
+        crate_name: advisory.package,
+        title,
+        url: advisory.url,
+        date: advisory.date,
+        patched: versions.patched,
+        unaffected: versions.unaffected,
+        informational: advisory.informational,
+        withdrawn: advisory.withdrawn.is_some(),
+        description,
+        severity: None,
+        affected: vec![],
+        published_by: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADVISORY: &str = r#"```toml
+[advisory]
+id = "RUSTSEC-2021-0001"
+package = "foo"
+date = "2021-01-05"
+url = "https://github.com/foo/foo/issues/1"
+categories = ["memory-corruption"]
+
+[versions]
+patched = [">= 1.2.1"]
+unaffected = ["< 1.0.0"]
+```
+
+# Use after free in `Foo::bar`
+
+Calling `Foo::bar` twice frees the same buffer twice.
+"#;
+
+    #[test]
+    fn advisories_are_parsed() {
+        let advisory = assert_ok!(parse_advisory(ADVISORY));
+        assert_eq!(advisory.id, "RUSTSEC-2021-0001");
+        assert_eq!(advisory.crate_name, "foo");
+        assert_eq!(advisory.title, "Use after free in `Foo::bar`");
+        assert_eq!(advisory.date, NaiveDate::from_ymd(2021, 1, 5));
+        assert_eq!(advisory.patched, vec![">= 1.2.1"]);
+        assert_eq!(advisory.unaffected, vec!["< 1.0.0"]);
+        assert_none!(advisory.informational);
+        assert!(!advisory.withdrawn);
+        assert_eq!(
+            advisory.description.as_deref(),
+            Some("Calling `Foo::bar` twice frees the same buffer twice.")
+        );
+    }
+
+    #[test]
+    fn informational_and_withdrawn_advisories_are_parsed() {
+        let advisory = ADVISORY
+            .replace(
+                "categories",
+                "informational = \"unmaintained\"\nwithdrawn = \"2021-02-01\"\ncategories",
+            )
+            .replace("[versions]\npatched = [\">= 1.2.1\"]\n", "[versions]\n");
+        let advisory = assert_ok!(parse_advisory(&advisory));
+        assert_eq!(advisory.informational.as_deref(), Some("unmaintained"));
+        assert!(advisory.withdrawn);
+        assert!(advisory.patched.is_empty());
+    }
+
+    #[test]
+    fn advisories_without_front_matter_are_rejected() {
+        assert_err!(parse_advisory("# Use after free in `Foo::bar`"));
+        assert_err!(parse_advisory(
+            "```toml\n[advisory]\nid = \"RUSTSEC-2021-0001\"\n"
+        ));
+    }
+
+    #[test]
+    fn only_the_newest_version_of_dependents_counts() {
+        let affected = vec![semver::Version::parse("1.1.0").unwrap()];
+        let dependencies = vec![
+            (1, "old_dependent".into(), "1.0.0".into(), "^1.0".into()),
+            (1, "old_dependent".into(), "2.0.0".into(), "^2.0".into()),
+            (2, "dependent".into(), "0.1.0".into(), "^2.0".into()),
+            (2, "dependent".into(), "0.2.0".into(), "^1.1".into()),
+        ];
+
+        let dependents = newest_dependents(dependencies, &affected);
+        assert_eq!(dependents.len(), 1);
+        assert_eq!(dependents[&2], "dependent");
+    }
+}
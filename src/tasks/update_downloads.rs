@@ -3,11 +3,10 @@ use crate::{
     schema::{crates, metadata, version_downloads, versions},
 };
 
+use crate::swirl::PerformError;
 use diesel::prelude::*;
-use swirl::PerformError;
 
-#[swirl::background_job]
-pub fn update_downloads(conn: &PgConnection) -> Result<(), PerformError> {
+pub fn perform_update_downloads(conn: &PgConnection) -> Result<(), PerformError> {
     update(&conn)?;
     Ok(())
 }
@@ -85,6 +84,7 @@ mod test {
     use super::*;
     use crate::{
         env,
+        license::InvalidLicensePolicy,
         models::{Crate, NewCrate, NewUser, NewVersion, User, Version},
     };
     use std::collections::HashMap;
@@ -114,6 +114,7 @@ mod test {
             &HashMap::new(),
             None,
             None,
+            InvalidLicensePolicy::Reject,
             0,
             user_id,
         )
@@ -0,0 +1,86 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use reqwest::blocking::Client;
+
+use crate::background_jobs::Environment;
+use crate::models::PendingUpload;
+use crate::schema::{crates, versions};
+use crate::swirl::PerformError;
+use crate::uploaders::Uploader;
+
+/// How long a publish request has to upload its tarball before the upload is
+/// retried by the background job
+const PENDING_UPLOAD_GRACE_MINUTES: i64 = 5;
+
+/// Uploads the tarballs of published versions that are still pending,
+/// usually because the server stopped or the storage was unavailable right
+/// after the publish was committed.
+pub fn perform_upload_pending_crates(
+    conn: &PgConnection,
+    env: &Environment,
+) -> Result<(), PerformError> {
+    let cutoff = Utc::now().naive_utc() - Duration::minutes(PENDING_UPLOAD_GRACE_MINUTES);
+    let version_ids = PendingUpload::created_before(conn, cutoff)?;
+
+    let mut failed = 0;
+    for version_id in version_ids {
+        if let Err(e) = upload_pending_crate(conn, &env.uploader, env.http_client(), version_id) {
+            eprintln!(
+                "Failed to upload the crate file of version {}: {}",
+                version_id, e
+            );
+            failed += 1;
+        }
+    }
+
+    // Failed uploads stay pending and are retried on the next run
+    if failed > 0 {
+        println!("{} pending crate files failed to upload", failed);
+    }
+    Ok(())
+}
+
+/// Uploads the tarball of a version, then enqueues the jobs that were waiting
+/// for it, such as adding the version to the index. Once uploaded, the
+/// version is downloaded by its checksum.
+///
+/// Does nothing if the tarball has already been uploaded, or is being
+/// uploaded by someone else.
+pub fn upload_pending_crate(
+    conn: &PgConnection,
+    uploader: &Uploader,
+    http_client: &Client,
+    version_id: i32,
+) -> Result<(), PerformError> {
+    conn.transaction(|| {
+        let pending = match PendingUpload::lock(conn, version_id)? {
+            Some(pending) => pending,
+            None => return Ok(()),
+        };
+
+        let (crate_name, num, checksum): (String, String, Option<String>) = versions::table
+            .inner_join(crates::table)
+            .filter(versions::id.eq(version_id))
+            .select((crates::name, versions::num, versions::checksum))
+            .first(conn)?;
+        let checksum = checksum.ok_or_else(|| {
+            format!(
+                "no checksum was recorded for `{}#{}`, not uploading it",
+                crate_name, num
+            )
+        })?;
+        uploader.upload_crate(
+            http_client,
+            &crate_name,
+            &num,
+            &checksum,
+            pending.tarball.clone(),
+        )?;
+
+        diesel::update(versions::table.find(version_id))
+            .set(versions::stored_by_checksum.eq(true))
+            .execute(conn)?;
+        pending.finish(conn)?;
+        Ok(())
+    })
+}
@@ -0,0 +1,217 @@
+use diesel::prelude::*;
+use hex::ToHex;
+use reqwest::StatusCode;
+use sha2::{Digest, Sha256};
+use std::io;
+
+use crate::background_jobs::Environment;
+use crate::models::{NewStorageMismatch, StorageMismatch, StorageMismatchKind};
+use crate::schema::{crates, pending_uploads, versions};
+use crate::swirl::PerformError;
+
+/// How many versions are verified on every run of the job
+const SAMPLE_SIZE: i64 = 100;
+
+no_arg_sql_function!(random, diesel::sql_types::Double);
+
+/// Downloads the crate files of a random sample of versions and records the
+/// ones that are missing from storage, or don't match the checksum or size
+/// recorded when they were published.
+///
+/// Files stored by checksum are verified at that location, which downloads
+/// are served from. Verified files of versions that are only stored by name
+/// are copied there, so older versions move over to it gradually.
+///
+/// Versions whose crate file is still waiting to be uploaded are skipped.
+pub fn perform_verify_storage(conn: &PgConnection, env: &Environment) -> Result<(), PerformError> {
+    type Sample = (i32, String, String, Option<String>, Option<i32>, bool);
+    let sample: Vec<Sample> = versions::table
+        .inner_join(crates::table)
+        .filter(diesel::dsl::not(diesel::dsl::exists(
+            pending_uploads::table.filter(pending_uploads::version_id.eq(versions::id)),
+        )))
+        .select((
+            versions::id,
+            crates::name,
+            versions::num,
+            versions::checksum,
+            versions::crate_size,
+            versions::stored_by_checksum,
+        ))
+        .order(random)
+        .limit(SAMPLE_SIZE)
+        .load(conn)?;
+
+    let mut mismatches = 0;
+    for (version_id, crate_name, num, checksum, crate_size, stored_by_checksum) in sample {
+        let download = match &checksum {
+            Some(checksum) if stored_by_checksum => env
+                .uploader
+                .download_crate_by_checksum(env.http_client(), checksum),
+            _ => env
+                .uploader
+                .download_crate(env.http_client(), &crate_name, &num),
+        };
+
+        let mismatch = match download {
+            Ok(tarball) => {
+                let mismatch = verify(checksum.as_deref(), crate_size, &tarball);
+                match &checksum {
+                    Some(checksum) if mismatch.is_none() && !stored_by_checksum => {
+                        store_by_checksum(conn, env, version_id, checksum, tarball)?;
+                    }
+                    _ => {}
+                }
+                mismatch
+            }
+            Err(e) if is_not_found(&e) => Some(Mismatch {
+                kind: StorageMismatchKind::Missing,
+                expected: None,
+                actual: None,
+            }),
+            Err(e) => {
+                // A storage outage is not a mismatch, the version will be
+                // part of another sample eventually
+                eprintln!("Failed to download `{}#{}`: {}", crate_name, num, e);
+                continue;
+            }
+        };
+
+        match mismatch {
+            Some(mismatch) => {
+                let kind: &'static str = mismatch.kind.into();
+                warn!(
+                    krate = %crate_name,
+                    version = %num,
+                    kind,
+                    "Crate file does not match the database"
+                );
+                NewStorageMismatch {
+                    version_id,
+                    kind: mismatch.kind,
+                    expected: mismatch.expected.as_deref(),
+                    actual: mismatch.actual.as_deref(),
+                }
+                .create(conn)?;
+                mismatches += 1;
+            }
+            None => {
+                if StorageMismatch::resolve_verified(conn, version_id)? {
+                    println!("Crate file of `{}#{}` has been repaired", crate_name, num);
+                }
+            }
+        }
+    }
+
+    println!("Found {} storage mismatches", mismatches);
+    Ok(())
+}
+
+/// Copies a verified crate file that is only stored by name to its location by
+/// checksum, which downloads of the version are served from afterwards
+fn store_by_checksum(
+    conn: &PgConnection,
+    env: &Environment,
+    version_id: i32,
+    checksum: &str,
+    tarball: Vec<u8>,
+) -> QueryResult<()> {
+    let uploaded = env
+        .uploader
+        .upload_crate_by_checksum(env.http_client(), checksum, tarball);
+    if let Err(e) = uploaded {
+        // The file is copied when the version is part of another sample
+        eprintln!("Failed to store version {} by checksum: {}", version_id, e);
+        return Ok(());
+    }
+
+    diesel::update(versions::table.find(version_id))
+        .set(versions::stored_by_checksum.eq(true))
+        .execute(conn)?;
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Mismatch {
+    kind: StorageMismatchKind,
+    expected: Option<String>,
+    actual: Option<String>,
+}
+
+/// Compares a downloaded crate file with what was recorded when it was
+/// published. The size is only compared if there is no checksum.
+fn verify(checksum: Option<&str>, crate_size: Option<i32>, tarball: &[u8]) -> Option<Mismatch> {
+    if let Some(expected) = checksum {
+        let actual = Sha256::digest(tarball).encode_hex::<String>();
+        return if actual == expected {
+            None
+        } else {
+            Some(Mismatch {
+                kind: StorageMismatchKind::Checksum,
+                expected: Some(expected.to_string()),
+                actual: Some(actual),
+            })
+        };
+    }
+
+    let expected = crate_size?;
+    if tarball.len() == expected as usize {
+        None
+    } else {
+        Some(Mismatch {
+            kind: StorageMismatchKind::Size,
+            expected: Some(expected.to_string()),
+            actual: Some(tarball.len().to_string()),
+        })
+    }
+}
+
+/// S3 responds with `403 Forbidden` instead of `404 Not Found` for missing
+/// files if the bucket can't be listed publicly
+fn is_not_found(error: &anyhow::Error) -> bool {
+    if let Some(error) = error.downcast_ref::<reqwest::Error>() {
+        return matches!(
+            error.status(),
+            Some(StatusCode::NOT_FOUND) | Some(StatusCode::FORBIDDEN)
+        );
+    }
+    if let Some(error) = error.downcast_ref::<io::Error>() {
+        return error.kind() == io::ErrorKind::NotFound;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_are_compared_if_present() {
+        let checksum = Sha256::digest(b"tarball").encode_hex::<String>();
+        assert_eq!(verify(Some(&checksum), Some(1), b"tarball"), None);
+
+        let mismatch = verify(Some(&checksum), Some(7), b"tampered").unwrap();
+        assert_eq!(mismatch.kind, StorageMismatchKind::Checksum);
+        assert_eq!(mismatch.expected, Some(checksum));
+    }
+
+    #[test]
+    fn sizes_are_compared_without_checksum() {
+        assert_eq!(verify(None, Some(7), b"tarball"), None);
+        assert_eq!(verify(None, None, b"tarball"), None);
+
+        let mismatch = verify(None, Some(7), b"truncated").unwrap();
+        assert_eq!(mismatch.kind, StorageMismatchKind::Size);
+        assert_eq!(mismatch.expected.as_deref(), Some("7"));
+        assert_eq!(mismatch.actual.as_deref(), Some("9"));
+    }
+
+    #[test]
+    fn missing_files_are_detected() {
+        let error = io::Error::new(io::ErrorKind::NotFound, "no such file");
+        assert!(is_not_found(&error.into()));
+
+        let error = io::Error::new(io::ErrorKind::TimedOut, "timed out");
+        assert!(!is_not_found(&error.into()));
+    }
+}
@@ -1,4 +1,5 @@
-use crate::{util::RequestHelper, TestApp};
+use crate::util::{MockCookieUser, RequestHelper};
+use crate::TestApp;
 use chrono::{Duration, NaiveDateTime, Utc};
 use conduit::StatusCode;
 
@@ -35,7 +36,7 @@ fn account_locked_indefinitely() {
     );
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": error_message }] })
+        json!({ "errors": [{ "detail": error_message, "code": "account_locked" }] })
     );
 }
 
@@ -56,7 +57,7 @@ fn account_locked_with_future_expiry() {
     );
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": error_message }] })
+        json!({ "errors": [{ "detail": error_message, "code": "account_locked" }] })
     );
 }
 
@@ -69,3 +70,104 @@ fn expired_account_lock() {
 
     user.get::<serde_json::Value>(URL).good();
 }
+
+fn lock_url(user_id: i32) -> String {
+    format!("/api/v1/admin/users/{}/lock", user_id)
+}
+
+#[test]
+fn admin_can_lock_and_unlock_accounts() {
+    let (app, _anon, user, token) = TestApp::init().with_token();
+    let admin = app.db_new_admin_user("admin");
+    let url = lock_url(user.as_model().id);
+
+    let body = json!({ "reason": LOCK_REASON }).to_string();
+    let json = admin.put::<serde_json::Value>(&url, body.as_bytes()).good();
+    assert_eq!(json["user"]["locked"], true);
+    assert_eq!(json["user"]["lock_reason"], LOCK_REASON);
+    assert_eq!(json["user"]["lock_until"], serde_json::Value::Null);
+
+    let error_message = format!(
+        "This account is indefinitely locked. Reason: {}",
+        LOCK_REASON
+    );
+    let response = user.get::<()>(URL);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": error_message, "code": "account_locked" }] })
+    );
+
+    let response = token.get::<()>(URL);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": error_message, "code": "account_locked" }] })
+    );
+
+    let json = admin.delete::<serde_json::Value>(&url).good();
+    assert_eq!(json["user"]["locked"], false);
+
+    // Locking the account revoked its sessions, so the user has to log in again
+    user.get::<()>(URL).assert_forbidden();
+    MockCookieUser::new(&app, user.as_model().clone())
+        .get::<serde_json::Value>(URL)
+        .good();
+    token.get::<serde_json::Value>(URL).good();
+}
+
+#[test]
+fn admin_can_lock_accounts_temporarily() {
+    let until = Utc::now().naive_utc() + Duration::days(1);
+
+    let (app, _anon, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    let until_rfc3339 = chrono::DateTime::<Utc>::from_utc(until, Utc).to_rfc3339();
+    let body = json!({ "reason": LOCK_REASON, "until": until_rfc3339 }).to_string();
+    let json = admin
+        .put::<serde_json::Value>(&lock_url(user.as_model().id), body.as_bytes())
+        .good();
+    assert_eq!(json["user"]["locked"], true);
+
+    let response = user.get::<()>(URL);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn lock_requires_a_reason() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    let body = json!({ "reason": "  " }).to_string();
+    let response = admin.put::<()>(&lock_url(user.as_model().id), body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    user.get::<serde_json::Value>(URL).good();
+}
+
+#[test]
+fn lock_rejects_invalid_expiry_times() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    // An invalid time must not lock the account indefinitely
+    let body = json!({ "reason": LOCK_REASON, "until": "tomorrow" }).to_string();
+    let response = admin.put::<()>(&lock_url(user.as_model().id), body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    user.get::<serde_json::Value>(URL).good();
+}
+
+#[test]
+fn non_admins_cannot_lock_accounts() {
+    let (app, _anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    let body = json!({ "reason": LOCK_REASON }).to_string();
+    other
+        .put::<()>(&lock_url(user.as_model().id), body.as_bytes())
+        .assert_forbidden();
+
+    user.get::<serde_json::Value>(URL).good();
+}
@@ -0,0 +1,38 @@
+use crate::util::{RequestHelper, TestApp};
+
+use serde_json::Value;
+
+const URL: &str = "/api/v1/admin/config";
+
+#[test]
+fn non_admins_cannot_see_the_config() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+}
+
+#[test]
+fn config_leaves_out_secrets() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let json: Value = admin.get(URL).good();
+    let config = &json["config"];
+    assert_eq!(config["env"], "Test");
+    assert_eq!(config["api_protocol"], "http");
+    assert_eq!(config["mirror"], false);
+    assert!(config["max_upload_size"].is_u64());
+
+    let serialized = json.to_string();
+    let secrets = [
+        &app.as_inner().config.session_key,
+        &app.as_inner().config.gh_client_secret,
+        &app.as_inner().config.db_url,
+    ];
+    for secret in secrets.iter().filter(|secret| !secret.is_empty()) {
+        assert!(!serialized.contains(secret.as_str()));
+    }
+    assert!(config.get("session_key").is_none());
+    assert!(config.get("db_url").is_none());
+}
@@ -0,0 +1,89 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::models::{Finding, VersionQuarantine};
+use cargo_registry::schema::{background_jobs, publish_rate_limit_rejections, versions};
+
+use diesel::prelude::*;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/admin/metrics";
+
+#[test]
+fn non_admins_cannot_see_metrics() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+}
+
+#[test]
+fn empty_metrics() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let json: Value = admin.get(URL).good();
+    assert_eq!(json["publishes_per_hour"], json!([]));
+    assert_eq!(json["background_jobs"]["queued"], 0);
+    assert_eq!(json["publish_rate_limit_rejections"]["last_day"], 0);
+    assert_eq!(json["new_accounts"]["last_day"], 1);
+    assert_eq!(json["moderation"]["pending_quarantines"], 0);
+    assert_eq!(json["index"]["pending_jobs"], 0);
+    assert_eq!(json["index"]["lag_seconds"], 0);
+}
+
+#[test]
+fn metrics_are_aggregated() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_metrics", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+
+        let version_id = versions::table
+            .filter(versions::num.eq("1.1.0"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        let finding = Finding {
+            scanner: "patterns".into(),
+            rule: "build-script-network".into(),
+            path: None,
+            message: "build script accesses the network".into(),
+        };
+        VersionQuarantine::quarantine(conn, version_id, &[finding]).unwrap();
+
+        diesel::insert_into(publish_rate_limit_rejections::table)
+            .values(publish_rate_limit_rejections::user_id.eq(user.as_model().id))
+            .execute(conn)
+            .unwrap();
+
+        Job::BulkYank { bulk_yank_id: 1 }.enqueue(conn).unwrap();
+    });
+
+    let json: Value = admin.get(URL).good();
+
+    let publishes = json["publishes_per_hour"].as_array().unwrap();
+    let total: i64 = publishes.iter().map(|h| h["count"].as_i64().unwrap()).sum();
+    assert_eq!(total, 2);
+
+    assert_eq!(json["background_jobs"]["queued"], 1);
+    assert_eq!(
+        json["background_jobs"]["by_type"][0]["job_type"],
+        "bulk_yank"
+    );
+    assert_eq!(json["publish_rate_limit_rejections"]["last_hour"], 1);
+    assert_eq!(json["new_accounts"]["last_day"], 2);
+    assert_eq!(json["moderation"]["pending_quarantines"], 1);
+    assert_eq!(json["index"]["pending_jobs"], 1);
+
+    // The job was never meant to run, so remove it before the app is dropped
+    app.db(|conn| {
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
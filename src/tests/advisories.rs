@@ -0,0 +1,235 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::VersionResponse;
+use cargo_registry::models::NewAdvisory;
+use cargo_registry::tasks::import_advisories;
+use cargo_registry::views::EncodableAdvisory;
+use chrono::Utc;
+use conduit::StatusCode;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+struct AdvisoryList {
+    advisories: Vec<EncodableAdvisory>,
+}
+
+#[derive(Deserialize)]
+struct AdvisoryResponse {
+    advisory: EncodableAdvisory,
+}
+
+const OWNER_ADVISORY: &[u8] = br#"{"advisory": {
+    "title": "Panic on empty input",
+    "description": "Parsing an empty string panics.",
+    "severity": "medium",
+    "affected": [">= 1.0.0"],
+    "patched": [">= 1.2.1"]
+}}"#;
+
+fn advisory(withdrawn: bool) -> NewAdvisory {
+    NewAdvisory {
+        id: "RUSTSEC-2021-0001".into(),
+        crate_name: "foo_vulnerable".into(),
+        title: "Use after free in `Foo::bar`".into(),
+        url: None,
+        date: Utc::now().naive_utc().date(),
+        patched: vec![">= 1.2.1".into()],
+        unaffected: vec!["< 1.0.0".into()],
+        informational: None,
+        withdrawn,
+        description: None,
+        severity: None,
+        affected: vec![],
+        published_by: None,
+    }
+}
+
+#[test]
+fn advisories_are_linked_to_affected_versions() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        let vulnerable = CrateBuilder::new("foo_vulnerable", user.as_model().id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .version("1.2.1")
+            .expect_build(conn);
+        CrateBuilder::new("foo_dependent", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").dependency(&vulnerable, None))
+            .expect_build(conn);
+
+        assert_ok!(import_advisories(conn, &[advisory(false)]));
+    });
+
+    let json: AdvisoryList = anon.get("/api/v1/crates/foo_vulnerable/advisories").good();
+    assert_eq!(json.advisories.len(), 1);
+    assert_eq!(json.advisories[0].id, "RUSTSEC-2021-0001");
+    assert_eq!(json.advisories[0].affected_versions, vec!["1.0.0"]);
+
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_vulnerable/1.0.0").good();
+    assert_eq!(json.version.advisories, vec!["RUSTSEC-2021-0001"]);
+    assert!(json.version.affected);
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_vulnerable/1.2.1").good();
+    assert!(json.version.advisories.is_empty());
+    assert!(!json.version.affected);
+
+    // Versions published later are linked, too
+    let crate_to_publish = PublishBuilder::new("foo_vulnerable").version("1.1.0");
+    user.enqueue_publish(crate_to_publish).good();
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_vulnerable/1.1.0").good();
+    assert_eq!(json.version.advisories, vec!["RUSTSEC-2021-0001"]);
+
+    // Withdrawing the advisory removes all links
+    app.db(|conn| assert_ok!(import_advisories(conn, &[advisory(true)])));
+    let json: AdvisoryList = anon.get("/api/v1/crates/foo_vulnerable/advisories").good();
+    assert!(json.advisories[0].withdrawn);
+    assert!(json.advisories[0].affected_versions.is_empty());
+}
+
+#[test]
+fn owners_can_publish_advisories() {
+    let (app, anon, user) = TestApp::full().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_owned", user.as_model().id)
+            .version("0.9.0")
+            .version("1.0.0")
+            .version("1.2.1")
+            .expect_build(conn);
+    });
+
+    let json: AdvisoryResponse = user
+        .put("/api/v1/crates/foo_owned/advisories", OWNER_ADVISORY)
+        .good();
+    let id = json.advisory.id;
+    assert!(id.starts_with("CRATESIO-"));
+    assert_eq!(json.advisory.source, "crates.io");
+    assert_eq!(json.advisory.severity.as_deref(), Some("medium"));
+    assert_eq!(json.advisory.affected_versions, vec!["1.0.0"]);
+    app.run_pending_background_jobs();
+
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_owned/1.0.0").good();
+    assert_eq!(json.version.advisories, vec![id.clone()]);
+    assert!(json.version.affected);
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_owned/0.9.0").good();
+    assert!(!json.version.affected);
+
+    let json: Value = anon.get("/api/v1/crates/foo_owned/advisories/osv").good();
+    let vuln = &json["vulns"][0];
+    assert_eq!(vuln["id"], id.as_str());
+    assert_eq!(vuln["details"], "Parsing an empty string panics.");
+    assert_eq!(vuln["affected"][0]["package"]["ecosystem"], "crates.io");
+    assert_eq!(vuln["affected"][0]["versions"], json!(["1.0.0"]));
+
+    // Amending the advisory relinks the versions
+    let url = format!("/api/v1/crates/foo_owned/advisories/{}", id);
+    let body = br#"{"advisory": {"title": "Panic", "severity": "high", "affected": ["< 1.2.1"]}}"#;
+    let json: AdvisoryResponse = user.put(&url, body).good();
+    assert_eq!(json.advisory.severity.as_deref(), Some("high"));
+    assert_eq!(json.advisory.affected_versions, vec!["0.9.0", "1.0.0"]);
+
+    let json: AdvisoryResponse = user.delete(&url).good();
+    assert!(json.advisory.withdrawn);
+    assert!(json.advisory.affected_versions.is_empty());
+    let json: VersionResponse = anon.get("/api/v1/crates/foo_owned/1.0.0").good();
+    assert!(!json.version.affected);
+}
+
+#[test]
+fn only_owners_can_publish_advisories() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let another_user = app.db_new_user("bar");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_owned", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_owned/advisories";
+    let response = anon.put::<()>(url, OWNER_ADVISORY);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let response = another_user.put::<()>(url, OWNER_ADVISORY);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let invalid = br#"{"advisory": {"title": "Panic", "severity": "huge"}}"#;
+    let response = user.put::<()>(url, invalid);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let invalid = br#"{"advisory": {"title": "Panic", "severity": "low", "affected": ["1.x.y"]}}"#;
+    let response = user.put::<()>(url, invalid);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn imported_advisories_cannot_be_changed() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_vulnerable", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        assert_ok!(import_advisories(conn, &[advisory(false)]));
+    });
+
+    let url = "/api/v1/crates/foo_vulnerable/advisories/RUSTSEC-2021-0001";
+    let response = user.put::<()>(url, OWNER_ADVISORY);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = user.delete::<()>(url);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn osv_feed_lists_advisories_modified_since() {
+    use cargo_registry::schema::advisories;
+    use chrono::Duration;
+    use diesel::prelude::*;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_vulnerable", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let withdrawn = NewAdvisory {
+            id: "RUSTSEC-2021-0004".into(),
+            ..advisory(true)
+        };
+        assert_ok!(import_advisories(conn, &[advisory(false), withdrawn]));
+        diesel::update(advisories::table.find("RUSTSEC-2021-0001"))
+            .set(advisories::updated_at.eq(Utc::now().naive_utc() - Duration::days(2)))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json: Value = anon.get("/api/v1/advisories/osv").good();
+    let ids: Vec<_> = json["vulns"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|vuln| vuln["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["RUSTSEC-2021-0001", "RUSTSEC-2021-0004"]);
+    assert_eq!(
+        json["vulns"][0]["affected"][0]["versions"],
+        json!(["1.0.0"])
+    );
+
+    let since = (Utc::now() - Duration::days(1)).format("%Y-%m-%dT%H:%M:%SZ");
+    let json: Value = anon
+        .get_with_query(
+            "/api/v1/advisories/osv",
+            &format!("modified_since={}", since),
+        )
+        .good();
+    let vulns = json["vulns"].as_array().unwrap();
+    assert_eq!(vulns.len(), 1);
+    assert_eq!(vulns[0]["id"], "RUSTSEC-2021-0004");
+    assert!(vulns[0]["withdrawn"].is_string());
+
+    let response = anon.get_with_query::<()>("/api/v1/advisories/osv", "modified_since=yesterday");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: Value = anon.get("/api/v1/advisories/osv/RUSTSEC-2021-0001").good();
+    assert_eq!(json["id"], "RUSTSEC-2021-0001");
+    anon.get::<()>("/api/v1/advisories/osv/RUSTSEC-2021-9999")
+        .assert_not_found();
+}
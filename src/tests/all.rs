@@ -30,23 +30,63 @@ use std::{
 use diesel::prelude::*;
 
 mod account_lock;
+mod admin_background_jobs;
+mod admin_config;
+mod admin_metrics;
+mod advisories;
+mod announcements;
+mod audit;
 mod authentication;
+mod background_jobs;
 mod badge;
 mod builders;
+mod bulk_yank;
+mod cache;
 mod categories;
 mod category;
+mod crate_lists;
+mod dev_mode;
 mod dump_db;
+mod feature_flags;
+mod featured_crates;
+mod first_publish_hold;
 mod git;
+mod github_cache;
+mod idempotency_keys;
 mod keyword;
 mod krate;
+mod license;
+mod login_rate_limit;
+mod maintenance_mode;
+mod metrics;
+mod monthly_stats;
+mod mutes;
+mod notifications;
 mod owners;
+mod policy;
+mod publish_rate_overrides;
+mod purl;
+mod quarantine;
+mod quotas;
 mod read_only_mode;
+mod read_only_replica;
+mod readme_rerenders;
 mod record;
+mod repository_verification;
+mod request_id;
+mod reserved_crate_names;
+mod scheduled_jobs;
 mod schema_details;
 mod server;
+mod sessions;
+mod sitemap;
+mod storage_mismatches;
 mod team;
 mod token;
 mod user;
+mod user_data;
+mod user_follows;
+mod username;
 mod util;
 mod version;
 
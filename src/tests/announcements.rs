@@ -0,0 +1,100 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::views::EncodableAnnouncement;
+
+use conduit::StatusCode;
+
+const ADMIN_URL: &str = "/api/v1/admin/announcements";
+
+#[derive(Deserialize)]
+struct AnnouncementResponse {
+    announcement: EncodableAnnouncement,
+}
+
+#[derive(Deserialize)]
+struct AnnouncementList {
+    announcements: Vec<EncodableAnnouncement>,
+}
+
+#[test]
+fn non_admins_cannot_manage_announcements() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(ADMIN_URL).assert_forbidden();
+    user.get::<()>(ADMIN_URL).assert_forbidden();
+    user.put::<()>(ADMIN_URL, b"{}").assert_forbidden();
+    user.delete::<()>(&format!("{}/1", ADMIN_URL))
+        .assert_forbidden();
+}
+
+#[test]
+fn current_announcements_are_listed_and_shown_when_publishing() {
+    let (app, anon, _, token) = TestApp::init().with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    let body = br#"{
+        "message": "crates.io is read-only on Saturday",
+        "url": "https://blog.rust-lang.org/",
+        "severity": "warning"
+    }"#;
+    let json: AnnouncementResponse = admin.put(ADMIN_URL, body).good();
+    let warning = json.announcement;
+    assert_eq!(warning.severity, "warning");
+    assert_none!(warning.ends_at);
+
+    let body =
+        br#"{ "message": "Later", "severity": "info", "starts_at": "2999-01-01T00:00:00+00:00" }"#;
+    admin.put::<AnnouncementResponse>(ADMIN_URL, body).good();
+    let body = br#"{
+        "message": "Over",
+        "severity": "critical",
+        "starts_at": "2000-01-01T00:00:00+00:00",
+        "ends_at": "2000-01-02T00:00:00+00:00"
+    }"#;
+    admin.put::<AnnouncementResponse>(ADMIN_URL, body).good();
+
+    let json: AnnouncementList = admin.get(ADMIN_URL).good();
+    assert_eq!(json.announcements.len(), 3);
+
+    let json: AnnouncementList = anon.get("/api/v1/announcements").good();
+    assert_eq!(json.announcements.len(), 1);
+    assert_eq!(json.announcements[0].id, warning.id);
+
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_announced"))
+        .good();
+    assert_eq!(
+        json.warnings.other,
+        vec!["crates.io is read-only on Saturday (https://blog.rust-lang.org/)"]
+    );
+
+    let url = format!("{}/{}", ADMIN_URL, warning.id);
+    admin.delete::<OkBool>(&url).good();
+    admin.delete::<()>(&url).assert_not_found();
+    let json: AnnouncementList = anon.get("/api/v1/announcements").good();
+    assert!(json.announcements.is_empty());
+}
+
+#[test]
+fn invalid_announcements_are_rejected() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let bodies: &[&[u8]] = &[
+        br#"{ "message": " ", "severity": "info" }"#,
+        br#"{ "message": "Hi", "severity": "urgent" }"#,
+        br#"{ "message": "Hi", "severity": "info", "url": "javascript:alert(1)" }"#,
+        br#"{
+            "message": "Hi",
+            "severity": "info",
+            "starts_at": "2000-01-02T00:00:00+00:00",
+            "ends_at": "2000-01-01T00:00:00+00:00"
+        }"#,
+        br#"{ "message": "Hi", "severity": "info", "ends_at": "soon" }"#,
+    ];
+    for body in bodies {
+        let response = admin.put::<()>(ADMIN_URL, body);
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+}
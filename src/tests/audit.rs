@@ -0,0 +1,98 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::NewAdvisory;
+use cargo_registry::tasks::import_advisories;
+use chrono::Utc;
+use conduit::StatusCode;
+use serde_json::Value;
+
+const LOCKFILE: &[u8] = br#"
+version = 3
+
+[[package]]
+name = "foo_app"
+version = "0.1.0"
+dependencies = ["foo_vulnerable", "foo_yanked", "foo_unpublished"]
+
+[[package]]
+name = "foo_vulnerable"
+version = "1.0.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo_yanked"
+version = "0.2.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "foo_unpublished"
+version = "0.1.0"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+fn advisory() -> NewAdvisory {
+    NewAdvisory {
+        id: "RUSTSEC-2021-0001".into(),
+        crate_name: "foo_vulnerable".into(),
+        title: "Use after free in `Foo::bar`".into(),
+        url: None,
+        date: Utc::now().naive_utc().date(),
+        patched: vec![">= 1.2.1".into()],
+        unaffected: vec![],
+        informational: None,
+        withdrawn: false,
+        description: None,
+        severity: None,
+        affected: vec![],
+        published_by: None,
+    }
+}
+
+#[test]
+fn lockfiles_are_audited() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_vulnerable", user.as_model().id)
+            .version("1.0.0")
+            .version("1.2.1")
+            .expect_build(conn);
+        CrateBuilder::new("foo_yanked", user.as_model().id)
+            .version(VersionBuilder::new("0.2.0").yanked(true))
+            .expect_build(conn);
+        assert_ok!(import_advisories(conn, &[advisory()]));
+    });
+
+    let json: Value = anon.post("/api/v1/audit", LOCKFILE).good();
+    let vulnerabilities = json["vulnerabilities"].as_array().unwrap();
+    assert_eq!(vulnerabilities.len(), 1);
+    assert_eq!(vulnerabilities[0]["crate"], "foo_vulnerable");
+    assert_eq!(vulnerabilities[0]["version"], "1.0.0");
+    assert_eq!(vulnerabilities[0]["advisory"]["id"], "RUSTSEC-2021-0001");
+    assert_eq!(
+        json["yanked"],
+        json!([{"crate": "foo_yanked", "version": "0.2.0"}])
+    );
+    assert_eq!(
+        json["unknown"],
+        json!([{"crate": "foo_unpublished", "version": "0.1.0"}])
+    );
+
+    let body = br#"{"packages": [{"name": "foo_vulnerable", "version": "1.2.1"}]}"#;
+    let json: Value = anon.post("/api/v1/audit", body).good();
+    assert_eq!(json["vulnerabilities"], json!([]));
+    assert_eq!(json["unknown"], json!([]));
+}
+
+#[test]
+fn invalid_lockfiles_are_rejected() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.post::<()>("/api/v1/audit", b"[[package]]\nname = 1");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let json = response.json();
+    assert!(json["errors"][0]["detail"]
+        .as_str()
+        .unwrap()
+        .starts_with("invalid Cargo.lock"));
+}
@@ -2,12 +2,12 @@ use crate::util::{RequestHelper, Response};
 use crate::TestApp;
 
 use crate::util::encode_session_header;
+use cargo_registry::models::UserSession;
 use conduit::{header, Body, Method, StatusCode};
 
 static URL: &str = "/api/v1/me/updates";
-static MUST_LOGIN: &[u8] = br#"{"errors":[{"detail":"must be logged in to perform that action"}]}"#;
-static INTERNAL_ERROR_NO_USER: &str =
-    "user_id from cookie not found in database caused by NotFound";
+static MUST_LOGIN: &[u8] =
+    br#"{"errors":[{"code":"unauthenticated","detail":"must be logged in to perform that action"}]}"#;
 
 #[test]
 fn anonymous_user_unauthorized() {
@@ -29,19 +29,27 @@ fn token_auth_cannot_find_token() {
     assert_eq!(response.json().to_string().as_bytes(), MUST_LOGIN);
 }
 
-// Ensure that an unexpected authentication error is available for logging.  The user would see
-// status 500 instead of 403 as in other authentication tests.  Due to foreign-key constraints in
-// the database, it is not possible to implement this same test for a token.
 #[test]
-fn cookie_auth_cannot_find_user() {
+fn cookie_auth_cannot_find_session() {
     let (app, anon) = TestApp::init().empty();
 
     let session_key = &app.as_inner().session_key;
-    let cookie = encode_session_header(session_key, -1);
+    let cookie = encode_session_header(session_key, "cisfake-session");
 
     let mut request = anon.request_builder(Method::GET, URL);
     request.header(header::COOKIE, &cookie);
+    let response: Response<Body> = anon.run(request);
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.json().to_string().as_bytes(), MUST_LOGIN);
+}
 
-    let error = anon.run_err(request);
-    assert_eq!(error.to_string(), INTERNAL_ERROR_NO_USER);
+#[test]
+fn cookie_auth_with_revoked_session() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| UserSession::revoke_all(conn, user.as_model().id).unwrap());
+
+    let response: Response<Body> = user.get(URL);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(response.json().to_string().as_bytes(), MUST_LOGIN);
 }
@@ -0,0 +1,71 @@
+use crate::util::TestApp;
+use cargo_registry::background_jobs::{Job, Queue};
+use cargo_registry::schema::background_jobs;
+
+use diesel::prelude::*;
+use std::time::Duration;
+
+#[test]
+fn jobs_are_enqueued_with_their_queue_and_priority() {
+    let (app, _) = TestApp::init().empty();
+
+    app.db(|conn| {
+        Job::UpdateDownloads {}.enqueue(conn).unwrap();
+        Job::BulkYank { bulk_yank_id: 1 }.enqueue(conn).unwrap();
+
+        let jobs: Vec<(String, String, i16)> = background_jobs::table
+            .select((
+                background_jobs::job_type,
+                background_jobs::queue,
+                background_jobs::priority,
+            ))
+            .order(background_jobs::id)
+            .load(conn)
+            .unwrap();
+        assert_eq!(
+            jobs,
+            vec![
+                (
+                    "update_downloads".into(),
+                    Queue::Maintenance.name().into(),
+                    5
+                ),
+                ("bulk_yank".into(), Queue::Index.name().into(), 0),
+            ]
+        );
+
+        // The jobs were never meant to run, so remove them before the app is dropped
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn delayed_jobs_only_run_once_their_delay_has_passed() {
+    let (app, _) = TestApp::init().with_git_index().with_job_runner().empty();
+    let queued_jobs = || {
+        app.db(|conn| {
+            background_jobs::table
+                .count()
+                .get_result::<i64>(conn)
+                .unwrap()
+        })
+    };
+
+    app.db(|conn| {
+        let delay = Duration::from_secs(3600);
+        Job::UpdateDownloads {}.enqueue_after(conn, delay).unwrap();
+    });
+    app.run_pending_background_jobs();
+    assert_eq!(queued_jobs(), 1);
+
+    app.db(|conn| {
+        diesel::update(background_jobs::table)
+            .set(background_jobs::run_at.eq(diesel::dsl::now))
+            .execute(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+    assert_eq!(queued_jobs(), 0);
+}
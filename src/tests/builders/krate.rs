@@ -60,6 +60,12 @@ impl<'a> CrateBuilder<'a> {
         self
     }
 
+    /// Sets the crate's `repository` URL.
+    pub fn repository(mut self, repository: &'a str) -> Self {
+        self.krate.repository = Some(repository);
+        self
+    }
+
     /// Sets the crate's `readme` content.
     pub fn readme(mut self, readme: &'a str) -> Self {
         self.krate.readme = Some(readme);
@@ -35,6 +35,7 @@ pub struct PublishBuilder {
     license: Option<String>,
     license_file: Option<String>,
     readme: Option<String>,
+    rust_version: Option<String>,
     tarball: Vec<u8>,
     version: semver::Version,
 }
@@ -55,6 +56,7 @@ impl PublishBuilder {
             license: Some("MIT".to_string()),
             license_file: None,
             readme: None,
+            rust_version: None,
             tarball: EMPTY_TARBALL_BYTES.to_vec(),
             version: semver::Version::parse("1.0.0").unwrap(),
         }
@@ -131,6 +133,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the `rust-version` of this crate
+    pub fn rust_version(mut self, rust_version: &str) -> Self {
+        self.rust_version = Some(rust_version.to_string());
+        self
+    }
+
     /// Set the documentation URL of this crate
     pub fn documentation(mut self, documentation: &str) -> Self {
         self.doc_url = Some(documentation.to_string());
@@ -156,6 +164,12 @@ impl PublishBuilder {
         self
     }
 
+    /// Set the license of this crate
+    pub fn license(mut self, license: &str) -> Self {
+        self.license = Some(license.into());
+        self
+    }
+
     /// Remove the license from this crate. Publish will fail unless license or license file is set.
     pub fn unset_license(mut self) -> Self {
         self.license = None;
@@ -206,6 +220,7 @@ impl PublishBuilder {
             repository: None,
             badges: Some(self.badges),
             links: None,
+            rust_version: self.rust_version.map(u::EncodableRustVersion),
         };
 
         (serde_json::to_string(&new_crate).unwrap(), self.tarball)
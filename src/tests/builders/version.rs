@@ -1,5 +1,6 @@
 use cargo_registry::{
-    models::{Crate, NewVersion, Version},
+    license::{self, InvalidLicensePolicy},
+    models::{Crate, NewVersion, Version, VersionLicense},
     schema::{dependencies, versions},
     util::errors::AppResult,
 };
@@ -82,6 +83,9 @@ impl<'a> VersionBuilder<'a> {
         use diesel::{insert_into, update};
 
         let license = self.license.map(|license| license.to_owned());
+        let normalized_license = self
+            .license
+            .and_then(|license| license::normalize(license).ok());
 
         let mut vers = NewVersion::new(
             crate_id,
@@ -89,11 +93,16 @@ impl<'a> VersionBuilder<'a> {
             &self.features,
             license,
             self.license_file,
+            InvalidLicensePolicy::Reject,
             self.size,
             published_by,
         )?
         .save(connection, &[], "someone@example.com")?;
 
+        if let Some(normalized_license) = normalized_license {
+            VersionLicense::new(vers.id, normalized_license).insert(connection)?;
+        }
+
         if self.yanked {
             vers = update(&vers)
                 .set(versions::yanked.eq(true))
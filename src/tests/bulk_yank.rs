@@ -0,0 +1,238 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::models::{BulkYank, NewBulkYank};
+use cargo_registry::views::{EncodableBulkYank, EncodableNotification};
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct BulkYankList {
+    bulk_yanks: Vec<EncodableBulkYank>,
+    meta: BulkYankMeta,
+}
+
+#[derive(Deserialize)]
+struct BulkYankMeta {
+    total: i64,
+}
+
+#[derive(Deserialize)]
+struct NotificationList {
+    notifications: Vec<EncodableNotification>,
+}
+
+#[derive(Deserialize)]
+struct BulkYankResponse {
+    bulk_yank: EncodableBulkYank,
+}
+
+#[test]
+fn non_admins_cannot_bulk_yank() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    let since = Utc::now() - Duration::days(1);
+    let body = json!({
+        "user_id": user.as_model().id,
+        "since": since.to_rfc3339(),
+        "reason": "compromised account",
+    })
+    .to_string();
+
+    anon.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes())
+        .assert_forbidden();
+    user.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes())
+        .assert_forbidden();
+    user.get::<()>("/api/v1/admin/bulk_yanks")
+        .assert_forbidden();
+}
+
+#[test]
+fn bulk_yank_requires_a_reason_and_a_valid_window() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let now = Utc::now();
+
+    let body = json!({
+        "user_id": user.as_model().id,
+        "since": (now - Duration::days(1)).to_rfc3339(),
+        "reason": " ",
+    })
+    .to_string();
+    let response = admin.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = json!({
+        "user_id": user.as_model().id,
+        "since": (now + Duration::days(1)).to_rfc3339(),
+        "reason": "compromised account",
+    })
+    .to_string();
+    let response = admin.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = json!({
+        "since": (now - Duration::days(1)).to_rfc3339(),
+        "reason": "compromised account",
+    })
+    .to_string();
+    let response = admin.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = json!({
+        "user_id": user.as_model().id,
+        "since": (now - Duration::days(1)).to_rfc3339(),
+        "until": "tomorrow",
+        "reason": "compromised account",
+    })
+    .to_string();
+    let response = admin.put::<()>("/api/v1/admin/bulk_yanks", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: BulkYankList = admin.get("/api/v1/admin/bulk_yanks").good();
+    assert_eq!(json.meta.total, 0);
+}
+
+#[test]
+fn pending_versions_are_limited_to_the_user_and_window() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let other = app.db_new_user("other");
+    let now = Utc::now().naive_utc();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_bulk_yank", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").created_at(now - Duration::days(30)))
+            .version("1.1.0")
+            .version(VersionBuilder::new("1.2.0").yanked(true))
+            .expect_build(conn);
+        CrateBuilder::new("bar_bulk_yank", other.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+
+        let bulk_yank = NewBulkYank {
+            admin_id: admin.as_model().id,
+            user_id: user.as_model().id,
+            api_token_id: None,
+            since: now - Duration::days(1),
+            until: now + Duration::days(1),
+            reason: "compromised account",
+        }
+        .create(conn)
+        .unwrap();
+
+        let pending = bulk_yank.pending_versions(conn).unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].0.num.to_string(), "1.1.0");
+        assert_eq!(pending[0].1, "foo_bulk_yank");
+
+        let versions = pending.into_iter().map(|(v, _)| v).collect::<Vec<_>>();
+        bulk_yank.yank_versions(conn, &versions).unwrap();
+        assert!(bulk_yank.pending_versions(conn).unwrap().is_empty());
+
+        let bulk_yank = bulk_yank.complete(conn).unwrap();
+        assert_some!(bulk_yank.completed_at);
+        assert_some_eq!(bulk_yank.versions_yanked, 1);
+        assert_eq!(
+            BulkYank::find(conn, bulk_yank.id).unwrap().versions_yanked,
+            Some(1)
+        );
+    });
+}
+
+#[test]
+fn yanks_versions_published_with_token() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    token.enqueue_publish(PublishBuilder::new("fyk")).good();
+    app.run_pending_background_jobs();
+
+    let since = Utc::now() - Duration::hours(1);
+    let body = json!({
+        "api_token_id": token.as_model().id,
+        "since": since.to_rfc3339(),
+        "reason": "token leaked in a public CI log",
+    })
+    .to_string();
+    let json: BulkYankResponse = admin
+        .put("/api/v1/admin/bulk_yanks", body.as_bytes())
+        .good();
+    assert_eq!(json.bulk_yank.user_id, token.as_model().user_id);
+    assert_eq!(json.bulk_yank.admin_id, admin.as_model().id);
+    assert_none!(json.bulk_yank.completed_at);
+    let id = json.bulk_yank.id;
+
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("3/f/fyk");
+    assert_eq!(crates.len(), 1);
+    assert_some_eq!(crates[0].yanked, true);
+
+    let json = anon.show_version("fyk", "1.0.0");
+    assert!(json.version.yanked);
+    assert_eq!(json.version.audit_actions[1].action, "yank");
+    assert_eq!(json.version.audit_actions[1].user.login, "admin");
+
+    let url = format!("/api/v1/admin/bulk_yanks/{}", id);
+    let json: BulkYankResponse = admin.get(&url).good();
+    assert_some!(json.bulk_yank.completed_at);
+    assert_some_eq!(json.bulk_yank.versions_yanked, 1);
+
+    let json: BulkYankList = admin.get("/api/v1/admin/bulk_yanks").good();
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.bulk_yanks[0].id, id);
+}
+
+#[test]
+fn retried_jobs_notify_owners_of_all_yanked_versions() {
+    let (app, _, user, token) = TestApp::full().with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    token.enqueue_publish(PublishBuilder::new("fyr")).good();
+    token
+        .enqueue_publish(PublishBuilder::new("fyr").version("1.1.0"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let now = Utc::now().naive_utc();
+    let bulk_yank = app.db(|conn| {
+        let bulk_yank = NewBulkYank {
+            admin_id: admin.as_model().id,
+            user_id: user.as_model().id,
+            api_token_id: None,
+            since: now - Duration::hours(1),
+            until: now + Duration::hours(1),
+            reason: "compromised account",
+        }
+        .create(conn)
+        .unwrap();
+
+        // An earlier attempt of the job yanked the first version before failing
+        let pending = bulk_yank.pending_versions(conn).unwrap();
+        let first = pending
+            .into_iter()
+            .map(|(v, _)| v)
+            .take(1)
+            .collect::<Vec<_>>();
+        bulk_yank.yank_versions(conn, &first).unwrap();
+
+        Job::BulkYank {
+            bulk_yank_id: bulk_yank.id,
+        }
+        .enqueue(conn)
+        .unwrap();
+        bulk_yank
+    });
+    app.run_pending_background_jobs();
+
+    let bulk_yank = app.db(|conn| BulkYank::find(conn, bulk_yank.id).unwrap());
+    assert_some_eq!(bulk_yank.versions_yanked, 2);
+
+    let json: NotificationList = user.get("/api/v1/me/notifications").good();
+    assert_eq!(json.notifications.len(), 1);
+    assert_eq!(
+        json.notifications[0].title,
+        "The crates.io team yanked fyr 1.0.0, 1.1.0: compromised account"
+    );
+}
@@ -0,0 +1,200 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::{CrateResponse, OkBool};
+use cargo_registry::cache::{self, CacheKey};
+use cargo_registry::schema::{crates, keywords, version_downloads};
+use cargo_registry::views::EncodableKeyword;
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[test]
+fn warm_up_caches_the_most_downloaded_crates() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.response_cache_ttl = 60)
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_popular", user.as_model().id)
+            .description("cached")
+            .downloads(1000)
+            .version("1.0.0")
+            .expect_build(conn);
+        CrateBuilder::new("foo_unpopular", user.as_model().id)
+            .downloads(10)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let count = cache::warm_up(app.as_inner(), 1).unwrap();
+    assert_eq!(count, 1);
+
+    let response_cache = &app.as_inner().response_cache;
+    assert_some!(response_cache.get(&CacheKey::Summary));
+    assert_some!(response_cache.get(&CacheKey::Crate("foo_popular".into())));
+    assert_some!(response_cache.get(&CacheKey::Versions("foo_popular".into())));
+    assert_none!(response_cache.get(&CacheKey::Crate("foo_unpopular".into())));
+
+    // Cached responses are served without hitting the database
+    app.db(|conn| {
+        diesel::update(crates::table)
+            .set(crates::description.eq("changed"))
+            .execute(conn)
+            .unwrap();
+    });
+    let json: CrateResponse = anon.show_crate("foo_popular");
+    assert_eq!(json.krate.description.as_deref(), Some("cached"));
+    assert_eq!(json.versions.len(), 1);
+    let json: CrateResponse = anon.show_crate("foo_unpopular");
+    assert_eq!(json.krate.description.as_deref(), Some("changed"));
+
+    response_cache.invalidate_crate("foo_popular");
+    let json: CrateResponse = anon.show_crate("foo_popular");
+    assert_eq!(json.krate.description.as_deref(), Some("changed"));
+}
+
+#[test]
+fn requested_responses_are_cached_with_a_ttl() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.response_cache_ttl = 60)
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_requested", user.as_model().id)
+            .description("cached")
+            .keyword("kw_cached")
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json: CrateResponse = anon.show_crate("FOO-requested");
+    assert_eq!(json.krate.description.as_deref(), Some("cached"));
+    let response_cache = &app.as_inner().response_cache;
+    assert_some!(response_cache.get(&CacheKey::Crate("foo_requested".into())));
+    assert_some!(response_cache.get(&CacheKey::Versions("foo_requested".into())));
+
+    #[derive(Deserialize)]
+    struct GoodKeyword {
+        keyword: EncodableKeyword,
+    }
+    let url = "/api/v1/keywords/kw_cached";
+    let json: GoodKeyword = anon.get(url).good();
+    assert_eq!(json.keyword.crates_cnt, 1);
+
+    app.db(|conn| {
+        diesel::update(crates::table)
+            .set(crates::description.eq("changed"))
+            .execute(conn)
+            .unwrap();
+        diesel::update(keywords::table)
+            .set(keywords::crates_cnt.eq(5))
+            .execute(conn)
+            .unwrap();
+    });
+    let json: CrateResponse = anon.show_crate("foo_requested");
+    assert_eq!(json.krate.description.as_deref(), Some("cached"));
+    let json: GoodKeyword = anon.get(url).good();
+    assert_eq!(json.keyword.crates_cnt, 1);
+
+    response_cache.invalidate_crate("foo_requested");
+    response_cache.invalidate_keywords();
+    let json: CrateResponse = anon.show_crate("foo_requested");
+    assert_eq!(json.krate.description.as_deref(), Some("changed"));
+    let json: GoodKeyword = anon.get(url).good();
+    assert_eq!(json.keyword.crates_cnt, 5);
+}
+
+#[test]
+fn keyword_lists_are_cached_by_page_and_sort_order() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.response_cache_ttl = 60)
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_listed", user.as_model().id)
+            .keyword("kw_listed")
+            .expect_build(conn);
+    });
+
+    let response = anon.get::<()>("/api/v1/keywords?sort=crates&per_page=5");
+    assert_eq!(response.status(), StatusCode::OK);
+    let response_cache = &app.as_inner().response_cache;
+    let key = |page, sort: &str| CacheKey::Keywords {
+        page,
+        per_page: 5,
+        sort: sort.into(),
+    };
+    assert_some!(response_cache.get(&key(1, "crates")));
+    assert_none!(response_cache.get(&key(1, "alpha")));
+
+    // Unknown parameters and sort orders don't create separate entries
+    let response = anon.get::<()>("/api/v1/keywords?per_page=5&sort=unknown&page=1&foo=bar");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_some!(response_cache.get(&key(1, "alpha")));
+    assert_none!(response_cache.get(&key(2, "alpha")));
+
+    response_cache.invalidate_keywords();
+    assert_none!(response_cache.get(&key(1, "crates")));
+    assert_none!(response_cache.get(&key(1, "alpha")));
+}
+
+#[test]
+fn downloads_are_resolved_from_the_version_lookup_cache() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_lookup", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let version_lookups = &app.as_inner().version_lookups;
+    assert!(version_lookups.is_empty());
+
+    let response = anon.get::<()>("/api/v1/crates/FOO-lookup/1.0.0/download");
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let (version_id, name, _) = assert_some!(version_lookups.get("foo_lookup", "1.0.0"));
+    assert_eq!(name, "foo_lookup");
+
+    let response = anon.get::<()>("/api/v1/crates/foo_lookup/1.0.0/download");
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(version_lookups.len(), 1);
+
+    let downloads: i32 = app.db(|conn| {
+        version_downloads::table
+            .filter(version_downloads::version_id.eq(version_id))
+            .select(version_downloads::downloads)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(downloads, 2);
+
+    // Unknown versions are not cached
+    anon.get::<()>("/api/v1/crates/foo_lookup/2.0.0/download")
+        .assert_not_found();
+    assert_eq!(version_lookups.len(), 1);
+}
+
+#[test]
+fn yanks_invalidate_the_cache_once_the_job_ran() {
+    let (app, anon, _, token) = TestApp::full()
+        .with_config(|config| config.response_cache_ttl = 60)
+        .with_token();
+    token
+        .enqueue_publish(PublishBuilder::new("foo_yanked"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let json: CrateResponse = anon.show_crate("foo_yanked");
+    assert!(!json.versions[0].yanked);
+    let response_cache = &app.as_inner().response_cache;
+    let key = CacheKey::Crate("foo_yanked".into());
+    assert_some!(response_cache.get(&key));
+
+    // Until the job has yanked the version, the cached response is still
+    // up to date
+    token
+        .delete::<OkBool>("/api/v1/crates/foo_yanked/1.0.0/yank")
+        .good();
+    assert_some!(response_cache.get(&key));
+
+    app.run_pending_background_jobs();
+    assert_none!(response_cache.get(&key));
+    let json: CrateResponse = anon.show_crate("foo_yanked");
+    assert!(json.versions[0].yanked);
+}
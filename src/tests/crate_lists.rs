@@ -0,0 +1,100 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::views::{EncodableCrateList, EncodableCrateListItem};
+
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct CrateListResponse {
+    crate_list: EncodableCrateList,
+}
+
+#[derive(Deserialize)]
+struct CrateListWithCrates {
+    crate_list: EncodableCrateList,
+    crates: Vec<EncodableCrateListItem>,
+}
+
+#[derive(Deserialize)]
+struct CrateLists {
+    crate_lists: Vec<EncodableCrateList>,
+    meta: Meta,
+}
+
+#[derive(Deserialize)]
+struct Meta {
+    total: i64,
+}
+
+const NEW_LIST: &[u8] =
+    br#"{"crate_list": {"name": "Awesome embedded", "description": "Crates for microcontrollers"}}"#;
+
+#[test]
+fn lists_are_curated_and_shared() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_embedded", user.as_model().id)
+            .description("A HAL")
+            .expect_build(conn);
+        CrateBuilder::new("foo_other", user.as_model().id).expect_build(conn);
+    });
+
+    let json: CrateListResponse = user.put("/api/v1/crate_lists", NEW_LIST).good();
+    let id = json.crate_list.id;
+    assert_eq!(json.crate_list.name, "Awesome embedded");
+    assert_eq!(json.crate_list.owner.login, user.as_model().gh_login);
+
+    let url = format!("/api/v1/crate_lists/{}", id);
+    let entry_url = format!("{}/crates/foo_embedded", url);
+    user.put::<OkBool>(&entry_url, br#"{"note": "Start here"}"#)
+        .good();
+    user.put::<OkBool>(&format!("{}/crates/foo_other", url), b"")
+        .good();
+    user.delete::<OkBool>(&format!("{}/crates/foo_other", url))
+        .good();
+
+    let json: CrateListWithCrates = anon.get(&url).good();
+    assert_eq!(json.crate_list.crates_cnt, 1);
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].krate, "foo_embedded");
+    assert_eq!(json.crates[0].description.as_deref(), Some("A HAL"));
+    assert_eq!(json.crates[0].note.as_deref(), Some("Start here"));
+
+    // Adding a crate again replaces its note
+    user.put::<OkBool>(&entry_url, br#"{"note": "The best HAL"}"#)
+        .good();
+    let json: CrateListWithCrates = anon.get(&url).good();
+    assert_eq!(json.crates[0].note.as_deref(), Some("The best HAL"));
+
+    let json: CrateLists = anon.get("/api/v1/crate_lists").good();
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.crate_lists[0].id, id);
+    assert_eq!(json.crate_lists[0].crates_cnt, 1);
+    let query = format!("user_id={}&sort=recent", user.as_model().id + 1);
+    let json: CrateLists = anon.get_with_query("/api/v1/crate_lists", &query).good();
+    assert_eq!(json.meta.total, 0);
+
+    user.delete::<OkBool>(&url).good();
+    anon.get::<()>(&url).assert_not_found();
+}
+
+#[test]
+fn only_the_owner_can_change_a_list() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    let json: CrateListResponse = user.put("/api/v1/crate_lists", NEW_LIST).good();
+    let url = format!("/api/v1/crate_lists/{}", json.crate_list.id);
+
+    other.put::<()>(&url, NEW_LIST).assert_forbidden();
+    other.delete::<()>(&url).assert_forbidden();
+
+    let response = user.put::<()>(&url, br#"{"crate_list": {"name": "  "}}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "the name of a list must not be empty"
+    );
+}
@@ -0,0 +1,41 @@
+use crate::util::{RequestHelper, TestApp};
+
+use conduit::header;
+use serde_json::Value;
+
+#[test]
+fn dev_login_is_only_available_in_dev_mode() {
+    let (_, anon) = TestApp::init().empty();
+
+    let json: Value = anon.get("/api/private/session/begin").good();
+    assert!(json["url"]
+        .as_str()
+        .unwrap()
+        .starts_with("https://github.com/"));
+    anon.get::<()>("/api/private/session/dev_login")
+        .assert_not_found();
+}
+
+#[test]
+fn dev_login_lists_the_seed_users() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.dev_mode = true)
+        .empty();
+
+    let json: Value = anon.get("/api/private/session/begin").good();
+    let state = json["state"].as_str().unwrap();
+    let url = json["url"].as_str().unwrap();
+    assert!(url.starts_with("/api/private/session/dev_login?state="));
+
+    let response = anon.get::<()>(url);
+    assert_some_eq!(
+        response.header(header::CONTENT_TYPE),
+        "text/html; charset=utf-8"
+    );
+    let body = String::from_utf8(response.into_bytes()).unwrap();
+    for login in &["alice", "bob", "carol"] {
+        let link = format!("/authorize/github?code={}&state=", login);
+        assert!(body.contains(&link));
+    }
+    assert!(body.contains(state));
+}
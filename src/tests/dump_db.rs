@@ -1,8 +1,11 @@
-use cargo_registry::tasks::dump_db;
+use crate::util::TestApp;
+use cargo_registry::models::{DatabaseDump, NewDatabaseDump};
+use cargo_registry::tasks::dump_db::{self, DumpFormat};
 use diesel::{
     connection::{Connection, SimpleConnection},
     pg::PgConnection,
 };
+use std::path::Path;
 
 #[test]
 fn dump_db_and_reimport_dump() {
@@ -11,17 +14,80 @@ fn dump_db_and_reimport_dump() {
     // TODO prefill database with some data
 
     let directory = dump_db::DumpDirectory::create().unwrap();
-    directory.populate(&database_url).unwrap();
+    directory
+        .populate(&database_url, None, &[DumpFormat::Ndjson])
+        .unwrap();
 
-    let schema = TemporarySchema::create(database_url, "test_db_dump");
+    let schema = TemporarySchema::create(database_url.clone(), "test_db_dump");
     schema.run_migrations();
 
+    let import_script = directory.export_dir.join("import.sql");
+    dump_db::run_psql(&import_script, &schema.database_url).unwrap();
+    check_ndjson_export(&directory.export_dir);
+    drop(directory);
+
+    // An incremental dump applies on top of the full dump
+    let since = chrono::Utc::now().naive_utc() - chrono::Duration::days(1);
+    let directory = dump_db::DumpDirectory::create().unwrap();
+    directory.populate(&database_url, Some(since), &[]).unwrap();
+
     let import_script = directory.export_dir.join("import.sql");
     dump_db::run_psql(&import_script, &schema.database_url).unwrap();
 
     // TODO: Consistency checks on the re-imported data?
 }
 
+#[test]
+fn download_history_is_listed_apart_from_the_dumps() {
+    let (app, _) = TestApp::init().empty();
+    let now = chrono::Utc::now().naive_utc();
+    app.db(|conn| {
+        let dump = |target_name, since: Option<_>, download_history| NewDatabaseDump {
+            target_name,
+            incremental: since.is_some(),
+            since,
+            dumped_at: now - chrono::Duration::hours(1),
+            size: 1,
+            download_history,
+        };
+        dump("db-dump.tar.gz", None, false).create(conn).unwrap();
+        dump(dump_db::DOWNLOAD_HISTORY_NAME, None, true)
+            .create(conn)
+            .unwrap();
+
+        let latest = DatabaseDump::latest(conn).unwrap().unwrap();
+        assert_eq!(latest.target_name, "db-dump.tar.gz");
+
+        let manifest = dump_db::Manifest::load(conn, now).unwrap();
+        assert_eq!(manifest.full.unwrap().path, "db-dump.tar.gz");
+        assert!(manifest.incremental.is_empty());
+        assert_eq!(
+            manifest.download_history.unwrap().path,
+            dump_db::DOWNLOAD_HISTORY_NAME
+        );
+    });
+}
+
+/// Checks that every CSV file has an NDJSON file of valid objects, and that
+/// both are listed in `schemas.json`.
+fn check_ndjson_export(export_dir: &Path) {
+    let schemas = std::fs::read_to_string(export_dir.join("schemas.json")).unwrap();
+    let schemas: serde_json::Value = serde_json::from_str(&schemas).unwrap();
+    assert_eq!(schemas["formats"], json!(["csv", "ndjson"]));
+
+    let tables = schemas["tables"].as_array().unwrap();
+    assert!(tables.iter().any(|table| table["name"] == "crates"));
+    for table in tables {
+        assert!(!table["columns"].as_array().unwrap().is_empty());
+        let path = table["files"]["ndjson"].as_str().unwrap();
+        let ndjson = std::fs::read_to_string(export_dir.join(path)).unwrap();
+        for line in ndjson.lines() {
+            let row: serde_json::Value = serde_json::from_str(line).unwrap();
+            assert!(row.is_object(), "{}", line);
+        }
+    }
+}
+
 struct TemporarySchema {
     pub database_url: String,
     pub schema_name: String,
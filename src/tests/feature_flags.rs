@@ -0,0 +1,82 @@
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+
+use serde_json::Value;
+
+const URL: &str = "/api/v1/admin/feature_flags";
+
+#[test]
+fn non_admins_cannot_manage_feature_flags() {
+    let (_, anon, user) = TestApp::init().with_user();
+    let url = format!("{}/new_search_ranking", URL);
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+    user.put::<()>(&url, br#"{ "enabled": true }"#)
+        .assert_forbidden();
+    user.delete::<()>(&url).assert_forbidden();
+}
+
+#[test]
+fn feature_flags_are_enabled_for_the_chosen_users() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let other = app.db_new_user("other");
+    let user_id = user.as_model().id;
+    let other_id = other.as_model().id;
+    let url = format!("{}/new_search_ranking", URL);
+
+    let is_enabled = |user_id| {
+        let app = app.as_inner();
+        app.feature_flags
+            .is_enabled(&app.primary_database, "new_search_ranking", user_id)
+            .unwrap()
+    };
+    assert!(!is_enabled(Some(user_id)));
+
+    let body = format!(
+        r#"{{ "description": "BM25 ranking", "user_ids": [{}] }}"#,
+        user_id
+    );
+    let json: Value = admin.put(&url, body.as_bytes()).good();
+    assert_eq!(json["feature_flag"]["name"], "new_search_ranking");
+    assert_eq!(json["feature_flag"]["description"], "BM25 ranking");
+    assert_eq!(json["feature_flag"]["percentage"], 0);
+    assert!(is_enabled(Some(user_id)));
+    assert!(!is_enabled(Some(other_id)));
+    assert!(!is_enabled(None));
+
+    admin.put::<Value>(&url, br#"{ "enabled": true }"#).good();
+    assert!(is_enabled(Some(other_id)));
+    assert!(is_enabled(None));
+
+    let json: Value = admin.get(URL).good();
+    let flags = json["feature_flags"].as_array().unwrap();
+    assert_eq!(flags.len(), 1);
+    assert_eq!(flags[0]["enabled"], true);
+    assert_eq!(flags[0]["description"], Value::Null);
+
+    admin.delete::<OkBool>(&url).good();
+    assert!(!is_enabled(None));
+    admin.delete::<()>(&url).assert_not_found();
+}
+
+#[test]
+fn invalid_feature_flags_are_rejected() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let response = admin.put::<()>(&format!("{}/New-Ranking", URL), b"{}");
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "the name must consist of at most 64 lowercase letters, digits and underscores"
+    );
+    let response = admin.put::<()>(
+        &format!("{}/new_search_ranking", URL),
+        br#"{ "percentage": 101 }"#,
+    );
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "the percentage must be between 0 and 100"
+    );
+}
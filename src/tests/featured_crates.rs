@@ -0,0 +1,109 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::views::{EncodableCrate, EncodableFeaturedCrate};
+
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+use serde_json::Value;
+
+const ADMIN_URL: &str = "/api/v1/admin/featured_crates";
+
+#[derive(Deserialize)]
+struct FeaturedCrateResponse {
+    featured_crate: EncodableFeaturedCrate,
+}
+
+#[derive(Deserialize)]
+struct FeaturedCrateList {
+    featured_crates: Vec<EncodableFeaturedCrate>,
+}
+
+#[derive(Deserialize)]
+struct FeaturedResponse {
+    crates: Vec<EncodableCrate>,
+    featured_crates: Vec<EncodableFeaturedCrate>,
+}
+
+#[test]
+fn non_admins_cannot_schedule_featured_crates() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(ADMIN_URL).assert_forbidden();
+    user.get::<()>(ADMIN_URL).assert_forbidden();
+    user.put::<()>(ADMIN_URL, b"{}").assert_forbidden();
+    user.delete::<()>(&format!("{}/1", ADMIN_URL))
+        .assert_forbidden();
+}
+
+#[test]
+fn featured_crates_are_listed_during_their_week() {
+    let (app, anon) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_featured", admin.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_featured", admin.as_model().id).expect_build(conn);
+    });
+
+    let today = Utc::now().naive_utc().date();
+    let body = json!({
+        "crate": "foo_featured",
+        "starts_on": today - Duration::days(1),
+        "description": "  A crate of the week  ",
+    });
+    let json: FeaturedCrateResponse = admin.put(ADMIN_URL, body.to_string().as_bytes()).good();
+    let featured = json.featured_crate;
+    assert_eq!(featured.crate_name, "foo_featured");
+    assert_eq!(featured.ends_on, today + Duration::days(5));
+    assert_some_eq!(featured.description.as_deref(), "A crate of the week");
+
+    let body = json!({ "crate": "bar_featured", "starts_on": today + Duration::days(7) });
+    admin
+        .put::<FeaturedCrateResponse>(ADMIN_URL, body.to_string().as_bytes())
+        .good();
+
+    let json: FeaturedCrateList = admin.get(ADMIN_URL).good();
+    assert_eq!(json.featured_crates.len(), 2);
+    assert_eq!(json.featured_crates[0].crate_name, "bar_featured");
+
+    let json: FeaturedResponse = anon.get("/api/v1/featured_crates").good();
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].name, "foo_featured");
+    assert_eq!(json.featured_crates[0].id, featured.id);
+
+    let json: Value = anon.get("/api/v1/summary").good();
+    assert_eq!(json["featured"][0]["name"], "foo_featured");
+
+    let url = format!("{}/{}", ADMIN_URL, featured.id);
+    admin.delete::<OkBool>(&url).good();
+    admin.delete::<()>(&url).assert_not_found();
+    let json: FeaturedResponse = anon.get("/api/v1/featured_crates").good();
+    assert!(json.crates.is_empty());
+}
+
+#[test]
+fn invalid_schedules_are_rejected() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_featured", admin.as_model().id).expect_build(conn);
+    });
+
+    let body =
+        br#"{ "crate": "foo_featured", "starts_on": "2021-04-12", "ends_on": "2021-04-11" }"#;
+    let response = admin.put::<()>(ADMIN_URL, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = br#"{ "crate": "foo_featured", "starts_on": "2021-04-12" }"#;
+    admin.put::<FeaturedCrateResponse>(ADMIN_URL, body).good();
+    let body = br#"{ "crate": "foo_featured", "starts_on": "2021-04-15" }"#;
+    let response = admin.put::<()>(ADMIN_URL, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "`foo_featured` is already featured during this time"
+    );
+
+    let body = br#"{ "crate": "unknown", "starts_on": "2021-04-12" }"#;
+    admin.put::<()>(ADMIN_URL, body).assert_not_found();
+}
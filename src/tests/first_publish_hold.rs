@@ -0,0 +1,168 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::first_publish_hold::FirstPublishHold;
+use cargo_registry::schema::{users, version_quarantines, versions};
+use cargo_registry::views::EncodableVersionQuarantine;
+use cargo_registry::Config;
+
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct QuarantineResponse {
+    quarantine: EncodableVersionQuarantine,
+}
+
+#[derive(Deserialize)]
+struct VerifyPublisherResponse {
+    user_id: i32,
+    released_versions: usize,
+}
+
+fn hold_new_accounts(config: &mut Config) {
+    config.first_publish_hold = Some(FirstPublishHold {
+        account_age: std::time::Duration::from_secs(60 * 60 * 24 * 3),
+        window: Some(std::time::Duration::from_secs(60 * 60)),
+    });
+}
+
+fn version_id(app: &TestApp, krate: &str) -> i32 {
+    use cargo_registry::schema::crates;
+
+    app.db(|conn| {
+        versions::table
+            .inner_join(crates::table)
+            .filter(crates::name.eq(krate))
+            .select(versions::id)
+            .first(conn)
+            .unwrap()
+    })
+}
+
+fn is_in_index(app: &TestApp, path: &str) -> bool {
+    let index = app.upstream_repository();
+    let tree = index.head().unwrap().peel_to_tree().unwrap();
+    tree.get_path(std::path::Path::new(path)).is_ok()
+}
+
+#[test]
+fn held_versions_are_added_to_the_index_once_released() {
+    let (app, anon, _, token) = TestApp::full().with_config(hold_new_accounts).with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_held"))
+        .good();
+    assert_eq!(json.warnings.other.len(), 1);
+    app.run_pending_background_jobs();
+    assert!(!is_in_index(&app, "fo/o_/foo_held"));
+
+    let response = anon.get::<()>("/api/v1/crates/foo_held/1.0.0/download");
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let url = format!(
+        "/api/v1/admin/quarantine/{}/release",
+        version_id(&app, "foo_held")
+    );
+    let json: QuarantineResponse = admin.put(&url, b"").good();
+    assert_eq!(json.quarantine.status, "released");
+    assert!(!json.quarantine.held);
+    app.run_pending_background_jobs();
+
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_held").len(), 1);
+}
+
+#[test]
+fn expired_holds_are_released_by_the_background_job() {
+    let (app, _, _, token) = TestApp::full().with_config(hold_new_accounts).with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_expired"))
+        .good();
+    app.run_pending_background_jobs();
+    assert!(!is_in_index(&app, "fo/o_/foo_expired"));
+
+    app.db(|conn| {
+        diesel::update(version_quarantines::table)
+            .set(version_quarantines::release_at.eq(Utc::now().naive_utc() - Duration::minutes(1)))
+            .execute(conn)
+            .unwrap();
+        Job::ReleaseHeldVersions {}.enqueue(conn).unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_expired").len(), 1);
+}
+
+#[test]
+fn rejecting_held_versions_yanks_them_without_adding_them_to_the_index() {
+    let (app, anon, _, token) = TestApp::full().with_config(hold_new_accounts).with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_rejected"))
+        .good();
+    app.run_pending_background_jobs();
+
+    let url = format!(
+        "/api/v1/admin/quarantine/{}/reject",
+        version_id(&app, "foo_rejected")
+    );
+    let json: QuarantineResponse = admin.put(&url, b"").good();
+    assert_eq!(json.quarantine.status, "rejected");
+    app.run_pending_background_jobs();
+
+    assert!(!is_in_index(&app, "fo/o_/foo_rejected"));
+    let json = anon.show_version("foo_rejected", "1.0.0");
+    assert!(json.version.yanked);
+}
+
+#[test]
+fn verified_publishers_are_not_held() {
+    let (app, _, user, token) = TestApp::full().with_config(hold_new_accounts).with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    token
+        .enqueue_publish(PublishBuilder::new("verified_a"))
+        .good();
+    app.run_pending_background_jobs();
+    assert!(!is_in_index(&app, "ve/ri/verified_a"));
+
+    let url = format!(
+        "/api/v1/admin/users/{}/verify_publisher",
+        user.as_model().id
+    );
+    user.put::<()>(&url, b"").assert_forbidden();
+    let json: VerifyPublisherResponse = admin.put(&url, b"").good();
+    assert_eq!(json.user_id, user.as_model().id);
+    assert_eq!(json.released_versions, 1);
+    app.run_pending_background_jobs();
+    assert_eq!(app.crates_from_index_head("ve/ri/verified_a").len(), 1);
+
+    let json = token
+        .enqueue_publish(PublishBuilder::new("verified_b"))
+        .good();
+    assert!(json.warnings.other.is_empty());
+    app.run_pending_background_jobs();
+    assert_eq!(app.crates_from_index_head("ve/ri/verified_b").len(), 1);
+}
+
+#[test]
+fn older_accounts_are_not_held() {
+    let (app, _, user, token) = TestApp::full().with_config(hold_new_accounts).with_token();
+    app.db(|conn| {
+        diesel::update(users::table.find(user.as_model().id))
+            .set(users::created_at.eq(Utc::now().naive_utc() - Duration::days(30)))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_old_account"))
+        .good();
+    assert!(json.warnings.other.is_empty());
+    app.run_pending_background_jobs();
+    assert_eq!(app.crates_from_index_head("fo/o_/foo_old_account").len(), 1);
+}
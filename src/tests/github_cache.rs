@@ -0,0 +1,30 @@
+use crate::util::TestApp;
+use cargo_registry::github::cache::{self, CachedResponse};
+use cargo_registry::github::GitHubClient;
+use chrono::{Duration, Utc};
+use oauth2::AccessToken;
+
+#[test]
+fn cached_responses_are_used_without_asking_github() {
+    let (app, _) = TestApp::init().empty();
+    // Without an HTTP client, any request to GitHub would panic
+    let github = GitHubClient::new(None, String::from("https://api.github.com"))
+        .with_cache(app.as_inner().primary_database.clone());
+    let token = AccessToken::new(String::from("gho_cached"));
+
+    let url = "/organizations/1/team/2/memberships/foo";
+    app.db(|conn| {
+        let key = cache::key(url, &token);
+        let body = json!({ "state": "active" });
+        CachedResponse::new(key, Some("\"etag\"".into()), body)
+            .save(conn)
+            .unwrap();
+    });
+
+    let membership = github.team_membership(1, 2, "foo", &token).unwrap();
+    assert_eq!(membership.state, "active");
+
+    let mut stale = CachedResponse::new(cache::key(url, &token), None, json!({}));
+    stale.fetched_at = (Utc::now() - Duration::minutes(5)).naive_utc();
+    assert!(!stale.is_fresh(cache::ttl(url)));
+}
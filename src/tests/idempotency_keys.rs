@@ -0,0 +1,68 @@
+use crate::builders::PublishBuilder;
+use crate::util::{MockTokenUser, RequestHelper, Response, TestApp};
+use cargo_registry::schema::versions;
+use cargo_registry::views::GoodCrate;
+use conduit::header::HeaderName;
+use conduit::{Method, StatusCode};
+use diesel::prelude::*;
+
+fn publish_with_key(token: &MockTokenUser, publish: PublishBuilder) -> Response<GoodCrate> {
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    request.header(HeaderName::from_static("idempotency-key"), "ci-run-42");
+    request.with_body(&publish.body());
+    token.run(request)
+}
+
+fn replayed(response: &Response<GoodCrate>) -> Option<&str> {
+    response.header(HeaderName::from_static("idempotent-replayed"))
+}
+
+#[test]
+fn retried_publishes_get_the_first_response() {
+    let (app, _, _, token) = TestApp::full().with_token();
+
+    let response = publish_with_key(&token, PublishBuilder::new("foo_retried"));
+    assert_eq!(replayed(&response), None);
+    assert_eq!(response.good().krate.name, "foo_retried");
+
+    // Without the key, the retry would fail as the version already exists
+    let response = publish_with_key(&token, PublishBuilder::new("foo_retried"));
+    assert_eq!(replayed(&response), Some("true"));
+    assert_eq!(response.good().krate.name, "foo_retried");
+
+    let versions = app.db(|conn| versions::table.count().get_result::<i64>(conn).unwrap());
+    assert_eq!(versions, 1);
+
+    let response = publish_with_key(&token, PublishBuilder::new("foo_retried").version("1.0.1"));
+    assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(
+        response.json()["errors"][0]["code"],
+        "idempotency_key_reused"
+    );
+}
+
+#[test]
+fn idempotency_keys_belong_to_their_user() {
+    let (app, _, _, token) = TestApp::full().with_token();
+    let other = app.db_new_user("other").db_new_token("ci");
+
+    let response = publish_with_key(&token, PublishBuilder::new("foo_first"));
+    assert_eq!(response.good().krate.name, "foo_first");
+
+    let response = publish_with_key(&other, PublishBuilder::new("foo_second"));
+    assert_eq!(replayed(&response), None);
+    assert_eq!(response.good().krate.name, "foo_second");
+}
+
+#[test]
+fn bodies_are_read_up_to_the_largest_upload() {
+    let (_, _, _, token) = TestApp::full().with_token();
+
+    // The metadata and the tarball may each be up to `max_upload_size`
+    let mut request = token.request_builder(Method::PUT, "/api/v1/crates/new");
+    request.header(HeaderName::from_static("idempotency-key"), "ci-run-42");
+    request.with_body(&[0; 10_000]);
+    let response: Response<GoodCrate> = token.run(request);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.json()["errors"][0]["code"], "upload_too_large");
+}
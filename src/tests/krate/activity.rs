@@ -0,0 +1,117 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{insert_version_owner_action, NewAdvisory, VersionAction};
+use cargo_registry::schema::{advisories, crate_owners, versions};
+use cargo_registry::tasks::import_advisories;
+use cargo_registry::views::EncodableCrateActivity;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct ActivityResponse {
+    activity: Vec<EncodableCrateActivity>,
+    meta: ActivityMeta,
+}
+
+#[derive(Deserialize)]
+struct ActivityMeta {
+    next_page: Option<String>,
+}
+
+fn set_up(app: &TestApp, user_id: i32) {
+    let days_ago = |days| Utc::now().naive_utc() - Duration::days(days);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_activity", user_id)
+            .version(VersionBuilder::new("1.0.0").created_at(days_ago(3)))
+            .version(VersionBuilder::new("1.1.0").created_at(days_ago(2)))
+            .expect_build(conn);
+        diesel::update(crate_owners::table)
+            .set(crate_owners::created_at.eq(days_ago(4)))
+            .execute(conn)
+            .unwrap();
+
+        let version_id: i32 = versions::table
+            .filter(versions::num.eq("1.0.0"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        insert_version_owner_action(conn, version_id, user_id, None, VersionAction::Yank).unwrap();
+
+        let advisory = NewAdvisory {
+            id: "RUSTSEC-2021-0003".into(),
+            crate_name: "foo_activity".into(),
+            title: "Use after free in `Foo::bar`".into(),
+            url: None,
+            date: days_ago(1).date(),
+            patched: vec![">= 1.1.0".into()],
+            unaffected: vec![],
+            informational: None,
+            withdrawn: false,
+            description: None,
+            severity: None,
+            affected: vec![],
+            published_by: None,
+        };
+        assert_ok!(import_advisories(conn, &[advisory]));
+        diesel::update(advisories::table)
+            .set(advisories::created_at.eq(days_ago(1)))
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn activity_lists_events_of_all_kinds_newest_first() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    set_up(&app, user.id);
+
+    let json: ActivityResponse = anon.get("/api/v1/crates/foo_activity/activity").good();
+    let events: Vec<_> = json
+        .activity
+        .iter()
+        .map(|event| {
+            (
+                &*event.kind,
+                event.version.as_deref(),
+                event.subject.as_deref(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        events,
+        vec![
+            ("yank", Some("1.0.0"), None),
+            ("advisory", None, Some("RUSTSEC-2021-0003")),
+            ("publish", Some("1.1.0"), None),
+            ("publish", Some("1.0.0"), None),
+            ("owner_added", None, Some(&*user.gh_login)),
+        ]
+    );
+    assert_eq!(json.activity[0].actor.as_deref(), Some(&*user.gh_login));
+    assert_none!(json.meta.next_page);
+}
+
+#[test]
+fn activity_is_paginated_with_seek() {
+    let (app, anon, user) = TestApp::init().with_user();
+    set_up(&app, user.as_model().id);
+
+    let url = "/api/v1/crates/foo_activity/activity";
+    let mut kinds = Vec::new();
+    let mut query = "per_page=2".to_string();
+    loop {
+        let json: ActivityResponse = anon.get_with_query(url, &query).good();
+        kinds.extend(json.activity.into_iter().map(|event| event.kind));
+        match json.meta.next_page {
+            Some(next_page) => query = next_page[1..].to_string(),
+            None => break,
+        }
+    }
+    assert_eq!(
+        kinds,
+        ["yank", "advisory", "publish", "publish", "owner_added"]
+    );
+}
@@ -30,6 +30,6 @@ fn dependencies() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "crate `foo_deps` does not have a version `1.0.2`" }] })
+        json!({ "errors": [{ "detail": "crate `foo_deps` does not have a version `1.0.2`", "code": "invalid_request" }] })
     );
 }
@@ -0,0 +1,86 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::{EncodableGraphEdge, EncodableGraphNode};
+
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct GraphResponse {
+    nodes: Vec<EncodableGraphNode>,
+    edges: Vec<EncodableGraphEdge>,
+    truncated: bool,
+}
+
+#[test]
+fn dependency_graph_resolves_versions_down_to_the_depth() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let leaf = CrateBuilder::new("leaf", user.id)
+            .version("1.0.0")
+            .version(VersionBuilder::new("2.0.0").yanked(true))
+            .expect_build(conn);
+        let middle = CrateBuilder::new("middle", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&leaf, None))
+            .expect_build(conn);
+        let deep = CrateBuilder::new("deep", user.id).expect_build(conn);
+        let bottom = CrateBuilder::new("bottom", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&deep, None))
+            .expect_build(conn);
+        CrateBuilder::new("root", user.id)
+            .version("0.1.0")
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .dependency(&middle, None)
+                    .dependency(&leaf, None)
+                    .dependency(&bottom, None),
+            )
+            .expect_build(conn);
+    });
+
+    let json: GraphResponse = anon
+        .get_with_query("/api/v1/crates/root/dependency_graph", "depth=2")
+        .good();
+    let nodes: Vec<(&str, &str, u32)> = json
+        .nodes
+        .iter()
+        .map(|node| (node.krate.as_str(), node.num.as_str(), node.depth))
+        .collect();
+    assert_eq!(
+        nodes,
+        vec![
+            ("root", "1.0.0", 0),
+            ("bottom", "1.0.0", 1),
+            ("leaf", "1.0.0", 1),
+            ("middle", "1.0.0", 1),
+            ("deep", "0.99.0", 2),
+        ]
+    );
+    assert_eq!(json.edges.len(), 5);
+    assert!(json.edges.iter().all(|edge| edge.to.is_some()));
+    assert!(!json.truncated);
+
+    let json: GraphResponse = anon
+        .get_with_query(
+            "/api/v1/crates/root/dependency_graph",
+            "version=0.1.0&depth=1",
+        )
+        .good();
+    assert_eq!(json.nodes.len(), 1);
+    assert!(json.edges.is_empty());
+}
+
+#[test]
+fn dependency_graph_rejects_invalid_depths() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("root", user.id).expect_build(conn);
+    });
+
+    let response = anon.get_with_query::<()>("/api/v1/crates/root/dependency_graph", "depth=11");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    anon.get::<()>("/api/v1/crates/missing/dependency_graph")
+        .assert_not_found();
+}
@@ -2,6 +2,8 @@ use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use cargo_registry::views::EncodableVersionDownload;
 use chrono::{Duration, Utc};
+use conduit::header::{self, HeaderName};
+use conduit::Method;
 use http::StatusCode;
 
 #[derive(Deserialize)]
@@ -62,6 +64,80 @@ fn download() {
     assert_dl_count("FOO_DOWNLOAD", Some(&query), 2);
 }
 
+#[test]
+fn head_requests_and_resumed_downloads_are_not_counted() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_resume", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_resume/1.0.0/download";
+    let download = |method: Method, range: Option<&str>| {
+        let mut request = anon.request_builder(method, url);
+        if let Some(range) = range {
+            request.header(header::RANGE, range);
+        }
+        anon.run::<()>(request)
+            .assert_redirect_ends_with("/crates/foo_resume/foo_resume-1.0.0.crate");
+    };
+
+    download(Method::HEAD, None);
+    download(Method::GET, Some("bytes=512-"));
+    download(Method::GET, Some("bytes=-512"));
+    download(Method::GET, Some("bytes=0-"));
+    download(Method::GET, None);
+
+    let downloads: Downloads = anon.get("/api/v1/crates/foo_resume/downloads").good();
+    assert_eq!(downloads.version_downloads[0].downloads, 2);
+}
+
+#[test]
+fn downloads_are_redirected_to_the_healthy_origin_of_their_region() {
+    use cargo_registry::download_origins::{DownloadOrigin, OriginHealth};
+
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            let origins = DownloadOrigin::parse(
+                "https://us.static.crates.io 1 US, https://eu.static.crates.io 0 DE",
+            )
+            .unwrap();
+            config.uploader = config.uploader.clone().with_download_origins(origins);
+        })
+        .with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_origin", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let download = |region: &str| {
+        let url = "/api/v1/crates/foo_origin/1.0.0/download";
+        let mut request = anon.request_builder(Method::GET, url);
+        request.header(HeaderName::from_static("cloudfront-viewer-country"), region);
+        let response = anon.run::<()>(request);
+        let location = response.header(header::LOCATION).unwrap();
+        location.split("/crates/").next().unwrap().to_string()
+    };
+
+    assert_eq!(download("DE"), "https://eu.static.crates.io");
+    assert_eq!(download("BR"), "https://us.static.crates.io");
+
+    app.db(|conn| {
+        for _ in 0..3 {
+            let error = Some(String::from("connection refused"));
+            OriginHealth::record(conn, "https://eu.static.crates.io", error).unwrap();
+        }
+    });
+    app.as_inner().download_origins.invalidate();
+    assert_eq!(download("DE"), "https://us.static.crates.io");
+}
+
 #[test]
 fn download_nonexistent_version_of_existing_crate_404s() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -91,3 +167,38 @@ fn download_noncanonical_crate_name() {
     anon.get::<()>("/api/v1/crates/foo-download/1.0.0/download")
         .assert_redirect_ends_with("/crates/foo_download/foo_download-1.0.0.crate");
 }
+
+#[test]
+fn downloads_are_served_by_checksum_once_stored_there() {
+    use cargo_registry::schema::versions;
+    use diesel::prelude::*;
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let checksum = "c0ffee".repeat(10) + "abcd";
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_checksum", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+        diesel::update(
+            versions::table
+                .filter(versions::crate_id.eq(krate.id))
+                .filter(versions::num.eq("1.1.0")),
+        )
+        .set((
+            versions::checksum.eq(&checksum),
+            versions::stored_by_checksum.eq(true),
+        ))
+        .execute(conn)
+        .unwrap();
+    });
+
+    anon.get::<()>("/api/v1/crates/foo_checksum/1.1.0/download")
+        .assert_redirect_ends_with(&format!("/crates-by-checksum/c0/ff/{}.crate", checksum));
+
+    // Versions that are only stored by name are still served from there
+    anon.get::<()>("/api/v1/crates/foo_checksum/1.0.0/download")
+        .assert_redirect_ends_with("/crates/foo_checksum/foo_checksum-1.0.0.crate");
+}
@@ -1,11 +1,17 @@
+mod activity;
 mod dependencies;
+mod dependency_graph;
 mod downloads;
 mod following;
 mod owners;
+mod project_links;
 mod publish;
 mod reverse_dependencies;
 mod search;
 mod show;
+mod storage;
 mod summary;
+mod timeline;
+mod trending;
 mod versions;
 mod yanking;
@@ -0,0 +1,125 @@
+use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{NewProjectLink, ProjectLink, ProjectLinkKind};
+use cargo_registry::views::{EncodableFundedDependency, EncodableProjectLink};
+
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct ProjectLinks {
+    project_links: Vec<EncodableProjectLink>,
+}
+
+#[derive(Deserialize)]
+struct Funding {
+    crates: Vec<EncodableFundedDependency>,
+}
+
+const LINKS: &[u8] = br#"{"project_links": [
+    {"kind": "changelog", "url": "https://example.com/CHANGELOG.md"},
+    {"kind": "funding", "url": "https://example.com/sponsor", "label": " Sponsor us "}
+]}"#;
+
+#[test]
+fn owners_attach_links_to_their_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_links", user.as_model().id).expect_build(conn);
+    });
+
+    let json: ProjectLinks = user
+        .put("/api/v1/crates/foo_links/project_links", LINKS)
+        .good();
+    assert_eq!(json.project_links.len(), 2);
+
+    let json = anon.show_crate("foo_links");
+    let links = json.krate.project_links.unwrap();
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].kind, "changelog");
+    assert_eq!(links[0].url, "https://example.com/CHANGELOG.md");
+    assert_eq!(links[1].kind, "funding");
+    assert_eq!(links[1].label.as_deref(), Some("Sponsor us"));
+
+    // The links of a request replace all previous links
+    let json: ProjectLinks = user
+        .put(
+            "/api/v1/crates/foo_links/project_links",
+            br#"{"project_links": []}"#,
+        )
+        .good();
+    assert!(json.project_links.is_empty());
+    let json = anon.show_crate("foo_links");
+    assert_eq!(json.krate.project_links.unwrap().len(), 0);
+}
+
+#[test]
+fn links_are_validated() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    app.db(|conn| {
+        CrateBuilder::new("foo_links", user.as_model().id).expect_build(conn);
+    });
+    let url = "/api/v1/crates/foo_links/project_links";
+
+    let response = other.put::<()>(url, LINKS);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = br#"{"project_links": [{"kind": "chat", "url": "javascript:alert(1)"}]}"#;
+    let response = user.put::<()>(url, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = br#"{"project_links": [{"kind": "blog", "url": "https://example.com"}]}"#;
+    let response = user.put::<()>(url, body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "unknown kind of link `blog`"
+    );
+}
+
+#[test]
+fn funding_links_are_read_from_the_manifest() {
+    let (app, anon, user, token) = TestApp::full().with_token();
+    let other = app.db_new_user("other");
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_funded", other.as_model().id).expect_build(conn);
+        let link = NewProjectLink {
+            crate_id: krate.id,
+            kind: ProjectLinkKind::Funding,
+            url: "https://example.com/foo_funded".into(),
+            label: None,
+            from_manifest: false,
+        };
+        ProjectLink::replace_all(conn, krate.id, false, &[link]).unwrap();
+    });
+
+    let manifest = br#"
+        [package]
+        name = "foo_app"
+        version = "1.0.0"
+
+        [package.metadata]
+        funding = "https://example.com/foo_app"
+    "#;
+    let files = [("foo_app-1.0.0/Cargo.toml", manifest as &[_])];
+    let crate_to_publish = PublishBuilder::new("foo_app")
+        .files(&files)
+        .dependency(DependencyBuilder::new("foo_funded"));
+    token.enqueue_publish(crate_to_publish).good();
+
+    let json = anon.show_crate("foo_app");
+    let links = json.krate.project_links.unwrap();
+    assert_eq!(links.len(), 1);
+    assert_eq!(links[0].kind, "funding");
+    assert_eq!(links[0].url, "https://example.com/foo_app");
+    assert!(links[0].from_manifest);
+
+    let json: Funding = user.get("/api/v1/me/funding").good();
+    assert_eq!(json.crates.len(), 1);
+    assert_eq!(json.crates[0].krate, "foo_funded");
+    assert_eq!(
+        json.crates[0].funding[0].url,
+        "https://example.com/foo_funded"
+    );
+    assert_eq!(json.crates[0].dependents, vec!["foo_app"]);
+}
@@ -1,11 +1,14 @@
 use crate::builders::{CrateBuilder, DependencyBuilder, PublishBuilder};
 use crate::new_category;
 use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
 use cargo_registry::controllers::krate::publish::{
     missing_metadata_error_message, MISSING_RIGHTS_ERROR_MESSAGE, WILDCARD_ERROR_MESSAGE,
 };
 use cargo_registry::models::krate::MAX_NAME_LENGTH;
-use cargo_registry::schema::{api_tokens, emails, versions_published_by};
+use cargo_registry::schema::{
+    api_tokens, emails, pending_uploads, versions, versions_published_by,
+};
 use cargo_registry::views::GoodCrate;
 use diesel::{delete, update, ExpressionMethods, QueryDsl, RunQueryDsl};
 use flate2::write::GzEncoder;
@@ -59,7 +62,7 @@ fn new_wrong_token() {
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "must be logged in to perform that action" }] })
+        json!({ "errors": [{ "detail": "must be logged in to perform that action", "code": "unauthenticated" }] })
     );
 
     // Try to publish with the wrong token (by changing the token in the database)
@@ -75,7 +78,7 @@ fn new_wrong_token() {
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "must be logged in to perform that action" }] })
+        json!({ "errors": [{ "detail": "must be logged in to perform that action", "code": "unauthenticated" }] })
     );
 }
 
@@ -122,6 +125,75 @@ fn new_krate() {
     assert_eq!(json.krate.max_version, "1.0.0");
 }
 
+#[test]
+fn new_krate_has_checksums_of_all_algorithms() {
+    let (_, anon, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_checksums").version("1.0.0");
+    user.enqueue_publish(crate_to_publish).good();
+
+    let version = anon.show_version("foo_checksums", "1.0.0").version;
+    let algorithms = version.checksums.keys().collect::<Vec<_>>();
+    assert_eq!(algorithms, vec!["sha256", "sha512"]);
+    assert_eq!(
+        version.checksums["sha256"],
+        "acb5604b126ac894c1eb11c4575bf2072fea61232a888e453770c79d7ed56419"
+    );
+    assert_eq!(version.checksums["sha512"].len(), 128);
+}
+
+#[test]
+fn new_krate_uploads_tarball_before_index_update() {
+    let (app, _, user) = TestApp::full().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_new").version("1.0.0");
+    user.enqueue_publish(crate_to_publish).good();
+
+    // The tarball was uploaded by the request, which enqueued the index update
+    let pending: i64 = app.db(|conn| pending_uploads::table.count().get_result(conn).unwrap());
+    assert_eq!(pending, 0);
+    app.run_pending_background_jobs();
+
+    // Adding the same version again, like a retried job would, is a no-op
+    let krate = app.crates_from_index_head("fo/o_/foo_new").remove(0);
+    app.db(|conn| Job::AddCrate { krate }.enqueue(conn).unwrap());
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("fo/o_/foo_new");
+    assert_eq!(crates.len(), 1);
+    assert_eq!(crates[0].vers, "1.0.0");
+    // Without `INDEX_EXTENSIONS`, the entry is what cargo expects
+    assert_none!(&crates[0].ext);
+
+    // The checksum is recorded for the storage verification
+    let checksum: Option<String> = app.db(|conn| {
+        versions::table
+            .select(versions::checksum)
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(checksum.as_deref(), Some(&*crates[0].cksum));
+}
+
+#[test]
+fn new_krate_has_index_extensions_if_enabled() {
+    let (app, _, user) = TestApp::full().with_index_extensions().with_user();
+
+    let crate_to_publish = PublishBuilder::new("foo_ext").version("1.0.0");
+    user.enqueue_publish(crate_to_publish).good();
+    app.run_pending_background_jobs();
+
+    let krate = app.crates_from_index_head("fo/o_/foo_ext").remove(0);
+    let ext = krate.ext.unwrap();
+    assert_eq!(ext.publisher.as_deref(), Some("foo"));
+    assert_eq!(
+        ext.checksums.keys().collect::<Vec<_>>(),
+        ["sha256", "sha512"]
+    );
+    assert_eq!(ext.checksums["sha256"], krate.cksum);
+    assert_none!(ext.yank_reason);
+}
+
 #[test]
 fn new_krate_with_token() {
     let (_, _, _, token) = TestApp::full().with_token();
@@ -232,7 +304,7 @@ fn new_krate_with_broken_dependency_requirement() {
 
     assert_eq!(
         response,
-        json!({"errors": [{"detail": "invalid upload request: invalid value: string \"broken\", expected a valid version req at line 1 column 136"}]})
+        json!({"errors": [{"detail": "invalid upload request: invalid value: string \"broken\", expected a valid version req at line 1 column 136", "code": "invalid_request"}]})
     );
 }
 
@@ -255,7 +327,7 @@ fn reject_new_krate_with_non_exact_dependency() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "no known crate named `foo_dep`" }] })
+        json!({ "errors": [{ "detail": "no known crate named `foo_dep`", "code": "unknown_dependency" }] })
     );
 }
 
@@ -284,7 +356,7 @@ fn reject_new_crate_with_alternative_registry_dependency() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "Dependency `dep` is hosted on another registry. Cross-registry dependencies are not permitted on crates.io." }] })
+        json!({ "errors": [{ "detail": "Dependency `dep` is hosted on another registry. Cross-registry dependencies are not permitted on crates.io.", "code": "invalid_request" }] })
     );
 }
 
@@ -307,7 +379,7 @@ fn new_krate_with_wildcard_dependency() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": WILDCARD_ERROR_MESSAGE }] })
+        json!({ "errors": [{ "detail": WILDCARD_ERROR_MESSAGE, "code": "invalid_request" }] })
     );
 }
 
@@ -346,7 +418,7 @@ fn new_krate_wrong_user() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": MISSING_RIGHTS_ERROR_MESSAGE }] })
+        json!({ "errors": [{ "detail": MISSING_RIGHTS_ERROR_MESSAGE, "code": "crate_name_taken" }] })
     );
 }
 
@@ -361,7 +433,7 @@ fn new_krate_too_big() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "uploaded tarball is malformed or too large when decompressed" }] })
+        json!({ "errors": [{ "detail": "uploaded tarball is malformed or too large when decompressed", "code": "invalid_request" }] })
     );
 }
 
@@ -394,7 +466,7 @@ fn new_krate_wrong_files() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid tarball uploaded" }] })
+        json!({ "errors": [{ "detail": "invalid tarball uploaded", "code": "invalid_request" }] })
     );
 }
 
@@ -413,7 +485,7 @@ fn new_krate_gzip_bomb() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "uploaded tarball is malformed or too large when decompressed" }] })
+        json!({ "errors": [{ "detail": "uploaded tarball is malformed or too large when decompressed", "code": "invalid_request" }] })
     );
 }
 
@@ -433,7 +505,7 @@ fn new_krate_duplicate_version() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "crate version `1.0.0` is already uploaded" }] })
+        json!({ "errors": [{ "detail": "crate version `1.0.0` is already uploaded", "code": "version_exists" }] })
     );
 }
 
@@ -452,7 +524,7 @@ fn new_crate_similar_name() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "crate was previously named `Foo_similar`" }] })
+        json!({ "errors": [{ "detail": "crate was previously named `Foo_similar`", "code": "crate_name_mismatch" }] })
     );
 }
 
@@ -471,7 +543,7 @@ fn new_crate_similar_name_hyphen() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "crate was previously named `foo_bar_hyphen`" }] })
+        json!({ "errors": [{ "detail": "crate was previously named `foo_bar_hyphen`", "code": "crate_name_mismatch" }] })
     );
 }
 
@@ -490,7 +562,7 @@ fn new_crate_similar_name_underscore() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "crate was previously named `foo-bar-underscore`" }] })
+        json!({ "errors": [{ "detail": "crate was previously named `foo-bar-underscore`", "code": "crate_name_mismatch" }] })
     );
 }
 
@@ -563,7 +635,7 @@ fn new_krate_dependency_missing() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "no known crate named `bar_missing`" }] })
+        json!({ "errors": [{ "detail": "no known crate named `bar_missing`", "code": "unknown_dependency" }] })
     );
 }
 
@@ -578,6 +650,30 @@ fn new_krate_with_readme() {
     assert_eq!(json.krate.max_version, "1.0.0");
 }
 
+#[test]
+fn new_krate_with_rust_version() {
+    let (_, anon, _, token) = TestApp::full().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_msrv").rust_version("1.56");
+    token.enqueue_publish(crate_to_publish).good();
+
+    let json = anon.show_version("foo_msrv", "1.0.0");
+    assert_eq!(json.version.rust_version.as_deref(), Some("1.56"));
+}
+
+#[test]
+fn invalid_rust_version() {
+    let (_, _, _, token) = TestApp::init().with_token();
+
+    let crate_to_publish = PublishBuilder::new("foo_msrv").rust_version("1.56-nightly");
+    let response = token.enqueue_publish(crate_to_publish);
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let json = response.json();
+    let detail = json["errors"][0]["detail"].as_str().unwrap();
+    assert!(detail.contains("a Rust version like"), "{:?}", detail);
+}
+
 #[test]
 fn new_krate_without_any_email_fails() {
     let (app, _, _, token) = TestApp::init().with_token();
@@ -592,7 +688,7 @@ fn new_krate_without_any_email_fails() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "A verified email address is required to publish crates to crates.io. Visit https://crates.io/me to set and verify your email address." }] })
+        json!({ "errors": [{ "detail": "A verified email address is required to publish crates to crates.io. Visit https://crates.io/me to set and verify your email address.", "code": "email_not_verified" }] })
     );
 }
 
@@ -613,7 +709,7 @@ fn new_krate_with_unverified_email_fails() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "A verified email address is required to publish crates to crates.io. Visit https://crates.io/me to set and verify your email address." }] })
+        json!({ "errors": [{ "detail": "A verified email address is required to publish crates to crates.io. Visit https://crates.io/me to set and verify your email address.", "code": "email_not_verified" }] })
     );
 }
 
@@ -703,7 +799,7 @@ fn bad_keywords() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid upload request: invalid length 29, expected a keyword with less than 20 characters at line 1 column 221" }] })
+        json!({ "errors": [{ "detail": "invalid upload request: invalid length 29, expected a keyword with less than 20 characters at line 1 column 221", "code": "invalid_request" }] })
     );
 
     let crate_to_publish = PublishBuilder::new("foo_bad_key").keyword("?@?%");
@@ -711,7 +807,7 @@ fn bad_keywords() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid upload request: invalid value: string \"?@?%\", expected a valid keyword specifier at line 1 column 196" }] })
+        json!({ "errors": [{ "detail": "invalid upload request: invalid value: string \"?@?%\", expected a valid keyword specifier at line 1 column 196", "code": "invalid_request" }] })
     );
 
     let crate_to_publish = PublishBuilder::new("foo_bad_key").keyword("áccênts");
@@ -719,7 +815,7 @@ fn bad_keywords() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid upload request: invalid value: string \"áccênts\", expected a valid keyword specifier at line 1 column 201" }] })
+        json!({ "errors": [{ "detail": "invalid upload request: invalid value: string \"áccênts\", expected a valid keyword specifier at line 1 column 201", "code": "invalid_request" }] })
     );
 }
 
@@ -831,7 +927,7 @@ fn author_license_and_description_required() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description", "license", "authors"]) }] })
+        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description", "license", "authors"]), "code": "missing_metadata" }] })
     );
 
     let crate_to_publish = PublishBuilder::new("foo_metadata")
@@ -844,7 +940,7 @@ fn author_license_and_description_required() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description", "authors"]) }] })
+        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description", "authors"]), "code": "missing_metadata" }] })
     );
 
     let crate_to_publish = PublishBuilder::new("foo_metadata")
@@ -857,7 +953,7 @@ fn author_license_and_description_required() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description"]) }] })
+        json!({ "errors": [{ "detail": missing_metadata_error_message(&["description"]), "code": "missing_metadata" }] })
     );
 }
 
@@ -884,7 +980,7 @@ fn new_krate_tarball_with_hard_links() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid tarball uploaded" }] })
+        json!({ "errors": [{ "detail": "invalid tarball uploaded", "code": "invalid_request" }] })
     );
 }
 
@@ -931,3 +1027,48 @@ fn publish_rate_limit_doesnt_affect_existing_crates() {
     token.enqueue_publish(new_version).good();
     app.run_pending_background_jobs();
 }
+
+#[test]
+fn publishing_notifies_the_other_owners() {
+    use cargo_registry::schema::{background_jobs, crate_owners, crates};
+
+    let (app, _, user) = TestApp::full().with_user();
+    let co_owner = app.db_new_user("co_owner");
+    let notify_jobs = || {
+        app.db(|conn| {
+            background_jobs::table
+                .filter(background_jobs::job_type.eq("notify_new_version"))
+                .count()
+                .get_result::<i64>(conn)
+                .unwrap()
+        })
+    };
+
+    // Nobody else to notify yet
+    let crate_to_publish = PublishBuilder::new("foo_shared").version("1.0.0");
+    user.enqueue_publish(crate_to_publish).good();
+    assert_eq!(notify_jobs(), 0);
+    app.run_pending_background_jobs();
+
+    app.db(|conn| {
+        let crate_id: i32 = crates::table
+            .filter(crates::name.eq("foo_shared"))
+            .select(crates::id)
+            .first(conn)
+            .unwrap();
+        diesel::insert_into(crate_owners::table)
+            .values((
+                crate_owners::crate_id.eq(crate_id),
+                crate_owners::owner_id.eq(co_owner.as_model().id),
+                crate_owners::created_by.eq(user.as_model().id),
+                crate_owners::owner_kind.eq(0),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_shared").version("1.1.0");
+    user.enqueue_publish(crate_to_publish).good();
+    assert_eq!(notify_jobs(), 1);
+    app.run_pending_background_jobs();
+}
@@ -2,6 +2,7 @@ use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::util::{RequestHelper, TestApp};
 use crate::CrateMeta;
 use cargo_registry::views::{EncodableDependency, EncodableVersion};
+use http::StatusCode;
 
 #[derive(Deserialize)]
 struct RevDeps {
@@ -215,3 +216,119 @@ fn reverse_dependencies_query_supports_u64_version_number_parts() {
     assert_eq!(deps.versions[0].krate, "c2");
     assert_eq!(deps.versions[0].num, large_but_valid_version_number);
 }
+
+#[test]
+fn reverse_dependencies_are_paginated_with_seek() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        for name in &["c2", "c3", "c4"] {
+            CrateBuilder::new(name, user.id)
+                .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+                .expect_build(conn);
+        }
+    });
+
+    let url = "/api/v1/crates/c1/reverse_dependencies";
+    let deps: RevDeps = anon.get_with_query(url, "per_page=2").good();
+    assert_eq!(deps.meta.total, 3);
+    let mut krates: Vec<_> = deps.versions.iter().map(|v| v.krate.as_str()).collect();
+    krates.sort_unstable();
+    assert_eq!(krates, ["c2", "c3"]);
+
+    let next_page = assert_some!(deps.meta.next_page);
+    assert!(next_page.contains("seek="));
+    let deps: RevDeps = anon.get_with_query(url, &next_page[1..]).good();
+    assert_eq!(deps.meta.total, 3);
+    assert_eq!(deps.versions.len(), 1);
+    assert_eq!(deps.versions[0].krate, "c4");
+    assert_none!(deps.meta.next_page);
+
+    // Numeric pages keep working
+    let deps: RevDeps = anon.get_with_query(url, "per_page=2&page=2").good();
+    assert_eq!(deps.versions.len(), 1);
+    assert_eq!(deps.versions[0].krate, "c4");
+
+    let response = anon.get_with_query::<()>(url, "seek=garbage");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn dependents_are_counted_ahead_of_time() {
+    use cargo_registry::models::{CrateDependencies, CrateDependents, Version};
+    use cargo_registry::schema::versions;
+    use diesel::prelude::*;
+
+    fn yank(conn: &PgConnection, version: &Version) {
+        let dependencies = CrateDependencies::load(conn, version.crate_id).unwrap();
+        diesel::update(versions::table.find(version.id))
+            .set(versions::yanked.eq(true))
+            .execute(conn)
+            .unwrap();
+        CrateDependents::update_dependencies_of(conn, &dependencies).unwrap();
+    }
+
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let (c2, c3) = app.db(|conn| {
+        let c1 = CrateBuilder::new("c1", user.id).expect_build(conn);
+        // Only an older version depends on c1
+        let c2 = CrateBuilder::new("c2", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+        let c3 = CrateBuilder::new("c3", user.id)
+            .version(VersionBuilder::new("1.0.0").dependency(&c1, None))
+            .expect_build(conn);
+        (c2, c3)
+    });
+
+    // Crates that were never counted have no counts yet
+    let json = anon.show_crate("c1");
+    assert_none!(json.krate.direct_dependents);
+
+    app.db(|conn| CrateDependents::reconcile(conn).unwrap());
+    let json = anon.show_crate("c1");
+    assert_eq!(json.krate.direct_dependents, Some(2));
+    assert_eq!(json.krate.total_dependents, Some(2));
+    let json = anon.show_crate("c2");
+    assert_eq!(json.krate.direct_dependents, Some(0));
+
+    // Publishing a version without the dependency only changes the direct count
+    let c2_v2 = app.db(|conn| {
+        let dependencies = CrateDependencies::load(conn, c2.id).unwrap();
+        let version = VersionBuilder::new("2.0.0").expect_build(c2.id, user.id, conn);
+        CrateDependents::update_dependencies_of(conn, &dependencies).unwrap();
+        version
+    });
+    let json = anon.show_crate("c1");
+    assert_eq!(json.krate.direct_dependents, Some(1));
+    assert_eq!(json.krate.total_dependents, Some(2));
+
+    // Yanking the newest version makes c2 depend on c1 again
+    app.db(|conn| yank(conn, &c2_v2));
+    let json = anon.show_crate("c1");
+    assert_eq!(json.krate.direct_dependents, Some(2));
+    assert_eq!(json.krate.total_dependents, Some(2));
+
+    // Yanking the only version removes c3 from both counts
+    app.db(|conn| {
+        let version = Version::belonging_to(&c3).first(conn).unwrap();
+        yank(conn, &version);
+    });
+    let json = anon.show_crate("c1");
+    assert_eq!(json.krate.direct_dependents, Some(1));
+    assert_eq!(json.krate.total_dependents, Some(1));
+
+    // The updated counts agree with a full recount
+    app.db(|conn| CrateDependents::reconcile(conn).unwrap());
+    let json = anon.show_crate("c1");
+    assert_eq!(json.krate.direct_dependents, Some(1));
+    assert_eq!(json.krate.total_dependents, Some(1));
+
+    let json = anon.search("sort=dependents");
+    assert_eq!(json.crates[0].name, "c1");
+    assert_eq!(json.crates[0].direct_dependents, Some(1));
+}
@@ -702,7 +702,7 @@ fn pagination_parameters_only_accept_integers() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid digit found in string" }] })
+        json!({ "errors": [{ "detail": "invalid digit found in string", "code": "invalid_request" }] })
     );
 
     let response =
@@ -710,6 +710,6 @@ fn pagination_parameters_only_accept_integers() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid digit found in string" }] })
+        json!({ "errors": [{ "detail": "invalid digit found in string", "code": "invalid_request" }] })
     );
 }
@@ -0,0 +1,78 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::Version;
+use cargo_registry::schema::versions;
+use cargo_registry::views::{EncodableCrateStorage, EncodableVersionStorage};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct CrateStorage {
+    storage: EncodableCrateStorage,
+    versions: Vec<EncodableVersionStorage>,
+}
+
+#[derive(Deserialize)]
+struct StorageSummary {
+    crates: Vec<EncodableCrateStorage>,
+    total_bytes: i64,
+    quota_bytes: Option<i64>,
+}
+
+#[test]
+fn owners_see_the_storage_used_by_their_crates() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_storage", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").size(100))
+            .version(VersionBuilder::new("1.1.0").size(200))
+            .expect_build(conn);
+        CrateBuilder::new("foo_storage_small", user.as_model().id)
+            .version(VersionBuilder::new("0.1.0").size(10))
+            .expect_build(conn);
+
+        let version_id = versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .filter(versions::num.eq("1.1.0"))
+            .select(versions::id)
+            .first(conn)
+            .unwrap();
+        Version::record_readme_rendering(version_id, conn).unwrap();
+        Version::record_readme_sizes(version_id, Some((30, 20)), conn).unwrap();
+    });
+
+    let url = "/api/v1/crates/foo_storage/storage";
+    let json: CrateStorage = user.get(url).good();
+    assert_eq!(json.storage.versions, 2);
+    assert_eq!(json.storage.crate_file_bytes, 300);
+    assert_eq!(json.storage.total_bytes, 350);
+    assert_eq!(json.versions[0].num, "1.0.0");
+    assert_none!(json.versions[0].readme_bytes);
+    assert_eq!(json.versions[1].readme_bytes, Some(30));
+    assert_eq!(json.versions[1].readme_source_bytes, Some(20));
+
+    anon.get::<()>(url).assert_forbidden();
+    let response = other.get::<()>(url);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: StorageSummary = user.get("/api/v1/me/storage").good();
+    assert_eq!(json.total_bytes, 360);
+    assert_eq!(json.crates.len(), 2);
+    assert_eq!(json.crates[0].krate, "foo_storage");
+    assert_eq!(json.crates[1].total_bytes, 10);
+    assert_none!(json.quota_bytes);
+}
+
+#[test]
+fn the_storage_quota_is_shown_if_configured() {
+    let (_, _, user) = TestApp::init()
+        .with_config(|config| config.quotas.storage_bytes = Some(1024))
+        .with_user();
+
+    let json: StorageSummary = user.get("/api/v1/me/storage").good();
+    assert!(json.crates.is_empty());
+    assert_eq!(json.total_bytes, 0);
+    assert_eq!(json.quota_bytes, Some(1024));
+}
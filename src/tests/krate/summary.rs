@@ -1,9 +1,11 @@
 use crate::builders::{CrateBuilder, VersionBuilder};
 use crate::new_category;
 use crate::util::{RequestHelper, TestApp};
-use cargo_registry::schema::metadata;
+use cargo_registry::schema::{crates, materialized_responses, metadata};
+use cargo_registry::tasks;
 use cargo_registry::views::{EncodableCategory, EncodableCrate, EncodableKeyword};
 use chrono::Utc;
+use conduit::header;
 use diesel::{update, ExpressionMethods, RunQueryDsl};
 
 #[derive(Deserialize)]
@@ -107,3 +109,47 @@ fn summary_new_crates() {
 
     assert_eq!(json.new_crates.len(), 5);
 }
+
+#[test]
+fn summary_is_served_from_the_refreshed_summary() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("refreshed", user.id).expect_build(conn);
+        tasks::perform_refresh_summary(conn).unwrap();
+        CrateBuilder::new("not_refreshed", user.id).expect_build(conn);
+    });
+
+    let response = anon.get::<SummaryResponse>("/api/v1/summary");
+    assert_eq!(
+        response.header(header::CACHE_CONTROL),
+        Some("public, max-age=60")
+    );
+    let json = response.good();
+    assert_eq!(json.num_crates, 1);
+    assert_eq!(json.new_crates[0].name, "refreshed");
+
+    // An outdated summary is computed on request
+    app.db(|conn| {
+        update(materialized_responses::table)
+            .set(
+                materialized_responses::refreshed_at
+                    .eq(Utc::now().naive_utc() - chrono::Duration::hours(1)),
+            )
+            .execute(conn)
+            .unwrap();
+    });
+    let json: SummaryResponse = anon.get("/api/v1/summary").good();
+    assert_eq!(json.num_crates, 2);
+
+    app.db(|conn| {
+        tasks::perform_refresh_summary(conn).unwrap();
+        update(crates::table)
+            .set(crates::description.eq("changed"))
+            .execute(conn)
+            .unwrap();
+    });
+    let json: SummaryResponse = anon.get("/api/v1/summary").good();
+    assert_eq!(json.num_crates, 2);
+    assert_none!(&json.new_crates[0].description);
+}
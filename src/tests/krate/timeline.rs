@@ -0,0 +1,115 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{insert_version_owner_action, NewAdvisory, VersionAction};
+use cargo_registry::schema::versions;
+use cargo_registry::tasks::import_advisories;
+use cargo_registry::views::EncodableTimelineEntry;
+
+use chrono::Utc;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct TimelineResponse {
+    timeline: Vec<EncodableTimelineEntry>,
+    meta: TimelineMeta,
+}
+
+#[derive(Deserialize)]
+struct TimelineMeta {
+    total: i64,
+    next_page: Option<String>,
+}
+
+fn advisory() -> NewAdvisory {
+    NewAdvisory {
+        id: "RUSTSEC-2021-0002".into(),
+        crate_name: "foo_timeline".into(),
+        title: "Data race in `Foo::baz`".into(),
+        url: None,
+        date: Utc::now().naive_utc().date(),
+        patched: vec![">= 1.2.1".into()],
+        unaffected: vec!["< 1.0.0".into()],
+        informational: None,
+        withdrawn: false,
+        description: None,
+        severity: None,
+        affected: vec![],
+        published_by: None,
+    }
+}
+
+fn set_up(app: &TestApp, user_id: i32) {
+    app.db(|conn| {
+        CrateBuilder::new("foo_timeline", user_id)
+            .version(VersionBuilder::new("0.9.0").yanked(true))
+            .version("1.0.0")
+            .version("1.2.1")
+            .expect_build(conn);
+
+        let version_ids: Vec<i32> = versions::table
+            .select(versions::id)
+            .order(versions::id)
+            .load(conn)
+            .unwrap();
+        insert_version_owner_action(conn, version_ids[0], user_id, None, VersionAction::Yank)
+            .unwrap();
+        diesel::update(versions::table.filter(versions::id.ne(version_ids[0])))
+            .set(versions::rust_version.eq("1.56"))
+            .execute(conn)
+            .unwrap();
+
+        assert_ok!(import_advisories(conn, &[advisory()]));
+    });
+}
+
+#[test]
+fn timeline_annotates_yanks_advisories_and_rust_version_changes() {
+    let (app, anon, user) = TestApp::init().with_user();
+    set_up(&app, user.as_model().id);
+
+    let json: TimelineResponse = anon.get("/api/v1/crates/foo_timeline/timeline").good();
+    assert_eq!(json.meta.total, 3);
+    let nums: Vec<_> = json.timeline.iter().map(|entry| &*entry.num).collect();
+    assert_eq!(nums, ["1.2.1", "1.0.0", "0.9.0"]);
+
+    let newest = &json.timeline[0];
+    assert!(!newest.rust_version_changed);
+    assert!(newest.advisories.is_empty());
+
+    let affected = &json.timeline[1];
+    assert_eq!(affected.rust_version.as_deref(), Some("1.56"));
+    assert!(affected.rust_version_changed);
+    assert_eq!(affected.advisories.len(), 1);
+    assert_eq!(affected.advisories[0].id, "RUSTSEC-2021-0002");
+
+    let yanked = &json.timeline[2];
+    assert!(yanked.yanked);
+    let yank = yanked.yank.as_ref().unwrap();
+    assert_eq!(yank.reason, "owner");
+    assert_some!(yank.yanked_at);
+    assert_eq!(
+        yank.yanked_by.as_ref().unwrap().login,
+        user.as_model().gh_login
+    );
+    assert!(!yanked.rust_version_changed);
+}
+
+#[test]
+fn timeline_pages_compare_with_the_release_before_the_page() {
+    let (app, anon, user) = TestApp::init().with_user();
+    set_up(&app, user.as_model().id);
+
+    let json: TimelineResponse = anon
+        .get_with_query("/api/v1/crates/foo_timeline/timeline", "per_page=2")
+        .good();
+    assert_eq!(json.timeline.len(), 2);
+    assert!(json.timeline[1].rust_version_changed);
+
+    let next_page = json.meta.next_page.unwrap();
+    let json: TimelineResponse = anon
+        .get_with_query("/api/v1/crates/foo_timeline/timeline", &next_page[1..])
+        .good();
+    let nums: Vec<_> = json.timeline.iter().map(|entry| &*entry.num).collect();
+    assert_eq!(nums, ["0.9.0"]);
+    assert_none!(json.meta.next_page);
+}
@@ -0,0 +1,107 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{Crate, CrateTrendingScore};
+use cargo_registry::schema::{version_downloads, versions};
+use cargo_registry::views::{EncodableCrate, EncodableTrendingScore};
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct TrendingResponse {
+    crates: Vec<EncodableCrate>,
+    scores: Vec<EncodableTrendingScore>,
+}
+
+#[derive(Deserialize)]
+struct SummaryResponse {
+    trending: Vec<EncodableCrate>,
+}
+
+/// Records the downloads of a crate yesterday and two weeks ago
+fn add_downloads(conn: &PgConnection, krate: &Crate, recent: i32, baseline: i32) {
+    let version_id: i32 = versions::table
+        .filter(versions::crate_id.eq(krate.id))
+        .select(versions::id)
+        .first(conn)
+        .unwrap();
+    let today = Utc::today().naive_utc();
+
+    diesel::insert_into(version_downloads::table)
+        .values(&vec![
+            (
+                version_downloads::version_id.eq(version_id),
+                version_downloads::downloads.eq(recent),
+                version_downloads::date.eq(today - Duration::days(1)),
+            ),
+            (
+                version_downloads::version_id.eq(version_id),
+                version_downloads::downloads.eq(baseline),
+                version_downloads::date.eq(today - Duration::days(14)),
+            ),
+        ])
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+fn crates_trend_with_growing_downloads_and_new_dependents() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let rising = CrateBuilder::new("rising", user.id).expect_build(conn);
+        add_downloads(conn, &rising, 700, 280);
+        let steady = CrateBuilder::new("steady", user.id).expect_build(conn);
+        add_downloads(conn, &steady, 700, 2800);
+        let small = CrateBuilder::new("small", user.id).expect_build(conn);
+        add_downloads(conn, &small, 50, 0);
+
+        let depended = CrateBuilder::new("depended", user.id).expect_build(conn);
+        add_downloads(conn, &depended, 100, 400);
+        for name in &["dependent_a", "dependent_b"] {
+            CrateBuilder::new(name, user.id)
+                .version(VersionBuilder::new("1.0.0").dependency(&depended, None))
+                .expect_build(conn);
+        }
+
+        assert_eq!(CrateTrendingScore::recompute(conn).unwrap(), 2);
+    });
+
+    let json = anon.search("sort=trending");
+    assert_eq!(json.crates[0].name, "rising");
+    assert_eq!(json.crates[1].name, "depended");
+
+    let json: TrendingResponse = anon.get("/api/v1/trending").good();
+    let names: Vec<_> = json.crates.iter().map(|c| &*c.name).collect();
+    assert_eq!(names, ["rising", "depended"]);
+    assert_eq!(json.scores[0].krate, "rising");
+    assert_eq!(json.scores[0].recent_downloads, 700);
+    assert_eq!(json.scores[0].baseline_downloads, 280);
+    assert_eq!(json.scores[1].new_dependents, 2);
+
+    let json: SummaryResponse = anon.get("/api/v1/summary").good();
+    let names: Vec<_> = json.trending.iter().map(|c| &*c.name).collect();
+    assert_eq!(names, ["rising", "depended"]);
+}
+
+#[test]
+fn recomputing_drops_crates_that_stopped_trending() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_trending", user.id).expect_build(conn);
+        add_downloads(conn, &krate, 700, 0);
+        assert_eq!(CrateTrendingScore::recompute(conn).unwrap(), 1);
+
+        diesel::delete(version_downloads::table)
+            .execute(conn)
+            .unwrap();
+        assert_eq!(CrateTrendingScore::recompute(conn).unwrap(), 0);
+    });
+
+    let json: TrendingResponse = anon.get("/api/v1/trending").good();
+    assert!(json.crates.is_empty());
+    assert!(json.scores.is_empty());
+}
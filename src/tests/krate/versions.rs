@@ -1,5 +1,6 @@
 use crate::builders::CrateBuilder;
 use crate::util::{RequestHelper, TestApp};
+use crate::CrateMeta;
 use cargo_registry::schema::versions;
 use cargo_registry::views::EncodableVersion;
 use diesel::{prelude::*, update};
@@ -9,6 +10,12 @@ struct VersionsList {
     versions: Vec<EncodableVersion>,
 }
 
+#[derive(Deserialize)]
+struct VersionsPage {
+    versions: Vec<EncodableVersion>,
+    meta: CrateMeta,
+}
+
 #[test]
 fn versions() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -41,3 +48,28 @@ fn versions() {
         user.gh_login
     );
 }
+
+#[test]
+fn versions_are_paginated_with_seek() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_versions", user.id)
+            .version("0.1.0")
+            .version("0.2.0")
+            .version("0.3.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_versions/versions";
+    let json: VersionsPage = anon.get_with_query(url, "per_page=2").good();
+    assert_eq!(json.meta.total, 3);
+    let nums: Vec<_> = json.versions.iter().map(|v| v.num.as_str()).collect();
+    assert_eq!(nums, ["0.3.0", "0.2.0"]);
+
+    let next_page = assert_some!(json.meta.next_page);
+    let json: VersionsPage = anon.get_with_query(url, &next_page[1..]).good();
+    let nums: Vec<_> = json.versions.iter().map(|v| v.num.as_str()).collect();
+    assert_eq!(nums, ["0.1.0"]);
+    assert_none!(json.meta.next_page);
+}
@@ -113,7 +113,7 @@ fn yank_by_a_non_owner_fails() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "must already be an owner to yank or unyank" }] })
+        json!({ "errors": [{ "detail": "must already be an owner to yank or unyank", "code": "not_owner" }] })
     );
 }
 
@@ -0,0 +1,147 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::license::InvalidLicensePolicy;
+use cargo_registry::schema::version_licenses;
+use cargo_registry::views::{EncodableDependencyLicense, EncodableLicenseUsage};
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct DependencyTreeResponse {
+    licenses: Vec<EncodableLicenseUsage>,
+    unknown: i64,
+    crates: Vec<EncodableDependencyLicense>,
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct StatsResponse {
+    licenses: Vec<EncodableLicenseUsage>,
+    expressions: Vec<EncodableLicenseUsage>,
+    unknown: i64,
+}
+
+fn usage(license: &str, crates: i64) -> EncodableLicenseUsage {
+    EncodableLicenseUsage {
+        license: license.into(),
+        crates,
+    }
+}
+
+#[test]
+fn published_licenses_are_normalized() {
+    let (app, _, _, token) = TestApp::init().with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_licensed").license("MIT/Apache-2.0"))
+        .good();
+
+    let (expression, licenses): (String, Vec<String>) = app.db(|conn| {
+        version_licenses::table
+            .select((version_licenses::expression, version_licenses::licenses))
+            .first(conn)
+            .unwrap()
+    });
+    assert_eq!(expression, "MIT OR Apache-2.0");
+    assert_eq!(licenses, vec!["MIT", "Apache-2.0"]);
+}
+
+#[test]
+fn invalid_licenses_are_rejected_unless_the_policy_warns() {
+    let (_, _, _, token) = TestApp::init().with_token();
+    let response =
+        token.enqueue_publish(PublishBuilder::new("foo_invalid").license("MIT OR Nope-1.0"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let detail = response.json()["errors"][0]["detail"].to_string();
+    assert!(detail.contains("spdx.org"), "{}", detail);
+
+    let (app, _, _, token) = TestApp::init()
+        .with_config(|config| config.invalid_license_policy = InvalidLicensePolicy::Warn)
+        .with_token();
+    let json = token
+        .enqueue_publish(PublishBuilder::new("foo_invalid").license("MIT OR Nope-1.0"))
+        .good();
+    assert_eq!(json.warnings.other.len(), 1);
+    assert!(json.warnings.other[0].contains("MIT OR Nope-1.0"));
+
+    let normalized: i64 = app.db(|conn| version_licenses::table.count().get_result(conn).unwrap());
+    assert_eq!(normalized, 0);
+}
+
+#[test]
+fn dependency_trees_count_the_licenses_of_resolved_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let leaf = CrateBuilder::new("leaf", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(
+                VersionBuilder::new("2.0.0")
+                    .license(Some("Zlib"))
+                    .yanked(true),
+            )
+            .expect_build(conn);
+        let unlicensed = CrateBuilder::new("unlicensed", user.id).expect_build(conn);
+        let middle = CrateBuilder::new("middle", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT OR Apache-2.0"))
+                    .dependency(&leaf, None),
+            )
+            .expect_build(conn);
+        CrateBuilder::new("root", user.id)
+            .version(
+                VersionBuilder::new("1.0.0")
+                    .license(Some("MIT"))
+                    .dependency(&middle, None)
+                    .dependency(&unlicensed, None),
+            )
+            .expect_build(conn);
+    });
+
+    let json: DependencyTreeResponse = anon.get("/api/v1/crates/root/1.0.0/licenses").good();
+    assert_eq!(json.licenses, vec![usage("MIT", 2), usage("Apache-2.0", 1)]);
+    assert_eq!(json.unknown, 1);
+    assert!(!json.truncated);
+
+    let crates: Vec<(&str, &str, u32)> = json
+        .crates
+        .iter()
+        .map(|krate| (krate.krate.as_str(), krate.version.as_str(), krate.depth))
+        .collect();
+    assert_eq!(
+        crates,
+        vec![
+            ("middle", "1.0.0", 1),
+            ("unlicensed", "0.99.0", 1),
+            ("leaf", "1.0.0", 2)
+        ]
+    );
+}
+
+#[test]
+fn registry_statistics_count_the_newest_version_of_every_crate() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_relicensed", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("MIT")))
+            .version(VersionBuilder::new("2.0.0").license(Some("MIT OR Apache-2.0")))
+            .expect_build(conn);
+        CrateBuilder::new("foo_apache", user.id)
+            .version(VersionBuilder::new("1.0.0").license(Some("Apache-2.0")))
+            .expect_build(conn);
+        CrateBuilder::new("foo_unlicensed", user.id).expect_build(conn);
+    });
+
+    let json: StatsResponse = anon.get("/api/v1/licenses").good();
+    assert_eq!(json.licenses, vec![usage("Apache-2.0", 2), usage("MIT", 1)]);
+    assert_eq!(
+        json.expressions,
+        vec![usage("Apache-2.0", 1), usage("MIT OR Apache-2.0", 1)]
+    );
+    assert_eq!(json.unknown, 1);
+}
@@ -0,0 +1,50 @@
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::EncodableLoginAnomaly;
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct LoginAnomalies {
+    login_anomalies: Vec<EncodableLoginAnomaly>,
+}
+
+#[test]
+fn too_many_login_attempts_are_rejected() {
+    let (_, anon) = TestApp::init()
+        .with_config(|config| config.login_rate_limit.max_attempts = 3)
+        .empty();
+
+    for _ in 0..3 {
+        let response = anon.get::<()>("/api/private/session/begin");
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let response = anon.get::<()>("/api/private/session/authorize");
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = anon.get::<()>("/api/private/session/begin");
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[test]
+fn bursts_of_failed_exchanges_are_flagged() {
+    let (app, anon) = TestApp::init()
+        .with_config(|config| config.login_rate_limit.failure_threshold = 3)
+        .empty();
+    let admin = app.db_new_admin_user("admin");
+
+    for _ in 0..2 {
+        let response = anon.get::<()>("/api/private/session/authorize?code=a&state=b");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let json: LoginAnomalies = admin.get("/api/v1/admin/login_anomalies").good();
+    assert!(json.login_anomalies.is_empty());
+
+    // Later failures within the window don't add more anomalies
+    for _ in 0..3 {
+        let response = anon.get::<()>("/api/private/session/authorize?code=a&state=b");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+    let json: LoginAnomalies = admin.get("/api/v1/admin/login_anomalies").good();
+    assert_eq!(json.login_anomalies.len(), 1);
+    assert_eq!(json.login_anomalies[0].kind, "failed_exchanges");
+    assert_eq!(json.login_anomalies[0].attempts, 3);
+}
@@ -0,0 +1,49 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+
+use conduit::{header, StatusCode};
+use serde_json::Value;
+
+const URL: &str = "/api/v1/admin/maintenance_mode";
+
+#[test]
+fn non_admins_cannot_toggle_the_maintenance_mode() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.put::<()>(URL, b"").assert_forbidden();
+    user.put::<()>(URL, b"").assert_forbidden();
+    user.delete::<()>(URL).assert_forbidden();
+}
+
+#[test]
+fn maintenance_mode_rejects_changes() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_maintenance", user.as_model().id).expect_build(conn);
+    });
+
+    let json: Value = anon.get("/api/v1/site_metadata").good();
+    assert_eq!(json["read_only"], false);
+    assert_eq!(json["maintenance"], Value::Null);
+
+    admin
+        .put::<OkBool>(URL, br#"{ "message": "Upgrading the database" }"#)
+        .good();
+    let json: Value = anon.get("/api/v1/site_metadata").good();
+    assert_eq!(json["read_only"], true);
+    assert_eq!(json["maintenance"]["message"], "Upgrading the database");
+
+    let follow_url = "/api/v1/crates/foo_maintenance/follow";
+    let response = user.put::<()>(follow_url, b"");
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(header::RETRY_AFTER), Some("300"));
+    anon.get::<Value>("/api/v1/crates/foo_maintenance").good();
+
+    admin.delete::<OkBool>(URL).good();
+    admin.delete::<()>(URL).assert_not_found();
+    user.put::<OkBool>(follow_url, b"").good();
+    let json: Value = anon.get("/api/v1/site_metadata").good();
+    assert_eq!(json["read_only"], false);
+}
@@ -0,0 +1,82 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, Response, TestApp};
+
+use conduit::{header, Method, StatusCode};
+
+const TOKEN: &str = "metrics-token";
+
+fn scrape(anon: &impl RequestHelper, kind: &str, token: Option<&str>) -> Response<()> {
+    let path = format!("/api/private/metrics/{}", kind);
+    let mut request = anon.request_builder(Method::GET, &path);
+    if let Some(token) = token {
+        request.header(header::AUTHORIZATION, &format!("Bearer {}", token));
+    }
+    anon.run(request)
+}
+
+#[test]
+fn metrics_are_disabled_without_a_token() {
+    let (_app, anon) = TestApp::init().empty();
+    scrape(&anon, "instance", Some(TOKEN)).assert_not_found();
+}
+
+#[test]
+fn metrics_require_the_token() {
+    let (_app, anon) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_token = Some(TOKEN.into()))
+        .empty();
+
+    scrape(&anon, "instance", None).assert_forbidden();
+    scrape(&anon, "instance", Some("wrong")).assert_forbidden();
+    scrape(&anon, "unknown", Some(TOKEN)).assert_not_found();
+}
+
+#[test]
+fn instance_metrics_count_requests_and_downloads() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_token = Some(TOKEN.into()))
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("metrics_dl", user.as_model().id).expect_build(conn);
+    });
+    let response = anon.get::<()>("/api/v1/crates/metrics_dl/0.99.0/download");
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    let response = scrape(&anon, "instance", Some(TOKEN));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(response.into_bytes()).unwrap();
+    assert!(body.contains("cratesio_instance_downloads_total 1"));
+    assert!(body.contains(
+        "cratesio_instance_response_times_count{endpoint=\"/crates/:crate_id/:version/download\"} 1"
+    ));
+    assert!(body.contains("cratesio_instance_responses_by_status_code_total{status=\"302\"} 1"));
+}
+
+#[test]
+fn service_metrics_report_queued_jobs() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| config.metrics_authorization_token = Some(TOKEN.into()))
+        .with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("metrics_jobs", user.as_model().id).expect_build(conn);
+        cargo_registry::background_jobs::Job::UpdateDownloads {}
+            .enqueue(conn)
+            .unwrap();
+    });
+
+    let response = scrape(&anon, "service", Some(TOKEN));
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = String::from_utf8(response.into_bytes()).unwrap();
+    assert!(body.contains("cratesio_service_background_jobs{job_type=\"update_downloads\"} 1"));
+    assert!(body.contains("cratesio_service_crates_total 1"));
+
+    app.db(|conn| {
+        use cargo_registry::schema::background_jobs;
+        use diesel::prelude::*;
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
@@ -0,0 +1,73 @@
+use crate::builders::CrateBuilder;
+use crate::new_category;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::schema::crates;
+use cargo_registry::views::EncodableMonthlyStats;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct StatsResponse {
+    stats: Vec<EncodableMonthlyStats>,
+}
+
+#[test]
+fn categories_and_keywords_are_counted_per_month() {
+    let (app, anon, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        new_category("Category 1", "cat1", "Category 1 crates")
+            .create_or_update(conn)
+            .unwrap();
+        let old = CrateBuilder::new("old_crate", user.id)
+            .category("cat1")
+            .keyword("kw1")
+            .expect_build(conn);
+        diesel::update(&old)
+            .set(crates::created_at.eq(Utc::now().naive_utc() - Duration::days(62)))
+            .execute(conn)
+            .unwrap();
+        CrateBuilder::new("new_crate", user.id)
+            .category("cat1")
+            .keyword("kw1")
+            .recent_downloads(100)
+            .expect_build(conn);
+
+        Job::RefreshMonthlyStats { backfill: true }
+            .enqueue(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    let this_month = Utc::today().format("%Y-%m").to_string();
+    for url in &[
+        "/api/v1/categories/cat1/stats",
+        "/api/v1/keywords/kw1/stats",
+    ] {
+        let json: StatsResponse = anon.get(url).good();
+        assert!(json.stats.len() >= 3);
+
+        let first = &json.stats[0];
+        assert_eq!((first.crates_cnt, first.new_crates), (1, 1));
+        for month in &json.stats[1..json.stats.len() - 1] {
+            assert_eq!((month.crates_cnt, month.new_crates), (1, 0));
+        }
+        let current = json.stats.last().unwrap();
+        assert_eq!(current.month, this_month);
+        assert_eq!((current.crates_cnt, current.new_crates), (2, 1));
+        assert_eq!(current.downloads, 100);
+    }
+}
+
+#[test]
+fn stats_of_unknown_categories_and_keywords_are_not_found() {
+    let (_, anon) = TestApp::init().empty();
+
+    anon.get::<()>("/api/v1/categories/unknown/stats")
+        .assert_not_found();
+    anon.get::<()>("/api/v1/keywords/unknown/stats")
+        .assert_not_found();
+}
@@ -0,0 +1,83 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::views::{EncodableCrateOwnerInvitation, EncodableNotification};
+
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct NotificationList {
+    notifications: Vec<EncodableNotification>,
+}
+
+#[derive(Deserialize)]
+struct InvitationList {
+    crate_owner_invitations: Vec<EncodableCrateOwnerInvitation>,
+}
+
+#[derive(Deserialize)]
+struct MuteList {
+    crates: Vec<String>,
+    users: Vec<serde_json::Value>,
+}
+
+#[test]
+fn muted_crates_and_inviters_do_not_notify() {
+    let (app, _, user, token) = TestApp::init().with_token();
+    let invitee = app.db_new_user("invitee");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_muted", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_muted", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("baz_muted", user.as_model().id).expect_build(conn);
+    });
+
+    invitee
+        .put::<OkBool>("/api/v1/me/mutes/crates/foo_muted", b"")
+        .good();
+    token.add_named_owner("foo_muted", "invitee").good();
+    token.add_named_owner("bar_muted", "invitee").good();
+
+    let json: NotificationList = invitee.get("/api/v1/me/notifications").good();
+    assert_eq!(json.notifications.len(), 1);
+    assert_eq!(json.notifications[0].krate.as_deref(), Some("bar_muted"));
+
+    invitee
+        .put::<OkBool>("/api/v1/me/mutes/users/foo", b"")
+        .good();
+    token.add_named_owner("baz_muted", "invitee").good();
+
+    let json: NotificationList = invitee.get("/api/v1/me/notifications").good();
+    assert_eq!(json.notifications.len(), 1);
+
+    // Muted invitations can still be accepted
+    let json: InvitationList = invitee.get("/api/v1/me/crate_owner_invitations").good();
+    assert_eq!(json.crate_owner_invitations.len(), 3);
+
+    let json: MuteList = invitee.get("/api/v1/me/mutes").good();
+    assert_eq!(json.crates, vec!["foo_muted"]);
+    assert_eq!(json.users.len(), 1);
+    assert_eq!(json.users[0]["login"], "foo");
+
+    invitee
+        .delete::<OkBool>("/api/v1/me/mutes/crates/foo_muted")
+        .good();
+    invitee
+        .delete::<OkBool>("/api/v1/me/mutes/users/foo")
+        .good();
+    let json: MuteList = invitee.get("/api/v1/me/mutes").good();
+    assert!(json.crates.is_empty());
+    assert!(json.users.is_empty());
+}
+
+#[test]
+fn users_cannot_mute_themselves() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response = user.put::<()>("/api/v1/me/mutes/users/foo", b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "you can't mute yourself"
+    );
+}
@@ -0,0 +1,65 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::EncodableNotification;
+
+#[derive(Deserialize)]
+struct NotificationList {
+    notifications: Vec<EncodableNotification>,
+    meta: NotificationMeta,
+}
+
+#[derive(Deserialize)]
+struct NotificationMeta {
+    unread: i64,
+    more: bool,
+}
+
+#[derive(Deserialize)]
+struct MarkedRead {
+    marked: usize,
+    unread: i64,
+}
+
+const URL: &str = "/api/v1/me/notifications";
+
+#[test]
+fn owner_invitations_create_notifications() {
+    let (app, anon, user, token) = TestApp::init().with_token();
+    let invitee = app.db_new_user("invitee");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_notified", user.as_model().id).expect_build(conn);
+        CrateBuilder::new("bar_notified", user.as_model().id).expect_build(conn);
+    });
+    token.add_named_owner("foo_notified", "invitee").good();
+    token.add_named_owner("bar_notified", "invitee").good();
+
+    let json: NotificationList = invitee.get(URL).good();
+    assert_eq!(json.notifications.len(), 2);
+    assert_eq!(json.meta.unread, 2);
+    assert!(!json.meta.more);
+    let newest = &json.notifications[0];
+    assert_eq!(newest.kind, "owner_invitation");
+    assert_eq!(newest.krate.as_deref(), Some("bar_notified"));
+    assert!(!newest.read);
+
+    let body = format!(r#"{{"ids":[{}]}}"#, newest.id);
+    let json: MarkedRead = invitee
+        .put(&format!("{}/read", URL), body.as_bytes())
+        .good();
+    assert_eq!(json.marked, 1);
+    assert_eq!(json.unread, 1);
+
+    let json: NotificationList = invitee.get_with_query(URL, "unread=true").good();
+    assert_eq!(json.notifications.len(), 1);
+    assert_eq!(json.notifications[0].krate.as_deref(), Some("foo_notified"));
+
+    let json: MarkedRead = invitee.put(&format!("{}/read", URL), b"").good();
+    assert_eq!(json.marked, 1);
+    assert_eq!(json.unread, 0);
+
+    // Notifications are private to their user
+    let json: NotificationList = user.get(URL).good();
+    assert!(json.notifications.is_empty());
+    anon.get::<()>(URL).assert_forbidden();
+}
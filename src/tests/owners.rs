@@ -22,6 +22,10 @@ struct UserResponse {
     users: Vec<EncodableOwner>,
 }
 #[derive(Deserialize)]
+struct BatchResponse {
+    owners: std::collections::HashMap<String, Vec<EncodableOwner>>,
+}
+#[derive(Deserialize)]
 struct InvitationListResponse {
     crate_owner_invitations: Vec<EncodableCrateOwnerInvitation>,
 }
@@ -139,7 +143,7 @@ fn owners_can_remove_self() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required." }] })
+        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required.", "code": "invalid_request" }] })
     );
 
     create_and_add_owner(&app, &token, "secondowner", &krate);
@@ -157,7 +161,7 @@ fn owners_can_remove_self() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "only owners have permission to modify owners" }] })
+        json!({ "errors": [{ "detail": "only owners have permission to modify owners", "code": "not_owner" }] })
     );
 }
 
@@ -178,7 +182,7 @@ fn modify_multiple_owners() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required." }] })
+        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required.", "code": "invalid_request" }] })
     );
     assert_eq!(app.db(|conn| krate.owners(&conn).unwrap()).len(), 3);
 
@@ -196,7 +200,7 @@ fn modify_multiple_owners() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "`foo` is already an owner" }] })
+        json!({ "errors": [{ "detail": "`foo` is already an owner", "code": "already_owner" }] })
     );
     assert_eq!(app.db(|conn| krate.owners(&conn).unwrap()).len(), 1);
 
@@ -282,6 +286,44 @@ fn check_ownership_one_crate() {
     assert_eq!(json.users[0].name, user.name);
 }
 
+#[test]
+fn owners_of_several_crates_are_returned_at_once() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    let user2 = app.db_new_user("user_bar");
+
+    app.db(|conn| {
+        let t = new_team("github:test_org:team_sloth")
+            .create_or_update(conn)
+            .unwrap();
+        let krate = CrateBuilder::new("foo_team", user.id).expect_build(conn);
+        add_team_to_crate(&t, &krate, user, conn).unwrap();
+        CrateBuilder::new("bar_user", user2.as_model().id).expect_build(conn);
+    });
+
+    let json: BatchResponse = anon
+        .get_with_query(
+            "/api/v1/owners",
+            "crates[]=foo_team&crates[]=bar_user&crates[]=missing",
+        )
+        .good();
+    assert_eq!(json.owners.len(), 2);
+    let kinds: Vec<&str> = json.owners["foo_team"]
+        .iter()
+        .map(|owner| owner.kind.as_str())
+        .collect();
+    assert_eq!(kinds, vec!["user", "team"]);
+    assert_eq!(json.owners["bar_user"].len(), 1);
+    assert_eq!(json.owners["bar_user"][0].login, "user_bar");
+
+    let too_many = (0..101)
+        .map(|i| format!("crates[]=crate_{}", i))
+        .collect::<Vec<_>>()
+        .join("&");
+    let response = anon.get_with_query::<()>("/api/v1/owners", &too_many);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
 #[test]
 fn deleted_ownership_isnt_in_owner_user() {
     let (app, anon, user) = TestApp::init().with_user();
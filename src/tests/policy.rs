@@ -0,0 +1,37 @@
+use crate::builders::PublishBuilder;
+use crate::util::{RequestHelper, TestApp};
+
+use conduit::StatusCode;
+use serde_json::Value;
+use std::time::Duration;
+
+#[test]
+fn policy_reflects_the_configuration() {
+    let (_, anon, user) = TestApp::init()
+        .with_publish_rate_limit(Duration::from_secs(30), 2)
+        .with_user();
+
+    let response = anon.get::<Value>("/api/v1/policy");
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = response.good();
+    assert_eq!(json["crate_names"]["max_length"], 64);
+    assert!(json["crate_names"]["reserved"]
+        .as_array()
+        .unwrap()
+        .contains(&json!("std")));
+    assert_eq!(json["uploads"]["max_upload_size"], 3000);
+    assert_eq!(json["rate_limits"]["new_crates"]["burst"], 2);
+    assert_eq!(json["rate_limits"]["new_crates"]["refill_seconds"], 30);
+    assert!(json["rate_limits"]["first_publish_hold"].is_null());
+    assert_eq!(json["licenses"]["invalid_license"], "reject");
+
+    // Publishing one keyword more than the policy allows is rejected
+    let max_keywords = json["keywords"]["max_per_crate"].as_u64().unwrap();
+    let crate_to_publish = (0..=max_keywords)
+        .fold(PublishBuilder::new("foo_policy"), |builder, i| {
+            builder.keyword(&format!("kw{}", i))
+        });
+    let response = user.enqueue_publish(crate_to_publish);
+    let detail = response.json()["errors"][0]["detail"].to_string();
+    assert!(detail.contains("keywords per crate"), "{}", detail);
+}
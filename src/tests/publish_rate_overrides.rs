@@ -0,0 +1,128 @@
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::{EncodablePublishRateOverride, EncodablePublishRateOverrideAction};
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct OverrideList {
+    publish_rate_overrides: Vec<EncodablePublishRateOverride>,
+}
+
+#[derive(Deserialize)]
+struct OverrideResponse {
+    publish_rate_override: EncodablePublishRateOverride,
+}
+
+#[derive(Deserialize)]
+struct ActionList {
+    actions: Vec<EncodablePublishRateOverrideAction>,
+}
+
+fn override_url(user_id: i32) -> String {
+    format!("/api/v1/admin/publish_rate_overrides/{}", user_id)
+}
+
+#[test]
+fn non_admins_cannot_manage_overrides() {
+    let (_, anon, user) = TestApp::init().with_user();
+    let url = override_url(user.as_model().id);
+
+    anon.get::<()>("/api/v1/admin/publish_rate_overrides")
+        .assert_forbidden();
+    user.get::<()>("/api/v1/admin/publish_rate_overrides")
+        .assert_forbidden();
+    user.put::<()>(&url, br#"{"burst":200}"#).assert_forbidden();
+    user.delete::<()>(&url).assert_forbidden();
+}
+
+#[test]
+fn set_list_and_expire_override() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let user_id = user.as_model().id;
+    let url = override_url(user_id);
+
+    let json: OverrideResponse = admin.put(&url, br#"{"burst":200}"#).good();
+    assert_eq!(json.publish_rate_override.user_id, user_id);
+    assert_eq!(json.publish_rate_override.burst, 200);
+    assert_none!(json.publish_rate_override.expires_at);
+    assert!(json.publish_rate_override.active);
+
+    let json: OverrideList = admin.get("/api/v1/admin/publish_rate_overrides").good();
+    assert_eq!(json.publish_rate_overrides.len(), 1);
+    assert_eq!(json.publish_rate_overrides[0].login, "foo");
+
+    let json: OverrideResponse = admin.delete(&url).good();
+    assert!(!json.publish_rate_override.active);
+
+    let json: OverrideList = admin.get("/api/v1/admin/publish_rate_overrides").good();
+    assert_eq!(json.publish_rate_overrides.len(), 0);
+
+    let json: OverrideList = admin
+        .get_with_query(
+            "/api/v1/admin/publish_rate_overrides",
+            "include_expired=true",
+        )
+        .good();
+    assert_eq!(json.publish_rate_overrides.len(), 1);
+
+    // An expired override can't be expired again
+    admin.delete::<()>(&url).assert_not_found();
+
+    let json: ActionList = admin.get(&format!("{}/actions", url)).good();
+    let actions = json
+        .actions
+        .iter()
+        .map(|a| (a.action.as_str(), a.burst))
+        .collect::<Vec<_>>();
+    assert_eq!(actions, vec![("set", Some(200)), ("expire", None)]);
+    assert!(json
+        .actions
+        .iter()
+        .all(|a| a.admin_id == admin.as_model().id));
+}
+
+#[test]
+fn override_with_expiry() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    let expires_at = Utc::now() + Duration::days(7);
+    let body = json!({ "burst": 200, "expires_at": expires_at.to_rfc3339() }).to_string();
+    let json: OverrideResponse = admin
+        .put(&override_url(user.as_model().id), body.as_bytes())
+        .good();
+    assert_some!(json.publish_rate_override.expires_at);
+    assert!(json.publish_rate_override.active);
+}
+
+#[test]
+fn invalid_override_requests() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let url = override_url(user.as_model().id);
+
+    let response = admin.put::<()>(&url, br#"{"burst":-1}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = admin.put::<()>(&url, b"not json");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    admin
+        .put::<()>(&override_url(-1), br#"{"burst":200}"#)
+        .assert_not_found();
+}
+
+#[test]
+fn overrides_with_invalid_expiry_times_are_rejected() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+
+    // An invalid time must not make the override permanent
+    let body = br#"{"burst":200,"expires_at":"next week"}"#;
+    let response = admin.put::<()>(&override_url(user.as_model().id), body);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: OverrideList = admin.get("/api/v1/admin/publish_rate_overrides").good();
+    assert_eq!(json.publish_rate_overrides.len(), 0);
+}
@@ -0,0 +1,65 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+
+use conduit::StatusCode;
+use serde_json::Value;
+
+#[test]
+fn purls_resolve_to_crates_and_versions() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_purl", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .version(VersionBuilder::new("1.1.0"))
+            .expect_build(conn);
+    });
+
+    let json: Value = anon
+        .get_with_query("/api/v1/purl", "purl=pkg:cargo/foo_purl@1.0.0")
+        .good();
+    assert_eq!(json["crate"]["name"], "foo_purl");
+    assert_eq!(json["version"]["num"], "1.0.0");
+
+    let json: Value = anon
+        .get_with_query("/api/v1/purl", "purl=pkg:cargo/foo_purl")
+        .good();
+    assert_eq!(json["crate"]["max_version"], "1.1.0");
+    assert!(json["version"].is_null());
+
+    anon.get::<()>("/api/v1/purl?purl=pkg:cargo/foo_purl@2.0.0")
+        .assert_not_found();
+    anon.get::<()>("/api/v1/purl?purl=pkg:cargo/missing@1.0.0")
+        .assert_not_found();
+    let response = anon.get::<()>("/api/v1/purl?purl=pkg:npm/foo_purl@1.0.0");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn purls_are_resolved_in_batches() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+    app.db(|conn| {
+        CrateBuilder::new("foo_purl", user.id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let json: Value = anon
+        .get_with_query(
+            "/api/v1/purls",
+            "purls[]=pkg:cargo/foo_purl@1.0.0&purls[]=pkg:cargo/missing",
+        )
+        .good();
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0]["purl"], "pkg:cargo/foo_purl@1.0.0");
+    assert_eq!(results[0]["version"]["num"], "1.0.0");
+    assert!(results[0]["error"].is_null());
+    assert!(results[1]["crate"].is_null());
+    assert!(results[1]["error"].is_string());
+
+    let query = vec!["purls[]=pkg:cargo/foo_purl"; 101].join("&");
+    let response = anon.get_with_query::<()>("/api/v1/purls", &query);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
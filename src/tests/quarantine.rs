@@ -0,0 +1,179 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{Finding, VersionQuarantine};
+use cargo_registry::schema::versions;
+use cargo_registry::views::EncodableVersionQuarantine;
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct QuarantineList {
+    quarantines: Vec<EncodableVersionQuarantine>,
+    meta: QuarantineMeta,
+}
+
+#[derive(Deserialize)]
+struct QuarantineMeta {
+    total: i64,
+}
+
+#[derive(Deserialize)]
+struct QuarantineResponse {
+    quarantine: EncodableVersionQuarantine,
+}
+
+fn finding() -> Finding {
+    Finding {
+        scanner: "patterns".into(),
+        rule: "build-script-network".into(),
+        path: Some("build.rs".into()),
+        message: "build script accesses the network at install time".into(),
+    }
+}
+
+fn quarantine_version(conn: &PgConnection, num: &str) -> i32 {
+    let version_id = versions::table
+        .filter(versions::num.eq(num))
+        .select(versions::id)
+        .first(conn)
+        .unwrap();
+    VersionQuarantine::quarantine(conn, version_id, &[finding()]).unwrap();
+    version_id
+}
+
+#[test]
+fn non_admins_cannot_list_quarantine() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>("/api/v1/admin/quarantine")
+        .assert_forbidden();
+    user.get::<()>("/api/v1/admin/quarantine")
+        .assert_forbidden();
+}
+
+#[test]
+fn non_admins_cannot_release_versions() {
+    let (app, _, user) = TestApp::init().with_user();
+    let version_id = app.db(|conn| {
+        CrateBuilder::new("foo_quarantine", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        quarantine_version(conn, "1.0.0")
+    });
+
+    let url = format!("/api/v1/admin/quarantine/{}/release", version_id);
+    user.put::<()>(&url, b"").assert_forbidden();
+}
+
+#[test]
+fn list_pending_quarantine() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_quarantine", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+        quarantine_version(conn, "1.1.0")
+    });
+
+    let json: QuarantineList = admin.get("/api/v1/admin/quarantine").good();
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.quarantines.len(), 1);
+    assert_eq!(json.quarantines[0].krate, "foo_quarantine");
+    assert_eq!(json.quarantines[0].num, "1.1.0");
+    assert_eq!(json.quarantines[0].status, "pending");
+    assert_eq!(json.quarantines[0].findings, vec![finding()]);
+
+    let json: QuarantineList = admin
+        .get_with_query("/api/v1/admin/quarantine", "status=released")
+        .good();
+    assert_eq!(json.meta.total, 0);
+
+    let response = admin.get_with_query::<()>("/api/v1/admin/quarantine", "status=nope");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn quarantined_versions_cannot_be_downloaded_until_released() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let version_id = app.db(|conn| {
+        CrateBuilder::new("foo_quarantine", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+        quarantine_version(conn, "1.0.0")
+    });
+
+    let download_url = "/api/v1/crates/foo_quarantine/1.0.0/download";
+    let response = anon.get::<()>(download_url);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let url = format!("/api/v1/admin/quarantine/{}/release", version_id);
+    let json: QuarantineResponse = admin.put(&url, b"").good();
+    assert_eq!(json.quarantine.status, "released");
+    assert_eq!(json.quarantine.reviewed_by, Some(admin.as_model().id));
+    assert!(json.quarantine.reviewed_at.is_some());
+
+    let response = anon.get::<()>(download_url);
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    // Only pending entries can be reviewed
+    admin.put::<()>(&url, b"").assert_not_found();
+}
+
+#[test]
+fn cached_versions_can_be_quarantined() {
+    let (app, anon, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_quarantine_cached", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let download_url = "/api/v1/crates/foo_quarantine_cached/1.0.0/download";
+    let response = anon.get::<()>(download_url);
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_some!(app
+        .as_inner()
+        .version_lookups
+        .get("foo_quarantine_cached", "1.0.0"));
+
+    // The scanners run on the background worker, so the quarantine is only
+    // noticed once the cached state is older than `QUARANTINE_CHECK_INTERVAL`
+    let version_id = app.db(|conn| quarantine_version(conn, "1.0.0"));
+    let response = anon.get::<()>(download_url);
+    assert_eq!(response.status(), StatusCode::FOUND);
+
+    app.as_inner()
+        .version_lookups
+        .invalidate_quarantine(version_id);
+    let response = anon.get::<()>(download_url);
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[test]
+fn rejecting_yanks_the_version() {
+    let (app, anon, _, token) = TestApp::full().with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    token.enqueue_publish(PublishBuilder::new("fyk")).good();
+    app.run_pending_background_jobs();
+
+    let version_id = app.db(|conn| quarantine_version(conn, "1.0.0"));
+
+    let url = format!("/api/v1/admin/quarantine/{}/reject", version_id);
+    let json: QuarantineResponse = admin.put(&url, b"").good();
+    assert_eq!(json.quarantine.status, "rejected");
+    app.run_pending_background_jobs();
+
+    let crates = app.crates_from_index_head("3/f/fyk");
+    assert_eq!(crates.len(), 1);
+    assert_some_eq!(crates[0].yanked, true);
+
+    let json = anon.show_version("fyk", "1.0.0");
+    assert!(json.version.yanked);
+    assert_eq!(json.version.audit_actions[1].action, "yank");
+    assert_eq!(json.version.audit_actions[1].user.login, "admin");
+}
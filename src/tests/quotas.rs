@@ -0,0 +1,119 @@
+use crate::builders::{CrateBuilder, PublishBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::{EncodableQuotaOverride, EncodableQuotas};
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct QuotasResponse {
+    quotas: EncodableQuotas,
+}
+
+#[derive(Deserialize)]
+struct OverrideResponse {
+    quotas: EncodableQuotas,
+    quota_override: Option<EncodableQuotaOverride>,
+}
+
+fn override_url(user_id: i32) -> String {
+    format!("/api/v1/admin/users/{}/quotas", user_id)
+}
+
+#[test]
+fn new_crates_over_the_crate_quota_are_rejected() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.quotas.crates = Some(1))
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_quota", user.as_model().id).expect_build(conn);
+    });
+
+    let response = user.enqueue_publish(PublishBuilder::new("foo_quota_second"));
+    assert_eq!(response.json()["errors"][0]["code"], "crate_quota_exceeded");
+
+    let json: QuotasResponse = user.get("/api/v1/me/quotas").good();
+    assert_eq!(json.quotas.crates.used, 1);
+    assert_eq!(json.quotas.crates.limit, Some(1));
+    assert_none!(json.quotas.storage_bytes.limit);
+}
+
+#[test]
+fn versions_over_the_storage_quota_are_rejected() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.quotas.storage_bytes = Some(100))
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_storage_quota", user.as_model().id)
+            .version(VersionBuilder::new("1.0.0").size(100))
+            .expect_build(conn);
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_storage_quota").version("1.1.0");
+    let response = user.enqueue_publish(crate_to_publish);
+    assert_eq!(
+        response.json()["errors"][0]["code"],
+        "storage_quota_exceeded"
+    );
+
+    let json: QuotasResponse = user.get("/api/v1/me/quotas").good();
+    assert_eq!(json.quotas.storage_bytes.used, 100);
+    assert_eq!(json.quotas.storage_bytes.limit, Some(100));
+    assert_eq!(json.quotas.versions_per_day.used, 1);
+}
+
+#[test]
+fn versions_over_the_daily_quota_are_rejected() {
+    let (app, _, user) = TestApp::init()
+        .with_config(|config| config.quotas.versions_per_day = Some(2))
+        .with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_daily", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+    });
+
+    let response = user.enqueue_publish(PublishBuilder::new("foo_daily").version("1.2.0"));
+    assert_eq!(
+        response.json()["errors"][0]["code"],
+        "version_quota_exceeded"
+    );
+}
+
+#[test]
+fn admins_override_the_quotas_of_a_user() {
+    let (app, anon, user) = TestApp::init()
+        .with_config(|config| {
+            config.quotas.crates = Some(1);
+            config.quotas.versions_per_day = Some(10);
+        })
+        .with_user();
+    let admin = app.db_new_admin_user("admin");
+    let url = override_url(user.as_model().id);
+
+    anon.get::<()>("/api/v1/me/quotas").assert_forbidden();
+    user.get::<()>(&url).assert_forbidden();
+    user.put::<()>(&url, br#"{"crates":5}"#).assert_forbidden();
+
+    let json: OverrideResponse = admin.get(&url).good();
+    assert_none!(json.quota_override);
+    assert_eq!(json.quotas.crates.limit, Some(1));
+
+    let json: OverrideResponse = admin.put(&url, br#"{"crates":5}"#).good();
+    let quota_override = json.quota_override.unwrap();
+    assert_eq!(quota_override.crates, Some(5));
+    assert_eq!(quota_override.admin_id, admin.as_model().id);
+    assert_eq!(json.quotas.crates.limit, Some(5));
+    // Quotas left out of the override are the configured ones
+    assert_eq!(json.quotas.versions_per_day.limit, Some(10));
+
+    let json: QuotasResponse = user.get("/api/v1/me/quotas").good();
+    assert_eq!(json.quotas.crates.limit, Some(5));
+
+    let response = admin.put::<()>(&url, br#"{"crates":-1}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: OverrideResponse = admin.delete(&url).good();
+    assert_none!(json.quota_override);
+    assert_eq!(json.quotas.crates.limit, Some(1));
+    admin.delete::<()>(&url).assert_not_found();
+}
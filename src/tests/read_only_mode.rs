@@ -1,7 +1,7 @@
 use crate::builders::CrateBuilder;
 use crate::{RequestHelper, TestApp};
 
-use conduit::StatusCode;
+use conduit::{header, StatusCode};
 use diesel::prelude::*;
 
 #[test]
@@ -24,6 +24,7 @@ fn cannot_hit_endpoint_which_writes_db_in_read_only_mode() {
 
     let response = token.delete::<()>("/api/v1/crates/foo_yank_read_only/1.0.0/yank");
     assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(response.header(header::RETRY_AFTER), Some("300"));
 
     // Restore the transaction so `TestApp::drop` can still access the transaction
     app.db(|conn| {
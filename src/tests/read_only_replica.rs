@@ -0,0 +1,27 @@
+use crate::TestApp;
+
+use cargo_registry::db::{replication_lag, ReplicaStatus};
+use std::time::Duration;
+
+#[test]
+fn primary_has_no_replication_lag() {
+    let (app, _) = TestApp::init().empty();
+    let lag = app.db(|conn| replication_lag(conn).unwrap());
+    assert_eq!(lag, 0.0);
+}
+
+#[test]
+fn replica_within_max_lag_is_used() {
+    let (app, _) = TestApp::init().empty();
+    let status = ReplicaStatus::new(Some(Duration::from_secs(30)));
+    assert!(status.use_replica(&app.as_inner().primary_database));
+    assert!(status.is_healthy());
+}
+
+#[test]
+fn unavailable_replica_is_not_used_until_next_check() {
+    let (app, _) = TestApp::init().empty();
+    let status = ReplicaStatus::new(None);
+    status.mark_unavailable();
+    assert!(!status.use_replica(&app.as_inner().primary_database));
+}
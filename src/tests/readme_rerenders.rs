@@ -0,0 +1,115 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::schema::background_jobs;
+use cargo_registry::views::EncodableReadmeRerender;
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+use serde_json::Value;
+
+const URL: &str = "/api/v1/admin/readme_rerenders";
+
+#[derive(Deserialize)]
+struct ReadmeRerenderResponse {
+    readme_rerender: EncodableReadmeRerender,
+}
+
+#[test]
+fn non_admins_cannot_rerender_readmes() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+    user.put::<()>(URL, b"{}").assert_forbidden();
+    user.delete::<()>(&format!("{}/1", URL)).assert_forbidden();
+}
+
+#[test]
+fn invalid_requests_are_rejected() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let response = admin.put::<()>(URL, br#"{ "batch_size": 0 }"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // An invalid time must not rerender every readme
+    let response = admin.put::<()>(URL, br#"{ "rendered_before": "yesterday" }"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    admin
+        .put::<()>(URL, br#"{ "crate": "unknown" }"#)
+        .assert_not_found();
+}
+
+#[test]
+fn rerender_reports_progress_and_can_be_cancelled() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_rerender", admin.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+        CrateBuilder::new("bar_rerender", admin.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let body = br#"{ "crate": "foo_rerender", "batch_size": 10 }"#;
+    let json: ReadmeRerenderResponse = admin.put(URL, body).good();
+    let rerender = json.readme_rerender;
+    assert_some_eq!(rerender.crate_name.as_deref(), "foo_rerender");
+    assert_eq!(rerender.batch_size, 10);
+    assert_eq!(rerender.total_versions, 2);
+    assert_eq!(rerender.processed_versions, 0);
+    assert_none!(rerender.completed_at);
+
+    let json: Value = admin
+        .get_with_query("/api/v1/admin/background_jobs", "job_type=rerender_readmes")
+        .good();
+    let job = &json["background_jobs"][0];
+    assert_eq!(job["queue"], "readme");
+    assert_eq!(job["data"]["readme_rerender_id"], rerender.id);
+    assert_eq!(job["progress"], json!({ "completed": 0, "total": 2 }));
+
+    let url = format!("{}/{}", URL, rerender.id);
+    let json: ReadmeRerenderResponse = admin.delete(&url).good();
+    assert_some!(json.readme_rerender.cancelled_at);
+
+    let response = admin.delete::<()>(&url);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: ReadmeRerenderResponse = admin.get(&url).good();
+    assert_some!(json.readme_rerender.cancelled_at);
+
+    let json: Value = admin.get(URL).good();
+    assert_eq!(json["meta"]["total"], 1);
+
+    // The job was never meant to run, so remove it before the app is dropped
+    app.db(|conn| {
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn rerender_without_matching_versions_completes() {
+    let (app, _) = TestApp::full().empty();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_rerender_none", admin.as_model().id).expect_build(conn);
+    });
+
+    let body = br#"{ "crate": "foo_rerender_none" }"#;
+    let json: ReadmeRerenderResponse = admin.put(URL, body).good();
+    assert_eq!(json.readme_rerender.total_versions, 0);
+
+    app.run_pending_background_jobs();
+
+    let url = format!("{}/{}", URL, json.readme_rerender.id);
+    let json: ReadmeRerenderResponse = admin.get(&url).good();
+    assert_some!(json.readme_rerender.completed_at);
+    assert_eq!(json.readme_rerender.processed_versions, 0);
+    assert_eq!(json.readme_rerender.failed_versions, 0);
+}
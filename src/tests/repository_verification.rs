@@ -0,0 +1,116 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::background_jobs::Job;
+use cargo_registry::models::RepositoryVerification;
+use cargo_registry::schema::{crates, repository_verifications};
+use cargo_registry::views::EncodableRepositoryVerification;
+use chrono::{Duration, Utc};
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct VerificationResponse {
+    repository_verification: Option<EncodableRepositoryVerification>,
+}
+
+const URL: &str = "/api/v1/crates/foo_verified/repository_verification";
+
+#[test]
+fn github_repositories_are_verified_with_push_access() {
+    let (app, anon, user) = TestApp::with_proxy().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_verified", user.as_model().id)
+            .repository("https://github.com/crates-test-org/foo_verified")
+            .expect_build(conn);
+    });
+
+    let json = anon.show_crate("foo_verified");
+    assert_eq!(json.krate.verified_repository, Some(false));
+
+    let json: VerificationResponse = user.put(URL, b"").good();
+    let verification = json.repository_verification.unwrap();
+    assert_eq!(verification.method, "github");
+    assert_none!(verification.well_known_url);
+    assert_none!(verification.verified_at);
+
+    let url = format!("{}/check", URL);
+    let json: VerificationResponse = user.put(&url, b"").good();
+    assert_some!(json.repository_verification.unwrap().verified_at);
+
+    let json = anon.show_crate("foo_verified");
+    assert_eq!(json.krate.verified_repository, Some(true));
+}
+
+#[test]
+fn only_owners_can_verify_the_repository() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    app.db(|conn| {
+        CrateBuilder::new("foo_verified", user.as_model().id)
+            .repository("https://github.com/crates-test-org/foo_verified")
+            .expect_build(conn);
+    });
+
+    let response = other.put::<()>(URL, b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = other.get::<()>(URL);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let json: VerificationResponse = user.get(URL).good();
+    assert_none!(json.repository_verification);
+}
+
+#[test]
+fn repositories_without_an_https_url_cannot_be_verified() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_verified", user.as_model().id)
+            .repository("http://git.example.com/foo_verified")
+            .expect_build(conn);
+    });
+
+    let response = user.put::<()>(URL, b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn rechecks_clear_verifications_of_repositories_the_crate_no_longer_links_to() {
+    let (app, _, user) = TestApp::full().with_user();
+    let user = user.as_model();
+
+    app.db(|conn| {
+        let krate = CrateBuilder::new("foo_verified", user.id)
+            .repository("https://github.com/crates-test-org/foo_verified")
+            .expect_build(conn);
+        let verification = RepositoryVerification::start(
+            conn,
+            krate.id,
+            "https://github.com/crates-test-org/foo_verified",
+            user.id,
+        )
+        .unwrap();
+        verification.record_check(conn, true, user.id).unwrap();
+        diesel::update(repository_verifications::table)
+            .set(
+                repository_verifications::last_checked_at
+                    .eq(Utc::now().naive_utc() - Duration::days(2)),
+            )
+            .execute(conn)
+            .unwrap();
+        diesel::update(crates::table)
+            .set(crates::repository.eq("https://github.com/someone-else/foo_verified"))
+            .execute(conn)
+            .unwrap();
+
+        Job::RecheckRepositoryVerifications {}
+            .enqueue(conn)
+            .unwrap();
+    });
+    app.run_pending_background_jobs();
+
+    app.db(|conn| {
+        let krate_id: i32 = crates::table.select(crates::id).first(conn).unwrap();
+        let verification = RepositoryVerification::for_crate(conn, krate_id).unwrap();
+        assert_none!(verification.unwrap().verified_at);
+    });
+}
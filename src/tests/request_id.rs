@@ -0,0 +1,47 @@
+use crate::util::{RequestHelper, TestApp};
+
+use cargo_registry::schema::background_jobs;
+use conduit::header::HeaderName;
+use conduit::Method;
+use diesel::prelude::*;
+
+#[test]
+fn responses_include_the_request_id() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let mut request = anon.request_builder(Method::GET, "/api/v1/summary");
+    request.header("X-Request-Id", "given-request-id");
+    let response = anon.run::<()>(request);
+    assert_some_eq!(
+        response.header(HeaderName::from_static("x-request-id")),
+        "given-request-id"
+    );
+
+    let response = anon.get::<()>("/api/v1/summary");
+    let generated = response
+        .header(HeaderName::from_static("x-request-id"))
+        .unwrap();
+    assert_eq!(generated.len(), 32);
+}
+
+#[test]
+fn enqueued_jobs_remember_the_request_id() {
+    let (app, _, user) = TestApp::init().with_user();
+
+    let mut request = user.request_builder(Method::PUT, "/api/v1/me/data_export");
+    request.header("X-Request-Id", "export-request");
+    let response = user.run::<()>(request);
+    assert!(response.status().is_success());
+
+    app.db(|conn| {
+        let request_ids: Vec<Option<String>> = background_jobs::table
+            .select(background_jobs::request_id)
+            .load(conn)
+            .unwrap();
+        assert_eq!(request_ids, vec![Some("export-request".to_string())]);
+
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
@@ -0,0 +1,113 @@
+use crate::builders::{CrateBuilder, PublishBuilder};
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::ReservedCrateName;
+use cargo_registry::views::EncodableReservedCrateName;
+
+use conduit::StatusCode;
+
+const URL: &str = "/api/v1/admin/reserved_crate_names";
+
+#[derive(Deserialize)]
+struct ReservedCrateNames {
+    reserved_crate_names: Vec<EncodableReservedCrateName>,
+}
+
+#[test]
+fn non_admins_cannot_manage_reserved_names() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+    user.put::<()>(&format!("{}/foo", URL), b"")
+        .assert_forbidden();
+    user.delete::<()>(&format!("{}/std", URL))
+        .assert_forbidden();
+}
+
+#[test]
+fn list_reserved_names() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+
+    let json: ReservedCrateNames = admin.get(URL).good();
+    let std = json
+        .reserved_crate_names
+        .iter()
+        .find(|reserved| reserved.name == "std")
+        .unwrap();
+    assert!(!std.wildcard);
+}
+
+#[test]
+fn reserve_and_release_wildcard() {
+    let (app, _, _, token) = TestApp::init().with_token();
+    let admin = app.db_new_admin_user("admin");
+
+    let response = admin.put::<()>(&format!("{}/rustc_*", URL), b"");
+    assert_eq!(response.status(), StatusCode::OK);
+    let json: ReservedCrateNames = admin.get(URL).good();
+    assert!(json
+        .reserved_crate_names
+        .iter()
+        .any(|reserved| reserved.name == "rustc_*" && reserved.wildcard));
+
+    for name in &["rustc_foo", "rustc-foo", "RUSTC_FOO"] {
+        let crate_to_publish = PublishBuilder::new(name).version("1.0.0");
+        let json = token.enqueue_publish(crate_to_publish).json();
+        let detail = json["errors"][0]["detail"].as_str().unwrap();
+        assert!(detail.contains("reserved name"), "{:?}", detail);
+    }
+
+    app.db(|conn| {
+        // `_` in the reservation is not a single character wildcard
+        assert!(!ReservedCrateName::matches(conn, "rustcxfoo").unwrap());
+        assert!(!ReservedCrateName::matches(conn, "myrustc_foo").unwrap());
+    });
+
+    let response = admin.delete::<()>(&format!("{}/rustc-*", URL));
+    assert_eq!(response.status(), StatusCode::OK);
+    app.db(|conn| {
+        assert!(!ReservedCrateName::matches(conn, "rustc_foo").unwrap());
+    });
+    admin
+        .delete::<()>(&format!("{}/rustc_*", URL))
+        .assert_not_found();
+}
+
+#[test]
+fn cannot_reserve_invalid_or_used_names() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_in_use", user.as_model().id).expect_build(conn);
+    });
+
+    for name in &["*", "**", "foo+bar", "_*"] {
+        let response = admin.put::<()>(&format!("{}/{}", URL, name), b"");
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{}", name);
+    }
+
+    let response = admin.put::<()>(&format!("{}/foo-in-use", URL), b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn wildcards_do_not_apply_to_existing_crates() {
+    let (app, _, user, token) = TestApp::full().with_token();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_twice", user.as_model().id).expect_build(conn);
+        ReservedCrateName::reserve(conn, "foo_*").unwrap();
+    });
+
+    let crate_to_publish = PublishBuilder::new("foo_twice")
+        .version("2.0.0")
+        .description("2.0.0 description");
+    let json = token.enqueue_publish(crate_to_publish).good();
+    assert_eq!(json.krate.name, "foo_twice");
+
+    let crate_to_publish = PublishBuilder::new("foo_new").version("1.0.0");
+    let json = token.enqueue_publish(crate_to_publish).json();
+    let detail = json["errors"][0]["detail"].as_str().unwrap();
+    assert!(detail.contains("reserved name"), "{:?}", detail);
+}
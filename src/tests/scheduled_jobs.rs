@@ -0,0 +1,141 @@
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::schedule::{self, ScheduledJobState, SCHEDULED_JOBS};
+use cargo_registry::schema::{background_jobs, scheduled_jobs};
+use cargo_registry::views::EncodableScheduledJob;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+
+const URL: &str = "/api/v1/admin/scheduled_jobs";
+
+#[derive(Deserialize)]
+struct ScheduledJobsResponse {
+    scheduled_jobs: Vec<EncodableScheduledJob>,
+}
+
+/// The current time, truncated to what the database can store
+fn now() -> NaiveDateTime {
+    NaiveDateTime::from_timestamp(Utc::now().timestamp(), 0)
+}
+
+fn find_state(conn: &PgConnection, name: &str) -> ScheduledJobState {
+    ScheduledJobState::all(conn)
+        .unwrap()
+        .into_iter()
+        .find(|state| state.name == name)
+        .unwrap()
+}
+
+fn queued_jobs(conn: &PgConnection, job_type: &str) -> i64 {
+    background_jobs::table
+        .filter(background_jobs::job_type.eq(job_type))
+        .count()
+        .get_result(conn)
+        .unwrap()
+}
+
+fn make_due(conn: &PgConnection, name: &str, next_run_at: NaiveDateTime) {
+    diesel::update(scheduled_jobs::table.find(name))
+        .set(scheduled_jobs::next_run_at.eq(next_run_at))
+        .execute(conn)
+        .unwrap();
+}
+
+#[test]
+fn non_admins_cannot_list_scheduled_jobs() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>(URL).assert_forbidden();
+    user.get::<()>(URL).assert_forbidden();
+}
+
+#[test]
+fn list_scheduled_jobs() {
+    let (app, _) = TestApp::init().empty();
+    let admin = app.db_new_admin_user("admin");
+    let now = now();
+
+    let json: ScheduledJobsResponse = admin.get(URL).good();
+    let enabled = SCHEDULED_JOBS.iter().filter(|job| job.is_enabled()).count();
+    assert_eq!(json.scheduled_jobs.len(), enabled);
+    let job = &json.scheduled_jobs[0];
+    assert_eq!(job.name, "update_downloads");
+    assert_eq!(job.schedule, "*/10 * * * *");
+    assert_none!(job.last_run_at);
+    assert_eq!(job.upcoming_runs.len(), 5);
+    assert_eq!(job.next_run_at, job.upcoming_runs[0]);
+    assert!(job.next_run_at > now);
+    assert!(job.upcoming_runs.windows(2).all(|runs| runs[0] < runs[1]));
+
+    // Overdue jobs are listed with their missed run
+    let missed_run = now - Duration::hours(3);
+    app.db(|conn| {
+        schedule::sync_scheduled_jobs(conn, now).unwrap();
+        make_due(conn, "update_downloads", missed_run);
+    });
+
+    let json: ScheduledJobsResponse = admin.get(URL).good();
+    let job = &json.scheduled_jobs[0];
+    assert_eq!(job.next_run_at, missed_run);
+    assert!(job.upcoming_runs[0] > now);
+}
+
+#[test]
+fn missed_runs_are_enqueued_once() {
+    let (app, _) = TestApp::init().empty();
+    let now = now();
+
+    app.db(|conn| {
+        // Nothing is due right after the schedules are first stored
+        assert_eq!(
+            schedule::enqueue_due_jobs(conn, now).unwrap(),
+            Vec::<&str>::new()
+        );
+
+        // The worker was down for a day
+        make_due(conn, "update_downloads", now - Duration::days(1));
+        let enqueued = schedule::enqueue_due_jobs(conn, now).unwrap();
+        assert_eq!(enqueued, vec!["update_downloads"]);
+        assert_eq!(queued_jobs(conn, "update_downloads"), 1);
+
+        let state = find_state(conn, "update_downloads");
+        assert_some_eq!(state.last_run_at, now);
+        assert!(state.next_run_at > now);
+
+        assert_eq!(
+            schedule::enqueue_due_jobs(conn, now).unwrap(),
+            Vec::<&str>::new()
+        );
+        assert_eq!(queued_jobs(conn, "update_downloads"), 1);
+
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
+
+#[test]
+fn due_jobs_are_skipped_while_still_queued() {
+    let (app, _) = TestApp::init().empty();
+    let now = now();
+
+    app.db(|conn| {
+        schedule::sync_scheduled_jobs(conn, now).unwrap();
+        let first_run = now - Duration::minutes(10);
+        make_due(conn, "update_downloads", first_run - Duration::minutes(10));
+        schedule::enqueue_due_jobs(conn, first_run).unwrap();
+
+        make_due(conn, "update_downloads", now);
+        let enqueued = schedule::enqueue_due_jobs(conn, now).unwrap();
+        assert_eq!(enqueued, Vec::<&str>::new());
+        assert_eq!(queued_jobs(conn, "update_downloads"), 1);
+
+        let state = find_state(conn, "update_downloads");
+        assert_some_eq!(state.last_run_at, first_run);
+        assert!(state.next_run_at > now);
+
+        diesel::delete(background_jobs::table)
+            .execute(conn)
+            .unwrap();
+    });
+}
@@ -74,3 +74,21 @@ fn block_traffic_via_arbitrary_header_and_value() {
     let resp = anon.run::<()>(req);
     assert_eq!(resp.status(), StatusCode::FOUND);
 }
+
+#[test]
+fn api_responses_have_security_headers() {
+    let (_app, anon) = TestApp::init().empty();
+
+    let resp = anon.get::<()>("/api/v1/summary");
+    assert_eq!(
+        resp.header(header::CONTENT_SECURITY_POLICY),
+        Some("default-src 'none'; frame-ancestors 'none'")
+    );
+    assert_eq!(resp.header(header::X_CONTENT_TYPE_OPTIONS), Some("nosniff"));
+    assert_eq!(resp.header(header::X_FRAME_OPTIONS), Some("SAMEORIGIN"));
+
+    // Errors get them, too
+    let resp = anon.get::<()>("/api/v1/crates/missing");
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    assert_eq!(resp.header(header::X_CONTENT_TYPE_OPTIONS), Some("nosniff"));
+}
@@ -0,0 +1,100 @@
+use crate::util::{MockCookieUser, RequestHelper, TestApp};
+use cargo_registry::views::EncodableUserSession;
+use conduit::header;
+
+#[derive(Deserialize)]
+struct Sessions {
+    sessions: Vec<EncodableUserSession>,
+}
+
+const URL: &str = "/api/v1/me/sessions";
+
+#[test]
+fn sessions_can_be_listed_and_revoked() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = MockCookieUser::new(&app, user.as_model().clone());
+    other.get::<serde_json::Value>("/api/v1/me").good();
+
+    let json: Sessions = user.get(URL).good();
+    assert_eq!(json.sessions.len(), 2);
+    let current = json.sessions.iter().filter(|session| session.current);
+    assert_eq!(current.count(), 1);
+
+    let other_session = json.sessions.iter().find(|session| !session.current);
+    let url = format!("{}/{}", URL, other_session.unwrap().id);
+    user.delete::<serde_json::Value>(&url).good();
+
+    other.get::<()>("/api/v1/me").assert_forbidden();
+    let json: Sessions = user.get(URL).good();
+    assert_eq!(json.sessions.len(), 1);
+    assert!(json.sessions[0].current);
+}
+
+#[test]
+fn sessions_of_other_users_cannot_be_revoked() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    let json: Sessions = other.get(URL).good();
+    let url = format!("{}/{}", URL, json.sessions[0].id);
+    user.delete::<serde_json::Value>(&url).good();
+
+    other.get::<serde_json::Value>("/api/v1/me").good();
+}
+
+#[test]
+fn revoking_all_sessions_rotates_the_current_one() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = MockCookieUser::new(&app, user.as_model().clone());
+
+    let response = user.delete::<serde_json::Value>(URL);
+    let cookie = response.header(header::SET_COOKIE).unwrap().to_string();
+    assert!(cookie.starts_with("cargo_session="));
+    assert!(cookie.contains("HttpOnly"));
+    assert!(cookie.contains("SameSite=Lax"));
+    response.good();
+
+    // Both the other session and the one the request was made with are gone,
+    // the browser continues with the session of the new cookie
+    other.get::<()>("/api/v1/me").assert_forbidden();
+    user.get::<()>("/api/v1/me").assert_forbidden();
+}
+
+#[test]
+fn logging_out_revokes_the_session() {
+    let (_, _, user) = TestApp::init().with_user();
+    user.get::<serde_json::Value>("/api/v1/me").good();
+
+    user.delete::<serde_json::Value>("/api/private/session")
+        .good();
+    user.get::<()>("/api/v1/me").assert_forbidden();
+}
+
+#[test]
+fn changing_the_admin_flag_revokes_the_sessions() {
+    use cargo_registry::schema::users;
+    use diesel::prelude::*;
+
+    let (app, _, user) = TestApp::init().with_user();
+    let user_id = user.as_model().id;
+    let set_admin = |is_admin: bool| {
+        app.db(|conn| {
+            diesel::update(users::table.find(user_id))
+                .set(users::is_admin.eq(is_admin))
+                .execute(conn)
+                .unwrap()
+        })
+    };
+
+    // Updates that keep the flag leave the sessions alone
+    set_admin(false);
+    user.get::<serde_json::Value>("/api/v1/me").good();
+
+    set_admin(true);
+    user.get::<()>("/api/v1/me").assert_forbidden();
+
+    let user = MockCookieUser::new(&app, user.as_model().clone());
+    user.get::<serde_json::Value>("/api/v1/me").good();
+    set_admin(false);
+    user.get::<()>("/api/v1/me").assert_forbidden();
+}
@@ -0,0 +1,40 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::tasks;
+
+#[test]
+fn sitemaps_list_the_pages_of_the_registry() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    anon.get::<()>("/sitemap.xml").assert_not_found();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_sitemap", user.id)
+            .keyword("mapping")
+            .expect_build(conn);
+        tasks::perform_generate_sitemaps(conn).unwrap();
+    });
+
+    let index = String::from_utf8(anon.get::<()>("/sitemap.xml").into_bytes()).unwrap();
+    assert!(index.contains("/sitemaps/crates-1.xml</loc>"));
+    assert!(index.contains("/sitemaps/keywords-1.xml</loc>"));
+    assert!(index.contains("/sitemaps/users-1.xml</loc>"));
+    assert!(!index.contains("/sitemaps/categories-1.xml</loc>"));
+
+    let crates = String::from_utf8(anon.get::<()>("/sitemaps/crates-1.xml").into_bytes()).unwrap();
+    assert!(crates.contains("/crates/foo_sitemap</loc><lastmod>"));
+    let users = String::from_utf8(anon.get::<()>("/sitemaps/users-1.xml").into_bytes()).unwrap();
+    assert!(users.contains(&format!("/users/{}</loc>", user.gh_login)));
+
+    anon.get::<()>("/sitemaps/crates-2.xml").assert_not_found();
+}
+
+#[test]
+fn robots_txt_points_to_the_sitemap() {
+    let (_, anon) = TestApp::init().empty();
+
+    let response = anon.get::<()>("/robots.txt");
+    let robots = String::from_utf8(response.into_bytes()).unwrap();
+    assert!(robots.contains("Sitemap: https://crates.io/sitemap.xml"));
+}
@@ -0,0 +1,124 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{NewStorageMismatch, StorageMismatch, StorageMismatchKind};
+use cargo_registry::schema::{storage_mismatches, versions};
+use cargo_registry::views::EncodableStorageMismatch;
+
+use conduit::StatusCode;
+use diesel::prelude::*;
+
+#[derive(Deserialize)]
+struct MismatchList {
+    storage_mismatches: Vec<EncodableStorageMismatch>,
+    meta: MismatchMeta,
+}
+
+#[derive(Deserialize)]
+struct MismatchMeta {
+    total: i64,
+}
+
+#[derive(Deserialize)]
+struct MismatchResponse {
+    storage_mismatch: EncodableStorageMismatch,
+}
+
+fn record_mismatch(conn: &PgConnection, num: &str, kind: StorageMismatchKind) -> i32 {
+    let version_id = versions::table
+        .filter(versions::num.eq(num))
+        .select(versions::id)
+        .first(conn)
+        .unwrap();
+    NewStorageMismatch {
+        version_id,
+        kind,
+        expected: Some("expected"),
+        actual: Some("actual"),
+    }
+    .create(conn)
+    .unwrap();
+    storage_mismatches::table
+        .filter(storage_mismatches::version_id.eq(version_id))
+        .select(storage_mismatches::id)
+        .first(conn)
+        .unwrap()
+}
+
+#[test]
+fn non_admins_cannot_list_storage_mismatches() {
+    let (_, anon, user) = TestApp::init().with_user();
+
+    anon.get::<()>("/api/v1/admin/storage_mismatches")
+        .assert_forbidden();
+    user.get::<()>("/api/v1/admin/storage_mismatches")
+        .assert_forbidden();
+}
+
+#[test]
+fn list_and_resolve_storage_mismatches() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    let (missing_id, _) = app.db(|conn| {
+        CrateBuilder::new("foo_storage", user.as_model().id)
+            .version("1.0.0")
+            .version("1.1.0")
+            .expect_build(conn);
+        (
+            record_mismatch(conn, "1.0.0", StorageMismatchKind::Missing),
+            record_mismatch(conn, "1.1.0", StorageMismatchKind::Checksum),
+        )
+    });
+
+    let json: MismatchList = admin.get("/api/v1/admin/storage_mismatches").good();
+    assert_eq!(json.meta.total, 2);
+    let kinds = json
+        .storage_mismatches
+        .iter()
+        .map(|m| m.kind.as_str())
+        .collect::<Vec<_>>();
+    assert!(kinds.contains(&"missing"));
+    assert!(kinds.contains(&"checksum"));
+    assert!(json
+        .storage_mismatches
+        .iter()
+        .all(|m| m.krate == "foo_storage"));
+
+    let url = format!("/api/v1/admin/storage_mismatches/{}/resolve", missing_id);
+    let json: MismatchResponse = admin.put(&url, b"").good();
+    assert_eq!(json.storage_mismatch.resolved_by, Some(admin.as_model().id));
+    assert_some!(json.storage_mismatch.resolved_at);
+
+    // Resolved mismatches are only listed on request
+    let json: MismatchList = admin.get("/api/v1/admin/storage_mismatches").good();
+    assert_eq!(json.meta.total, 1);
+    assert_eq!(json.storage_mismatches[0].kind, "checksum");
+    let json: MismatchList = admin
+        .get_with_query("/api/v1/admin/storage_mismatches", "all=true")
+        .good();
+    assert_eq!(json.meta.total, 2);
+
+    let response = admin.put::<()>(&url, b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn versions_have_one_unresolved_mismatch_at_most() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_storage", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+        let first = record_mismatch(conn, "1.0.0", StorageMismatchKind::Missing);
+        let second = record_mismatch(conn, "1.0.0", StorageMismatchKind::Size);
+        assert_eq!(first, second);
+
+        let mismatch = StorageMismatch::find(conn, first).unwrap();
+        assert_eq!(mismatch.kind, StorageMismatchKind::Missing);
+
+        // A successful verification resolves the mismatch, so a new one can
+        // be recorded later on
+        assert!(StorageMismatch::resolve_verified(conn, mismatch.version_id).unwrap());
+        assert!(!StorageMismatch::resolve_verified(conn, mismatch.version_id).unwrap());
+        assert!(StorageMismatch::find(conn, first).unwrap().is_resolved());
+    });
+}
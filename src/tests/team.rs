@@ -53,7 +53,7 @@ fn not_github() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "unknown organization handler, only 'github:org:team' is supported" }] })
+        json!({ "errors": [{ "detail": "unknown organization handler, only 'github:org:team' is supported", "code": "invalid_request" }] })
     );
 }
 
@@ -69,7 +69,7 @@ fn weird_name() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "organization cannot contain special characters like /" }] })
+        json!({ "errors": [{ "detail": "organization cannot contain special characters like /", "code": "invalid_request" }] })
     );
 }
 
@@ -86,7 +86,7 @@ fn one_colon() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "missing github team argument; format is github:org:team" }] })
+        json!({ "errors": [{ "detail": "missing github team argument; format is github:org:team", "code": "invalid_request" }] })
     );
 }
 
@@ -105,7 +105,7 @@ fn nonexistent_team() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "could not find the github team crates-test-org/this-does-not-exist" }] })
+        json!({ "errors": [{ "detail": "could not find the github team crates-test-org/this-does-not-exist", "code": "invalid_request" }] })
     );
 }
 
@@ -155,7 +155,7 @@ fn add_team_as_non_member() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "only members of a team can add it as an owner" }] })
+        json!({ "errors": [{ "detail": "only members of a team can add it as an owner", "code": "invalid_request" }] })
     );
 }
 
@@ -180,7 +180,7 @@ fn remove_team_as_named_owner() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required." }] })
+        json!({ "errors": [{ "detail": "cannot remove all individual owners of a crate. Team member don't have permission to modify owners, so at least one individual owner is required.", "code": "invalid_request" }] })
     );
 
     token_on_both_teams
@@ -193,7 +193,7 @@ fn remove_team_as_named_owner() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing." }] })
+        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing.", "code": "crate_name_taken" }] })
     );
 }
 
@@ -220,7 +220,7 @@ fn remove_team_as_team_owner() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "team members don't have permission to modify owners" }] })
+        json!({ "errors": [{ "detail": "team members don't have permission to modify owners", "code": "not_owner" }] })
     );
 }
 
@@ -246,7 +246,7 @@ fn publish_not_owned() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing." }] })
+        json!({ "errors": [{ "detail": "this crate exists but you don't seem to be an owner. If you believe this is a mistake, perhaps you need to accept an invitation to be an owner before publishing.", "code": "crate_name_taken" }] })
     );
 }
 
@@ -293,7 +293,7 @@ fn add_owners_as_team_owner() {
     assert_eq!(response.status(), StatusCode::OK);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "team members don't have permission to modify owners" }] })
+        json!({ "errors": [{ "detail": "team members don't have permission to modify owners", "code": "not_owner" }] })
     );
 }
 
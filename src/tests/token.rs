@@ -112,7 +112,7 @@ fn create_token_invalid_request() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid new token request: Error(\"missing field `api_token`\", line: 1, column: 14)" }] })
+        json!({ "errors": [{ "detail": "invalid new token request: Error(\"missing field `api_token`\", line: 1, column: 14)", "code": "invalid_request" }] })
     );
 }
 
@@ -124,7 +124,7 @@ fn create_token_no_name() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "name must have a value" }] })
+        json!({ "errors": [{ "detail": "name must have a value", "code": "invalid_request" }] })
     );
 }
 
@@ -136,7 +136,7 @@ fn create_token_long_body() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "max content length is: 2000" }] })
+        json!({ "errors": [{ "detail": "max content length is: 2000", "code": "invalid_request" }] })
     );
 }
 
@@ -153,7 +153,7 @@ fn create_token_exceeded_tokens_per_user() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "maximum tokens per user is: 500" }] })
+        json!({ "errors": [{ "detail": "maximum tokens per user is: 500", "code": "invalid_request" }] })
     );
 }
 
@@ -203,7 +203,7 @@ fn cannot_create_token_with_token() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "cannot use an API token to create a new API token" }] })
+        json!({ "errors": [{ "detail": "cannot use an API token to create a new API token", "code": "invalid_request" }] })
     );
 }
 
@@ -298,6 +298,49 @@ fn using_token_updates_last_used_at() {
     // this test framework.
 }
 
+#[test]
+fn tokens_with_legacy_hashes_are_rehashed_on_use() {
+    use sha2::{Digest, Sha256};
+
+    let url = "/api/v1/me";
+    let (app, _, _, token) = TestApp::init().with_token();
+    let id = token.as_model().id;
+
+    let (hash, iterations) = app.db(|conn| {
+        api_tokens::table
+            .find(id)
+            .select((api_tokens::token, api_tokens::kdf_iterations))
+            .first::<(Vec<u8>, Option<i32>)>(conn)
+            .unwrap()
+    });
+    assert_some!(iterations);
+
+    // Mimic a token created before tokens were hashed with a KDF
+    let legacy = Sha256::digest(token.plaintext().as_bytes()).to_vec();
+    app.db(|conn| {
+        diesel::update(api_tokens::table.find(id))
+            .set((
+                api_tokens::token.eq(&legacy),
+                api_tokens::kdf_iterations.eq(None::<i32>),
+            ))
+            .execute(conn)
+            .unwrap();
+    });
+
+    token.get::<EncodableMe>(url).good();
+    let rehashed = app.db(|conn| {
+        api_tokens::table
+            .find(id)
+            .select((api_tokens::token, api_tokens::kdf_iterations))
+            .first::<(Vec<u8>, Option<i32>)>(conn)
+            .unwrap()
+    });
+    assert_eq!(rehashed, (hash, iterations));
+
+    // The token keeps working with the new hash
+    token.get::<EncodableMe>(url).good();
+}
+
 #[test]
 fn old_tokens_give_specific_error_message() {
     let url = "/api/v1/me";
@@ -309,6 +352,6 @@ fn old_tokens_give_specific_error_message() {
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": TOKEN_FORMAT_ERROR }] })
+        json!({ "errors": [{ "detail": TOKEN_FORMAT_ERROR, "code": "token_revoked" }] })
     );
 }
@@ -5,9 +5,12 @@ use crate::{
     OkBool, TestApp,
 };
 use cargo_registry::{
-    models::{Email, NewUser, User},
+    models::{Email, EmailEvent, NewUser, User},
     schema::crate_owners,
-    views::{EncodablePrivateUser, EncodablePublicUser, EncodableVersion, OwnedCrate},
+    views::{
+        EncodableEmailPreferences, EncodablePrivateUser, EncodablePublicUser, EncodableVersion,
+        OwnedCrate,
+    },
 };
 
 use conduit::StatusCode;
@@ -28,6 +31,7 @@ pub struct UserShowPublicResponse {
 pub struct UserShowPrivateResponse {
     pub user: EncodablePrivateUser,
     pub owned_crates: Vec<OwnedCrate>,
+    pub pending_email: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -104,7 +108,7 @@ fn access_token_needs_data() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "invalid state parameter" }] })
+        json!({ "errors": [{ "detail": "invalid state parameter", "code": "invalid_request" }] })
     );
 }
 
@@ -288,7 +292,7 @@ fn following() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "page indexing starts from 1, page 0 is invalid" }] })
+        json!({ "errors": [{ "detail": "page indexing starts from 1, page 0 is invalid", "code": "invalid_request" }] })
     );
 }
 
@@ -459,10 +463,57 @@ fn test_email_get_and_put() {
 
     user.update_email("mango@mangos.mango");
 
+    // The verified address is kept until the change has been confirmed
+    let json = user.show_me();
+    assert_eq!(json.user.email.unwrap(), "something@example.com");
+    assert!(json.user.email_verified);
+    assert_eq!(json.pending_email.unwrap(), "mango@mangos.mango");
+}
+
+#[test]
+fn changing_a_verified_email_requires_confirming_both_addresses() {
+    use cargo_registry::models::EmailChange;
+
+    let (app, _anon, user) = TestApp::init().with_user();
+    user.update_email("mango@mangos.mango");
+
+    let change =
+        app.db(|conn| assert_some!(EmailChange::pending_for(conn, user.as_model().id).unwrap()));
+
+    user.confirm_email(&change.new_email_token);
+    let json = user.show_me();
+    assert_eq!(json.user.email.unwrap(), "something@example.com");
+    assert_eq!(json.pending_email.unwrap(), "mango@mangos.mango");
+
+    user.confirm_email(&change.old_email_token);
+
+    // Changing the address revoked the sessions of the user
+    user.get::<()>("/api/v1/me").assert_forbidden();
+    let user = MockCookieUser::new(&app, user.as_model().clone());
     let json = user.show_me();
     assert_eq!(json.user.email.unwrap(), "mango@mangos.mango");
-    assert!(!json.user.email_verified);
-    assert!(json.user.email_verification_sent);
+    assert!(json.user.email_verified);
+    assert_none!(json.pending_email);
+
+    // The tokens can't be used again
+    let url = format!("/api/v1/confirm/{}", change.old_email_token);
+    let response = user.put::<()>(&url, &[]);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn email_changes_are_rate_limited_and_can_be_cancelled() {
+    let (_app, _anon, user) = TestApp::init().with_user();
+    user.update_email("mango@mangos.mango");
+
+    let response = user.update_email_more_control(user.as_model().id, Some("kiwi@kiwis.kiwi"));
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    // Setting the current address again cancels the change
+    user.update_email("something@example.com");
+    let json = user.show_me();
+    assert_eq!(json.user.email.unwrap(), "something@example.com");
+    assert_none!(json.pending_email);
 }
 
 /*  Given a crates.io user, check to make sure that the user
@@ -484,14 +535,14 @@ fn test_empty_email_not_added() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "empty email rejected" }] })
+        json!({ "errors": [{ "detail": "empty email rejected", "code": "invalid_request" }] })
     );
 
     let response = user.update_email_more_control(model.id, None);
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "empty email rejected" }] })
+        json!({ "errors": [{ "detail": "empty email rejected", "code": "invalid_request" }] })
     );
 }
 
@@ -514,7 +565,7 @@ fn test_other_users_cannot_change_my_email() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "current user does not match requested user" }] })
+        json!({ "errors": [{ "detail": "current user does not match requested user", "code": "invalid_request" }] })
     );
 
     let response = anon.update_email_more_control(
@@ -524,7 +575,45 @@ fn test_other_users_cannot_change_my_email() {
     assert_eq!(response.status(), StatusCode::FORBIDDEN);
     assert_eq!(
         response.json(),
-        json!({ "errors": [{ "detail": "must be logged in to perform that action" }] })
+        json!({ "errors": [{ "detail": "must be logged in to perform that action", "code": "unauthenticated" }] })
+    );
+}
+
+#[test]
+fn users_can_edit_their_profile() {
+    let (_, anon, user) = TestApp::init().with_user();
+    let model = user.as_model();
+    let url = format!("/api/v1/users/{}", model.id);
+
+    let body = json!({"user": {
+        "display_name": "  Ferris the Crab ",
+        "bio": "Loves Rust.\r\nAnd the sea.",
+        "website": "https://ferris.example.com",
+        "location": "The sea",
+    }});
+    user.put::<OkBool>(&url, body.to_string().as_bytes()).good();
+
+    let json: UserShowPublicResponse = anon
+        .get(&format!("/api/v1/users/{}", model.gh_login))
+        .good();
+    assert_eq!(json.user.display_name.unwrap(), "Ferris the Crab");
+    assert_eq!(json.user.bio.unwrap(), "Loves Rust.\nAnd the sea.");
+    assert_eq!(json.user.website.unwrap(), "https://ferris.example.com/");
+    assert_eq!(json.user.location.unwrap(), "The sea");
+
+    // Fields that are left out stay unchanged, `null` clears them
+    let body = json!({"user": { "location": null }});
+    user.put::<OkBool>(&url, body.to_string().as_bytes()).good();
+    let json = user.show_me();
+    assert_eq!(json.user.display_name.unwrap(), "Ferris the Crab");
+    assert_none!(json.user.location);
+
+    let body = json!({"user": { "website": "javascript:alert(1)" }});
+    let response = user.put::<()>(&url, body.to_string().as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json(),
+        json!({ "errors": [{ "detail": "the website must be an http or https URL", "code": "invalid_request" }] })
     );
 }
 
@@ -568,6 +657,57 @@ fn test_confirm_user_email() {
     assert!(json.user.email_verification_sent);
 }
 
+#[test]
+fn expired_email_tokens_are_rejected() {
+    use cargo_registry::schema::emails;
+    use chrono::{Duration, Utc};
+
+    let (app, _) = TestApp::init().empty();
+    let (user, token) = app.db(|conn| {
+        let u = new_user("arbitrary_username")
+            .create_or_update(Some("potato3@example.com"), conn)
+            .unwrap();
+        let token: String = diesel::update(Email::belonging_to(&u))
+            .set(emails::token_generated_at.eq(Utc::now().naive_utc() - Duration::days(3)))
+            .returning(emails::token)
+            .get_result(conn)
+            .unwrap();
+        (MockCookieUser::new(&app, u), token)
+    });
+
+    let url = format!("/api/v1/confirm/{}", token);
+    let response = user.put::<()>(&url, &[]);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(!user.show_me().user.email_verified);
+}
+
+#[test]
+fn resending_the_confirmation_email_is_rate_limited() {
+    use cargo_registry::schema::emails;
+    use chrono::{Duration, Utc};
+
+    let (app, _) = TestApp::init().empty();
+    let user = app.db(|conn| {
+        let u = new_user("arbitrary_username")
+            .create_or_update(Some("potato4@example.com"), conn)
+            .unwrap();
+        MockCookieUser::new(&app, u)
+    });
+    let url = format!("/api/v1/users/{}/resend", user.as_model().id);
+
+    // The email was just sent when the user signed up
+    let response = user.put::<OkBool>(&url, &[]);
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    app.db(|conn| {
+        diesel::update(Email::belonging_to(user.as_model()))
+            .set(emails::token_generated_at.eq(Utc::now().naive_utc() - Duration::minutes(10)))
+            .execute(conn)
+            .unwrap();
+    });
+    user.put::<OkBool>(&url, &[]).good();
+}
+
 /* Given a user who existed before we added email confirmation,
    test that `email_verification_sent` is false so that we don't
    make the user think we've sent an email when we haven't.
@@ -736,3 +876,89 @@ fn test_update_email_notifications_not_owned() {
     // There should be no change to the `email_notifications` value for a crate not belonging to me
     assert!(email_notifications);
 }
+
+#[derive(Deserialize)]
+struct EmailPreferencesResponse {
+    email_preferences: EncodableEmailPreferences,
+}
+
+#[test]
+fn email_preferences_are_changed_one_by_one() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let url = "/api/v1/me/email_preferences";
+
+    let json: EmailPreferencesResponse = user.get(url).good();
+    let defaults = EncodableEmailPreferences {
+        owner_invitations: true,
+        new_versions: true,
+        security_notices: true,
+        moderation: true,
+        weekly_digest: false,
+    };
+    assert_eq!(json.email_preferences, defaults);
+
+    let json: EmailPreferencesResponse = user.put(url, br#"{"new_versions":false}"#).good();
+    assert!(!json.email_preferences.new_versions);
+    assert!(json.email_preferences.moderation);
+    let json: EmailPreferencesResponse = user.put(url, br#"{"moderation":false}"#).good();
+    assert!(!json.email_preferences.new_versions);
+    assert!(!json.email_preferences.moderation);
+
+    let model = user.as_model();
+    app.db(|conn| {
+        assert_none!(model
+            .notification_email(conn, EmailEvent::Moderation)
+            .unwrap());
+        assert_some!(model
+            .notification_email(conn, EmailEvent::OwnerInvitations)
+            .unwrap());
+    });
+
+    let response = user.put::<()>(url, br#"{"newsletter":false}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    anon.get::<()>(url).assert_forbidden();
+}
+
+#[test]
+fn weekly_digests_are_sent_to_subscribers_once_a_week() {
+    use cargo_registry::background_jobs::Job;
+    use cargo_registry::schema::email_preferences;
+
+    let (app, _, user) = TestApp::full().with_user();
+    let subscriber = app.db_new_user("subscriber");
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_digest", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+    subscriber
+        .put::<OkBool>("/api/v1/crates/foo_digest/follow", b"")
+        .good();
+    subscriber
+        .put::<EmailPreferencesResponse>(
+            "/api/v1/me/email_preferences",
+            br#"{"weekly_digest":true}"#,
+        )
+        .good();
+
+    let sent_at = || {
+        app.db(|conn| {
+            email_preferences::table
+                .find(subscriber.as_model().id)
+                .select(email_preferences::weekly_digest_sent_at)
+                .first::<Option<chrono::NaiveDateTime>>(conn)
+                .unwrap()
+        })
+    };
+    assert_none!(sent_at());
+
+    app.db(|conn| Job::SendWeeklyDigests {}.enqueue(conn).unwrap());
+    app.run_pending_background_jobs();
+    let first_digest = assert_some!(sent_at());
+
+    // The next digest is only due in a week
+    app.db(|conn| Job::SendWeeklyDigests {}.enqueue(conn).unwrap());
+    app.run_pending_background_jobs();
+    assert_eq!(sent_at(), Some(first_digest));
+}
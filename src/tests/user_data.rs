@@ -0,0 +1,238 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::models::{CrateOwner, User};
+use cargo_registry::schema::{crate_owners, emails, versions};
+use cargo_registry::views::EncodableDataExport;
+
+use conduit::{header, StatusCode};
+use diesel::prelude::*;
+use std::io::Read;
+
+#[derive(Deserialize)]
+struct DataExportResponse {
+    data_export: Option<EncodableDataExport>,
+}
+
+#[derive(Deserialize)]
+struct AdminDeleteResponse {
+    orphaned_crates: Vec<String>,
+}
+
+fn add_user_to_crate(conn: &PgConnection, crate_id: i32, owner: &User, created_by: &User) {
+    diesel::insert_into(crate_owners::table)
+        .values(&CrateOwner {
+            crate_id,
+            owner_id: owner.id,
+            created_by: created_by.id,
+            owner_kind: 0,
+            email_notifications: true,
+        })
+        .execute(conn)
+        .unwrap();
+}
+
+/// Returns the contents of `name` inside the exported tarball
+fn read_archive_file(archive: &[u8], name: &str) -> serde_json::Value {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+    let path = format!("crates-io-data-export/{}.json", name);
+    for entry in archive.entries().unwrap() {
+        let mut entry = entry.unwrap();
+        if entry.path().unwrap().to_str() == Some(&path) {
+            let mut contents = String::new();
+            entry.read_to_string(&mut contents).unwrap();
+            return serde_json::from_str(&contents).unwrap();
+        }
+    }
+    panic!("{} not found in archive", path);
+}
+
+#[test]
+fn data_export_requires_authentication() {
+    let (_, anon) = TestApp::init().empty();
+
+    anon.get::<()>("/api/v1/me/data_export").assert_forbidden();
+    anon.put::<()>("/api/v1/me/data_export", b"")
+        .assert_forbidden();
+    anon.get::<()>("/api/v1/me/data_export/download")
+        .assert_forbidden();
+}
+
+#[test]
+fn export_and_download_user_data() {
+    let (app, _) = TestApp::full().empty();
+    let user = app.db_new_user("foo");
+    app.db(|conn| {
+        CrateBuilder::new("foo_export", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let json: DataExportResponse = user.get("/api/v1/me/data_export").good();
+    assert_none!(json.data_export);
+    user.get::<()>("/api/v1/me/data_export/download")
+        .assert_not_found();
+
+    let json: DataExportResponse = user.put("/api/v1/me/data_export", b"").good();
+    let data_export = json.data_export.unwrap();
+    assert_none!(data_export.completed_at);
+    assert_none!(data_export.download_path);
+
+    // Requesting again while the export is pending doesn't start a new one
+    let json: DataExportResponse = user.put("/api/v1/me/data_export", b"").good();
+    assert_eq!(json.data_export.unwrap().id, data_export.id);
+
+    app.run_pending_background_jobs();
+
+    let json: DataExportResponse = user.get("/api/v1/me/data_export").good();
+    let data_export = json.data_export.unwrap();
+    assert_some!(data_export.completed_at);
+    assert_some_eq!(
+        data_export.download_path.as_deref(),
+        "/api/v1/me/data_export/download"
+    );
+
+    let response = user.get::<()>("/api/v1/me/data_export/download");
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_some_eq!(response.header(header::CONTENT_TYPE), "application/gzip");
+    let archive = response.into_bytes();
+
+    let account = read_archive_file(&archive, "account");
+    assert_eq!(account["login"], "foo");
+    let emails = read_archive_file(&archive, "emails");
+    assert_eq!(emails[0]["email"], "something@example.com");
+    assert_eq!(
+        read_archive_file(&archive, "owned_crates"),
+        json!(["foo_export"])
+    );
+    let published = read_archive_file(&archive, "published_versions");
+    assert_eq!(published[0]["crate"], "foo_export");
+    assert_eq!(published[0]["num"], "1.0.0");
+}
+
+#[test]
+fn delete_account_requires_confirmation() {
+    let (_, _, user) = TestApp::init().with_user();
+
+    let response = user.delete::<()>("/api/v1/me");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let body = json!({ "login": "someone-else" }).to_string();
+    let response = user.delete_with_body::<()>("/api/v1/me", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn delete_account_is_blocked_by_sole_owned_crates() {
+    let (app, _, user) = TestApp::init().with_user();
+    app.db(|conn| {
+        CrateBuilder::new("foo_sole_owner", user.as_model().id).expect_build(conn);
+    });
+
+    let body = json!({ "login": user.as_model().gh_login }).to_string();
+    let response = user.delete_with_body::<()>("/api/v1/me", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(response.json()["errors"][0]["detail"]
+        .as_str()
+        .unwrap()
+        .contains("foo_sole_owner"));
+}
+
+#[test]
+fn delete_account_anonymizes_personal_data() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+    let user_model = user.as_model().clone();
+    let version_id = app.db(|conn| {
+        let krate = CrateBuilder::new("foo_deleted_owner", user_model.id)
+            .version("1.0.0")
+            .expect_build(conn);
+        add_user_to_crate(conn, krate.id, other.as_model(), &user_model);
+        versions::table
+            .filter(versions::crate_id.eq(krate.id))
+            .select(versions::id)
+            .first::<i32>(conn)
+            .unwrap()
+    });
+
+    let body = json!({ "login": user_model.gh_login }).to_string();
+    let response = user.delete_with_body::<()>("/api/v1/me", body.as_bytes());
+    assert_eq!(response.status(), StatusCode::OK);
+
+    app.db(|conn| {
+        let deleted = User::find(conn, user_model.id).unwrap();
+        assert_eq!(deleted.gh_login, format!("deleted-user-{}", user_model.id));
+        assert_eq!(deleted.gh_id, -1);
+        assert_none!(&deleted.name);
+        assert_none!(&deleted.gh_avatar);
+        assert_err!(deleted.check_account_lock());
+
+        let emails: i64 = emails::table
+            .filter(emails::user_id.eq(user_model.id))
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(emails, 0);
+
+        // Provenance is kept
+        let published_by: Option<i32> = versions::table
+            .find(version_id)
+            .select(versions::published_by)
+            .first(conn)
+            .unwrap();
+        assert_some_eq!(published_by, user_model.id);
+    });
+
+    let json = anon.show_crate("foo_deleted_owner");
+    assert_eq!(json.krate.name, "foo_deleted_owner");
+    let owners = anon.show_crate_owners("foo_deleted_owner");
+    assert_eq!(owners.users.len(), 1);
+    assert_eq!(owners.users[0].login, "other");
+
+    // The session no longer works
+    user.get::<()>("/api/v1/me").assert_forbidden();
+}
+
+#[test]
+fn non_admins_cannot_delete_other_accounts() {
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    let url = format!("/api/v1/admin/users/{}", other.as_model().id);
+    user.delete::<()>(&url).assert_forbidden();
+    let url = format!("/api/v1/admin/users/{}/data_export", other.as_model().id);
+    user.put::<()>(&url, b"").assert_forbidden();
+}
+
+#[test]
+fn admins_can_delete_accounts_with_sole_owned_crates() {
+    let (app, _, user) = TestApp::init().with_user();
+    let admin = app.db_new_admin_user("admin");
+    app.db(|conn| {
+        CrateBuilder::new("foo_orphaned", user.as_model().id).expect_build(conn);
+    });
+
+    let url = format!("/api/v1/admin/users/{}", user.as_model().id);
+    let json: AdminDeleteResponse = admin.delete(&url).good();
+    assert_eq!(json.orphaned_crates, vec!["foo_orphaned"]);
+
+    user.get::<()>("/api/v1/me").assert_forbidden();
+}
+
+#[test]
+fn admins_can_export_user_data() {
+    let (app, _) = TestApp::full().empty();
+    let admin = app.db_new_admin_user("admin");
+    let user = app.db_new_user("foo");
+    let url = format!("/api/v1/admin/users/{}/data_export", user.as_model().id);
+
+    let json: DataExportResponse = admin.put(&url, b"").good();
+    assert_some!(json.data_export);
+    app.run_pending_background_jobs();
+
+    let json: DataExportResponse = admin.get(&url).good();
+    let download_path = json.data_export.unwrap().download_path.unwrap();
+    assert_eq!(download_path, format!("{}/download", url));
+
+    let archive = admin.get::<()>(&download_path).into_bytes();
+    assert_eq!(read_archive_file(&archive, "account")["login"], "foo");
+}
@@ -0,0 +1,80 @@
+use crate::builders::{CrateBuilder, VersionBuilder};
+use crate::util::{RequestHelper, TestApp};
+use crate::OkBool;
+use cargo_registry::views::EncodableVersion;
+
+use conduit::StatusCode;
+
+#[derive(Deserialize)]
+struct Updates {
+    versions: Vec<EncodableVersion>,
+}
+
+#[derive(Deserialize)]
+struct Following {
+    following: bool,
+    followable: bool,
+}
+
+#[test]
+fn followed_users_releases_are_in_updates() {
+    let (app, _, user) = TestApp::init().with_user();
+    let author = app.db_new_user("author");
+    let author_id = author.as_model().id;
+
+    user.put::<OkBool>("/api/v1/users/author/follow", b"")
+        .good();
+    let json: Following = user.get("/api/v1/users/author/following").good();
+    assert!(json.following);
+    assert!(json.followable);
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_followed", author_id)
+            .version(VersionBuilder::new("1.0.0"))
+            .expect_build(conn);
+    });
+
+    let json: Updates = user.get("/api/v1/me/updates").good();
+    assert_eq!(json.versions.len(), 1);
+    assert_eq!(json.versions[0].krate, "foo_followed");
+
+    user.delete::<OkBool>("/api/v1/users/author/follow").good();
+    let json: Updates = user.get("/api/v1/me/updates").good();
+    assert_eq!(json.versions.len(), 0);
+}
+
+#[test]
+fn users_cannot_follow_themselves() {
+    let (_, _, user) = TestApp::init().with_user();
+    let url = format!("/api/v1/users/{}/follow", user.as_model().gh_login);
+
+    let response = user.put::<()>(&url, b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "you can't follow yourself"
+    );
+}
+
+#[test]
+fn users_can_opt_out_of_being_followed() {
+    let (app, _, user) = TestApp::init().with_user();
+    let author = app.db_new_user("author");
+
+    user.put::<OkBool>("/api/v1/users/author/follow", b"")
+        .good();
+    author
+        .put::<OkBool>("/api/v1/me/privacy", br#"{"followable": false}"#)
+        .good();
+
+    let json: Following = user.get("/api/v1/users/author/following").good();
+    assert!(!json.following);
+    assert!(!json.followable);
+
+    let response = user.put::<()>("/api/v1/users/author/follow", b"");
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "this user can't be followed"
+    );
+}
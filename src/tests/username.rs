@@ -0,0 +1,105 @@
+use crate::builders::CrateBuilder;
+use crate::util::{RequestHelper, TestApp};
+use cargo_registry::views::EncodablePublicUser;
+
+use conduit::{header, StatusCode};
+
+#[derive(Deserialize)]
+struct UserResponse {
+    user: EncodablePublicUser,
+}
+
+#[test]
+fn old_logins_redirect_to_the_new_username() {
+    let (_, anon, user) = TestApp::init().with_user();
+    let gh_login = user.as_model().gh_login.clone();
+
+    let json: UserResponse = user
+        .put("/api/v1/me/username", br#"{"username": "ferris_rs"}"#)
+        .good();
+    assert_eq!(json.user.login, "ferris_rs");
+
+    let json: UserResponse = anon.get("/api/v1/users/Ferris_RS").good();
+    assert_eq!(json.user.login, "ferris_rs");
+
+    let response = anon.get::<()>(&format!("/api/v1/users/{}", gh_login));
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response.header(header::LOCATION).unwrap(),
+        "/api/v1/users/ferris_rs"
+    );
+
+    // Usernames can only be changed once in a while
+    let response = user.put::<()>("/api/v1/me/username", br#"{"username": "crab_rs"}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn usernames_must_be_valid_and_available() {
+    use cargo_registry::schema::username_history;
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+
+    let (app, _, user) = TestApp::init().with_user();
+    let other = app.db_new_user("other");
+
+    for invalid in &["ferris", "_crab", "crab-", "crab rs"] {
+        let body = json!({ "username": invalid });
+        let response = user.put::<()>("/api/v1/me/username", body.to_string().as_bytes());
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    other
+        .put::<UserResponse>("/api/v1/me/username", br#"{"username": "other_crab"}"#)
+        .good();
+    let response = user.put::<()>("/api/v1/me/username", br#"{"username": "OTHER_crab"}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert_eq!(
+        response.json()["errors"][0]["detail"],
+        "the username `OTHER_crab` is not available"
+    );
+
+    // Previous usernames are reserved during the grace period
+    app.db(|conn| {
+        diesel::update(username_history::table)
+            .set(username_history::changed_at.eq(Utc::now().naive_utc() - Duration::days(31)))
+            .execute(conn)
+            .unwrap();
+    });
+    other
+        .put::<UserResponse>("/api/v1/me/username", br#"{"username": "other_ferris"}"#)
+        .good();
+    let response = user.put::<()>("/api/v1/me/username", br#"{"username": "other_crab"}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[test]
+fn usernames_dont_shadow_github_logins_of_later_users() {
+    use cargo_registry::schema::crate_owner_invitations;
+    use diesel::prelude::*;
+
+    let (app, anon, user, token) = TestApp::init().with_token();
+    app.db(|conn| CrateBuilder::new("foo_owned", user.as_model().id).expect_build(conn));
+    user.put::<UserResponse>("/api/v1/me/username", br#"{"username": "new_user"}"#)
+        .good();
+
+    // The GitHub login `newcomer` can't be taken as a username, so the user
+    // signing up with it later is the one that is found by it
+    let response = user.put::<()>("/api/v1/me/username", br#"{"username": "newcomer"}"#);
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let newcomer = app.db_new_user("newcomer");
+
+    let json: UserResponse = anon.get("/api/v1/users/newcomer").good();
+    assert_eq!(json.user.id, newcomer.as_model().id);
+    let json: UserResponse = anon.get("/api/v1/users/new_user").good();
+    assert_eq!(json.user.id, user.as_model().id);
+
+    token.add_named_owner("foo_owned", "newcomer").good();
+    let invited: Vec<i32> = app.db(|conn| {
+        crate_owner_invitations::table
+            .select(crate_owner_invitations::invited_user_id)
+            .load(conn)
+            .unwrap()
+    });
+    assert_eq!(invited, vec![newcomer.as_model().id]);
+}
@@ -23,7 +23,7 @@ use crate::{
     builders::PublishBuilder, CategoryListResponse, CategoryResponse, CrateList, CrateResponse,
     GoodCrate, OkBool, OwnersResponse, VersionResponse,
 };
-use cargo_registry::models::{ApiToken, CreatedApiToken, User};
+use cargo_registry::models::{ApiToken, CreatedApiToken, User, UserSession};
 
 use conduit::{BoxError, Handler, Method};
 use conduit_cookie::SessionMiddleware;
@@ -43,19 +43,19 @@ pub use test_app::TestApp;
 /// include cookie-based authentication.
 ///
 /// ```
-/// let cookie = encode_session_header(session_key, user_id);
+/// let cookie = encode_session_header(session_key, session_token);
 /// request.header(header::COOKIE, &cookie);
 /// ```
 ///
 /// The implementation matches roughly what is happening inside of the
 /// `SessionMiddleware` from `conduit_cookie`.
-pub fn encode_session_header(session_key: &str, user_id: i32) -> String {
+pub fn encode_session_header(session_key: &str, session_token: &str) -> String {
     let cookie_name = "cargo_session";
     let cookie_key = cookie::Key::derive_from(session_key.as_bytes());
 
     // build session data map
     let mut map = HashMap::new();
-    map.insert("user_id".into(), user_id.to_string());
+    map.insert("session_token".into(), session_token.to_string());
 
     // encode the map into a cookie value string
     let encoded = SessionMiddleware::encode(&map);
@@ -115,6 +115,14 @@ pub trait RequestHelper {
         self.run(request)
     }
 
+    /// Issue a POST request
+    #[track_caller]
+    fn post<T>(&self, path: &str, body: &[u8]) -> Response<T> {
+        let mut request = self.request_builder(Method::POST, path);
+        request.with_body(body);
+        self.run(request)
+    }
+
     /// Issue a DELETE request
     #[track_caller]
     fn delete<T>(&self, path: &str) -> Response<T> {
@@ -204,17 +212,18 @@ impl RequestHelper for MockAnonymousUser {
 
 /// A type that can generate cookie authenticated requests
 ///
-/// The `user.id` value is directly injected into a request extension and thus the conduit_cookie
-/// session logic is not exercised.
+/// The token of a session created in the database is encoded into the cookie the same way the
+/// conduit_cookie session logic does.
 pub struct MockCookieUser {
     app: TestApp,
     user: User,
+    session_token: String,
 }
 
 impl RequestHelper for MockCookieUser {
     fn request_builder(&self, method: Method, path: &str) -> MockRequest {
         let session_key = &self.app.as_inner().session_key;
-        let cookie = encode_session_header(session_key, self.user.id);
+        let cookie = encode_session_header(session_key, &self.session_token);
 
         let mut request = req(method, path);
         request.header(header::COOKIE, &cookie);
@@ -227,11 +236,15 @@ impl RequestHelper for MockCookieUser {
 }
 
 impl MockCookieUser {
-    /// Creates an instance from a database `User` instance
+    /// Creates an instance from a database `User` instance, with a new session
+    ///
+    /// This method updates the database directly
     pub fn new(app: &TestApp, user: User) -> Self {
+        let session = app.db(|conn| UserSession::create(conn, user.id, "").unwrap());
         Self {
             app: app.clone(),
             user,
+            session_token: session.plaintext,
         }
     }
 
@@ -43,6 +43,18 @@ impl<T> Response<T> {
         json(&mut self.response)
     }
 
+    /// Consume the response body and return it without any conversion
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        take_body(&mut self.response).into_owned()
+    }
+
+    pub fn header(&self, name: header::HeaderName) -> Option<&str> {
+        self.response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+
     pub fn status(&self) -> StatusCode {
         self.response.status()
     }
@@ -79,15 +91,7 @@ fn json<T>(r: &mut AppResponse) -> T
 where
     for<'de> T: serde::Deserialize<'de>,
 {
-    use conduit::Body::*;
-
-    let mut body = Body::empty();
-    std::mem::swap(r.body_mut(), &mut body);
-    let body: std::borrow::Cow<'static, [u8]> = match body {
-        Static(slice) => slice.into(),
-        Owned(vec) => vec.into(),
-        File(_) => unimplemented!(),
-    };
+    let body = take_body(r);
 
     assert_eq!(
         r.headers()
@@ -111,3 +115,15 @@ where
         Err(e) => panic!("failed to decode: {:?}", e),
     }
 }
+
+fn take_body(r: &mut AppResponse) -> std::borrow::Cow<'static, [u8]> {
+    use conduit::Body::*;
+
+    let mut body = Body::empty();
+    std::mem::swap(r.body_mut(), &mut body);
+    match body {
+        Static(slice) => slice.into(),
+        Owned(vec) => vec.into(),
+        File(_) => unimplemented!(),
+    }
+}
@@ -1,9 +1,12 @@
 use super::{MockAnonymousUser, MockCookieUser, MockTokenUser};
 use crate::{env, record};
 use cargo_registry::{
-    background_jobs::Environment,
-    db::DieselPool,
+    background_jobs::{Environment, Queue},
     git::{Credentials, RepositoryConfig},
+    license::InvalidLicensePolicy,
+    middleware::security_headers::SecurityHeaders,
+    middleware::session_cookie::SessionCookie,
+    swirl::Runner,
     App, Config, Env, Replica, Uploader,
 };
 use std::{rc::Rc, sync::Arc, time::Duration};
@@ -12,7 +15,6 @@ use cargo_registry::git::Repository as WorkerRepository;
 use diesel::{Connection, PgConnection};
 use git2::Repository as UpstreamRepository;
 use reqwest::{blocking::Client, Proxy};
-use swirl::Runner;
 use url::Url;
 
 struct TestAppInner {
@@ -21,13 +23,13 @@ struct TestAppInner {
     _bomb: Option<record::Bomb>,
     middle: conduit_middleware::MiddlewareBuilder,
     index: Option<UpstreamRepository>,
-    runner: Option<Runner<Environment, DieselPool>>,
+    runner: Option<Runner>,
 }
 
 impl Drop for TestAppInner {
     fn drop(&mut self) {
+        use cargo_registry::schema::background_jobs::dsl::*;
         use diesel::prelude::*;
-        use swirl::schema::background_jobs::dsl::*;
 
         // Avoid a double-panic if the test is already failing
         if std::thread::panicking() {
@@ -68,6 +70,7 @@ impl TestApp {
             bomb: None,
             index: None,
             build_job_runner: false,
+            index_extensions: false,
         }
     }
 
@@ -115,10 +118,26 @@ impl TestApp {
                 .unwrap();
             user
         });
-        MockCookieUser {
-            app: self.clone(),
-            user,
-        }
+        MockCookieUser::new(self, user)
+    }
+
+    /// Create a new admin user with a verified email address in the database and return a mock
+    /// user session
+    ///
+    /// This method updates the database directly
+    pub fn db_new_admin_user(&self, username: &str) -> MockCookieUser {
+        use cargo_registry::schema::users;
+        use diesel::prelude::*;
+
+        // Changing the flag revokes the session of `db_new_user`
+        let user = self.db_new_user(username);
+        let user = self.db(|conn| {
+            diesel::update(users::table.find(user.as_model().id))
+                .set(users::is_admin.eq(true))
+                .get_result(conn)
+                .unwrap()
+        });
+        MockCookieUser::new(self, user)
     }
 
     /// Obtain a reference to the upstream repository ("the index")
@@ -175,6 +194,7 @@ pub struct TestAppBuilder {
     bomb: Option<record::Bomb>,
     index: Option<UpstreamRepository>,
     build_job_runner: bool,
+    index_extensions: bool,
 }
 
 impl TestAppBuilder {
@@ -188,23 +208,25 @@ impl TestAppBuilder {
             let repository_config = RepositoryConfig {
                 index_location: Url::from_file_path(&git::bare()).unwrap(),
                 credentials: Credentials::Missing,
+                index_extensions: self.index_extensions,
             };
             let index = WorkerRepository::open(&repository_config).expect("Could not clone index");
             let environment = Environment::new(
                 index,
                 app.config.uploader.clone(),
                 app.http_client().clone(),
+                app.response_cache.clone(),
             );
 
-            Some(
-                Runner::builder(environment)
-                    // We only have 1 connection in tests, so trying to run more than
-                    // 1 job concurrently will just block
-                    .thread_count(1)
-                    .connection_pool(app.primary_database.clone())
-                    .job_start_timeout(Duration::from_secs(5))
-                    .build(),
-            )
+            let mut runner = Runner::builder(environment)
+                .connection_pool(app.primary_database.clone())
+                .job_start_timeout(Duration::from_secs(5));
+            // We only have 1 connection in tests, so trying to run more than
+            // 1 job per queue concurrently will just block
+            for &queue in Queue::ALL {
+                runner = runner.concurrency(queue, 1);
+            }
+            Some(runner.build())
         } else {
             None
         };
@@ -272,6 +294,12 @@ impl TestAppBuilder {
         self.build_job_runner = true;
         self
     }
+
+    /// Write the `IndexExtensions` of index entries, like with `INDEX_EXTENSIONS` set
+    pub fn with_index_extensions(mut self) -> Self {
+        self.index_extensions = true;
+        self
+    }
 }
 
 pub fn init_logger() {
@@ -283,8 +311,8 @@ pub fn init_logger() {
 }
 
 fn simple_config() -> Config {
-    let uploader = Uploader::S3 {
-        bucket: s3::Bucket::new(
+    let uploader = Uploader::s3(
+        s3::Bucket::new(
             String::from("alexcrichton-test"),
             None,
             dotenv::var("S3_ACCESS_KEY").unwrap_or_default(),
@@ -293,11 +321,12 @@ fn simple_config() -> Config {
             // sniff/record it, but everywhere else we use https
             "http",
         ),
-        cdn: None,
-    };
+        None,
+    );
 
     Config {
         uploader,
+        download_region_header: String::from("CloudFront-Viewer-Country"),
         session_key: "test this has to be over 32 bytes long".to_string(),
         gh_client_id: dotenv::var("GH_CLIENT_ID").unwrap_or_default(),
         gh_client_secret: dotenv::var("GH_CLIENT_SECRET").unwrap_or_default(),
@@ -305,6 +334,7 @@ fn simple_config() -> Config {
         db_url: env("TEST_DATABASE_URL"),
         replica_db_url: None,
         env: Env::Test,
+        dev_mode: false,
         max_upload_size: 3000,
         max_unpack_size: 2000,
         mirror: Replica::Primary,
@@ -312,9 +342,21 @@ fn simple_config() -> Config {
         // sniff/record it, but everywhere else we use https
         api_protocol: String::from("http"),
         publish_rate_limit: Default::default(),
+        login_rate_limit: Default::default(),
         blocked_traffic: Default::default(),
         domain_name: "crates.io".into(),
         allowed_origins: Vec::new(),
+        upload_scanners: Vec::new(),
+        metrics_authorization_token: None,
+        log_format: Default::default(),
+        cache_warm_up_crates: 0,
+        redis_url: None,
+        response_cache_ttl: 0,
+        security_headers: SecurityHeaders::new(Env::Test, None),
+        session_cookie: SessionCookie::new(Env::Test),
+        first_publish_hold: None,
+        quotas: Default::default(),
+        invalid_license_policy: InvalidLicensePolicy::Reject,
     }
 }
 
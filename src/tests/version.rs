@@ -2,8 +2,13 @@ use crate::{
     builders::{CrateBuilder, PublishBuilder, VersionBuilder},
     RequestHelper, TestApp, VersionResponse,
 };
-use cargo_registry::{models::Version, schema::versions, views::EncodableVersion};
+use cargo_registry::{
+    models::Version,
+    schema::versions,
+    views::{EncodableVersion, EncodableVersionProvenance},
+};
 
+use conduit::{header, StatusCode};
 use diesel::prelude::*;
 use serde_json::Value;
 
@@ -102,6 +107,57 @@ fn show_by_crate_name_and_semver_no_published_by() {
     assert_none!(json.version.published_by);
 }
 
+#[derive(Deserialize)]
+struct ProvenanceResponse {
+    provenance: EncodableVersionProvenance,
+}
+
+#[test]
+fn provenance_shows_the_publishing_token_and_later_actions() {
+    let (_, anon, user, token) = TestApp::full().with_token();
+
+    token
+        .enqueue_publish(PublishBuilder::new("foo_provenance").version("1.0.0"))
+        .good();
+    token
+        .delete::<Value>("/api/v1/crates/foo_provenance/1.0.0/yank")
+        .good();
+
+    let url = "/api/v1/crates/foo_provenance/1.0.0/provenance";
+    let json: ProvenanceResponse = anon.get(url).good();
+    let provenance = json.provenance;
+    assert_eq!(provenance.krate, "foo_provenance");
+    assert_eq!(
+        provenance.published_by.unwrap().login,
+        user.as_model().gh_login
+    );
+    assert_eq!(provenance.token.unwrap().name, token.as_model().name);
+    assert_some!(provenance.ip_class);
+    let actions: Vec<_> = provenance
+        .actions
+        .iter()
+        .map(|a| a.action.as_str())
+        .collect();
+    assert_eq!(actions, vec!["publish", "yank"]);
+}
+
+#[test]
+fn provenance_of_versions_without_records() {
+    let (app, anon, user) = TestApp::init().with_user();
+
+    app.db(|conn| {
+        CrateBuilder::new("foo_provenance", user.as_model().id)
+            .version("1.0.0")
+            .expect_build(conn);
+    });
+
+    let url = "/api/v1/crates/foo_provenance/1.0.0/provenance";
+    let json: ProvenanceResponse = anon.get(url).good();
+    assert_none!(json.provenance.token);
+    assert_none!(json.provenance.ip_class);
+    assert!(json.provenance.actions.is_empty());
+}
+
 #[test]
 fn authors() {
     let (app, anon, user) = TestApp::init().with_user();
@@ -132,6 +188,33 @@ fn record_rerendered_readme_time() {
     });
 }
 
+#[test]
+fn readme_sources_are_only_served_once_stored() {
+    let (app, anon, user) = TestApp::init().with_user();
+    let user = user.as_model();
+
+    let version_id = app.db(|conn| {
+        let c = CrateBuilder::new("foo_readme_source", user.id).expect_build(conn);
+        let version = VersionBuilder::new("1.0.0").expect_build(c.id, user.id, conn);
+        Version::record_readme_rendering(version.id, conn).unwrap();
+        version.id
+    });
+
+    let url = "/api/v1/crates/foo_readme_source/1.0.0/readme/source";
+    anon.get::<()>(url).assert_not_found();
+
+    app.db(|conn| {
+        Version::record_readme_source(version_id, Some("docs/README.md"), conn).unwrap();
+    });
+    let response = anon.get::<()>(url);
+    assert_eq!(response.status(), StatusCode::FOUND);
+    let location = response.header(header::LOCATION).unwrap();
+    assert!(location.ends_with("/readmes/foo_readme_source/foo_readme_source-1.0.0.txt"));
+
+    anon.get::<()>("/api/v1/crates/foo_readme_source/2.0.0/readme/source")
+        .assert_not_found();
+}
+
 #[test]
 fn version_size() {
     let (_, _, user) = TestApp::full().with_user();
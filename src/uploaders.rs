@@ -1,78 +1,133 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use conduit::RequestExt;
 use flate2::read::GzDecoder;
+use hex::ToHex;
 use reqwest::{blocking::Client, header};
 use sha2::{Digest, Sha256};
 
-use crate::util::errors::{cargo_err, internal, AppResult, ChainError};
-use crate::util::{LimitErrorReader, Maximums};
+use crate::util::errors::{cargo_err, AppResult, ChainError};
+use crate::util::{HashingReader, LimitErrorReader, Maximums};
 
-use std::env;
-use std::fs::{self, File};
-use std::io::{Cursor, Read};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::path::Path;
 use std::sync::Arc;
 
-use crate::middleware::app::RequestApp;
+use crate::download_origins::DownloadOrigin;
 use crate::models::Crate;
+use crate::storage::{FileSystem, Storage, S3};
 
 const CACHE_CONTROL_IMMUTABLE: &str = "public,max-age=31536000,immutable";
 const CACHE_CONTROL_README: &str = "public,max-age=604800";
 
+/// The largest `Cargo.toml` that is read from crate files
+const MAX_MANIFEST_SIZE: u64 = 1024 * 1024;
+
+/// Stores the files of crates, see the `storage` module
 #[derive(Clone, Debug)]
-pub enum Uploader {
-    /// For production usage, uploads and redirects to s3.
-    /// For test usage with `TestApp::with_proxy()`, the recording proxy is used.
-    S3 {
-        bucket: s3::Bucket,
-        cdn: Option<String>,
-    },
-
-    /// For development usage only: "uploads" crate files to `dist` and serves them
-    /// from there as well to enable local publishing and download
-    Local,
+pub struct Uploader {
+    primary: Arc<dyn Storage>,
+    /// The backend that files are copied to and read from if the primary
+    /// backend fails
+    secondary: Option<Arc<dyn Storage>>,
+    /// The hosts crate files are downloaded from instead of the primary
+    /// backend, see the `download_origins` module
+    download_origins: Vec<DownloadOrigin>,
 }
 
 impl Uploader {
-    /// Returns the URL of an uploaded crate's version archive.
+    pub fn new(primary: impl Storage + 'static) -> Self {
+        Self {
+            primary: Arc::new(primary),
+            secondary: None,
+            download_origins: Vec::new(),
+        }
+    }
+
+    /// For production usage, uploads and redirects to s3.
+    /// For test usage with `TestApp::with_proxy()`, the recording proxy is used.
+    pub fn s3(bucket: s3::Bucket, cdn: Option<String>) -> Self {
+        Self::new(S3 { bucket, cdn })
+    }
+
+    /// For development usage only: "uploads" crate files to `local_uploads`
+    /// and serves them from there as well to enable local publishing and
+    /// download
+    pub fn local() -> Self {
+        Self::new(FileSystem::local_uploads())
+    }
+
+    pub fn with_secondary(mut self, secondary: Box<dyn Storage>) -> Self {
+        self.secondary = Some(Arc::from(secondary));
+        self
+    }
+
+    pub fn with_download_origins(mut self, download_origins: Vec<DownloadOrigin>) -> Self {
+        self.download_origins = download_origins;
+        self
+    }
+
+    pub fn primary(&self) -> &dyn Storage {
+        &*self.primary
+    }
+
+    pub fn secondary(&self) -> Option<&dyn Storage> {
+        self.secondary.as_deref()
+    }
+
+    pub fn download_origins(&self) -> &[DownloadOrigin] {
+        &self.download_origins
+    }
+
+    /// Returns the host uploaded files are served from, unless they are
+    /// served by the application itself.
+    pub fn host(&self) -> Option<String> {
+        self.primary.host()
+    }
+
+    /// Returns the URL of an uploaded crate's version archive, at the download
+    /// origin if one was picked or at the primary backend otherwise.
     ///
     /// The function doesn't check for the existence of the file.
-    pub fn crate_location(&self, crate_name: &str, version: &str) -> String {
-        match *self {
-            Uploader::S3 {
-                ref bucket,
-                ref cdn,
-                ..
-            } => {
-                let host = match *cdn {
-                    Some(ref s) => s.clone(),
-                    None => bucket.host(),
-                };
-                let path = Uploader::crate_path(crate_name, version);
-                format!("https://{}/{}", host, path)
-            }
-            Uploader::Local => format!("/{}", Uploader::crate_path(crate_name, version)),
-        }
+    pub fn crate_location(
+        &self,
+        origin: Option<&DownloadOrigin>,
+        crate_name: &str,
+        version: &str,
+    ) -> String {
+        let path = Uploader::crate_path(crate_name, version);
+        origin.map_or_else(|| self.location(&path), |origin| origin.location(&path))
+    }
+
+    /// Returns the URL of an uploaded crate archive by its hex encoded SHA256
+    /// checksum. Unlike the location by name, the file there can't be swapped
+    /// for another one without changing the URL.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn crate_location_by_checksum(
+        &self,
+        origin: Option<&DownloadOrigin>,
+        checksum: &str,
+    ) -> String {
+        let path = Uploader::checksum_path(checksum);
+        origin.map_or_else(|| self.location(&path), |origin| origin.location(&path))
     }
 
     /// Returns the URL of an uploaded crate's version readme.
     ///
     /// The function doesn't check for the existence of the file.
     pub fn readme_location(&self, crate_name: &str, version: &str) -> String {
-        match *self {
-            Uploader::S3 {
-                ref bucket,
-                ref cdn,
-                ..
-            } => {
-                let host = match *cdn {
-                    Some(ref s) => s.clone(),
-                    None => bucket.host(),
-                };
-                let path = Uploader::readme_path(crate_name, version);
-                format!("https://{}/{}", host, path)
-            }
-            Uploader::Local => format!("/{}", Uploader::readme_path(crate_name, version)),
-        }
+        self.location(&Uploader::readme_path(crate_name, version))
+    }
+
+    /// Returns the URL of the README source of an uploaded crate's version.
+    ///
+    /// The function doesn't check for the existence of the file.
+    pub fn readme_source_location(&self, crate_name: &str, version: &str) -> String {
+        self.location(&Uploader::readme_source_path(crate_name, version))
+    }
+
+    fn location(&self, path: &str) -> String {
+        self.primary.location(path)
     }
 
     /// Returns the internal path of an uploaded crate's version archive.
@@ -81,19 +136,34 @@ impl Uploader {
         format!("crates/{}/{}-{}.crate", name, name, version)
     }
 
+    /// Returns the internal path of a crate archive stored by its checksum.
+    /// The first bytes of the checksum are used as directories, so that no
+    /// directory gets too large.
+    fn checksum_path(checksum: &str) -> String {
+        format!(
+            "crates-by-checksum/{}/{}/{}.crate",
+            &checksum[..2],
+            &checksum[2..4],
+            checksum
+        )
+    }
+
     /// Returns the internal path of an uploaded crate's version readme.
     fn readme_path(name: &str, version: &str) -> String {
         format!("readmes/{}/{}-{}.html", name, name, version)
     }
 
-    /// Uploads a file using the configured uploader (either `S3`, `Local`).
-    ///
-    /// It returns the path of the uploaded file.
-    ///
-    /// # Panics
+    /// Returns the internal path of the README source of an uploaded crate's
+    /// version, which is stored as it was found in the crate file.
+    fn readme_source_path(name: &str, version: &str) -> String {
+        format!("readmes/{}/{}-{}.txt", name, name, version)
+    }
+
+    /// Uploads a file to the primary backend, and to the secondary backend
+    /// if there is one.
     ///
-    /// This function can panic on an `Self::Local` during development.
-    /// Production and tests use `Self::S3` which should not panic.
+    /// It returns where the file was stored by the primary backend. Failing to
+    /// store the copy is logged, but doesn't fail the upload.
     pub fn upload<R: std::io::Read + Send + 'static>(
         &self,
         client: &Client,
@@ -103,60 +173,188 @@ impl Uploader {
         content_type: &str,
         extra_headers: header::HeaderMap,
     ) -> Result<Option<String>> {
-        match *self {
-            Uploader::S3 { ref bucket, .. } => {
-                bucket.put(
+        let secondary = match &self.secondary {
+            Some(secondary) => secondary,
+            None => {
+                return self.primary.put(
                     client,
                     path,
-                    content,
+                    Box::new(content),
                     content_length,
                     content_type,
                     extra_headers,
-                )?;
-                Ok(Some(String::from(path)))
-            }
-            Uploader::Local => {
-                let filename = env::current_dir().unwrap().join("local_uploads").join(path);
-                let dir = filename.parent().unwrap();
-                fs::create_dir_all(dir)?;
-                let mut file = File::create(&filename)?;
-                std::io::copy(&mut content, &mut file)?;
-                Ok(filename.to_str().map(String::from))
+                )
             }
+        };
+
+        // The content can only be read once, so it is buffered in a
+        // temporary file for the second upload
+        let mut file = tempfile::tempfile()?;
+        std::io::copy(&mut content, &mut file)?;
+        file.seek(SeekFrom::Start(0))?;
+        let stored = self.primary.put(
+            client,
+            path,
+            Box::new(file.try_clone()?),
+            content_length,
+            content_type,
+            extra_headers.clone(),
+        )?;
+        file.seek(SeekFrom::Start(0))?;
+        let copied = secondary.put(
+            client,
+            path,
+            Box::new(file),
+            content_length,
+            content_type,
+            extra_headers,
+        );
+        if let Err(error) = copied {
+            warn!(
+                path,
+                storage = secondary.kind(),
+                %error,
+                "Failed to copy the file to the secondary storage"
+            );
         }
+        Ok(stored)
     }
 
-    /// Uploads a crate and returns the checksum of the uploaded crate file.
-    pub fn upload_crate(
-        &self,
+    /// Reads the crate file of a publish request and verifies its contents.
+    ///
+    /// Returns the file, its hex encoded SHA256 checksum, which is computed
+    /// while the request body is read, and the `Cargo.toml` of the crate if
+    /// the file contains one. The file is uploaded by
+    /// [`Uploader::upload_crate`] once the version has been committed to the
+    /// database.
+    pub fn read_crate(
         req: &mut dyn RequestExt,
         krate: &Crate,
         maximums: Maximums,
         vers: &semver::Version,
-    ) -> AppResult<[u8; 32]> {
-        let app = Arc::clone(req.app());
-        let path = Uploader::crate_path(&krate.name, &vers.to_string());
+    ) -> AppResult<(Vec<u8>, String, Option<String>)> {
         let mut body = Vec::new();
-        LimitErrorReader::new(req.body(), maximums.max_upload_size).read_to_end(&mut body)?;
-        verify_tarball(krate, vers, &body, maximums.max_unpack_size)?;
-        let checksum = Sha256::digest(&body);
+        let mut reader =
+            HashingReader::new(LimitErrorReader::new(req.body(), maximums.max_upload_size));
+        reader.read_to_end(&mut body)?;
+        let checksum = reader.finish();
+        let manifest = verify_tarball(krate, vers, &body, maximums.max_unpack_size)?;
+        Ok((body, checksum, manifest))
+    }
+
+    /// Uploads a crate file that was read by [`Uploader::read_crate`], both
+    /// by its checksum and by its name and version.
+    ///
+    /// Nothing is uploaded if the file doesn't match the checksum that was
+    /// recorded when it was published.
+    pub(crate) fn upload_crate(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        checksum: &str,
+        body: Vec<u8>,
+    ) -> Result<()> {
+        let actual = Sha256::digest(&body).encode_hex::<String>();
+        if actual != checksum {
+            return Err(anyhow!(
+                "crate file of `{}#{}` has the checksum {}, expected {}",
+                crate_name,
+                vers,
+                actual,
+                checksum
+            ));
+        }
+
+        // The file is uploaded by checksum first, so that the version is
+        // never marked as stored by checksum without the file being there
+        let paths = [
+            Uploader::checksum_path(checksum),
+            Uploader::crate_path(crate_name, vers),
+        ];
+        for path in &paths {
+            let content_length = body.len() as u64;
+            let content = Cursor::new(body.clone());
+            let mut extra_headers = header::HeaderMap::new();
+            extra_headers.insert(
+                header::CACHE_CONTROL,
+                CACHE_CONTROL_IMMUTABLE.parse().unwrap(),
+            );
+            self.upload(
+                http_client,
+                path,
+                content,
+                content_length,
+                "application/x-tar",
+                extra_headers,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Uploads a crate file that is only stored by its name and version by
+    /// its checksum as well, see [`Uploader::upload_crate`].
+    pub(crate) fn upload_crate_by_checksum(
+        &self,
+        http_client: &Client,
+        checksum: &str,
+        body: Vec<u8>,
+    ) -> Result<()> {
         let content_length = body.len() as u64;
-        let content = Cursor::new(body);
         let mut extra_headers = header::HeaderMap::new();
         extra_headers.insert(
             header::CACHE_CONTROL,
             CACHE_CONTROL_IMMUTABLE.parse().unwrap(),
         );
         self.upload(
-            app.http_client(),
-            &path,
-            content,
+            http_client,
+            &Uploader::checksum_path(checksum),
+            Cursor::new(body),
             content_length,
             "application/x-tar",
             extra_headers,
-        )
-        .map_err(|e| internal(&format_args!("failed to upload crate: {}", e)))?;
-        Ok(checksum.into())
+        )?;
+        Ok(())
+    }
+
+    /// Downloads a previously uploaded crate file.
+    pub(crate) fn download_crate(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+    ) -> Result<Vec<u8>> {
+        self.download(http_client, &Uploader::crate_path(crate_name, vers))
+    }
+
+    /// Downloads a crate file that was uploaded by its checksum.
+    pub(crate) fn download_crate_by_checksum(
+        &self,
+        http_client: &Client,
+        checksum: &str,
+    ) -> Result<Vec<u8>> {
+        self.download(http_client, &Uploader::checksum_path(checksum))
+    }
+
+    /// Downloads a file from the primary backend, or from the secondary
+    /// backend if that fails.
+    fn download(&self, http_client: &Client, path: &str) -> Result<Vec<u8>> {
+        let error = match self.primary.get(http_client, path) {
+            Ok(body) => return Ok(body),
+            Err(error) => error,
+        };
+        match &self.secondary {
+            Some(secondary) => {
+                warn!(
+                    path,
+                    storage = self.primary.kind(),
+                    %error,
+                    "Failed to download the file, reading it from the secondary storage"
+                );
+                secondary.get(http_client, path)
+            }
+            None => Err(error),
+        }
     }
 
     pub(crate) fn upload_readme(
@@ -181,14 +379,39 @@ impl Uploader {
         )?;
         Ok(())
     }
+
+    pub(crate) fn upload_readme_source(
+        &self,
+        http_client: &Client,
+        crate_name: &str,
+        vers: &str,
+        source: String,
+    ) -> Result<()> {
+        let path = Uploader::readme_source_path(crate_name, vers);
+        let content_length = source.len() as u64;
+        let content = Cursor::new(source);
+        let mut extra_headers = header::HeaderMap::new();
+        extra_headers.insert(header::CACHE_CONTROL, CACHE_CONTROL_README.parse().unwrap());
+        self.upload(
+            http_client,
+            &path,
+            content,
+            content_length,
+            "text/plain; charset=utf-8",
+            extra_headers,
+        )?;
+        Ok(())
+    }
 }
 
+/// Verifies the entries of a crate file, and returns its `Cargo.toml` if it
+/// is valid UTF-8 and not larger than `MAX_MANIFEST_SIZE`
 fn verify_tarball(
     krate: &Crate,
     vers: &semver::Version,
     tarball: &[u8],
     max_unpack: u64,
-) -> AppResult<()> {
+) -> AppResult<Option<String>> {
     // All our data is currently encoded with gzip
     let decoder = GzDecoder::new(tarball);
 
@@ -199,8 +422,10 @@ fn verify_tarball(
     // Use this I/O object now to take a peek inside
     let mut archive = tar::Archive::new(decoder);
     let prefix = format!("{}-{}", krate.name, vers);
+    let manifest_path = Path::new(&prefix).join("Cargo.toml");
+    let mut manifest = None;
     for entry in archive.entries()? {
-        let entry = entry.chain_error(|| {
+        let mut entry = entry.chain_error(|| {
             cargo_err("uploaded tarball is malformed or too large when decompressed")
         })?;
 
@@ -222,6 +447,80 @@ fn verify_tarball(
         if entry_type.is_hard_link() || entry_type.is_symlink() {
             return Err(cargo_err("invalid tarball uploaded"));
         }
+
+        if entry.path()? == manifest_path && entry.size() <= MAX_MANIFEST_SIZE {
+            let mut contents = String::new();
+            if entry.read_to_string(&mut contents).is_ok() {
+                manifest = Some(contents);
+            }
+        }
+    }
+    Ok(manifest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crate_files_are_located_by_checksum() {
+        let checksum = Sha256::digest(b"tarball").encode_hex::<String>();
+        let location = Uploader::local().crate_location_by_checksum(None, &checksum);
+        assert_eq!(
+            location,
+            format!(
+                "/crates-by-checksum/{}/{}/{}.crate",
+                &checksum[..2],
+                &checksum[2..4],
+                checksum
+            )
+        );
+    }
+
+    #[test]
+    fn files_not_matching_the_checksum_are_not_uploaded() {
+        let checksum = Sha256::digest(b"tarball").encode_hex::<String>();
+        let result = Uploader::local().upload_crate(
+            &Client::new(),
+            "foo",
+            "1.0.0",
+            &checksum,
+            b"tampered".to_vec(),
+        );
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("expected"), "{}", error);
+    }
+
+    #[test]
+    fn files_are_copied_to_the_secondary_storage() {
+        let primary = tempfile::tempdir().unwrap();
+        let secondary = tempfile::tempdir().unwrap();
+        let uploader = Uploader::new(FileSystem::new(primary.path().into()))
+            .with_secondary(Box::new(FileSystem::new(secondary.path().into())));
+        let client = Client::new();
+
+        let content = b"readme".to_vec();
+        let length = content.len() as u64;
+        let path = "readmes/foo/foo-1.0.0.html";
+        uploader
+            .upload(
+                &client,
+                path,
+                Cursor::new(content),
+                length,
+                "text/html",
+                header::HeaderMap::new(),
+            )
+            .unwrap();
+        assert_eq!(
+            std::fs::read(secondary.path().join(path)).unwrap(),
+            b"readme"
+        );
+
+        // Reads fall back to the secondary storage
+        std::fs::remove_file(primary.path().join(path)).unwrap();
+        assert_eq!(uploader.download(&client, path).unwrap(), b"readme");
+        std::fs::remove_file(secondary.path().join(path)).unwrap();
+        assert!(uploader.download(&client, path).is_err());
     }
-    Ok(())
 }
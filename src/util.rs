@@ -3,13 +3,16 @@ use std::cmp;
 use conduit::{header, Body, Response};
 use serde::Serialize;
 
-pub use self::io_util::{read_fill, read_le_u32, LimitErrorReader};
+pub use self::io_util::{read_fill, read_le_u32, HashingReader, LimitErrorReader};
+pub use self::json_stream::{json_file_response, SerializeIter};
 pub use self::request_helpers::*;
 pub use self::request_proxy::RequestProxy;
 
 pub mod errors;
 mod io_util;
+mod json_stream;
 mod request_helpers;
+pub mod request_id;
 mod request_proxy;
 pub mod rfc3339;
 pub(crate) mod token;
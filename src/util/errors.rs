@@ -18,12 +18,15 @@ use std::error::Error;
 use std::fmt;
 
 use chrono::NaiveDateTime;
+use conduit::StatusCode;
 use diesel::result::Error as DieselError;
 
 use crate::util::AppResponse;
 
+mod code;
 mod json;
 
+pub use code::ErrorCode;
 pub use json::TOKEN_FORMAT_ERROR;
 pub(crate) use json::{InsecurelyGeneratedTokenRevoked, NotFound, ReadOnlyMode, TooManyRequests};
 
@@ -33,7 +36,13 @@ pub(crate) use json::{InsecurelyGeneratedTokenRevoked, NotFound, ReadOnlyMode, T
 /// endpoints, use helpers like `bad_request` or `server_error` which set a
 /// correct status code.
 pub fn cargo_err<S: ToString + ?Sized>(error: &S) -> Box<dyn AppError> {
-    Box::new(json::Ok(error.to_string()))
+    cargo_err_with_code(ErrorCode::InvalidRequest, error)
+}
+
+/// Returns an error with status 200 and the provided code and description as
+/// JSON, like `cargo_err`
+pub fn cargo_err_with_code<S: ToString + ?Sized>(code: ErrorCode, error: &S) -> Box<dyn AppError> {
+    Box::new(json::Ok(error.to_string(), code))
 }
 
 // The following are intended to be used for errors being sent back to the Ember
@@ -66,6 +75,24 @@ pub fn server_error<S: ToString + ?Sized>(error: &S) -> Box<dyn AppError> {
     Box::new(json::ServerError(error.to_string()))
 }
 
+/// Returns an error with the provided status and description as JSON
+pub fn custom<S: ToString + ?Sized>(status: StatusCode, detail: &S) -> Box<dyn AppError> {
+    custom_with_code(status, ErrorCode::from_status(status), detail)
+}
+
+/// Returns an error with the provided status, code and description as JSON
+pub fn custom_with_code<S: ToString + ?Sized>(
+    status: StatusCode,
+    code: ErrorCode,
+    detail: &S,
+) -> Box<dyn AppError> {
+    Box::new(json::CustomApiError {
+        status,
+        code,
+        detail: detail.to_string(),
+    })
+}
+
 // =============================================================================
 // AppError trait
 
@@ -86,6 +113,11 @@ pub trait AppError: Send + fmt::Display + fmt::Debug + 'static {
         None
     }
 
+    /// The `code` of the error response, see `ErrorCode`
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InternalError
+    }
+
     fn get_type_id(&self) -> TypeId {
         TypeId::of::<Self>()
     }
@@ -129,6 +161,10 @@ impl AppError for Box<dyn AppError> {
         (**self).cause()
     }
 
+    fn code(&self) -> ErrorCode {
+        (**self).code()
+    }
+
     fn get_type_id(&self) -> TypeId {
         (**self).get_type_id()
     }
@@ -183,6 +219,10 @@ impl<E: AppError> AppError for ChainedError<E> {
     fn cause(&self) -> Option<&dyn AppError> {
         Some(&*self.cause)
     }
+
+    fn code(&self) -> ErrorCode {
+        self.error.code()
+    }
 }
 
 impl<E: AppError> fmt::Display for ChainedError<E> {
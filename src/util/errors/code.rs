@@ -0,0 +1,136 @@
+use conduit::StatusCode;
+use serde::{Serialize, Serializer};
+
+/// The machine-readable `code` of every error in a JSON error response
+///
+/// Clients should branch on the code instead of the `detail`, whose wording
+/// may change. The names of the codes are stable: new codes may be added,
+/// but existing ones are neither renamed nor removed. Errors without a more
+/// specific code get the generic code of their status, see
+/// `ErrorCode::from_status`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    // Generic codes
+    /// Any other problem with the request, including the errors sent to cargo
+    /// with a status of 200
+    InvalidRequest,
+    /// The endpoint requires a session cookie or an API token
+    Unauthenticated,
+    Unauthorized,
+    Forbidden,
+    NotFound,
+    Conflict,
+    Gone,
+    PayloadTooLarge,
+    RateLimited,
+    ReadOnlyMode,
+    Unavailable,
+    InternalError,
+
+    // Accounts and tokens
+    AccountLocked,
+    /// The API token was generated insecurely and has been revoked
+    TokenRevoked,
+    EmailNotVerified,
+
+    // Publishing
+    /// The crate exists and the user isn't one of its owners
+    CrateNameTaken,
+    /// The crate exists with a different spelling of the name
+    CrateNameMismatch,
+    CrateNameReserved,
+    VersionExists,
+    MissingMetadata,
+    UnknownDependency,
+    UploadTooLarge,
+
+    // Quotas of accounts, see the `quotas` module
+    StorageQuotaExceeded,
+    VersionQuotaExceeded,
+    CrateQuotaExceeded,
+
+    // Owners and yanking
+    NotOwner,
+    AlreadyOwner,
+    /// The version is held out of the index for review
+    VersionHeld,
+
+    // Idempotency keys
+    /// The `Idempotency-Key` was already used for a different request
+    IdempotencyKeyReused,
+}
+
+impl ErrorCode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::InvalidRequest => "invalid_request",
+            ErrorCode::Unauthenticated => "unauthenticated",
+            ErrorCode::Unauthorized => "unauthorized",
+            ErrorCode::Forbidden => "forbidden",
+            ErrorCode::NotFound => "not_found",
+            ErrorCode::Conflict => "conflict",
+            ErrorCode::Gone => "gone",
+            ErrorCode::PayloadTooLarge => "payload_too_large",
+            ErrorCode::RateLimited => "rate_limited",
+            ErrorCode::ReadOnlyMode => "read_only_mode",
+            ErrorCode::Unavailable => "unavailable",
+            ErrorCode::InternalError => "internal_error",
+            ErrorCode::AccountLocked => "account_locked",
+            ErrorCode::TokenRevoked => "token_revoked",
+            ErrorCode::EmailNotVerified => "email_not_verified",
+            ErrorCode::CrateNameTaken => "crate_name_taken",
+            ErrorCode::CrateNameMismatch => "crate_name_mismatch",
+            ErrorCode::CrateNameReserved => "crate_name_reserved",
+            ErrorCode::VersionExists => "version_exists",
+            ErrorCode::MissingMetadata => "missing_metadata",
+            ErrorCode::UnknownDependency => "unknown_dependency",
+            ErrorCode::UploadTooLarge => "upload_too_large",
+            ErrorCode::StorageQuotaExceeded => "storage_quota_exceeded",
+            ErrorCode::VersionQuotaExceeded => "version_quota_exceeded",
+            ErrorCode::CrateQuotaExceeded => "crate_quota_exceeded",
+            ErrorCode::NotOwner => "not_owner",
+            ErrorCode::AlreadyOwner => "already_owner",
+            ErrorCode::VersionHeld => "version_held",
+            ErrorCode::IdempotencyKeyReused => "idempotency_key_reused",
+        }
+    }
+
+    /// The generic code of errors with the status
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => ErrorCode::Unauthorized,
+            StatusCode::FORBIDDEN => ErrorCode::Forbidden,
+            StatusCode::NOT_FOUND => ErrorCode::NotFound,
+            StatusCode::CONFLICT => ErrorCode::Conflict,
+            StatusCode::GONE => ErrorCode::Gone,
+            StatusCode::PAYLOAD_TOO_LARGE => ErrorCode::PayloadTooLarge,
+            StatusCode::TOO_MANY_REQUESTS => ErrorCode::RateLimited,
+            StatusCode::SERVICE_UNAVAILABLE => ErrorCode::Unavailable,
+            status if status.is_server_error() => ErrorCode::InternalError,
+            _ => ErrorCode::InvalidRequest,
+        }
+    }
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[test]
+fn statuses_have_generic_codes() {
+    assert_eq!(
+        ErrorCode::from_status(StatusCode::TOO_MANY_REQUESTS),
+        ErrorCode::RateLimited
+    );
+    assert_eq!(
+        ErrorCode::from_status(StatusCode::BAD_GATEWAY),
+        ErrorCode::InternalError
+    );
+    assert_eq!(
+        ErrorCode::from_status(StatusCode::OK),
+        ErrorCode::InvalidRequest
+    );
+    assert_eq!(ErrorCode::CrateNameTaken.as_str(), "crate_name_taken");
+}
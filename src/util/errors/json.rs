@@ -1,16 +1,18 @@
 use std::fmt;
 
-use super::{AppError, InternalAppErrorStatic};
+use super::{AppError, ErrorCode, InternalAppErrorStatic};
 use crate::util::{json_response, AppResponse};
 
 use chrono::NaiveDateTime;
 use conduit::{header, StatusCode};
 
-/// Generates a response with the provided status and description as JSON
-fn json_error(detail: &str, status: StatusCode) -> AppResponse {
+/// Generates a response with the provided status, description and code as
+/// JSON
+fn json_error(detail: &str, code: ErrorCode, status: StatusCode) -> AppResponse {
     #[derive(Serialize)]
     struct StringError<'a> {
         detail: &'a str,
+        code: ErrorCode,
     }
     #[derive(Serialize)]
     struct Bad<'a> {
@@ -18,7 +20,7 @@ fn json_error(detail: &str, status: StatusCode) -> AppResponse {
     }
 
     let mut response = json_response(&Bad {
-        errors: [StringError { detail }],
+        errors: [StringError { detail, code }],
     });
     *response.status_mut() = status;
     response
@@ -32,7 +34,7 @@ pub(crate) struct NotFound;
 // This struct has this helper impl for use as `NotFound.into()`
 impl From<NotFound> for AppResponse {
     fn from(_: NotFound) -> AppResponse {
-        json_error("Not Found", StatusCode::NOT_FOUND)
+        json_error("Not Found", ErrorCode::NotFound, StatusCode::NOT_FOUND)
     }
 }
 
@@ -40,6 +42,10 @@ impl AppError for NotFound {
     fn response(&self) -> Option<AppResponse> {
         Some(Self.into())
     }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::NotFound
+    }
 }
 
 impl fmt::Display for NotFound {
@@ -56,7 +62,11 @@ pub(crate) struct ReadOnlyMode;
 impl AppError for Forbidden {
     fn response(&self) -> Option<AppResponse> {
         let detail = "must be logged in to perform that action";
-        Some(json_error(detail, StatusCode::FORBIDDEN))
+        Some(json_error(detail, self.code(), StatusCode::FORBIDDEN))
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::Unauthenticated
     }
 }
 
@@ -66,11 +76,23 @@ impl fmt::Display for Forbidden {
     }
 }
 
+/// How many seconds clients are asked to wait before retrying requests that
+/// were rejected because of the read-only mode
+const READ_ONLY_RETRY_AFTER_SECONDS: u32 = 300;
+
 impl AppError for ReadOnlyMode {
     fn response(&self) -> Option<AppResponse> {
         let detail = "Crates.io is currently in read-only mode for maintenance. \
                       Please try again later.";
-        Some(json_error(detail, StatusCode::SERVICE_UNAVAILABLE))
+        let mut response = json_error(detail, self.code(), StatusCode::SERVICE_UNAVAILABLE);
+        response
+            .headers_mut()
+            .insert(header::RETRY_AFTER, READ_ONLY_RETRY_AFTER_SECONDS.into());
+        Some(response)
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::ReadOnlyMode
     }
 }
 
@@ -83,19 +105,29 @@ impl fmt::Display for ReadOnlyMode {
 // The following structs wrap owned data and provide a custom message to the user
 
 #[derive(Debug)]
-pub(super) struct Ok(pub(super) String);
+pub(super) struct Ok(pub(super) String, pub(super) ErrorCode);
 #[derive(Debug)]
 pub(super) struct BadRequest(pub(super) String);
 #[derive(Debug)]
 pub(super) struct ServerError(pub(super) String);
 #[derive(Debug)]
+pub(super) struct CustomApiError {
+    pub(super) status: StatusCode,
+    pub(super) code: ErrorCode,
+    pub(super) detail: String,
+}
+#[derive(Debug)]
 pub(crate) struct TooManyRequests {
     pub retry_after: NaiveDateTime,
 }
 
 impl AppError for Ok {
     fn response(&self) -> Option<AppResponse> {
-        Some(json_error(&self.0, StatusCode::OK))
+        Some(json_error(&self.0, self.1, StatusCode::OK))
+    }
+
+    fn code(&self) -> ErrorCode {
+        self.1
     }
 }
 
@@ -107,7 +139,11 @@ impl fmt::Display for Ok {
 
 impl AppError for BadRequest {
     fn response(&self) -> Option<AppResponse> {
-        Some(json_error(&self.0, StatusCode::BAD_REQUEST))
+        Some(json_error(&self.0, self.code(), StatusCode::BAD_REQUEST))
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::InvalidRequest
     }
 }
 
@@ -119,7 +155,11 @@ impl fmt::Display for BadRequest {
 
 impl AppError for ServerError {
     fn response(&self) -> Option<AppResponse> {
-        Some(json_error(&self.0, StatusCode::INTERNAL_SERVER_ERROR))
+        Some(json_error(
+            &self.0,
+            self.code(),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ))
     }
 }
 
@@ -129,6 +169,22 @@ impl fmt::Display for ServerError {
     }
 }
 
+impl AppError for CustomApiError {
+    fn response(&self) -> Option<AppResponse> {
+        Some(json_error(&self.detail, self.code(), self.status))
+    }
+
+    fn code(&self) -> ErrorCode {
+        self.code
+    }
+}
+
+impl fmt::Display for CustomApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.detail.fmt(f)
+    }
+}
+
 impl AppError for TooManyRequests {
     fn response(&self) -> Option<AppResponse> {
         use std::convert::TryInto;
@@ -142,7 +198,7 @@ impl AppError for TooManyRequests {
              help@crates.io to have your limit increased.",
             retry_after
         );
-        let mut response = json_error(&detail, StatusCode::TOO_MANY_REQUESTS);
+        let mut response = json_error(&detail, self.code(), StatusCode::TOO_MANY_REQUESTS);
         response.headers_mut().insert(
             header::RETRY_AFTER,
             retry_after
@@ -152,6 +208,10 @@ impl AppError for TooManyRequests {
         );
         Some(response)
     }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::RateLimited
+    }
 }
 
 impl fmt::Display for TooManyRequests {
@@ -171,7 +231,15 @@ impl InsecurelyGeneratedTokenRevoked {
 
 impl AppError for InsecurelyGeneratedTokenRevoked {
     fn response(&self) -> Option<AppResponse> {
-        Some(json_error(&self.to_string(), StatusCode::UNAUTHORIZED))
+        Some(json_error(
+            &self.to_string(),
+            self.code(),
+            StatusCode::UNAUTHORIZED,
+        ))
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::TokenRevoked
     }
 
     fn cause(&self) -> Option<&dyn AppError> {
@@ -207,7 +275,15 @@ pub(super) struct AccountLocked {
 
 impl AppError for AccountLocked {
     fn response(&self) -> Option<AppResponse> {
-        Some(json_error(&self.to_string(), StatusCode::FORBIDDEN))
+        Some(json_error(
+            &self.to_string(),
+            self.code(),
+            StatusCode::FORBIDDEN,
+        ))
+    }
+
+    fn code(&self) -> ErrorCode {
+        ErrorCode::AccountLocked
     }
 }
 
@@ -1,3 +1,5 @@
+use hex::ToHex;
+use sha2::{Digest, Sha256};
 use std::io;
 use std::io::prelude::*;
 use std::mem;
@@ -27,6 +29,35 @@ impl<R: Read> Read for LimitErrorReader<R> {
     }
 }
 
+/// Computes the SHA256 checksum of everything that is read through it
+#[allow(missing_debug_implementations)]
+pub struct HashingReader<R> {
+    inner: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(inner: R) -> HashingReader<R> {
+        HashingReader {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// The hex encoded checksum of the bytes read so far
+    pub fn finish(self) -> String {
+        self.hasher.finalize().encode_hex()
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
 pub fn read_le_u32<R: Read + ?Sized>(r: &mut R) -> io::Result<u32> {
     let mut b = [0; 4];
     read_fill(r, &mut b)?;
@@ -0,0 +1,108 @@
+use std::cell::RefCell;
+use std::io::{self, BufWriter, Seek, SeekFrom};
+
+use conduit::{header, Body, Response};
+use serde::ser::{Serialize, Serializer};
+
+use super::errors::AppResult;
+use super::AppResponse;
+
+/// Serializes a value to JSON in a temporary file and responds with the file.
+///
+/// Unlike [`json_response`](super::json_response) the encoded JSON is never
+/// held in memory, which matters for responses with thousands of items.
+/// Combined with [`SerializeIter`] the items don't have to be in memory all
+/// at once either.
+pub fn json_file_response<T: Serialize>(value: &T) -> AppResult<AppResponse> {
+    let mut writer = BufWriter::new(tempfile::tempfile()?);
+    serde_json::to_writer(&mut writer, value)?;
+    let mut file = writer
+        .into_inner()
+        .map_err(|e| io::Error::new(e.error().kind(), e.to_string()))?;
+
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, "application/json; charset=utf-8")
+        .header(header::CONTENT_LENGTH, len)
+        .body(Body::File(file))
+        .unwrap()) // Header values are well formed, so should not panic
+}
+
+/// Serializes the items of an iterator as a JSON array, producing them only
+/// as they are written.
+///
+/// The iterator is consumed by the first serialization. Errors of fallible
+/// items, like a failed query of the next batch, abort the serialization.
+pub struct SerializeIter<I>(RefCell<Option<I>>);
+
+impl<I> SerializeIter<I> {
+    pub fn new(iter: I) -> Self {
+        Self(RefCell::new(Some(iter)))
+    }
+}
+
+impl<I, T, E> Serialize for SerializeIter<I>
+where
+    I: Iterator<Item = Result<T, E>>,
+    T: Serialize,
+    E: std::fmt::Display,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::{Error, SerializeSeq};
+
+        let iter = self
+            .0
+            .borrow_mut()
+            .take()
+            .ok_or_else(|| S::Error::custom("the items have already been serialized"))?;
+
+        let mut seq = serializer.serialize_seq(None)?;
+        for item in iter {
+            seq.serialize_element(&item.map_err(S::Error::custom)?)?;
+        }
+        seq.end()
+    }
+}
+
+impl<I> std::fmt::Debug for SerializeIter<I> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SerializeIter").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn items_are_serialized_as_they_are_produced() {
+        let items: Vec<Result<i32, String>> = vec![Ok(1), Ok(2), Ok(3)];
+        let iter = SerializeIter::new(items.into_iter());
+        assert_eq!(assert_ok!(serde_json::to_string(&iter)), "[1,2,3]");
+        assert_err!(serde_json::to_string(&iter));
+    }
+
+    #[test]
+    fn failed_items_abort_the_serialization() {
+        let items = vec![Ok(1), Err("connection lost")];
+        let iter = SerializeIter::new(items.into_iter());
+        let error = assert_err!(serde_json::to_string(&iter));
+        assert!(error.to_string().contains("connection lost"));
+    }
+
+    #[test]
+    fn responses_are_written_to_a_file() {
+        let response = assert_ok!(json_file_response(&vec!["a", "b"]));
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], "9");
+
+        let mut json = String::new();
+        match response.into_body() {
+            Body::File(mut file) => assert_ok!(file.read_to_string(&mut json)),
+            _ => panic!("expected a file body"),
+        };
+        assert_eq!(json, r#"["a","b"]"#);
+    }
+}
@@ -1,4 +1,5 @@
 use conduit::{header::AsHeaderName, RequestExt};
+use std::net::IpAddr;
 
 /// Returns the value of the request header, or an empty slice if it is not
 /// present.
@@ -15,3 +16,44 @@ where
         .map(|value| value.to_str().unwrap_or_default())
         .unwrap_or_default()
 }
+
+/// Returns the address of the client, as forwarded by the proxy in front of
+/// the application in the `X-Real-Ip` header, or the address of the peer
+pub fn request_ip(req: &dyn RequestExt) -> IpAddr {
+    request_header(req, "x-real-ip")
+        .parse()
+        .unwrap_or_else(|_| req.remote_addr().ip())
+}
+
+/// Classifies an address without revealing it, for records that are shown
+/// publicly. Returns one of `loopback`, `private`, `public_ipv4` and
+/// `public_ipv6`.
+pub fn ip_class(ip: IpAddr) -> &'static str {
+    match ip {
+        _ if ip.is_loopback() => "loopback",
+        IpAddr::V4(ip) if ip.is_private() || ip.is_link_local() => "private",
+        // Unique local (`fc00::/7`) and link local (`fe80::/10`) addresses
+        IpAddr::V6(ip) if ip.segments()[0] & 0xfe00 == 0xfc00 => "private",
+        IpAddr::V6(ip) if ip.segments()[0] & 0xffc0 == 0xfe80 => "private",
+        IpAddr::V4(_) => "public_ipv4",
+        IpAddr::V6(_) => "public_ipv6",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ip_classes() {
+        let class = |ip: &str| ip_class(ip.parse().unwrap());
+        assert_eq!(class("127.0.0.1"), "loopback");
+        assert_eq!(class("::1"), "loopback");
+        assert_eq!(class("10.1.2.3"), "private");
+        assert_eq!(class("192.168.0.1"), "private");
+        assert_eq!(class("fd00::1"), "private");
+        assert_eq!(class("fe80::1"), "private");
+        assert_eq!(class("93.184.216.34"), "public_ipv4");
+        assert_eq!(class("2606:2800:220:1::1"), "public_ipv6");
+    }
+}
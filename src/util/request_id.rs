@@ -0,0 +1,81 @@
+//! Request IDs tie together the log lines, error reports and background jobs
+//! that belong to the same request.
+//!
+//! The ID is taken from the `X-Request-Id` header set by the Heroku router,
+//! or generated if the header is missing. While a request is handled, the ID
+//! is available through [`current`], which is how jobs enqueued by the
+//! request remember it. The background worker does the same while running a
+//! job, so jobs enqueued by that job carry the ID of the original request.
+
+use conduit::RequestExt;
+use std::cell::RefCell;
+
+pub const HEADER: &str = "x-request-id";
+
+thread_local! {
+    static CURRENT: RefCell<Option<String>> = RefCell::new(None);
+}
+
+/// The request ID of a request, stored in its extensions
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// The ID of a request, or an empty string for requests that didn't go
+/// through the middleware that assigns it
+pub fn for_request(req: &dyn RequestExt) -> &str {
+    match req.extensions().find::<RequestId>() {
+        Some(RequestId(id)) => id,
+        None => "",
+    }
+}
+
+/// Generates a new random request ID
+pub fn generate() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+/// The ID of the request or job this thread is currently working on
+pub fn current() -> Option<String> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Runs `f` with `id` as the current request ID, restoring the previous one
+/// afterwards
+pub fn with<R>(id: Option<&str>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<String>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            CURRENT.with(|current| *current.borrow_mut() = previous);
+        }
+    }
+
+    let previous = CURRENT.with(|current| current.replace(id.map(String::from)));
+    let _restore = Restore(previous);
+    f()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn with_sets_and_restores_the_current_id() {
+        assert_none!(current());
+        with(Some("outer"), || {
+            assert_some_eq!(current(), "outer");
+            with(None, || assert_none!(current()));
+            with(Some("inner"), || assert_some_eq!(current(), "inner"));
+            assert_some_eq!(current(), "outer");
+        });
+        assert_none!(current());
+    }
+
+    #[test]
+    fn generated_ids_are_unique() {
+        let id = generate();
+        assert_eq!(id.len(), 32);
+        assert_ne!(id, generate());
+    }
+}
@@ -1,6 +1,7 @@
-//! A helper that wraps a request to overwrite the method.
+//! A helper that wraps a request to overwrite the method or the body.
 
-use std::{io::Read, net::SocketAddr};
+use std::io::{Cursor, Read};
+use std::net::SocketAddr;
 
 use conduit::{Method, RequestExt};
 
@@ -10,19 +11,37 @@ type RequestMutRef<'a> = &'a mut (dyn RequestExt + 'a);
 #[allow(missing_debug_implementations)]
 pub struct RequestProxy<'a> {
     other: RequestMutRef<'a>,
-    method: conduit::Method,
+    method: Option<conduit::Method>,
+    body: Option<Cursor<Vec<u8>>>,
 }
 
 impl<'a> RequestProxy<'a> {
     /// Wrap a request and overwrite the method with the provided value.
     pub(crate) fn rewrite_method(req: RequestMutRef<'a>, method: Method) -> Self {
-        RequestProxy { other: req, method }
+        RequestProxy {
+            other: req,
+            method: Some(method),
+            body: None,
+        }
+    }
+
+    /// Wrap a request whose body was already read, and serve the body from
+    /// the provided bytes instead.
+    pub(crate) fn replace_body(req: RequestMutRef<'a>, body: Vec<u8>) -> Self {
+        RequestProxy {
+            other: req,
+            method: None,
+            body: Some(Cursor::new(body)),
+        }
     }
 }
 
 impl<'a> RequestExt for RequestProxy<'a> {
     fn method(&self) -> &conduit::Method {
-        &self.method
+        match &self.method {
+            Some(method) => method,
+            None => self.other.method(),
+        }
     }
 
     fn path(&self) -> &str {
@@ -59,7 +78,10 @@ impl<'a> RequestExt for RequestProxy<'a> {
         self.other.headers()
     }
     fn body(&mut self) -> &mut dyn Read {
-        self.other.body()
+        match &mut self.body {
+            Some(body) => body,
+            None => self.other.body(),
+        }
     }
     fn extensions(&self) -> &conduit::Extensions {
         self.other.extensions()
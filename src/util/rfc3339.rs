@@ -24,7 +24,7 @@ where
 /// Wrapper for dealing with Option<NaiveDateTime>
 pub mod option {
     use chrono::NaiveDateTime;
-    use serde::{Deserializer, Serializer};
+    use serde::{Deserialize, Deserializer, Serializer};
 
     pub fn serialize<S>(dt: &Option<NaiveDateTime>, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -45,4 +45,42 @@ pub mod option {
             Err(_) => Ok(None),
         }
     }
+
+    /// Unlike `deserialize`, fails on invalid times instead of turning them
+    /// into `None`, for request bodies where a missing time has a meaning of
+    /// its own. `null` is still `None`.
+    pub fn deserialize_strict<'de, D>(deserializer: D) -> Result<Option<NaiveDateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super")] NaiveDateTime);
+
+        let dt = Option::<Wrapper>::deserialize(deserializer)?;
+        Ok(dt.map(|Wrapper(dt)| dt))
+    }
+}
+
+/// Wrapper for dealing with Vec<NaiveDateTime>
+pub mod vec {
+    use chrono::NaiveDateTime;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct Wrapper(#[serde(with = "super")] NaiveDateTime);
+
+    pub fn serialize<S>(dts: &[NaiveDateTime], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_seq(dts.iter().map(|&dt| Wrapper(dt)))
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<NaiveDateTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let dts = Vec::<Wrapper>::deserialize(deserializer)?;
+        Ok(dts.into_iter().map(|Wrapper(dt)| dt).collect())
+    }
 }
@@ -1,39 +1,172 @@
+use hmac::{Hmac, Mac, NewMac};
+use lazy_static::lazy_static;
 use rand::{distributions::Uniform, rngs::OsRng, Rng};
 use sha2::{Digest, Sha256};
 
 const TOKEN_LENGTH: usize = 32;
 
+/// The work factor used unless `API_TOKEN_KDF_ITERATIONS` is set
+const DEFAULT_KDF_ITERATIONS: u32 = 10_000;
+
+lazy_static! {
+    static ref HASHING: TokenHashing = TokenHashing::from_environment();
+}
+
+/// How tokens are hashed before they are stored.
+///
+/// Tokens are hashed with PBKDF2-HMAC-SHA256, salted with the secret pepper in
+/// `API_TOKEN_PEPPER`, which isn't stored in the database, so a leaked
+/// database doesn't allow checking guesses offline. It is required in
+/// production and empty by default elsewhere. The hash has to be
+/// deterministic to look tokens up by it, which rules out per-token salts.
+///
+/// `API_TOKEN_KDF_ITERATIONS` tunes the work factor. Tokens hashed with one of
+/// the comma separated values in `API_TOKEN_KDF_PREVIOUS_ITERATIONS`, and
+/// tokens created before they were hashed like this, are still accepted and
+/// re-hashed when they are used.
+///
+/// Session tokens are checked on every request made with a cookie, so they
+/// are hashed once with HMAC-SHA256 keyed with the pepper instead. Their 32
+/// random characters are too many to guess, even without a work factor.
+#[derive(Debug, Clone)]
+pub(crate) struct TokenHashing {
+    pepper: Vec<u8>,
+    iterations: u32,
+    previous_iterations: Vec<u32>,
+}
+
+impl TokenHashing {
+    fn from_environment() -> Self {
+        let parse = |value: &str| {
+            value
+                .trim()
+                .parse::<u32>()
+                .ok()
+                .filter(|&iterations| iterations > 0)
+                .unwrap_or_else(|| panic!("invalid API token KDF iterations `{}`", value))
+        };
+
+        // `Config::load` checks that production has a pepper at startup
+        let pepper = match dotenv::var("API_TOKEN_PEPPER") {
+            Ok(pepper) if !pepper.is_empty() => pepper,
+            _ if dotenv::var("HEROKU").is_ok() => panic!("`API_TOKEN_PEPPER` must be set"),
+            _ => String::new(),
+        };
+
+        Self {
+            pepper: pepper.into_bytes(),
+            iterations: dotenv::var("API_TOKEN_KDF_ITERATIONS")
+                .map(|value| parse(&value))
+                .unwrap_or(DEFAULT_KDF_ITERATIONS),
+            previous_iterations: dotenv::var("API_TOKEN_KDF_PREVIOUS_ITERATIONS")
+                .map(|values| {
+                    values
+                        .split(',')
+                        .filter(|value| !value.trim().is_empty())
+                        .map(parse)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn hash(&self, plaintext: &str, iterations: u32) -> TokenHash {
+        let mut hash = vec![0; 32];
+        pbkdf2::pbkdf2::<Hmac<Sha256>>(plaintext.as_bytes(), &self.pepper, iterations, &mut hash);
+        TokenHash {
+            hash,
+            kdf_iterations: Some(iterations as i32),
+        }
+    }
+
+    fn session_hash(&self, plaintext: &str) -> TokenHash {
+        let mut mac =
+            Hmac::<Sha256>::new_varkey(&self.pepper).expect("HMAC can take key of any size");
+        mac.update(plaintext.as_bytes());
+        TokenHash {
+            hash: mac.finalize().into_bytes().to_vec(),
+            kdf_iterations: None,
+        }
+    }
+
+    /// The hash of tokens created before they were hashed with a KDF
+    fn legacy_hash(plaintext: &str) -> TokenHash {
+        TokenHash {
+            hash: Sha256::digest(plaintext.as_bytes()).as_slice().to_vec(),
+            kdf_iterations: None,
+        }
+    }
+}
+
+/// A hash of a token as it is stored in the `token` column of `api_tokens`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TokenHash {
+    pub(crate) hash: Vec<u8>,
+    /// The value of the `kdf_iterations` column, `None` for legacy hashes and
+    /// session tokens
+    pub(crate) kdf_iterations: Option<i32>,
+}
+
 pub(crate) struct SecureToken {
-    sha256: Vec<u8>,
+    /// The hash with the current settings, followed by the ones of previous
+    /// settings for parsed tokens
+    hashes: Vec<TokenHash>,
 }
 
 impl SecureToken {
     pub(crate) fn generate(kind: SecureTokenKind) -> NewSecureToken {
+        Self::generate_with(&HASHING, kind)
+    }
+
+    fn generate_with(hashing: &TokenHashing, kind: SecureTokenKind) -> NewSecureToken {
         let plaintext = format!(
             "{}{}",
             kind.prefix(),
             generate_secure_alphanumeric_string(TOKEN_LENGTH)
         );
-        let sha256 = Sha256::digest(plaintext.as_bytes()).as_slice().to_vec();
+        let hashes = match kind {
+            SecureTokenKind::Api => vec![hashing.hash(&plaintext, hashing.iterations)],
+            SecureTokenKind::Session => vec![hashing.session_hash(&plaintext)],
+        };
 
         NewSecureToken {
             plaintext,
-            inner: Self { sha256 },
+            inner: Self { hashes },
         }
     }
 
     pub(crate) fn parse(kind: SecureTokenKind, plaintext: &str) -> Option<Self> {
+        Self::parse_with(&HASHING, kind, plaintext)
+    }
+
+    fn parse_with(hashing: &TokenHashing, kind: SecureTokenKind, plaintext: &str) -> Option<Self> {
         // This will both reject tokens without a prefix and tokens of the wrong kind.
         if SecureTokenKind::from_token(plaintext) != Some(kind) {
             return None;
         }
+        if kind == SecureTokenKind::Session {
+            let hashes = vec![hashing.session_hash(plaintext)];
+            return Some(Self { hashes });
+        }
 
-        let sha256 = Sha256::digest(plaintext.as_bytes()).as_slice().to_vec();
-        Some(Self { sha256 })
+        let mut hashes = vec![hashing.hash(plaintext, hashing.iterations)];
+        for &iterations in &hashing.previous_iterations {
+            if iterations != hashing.iterations {
+                hashes.push(hashing.hash(plaintext, iterations));
+            }
+        }
+        hashes.push(TokenHashing::legacy_hash(plaintext));
+        Some(Self { hashes })
     }
 
-    pub(crate) fn sha256(&self) -> &[u8] {
-        &self.sha256
+    /// The hash with the current settings, which new tokens are stored with
+    pub(crate) fn hash(&self) -> &TokenHash {
+        &self.hashes[0]
+    }
+
+    /// All hashes a stored token matching this one can have
+    pub(crate) fn candidates(&self) -> &[TokenHash] {
+        &self.hashes
     }
 }
 
@@ -94,6 +227,7 @@ secure_token_kind! {
     #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
     pub(crate) enum SecureTokenKind {
         Api => "cio", // Crates.IO
+        Session => "cis", // Crates.Io Session
     }
 }
 
@@ -111,20 +245,75 @@ mod tests {
     use super::*;
     use std::collections::HashSet;
 
+    fn hashing(iterations: u32, previous_iterations: Vec<u32>) -> TokenHashing {
+        TokenHashing {
+            pepper: b"pepper".to_vec(),
+            iterations,
+            previous_iterations,
+        }
+    }
+
     #[test]
     fn test_generated_and_parse() {
         const KIND: SecureTokenKind = SecureTokenKind::Api;
+        let hashing = hashing(2, vec![]);
 
-        let token = SecureToken::generate(KIND);
+        let token = SecureToken::generate_with(&hashing, KIND);
         assert!(token.plaintext().starts_with(KIND.prefix()));
-        assert_eq!(
-            token.sha256(),
+        assert_eq!(token.hash().kdf_iterations, Some(2));
+        assert_ne!(
+            token.hash().hash,
             Sha256::digest(token.plaintext().as_bytes()).as_slice()
         );
 
+        let parsed = SecureToken::parse_with(&hashing, KIND, &token.plaintext())
+            .expect("failed to parse back the token");
+        assert_eq!(parsed.hash(), token.hash());
+    }
+
+    #[test]
+    fn test_hashes_depend_on_the_pepper() {
+        let token = SecureToken::generate_with(&hashing(2, vec![]), SecureTokenKind::Api);
+        let other_pepper = TokenHashing {
+            pepper: b"salt".to_vec(),
+            ..hashing(2, vec![])
+        };
         let parsed =
-            SecureToken::parse(KIND, &token.plaintext()).expect("failed to parse back the token");
-        assert_eq!(parsed.sha256(), token.sha256());
+            SecureToken::parse_with(&other_pepper, SecureTokenKind::Api, token.plaintext())
+                .unwrap();
+        assert_ne!(parsed.hash(), token.hash());
+    }
+
+    #[test]
+    fn test_previous_hashes_are_candidates() {
+        const KIND: SecureTokenKind = SecureTokenKind::Api;
+        let old = SecureToken::generate_with(&hashing(2, vec![]), KIND);
+
+        let parsed = SecureToken::parse_with(&hashing(3, vec![2]), KIND, old.plaintext()).unwrap();
+        assert_eq!(parsed.hash().kdf_iterations, Some(3));
+        let candidates = parsed.candidates();
+        assert_eq!(candidates.len(), 3);
+        assert_eq!(&candidates[1], old.hash());
+        assert_eq!(candidates[2].kdf_iterations, None);
+        assert_eq!(
+            candidates[2].hash,
+            Sha256::digest(old.plaintext().as_bytes()).as_slice()
+        );
+    }
+
+    #[test]
+    fn test_session_tokens_have_a_single_hash() {
+        const KIND: SecureTokenKind = SecureTokenKind::Session;
+        let token = SecureToken::generate_with(&hashing(2, vec![]), KIND);
+        assert_eq!(token.hash().kdf_iterations, None);
+
+        let parsed =
+            SecureToken::parse_with(&hashing(3, vec![2]), KIND, token.plaintext()).unwrap();
+        assert_eq!(parsed.candidates(), [token.hash().clone()]);
+
+        let mut mac = Hmac::<Sha256>::new_varkey(b"pepper").unwrap();
+        mac.update(token.plaintext().as_bytes());
+        assert_eq!(token.hash().hash, mac.finalize().into_bytes().as_slice());
     }
 
     #[test]
@@ -144,6 +333,7 @@ mod tests {
         };
 
         ensure(SecureTokenKind::Api, "cio");
+        ensure(SecureTokenKind::Session, "cis");
 
         assert!(
             remaining.is_empty(),
@@ -1,14 +1,23 @@
-use chrono::NaiveDateTime;
+use chrono::{NaiveDate, NaiveDateTime};
 use diesel::PgConnection;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use url::Url;
 
+use crate::background_jobs::JobProgress;
+use crate::feature_flags::FeatureFlag;
 use crate::github;
 use crate::models::{
-    Badge, Category, Crate, CrateOwnerInvitation, CreatedApiToken, Dependency, DependencyKind,
-    Keyword, Owner, ReverseDependency, Team, TopVersions, User, Version, VersionDownload,
-    VersionOwnerAction,
+    Advisory, Announcement, ApiToken, Badge, BulkYank, Category, CategoryStats, Crate,
+    CrateActivity, CrateDependents, CrateList, CrateListItem, CrateOwnerInvitation,
+    CreatedApiToken, DataExport, Dependency, DependencyKind, EmailPreferences, FeaturedCrate,
+    Finding, Keyword, KeywordStats, LoginAnomaly, Notification, Owner, ProjectLink,
+    PublishRateOverride, PublishRateOverrideAction, QuotaOverride, ReadmeRerender,
+    RepositoryVerification, ReservedCrateName, ReverseDependency, StorageMismatch, Team,
+    TopVersions, User, UserSession, Version, VersionDownload, VersionOwnerAction,
+    VersionQuarantine,
 };
+use crate::quotas::{Quotas, Usage};
+use crate::repository_verification::VerificationMethod;
 use crate::util::rfc3339;
 
 /// Hosts in this list are known to not be hosting documentation,
@@ -184,6 +193,38 @@ impl From<Keyword> for EncodableKeyword {
     }
 }
 
+/// The crates and downloads of a category or keyword in a month
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableMonthlyStats {
+    /// The month as `YYYY-MM`
+    pub month: String,
+    pub crates_cnt: i32,
+    pub new_crates: i32,
+    pub downloads: i64,
+}
+
+impl From<CategoryStats> for EncodableMonthlyStats {
+    fn from(stats: CategoryStats) -> Self {
+        Self {
+            month: stats.month.format("%Y-%m").to_string(),
+            crates_cnt: stats.crates_cnt,
+            new_crates: stats.new_crates,
+            downloads: stats.downloads,
+        }
+    }
+}
+
+impl From<KeywordStats> for EncodableMonthlyStats {
+    fn from(stats: KeywordStats) -> Self {
+        Self {
+            month: stats.month.format("%Y-%m").to_string(),
+            crates_cnt: stats.crates_cnt,
+            new_crates: stats.new_crates,
+            downloads: stats.downloads,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCrate {
     pub id: String,
@@ -199,6 +240,10 @@ pub struct EncodableCrate {
     // NOTE: Used by shields.io, altering `downloads` requires a PR with shields.io
     pub downloads: i32,
     pub recent_downloads: Option<i64>,
+    /// Crates whose newest version depends on this crate
+    pub direct_dependents: Option<i32>,
+    /// Crates with any non-yanked version that depends on this crate
+    pub total_dependents: Option<i32>,
     // NOTE: Used by shields.io, altering `max_version` requires a PR with shields.io
     pub max_version: String,
     pub newest_version: String, // Most recently updated version, which may not be max
@@ -207,6 +252,12 @@ pub struct EncodableCrate {
     pub homepage: Option<String>,
     pub documentation: Option<String>,
     pub repository: Option<String>,
+    /// Whether an owner proved control of the repository, which is only
+    /// included in the responses for a single crate
+    pub verified_repository: Option<bool>,
+    /// The links the owners attached to the crate, which are only included
+    /// in the responses for a single crate
+    pub project_links: Option<Vec<EncodableProjectLink>>,
     pub links: EncodableCrateLinks,
     pub exact_match: bool,
 }
@@ -264,6 +315,8 @@ impl EncodableCrate {
             created_at,
             downloads,
             recent_downloads,
+            direct_dependents: None,
+            total_dependents: None,
             versions,
             keywords: keyword_ids,
             categories: category_ids,
@@ -276,6 +329,8 @@ impl EncodableCrate {
             exact_match,
             description,
             repository,
+            verified_repository: None,
+            project_links: None,
             links: EncodableCrateLinks {
                 version_downloads: format!("/api/v1/crates/{}/downloads", name),
                 versions: versions_link,
@@ -306,6 +361,33 @@ impl EncodableCrate {
         )
     }
 
+    /// Adds the precomputed dependents counts, which are `None` until they
+    /// have been counted for the first time
+    pub fn with_dependents(mut self, dependents: Option<&CrateDependents>) -> Self {
+        self.direct_dependents = dependents.map(|d| d.direct_dependents);
+        self.total_dependents = dependents.map(|d| d.total_dependents);
+        self
+    }
+
+    /// Adds whether the crate links to a repository that was verified
+    pub fn with_repository_verification(
+        mut self,
+        verification: Option<&RepositoryVerification>,
+    ) -> Self {
+        let repository = self.repository.as_deref();
+        self.verified_repository = Some(
+            verification
+                .map(|v| v.is_verified_for(repository))
+                .unwrap_or(false),
+        );
+        self
+    }
+
+    pub fn with_project_links(mut self, links: Vec<ProjectLink>) -> Self {
+        self.project_links = Some(links.into_iter().map(ProjectLink::into).collect());
+        self
+    }
+
     /// Return `None` if the documentation URL host matches a blocked host
     fn remove_blocked_documentation_urls(url: Option<String>) -> Option<String> {
         // Handles if documentation URL is None
@@ -345,6 +427,36 @@ pub struct EncodableCrateLinks {
     pub reverse_dependencies: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableProjectLink {
+    pub kind: String,
+    pub url: String,
+    pub label: Option<String>,
+    /// Whether the link comes from the manifest of the crate instead of the
+    /// API
+    pub from_manifest: bool,
+}
+
+impl From<ProjectLink> for EncodableProjectLink {
+    fn from(link: ProjectLink) -> Self {
+        Self {
+            kind: link.kind.into(),
+            url: link.url,
+            label: link.label,
+            from_manifest: link.from_manifest,
+        }
+    }
+}
+
+/// A crate that accepts funding, with the crates of a user that depend on it
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableFundedDependency {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub funding: Vec<EncodableProjectLink>,
+    pub dependents: Vec<String>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableOwner {
     pub id: i32,
@@ -363,12 +475,13 @@ impl From<Owner> for EncodableOwner {
                 name,
                 gh_login,
                 gh_avatar,
+                username,
                 ..
             }) => {
                 let url = format!("https://github.com/{}", gh_login);
                 Self {
                     id,
-                    login: gh_login,
+                    login: username.unwrap_or(gh_login),
                     avatar: gh_avatar,
                     url: Some(url),
                     name,
@@ -461,10 +574,112 @@ pub struct OwnedCrate {
     pub email_notifications: bool,
 }
 
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableEmailPreferences {
+    pub owner_invitations: bool,
+    pub new_versions: bool,
+    pub security_notices: bool,
+    pub moderation: bool,
+    pub weekly_digest: bool,
+}
+
+impl From<EmailPreferences> for EncodableEmailPreferences {
+    fn from(preferences: EmailPreferences) -> Self {
+        Self {
+            owner_invitations: preferences.owner_invitations,
+            new_versions: preferences.new_versions,
+            security_notices: preferences.security_notices,
+            moderation: preferences.moderation,
+            weekly_digest: preferences.weekly_digest,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableNotification {
+    pub id: i32,
+    pub kind: String,
+    pub title: String,
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub read: bool,
+}
+
+impl From<Notification> for EncodableNotification {
+    fn from(notification: Notification) -> Self {
+        let kind: &'static str = notification.kind.into();
+        Self {
+            id: notification.id,
+            kind: kind.to_string(),
+            title: notification.title,
+            krate: notification.crate_name,
+            created_at: notification.created_at,
+            read: notification.read_at.is_some(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateList {
+    pub id: i32,
+    pub name: String,
+    pub description: Option<String>,
+    pub owner: EncodablePublicUser,
+    pub crates_cnt: i64,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+impl EncodableCrateList {
+    pub fn from(list: CrateList, owner: User, crates_cnt: i64) -> Self {
+        Self {
+            id: list.id,
+            name: list.name,
+            description: list.description,
+            owner: owner.into(),
+            crates_cnt,
+            created_at: list.created_at,
+            updated_at: list.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateListItem {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub description: Option<String>,
+    pub downloads: i32,
+    pub recent_downloads: Option<i64>,
+    pub note: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub added_at: NaiveDateTime,
+}
+
+impl From<CrateListItem> for EncodableCrateListItem {
+    fn from(item: CrateListItem) -> Self {
+        Self {
+            krate: item.name,
+            description: item.description,
+            downloads: item.downloads,
+            recent_downloads: item.recent_downloads,
+            note: item.note,
+            added_at: item.added_at,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableMe {
     pub user: EncodablePrivateUser,
     pub owned_crates: Vec<OwnedCrate>,
+    /// The address the user asked to change their email to, until both
+    /// addresses have been confirmed
+    pub pending_email: Option<String>,
 }
 
 /// The serialization format for the `User` model.
@@ -480,6 +695,12 @@ pub struct EncodablePrivateUser {
     pub email: Option<String>,
     pub avatar: Option<String>,
     pub url: Option<String>,
+    /// Whether other users can follow the user
+    pub followable: bool,
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub location: Option<String>,
 }
 
 impl EncodablePrivateUser {
@@ -495,17 +716,28 @@ impl EncodablePrivateUser {
             name,
             gh_login,
             gh_avatar,
+            followable,
+            display_name,
+            bio,
+            website,
+            location,
+            username,
             ..
         } = user;
         let url = format!("https://github.com/{}", gh_login);
 
         EncodablePrivateUser {
             id,
+            followable,
+            display_name,
+            bio,
+            website,
+            location,
             email,
             email_verified,
             email_verification_sent,
             avatar: gh_avatar,
-            login: gh_login,
+            login: username.unwrap_or(gh_login),
             name,
             url: Some(url),
         }
@@ -521,6 +753,11 @@ pub struct EncodablePublicUser {
     pub name: Option<String>,
     pub avatar: Option<String>,
     pub url: Option<String>,
+    /// The profile fields the user edits on crates.io
+    pub display_name: Option<String>,
+    pub bio: Option<String>,
+    pub website: Option<String>,
+    pub location: Option<String>,
 }
 
 /// Converts a `User` model into an `EncodablePublicUser` for JSON serialization.
@@ -531,19 +768,571 @@ impl From<User> for EncodablePublicUser {
             name,
             gh_login,
             gh_avatar,
+            display_name,
+            bio,
+            website,
+            location,
+            username,
             ..
         } = user;
         let url = format!("https://github.com/{}", gh_login);
         EncodablePublicUser {
             id,
             avatar: gh_avatar,
-            login: gh_login,
+            login: username.unwrap_or(gh_login),
             name,
             url: Some(url),
+            display_name,
+            bio,
+            website,
+            location,
+        }
+    }
+}
+
+/// The serialization format for the lock state of a `User`, used by the
+/// admin endpoints.
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableAccountLock {
+    pub id: i32,
+    pub login: String,
+    pub locked: bool,
+    pub lock_reason: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub lock_until: Option<NaiveDateTime>,
+}
+
+impl From<User> for EncodableAccountLock {
+    fn from(user: User) -> Self {
+        let locked = user.check_account_lock().is_err();
+        let User {
+            id,
+            gh_login,
+            account_lock_reason,
+            account_lock_until,
+            ..
+        } = user;
+        EncodableAccountLock {
+            id,
+            login: gh_login,
+            locked,
+            lock_reason: account_lock_reason,
+            lock_until: account_lock_until,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodablePublishRateOverride {
+    pub user_id: i32,
+    pub login: String,
+    pub burst: i32,
+    #[serde(with = "rfc3339::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    pub active: bool,
+}
+
+impl EncodablePublishRateOverride {
+    pub fn from(rate_override: PublishRateOverride, login: String, now: NaiveDateTime) -> Self {
+        Self {
+            user_id: rate_override.user_id,
+            login,
+            burst: rate_override.burst,
+            expires_at: rate_override.expires_at,
+            active: rate_override.is_active(now),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodablePublishRateOverrideAction {
+    pub id: i32,
+    pub admin_id: i32,
+    pub action: String,
+    pub burst: Option<i32>,
+    #[serde(with = "rfc3339::option")]
+    pub expires_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339")]
+    pub time: NaiveDateTime,
+}
+
+impl From<PublishRateOverrideAction> for EncodablePublishRateOverrideAction {
+    fn from(action: PublishRateOverrideAction) -> Self {
+        Self {
+            id: action.id,
+            admin_id: action.admin_id,
+            action: action.action.into(),
+            burst: action.burst,
+            expires_at: action.expires_at,
+            time: action.time,
+        }
+    }
+}
+
+/// The usage of a quota of an account, see the `quotas` module
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodableQuota {
+    pub used: i64,
+    /// `None` if unlimited
+    pub limit: Option<i64>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EncodableQuotas {
+    pub storage_bytes: EncodableQuota,
+    pub versions_per_day: EncodableQuota,
+    pub crates: EncodableQuota,
+}
+
+impl EncodableQuotas {
+    pub fn new(quotas: Quotas, usage: Usage) -> Self {
+        let quota = |used, limit| EncodableQuota { used, limit };
+        Self {
+            storage_bytes: quota(usage.storage_bytes, quotas.storage_bytes),
+            versions_per_day: quota(usage.versions_per_day, quotas.versions_per_day),
+            crates: quota(usage.crates, quotas.crates),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableQuotaOverride {
+    pub storage_bytes: Option<i64>,
+    pub versions_per_day: Option<i64>,
+    pub crates: Option<i64>,
+    pub admin_id: i32,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<QuotaOverride> for EncodableQuotaOverride {
+    fn from(quota_override: QuotaOverride) -> Self {
+        Self {
+            storage_bytes: quota_override.storage_bytes,
+            versions_per_day: quota_override.versions_per_day,
+            crates: quota_override.crates,
+            admin_id: quota_override.admin_id,
+            updated_at: quota_override.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableBulkYank {
+    pub id: i32,
+    pub admin_id: i32,
+    pub user_id: i32,
+    pub api_token_id: Option<i32>,
+    #[serde(with = "rfc3339")]
+    pub since: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub until: NaiveDateTime,
+    pub reason: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub completed_at: Option<NaiveDateTime>,
+    pub versions_yanked: Option<i32>,
+}
+
+impl From<BulkYank> for EncodableBulkYank {
+    fn from(bulk_yank: BulkYank) -> Self {
+        Self {
+            id: bulk_yank.id,
+            admin_id: bulk_yank.admin_id,
+            user_id: bulk_yank.user_id,
+            api_token_id: bulk_yank.api_token_id,
+            since: bulk_yank.since,
+            until: bulk_yank.until,
+            reason: bulk_yank.reason,
+            created_at: bulk_yank.created_at,
+            completed_at: bulk_yank.completed_at,
+            versions_yanked: bulk_yank.versions_yanked,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableDataExport {
+    pub id: i32,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub completed_at: Option<NaiveDateTime>,
+    pub download_path: Option<String>,
+}
+
+impl EncodableDataExport {
+    pub fn from(data_export: DataExport, download_path: Option<String>) -> Self {
+        Self {
+            id: data_export.id,
+            created_at: data_export.created_at,
+            completed_at: data_export.completed_at,
+            download_path,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableReservedCrateName {
+    pub name: String,
+    pub wildcard: bool,
+}
+
+impl From<ReservedCrateName> for EncodableReservedCrateName {
+    fn from(reserved_name: ReservedCrateName) -> Self {
+        Self {
+            wildcard: reserved_name.is_wildcard(),
+            name: reserved_name.name,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableAnnouncement {
+    pub id: i32,
+    pub message: String,
+    pub url: Option<String>,
+    /// One of `info`, `warning` or `critical`
+    pub severity: String,
+    #[serde(with = "rfc3339")]
+    pub starts_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub ends_at: Option<NaiveDateTime>,
+}
+
+impl From<Announcement> for EncodableAnnouncement {
+    fn from(announcement: Announcement) -> Self {
+        Self {
+            id: announcement.id,
+            message: announcement.message,
+            url: announcement.url,
+            severity: announcement.severity.into(),
+            starts_at: announcement.starts_at,
+            ends_at: announcement.ends_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableFeatureFlag {
+    pub name: String,
+    pub description: Option<String>,
+    pub enabled: bool,
+    pub percentage: i32,
+    pub user_ids: Vec<i32>,
+    #[serde(with = "rfc3339")]
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<FeatureFlag> for EncodableFeatureFlag {
+    fn from(flag: FeatureFlag) -> Self {
+        Self {
+            name: flag.name,
+            description: flag.description,
+            enabled: flag.enabled,
+            percentage: flag.percentage,
+            user_ids: flag.user_ids,
+            updated_at: flag.updated_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableFeaturedCrate {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub crate_name: String,
+    pub starts_on: NaiveDate,
+    pub ends_on: NaiveDate,
+    pub description: Option<String>,
+}
+
+impl From<(FeaturedCrate, String)> for EncodableFeaturedCrate {
+    fn from((featured, crate_name): (FeaturedCrate, String)) -> Self {
+        Self {
+            id: featured.id,
+            crate_name,
+            starts_on: featured.starts_on,
+            ends_on: featured.ends_on,
+            description: featured.description,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct EncodableReadmeRerender {
+    pub id: i32,
+    pub admin_id: i32,
+    #[serde(rename = "crate")]
+    pub crate_name: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub rendered_before: NaiveDateTime,
+    pub batch_size: i32,
+    pub total_versions: i32,
+    pub processed_versions: i32,
+    pub failed_versions: i32,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339::option")]
+    pub completed_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub cancelled_at: Option<NaiveDateTime>,
+}
+
+impl From<ReadmeRerender> for EncodableReadmeRerender {
+    fn from(rerender: ReadmeRerender) -> Self {
+        Self {
+            id: rerender.id,
+            admin_id: rerender.admin_id,
+            crate_name: rerender.crate_name,
+            rendered_before: rerender.rendered_before,
+            batch_size: rerender.batch_size,
+            total_versions: rerender.total_versions,
+            processed_versions: rerender.processed_versions,
+            failed_versions: rerender.failed_versions,
+            created_at: rerender.created_at,
+            completed_at: rerender.completed_at,
+            cancelled_at: rerender.cancelled_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableBackgroundJob {
+    pub id: i64,
+    pub job_type: String,
+    pub queue: String,
+    pub priority: i16,
+    /// One of `queued`, `running` or `failed`
+    pub status: String,
+    pub data: serde_json::Value,
+    pub retries: i32,
+    #[serde(with = "rfc3339::option")]
+    pub last_retry: Option<NaiveDateTime>,
+    pub last_error: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    /// The request that enqueued the job, see `util::request_id`
+    pub request_id: Option<String>,
+    /// The job isn't run before this time
+    #[serde(with = "rfc3339")]
+    pub run_at: NaiveDateTime,
+    pub progress: Option<JobProgress>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableBackgroundJobRun {
+    #[serde(with = "rfc3339")]
+    pub started_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub finished_at: NaiveDateTime,
+    pub succeeded: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableScheduledJob {
+    pub name: String,
+    pub schedule: String,
+    #[serde(with = "rfc3339::option")]
+    pub last_run_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339")]
+    pub next_run_at: NaiveDateTime,
+    #[serde(with = "rfc3339::vec")]
+    pub upcoming_runs: Vec<NaiveDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableStorageMismatch {
+    pub id: i32,
+    pub version_id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub kind: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+    #[serde(with = "rfc3339")]
+    pub detected_at: NaiveDateTime,
+    pub resolved_by: Option<i32>,
+    #[serde(with = "rfc3339::option")]
+    pub resolved_at: Option<NaiveDateTime>,
+}
+
+impl EncodableStorageMismatch {
+    pub fn from(mismatch: StorageMismatch, crate_name: String, num: String) -> Self {
+        Self {
+            id: mismatch.id,
+            version_id: mismatch.version_id,
+            krate: crate_name,
+            num,
+            kind: mismatch.kind.into(),
+            expected: mismatch.expected,
+            actual: mismatch.actual,
+            detected_at: mismatch.detected_at,
+            resolved_by: mismatch.resolved_by,
+            resolved_at: mismatch.resolved_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableUserSession {
+    pub id: i32,
+    pub user_agent: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub last_used_at: NaiveDateTime,
+    /// Whether this is the session of the request
+    pub current: bool,
+}
+
+impl EncodableUserSession {
+    pub fn from(session: UserSession, current_session_id: Option<i32>) -> Self {
+        Self {
+            current: current_session_id == Some(session.id),
+            id: session.id,
+            user_agent: session.user_agent,
+            created_at: session.created_at,
+            last_used_at: session.last_used_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableLoginAnomaly {
+    pub id: i32,
+    pub ip: String,
+    pub kind: String,
+    pub attempts: i32,
+    #[serde(with = "rfc3339")]
+    pub detected_at: NaiveDateTime,
+}
+
+impl From<LoginAnomaly> for EncodableLoginAnomaly {
+    fn from(anomaly: LoginAnomaly) -> Self {
+        Self {
+            id: anomaly.id,
+            ip: anomaly.ip,
+            kind: anomaly.kind,
+            attempts: anomaly.attempts,
+            detected_at: anomaly.detected_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableRepositoryVerification {
+    pub repository: String,
+    /// `github` or `well_known_file`
+    pub method: String,
+    pub token: String,
+    /// The file that has to contain the token, for the `well_known_file` method
+    pub well_known_url: Option<String>,
+    #[serde(with = "rfc3339::option")]
+    pub verified_at: Option<NaiveDateTime>,
+    #[serde(with = "rfc3339::option")]
+    pub last_checked_at: Option<NaiveDateTime>,
+}
+
+impl EncodableRepositoryVerification {
+    pub fn from(verification: RepositoryVerification, method: &VerificationMethod) -> Self {
+        Self {
+            repository: verification.repository,
+            method: method.as_str().into(),
+            token: verification.token,
+            well_known_url: method.well_known_url().map(Into::into),
+            verified_at: verification.verified_at,
+            last_checked_at: verification.last_checked_at,
         }
     }
 }
 
+/// The bytes a crate takes up in storage. Versions and READMEs whose size
+/// wasn't recorded are not counted.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct EncodableCrateStorage {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub versions: i64,
+    pub crate_file_bytes: i64,
+    pub readme_bytes: i64,
+    pub readme_source_bytes: i64,
+    pub total_bytes: i64,
+}
+
+/// How much a crate is trending, see [`crate::models::CrateTrendingScore`]
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct EncodableTrendingScore {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub score: f64,
+    pub recent_downloads: i64,
+    pub baseline_downloads: i64,
+    pub new_dependents: i32,
+    #[serde(with = "rfc3339")]
+    pub computed_at: NaiveDateTime,
+}
+
+/// How many crates use a license or a license expression
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableLicenseUsage {
+    pub license: String,
+    pub crates: i64,
+}
+
+/// A crate in the dependency tree of a version, with the license of the
+/// version it resolves to
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableDependencyLicense {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub version: String,
+    /// The `license` field as published
+    pub license: Option<String>,
+    /// The license identifiers of the normalized expression, empty if it
+    /// couldn't be normalized
+    pub licenses: Vec<String>,
+    /// How many dependencies away from the version the crate is
+    pub depth: u32,
+}
+
+/// A version in the dependency graph of another version
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableGraphNode {
+    pub id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    /// How many dependencies away from the root version this version is
+    pub depth: u32,
+}
+
+/// A dependency in the dependency graph of a version
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableGraphEdge {
+    /// The node of the version that has the dependency
+    pub from: i32,
+    /// The node of the version the dependency resolves to
+    pub to: Option<i32>,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub req: String,
+    pub kind: DependencyKind,
+    pub optional: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct EncodableVersionStorage {
+    pub num: String,
+    pub crate_file_bytes: Option<i32>,
+    /// The rendered HTML of the README, `None` if the version has none
+    pub readme_bytes: Option<i32>,
+    pub readme_source_bytes: Option<i32>,
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableAuditAction {
     pub action: String,
@@ -552,6 +1341,43 @@ pub struct EncodableAuditAction {
     pub time: NaiveDateTime,
 }
 
+/// The provenance of a version, answering who published it and how
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionProvenance {
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    #[serde(with = "rfc3339")]
+    pub published_at: NaiveDateTime,
+    pub published_by: Option<EncodablePublicUser>,
+    /// The token used to publish the version, unless it was published
+    /// without one or before tokens were recorded
+    pub token: Option<EncodableProvenanceToken>,
+    /// The class of the address the version was published from, see
+    /// `util::ip_class`. Only recorded for versions published recently.
+    pub ip_class: Option<String>,
+    /// Everything owners did with the version, starting with its publication
+    pub actions: Vec<EncodableAuditAction>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableProvenanceToken {
+    pub name: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub revoked: bool,
+}
+
+impl From<ApiToken> for EncodableProvenanceToken {
+    fn from(token: ApiToken) -> Self {
+        Self {
+            name: token.name,
+            created_at: token.created_at,
+            revoked: token.revoked,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableVersion {
     pub id: i32,
@@ -572,8 +1398,17 @@ pub struct EncodableVersion {
     pub license: Option<String>,
     pub links: EncodableVersionLinks,
     pub crate_size: Option<i32>,
+    /// The `rust-version` of the manifest, if it declared one
+    pub rust_version: Option<String>,
     pub published_by: Option<EncodablePublicUser>,
     pub audit_actions: Vec<EncodableAuditAction>,
+    /// The ids of the security advisories affecting this version
+    pub advisories: Vec<String>,
+    /// Whether any security advisory affects this version
+    pub affected: bool,
+    /// The hex encoded checksums of the crate file by algorithm, see
+    /// `ChecksumAlgorithm`
+    pub checksums: BTreeMap<String, String>,
 }
 
 impl EncodableVersion {
@@ -593,6 +1428,7 @@ impl EncodableVersion {
             yanked,
             license,
             crate_size,
+            rust_version,
             ..
         } = version;
 
@@ -616,6 +1452,7 @@ impl EncodableVersion {
                 authors: format!("/api/v1/crates/{}/{}/authors", crate_name, num),
             },
             crate_size,
+            rust_version,
             published_by: published_by.map(User::into),
             audit_actions: audit_actions
                 .into_iter()
@@ -625,6 +1462,99 @@ impl EncodableVersion {
                     time: audit_action.time,
                 })
                 .collect(),
+            advisories: Vec::new(),
+            affected: false,
+            checksums: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_advisories(mut self, advisories: Vec<String>) -> Self {
+        self.affected = !advisories.is_empty();
+        self.advisories = advisories;
+        self
+    }
+
+    pub fn with_checksums(mut self, checksums: BTreeMap<String, String>) -> Self {
+        self.checksums = checksums;
+        self
+    }
+}
+
+/// An event in the activity feed of a crate, see [`CrateActivity`]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableCrateActivity {
+    pub id: String,
+    pub kind: String,
+    #[serde(with = "rfc3339")]
+    pub occurred_at: NaiveDateTime,
+    pub version: Option<String>,
+    pub actor: Option<String>,
+    pub subject: Option<String>,
+    pub detail: Option<String>,
+}
+
+impl From<CrateActivity> for EncodableCrateActivity {
+    fn from(activity: CrateActivity) -> Self {
+        Self {
+            id: activity.id,
+            kind: activity.kind,
+            occurred_at: activity.occurred_at,
+            version: activity.version,
+            actor: activity.actor,
+            subject: activity.subject,
+            detail: activity.detail,
+        }
+    }
+}
+
+/// A release in the timeline of a crate
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTimelineEntry {
+    pub num: String,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub published_by: Option<EncodablePublicUser>,
+    pub yanked: bool,
+    /// Who yanked the version and why, `None` unless it is yanked
+    pub yank: Option<EncodableTimelineYank>,
+    pub advisories: Vec<EncodableTimelineAdvisory>,
+    pub rust_version: Option<String>,
+    /// Whether the `rust_version` differs from the one of the release before,
+    /// which is `false` for the first release
+    pub rust_version_changed: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTimelineYank {
+    /// `None` for versions yanked before yanks were recorded
+    #[serde(with = "rfc3339::option")]
+    pub yanked_at: Option<NaiveDateTime>,
+    pub yanked_by: Option<EncodablePublicUser>,
+    /// `owner` for yanks by an owner, `bulk_yank` for versions yanked by the
+    /// crates.io team along with other versions of the publisher, and
+    /// `quarantine` for versions rejected by a review of the quarantine
+    pub reason: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableTimelineAdvisory {
+    pub id: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub informational: Option<String>,
+    pub severity: Option<String>,
+    pub withdrawn: bool,
+}
+
+impl From<Advisory> for EncodableTimelineAdvisory {
+    fn from(advisory: Advisory) -> Self {
+        Self {
+            id: advisory.id,
+            title: advisory.title,
+            url: advisory.url,
+            informational: advisory.informational,
+            severity: advisory.severity,
+            withdrawn: advisory.withdrawn,
         }
     }
 }
@@ -636,6 +1566,235 @@ pub struct EncodableVersionLinks {
     pub authors: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableAdvisory {
+    pub id: String,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub title: String,
+    pub url: Option<String>,
+    pub date: String,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+    pub informational: Option<String>,
+    pub withdrawn: bool,
+    pub affected_versions: Vec<String>,
+    pub description: Option<String>,
+    pub severity: Option<String>,
+    /// Version requirements of the affected versions, if given
+    pub affected: Vec<String>,
+    /// `rustsec` for imported advisories, `crates.io` for ones published by
+    /// an owner of the crate
+    pub source: String,
+}
+
+impl EncodableAdvisory {
+    pub fn from(advisory: Advisory, affected_versions: Vec<String>) -> Self {
+        let source = advisory_source(&advisory);
+        let Advisory {
+            id,
+            crate_name,
+            title,
+            url,
+            date,
+            patched,
+            unaffected,
+            informational,
+            withdrawn,
+            description,
+            severity,
+            affected,
+            ..
+        } = advisory;
+
+        Self {
+            id,
+            krate: crate_name,
+            title,
+            url,
+            date: date.to_string(),
+            patched,
+            unaffected,
+            informational,
+            withdrawn,
+            affected_versions,
+            description,
+            severity,
+            affected,
+            source: source.to_string(),
+        }
+    }
+}
+
+/// An advisory in the [Open Source Vulnerability format][osv]
+///
+/// [osv]: https://ossf.github.io/osv-schema/
+#[derive(Serialize, Debug)]
+pub struct EncodableOsvAdvisory {
+    pub schema_version: &'static str,
+    pub id: String,
+    #[serde(with = "rfc3339")]
+    pub modified: NaiveDateTime,
+    #[serde(with = "rfc3339")]
+    pub published: NaiveDateTime,
+    #[serde(with = "rfc3339::option", skip_serializing_if = "Option::is_none")]
+    pub withdrawn: Option<NaiveDateTime>,
+    pub summary: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    pub affected: Vec<OsvAffected>,
+    pub references: Vec<OsvReference>,
+    pub database_specific: OsvDatabaseSpecific,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OsvAffected {
+    pub package: OsvPackage,
+    /// All affected versions, which OSV consumers can match exactly
+    pub versions: Vec<String>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OsvPackage {
+    pub ecosystem: &'static str,
+    pub name: String,
+    pub purl: String,
+}
+
+#[derive(Serialize, Debug)]
+pub struct OsvReference {
+    #[serde(rename = "type")]
+    pub kind: &'static str,
+    pub url: String,
+}
+
+/// The fields of an advisory that OSV has no place for
+#[derive(Serialize, Debug)]
+pub struct OsvDatabaseSpecific {
+    pub source: &'static str,
+    pub severity: Option<String>,
+    pub informational: Option<String>,
+    pub affected: Vec<String>,
+    pub patched: Vec<String>,
+    pub unaffected: Vec<String>,
+}
+
+impl EncodableOsvAdvisory {
+    pub fn from(advisory: Advisory, affected_versions: Vec<String>) -> Self {
+        let source = advisory_source(&advisory);
+        let Advisory {
+            id,
+            crate_name,
+            title,
+            url,
+            date,
+            patched,
+            unaffected,
+            informational,
+            withdrawn,
+            updated_at,
+            description,
+            severity,
+            affected,
+            ..
+        } = advisory;
+
+        let mut references = Vec::new();
+        if source == "rustsec" {
+            references.push(OsvReference {
+                kind: "ADVISORY",
+                url: format!("https://rustsec.org/advisories/{}.html", id),
+            });
+        }
+        if let Some(url) = url {
+            references.push(OsvReference { kind: "WEB", url });
+        }
+
+        Self {
+            schema_version: "1.0.0",
+            modified: updated_at,
+            published: date.and_hms(0, 0, 0),
+            withdrawn: if withdrawn { Some(updated_at) } else { None },
+            summary: title,
+            details: description,
+            affected: vec![OsvAffected {
+                package: OsvPackage {
+                    ecosystem: "crates.io",
+                    purl: format!("pkg:cargo/{}", crate_name),
+                    name: crate_name,
+                },
+                versions: affected_versions,
+            }],
+            references,
+            database_specific: OsvDatabaseSpecific {
+                source,
+                severity,
+                informational,
+                affected,
+                patched,
+                unaffected,
+            },
+            id,
+        }
+    }
+}
+
+fn advisory_source(advisory: &Advisory) -> &'static str {
+    if advisory.is_owner_published() {
+        "crates.io"
+    } else {
+        "rustsec"
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncodableVersionQuarantine {
+    pub version_id: i32,
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub num: String,
+    pub status: String,
+    pub findings: Vec<Finding>,
+    #[serde(with = "rfc3339")]
+    pub created_at: NaiveDateTime,
+    pub reviewed_by: Option<i32>,
+    #[serde(with = "rfc3339::option")]
+    pub reviewed_at: Option<NaiveDateTime>,
+    /// Whether the version is waiting to be added to the index
+    pub held: bool,
+    #[serde(with = "rfc3339::option")]
+    pub release_at: Option<NaiveDateTime>,
+}
+
+impl EncodableVersionQuarantine {
+    pub fn from(quarantine: VersionQuarantine, crate_name: String, num: String) -> Self {
+        let findings = quarantine.findings();
+        let held = quarantine.is_held();
+        let VersionQuarantine {
+            version_id,
+            status,
+            created_at,
+            reviewed_by,
+            reviewed_at,
+            release_at,
+            ..
+        } = quarantine;
+
+        Self {
+            version_id,
+            krate: crate_name,
+            num,
+            status: status.into(),
+            findings,
+            created_at,
+            reviewed_by,
+            reviewed_at,
+            held,
+            release_at,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct GoodCrate {
     #[serde(rename = "crate")]
@@ -726,6 +1885,7 @@ mod tests {
                 authors: "".to_string(),
             },
             crate_size: Some(1234),
+            rust_version: None,
             published_by: None,
             audit_actions: vec![EncodableAuditAction {
                 action: "publish".to_string(),
@@ -735,9 +1895,16 @@ mod tests {
                     name: None,
                     avatar: None,
                     url: None,
+                    display_name: None,
+                    bio: None,
+                    website: None,
+                    location: None,
                 },
                 time: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             }],
+            advisories: vec![],
+            affected: false,
+            checksums: BTreeMap::new(),
         };
         let json = serde_json::to_string(&ver).unwrap();
         assert_some!(json
@@ -762,6 +1929,8 @@ mod tests {
             created_at: NaiveDate::from_ymd(2017, 1, 6).and_hms(14, 23, 12),
             downloads: 0,
             recent_downloads: None,
+            direct_dependents: None,
+            total_dependents: None,
             max_version: "".to_string(),
             newest_version: "".to_string(),
             max_stable_version: None,
@@ -769,6 +1938,8 @@ mod tests {
             homepage: None,
             documentation: None,
             repository: None,
+            verified_repository: None,
+            project_links: None,
             links: EncodableCrateLinks {
                 version_downloads: "".to_string(),
                 versions: None,
@@ -12,6 +12,15 @@ use crate::models::Crate;
 use crate::models::DependencyKind;
 use crate::models::Keyword as CrateKeyword;
 
+/// How many keywords a crate can have
+pub const MAX_KEYWORDS: usize = 5;
+
+/// How many characters a keyword can have
+pub const MAX_KEYWORD_LENGTH: usize = 20;
+
+/// How many categories a crate can be in
+pub const MAX_CATEGORIES: usize = 5;
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct EncodableCrateUpload {
     pub name: EncodableCrateName,
@@ -34,6 +43,8 @@ pub struct EncodableCrateUpload {
     pub badges: Option<HashMap<String, HashMap<String, String>>>,
     #[serde(default)]
     pub links: Option<String>,
+    #[serde(default)]
+    pub rust_version: Option<EncodableRustVersion>,
 }
 
 #[derive(PartialEq, Eq, Hash, Serialize, Debug, Deref)]
@@ -54,6 +65,8 @@ pub struct EncodableCategory(pub String);
 pub struct EncodableFeature(pub String);
 #[derive(PartialEq, Eq, Hash, Serialize, Debug, Deref)]
 pub struct EncodableFeatureName(pub String);
+#[derive(Serialize, Debug, Deref)]
+pub struct EncodableRustVersion(pub String);
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EncodableCrateDependency {
@@ -121,6 +134,24 @@ impl<'de> Deserialize<'de> for EncodableFeatureName {
     }
 }
 
+impl<'de> Deserialize<'de> for EncodableRustVersion {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(d)?;
+        let parts: Vec<&str> = s.split('.').collect();
+        let valid = (2..=3).contains(&parts.len())
+            && parts
+                .iter()
+                .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if !valid {
+            let value = de::Unexpected::Str(&s);
+            let expected = "a Rust version like `1.56` or `1.56.1`";
+            Err(de::Error::invalid_value(value, &expected))
+        } else {
+            Ok(EncodableRustVersion(s))
+        }
+    }
+}
+
 impl<'de> Deserialize<'de> for EncodableFeature {
     fn deserialize<D: Deserializer<'de>>(d: D) -> Result<EncodableFeature, D::Error> {
         let s = String::deserialize(d)?;
@@ -165,14 +196,15 @@ impl<'de> Deserialize<'de> for EncodableCrateVersionReq {
 impl<'de> Deserialize<'de> for EncodableKeywordList {
     fn deserialize<D: Deserializer<'de>>(d: D) -> Result<EncodableKeywordList, D::Error> {
         let inner = <Vec<EncodableKeyword> as Deserialize<'de>>::deserialize(d)?;
-        if inner.len() > 5 {
-            let expected = "at most 5 keywords per crate";
-            return Err(de::Error::invalid_length(inner.len(), &expected));
+        if inner.len() > MAX_KEYWORDS {
+            let expected = format!("at most {} keywords per crate", MAX_KEYWORDS);
+            return Err(de::Error::invalid_length(inner.len(), &expected.as_ref()));
         }
         for val in &inner {
-            if val.len() > 20 {
-                let expected = "a keyword with less than 20 characters";
-                return Err(de::Error::invalid_length(val.len(), &expected));
+            if val.len() > MAX_KEYWORD_LENGTH {
+                let expected =
+                    format!("a keyword with less than {} characters", MAX_KEYWORD_LENGTH);
+                return Err(de::Error::invalid_length(val.len(), &expected.as_ref()));
             }
         }
         Ok(EncodableKeywordList(inner))
@@ -182,9 +214,9 @@ impl<'de> Deserialize<'de> for EncodableKeywordList {
 impl<'de> Deserialize<'de> for EncodableCategoryList {
     fn deserialize<D: Deserializer<'de>>(d: D) -> Result<EncodableCategoryList, D::Error> {
         let inner = <Vec<EncodableCategory> as Deserialize<'de>>::deserialize(d)?;
-        if inner.len() > 5 {
-            let expected = "at most 5 categories per crate";
-            Err(de::Error::invalid_length(inner.len(), &expected))
+        if inner.len() > MAX_CATEGORIES {
+            let expected = format!("at most {} categories per crate", MAX_CATEGORIES);
+            Err(de::Error::invalid_length(inner.len(), &expected.as_ref()))
         } else {
             Ok(EncodableCategoryList(inner))
         }
@@ -231,3 +263,15 @@ fn feature_deserializes_for_valid_features() {
     assert_ok!(json::from_str::<EncodableFeature>("\"a/a\""));
     assert_ok!(json::from_str::<EncodableFeature>("\"32-column-tables\""));
 }
+
+#[test]
+fn rust_version_deserializes_for_valid_versions() {
+    use serde_json as json;
+
+    assert_ok!(json::from_str::<EncodableRustVersion>("\"1.56\""));
+    assert_ok!(json::from_str::<EncodableRustVersion>("\"1.56.1\""));
+    assert_err!(json::from_str::<EncodableRustVersion>("\"1\""));
+    assert_err!(json::from_str::<EncodableRustVersion>("\"1.56.1.0\""));
+    assert_err!(json::from_str::<EncodableRustVersion>("\"1.56-nightly\""));
+    assert_err!(json::from_str::<EncodableRustVersion>("\"1..56\""));
+}